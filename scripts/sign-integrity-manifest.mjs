@@ -0,0 +1,64 @@
+import crypto from "node:crypto";
+import fs from "node:fs";
+import path from "node:path";
+
+// Writes checksums.sha256 for a packaged launcher folder and signs it with the
+// release Ed25519 key, producing checksums.sha256.sig. The launcher checks both
+// on every start (src-tauri/src/runtime_integrity.rs), so this runs on the
+// final folder, after everything else has been copied in.
+//
+// Usage: node scripts/sign-integrity-manifest.mjs <package dir>
+// The PKCS#8 PEM private key comes from OTOSHI_INTEGRITY_PRIVATE_KEY_PEM; the
+// build must embed the matching public key via OTOSHI_INTEGRITY_PUBLIC_KEY_PEM.
+
+const CHECKSUM_FILE = "checksums.sha256";
+const SIGNATURE_FILE = "checksums.sha256.sig";
+
+const packageDir = process.argv[2] ? path.resolve(process.argv[2]) : null;
+if (!packageDir || !fs.existsSync(packageDir)) {
+  console.error("[integrity] Usage: sign-integrity-manifest.mjs <package dir>");
+  process.exit(1);
+}
+
+const privateKeyPem = process.env.OTOSHI_INTEGRITY_PRIVATE_KEY_PEM;
+if (!privateKeyPem) {
+  console.error("[integrity] OTOSHI_INTEGRITY_PRIVATE_KEY_PEM is not set");
+  process.exit(1);
+}
+
+const collectFiles = (dir) =>
+  fs.readdirSync(dir, { withFileTypes: true }).flatMap((entry) => {
+    const fullPath = path.join(dir, entry.name);
+    return entry.isDirectory() ? collectFiles(fullPath) : [fullPath];
+  });
+
+const files = collectFiles(packageDir)
+  .map((file) => path.relative(packageDir, file).split(path.sep).join("/"))
+  .filter((relative) => relative !== CHECKSUM_FILE && relative !== SIGNATURE_FILE)
+  .sort();
+
+const lines = files.map((relative) => {
+  const hash = crypto
+    .createHash("sha256")
+    .update(fs.readFileSync(path.join(packageDir, relative)))
+    .digest("hex");
+  return `${hash}  ${relative}\n`;
+});
+const manifest = Buffer.from(lines.join(""), "utf8");
+
+const privateKey = crypto.createPrivateKey(privateKeyPem);
+if (privateKey.asymmetricKeyType !== "ed25519") {
+  console.error("[integrity] Signing key is not an Ed25519 key");
+  process.exit(1);
+}
+const signature = crypto.sign(null, manifest, privateKey).toString("base64");
+
+const publicKeyPem = process.env.OTOSHI_INTEGRITY_PUBLIC_KEY_PEM;
+if (publicKeyPem && !crypto.verify(null, manifest, publicKeyPem, Buffer.from(signature, "base64"))) {
+  console.error("[integrity] Signature does not verify with OTOSHI_INTEGRITY_PUBLIC_KEY_PEM");
+  process.exit(1);
+}
+
+fs.writeFileSync(path.join(packageDir, CHECKSUM_FILE), manifest);
+fs.writeFileSync(path.join(packageDir, SIGNATURE_FILE), `${signature}\n`);
+console.log(`[integrity] Signed ${files.length} files in ${packageDir}`);
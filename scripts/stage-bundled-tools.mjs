@@ -0,0 +1,41 @@
+import fs from "node:fs";
+import path from "node:path";
+
+// Copies portable xdelta3 and aria2c into src-tauri/resources/tools, which the
+// bundle ships as `tools/` next to the launcher. At startup
+// configure_bundled_tools_env (src-tauri/src/main.rs) points the launcher at
+// them, so packaged builds don't depend on PATH.
+//
+// Usage: node scripts/stage-bundled-tools.mjs
+// Binaries are taken from OTOSHI_TOOLS_DIR when set, otherwise from PATH.
+
+const TOOLS = ["xdelta3", "aria2c"];
+const exeSuffix = process.platform === "win32" ? ".exe" : "";
+const projectRoot = process.cwd();
+const targetDir = path.join(projectRoot, "src-tauri", "resources", "tools");
+
+const searchDirs = process.env.OTOSHI_TOOLS_DIR
+  ? [path.resolve(process.env.OTOSHI_TOOLS_DIR)]
+  : (process.env.PATH || "").split(path.delimiter).filter(Boolean);
+
+fs.mkdirSync(targetDir, { recursive: true });
+
+let missing = 0;
+for (const tool of TOOLS) {
+  const fileName = `${tool}${exeSuffix}`;
+  const source = searchDirs
+    .map((dir) => path.join(dir, fileName))
+    .find((candidate) => fs.existsSync(candidate) && fs.statSync(candidate).isFile());
+  if (!source) {
+    console.warn(`[tools] ${fileName} not found; the build will fall back to PATH for it`);
+    missing += 1;
+    continue;
+  }
+  fs.copyFileSync(source, path.join(targetDir, fileName));
+  console.log(`[tools] Staged ${fileName} from ${path.dirname(source)}`);
+}
+
+if (missing > 0 && process.env.OTOSHI_REQUIRE_BUNDLED_TOOLS === "1") {
+  console.error(`[tools] ${missing} bundled tool(s) missing`);
+  process.exit(1);
+}
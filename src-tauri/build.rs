@@ -136,6 +136,10 @@ fn main() {
     // If generation fails for any reason, we still proceed with the normal tauri build.
     ensure_installer_assets();
 
+    // The integrity public key is embedded with env!/option_env!, so a new
+    // key must rebuild the crate.
+    println!("cargo:rerun-if-env-changed=OTOSHI_INTEGRITY_PUBLIC_KEY_PEM");
+
     // Workaround: if RC.EXE is missing (Windows SDK not installed),
     // the build will fail. We can't easily bypass it in tauri_build v2,
     // so we just run the default build.
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS telemetry_queue (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
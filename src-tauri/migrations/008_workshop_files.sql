@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS workshop_files (
+    item_id TEXT NOT NULL,
+    relative_path TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL DEFAULT 0,
+    sha256 TEXT NOT NULL,
+    recorded_at INTEGER NOT NULL,
+    PRIMARY KEY (item_id, relative_path)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS cloud_save_snapshots (
+    game_id TEXT PRIMARY KEY,
+    local_hash TEXT NOT NULL,
+    remote_version TEXT NOT NULL,
+    synced_at INTEGER NOT NULL
+);
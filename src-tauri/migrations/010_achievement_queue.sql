@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS achievement_queue (
+    game_id TEXT NOT NULL,
+    achievement_key TEXT NOT NULL,
+    unlocked_at INTEGER NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (game_id, achievement_key)
+);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS game_updates (
+    game_id TEXT PRIMARY KEY,
+    slug TEXT NOT NULL,
+    installed_version TEXT NOT NULL,
+    latest_version TEXT NOT NULL,
+    etag TEXT,
+    update_available INTEGER NOT NULL DEFAULT 0,
+    checked_at INTEGER NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS discovery_queue (
+    position INTEGER PRIMARY KEY,
+    game_id TEXT NOT NULL,
+    game_json TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL
+);
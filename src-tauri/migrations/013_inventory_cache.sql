@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS inventory_cache (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (kind, id)
+);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS bandwidth_usage (
+    game_id TEXT NOT NULL,
+    usage_date TEXT NOT NULL,
+    network_bytes INTEGER NOT NULL DEFAULT 0,
+    peer_bytes INTEGER NOT NULL DEFAULT 0,
+    depotcache_bytes INTEGER NOT NULL DEFAULT 0,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (game_id, usage_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_bandwidth_usage_date
+    ON bandwidth_usage(usage_date);
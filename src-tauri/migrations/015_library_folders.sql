@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS library_folders (
+    path TEXT PRIMARY KEY,
+    added_at INTEGER NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS library_cache (
+    position INTEGER PRIMARY KEY,
+    entry_id TEXT NOT NULL,
+    entry_json TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL
+);
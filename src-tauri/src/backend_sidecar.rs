@@ -1,11 +1,12 @@
 use std::net::TcpListener;
 use std::process::{Child, Command, Stdio};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
-use tauri::Manager;
+use serde::Serialize;
+use tauri::{Emitter, Manager};
 
 use crate::errors::{LauncherError, Result};
 use crate::utils::paths::{
@@ -17,6 +18,24 @@ use crate::utils::paths::{
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 const READINESS_WAIT_ATTEMPTS: usize = 8;
 const READINESS_WAIT_DELAY_MS: u64 = 150;
+const SUPERVISOR_POLL: Duration = Duration::from_secs(1);
+const RESTART_BACKOFF_START: Duration = Duration::from_secs(1);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+/// A sidecar that stayed up this long starts over from the shortest backoff
+/// when it next exits.
+const RESTART_STABLE_AFTER: Duration = Duration::from_secs(120);
+
+/// Payload of the `backend-status` event.
+#[derive(Clone, Debug, Serialize)]
+pub struct BackendStatusEvent {
+    /// `reconnecting`, `running` or `failed`.
+    pub state: &'static str,
+    /// Restart attempts since the sidecar last ran stably.
+    pub attempt: u32,
+    pub retry_in_ms: Option<u64>,
+    pub api_base: Option<String>,
+    pub message: Option<String>,
+}
 
 /// Holds the backend child process and guarantees it is terminated when the app exits.
 pub struct BackendProcess(std::sync::Mutex<Option<Child>>);
@@ -44,6 +63,130 @@ impl BackendProcess {
     }
 }
 
+impl BackendProcess {
+    /// Watches the sidecar and restarts it with exponential backoff when it
+    /// exits on its own, emitting `backend-status` as it goes. Stops once
+    /// the process is terminated or `is_quitting` returns true.
+    pub fn supervise(app: &tauri::AppHandle, is_quitting: impl Fn() -> bool + Send + 'static) {
+        let app = app.clone();
+        std::thread::spawn(move || {
+            let mut backoff = RESTART_BACKOFF_START;
+            let mut attempt = 0u32;
+            let mut started_at = Instant::now();
+            loop {
+                std::thread::sleep(SUPERVISOR_POLL);
+                if is_quitting() {
+                    return;
+                }
+                let Some(process) = app.try_state::<BackendProcess>() else {
+                    return;
+                };
+                let exit = match process.0.lock() {
+                    Ok(mut guard) => match guard.as_mut() {
+                        Some(child) => child.try_wait(),
+                        None => return,
+                    },
+                    Err(_) => return,
+                };
+                let message = match exit {
+                    Ok(None) => continue,
+                    Ok(Some(status)) => format!("backend exited with {status}"),
+                    Err(err) => format!("backend status unavailable: {err}"),
+                };
+
+                if started_at.elapsed() >= RESTART_STABLE_AFTER {
+                    backoff = RESTART_BACKOFF_START;
+                    attempt = 0;
+                }
+                attempt += 1;
+                tracing::warn!(
+                    "{}; restarting in {:?} (attempt {})",
+                    message,
+                    backoff,
+                    attempt
+                );
+                emit_status(&app, "reconnecting", attempt, Some(backoff), Some(message));
+                let deadline = Instant::now() + backoff;
+                while Instant::now() < deadline {
+                    if is_quitting() {
+                        return;
+                    }
+                    std::thread::sleep(
+                        SUPERVISOR_POLL.min(deadline.saturating_duration_since(Instant::now())),
+                    );
+                }
+                backoff = (backoff * 2).min(RESTART_BACKOFF_MAX);
+
+                match start_sidecar(&app) {
+                    Ok(Some(mut child)) => {
+                        if is_quitting() {
+                            let _ = child.kill();
+                            return;
+                        }
+                        if let Ok(mut guard) = process.0.lock() {
+                            *guard = Some(child);
+                        }
+                        started_at = Instant::now();
+                        sync_api_base(&app);
+                        emit_status(&app, "running", attempt, None, None);
+                    }
+                    Ok(None) => {
+                        // Another compatible backend answers on the port; it
+                        // isn't ours to supervise.
+                        if let Ok(mut guard) = process.0.lock() {
+                            *guard = None;
+                        }
+                        sync_api_base(&app);
+                        emit_status(&app, "running", attempt, None, None);
+                        return;
+                    }
+                    Err(err) => {
+                        tracing::warn!("backend restart failed: {}", err);
+                        emit_status(
+                            &app,
+                            "failed",
+                            attempt,
+                            Some(backoff),
+                            Some(err.to_string()),
+                        );
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// A restart can land on a fallback port; points the API client at it.
+fn sync_api_base(app: &tauri::AppHandle) {
+    let Ok(api_base) = std::env::var("LAUNCHER_API_URL") else {
+        return;
+    };
+    if let Some(state) = app.try_state::<std::sync::Arc<crate::AppState>>() {
+        if state.api.base_url() != api_base {
+            state.api.set_base_url(&api_base);
+        }
+    }
+}
+
+fn emit_status(
+    app: &tauri::AppHandle,
+    state: &'static str,
+    attempt: u32,
+    retry_in: Option<Duration>,
+    message: Option<String>,
+) {
+    let _ = app.emit(
+        "backend-status",
+        BackendStatusEvent {
+            state,
+            attempt,
+            retry_in_ms: retry_in.map(|delay| delay.as_millis() as u64),
+            api_base: std::env::var("LAUNCHER_API_URL").ok(),
+            message,
+        },
+    );
+}
+
 impl Drop for BackendProcess {
     fn drop(&mut self) {
         self.terminate();
@@ -329,7 +472,12 @@ pub fn spawn_backend(app: &tauri::AppHandle) -> Result<Option<Child>> {
         tracing::info!("LAUNCHER_API_URL is set, skipping backend auto-start");
         return Ok(None);
     }
+    start_sidecar(app)
+}
 
+/// Starts the bundled backend, or reuses a compatible one already listening.
+/// `LAUNCHER_API_URL` is pointed at whichever answers.
+fn start_sidecar(app: &tauri::AppHandle) -> Result<Option<Child>> {
     let base_port: u16 = std::env::var("BACKEND_PORT")
         .ok()
         .and_then(|v| v.parse().ok())
@@ -32,7 +32,11 @@ pub async fn login(
 
 #[tauri::command]
 pub async fn logout(state: State<'_, Arc<AppState>>) -> Result<(), String> {
-    state.auth.logout().await.map_err(|err| err.to_string())
+    state.auth.logout().await.map_err(|err| err.to_string())?;
+    if let Err(err) = state.inventory.clear_cache() {
+        tracing::warn!("failed to clear inventory cache on logout: {err}");
+    }
+    Ok(())
 }
 
 #[tauri::command]
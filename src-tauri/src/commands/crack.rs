@@ -4,7 +4,8 @@ use serde::{Deserialize, Serialize};
 use tauri::State;
 
 use crate::services::crack_manager::{
-    CrackDownloadProgress, CrackInstallResult, CrackOption, CrackUninstallResult, GameInstallInfo,
+    CrackDownloadProgress, CrackInstallResult, CrackOption, CrackRestoreResult,
+    CrackUninstallResult, GameInstallInfo,
 };
 use crate::AppState;
 
@@ -84,6 +85,22 @@ pub async fn uninstall_crack(
         .map_err(|e| e.to_string())
 }
 
+/// Restores original files from the crack backup while leaving the crack
+/// installed; `paths` picks files, `None` restores all of them.
+#[tauri::command]
+pub async fn restore_crack_backup(
+    app_id: String,
+    game_path: String,
+    paths: Option<Vec<String>>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<CrackRestoreResult, String> {
+    state
+        .crack_manager
+        .restore_from_backup(&app_id, &game_path, paths)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn is_crack_installed(
     app_id: String,
@@ -110,3 +127,21 @@ pub async fn verify_game_integrity_after_uninstall(
         .await
         .map_err(|e| e.to_string())
 }
+
+#[tauri::command]
+pub async fn get_crack_extract_workers(state: State<'_, Arc<AppState>>) -> Result<usize, String> {
+    Ok(state.crack_manager.extract_workers())
+}
+
+/// Sets how many crack files are extracted at once; `None` follows the core
+/// count.
+#[tauri::command]
+pub async fn set_crack_extract_workers(
+    workers: Option<usize>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<usize, String> {
+    state
+        .crack_manager
+        .set_extract_workers(workers)
+        .map_err(|e| e.to_string())
+}
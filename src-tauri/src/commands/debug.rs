@@ -1,10 +1,40 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{Emitter, State};
+
+use crate::logging::{self, LogEntry, LogQuery, LogRetention};
+use crate::safe_mode::{self, StartupPlan};
 use crate::utils::paths::{resolve_cache_dir, resolve_data_dir, resolve_log_dir};
+use crate::web_assets;
+use crate::AppState;
+
+/// Holds the API base picked with `set_runtime_api_base`, in the data dir.
+const API_BASE_OVERRIDE_FILE: &str = "api_base.override";
+
+#[derive(Serialize)]
+pub struct WebAssetsRestoreResult {
+    pub restored: bool,
+    pub stamp: Option<String>,
+}
+
+/// Support-only commands are available in debug builds, or in release builds
+/// started with `OTOSHI_SUPPORT_TOOLS=1`.
+fn support_tools_enabled() -> bool {
+    cfg!(debug_assertions)
+        || std::env::var("OTOSHI_SUPPORT_TOOLS")
+            .map(|value| matches!(value.trim(), "1" | "true" | "yes"))
+            .unwrap_or(false)
+}
 
 #[tauri::command]
 pub async fn get_app_logs(app: tauri::AppHandle) -> Result<String, String> {
     let log_dir = resolve_log_dir(&app);
     let backend_log = log_dir.join("backend.log");
-    let launcher_log = log_dir.join("launcher.log");
+    let launcher_log =
+        logging::active_log_file(&log_dir).unwrap_or_else(|| log_dir.join("launcher.log"));
 
     let mut result = String::new();
 
@@ -33,6 +63,57 @@ pub async fn get_app_logs(app: tauri::AppHandle) -> Result<String, String> {
     Ok(result)
 }
 
+const DEFAULT_LOG_QUERY_LIMIT: usize = 200;
+const MAX_LOG_QUERY_LIMIT: usize = 5000;
+
+/// Entries from the structured launcher log at `level` or more severe, logged
+/// at or after `since` (unix seconds), whose message or target `contains` the
+/// given text. Returns the newest `limit` matches, oldest first.
+#[tauri::command]
+pub async fn query_logs(
+    app: tauri::AppHandle,
+    level: Option<String>,
+    since: Option<i64>,
+    contains: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, String> {
+    let level = level
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| logging::parse_level(&value))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let query = LogQuery {
+        level,
+        since,
+        contains: contains.filter(|value| !value.trim().is_empty()),
+        limit: limit
+            .unwrap_or(DEFAULT_LOG_QUERY_LIMIT)
+            .clamp(1, MAX_LOG_QUERY_LIMIT),
+    };
+    let log_dir = resolve_log_dir(&app);
+    tauri::async_runtime::spawn_blocking(move || logging::query(&log_dir, &query))
+        .await
+        .map_err(|e| format!("Log query task failed: {}", e))?
+        .map_err(|e| format!("Failed to query logs: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_log_retention(state: State<'_, Arc<AppState>>) -> Result<LogRetention, String> {
+    Ok(logging::load_retention(&state.db))
+}
+
+/// Saves how many daily log files and how many MB of logs to keep, and prunes
+/// the logs folder to the new limits right away.
+#[tauri::command]
+pub async fn set_log_retention(
+    app: tauri::AppHandle,
+    retention: LogRetention,
+    state: State<'_, Arc<AppState>>,
+) -> Result<LogRetention, String> {
+    logging::set_retention(&state.db, &resolve_log_dir(&app), retention)
+        .map_err(|e| format!("Failed to save log retention: {}", e))
+}
+
 #[tauri::command]
 pub async fn get_backend_status(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
     let data_dir = resolve_data_dir(&app);
@@ -130,3 +211,125 @@ pub async fn toggle_devtools(window: tauri::WebviewWindow) -> Result<(), String>
 pub async fn get_runtime_api_base() -> Result<String, String> {
     Ok(std::env::var("LAUNCHER_API_URL").unwrap_or_else(|_| "http://127.0.0.1:8000".to_string()))
 }
+
+/// Switches every service to the backend at `url` once its `/health`
+/// answers, and keeps using it on later starts. Switching to another backend
+/// signs out, since the session belongs to the old one. An empty or missing
+/// `url` forgets the saved base from the next start on. Support builds only.
+#[tauri::command]
+pub async fn set_runtime_api_base(
+    app: tauri::AppHandle,
+    url: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    if !support_tools_enabled() {
+        return Err("set_runtime_api_base is only available in dev or support builds".to_string());
+    }
+    let override_path = resolve_data_dir(&app).join(API_BASE_OVERRIDE_FILE);
+    let url = url
+        .as_deref()
+        .map(|url| url.trim().trim_end_matches('/'))
+        .unwrap_or_default()
+        .to_string();
+    if url.is_empty() {
+        if override_path.exists() {
+            std::fs::remove_file(&override_path)
+                .map_err(|e| format!("Failed to clear API base: {}", e))?;
+        }
+        tracing::info!("saved API base cleared; the default applies from the next start");
+        return get_runtime_api_base().await;
+    }
+    let parsed = reqwest::Url::parse(&url).map_err(|e| format!("Invalid API base: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("API base must be an http or https URL".to_string());
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(6))
+        .build()
+        .map_err(|e| format!("Failed to init HTTP client: {}", e))?;
+    let healthy = client
+        .get(format!("{}/health", url))
+        .send()
+        .await
+        .map(|r| r.status().is_success())
+        .unwrap_or(false);
+    if !healthy {
+        return Err(format!("{} did not pass the health check", url));
+    }
+
+    let previous = get_runtime_api_base().await?;
+    std::fs::write(&override_path, &url).map_err(|e| format!("Failed to save API base: {}", e))?;
+    std::env::set_var("LAUNCHER_API_URL", &url);
+    state.api.set_base_url(&url);
+    if !same_backend(&previous, &parsed) {
+        state.auth.logout().await.map_err(|e| e.to_string())?;
+        let _ = app.emit("auth-expired", ());
+        tracing::info!("API base switched from {} to {}; signed out", previous, url);
+    } else {
+        tracing::info!("API base switched to {}", url);
+    }
+    Ok(url)
+}
+
+/// Whether a session from `previous` is valid at `next`: same scheme, host
+/// and port.
+fn same_backend(previous: &str, next: &reqwest::Url) -> bool {
+    reqwest::Url::parse(previous)
+        .map(|previous| previous.origin() == next.origin())
+        .unwrap_or(false)
+}
+
+/// Applies an API base saved by `set_runtime_api_base`. Runs before the
+/// backend sidecar starts, which it then skips.
+pub fn apply_saved_api_base(data_dir: &Path) {
+    if !support_tools_enabled() || std::env::var("LAUNCHER_API_URL").is_ok() {
+        return;
+    }
+    let Ok(saved) = std::fs::read_to_string(data_dir.join(API_BASE_OVERRIDE_FILE)) else {
+        return;
+    };
+    let saved = saved.trim();
+    if !saved.is_empty() {
+        std::env::set_var("LAUNCHER_API_URL", saved);
+    }
+}
+
+/// Reports whether this session started in safe mode and which optional
+/// subsystems were skipped.
+#[tauri::command]
+pub async fn get_safe_mode_status(startup: State<'_, StartupPlan>) -> Result<StartupPlan, String> {
+    Ok(startup.inner().clone())
+}
+
+#[tauri::command]
+pub async fn set_safe_mode_next_start(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    safe_mode::request_next_start(&resolve_data_dir(&app), enabled)
+        .map_err(|e| format!("Failed to update safe mode request: {}", e))
+}
+
+/// Re-extracts `web.pack` over the current web directory, then asks the UI to
+/// reload. Recovers a corrupted extraction without restarting the launcher.
+#[tauri::command]
+pub async fn restore_web_assets(app: tauri::AppHandle) -> Result<WebAssetsRestoreResult, String> {
+    if !support_tools_enabled() {
+        return Err("restore_web_assets is only available in dev or support builds".to_string());
+    }
+    let handle = app.clone();
+    let stamp = tauri::async_runtime::spawn_blocking(move || {
+        let Some(pending) = web_assets::check_with(&handle, true)? else {
+            return Ok(None);
+        };
+        web_assets::restore(&handle, &pending)?;
+        Ok::<_, crate::errors::LauncherError>(Some(pending.stamp().to_string()))
+    })
+    .await
+    .map_err(|e| format!("Web asset restore task failed: {}", e))?
+    .map_err(|e| format!("Failed to restore web assets: {}", e))?;
+
+    let restored = stamp.is_some();
+    if restored {
+        let _ = app.emit("web-assets-reload-required", &stamp);
+    }
+    Ok(WebAssetsRestoreResult { restored, stamp })
+}
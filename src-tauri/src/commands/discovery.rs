@@ -3,18 +3,29 @@ use std::sync::Arc;
 use tauri::State;
 
 use crate::models::Game;
+use crate::services::DiscoveryQueuePage;
 use crate::AppState;
 
+/// Returns the discovery queue, from the local cache when it is fresh or the
+/// API is unreachable. `fetched_at` tells the UI how old it is.
 #[tauri::command]
-pub async fn get_discovery_queue(state: State<'_, Arc<AppState>>) -> Result<Vec<Game>, String> {
+pub async fn get_discovery_queue(
+    state: State<'_, Arc<AppState>>,
+) -> Result<DiscoveryQueuePage, String> {
     state.discovery.queue().await.map_err(|err| err.to_string())
 }
 
+/// Offset 0 (the default) regenerates the queue; pass `next_offset` from the
+/// previous page to load more.
 #[tauri::command]
-pub async fn refresh_discovery_queue(state: State<'_, Arc<AppState>>) -> Result<Vec<Game>, String> {
+pub async fn refresh_discovery_queue(
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<DiscoveryQueuePage, String> {
     state
         .discovery
-        .refresh_queue()
+        .refresh_queue(offset.unwrap_or(0), limit)
         .await
         .map_err(|err| err.to_string())
 }
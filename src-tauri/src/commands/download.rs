@@ -1,10 +1,15 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use reqwest::Method;
+use serde::Serialize;
 use tauri::State;
 
 use crate::db::queries::{DownloadQueries, DownloadStateQueries};
+use crate::errors::ErrorPayload;
 use crate::models::{DownloadPreparePayload, DownloadTask, Game, LocalDownload};
+use crate::services::post_install::PostInstallReport;
+use crate::services::{LocalImportReport, ManifestInfo};
 use crate::AppState;
 
 fn sanitize_folder_name(value: &str) -> String {
@@ -60,6 +65,8 @@ fn local_download_to_task(local: &LocalDownload, slug: Option<String>) -> Downlo
         read_bytes: local.read_bytes,
         written_bytes: local.written_bytes,
         remaining_bytes: local.remaining_bytes,
+        engine: None,
+        method: None,
         game: Game {
             id: local.game_id.clone(),
             slug: slug.unwrap_or_else(|| local.game_id.clone()),
@@ -82,11 +89,11 @@ fn fallback_task_with_status(
     state: &Arc<AppState>,
     download_id: &str,
     status: &str,
-) -> Result<DownloadTask, String> {
+) -> Result<DownloadTask, ErrorPayload> {
     let mut local = state
         .db
         .get_downloads()
-        .map_err(|err| err.to_string())?
+        .map_err(ErrorPayload::from)?
         .into_iter()
         .find(|item| item.id == download_id)
         .unwrap_or(LocalDownload {
@@ -118,7 +125,7 @@ fn fallback_task_with_status(
     state
         .db
         .upsert_download(&local)
-        .map_err(|err| err.to_string())?;
+        .map_err(ErrorPayload::from)?;
 
     let slug = state
         .db
@@ -130,32 +137,54 @@ fn fallback_task_with_status(
     Ok(local_download_to_task(&local, slug))
 }
 
-fn enforce_download_guard(state: &Arc<AppState>, action: &str) -> Result<(), String> {
+fn enforce_download_guard(state: &Arc<AppState>, action: &str) -> Result<(), ErrorPayload> {
     state
         .security_guard_v2
         .enforce(action)
         .map(|_| ())
-        .map_err(|err| err.to_string())
+        .map_err(ErrorPayload::from)
 }
 
 #[tauri::command]
 pub async fn start_download(
     game_id: String,
     state: State<'_, Arc<AppState>>,
-) -> Result<DownloadTask, String> {
-    enforce_download_guard(state.inner(), "start_download")?;
+) -> Result<DownloadTask, ErrorPayload> {
+    begin_download(state.inner(), &game_id).await
+}
+
+/// Shared by `start_download` and downloads started without a UI request.
+pub(crate) async fn begin_download(
+    state: &Arc<AppState>,
+    game_id: &str,
+) -> Result<DownloadTask, ErrorPayload> {
+    begin_download_in(state, game_id, None).await
+}
+
+/// Like `begin_download`, but installs into `install_dir` instead of the
+/// default game folder.
+pub(crate) async fn begin_download_in(
+    state: &Arc<AppState>,
+    game_id: &str,
+    install_dir: Option<&str>,
+) -> Result<DownloadTask, ErrorPayload> {
+    enforce_download_guard(state, "start_download")?;
+    state
+        .download_manager
+        .ensure_within_data_cap()
+        .map_err(ErrorPayload::from)?;
 
     let task = state
         .downloads
-        .start_download(&game_id)
+        .start_download(game_id)
         .await
-        .map_err(|err| err.to_string())?;
+        .map_err(ErrorPayload::from)?;
 
     state
         .download_manager
-        .start_download(&task.id, &task.game.id, &task.game.slug, None, None)
+        .start_download(&task.id, &task.game.id, &task.game.slug, None, install_dir)
         .await
-        .map_err(|err| err.to_string())?;
+        .map_err(ErrorPayload::from)?;
 
     let local = LocalDownload {
         id: task.id.clone(),
@@ -178,18 +207,77 @@ pub async fn start_download(
     state
         .db
         .upsert_download(&local)
-        .map_err(|err| err.to_string())?;
+        .map_err(ErrorPayload::from)?;
 
     Ok(task)
 }
 
+/// Starts the download that carries out a repair primed for `install_dir`.
+/// When the download isn't accepted the primed repair is dropped, so no file
+/// was deleted for it.
+pub(crate) async fn begin_repair_download(
+    state: &Arc<AppState>,
+    game_id: &str,
+    install_dir: &str,
+) -> Result<DownloadTask, ErrorPayload> {
+    let result = begin_download_in(state, game_id, Some(install_dir)).await;
+    if result.is_err() {
+        state
+            .download_manager
+            .discard_repair(Path::new(install_dir.trim()));
+    }
+    result
+}
+
+#[derive(Serialize)]
+pub struct LocalImportResult {
+    pub task: DownloadTask,
+    pub report: LocalImportReport,
+}
+
+/// Imports an existing copy of a game from `source_dir` into the managed
+/// install dir, then downloads only the files that were missing or corrupt.
+#[tauri::command]
+pub async fn import_local_install(
+    slug: String,
+    source_dir: String,
+    move_files: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<LocalImportResult, ErrorPayload> {
+    enforce_download_guard(state.inner(), "import_local_install")?;
+
+    let source = PathBuf::from(source_dir.trim());
+    if source.as_os_str().is_empty() {
+        return Err(ErrorPayload::new("config", "Import folder is required"));
+    }
+    let game = state
+        .library
+        .get_game_details(&slug)
+        .await
+        .map_err(ErrorPayload::from)?;
+    let install_dir = state.download_manager.claim_game_dir(&game.slug);
+    let report = state
+        .download_manager
+        .import_local_install(
+            &game.slug,
+            &source,
+            &install_dir,
+            move_files.unwrap_or(false),
+        )
+        .await
+        .map_err(ErrorPayload::from)?;
+
+    let task = start_download(game.id, state).await?;
+    Ok(LocalImportResult { task, report })
+}
+
 #[tauri::command]
 pub async fn start_steam_download(
     app_id: String,
     payload: DownloadPreparePayload,
     token: Option<String>,
     state: State<'_, Arc<AppState>>,
-) -> Result<DownloadTask, String> {
+) -> Result<DownloadTask, ErrorPayload> {
     enforce_download_guard(state.inner(), "start_steam_download")?;
 
     tracing::info!(
@@ -207,7 +295,7 @@ pub async fn start_steam_download(
         state
             .auth
             .set_tokens_external(Some(frontend_token), None)
-            .map_err(|err| err.to_string())?;
+            .map_err(ErrorPayload::from)?;
     }
 
     if let Err(err) = state.auth.ensure_access_token().await {
@@ -216,9 +304,12 @@ pub async fn start_steam_download(
             app_id,
             err
         );
-        return Err(format!(
-            "Authentication required. Please login to download games. ({})",
-            err
+        return Err(ErrorPayload::new(
+            "auth",
+            format!(
+                "Authentication required. Please login to download games. ({})",
+                err
+            ),
         ));
     }
 
@@ -233,9 +324,12 @@ pub async fn start_steam_download(
                 err
             );
             if err.to_string().contains("401") || err.to_string().contains("Unauthorized") {
-                "Authentication required. Please login to download games.".to_string()
+                ErrorPayload::new(
+                    "auth",
+                    "Authentication required. Please login to download games.",
+                )
             } else {
-                err.to_string()
+                ErrorPayload::from(err)
             }
         })?;
 
@@ -258,7 +352,7 @@ pub async fn start_steam_download(
                 app_id,
                 err
             );
-            err.to_string()
+            ErrorPayload::from(err)
         })?;
 
     let local = LocalDownload {
@@ -282,16 +376,37 @@ pub async fn start_steam_download(
     state
         .db
         .upsert_download(&local)
-        .map_err(|err| err.to_string())?;
+        .map_err(ErrorPayload::from)?;
 
     Ok(task)
 }
 
+fn download_sync_key(download_id: &str) -> String {
+    format!("download:{download_id}")
+}
+
+/// Replays a pause/resume/cancel the backend missed once it's reachable
+/// again, so its download list catches up with the local runtime. Only the
+/// latest action per download is kept.
+fn queue_backend_sync(state: &Arc<AppState>, download_id: &str, action: &str) {
+    if state.connectivity.is_online() {
+        return;
+    }
+    if let Err(err) = state.connectivity.enqueue(
+        &download_sync_key(download_id),
+        Method::POST,
+        &format!("downloads/{}/{}", download_id, action),
+        serde_json::json!({}),
+    ) {
+        tracing::warn!("failed to queue {} for {}: {}", action, download_id, err);
+    }
+}
+
 #[tauri::command]
 pub async fn pause_download(
     download_id: String,
     state: State<'_, Arc<AppState>>,
-) -> Result<DownloadTask, String> {
+) -> Result<DownloadTask, ErrorPayload> {
     enforce_download_guard(state.inner(), "pause_download")?;
 
     if let Err(err) = state.download_manager.pause_download(&download_id).await {
@@ -299,9 +414,15 @@ pub async fn pause_download(
     }
 
     let task = match state.downloads.pause_download(&download_id).await {
-        Ok(task) => task,
+        Ok(task) => {
+            state
+                .connectivity
+                .supersede(&download_sync_key(&download_id));
+            task
+        }
         Err(err) => {
             tracing::warn!("pause_download backend sync failed {}: {}", download_id, err);
+            queue_backend_sync(state.inner(), &download_id, "pause");
             return fallback_task_with_status(state.inner(), &download_id, "paused");
         }
     };
@@ -327,7 +448,7 @@ pub async fn pause_download(
     state
         .db
         .upsert_download(&local)
-        .map_err(|err| err.to_string())?;
+        .map_err(ErrorPayload::from)?;
 
     Ok(task)
 }
@@ -336,7 +457,7 @@ pub async fn pause_download(
 pub async fn resume_download(
     download_id: String,
     state: State<'_, Arc<AppState>>,
-) -> Result<DownloadTask, String> {
+) -> Result<DownloadTask, ErrorPayload> {
     enforce_download_guard(state.inner(), "resume_download")?;
 
     let mut runtime_resumed = state.download_manager.resume_download(&download_id).await.is_ok();
@@ -360,9 +481,15 @@ pub async fn resume_download(
     }
 
     let task = match state.downloads.resume_download(&download_id).await {
-        Ok(task) => task,
+        Ok(task) => {
+            state
+                .connectivity
+                .supersede(&download_sync_key(&download_id));
+            task
+        }
         Err(err) => {
             tracing::warn!("resume_download backend sync failed {}: {}", download_id, err);
+            queue_backend_sync(state.inner(), &download_id, "resume");
             return fallback_task_with_status(state.inner(), &download_id, "downloading");
         }
     };
@@ -388,26 +515,40 @@ pub async fn resume_download(
     state
         .db
         .upsert_download(&local)
-        .map_err(|err| err.to_string())?;
+        .map_err(ErrorPayload::from)?;
 
     Ok(task)
 }
 
+/// `keep_partial` defaults to true: partial files stay on disk for a later
+/// resume. Pass false to delete them and forget the completed chunks.
 #[tauri::command]
 pub async fn cancel_download(
     download_id: String,
+    keep_partial: Option<bool>,
     state: State<'_, Arc<AppState>>,
-) -> Result<DownloadTask, String> {
+) -> Result<DownloadTask, ErrorPayload> {
     enforce_download_guard(state.inner(), "cancel_download")?;
 
-    if let Err(err) = state.download_manager.cancel_download(&download_id).await {
+    let keep_partial = keep_partial.unwrap_or(true);
+    if let Err(err) = state
+        .download_manager
+        .cancel_download(&download_id, keep_partial)
+        .await
+    {
         tracing::warn!("cancel_download local runtime signal failed {}: {}", download_id, err);
     }
 
     let task = match state.downloads.cancel_download(&download_id).await {
-        Ok(task) => task,
+        Ok(task) => {
+            state
+                .connectivity
+                .supersede(&download_sync_key(&download_id));
+            task
+        }
         Err(err) => {
             tracing::warn!("cancel_download backend sync failed {}: {}", download_id, err);
+            queue_backend_sync(state.inner(), &download_id, "cancel");
             return fallback_task_with_status(state.inner(), &download_id, "cancelled");
         }
     };
@@ -433,7 +574,7 @@ pub async fn cancel_download(
     state
         .db
         .upsert_download(&local)
-        .map_err(|err| err.to_string())?;
+        .map_err(ErrorPayload::from)?;
     Ok(task)
 }
 
@@ -441,18 +582,88 @@ pub async fn cancel_download(
 pub async fn get_download_progress(
     download_id: String,
     state: State<'_, Arc<AppState>>,
-) -> Result<Option<DownloadTask>, String> {
-    let tasks = state
-        .downloads
-        .list_downloads()
+) -> Result<Option<DownloadTask>, ErrorPayload> {
+    let tasks = match state.downloads.list_downloads().await {
+        Ok(tasks) => tasks,
+        // Offline, the last progress the runtime recorded stands in.
+        Err(_) if !state.connectivity.is_online() => state
+            .db
+            .get_downloads()
+            .map_err(ErrorPayload::from)?
+            .iter()
+            .filter(|local| local.id == download_id)
+            .map(|local| {
+                let slug = state
+                    .db
+                    .get_download_state(&local.id)
+                    .ok()
+                    .flatten()
+                    .map(|saved| saved.slug);
+                local_download_to_task(local, slug)
+            })
+            .collect(),
+        Err(err) => return Err(ErrorPayload::from(err)),
+    };
+    let Some(mut task) = tasks.into_iter().find(|task| task.id == download_id) else {
+        return Ok(None);
+    };
+    // The backend doesn't know how the local runtime transfers; fill that in.
+    if let Some(saved) = state
+        .db
+        .get_download_state(&download_id)
+        .map_err(ErrorPayload::from)?
+    {
+        task.engine = saved.engine;
+        task.method = saved.method;
+    }
+    Ok(Some(task))
+}
+
+/// Stops the integrity scan of a running download. The download pauses if it
+/// was verifying its files, and `verify-cancelled` carries the partial result.
+#[tauri::command]
+pub async fn cancel_verify(
+    download_id: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), ErrorPayload> {
+    state
+        .download_manager
+        .cancel_verify(&download_id)
+        .await
+        .map_err(ErrorPayload::from)
+}
+
+/// Runs the installed game's post-install step after the user confirmed it.
+/// Output also arrives as a `post-install-finished` event.
+#[tauri::command]
+pub async fn run_post_install(
+    slug: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<PostInstallReport, ErrorPayload> {
+    state
+        .download_manager
+        .run_post_install(&slug)
+        .await
+        .map_err(ErrorPayload::from)
+}
+
+/// Version, build and size of the current manifest of `slug`, next to the
+/// installed version, without downloading the game.
+#[tauri::command]
+pub async fn get_manifest_info(
+    slug: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<ManifestInfo, ErrorPayload> {
+    state
+        .download_manager
+        .manifest_info(&slug)
         .await
-        .map_err(|err| err.to_string())?;
-    Ok(tasks.into_iter().find(|task| task.id == download_id))
+        .map_err(ErrorPayload::from)
 }
 
 #[tauri::command]
 pub async fn get_cached_downloads(
     state: State<'_, Arc<AppState>>,
-) -> Result<Vec<LocalDownload>, String> {
-    state.db.get_downloads().map_err(|err| err.to_string())
+) -> Result<Vec<LocalDownload>, ErrorPayload> {
+    state.db.get_downloads().map_err(ErrorPayload::from)
 }
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use tauri::State;
 
+use crate::errors::ErrorPayload;
 use crate::services::{DownloadSessionV2, StartDownloadV2Request};
 use crate::AppState;
 
@@ -9,16 +10,24 @@ use crate::AppState;
 pub async fn start_download_v2(
     payload: StartDownloadV2Request,
     state: State<'_, Arc<AppState>>,
-) -> Result<DownloadSessionV2, String> {
+) -> Result<DownloadSessionV2, ErrorPayload> {
+    begin_download_v2(state.inner(), payload).await
+}
+
+/// Shared by `start_download_v2` and installs requested through deep links.
+pub(crate) async fn begin_download_v2(
+    state: &Arc<AppState>,
+    payload: StartDownloadV2Request,
+) -> Result<DownloadSessionV2, ErrorPayload> {
     state
         .security_guard_v2
         .enforce("start_download_v2")
-        .map_err(|err| err.to_string())?;
+        .map_err(ErrorPayload::from)?;
     state
         .download_manager_v2
         .start_download(payload)
         .await
-        .map_err(|err| err.to_string())
+        .map_err(ErrorPayload::from)
 }
 
 #[tauri::command]
@@ -26,26 +35,26 @@ pub async fn control_download_v2(
     session_id: String,
     action: String,
     state: State<'_, Arc<AppState>>,
-) -> Result<DownloadSessionV2, String> {
+) -> Result<DownloadSessionV2, ErrorPayload> {
     state
         .security_guard_v2
         .enforce("control_download_v2")
-        .map_err(|err| err.to_string())?;
+        .map_err(ErrorPayload::from)?;
     state
         .download_manager_v2
         .control_download(&session_id, &action)
         .await
-        .map_err(|err| err.to_string())
+        .map_err(ErrorPayload::from)
 }
 
 #[tauri::command]
 pub async fn get_download_state_v2(
     session_id: String,
     state: State<'_, Arc<AppState>>,
-) -> Result<Option<DownloadSessionV2>, String> {
+) -> Result<Option<DownloadSessionV2>, ErrorPayload> {
     state
         .download_manager_v2
         .get_session(&session_id)
-        .map_err(|err| err.to_string())
+        .map_err(ErrorPayload::from)
 }
 
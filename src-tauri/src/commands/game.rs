@@ -9,18 +9,25 @@ use std::sync::Arc;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sysinfo::{Pid, System};
-use tauri::{AppHandle, Manager, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 use uuid::Uuid;
 
 use crate::commands::overlay::set_overlay_window_visible;
-use crate::db::queries::{GameQueries, LaunchPrefQueries, PlaySessionQueries};
-use crate::models::{Game, GameLaunchPref, LibraryEntry, LocalGame, PlaySessionLocal};
+use crate::db::queries::{GameQueries, LaunchPrefQueries, PlaySessionQueries, SettingsQueries};
+use crate::models::{
+    Game, GameLaunchPref, GameUpdateStatus, LibraryEntry, LibraryUpdateReport, LocalGame,
+    PlaySessionLocal,
+};
 use crate::services::RunningGame;
+use crate::utils::game_stores;
 use crate::utils::paths::resolve_data_dir;
 use crate::AppState;
 
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
+const VERIFY_BEFORE_LAUNCH_SETTING: &str = "verify_before_launch";
+/// How many broken paths a refused launch names before summarizing.
+const LAUNCH_CHECK_LISTED_FILES: usize = 10;
 
 #[tauri::command]
 pub async fn get_library(state: State<'_, Arc<AppState>>) -> Result<Vec<LibraryEntry>, String> {
@@ -64,6 +71,55 @@ pub async fn get_cached_library(state: State<'_, Arc<AppState>>) -> Result<Vec<L
     state.db.get_games().map_err(|err| err.to_string())
 }
 
+/// Installed games flagged by the background update check.
+#[tauri::command]
+pub async fn get_available_updates(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<GameUpdateStatus>, String> {
+    state
+        .update_checks
+        .list_available()
+        .map_err(|err| err.to_string())
+}
+
+/// Runs an update check now instead of waiting for the next cycle. Returns
+/// the games newly flagged by this check.
+#[tauri::command]
+pub async fn check_game_updates(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<GameUpdateStatus>, String> {
+    state
+        .update_checks
+        .check_all()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Runs the update check now for every installed game and returns the games
+/// with an update, including the download size of each.
+#[tauri::command]
+pub async fn check_library_updates(
+    state: State<'_, Arc<AppState>>,
+) -> Result<LibraryUpdateReport, String> {
+    state
+        .update_checks
+        .check_library()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// The games flagged by the last update check, background or manual, so
+/// badges show without a fetch.
+#[tauri::command]
+pub async fn get_library_updates(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Option<LibraryUpdateReport>, String> {
+    state
+        .update_checks
+        .library_report()
+        .map_err(|err| err.to_string())
+}
+
 #[tauri::command]
 pub async fn update_playtime(
     game_id: String,
@@ -106,6 +162,7 @@ pub struct LaunchPrefPayload {
     pub game_id: String,
     pub require_admin: bool,
     pub ask_every_time: Option<bool>,
+    pub verify_before_launch: Option<bool>,
 }
 
 #[tauri::command]
@@ -128,6 +185,7 @@ pub async fn set_game_launch_pref(
         game_id: payload.game_id,
         require_admin: payload.require_admin,
         ask_every_time: payload.ask_every_time.unwrap_or(false),
+        verify_before_launch: payload.verify_before_launch,
         updated_at: Utc::now().timestamp(),
     };
     state
@@ -137,6 +195,37 @@ pub async fn set_game_launch_pref(
     Ok(pref)
 }
 
+/// Whether games without their own launch preference are size-checked before
+/// they start.
+#[tauri::command]
+pub async fn get_verify_before_launch(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    Ok(verify_before_launch_default(&state))
+}
+
+#[tauri::command]
+pub async fn set_verify_before_launch(
+    enabled: bool,
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool, String> {
+    state
+        .db
+        .set_setting(
+            VERIFY_BEFORE_LAUNCH_SETTING,
+            if enabled { "true" } else { "false" },
+        )
+        .map_err(|err| err.to_string())?;
+    Ok(enabled)
+}
+
+fn verify_before_launch_default(state: &AppState) -> bool {
+    state
+        .db
+        .get_setting(VERIFY_BEFORE_LAUNCH_SETTING)
+        .ok()
+        .flatten()
+        .is_some_and(|value| value == "true")
+}
+
 #[tauri::command]
 pub async fn get_running_games(state: State<'_, Arc<AppState>>) -> Result<Vec<RunningGame>, String> {
     Ok(state.game_runtime.list())
@@ -185,14 +274,21 @@ pub async fn launch_game(
 
     let install_dir = resolve_install_dir(&state, &payload, game_config)
         .ok_or_else(|| "Install folder not found.".to_string())?;
-
-    let exe_path = resolve_exe_path(&install_dir, &payload, game_config)?;
-    let working_dir = resolve_working_dir(&install_dir, &payload, game_config);
-    let args = resolve_renderer_args(&payload.renderer, config.as_ref(), game_config);
     let launch_pref = state
         .db
         .get_launch_pref(&payload.game_id)
         .map_err(|err| err.to_string())?;
+    let verify_before_launch = launch_pref
+        .as_ref()
+        .and_then(|pref| pref.verify_before_launch)
+        .unwrap_or_else(|| verify_before_launch_default(&state));
+    if verify_before_launch {
+        check_install_before_launch(&state, &install_dir).await?;
+    }
+
+    let exe_path = resolve_exe_path(&install_dir, &payload, game_config)?;
+    let working_dir = resolve_working_dir(&install_dir, &payload, game_config);
+    let args = resolve_renderer_args(&payload.renderer, config.as_ref(), game_config);
     let require_admin = launch_pref.as_ref().map(|pref| pref.require_admin).unwrap_or(false);
 
     state.overlay.set_visible(payload.overlay_enabled);
@@ -210,6 +306,7 @@ pub async fn launch_game(
         synced: false,
         updated_at: session_started_at,
     });
+    spawn_save_fingerprint(state.inner().clone(), payload.game_id.clone());
 
     if require_admin {
         let pid = launch_with_admin(
@@ -272,6 +369,7 @@ pub async fn launch_game(
             if overlay_enabled {
                 let _ = set_overlay_window_visible(&app_handle, false);
             }
+            spawn_save_change_check(app_handle, state_for_thread.clone(), game_id.clone());
 
             let state_for_sync = state_for_thread.clone();
             let game_for_sync = game_id.clone();
@@ -355,6 +453,7 @@ pub async fn launch_game(
         if overlay_enabled {
             let _ = set_overlay_window_visible(&app_handle, false);
         }
+        spawn_save_change_check(app_handle, state_for_thread.clone(), game_id.clone());
 
         let state_for_sync = state_for_thread.clone();
         let session_for_sync = session_id.clone();
@@ -514,6 +613,7 @@ pub async fn stop_game(
             updated_at: ended_at,
         })
         .map_err(|err| err.to_string())?;
+    spawn_save_change_check(app, state.inner().clone(), game_id.clone());
 
     let state_for_sync = state.inner().clone();
     let session_for_sync = running.session_id.clone();
@@ -534,6 +634,48 @@ pub async fn stop_game(
     Ok(())
 }
 
+/// Fingerprints the game's saves in the background so a large save folder
+/// doesn't hold up the launch.
+fn spawn_save_fingerprint(state: Arc<AppState>, game_id: String) {
+    tauri::async_runtime::spawn(async move {
+        let locations = match crate::commands::properties::save_locations(&game_id).await {
+            Ok(locations) => locations,
+            Err(err) => {
+                tracing::debug!("no save locations for {}: {}", game_id, err);
+                return;
+            }
+        };
+        if let Err(err) = state.cloud_saves.record_launch(&game_id, locations).await {
+            tracing::debug!("no save fingerprint for {}: {}", game_id, err);
+        }
+    });
+}
+
+/// Emits `saves-changed` if the game's saves differ from when it launched,
+/// uploading them first when the after-play setting says so.
+fn spawn_save_change_check(app: AppHandle, state: Arc<AppState>, game_id: String) {
+    tauri::async_runtime::spawn(async move {
+        let locations = match crate::commands::properties::save_locations(&game_id).await {
+            Ok(locations) => locations,
+            Err(err) => {
+                tracing::debug!("no save locations for {}: {}", game_id, err);
+                return;
+            }
+        };
+        match state
+            .cloud_saves
+            .check_after_play(&game_id, locations)
+            .await
+        {
+            Ok(Some(changed)) => {
+                let _ = app.emit("saves-changed", &changed);
+            }
+            Ok(None) => {}
+            Err(err) => tracing::warn!("save change check for {} failed: {}", game_id, err),
+        }
+    });
+}
+
 #[cfg(not(target_os = "windows"))]
 fn launch_with_admin(
     _exe_path: &Path,
@@ -587,6 +729,56 @@ async fn sync_play_session_to_backend(
     Ok(())
 }
 
+/// Refuses the launch when the size-only check finds files missing or
+/// truncated. Installs without a launcher manifest can't be checked and pass.
+async fn check_install_before_launch(state: &AppState, install_dir: &Path) -> Result<(), String> {
+    let Some(check) = state
+        .download_manager
+        .check_install_sizes(install_dir)
+        .await
+        .map_err(|err| err.to_string())?
+    else {
+        tracing::info!(
+            "no manifest to verify before launch in {}",
+            install_dir.display()
+        );
+        return Ok(());
+    };
+    if check.is_intact() {
+        return Ok(());
+    }
+
+    let describe = |label: &str, paths: &[String]| {
+        let mut listed = paths
+            .iter()
+            .take(LAUNCH_CHECK_LISTED_FILES)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if paths.len() > LAUNCH_CHECK_LISTED_FILES {
+            listed.push_str(&format!(
+                " and {} more",
+                paths.len() - LAUNCH_CHECK_LISTED_FILES
+            ));
+        }
+        format!("{} {label}: {listed}", paths.len())
+    };
+    let mut problems = Vec::new();
+    if !check.missing.is_empty() {
+        problems.push(describe("missing", &check.missing));
+    }
+    if !check.size_mismatches.is_empty() {
+        problems.push(describe("with the wrong size", &check.size_mismatches));
+    }
+    if !check.unreadable.is_empty() {
+        problems.push(describe("that couldn't be read", &check.unreadable));
+    }
+    Err(format!(
+        "Install is incomplete ({}). Repair the game before launching.",
+        problems.join("; ")
+    ))
+}
+
 fn load_launchers_config(app: &AppHandle) -> Option<LaunchersConfig> {
     let data_dir = resolve_data_dir(app);
     let resource_dir = app.path().resource_dir().ok();
@@ -643,33 +835,26 @@ fn resolve_install_dir(
 }
 
 fn find_steam_game_path(app_id: &str) -> Option<PathBuf> {
-    #[cfg(target_os = "windows")]
-    {
-        let steam_paths = vec![
-            PathBuf::from("C:\\Program Files (x86)\\Steam"),
-            PathBuf::from("C:\\Program Files\\Steam"),
-        ];
-
-        for steam_path in steam_paths {
-            let library_folders = steam_path.join("steamapps").join("libraryfolders.vdf");
-            if !library_folders.exists() {
-                continue;
-            }
-            let common_dir = steam_path.join("steamapps").join("common");
-            if !common_dir.exists() {
-                continue;
-            }
-            if let Ok(entries) = fs::read_dir(&common_dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if !path.is_dir() {
-                        continue;
-                    }
-                    let appid_file = path.join("steam_appid.txt");
-                    if let Ok(content) = fs::read_to_string(&appid_file) {
-                        if content.trim() == app_id {
-                            return Some(path);
-                        }
+    if let Some(path) = game_stores::find_steam_game(app_id) {
+        return Some(path);
+    }
+
+    // Installs without an app manifest still carry steam_appid.txt.
+    for steam_path in game_stores::steam_libraries() {
+        let common_dir = steam_path.join("steamapps").join("common");
+        if !common_dir.exists() {
+            continue;
+        }
+        if let Ok(entries) = fs::read_dir(&common_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if !path.is_dir() {
+                    continue;
+                }
+                let appid_file = path.join("steam_appid.txt");
+                if let Ok(content) = fs::read_to_string(&appid_file) {
+                    if content.trim() == app_id {
+                        return Some(path);
                     }
                 }
             }
@@ -2,9 +2,12 @@ use std::sync::Arc;
 
 use tauri::State;
 
-use crate::services::inventory_service::{InventoryItem, TradeOffer, TradeOfferRequest};
+use crate::services::inventory_service::{
+    CachedInventory, CraftPreview, InventoryItem, InventorySnapshot, TradeOffer, TradeOfferRequest,
+};
 use crate::AppState;
 
+/// Served from the local cache once it has been synced.
 #[tauri::command]
 pub async fn list_inventory(state: State<'_, Arc<AppState>>) -> Result<Vec<InventoryItem>, String> {
     state
@@ -14,6 +17,29 @@ pub async fn list_inventory(state: State<'_, Arc<AppState>>) -> Result<Vec<Inven
         .map_err(|err| err.to_string())
 }
 
+/// Everything the inventory tab needs in one call. `force` skips the cache.
+#[tauri::command]
+pub async fn sync_inventory(
+    force: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<InventorySnapshot, String> {
+    state
+        .inventory
+        .sync_all(force.unwrap_or(false))
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Forces a server sync of the cached inventory and trades.
+#[tauri::command]
+pub async fn refresh_inventory(state: State<'_, Arc<AppState>>) -> Result<CachedInventory, String> {
+    state
+        .inventory
+        .refresh_inventory()
+        .await
+        .map_err(|err| err.to_string())
+}
+
 #[tauri::command]
 pub async fn card_drop(
     game_id: String,
@@ -38,6 +64,32 @@ pub async fn craft_badge(
         .map_err(|err| err.to_string())
 }
 
+#[tauri::command]
+pub async fn preview_craft_badge(
+    badge_id: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<CraftPreview, String> {
+    state
+        .inventory
+        .preview_craft(&badge_id)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Whether the craft button should be enabled for `badge_id`.
+#[tauri::command]
+pub async fn can_craft_badge(
+    badge_id: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool, String> {
+    state
+        .inventory
+        .can_craft_badge(&badge_id)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Served from the local cache once it has been synced.
 #[tauri::command]
 pub async fn list_trades(state: State<'_, Arc<AppState>>) -> Result<Vec<TradeOffer>, String> {
     state
@@ -58,6 +110,7 @@ pub async fn create_trade(
         to_user_id,
         offered_item_ids,
         requested_item_ids,
+        expires_at: None,
     };
     state
         .inventory
@@ -66,6 +119,23 @@ pub async fn create_trade(
         .map_err(|err| err.to_string())
 }
 
+#[tauri::command]
+pub async fn get_trade_expiry_hours(state: State<'_, Arc<AppState>>) -> Result<u32, String> {
+    Ok(state.inventory.trade_expiry_hours())
+}
+
+/// How long new trade offers stay open before they are declined.
+#[tauri::command]
+pub async fn set_trade_expiry_hours(
+    hours: u32,
+    state: State<'_, Arc<AppState>>,
+) -> Result<u32, String> {
+    state
+        .inventory
+        .set_trade_expiry_hours(hours)
+        .map_err(|err| err.to_string())
+}
+
 #[tauri::command]
 pub async fn accept_trade(
     trade_id: String,
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use tauri::State;
+
+use crate::services::update_service::LauncherUpdateStatus;
+use crate::AppState;
+
+#[tauri::command]
+pub async fn check_launcher_update(
+    state: State<'_, Arc<AppState>>,
+) -> Result<LauncherUpdateStatus, String> {
+    state
+        .launcher_updates
+        .check()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Downloads and verifies the latest launcher build; it replaces the
+/// executable when the launcher quits.
+#[tauri::command]
+pub async fn download_launcher_update(
+    state: State<'_, Arc<AppState>>,
+) -> Result<LauncherUpdateStatus, String> {
+    state
+        .launcher_updates
+        .download_and_stage()
+        .await
+        .map_err(|err| err.to_string())
+}
@@ -7,6 +7,7 @@ pub mod download;
 pub mod download_v2;
 pub mod game;
 pub mod inventory;
+pub mod launcher_update;
 pub mod lua;
 pub mod oauth;
 pub mod overlay;
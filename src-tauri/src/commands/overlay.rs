@@ -3,13 +3,19 @@ use std::sync::Arc;
 use tauri::{
     AppHandle, Manager, PhysicalPosition, PhysicalSize, State, WebviewUrl, WebviewWindowBuilder,
 };
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
 
+use crate::db::queries::SettingsQueries;
+use crate::services::overlay_service::{
+    normalize_hotkey, ScreenshotOptions, DEFAULT_OVERLAY_HOTKEY,
+};
 use crate::AppState;
 
 const OVERLAY_LABEL: &str = "overlay";
 const STORE_NEWS_LABEL: &str = "steam-news";
 const STORE_NEWS_WIDTH: u32 = 920;
 const STORE_NEWS_HEIGHT: u32 = 640;
+const OVERLAY_HOTKEY_SETTING: &str = "overlay_hotkey";
 
 fn apply_overlay_icon(window: &tauri::WebviewWindow) {
     if let Ok(icon) = tauri::image::Image::from_bytes(include_bytes!("../../icons/icon.png")) {
@@ -88,14 +94,102 @@ pub fn set_overlay_window_visible(app: &AppHandle, visible: bool) -> Result<(),
     Ok(())
 }
 
+fn toggle_overlay_window(app: &AppHandle, state: &AppState) -> bool {
+    let next = state.overlay.toggle();
+    let _ = set_overlay_window_visible(app, next);
+    next
+}
+
+/// Swaps the registered toggle hotkey for `combo`. The previous one stays
+/// registered if the new one can't be.
+fn register_overlay_hotkey(app: &AppHandle, state: &AppState, combo: &str) -> Result<(), String> {
+    let shortcut: Shortcut = combo
+        .parse()
+        .map_err(|err| format!("Invalid hotkey {combo}: {err}"))?;
+    let previous = state.overlay.hotkey();
+    if previous.as_deref() == Some(combo) {
+        return Ok(());
+    }
+    let shortcuts = app.global_shortcut();
+    shortcuts
+        .register(shortcut)
+        .map_err(|err| format!("Hotkey {combo} is already in use: {err}"))?;
+    if let Some(previous) = previous.and_then(|value| value.parse::<Shortcut>().ok()) {
+        let _ = shortcuts.unregister(previous);
+    }
+    state.overlay.set_hotkey(Some(combo.to_string()));
+    Ok(())
+}
+
+/// Registers and saves an already normalized hotkey.
+pub(crate) fn apply_overlay_hotkey(
+    app: &AppHandle,
+    state: &AppState,
+    normalized: &str,
+) -> Result<(), String> {
+    register_overlay_hotkey(app, state, normalized)?;
+    state
+        .db
+        .set_setting(OVERLAY_HOTKEY_SETTING, normalized)
+        .map_err(|err| err.to_string())
+}
+
+/// Registers the saved overlay hotkey at startup, falling back to the
+/// default when the saved one is missing or no longer valid.
+pub fn register_saved_overlay_hotkey(app: &AppHandle, state: &AppState) {
+    let saved = state
+        .db
+        .get_setting(OVERLAY_HOTKEY_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|value| normalize_hotkey(&value).ok());
+    let combo = saved.unwrap_or_else(|| DEFAULT_OVERLAY_HOTKEY.to_string());
+    if let Err(err) = register_overlay_hotkey(app, state, &combo) {
+        tracing::warn!("overlay hotkey not registered: {}", err);
+    }
+}
+
+/// Global shortcut handler; toggles the overlay when its hotkey is pressed.
+pub fn handle_overlay_hotkey(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let Some(state) = app.try_state::<Arc<AppState>>() else {
+        return;
+    };
+    let registered = state
+        .overlay
+        .hotkey()
+        .and_then(|value| value.parse::<Shortcut>().ok());
+    if registered.as_ref() == Some(shortcut) {
+        toggle_overlay_window(app, &state);
+    }
+}
+
+#[tauri::command]
+pub async fn get_overlay_hotkey(state: State<'_, Arc<AppState>>) -> Result<Option<String>, String> {
+    Ok(state.overlay.hotkey())
+}
+
+/// Validates, registers and saves a new overlay toggle hotkey. Returns the
+/// normalized combo.
+#[tauri::command]
+pub async fn set_overlay_hotkey(
+    combo: String,
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    let normalized = normalize_hotkey(&combo)?;
+    apply_overlay_hotkey(&app, &state, &normalized)?;
+    Ok(normalized)
+}
+
 #[tauri::command]
 pub async fn toggle_overlay(
     app: AppHandle,
     state: State<'_, Arc<AppState>>,
 ) -> Result<bool, String> {
-    let next = state.overlay.toggle();
-    let _ = set_overlay_window_visible(&app, next);
-    Ok(next)
+    Ok(toggle_overlay_window(&app, &state))
 }
 
 #[tauri::command]
@@ -114,9 +208,24 @@ pub async fn is_overlay_visible(state: State<'_, Arc<AppState>>) -> Result<bool,
     Ok(state.overlay.is_visible())
 }
 
+/// Saves a screenshot of the primary monitor and returns its path. Without
+/// a directory it goes to the user's pictures folder.
 #[tauri::command]
-pub async fn capture_overlay_screenshot(state: State<'_, Arc<AppState>>) -> Result<String, String> {
-    state.overlay.capture_screenshot()
+pub async fn capture_overlay_screenshot(
+    options: Option<ScreenshotOptions>,
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+    let default_dir = app
+        .path()
+        .picture_dir()
+        .map(|dir| dir.join("Otoshi"))
+        .unwrap_or_else(|_| std::env::temp_dir());
+    let overlay = state.overlay.clone();
+    tauri::async_runtime::spawn_blocking(move || overlay.capture_screenshot(&options, default_dir))
+        .await
+        .map_err(|err| err.to_string())?
 }
 
 #[tauri::command]
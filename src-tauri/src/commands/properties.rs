@@ -2,9 +2,23 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
+use tauri::{AppHandle, State};
 use tokio::fs;
 
+use crate::commands::download::begin_repair_download;
+use crate::commands::system::invalidate_installed_games;
+use crate::db::queries::DownloadStateQueries;
+use crate::models::DownloadTask;
+use crate::services::cloud_save_service::{resolve_save_locations, SaveBackup};
+use crate::services::crack_manager::BACKUP_DIR_NAME;
+use crate::services::GameRepairPlan;
+use crate::utils::client_identity;
+use crate::utils::game_stores;
+use crate::utils::paths::resolve_root_dir;
+use crate::AppState;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HashMismatchOut {
@@ -74,6 +88,24 @@ pub struct LaunchOptionsOut {
     pub updated_at: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairGameResult {
+    pub plan: GameRepairPlan,
+    /// The download refetching the damaged files; `None` when nothing was damaged.
+    pub download: Option<DownloadTask>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallResult {
+    pub slug: String,
+    pub install_dir: String,
+    /// Where the saves were copied to; `None` when no backup was asked for or
+    /// the game had no local saves.
+    pub backup: Option<SaveBackup>,
+}
+
 fn backend_api_base() -> String {
     std::env::var("LAUNCHER_API_URL").unwrap_or_else(|_| "http://127.0.0.1:8000".to_string())
 }
@@ -91,6 +123,7 @@ async fn backend_get<T: DeserializeOwned>(path: &str) -> Result<T, String> {
     let url = format!("{}{}", backend_api_base().trim_end_matches('/'), path);
     let response = client
         .get(&url)
+        .headers(client_identity::request_headers())
         .send()
         .await
         .map_err(|e| format!("Backend request failed: {e}"))?;
@@ -105,12 +138,26 @@ async fn backend_get<T: DeserializeOwned>(path: &str) -> Result<T, String> {
         .map_err(|e| format!("Invalid backend JSON: {e}"))
 }
 
+#[derive(Deserialize, Default)]
+struct SaveLocationsOut {
+    #[serde(default)]
+    locations: Vec<String>,
+}
+
+/// Local save folders of `app_id` known to the backend.
+pub(crate) async fn save_locations(app_id: &str) -> Result<Vec<PathBuf>, String> {
+    let payload =
+        backend_get::<SaveLocationsOut>(&format!("/properties/{}/save-locations", app_id)).await?;
+    Ok(resolve_save_locations(&payload.locations))
+}
+
 async fn backend_post<B: Serialize, T: DeserializeOwned>(path: &str, body: &B) -> Result<T, String> {
     let client = backend_client()?;
     let url = format!("{}{}", backend_api_base().trim_end_matches('/'), path);
     let response = client
         .post(&url)
         .json(body)
+        .headers(client_identity::request_headers())
         .send()
         .await
         .map_err(|e| format!("Backend request failed: {e}"))?;
@@ -131,6 +178,7 @@ async fn backend_post_unit<B: Serialize>(path: &str, body: &B) -> Result<(), Str
     let response = client
         .post(&url)
         .json(body)
+        .headers(client_identity::request_headers())
         .send()
         .await
         .map_err(|e| format!("Backend request failed: {e}"))?;
@@ -167,13 +215,67 @@ pub async fn verify_game_files(app_id: String, install_path: String) -> Result<V
 #[tauri::command]
 pub async fn uninstall_game(app_id: String, install_path: String) -> Result<(), String> {
     let body = json!({ "install_path": install_path });
-    if backend_post_unit(&format!("/properties/{}/uninstall", app_id), &body)
+    let result = if backend_post_unit(&format!("/properties/{}/uninstall", app_id), &body)
         .await
         .is_ok()
     {
-        return Ok(());
-    }
-    legacy_uninstall_game(app_id, install_path).await
+        Ok(())
+    } else {
+        legacy_uninstall_game(app_id, install_path).await
+    };
+    invalidate_installed_games();
+    result
+}
+
+/// Uninstalls a launcher-installed game. With `backup_saves`, its save
+/// locations are copied to the save backup folder first and the game is
+/// only removed once that succeeded. The depotcache is kept.
+#[tauri::command]
+pub async fn uninstall_game_with_backup(
+    slug: String,
+    backup_saves: bool,
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<UninstallResult, String> {
+    let backup = if backup_saves {
+        let game_id = state
+            .db
+            .get_download_state_by_slug(&slug)
+            .map_err(|err| err.to_string())?
+            .map(|installed| installed.game_id)
+            .unwrap_or_else(|| slug.clone());
+        let root = save_backup_root(&app, &state)?;
+        let locations = save_locations(&game_id)
+            .await
+            .map_err(|err| format!("Save backup failed, game was not removed: {err}"))?;
+        state
+            .cloud_saves
+            .backup_saves(&game_id, locations, &root)
+            .await
+            .map_err(|err| format!("Save backup failed, game was not removed: {err}"))?
+    } else {
+        None
+    };
+    let install_dir = state
+        .download_manager
+        .uninstall(&slug)
+        .await
+        .map_err(|err| err.to_string())?;
+    invalidate_installed_games();
+    Ok(UninstallResult {
+        slug,
+        install_dir: install_dir.to_string_lossy().to_string(),
+        backup,
+    })
+}
+
+/// The configured save backup folder, or `save-backups` in the launcher dir.
+pub(crate) fn save_backup_root(app: &AppHandle, state: &AppState) -> Result<PathBuf, String> {
+    Ok(state
+        .cloud_saves
+        .backup_dir()
+        .map_err(|err| err.to_string())?
+        .unwrap_or_else(|| resolve_root_dir(app).join("save-backups")))
 }
 
 /// Move game folder to new location.
@@ -297,6 +399,27 @@ pub async fn open_folder(path: String) -> Result<(), String> {
     Ok(())
 }
 
+/// Re-checks an installed game against its manifest and downloads only the
+/// chunks of files that are missing or corrupt.
+#[tauri::command]
+pub async fn repair_game(slug: String, state: State<'_, Arc<AppState>>) -> Result<RepairGameResult, String> {
+    let plan = state
+        .download_manager
+        .plan_game_repair(&slug)
+        .await
+        .map_err(|err| err.to_string())?;
+    if plan.targets.files == 0 {
+        return Ok(RepairGameResult { plan, download: None });
+    }
+    let download = begin_repair_download(state.inner(), &plan.game_id, &plan.install_dir)
+        .await
+        .map_err(|err| err.message)?;
+    Ok(RepairGameResult {
+        plan,
+        download: Some(download),
+    })
+}
+
 async fn legacy_get_game_install_info(app_id: String) -> Result<GameInstallInfo, String> {
     let steam_path = find_steam_game_path(&app_id).await;
     match steam_path {
@@ -362,7 +485,7 @@ async fn legacy_uninstall_game(_app_id: String, install_path: String) -> Result<
     Ok(())
 }
 
-async fn legacy_move_game_folder(
+pub(crate) async fn legacy_move_game_folder(
     _app_id: String,
     source_path: String,
     dest_path: String,
@@ -402,33 +525,26 @@ async fn legacy_sync_cloud_saves(_app_id: String) -> Result<(), String> {
 }
 
 async fn find_steam_game_path(app_id: &str) -> Option<PathBuf> {
-    #[cfg(target_os = "windows")]
-    {
-        let steam_paths = vec![
-            PathBuf::from("C:\\Program Files (x86)\\Steam"),
-            PathBuf::from("C:\\Program Files\\Steam"),
-        ];
-
-        for steam_path in steam_paths {
-            let library_folders = steam_path.join("steamapps").join("libraryfolders.vdf");
-            if !library_folders.exists() {
-                continue;
-            }
-            let game_path = steam_path.join("steamapps").join("common");
-            if !game_path.exists() {
-                continue;
-            }
-            if let Ok(mut entries) = tokio::fs::read_dir(&game_path).await {
-                while let Ok(Some(entry)) = entries.next_entry().await {
-                    let path = entry.path();
-                    if !path.is_dir() {
-                        continue;
-                    }
-                    let appid_file = path.join("steam_appid.txt");
-                    if let Ok(content) = tokio::fs::read_to_string(&appid_file).await {
-                        if content.trim() == app_id {
-                            return Some(path);
-                        }
+    if let Some(path) = game_stores::find_steam_game(app_id) {
+        return Some(path);
+    }
+
+    // Installs without an app manifest still carry steam_appid.txt.
+    for steam_path in game_stores::steam_libraries() {
+        let game_path = steam_path.join("steamapps").join("common");
+        if !game_path.exists() {
+            continue;
+        }
+        if let Ok(mut entries) = tokio::fs::read_dir(&game_path).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                if !path.is_dir() {
+                    continue;
+                }
+                let appid_file = path.join("steam_appid.txt");
+                if let Ok(content) = tokio::fs::read_to_string(&appid_file).await {
+                    if content.trim() == app_id {
+                        return Some(path);
                     }
                 }
             }
@@ -473,7 +589,10 @@ async fn count_and_verify_files(path: &PathBuf) -> Result<(u32, u32, u32), std::
         while let Some(entry) = entries.next_entry().await? {
             let entry_path = entry.path();
             if entry_path.is_dir() {
-                stack.push(entry_path);
+                // Crack backups aren't part of the game.
+                if entry.file_name() != BACKUP_DIR_NAME {
+                    stack.push(entry_path);
+                }
             } else {
                 total += 1;
                 if tokio::fs::metadata(&entry_path).await.is_ok() {
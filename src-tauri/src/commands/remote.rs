@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use tauri::State;
 
+use crate::commands::download::begin_download;
 use crate::services::remote_download_service::RemoteDownload;
 use crate::AppState;
 
@@ -41,3 +42,33 @@ pub async fn update_remote_download_status(
         .await
         .map_err(|err| err.to_string())
 }
+
+/// Starts the local download for a remote queue entry marked ready for this
+/// device, then reports the new state back to the queue.
+pub async fn start_ready_remote_download(state: Arc<AppState>, download: RemoteDownload) {
+    let status = match begin_download(&state, &download.game.id).await {
+        Ok(task) => {
+            tracing::info!(
+                "started remote-queued download {} for {}",
+                task.id,
+                download.game.slug
+            );
+            "downloading"
+        }
+        Err(err) => {
+            tracing::warn!(
+                "failed to start remote-queued download for {}: {}",
+                download.game.slug,
+                err.message
+            );
+            "failed"
+        }
+    };
+    if let Err(err) = state
+        .remote_downloads
+        .update_status(&download.id, status)
+        .await
+    {
+        tracing::warn!("failed to update remote download {}: {}", download.id, err);
+    }
+}
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use tauri::State;
 
 use crate::models::LicenseInfo;
+use crate::services::license_service::HardwareDrift;
 use crate::AppState;
 
 #[tauri::command]
@@ -20,3 +21,28 @@ pub async fn validate_license(
         .validate_license(&license_json)
         .map_err(|err| err.to_string())
 }
+
+/// Which hardware components changed since the license was bound, and
+/// whether the change is small enough for `request_license_rebind`.
+#[tauri::command]
+pub async fn hardware_id_changed(
+    license_json: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<HardwareDrift, String> {
+    state
+        .license
+        .hardware_id_changed(&license_json)
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn request_license_rebind(
+    license_json: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<LicenseInfo, String> {
+    state
+        .license
+        .request_rebind(&state.api, &license_json)
+        .await
+        .map_err(|err| err.to_string())
+}
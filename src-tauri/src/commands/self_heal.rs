@@ -1,8 +1,13 @@
+use std::path::Path;
 use std::sync::Arc;
 
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 
-use crate::services::{SelfHealRepairPlanV2, SelfHealReportV2, SelfHealScanRequestV2};
+use crate::commands::download::begin_repair_download;
+use crate::services::volatile_paths;
+use crate::services::{
+    IntegrityHistoryV2, SelfHealRepairPlanV2, SelfHealReportV2, SelfHealScanRequestV2,
+};
 use crate::AppState;
 
 #[tauri::command]
@@ -17,15 +22,93 @@ pub async fn run_self_heal_scan_v2(
         .map_err(|err| err.to_string())
 }
 
+/// Builds the repair plan for `report` and starts a download that refetches
+/// only the queued files into the scanned install. Progress arrives through
+/// the usual download events for the returned `download_id`, and the run
+/// re-verifies the files before completing.
 #[tauri::command]
 pub async fn apply_self_heal_v2(
     report: SelfHealReportV2,
+    app: AppHandle,
     state: State<'_, Arc<AppState>>,
 ) -> Result<SelfHealRepairPlanV2, String> {
-    state
+    let slug = report.slug.clone();
+    let game_id = report.game_id.clone();
+    let install_path = report.install_path.clone();
+    let mut plan = state
         .self_heal
         .build_repair_plan(report)
         .await
+        .map_err(|err| err.to_string())?;
+    if plan.queue.is_empty() {
+        return Ok(plan);
+    }
+    let slug = slug
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| "repair needs the game slug to fetch its manifest".to_string())?;
+
+    let paths: Vec<String> = plan.queue.iter().map(|item| item.path.clone()).collect();
+    let targets = state
+        .download_manager
+        .prepare_repair(&slug, Path::new(&install_path), &paths)
+        .await
+        .map_err(|err| err.to_string())?;
+    if !targets.unknown_paths.is_empty() {
+        tracing::warn!(
+            "repair {} skipped {} paths missing from the manifest",
+            plan.repair_id,
+            targets.unknown_paths.len()
+        );
+    }
+    let task = begin_repair_download(state.inner(), &game_id, &install_path)
+        .await
+        .map_err(|err| err.message)?;
+
+    plan.download_id = Some(task.id);
+    plan.refetch_bytes = targets.bytes;
+    let _ = app.emit("self-heal-repair-started", &plan);
+    Ok(plan)
+}
+
+const DEFAULT_INTEGRITY_EVENT_LIMIT: usize = 50;
+const MAX_INTEGRITY_EVENT_LIMIT: usize = 500;
+
+/// Scan and repair history for a game, newest first, with corrupt-file totals
+/// per day for the repair timeline.
+#[tauri::command]
+pub async fn list_integrity_events(
+    game_id: String,
+    limit: Option<usize>,
+    install_path: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<IntegrityHistoryV2, String> {
+    let limit = limit
+        .unwrap_or(DEFAULT_INTEGRITY_EVENT_LIMIT)
+        .clamp(1, MAX_INTEGRITY_EVENT_LIMIT);
+    state
+        .self_heal
+        .list_integrity_events(&game_id, install_path.as_deref(), limit)
         .map_err(|err| err.to_string())
 }
 
+/// The volatile path patterns set for `game_id`, or `None` when scans follow
+/// the list in the game's manifest.
+#[tauri::command]
+pub async fn get_volatile_paths(
+    game_id: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Option<Vec<String>>, String> {
+    Ok(volatile_paths::get_override(&state.db, &game_id))
+}
+
+/// Overrides which files integrity scans of `game_id` report without
+/// verifying; `None` goes back to the manifest's list. The launcher's crack
+/// backup folder stays excluded either way.
+#[tauri::command]
+pub async fn set_volatile_paths(
+    game_id: String,
+    patterns: Option<Vec<String>>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    volatile_paths::set_override(&state.db, &game_id, patterns).map_err(|err| err.to_string())
+}
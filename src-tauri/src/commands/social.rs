@@ -1,10 +1,13 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use serde_json::Value;
-use tauri::State;
+use tauri::{Emitter, State};
 
 use crate::services::achievement_service::UserAchievement;
-use crate::services::cloud_save_service::CloudSave;
+use crate::services::cloud_save_service::{
+    AfterPlaySync, CloudSave, SaveSyncResolution, SaveUploadProgress, SaveUploadReport,
+};
 use crate::AppState;
 
 #[tauri::command]
@@ -31,17 +34,28 @@ pub async fn list_achievements(
         .map_err(|err| err.to_string())
 }
 
+/// Uploads a save. With `save_dir` the local files are recorded as the synced
+/// state for later conflict checks.
 #[tauri::command]
 pub async fn upload_cloud_save(
     game_id: String,
     payload: Value,
+    save_dir: Option<String>,
     state: State<'_, Arc<AppState>>,
 ) -> Result<CloudSave, String> {
-    state
+    let save = state
         .cloud_saves
         .upload_save(&game_id, payload)
         .await
-        .map_err(|err| err.to_string())
+        .map_err(|err| err.to_string())?;
+    if let Some(save_dir) = save_dir {
+        state
+            .cloud_saves
+            .mark_synced(&game_id, Path::new(&save_dir), &save)
+            .await
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(save)
 }
 
 #[tauri::command]
@@ -55,3 +69,81 @@ pub async fn fetch_cloud_save(
         .await
         .map_err(|err| err.to_string())
 }
+
+/// Uploads only changed save files. `dry_run` reports what would transfer.
+/// Progress is emitted as `cloud-save-upload-progress`.
+#[tauri::command]
+pub async fn upload_cloud_save_incremental(
+    game_id: String,
+    save_dir: String,
+    dry_run: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<SaveUploadReport, String> {
+    let on_progress = move |progress: &SaveUploadProgress| {
+        let _ = app.emit("cloud-save-upload-progress", progress);
+    };
+    state
+        .cloud_saves
+        .upload_incremental(
+            &game_id,
+            Path::new(&save_dir),
+            dry_run.unwrap_or(false),
+            &on_progress,
+        )
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn get_save_sync_after_play(
+    state: State<'_, Arc<AppState>>,
+) -> Result<AfterPlaySync, String> {
+    Ok(state.cloud_saves.after_play_sync())
+}
+
+/// `off`, `notify` (emit `saves-changed`) or `upload` (upload, then emit).
+#[tauri::command]
+pub async fn set_save_sync_after_play(
+    mode: AfterPlaySync,
+    state: State<'_, Arc<AppState>>,
+) -> Result<AfterPlaySync, String> {
+    state
+        .cloud_saves
+        .set_after_play_sync(mode)
+        .map_err(|err| err.to_string())?;
+    Ok(mode)
+}
+
+/// Records the current local saves and cloud version as in sync, e.g. after
+/// the frontend has written a fetched save to disk.
+#[tauri::command]
+pub async fn mark_cloud_save_synced(
+    game_id: String,
+    save_dir: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let save = state
+        .cloud_saves
+        .fetch_save(&game_id)
+        .await
+        .map_err(|err| err.to_string())?;
+    state
+        .cloud_saves
+        .mark_synced(&game_id, Path::new(&save_dir), &save)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn resolve_cloud_save(
+    game_id: String,
+    save_dir: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<SaveSyncResolution, String> {
+    state
+        .cloud_saves
+        .resolve(&game_id, Path::new(&save_dir))
+        .await
+        .map_err(|err| err.to_string())
+}
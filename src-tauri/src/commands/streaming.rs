@@ -67,3 +67,15 @@ pub async fn add_streaming_ice_candidate(
         .await
         .map_err(|err| err.to_string())
 }
+
+#[tauri::command]
+pub async fn restart_streaming_ice(
+    session_id: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<StreamingSession, String> {
+    state
+        .streaming
+        .restart_ice(&session_id)
+        .await
+        .map_err(|err| err.to_string())
+}
@@ -2,19 +2,101 @@ use once_cell::sync::Lazy;
 use serde::Deserialize;
 use serde::Serialize;
 use std::fs;
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::Method;
 
 use tauri::{Manager, State};
-use chrono::Utc;
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDate, Utc};
 use sysinfo::System;
 
-use crate::services::{ArtworkPrefetchItem, ArtworkSources};
-use crate::utils::paths::resolve_games_dir;
+use crate::commands::overlay::apply_overlay_hotkey;
+use crate::commands::properties::{legacy_move_game_folder, save_backup_root};
+use crate::db::queries::{
+    BandwidthUsageQueries, DownloadStateQueries, GameQueries, LaunchPrefQueries,
+    LibraryFolderQueries, SettingsQueries,
+};
+use crate::db::{Database, DownloadPruneReport, VacuumReport};
+use crate::models::{BandwidthUsageRecord, BandwidthUsageTotals, GameLaunchPref};
+use crate::services::api_client::{ApiClientConfig, API_CLIENT_SETTING};
+use crate::services::data_cap::DataCapStatus;
+use crate::services::download_manager::{
+    parse_download_method, persist_game_install_roots, DownloadSchedule, Http3Status,
+    GAME_INSTALL_ROOTS_SETTING,
+};
+use crate::services::overlay_service::normalize_hotkey;
+use crate::services::steam_prefetch_worker::LocaleSettingsResponse;
+use crate::services::{
+    ArtworkPrefetchItem, ArtworkSources, ConnectivityStatus, DownloadTuning, LibraryScanEntry,
+    P2pStatus, PeerSourceConfig, PeerSourcePolicy, PeerStats, StorageOptions, WriteStrategy,
+    WriteStrategyInfo,
+};
+use crate::utils::file::{available_disk_space, FileManager};
+use crate::utils::game_stores::{self, StoreGame};
 use crate::AppState;
 
+const INSTALL_ROOT_SETTING: &str = "install_root";
+
+const SETTINGS_EXPORT_FORMAT: &str = "otoshi-launcher-settings";
+const SETTINGS_EXPORT_VERSION: u32 = 1;
+/// Preferences a settings export carries. Anything else, such as logins,
+/// caches, per-machine state and `post_install_scripts_allowed`, stays put.
+const PORTABLE_SETTINGS: [&str; 20] = [
+    "api_client_config",
+    "archive_extract_workers",
+    "chunk_write_strategy",
+    "crack_extract_workers",
+    "data_cap",
+    "download_http3",
+    "download_method",
+    "download_schedule",
+    "download_tuning",
+    "log_retention",
+    "overlay_hotkey",
+    "p2p_enabled",
+    "p2p_fanout",
+    "p2p_source_policy",
+    "p2p_upload_limit_bps",
+    "save_sync_after_play",
+    "telemetry_enabled",
+    "trade_expiry_hours",
+    "verify_before_launch",
+    "volatile_paths",
+];
+/// Portable settings holding a directory, imported only where it exists.
+const PORTABLE_PATH_SETTINGS: [&str; 3] = [
+    INSTALL_ROOT_SETTING,
+    "save_backup_dir",
+    "workshop_storage_dir",
+];
+
+const DEFAULT_PRUNE_RETENTION_DAYS: u32 = 30;
+const TERMINAL_DOWNLOAD_STATUSES: [&str; 3] = ["completed", "cancelled", "failed"];
+
+const DEFAULT_BENCHMARK_MB: u64 = 256;
+const MAX_BENCHMARK_MB: u64 = 4096;
+const BENCHMARK_BLOCK_BYTES: usize = 4 * 1024 * 1024;
+
+/// How long a `scan_installed_games` result is reused before rescanning,
+/// unless an install, uninstall or library folder change drops it first.
+const INSTALLED_GAMES_CACHE_TTL: Duration = Duration::from_secs(300);
+
 static START_INSTANT: Lazy<Instant> = Lazy::new(Instant::now);
+static INSTALLED_GAMES_CACHE: Lazy<Mutex<Option<(Instant, Vec<InstalledGame>)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DiskBenchmark {
+    path: String,
+    bytes_written: u64,
+    elapsed_ms: u64,
+    write_mbps: f64,
+}
 
 #[derive(Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -57,6 +139,8 @@ pub struct RuntimeTuningRecommendation {
     polling_fast_ms: u64,
     polling_idle_ms: u64,
     animation_level: String,
+    max_concurrent_chunks: usize,
+    integrity_workers: usize,
     reason: String,
     auto_apply_allowed: bool,
     fallback_used: bool,
@@ -72,6 +156,8 @@ pub struct RuntimeTuningApplyResult {
     polling_fast_ms: u64,
     polling_idle_ms: u64,
     animation_level: String,
+    max_concurrent_chunks: usize,
+    integrity_workers: usize,
     fallback_used: bool,
     settings_path: String,
     applied_at: String,
@@ -87,10 +173,38 @@ struct RuntimeTuningStateFile {
     polling_fast_ms: u64,
     polling_idle_ms: u64,
     animation_level: String,
+    max_concurrent_chunks: usize,
+    integrity_workers: usize,
     fallback_used: bool,
     applied_at: String,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct BandwidthUsageDay {
+    date: String,
+    #[serde(flatten)]
+    bytes: BandwidthUsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BandwidthUsageGame {
+    game_id: String,
+    #[serde(flatten)]
+    bytes: BandwidthUsageTotals,
+}
+
+/// Download volume over a date range. `network_bytes` is the metered part;
+/// peer and depotcache bytes are reported next to it, never inside it.
+#[derive(Debug, Clone, Serialize)]
+pub struct BandwidthUsageReport {
+    range: String,
+    from: String,
+    to: String,
+    total: BandwidthUsageTotals,
+    days: Vec<BandwidthUsageDay>,
+    games: Vec<BandwidthUsageGame>,
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 #[serde(default, rename_all = "snake_case")]
 pub struct ArtworkSourcesPayload {
@@ -232,6 +346,7 @@ fn recommendation_from_capabilities(
     profile_override: Option<&str>,
 ) -> RuntimeTuningRecommendation {
     if !consent {
+        let tuning = download_tuning_for(capabilities, "balanced");
         return RuntimeTuningRecommendation {
             profile: "balanced".to_string(),
             decode_concurrency: 4,
@@ -239,6 +354,8 @@ fn recommendation_from_capabilities(
             polling_fast_ms: 1100,
             polling_idle_ms: 9000,
             animation_level: "normal".to_string(),
+            max_concurrent_chunks: tuning.max_concurrent_chunks,
+            integrity_workers: tuning.integrity_workers,
             reason: "opt_in_required".to_string(),
             auto_apply_allowed: false,
             fallback_used: capabilities.fallback_used,
@@ -267,6 +384,7 @@ fn recommendation_from_capabilities(
         }
     }
 
+    let tuning = download_tuning_for(capabilities, profile);
     match profile {
         "performance" => RuntimeTuningRecommendation {
             profile: "performance".to_string(),
@@ -275,6 +393,8 @@ fn recommendation_from_capabilities(
             polling_fast_ms: 700,
             polling_idle_ms: 5000,
             animation_level: "full".to_string(),
+            max_concurrent_chunks: tuning.max_concurrent_chunks,
+            integrity_workers: tuning.integrity_workers,
             reason: "high_core_count_and_memory".to_string(),
             auto_apply_allowed: true,
             fallback_used: capabilities.fallback_used,
@@ -286,6 +406,8 @@ fn recommendation_from_capabilities(
             polling_fast_ms: 1600,
             polling_idle_ms: 12000,
             animation_level: "reduced".to_string(),
+            max_concurrent_chunks: tuning.max_concurrent_chunks,
+            integrity_workers: tuning.integrity_workers,
             reason: "limited_cpu_or_memory_budget".to_string(),
             auto_apply_allowed: true,
             fallback_used: capabilities.fallback_used,
@@ -297,6 +419,8 @@ fn recommendation_from_capabilities(
             polling_fast_ms: 1000,
             polling_idle_ms: 8000,
             animation_level: "normal".to_string(),
+            max_concurrent_chunks: tuning.max_concurrent_chunks,
+            integrity_workers: tuning.integrity_workers,
             reason: "balanced_default".to_string(),
             auto_apply_allowed: true,
             fallback_used: capabilities.fallback_used,
@@ -304,6 +428,82 @@ fn recommendation_from_capabilities(
     }
 }
 
+/// Downloader concurrency for `profile`. Every chunk is hashed as it lands, so
+/// CPUs with AVX2 hash fast enough to keep more chunks and scan workers busy
+/// per core; without it both stay closer to the physical core count.
+fn download_tuning_for(capabilities: &AsmCpuCapabilities, profile: &str) -> DownloadTuning {
+    let fast_hashing = capabilities.has_avx2 || capabilities.has_avx512;
+    let logical = capabilities.logical_cores.max(1);
+    let physical = capabilities.physical_cores.clamp(1, logical);
+    let (base_chunks, workers) = match (profile, fast_hashing) {
+        ("performance", true) => (48, logical * 2),
+        ("performance", false) => (32, logical),
+        ("power_save", _) => (12, physical / 2),
+        (_, true) => (24, logical),
+        (_, false) => (16, physical),
+    };
+    DownloadTuning {
+        max_concurrent_chunks: base_chunks.min(logical * 4).clamp(4, 64),
+        integrity_workers: workers.clamp(2, 32),
+    }
+}
+
+/// Inclusive date bounds for `range`: `today`, `7d`, `30d`, `month` (the
+/// current calendar month) or `all`.
+fn bandwidth_range_bounds(range: &str, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), String> {
+    let from = match range {
+        "today" => today,
+        "7d" => today - ChronoDuration::days(6),
+        "30d" => today - ChronoDuration::days(29),
+        "month" => today.with_day(1).unwrap_or(today),
+        "all" => NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or(NaiveDate::MIN),
+        other => return Err(format!("unsupported bandwidth usage range: {other}")),
+    };
+    Ok((from, today))
+}
+
+fn summarize_bandwidth_usage(
+    range: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    records: Vec<BandwidthUsageRecord>,
+) -> BandwidthUsageReport {
+    let mut total = BandwidthUsageTotals::default();
+    let mut days: Vec<BandwidthUsageDay> = Vec::new();
+    let mut games: HashMap<String, BandwidthUsageTotals> = HashMap::new();
+    for record in records {
+        total.add(&record.bytes);
+        games.entry(record.game_id).or_default().add(&record.bytes);
+        match days.last_mut() {
+            Some(day) if day.date == record.date => day.bytes.add(&record.bytes),
+            _ => days.push(BandwidthUsageDay {
+                date: record.date,
+                bytes: record.bytes,
+            }),
+        }
+    }
+    let mut games: Vec<BandwidthUsageGame> = games
+        .into_iter()
+        .map(|(game_id, bytes)| BandwidthUsageGame { game_id, bytes })
+        .collect();
+    games.sort_by(|left, right| {
+        right
+            .bytes
+            .network_bytes
+            .cmp(&left.bytes.network_bytes)
+            .then_with(|| left.game_id.cmp(&right.game_id))
+    });
+
+    BandwidthUsageReport {
+        range: range.to_string(),
+        from: from.format("%Y-%m-%d").to_string(),
+        to: to.format("%Y-%m-%d").to_string(),
+        total,
+        days,
+        games,
+    }
+}
+
 fn runtime_tuning_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     let data_dir = app.path().app_data_dir().map_err(|err| err.to_string())?;
     fs::create_dir_all(&data_dir).map_err(|err| err.to_string())?;
@@ -337,8 +537,1435 @@ pub async fn set_download_limit(
 }
 
 #[tauri::command]
-pub async fn get_default_install_root(app: tauri::AppHandle) -> Result<String, String> {
-    Ok(resolve_games_dir(&app).to_string_lossy().to_string())
+pub async fn get_peer_stats(state: State<'_, Arc<AppState>>) -> Result<PeerStats, String> {
+    Ok(state.download_manager.peer_stats())
+}
+
+#[tauri::command]
+pub async fn get_p2p_status(state: State<'_, Arc<AppState>>) -> Result<P2pStatus, String> {
+    Ok(state.download_manager.p2p_status())
+}
+
+/// Whether the backend is reachable and how many writes are queued for it.
+#[tauri::command]
+pub async fn get_connectivity_status(
+    state: State<'_, Arc<AppState>>,
+) -> Result<ConnectivityStatus, String> {
+    state.connectivity.status().map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn get_api_client_config(
+    state: State<'_, Arc<AppState>>,
+) -> Result<ApiClientConfig, String> {
+    Ok(state.api.config())
+}
+
+/// Sets backend request timeouts and how often idempotent requests are
+/// retried. Out-of-range values are clamped; applies to new requests.
+#[tauri::command]
+pub async fn set_api_client_config(
+    config: ApiClientConfig,
+    state: State<'_, Arc<AppState>>,
+) -> Result<ApiClientConfig, String> {
+    let config = config.clamped();
+    let raw = serde_json::to_string(&config).map_err(|err| err.to_string())?;
+    state
+        .db
+        .set_setting(API_CLIENT_SETTING, &raw)
+        .map_err(|err| err.to_string())?;
+    Ok(state.api.set_config(config))
+}
+
+/// Starts or stops sharing depotcache chunks with peers on the local network.
+#[tauri::command]
+pub async fn set_p2p_enabled(
+    enabled: bool,
+    state: State<'_, Arc<AppState>>,
+) -> Result<P2pStatus, String> {
+    state
+        .download_manager
+        .set_p2p_enabled(enabled)
+        .map_err(|err| err.to_string())
+}
+
+/// Caps uploads to peers in MB/s. Zero or less drops the cap back to half of
+/// the measured upload rate.
+#[tauri::command]
+pub async fn set_p2p_upload_limit(
+    mbps: f64,
+    state: State<'_, Arc<AppState>>,
+) -> Result<P2pStatus, String> {
+    let max_bps = (mbps > 0.0).then(|| (mbps * 1024.0 * 1024.0) as u64);
+    state
+        .download_manager
+        .set_p2p_upload_limit(max_bps)
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn get_peer_source_policy(
+    state: State<'_, Arc<AppState>>,
+) -> Result<PeerSourceConfig, String> {
+    Ok(state.download_manager.peer_source_config())
+}
+
+/// Sets how peer URLs rank against CDN URLs (`prefer-cdn`, `prefer-peer`,
+/// `balanced`) and optionally the per-chunk peer fanout.
+#[tauri::command]
+pub async fn set_peer_source_policy(
+    policy: String,
+    fanout: Option<usize>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<PeerSourceConfig, String> {
+    let policy = PeerSourcePolicy::parse(&policy)
+        .ok_or_else(|| format!("unknown peer source policy: {policy}"))?;
+    state
+        .download_manager
+        .set_peer_source_config(policy, fanout)
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn get_write_strategy(
+    state: State<'_, Arc<AppState>>,
+) -> Result<WriteStrategyInfo, String> {
+    Ok(state
+        .download_manager
+        .write_strategy_info(&state.files.install_dir()))
+}
+
+/// Overrides the chunk write strategy (`direct`, `sequential-merge`). `auto`
+/// goes back to picking by detected disk type.
+#[tauri::command]
+pub async fn set_write_strategy(
+    strategy: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<WriteStrategyInfo, String> {
+    let strategy = if strategy.trim().eq_ignore_ascii_case("auto") {
+        None
+    } else {
+        Some(
+            WriteStrategy::parse(&strategy)
+                .ok_or_else(|| format!("unknown write strategy: {strategy}"))?,
+        )
+    };
+    state
+        .download_manager
+        .set_write_strategy_override(strategy)
+        .map_err(|err| err.to_string())?;
+    Ok(state
+        .download_manager
+        .write_strategy_info(&state.files.install_dir()))
+}
+
+#[tauri::command]
+pub async fn get_extract_workers(state: State<'_, Arc<AppState>>) -> Result<usize, String> {
+    Ok(state.download_manager.extract_workers())
+}
+
+/// Sets how many archives are unpacked at once; `None` follows the core count.
+#[tauri::command]
+pub async fn set_extract_workers(
+    workers: Option<usize>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<usize, String> {
+    state
+        .download_manager
+        .set_extract_workers(workers)
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn get_storage_options(
+    state: State<'_, Arc<AppState>>,
+) -> Result<StorageOptions, String> {
+    Ok(state.download_manager.storage_options())
+}
+
+/// Sets the free-space safety margin and whether `.part` files are
+/// preallocated (and sparse). Applies to downloads started afterwards.
+#[tauri::command]
+pub async fn set_storage_options(
+    options: StorageOptions,
+    state: State<'_, Arc<AppState>>,
+) -> Result<StorageOptions, String> {
+    state
+        .download_manager
+        .set_storage_options(options)
+        .map_err(|err| err.to_string())
+}
+
+/// Daily totals and a per-game breakdown of downloaded bytes. `range` is one
+/// of `today`, `7d`, `30d`, `month` (default) or `all`, in local dates.
+#[tauri::command]
+pub async fn get_bandwidth_usage(
+    range: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<BandwidthUsageReport, String> {
+    let range = range
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "month".to_string());
+    let (from, to) = bandwidth_range_bounds(&range, Local::now().date_naive())?;
+    let records = state
+        .db
+        .list_bandwidth_usage(
+            &from.format("%Y-%m-%d").to_string(),
+            &to.format("%Y-%m-%d").to_string(),
+        )
+        .map_err(|err| err.to_string())?;
+    Ok(summarize_bandwidth_usage(&range, from, to, records))
+}
+
+#[tauri::command]
+pub async fn get_data_cap(state: State<'_, Arc<AppState>>) -> Result<DataCapStatus, String> {
+    state
+        .download_manager
+        .data_cap_status()
+        .map_err(|err| err.to_string())
+}
+
+/// `monthly_cap_bytes` of 0 turns the cap off. `reset_day` (1-28, default 1)
+/// is the day of the month a new cycle starts.
+#[tauri::command]
+pub async fn set_data_cap(
+    monthly_cap_bytes: u64,
+    reset_day: Option<u32>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<DataCapStatus, String> {
+    state
+        .download_manager
+        .set_data_cap(monthly_cap_bytes, reset_day.unwrap_or(1))
+        .map_err(|err| err.to_string())
+}
+
+/// Keeps downloading past the cap for the rest of the current cycle.
+#[tauri::command]
+pub async fn override_data_cap(state: State<'_, Arc<AppState>>) -> Result<DataCapStatus, String> {
+    state
+        .download_manager
+        .override_data_cap()
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn get_download_http3(state: State<'_, Arc<AppState>>) -> Result<Http3Status, String> {
+    Ok(state.download_manager.http3_status())
+}
+
+#[tauri::command]
+pub async fn set_download_http3(
+    enabled: bool,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Http3Status, String> {
+    state
+        .download_manager
+        .set_http3_enabled(enabled)
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn get_post_install_allowed(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    Ok(state.download_manager.post_install_allowed())
+}
+
+/// When allowed, manifest post-install steps run right after a download
+/// completes; otherwise each one waits for `run_post_install`.
+#[tauri::command]
+pub async fn set_post_install_allowed(
+    allowed: bool,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    state
+        .download_manager
+        .set_post_install_allowed(allowed)
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn get_save_backup_dir(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    save_backup_root(&app, &state).map(|dir| dir.to_string_lossy().to_string())
+}
+
+/// Folder `uninstall_game_with_backup` copies saves into; `None` resets it.
+#[tauri::command]
+pub async fn set_save_backup_dir(
+    dir: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    state
+        .cloud_saves
+        .set_backup_dir(dir.as_deref())
+        .map_err(|err| err.to_string())
+}
+
+/// Removes the benchmark file however the measurement ends.
+struct BenchmarkFile(PathBuf);
+
+impl Drop for BenchmarkFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Writes `size_bytes` to a temp file in `dir`, fsyncs it and deletes it.
+/// The fsync is part of the timing so OS write caching doesn't inflate the
+/// result.
+fn measure_sequential_write(dir: &Path, size_bytes: u64) -> std::io::Result<DiskBenchmark> {
+    let path = dir.join(format!(".otoshi-disk-bench-{}.tmp", uuid::Uuid::new_v4()));
+    let guard = BenchmarkFile(path.clone());
+    // Non-zero data so compressing or deduplicating filesystems can't skip it.
+    let block: Vec<u8> = (0..BENCHMARK_BLOCK_BYTES)
+        .map(|index| (index % 251) as u8)
+        .collect();
+
+    let started = Instant::now();
+    let mut file = fs::File::create(&guard.0)?;
+    let mut written = 0u64;
+    while written < size_bytes {
+        let len = (size_bytes - written).min(block.len() as u64) as usize;
+        file.write_all(&block[..len])?;
+        written += len as u64;
+    }
+    file.sync_all()?;
+    let elapsed = started.elapsed();
+    drop(file);
+    drop(guard);
+
+    let seconds = elapsed.as_secs_f64().max(1e-6);
+    Ok(DiskBenchmark {
+        path: path.to_string_lossy().to_string(),
+        bytes_written: written,
+        elapsed_ms: elapsed.as_millis() as u64,
+        write_mbps: written as f64 / (1024.0 * 1024.0) / seconds,
+    })
+}
+
+/// Measures sequential write speed at `path` with a temporary file of
+/// `size_mb` (default 256 MB), so a slow drive can be told apart from a slow
+/// network.
+#[tauri::command]
+pub async fn benchmark_disk(path: String, size_mb: Option<u64>) -> Result<DiskBenchmark, String> {
+    let dir = PathBuf::from(path.trim());
+    if !dir.is_dir() {
+        return Err(format!("{} is not a folder", dir.display()));
+    }
+    let size_mb = size_mb.unwrap_or(DEFAULT_BENCHMARK_MB);
+    if size_mb == 0 || size_mb > MAX_BENCHMARK_MB {
+        return Err(format!("benchmark size must be 1-{} MB", MAX_BENCHMARK_MB));
+    }
+    let size_bytes = size_mb * 1024 * 1024;
+    if let Some(available) = available_disk_space(&dir) {
+        if available < size_bytes.saturating_mul(2) {
+            return Err(format!(
+                "not enough free space for a {} MB benchmark",
+                size_mb
+            ));
+        }
+    }
+
+    let report =
+        tauri::async_runtime::spawn_blocking(move || measure_sequential_write(&dir, size_bytes))
+            .await
+            .map_err(|err| err.to_string())?
+            .map_err(|err| err.to_string())?;
+    tracing::info!(
+        "disk benchmark path={} mb={} write_mbps={:.1}",
+        report.path,
+        size_mb,
+        report.write_mbps
+    );
+    Ok(report)
+}
+
+/// Generates a new anonymous install id for request tagging.
+#[tauri::command]
+pub async fn reset_install_id() -> Result<String, String> {
+    crate::utils::client_identity::reset_install_id()
+        .ok_or_else(|| "install id is not initialized".to_string())
+}
+
+/// Records the user's telemetry consent. Disabling drops queued events.
+#[tauri::command]
+pub async fn set_telemetry_enabled(
+    enabled: bool,
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool, String> {
+    state
+        .telemetry
+        .set_enabled(enabled)
+        .map_err(|err| err.to_string())?;
+    Ok(state.telemetry.is_enabled())
+}
+
+const SUPPORTED_LOCALES: [&str; 2] = ["en", "vi"];
+const LOCALE_SYNC_KEY: &str = "settings:locale";
+
+#[derive(Debug, Serialize)]
+pub struct SettingsSnapshot {
+    pub download_limit_mbps: f64,
+    pub download_method: String,
+    pub download_schedule: Option<DownloadSchedule>,
+    pub install_root: String,
+    pub peer_source: PeerSourceConfig,
+    pub write_strategy: WriteStrategyInfo,
+    pub storage: StorageOptions,
+    pub telemetry_enabled: bool,
+    pub workshop_storage_dir: Option<String>,
+    pub overlay_hotkey: Option<String>,
+    /// The backend's saved locale; `None` while it can't be reached.
+    pub locale: Option<String>,
+}
+
+/// Fields left out are not touched. An empty `workshop_storage_dir` clears it,
+/// `download_schedule: null` drops the window and `write_strategy: "auto"`
+/// drops the override.
+#[derive(Debug, Default, Deserialize)]
+pub struct SettingsPatch {
+    pub download_limit_mbps: Option<f64>,
+    pub download_method: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub download_schedule: Option<Option<DownloadSchedule>>,
+    pub install_root: Option<String>,
+    pub peer_source_policy: Option<String>,
+    pub peer_fanout: Option<usize>,
+    pub write_strategy: Option<String>,
+    pub storage: Option<StorageOptions>,
+    pub telemetry_enabled: Option<bool>,
+    pub workshop_storage_dir: Option<String>,
+    pub overlay_hotkey: Option<String>,
+    pub locale: Option<String>,
+}
+
+/// Tells a field sent as `null` apart from one left out.
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SettingFailure {
+    pub field: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApplySettingsReport {
+    pub applied: Vec<String>,
+    pub failed: Vec<SettingFailure>,
+    pub settings: SettingsSnapshot,
+}
+
+#[derive(Debug, PartialEq)]
+enum SettingChange {
+    DownloadLimit(f64),
+    DownloadMethod(String),
+    DownloadSchedule(Option<DownloadSchedule>),
+    InstallRoot(PathBuf),
+    PeerSource {
+        policy: Option<PeerSourcePolicy>,
+        fanout: Option<usize>,
+    },
+    WriteStrategy(Option<WriteStrategy>),
+    Storage(StorageOptions),
+    Telemetry(bool),
+    WorkshopStorageDir(Option<PathBuf>),
+    OverlayHotkey(String),
+    Locale(&'static str),
+}
+
+impl SettingChange {
+    fn field(&self) -> &'static str {
+        match self {
+            Self::DownloadLimit(_) => "download_limit_mbps",
+            Self::DownloadMethod(_) => "download_method",
+            Self::DownloadSchedule(_) => "download_schedule",
+            Self::InstallRoot(_) => "install_root",
+            Self::PeerSource { .. } => "peer_source",
+            Self::WriteStrategy(_) => "write_strategy",
+            Self::Storage(_) => "storage",
+            Self::Telemetry(_) => "telemetry_enabled",
+            Self::WorkshopStorageDir(_) => "workshop_storage_dir",
+            Self::OverlayHotkey(_) => "overlay_hotkey",
+            Self::Locale(_) => "locale",
+        }
+    }
+}
+
+/// Maps `en-US`, `vi_VN` and the like onto a locale the launcher ships.
+fn parse_locale(value: &str) -> Option<&'static str> {
+    let language = value.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+    SUPPORTED_LOCALES
+        .into_iter()
+        .find(|locale| *locale == language)
+}
+
+/// Checks every field of `patch` on its own, so one bad value does not block
+/// the others.
+fn validate_settings_patch(patch: &SettingsPatch) -> (Vec<SettingChange>, Vec<SettingFailure>) {
+    let mut changes = Vec::new();
+    let mut failures = Vec::new();
+    let mut fail = |field: &str, error: String| {
+        failures.push(SettingFailure {
+            field: field.to_string(),
+            error,
+        })
+    };
+
+    if let Some(limit) = patch.download_limit_mbps {
+        if limit.is_finite() && limit >= 0.0 {
+            changes.push(SettingChange::DownloadLimit(limit));
+        } else {
+            fail(
+                "download_limit_mbps",
+                format!("invalid download limit: {limit}"),
+            );
+        }
+    }
+    if let Some(method) = patch.download_method.as_deref() {
+        match parse_download_method(method) {
+            Some(key) => changes.push(SettingChange::DownloadMethod(key)),
+            None => fail(
+                "download_method",
+                format!("unknown download method: {method}"),
+            ),
+        }
+    }
+    match patch.download_schedule {
+        Some(Some(schedule)) if !schedule.is_valid() => fail(
+            "download_schedule",
+            format!(
+                "invalid download schedule: {}-{}",
+                schedule.start_hour, schedule.end_hour
+            ),
+        ),
+        Some(schedule) => changes.push(SettingChange::DownloadSchedule(schedule)),
+        None => {}
+    }
+    if let Some(path) = patch.install_root.as_deref() {
+        match parse_install_root(path) {
+            Ok(root) => changes.push(SettingChange::InstallRoot(root)),
+            Err(err) => fail("install_root", err),
+        }
+    }
+
+    let policy = match patch.peer_source_policy.as_deref() {
+        Some(value) => PeerSourcePolicy::parse(value)
+            .map(Some)
+            .ok_or_else(|| format!("unknown peer source policy: {value}")),
+        None => Ok(None),
+    };
+    match (policy, patch.peer_fanout) {
+        (Err(err), _) => fail("peer_source_policy", err),
+        (Ok(_), Some(0)) => fail("peer_fanout", "peer fanout must be at least 1".to_string()),
+        (Ok(None), None) => {}
+        (Ok(policy), fanout) => changes.push(SettingChange::PeerSource { policy, fanout }),
+    }
+
+    if let Some(value) = patch.write_strategy.as_deref() {
+        if value.trim().eq_ignore_ascii_case("auto") {
+            changes.push(SettingChange::WriteStrategy(None));
+        } else {
+            match WriteStrategy::parse(value) {
+                Some(strategy) => changes.push(SettingChange::WriteStrategy(Some(strategy))),
+                None => fail("write_strategy", format!("unknown write strategy: {value}")),
+            }
+        }
+    }
+    if let Some(options) = patch.storage {
+        changes.push(SettingChange::Storage(options));
+    }
+    if let Some(enabled) = patch.telemetry_enabled {
+        changes.push(SettingChange::Telemetry(enabled));
+    }
+    if let Some(path) = patch.workshop_storage_dir.as_deref() {
+        let trimmed = path.trim();
+        if trimmed.is_empty() {
+            changes.push(SettingChange::WorkshopStorageDir(None));
+        } else if Path::new(trimmed).is_absolute() {
+            changes.push(SettingChange::WorkshopStorageDir(Some(PathBuf::from(
+                trimmed,
+            ))));
+        } else {
+            fail(
+                "workshop_storage_dir",
+                "workshop storage dir must be an absolute path".to_string(),
+            );
+        }
+    }
+    if let Some(combo) = patch.overlay_hotkey.as_deref() {
+        match normalize_hotkey(combo) {
+            Ok(normalized) => changes.push(SettingChange::OverlayHotkey(normalized)),
+            Err(err) => fail("overlay_hotkey", err),
+        }
+    }
+    if let Some(value) = patch.locale.as_deref() {
+        match parse_locale(value) {
+            Some(locale) => changes.push(SettingChange::Locale(locale)),
+            None => fail("locale", format!("unsupported locale: {value}")),
+        }
+    }
+
+    (changes, failures)
+}
+
+async fn apply_setting_change(
+    change: SettingChange,
+    app: &tauri::AppHandle,
+    state: &AppState,
+) -> Result<(), String> {
+    match change {
+        SettingChange::DownloadLimit(limit) => state
+            .download_manager
+            .set_download_limit(limit)
+            .await
+            .map_err(|err| err.to_string()),
+        SettingChange::DownloadMethod(method) => state
+            .download_manager
+            .set_download_method(&method)
+            .map(|_| ())
+            .map_err(|err| err.to_string()),
+        SettingChange::DownloadSchedule(schedule) => state
+            .download_manager
+            .set_download_schedule(schedule)
+            .map_err(|err| err.to_string()),
+        SettingChange::InstallRoot(root) => {
+            fs::create_dir_all(&root).map_err(|err| err.to_string())?;
+            save_global_install_root(state, &root)
+        }
+        SettingChange::PeerSource { policy, fanout } => {
+            let policy =
+                policy.unwrap_or_else(|| state.download_manager.peer_source_config().policy);
+            state
+                .download_manager
+                .set_peer_source_config(policy, fanout)
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        }
+        SettingChange::WriteStrategy(strategy) => state
+            .download_manager
+            .set_write_strategy_override(strategy)
+            .map_err(|err| err.to_string()),
+        SettingChange::Storage(options) => state
+            .download_manager
+            .set_storage_options(options)
+            .map(|_| ())
+            .map_err(|err| err.to_string()),
+        SettingChange::Telemetry(enabled) => state
+            .telemetry
+            .set_enabled(enabled)
+            .map_err(|err| err.to_string()),
+        SettingChange::WorkshopStorageDir(dir) => {
+            let workshop = state.workshop.clone();
+            tokio::task::spawn_blocking(move || workshop.set_storage_dir(dir))
+                .await
+                .map_err(|err| err.to_string())?
+                .map_err(|err| err.to_string())
+        }
+        SettingChange::OverlayHotkey(normalized) => apply_overlay_hotkey(app, state, &normalized),
+        SettingChange::Locale(locale) => save_locale(state, locale).await,
+    }
+}
+
+/// Saves the locale on the backend. Offline, the change is queued and sent
+/// once it's reachable; the latest one wins.
+async fn save_locale(state: &AppState, locale: &str) -> Result<(), String> {
+    let body = serde_json::json!({ "locale": locale });
+    match state
+        .api
+        .post::<serde_json::Value, _>("/settings/locale", body.clone(), false)
+        .await
+    {
+        Ok(_) => {
+            state.connectivity.supersede(LOCALE_SYNC_KEY);
+            Ok(())
+        }
+        Err(err) if !state.connectivity.is_online() => {
+            tracing::warn!("locale change deferred: {}", err);
+            state
+                .connectivity
+                .enqueue(LOCALE_SYNC_KEY, Method::POST, "/settings/locale", body)
+                .map_err(|err| err.to_string())
+        }
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+async fn settings_snapshot(state: &AppState) -> SettingsSnapshot {
+    let install_root = state.files.install_dir();
+    SettingsSnapshot {
+        download_limit_mbps: state.download_manager.download_limit_mbps().await,
+        download_method: state.download_manager.download_method(),
+        download_schedule: state.download_manager.download_schedule(),
+        install_root: install_root.to_string_lossy().to_string(),
+        peer_source: state.download_manager.peer_source_config(),
+        write_strategy: state.download_manager.write_strategy_info(&install_root),
+        storage: state.download_manager.storage_options(),
+        telemetry_enabled: state.telemetry.is_enabled(),
+        workshop_storage_dir: state
+            .workshop
+            .storage_dir()
+            .map(|dir| dir.to_string_lossy().to_string()),
+        overlay_hotkey: state.overlay.hotkey(),
+        locale: state
+            .api
+            .get::<LocaleSettingsResponse>("/settings/locale", false)
+            .await
+            .ok()
+            .and_then(|settings| settings.locale),
+    }
+}
+
+/// Returns every user-facing launcher setting in one response.
+#[tauri::command]
+pub async fn get_all_settings(state: State<'_, Arc<AppState>>) -> Result<SettingsSnapshot, String> {
+    Ok(settings_snapshot(&state).await)
+}
+
+/// Applies the fields present in `patch`. Invalid or failing fields are
+/// reported per field; the rest are still applied.
+#[tauri::command]
+pub async fn apply_settings(
+    patch: SettingsPatch,
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<ApplySettingsReport, String> {
+    let (changes, mut failed) = validate_settings_patch(&patch);
+    let mut applied = Vec::new();
+    for change in changes {
+        let field = change.field();
+        match apply_setting_change(change, &app, &state).await {
+            Ok(()) => applied.push(field.to_string()),
+            Err(error) => {
+                tracing::warn!("failed to apply setting {field}: {error}");
+                failed.push(SettingFailure {
+                    field: field.to_string(),
+                    error,
+                });
+            }
+        }
+    }
+    Ok(ApplySettingsReport {
+        applied,
+        failed,
+        settings: settings_snapshot(&state).await,
+    })
+}
+
+/// Writes a consistent snapshot of the launcher database to `dest_path`
+/// while the app keeps running.
+#[tauri::command]
+pub async fn backup_database(
+    dest_path: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    let dest = PathBuf::from(dest_path.trim());
+    if dest.as_os_str().is_empty() {
+        return Err("backup path cannot be empty".to_string());
+    }
+    state.db.backup_to(&dest).map_err(|err| err.to_string())?;
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Replaces the launcher database with a backup made by `backup_database`.
+#[tauri::command]
+pub async fn restore_database(
+    src_path: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let src = PathBuf::from(src_path.trim());
+    state.db.restore_from(&src).map_err(|err| err.to_string())?;
+    restore_install_roots(&state.db, &state.files);
+    tracing::info!("database restored from {}", src.display());
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn vacuum_database(state: State<'_, Arc<AppState>>) -> Result<VacuumReport, String> {
+    state.db.vacuum().map_err(|err| err.to_string())
+}
+
+/// Removes download states, chunks and download rows for terminal downloads
+/// older than `retention_days` (default 30) whose install dir is gone.
+#[tauri::command]
+pub async fn prune_download_state(
+    retention_days: Option<u32>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<DownloadPruneReport, String> {
+    let retention_secs = i64::from(retention_days.unwrap_or(DEFAULT_PRUNE_RETENTION_DAYS)) * 86_400;
+    let cutoff = Utc::now().timestamp() - retention_secs;
+    let states = state
+        .db
+        .list_download_states()
+        .map_err(|err| err.to_string())?;
+    let prunable = states
+        .into_iter()
+        .filter(|item| {
+            TERMINAL_DOWNLOAD_STATUSES.contains(&item.status.as_str())
+                && item.updated_at < cutoff
+                && !Path::new(item.install_dir.trim()).exists()
+        })
+        .map(|item| item.id)
+        .collect::<Vec<_>>();
+    let report = state
+        .db
+        .prune_download_states(&prunable, cutoff)
+        .map_err(|err| err.to_string())?;
+    tracing::info!(
+        "pruned {} download states, {} chunks, {} download rows",
+        report.states,
+        report.chunks,
+        report.downloads
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn get_default_install_root(
+    slug: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    let root = match slug.as_deref().map(str::trim).filter(|value| !value.is_empty()) {
+        Some(slug) => state.files.game_install_root(slug),
+        None => state.files.install_dir(),
+    };
+    Ok(root.to_string_lossy().to_string())
+}
+
+/// Sets the install root for new downloads, either globally or for one game.
+/// An already-installed game is moved to the new root.
+#[tauri::command]
+pub async fn set_default_install_root(
+    path: String,
+    slug: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    let root = parse_install_root(&path)?;
+    fs::create_dir_all(&root).map_err(|err| err.to_string())?;
+
+    let slug = slug
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string);
+
+    let Some(slug) = slug else {
+        save_global_install_root(&state, &root)?;
+        return Ok(root.to_string_lossy().to_string());
+    };
+
+    let install_state = state
+        .db
+        .get_download_state_by_slug(&slug)
+        .map_err(|err| err.to_string())?;
+    let mut moved_dir = None;
+    if let Some(mut existing) = install_state {
+        if matches!(existing.status.as_str(), "downloading" | "verifying") {
+            return Err(format!("{slug} is busy; pause the download before moving it"));
+        }
+        let current_dir = PathBuf::from(existing.install_dir.trim());
+        let folder_name = current_dir
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_else(|| slug.clone().into());
+        let target_dir = root.join(folder_name);
+        if current_dir.exists() && target_dir != current_dir {
+            legacy_move_game_folder(
+                existing.game_id.clone(),
+                current_dir.to_string_lossy().to_string(),
+                target_dir.to_string_lossy().to_string(),
+            )
+            .await?;
+            tracing::info!(
+                "moved {} from {} to {}",
+                slug,
+                current_dir.display(),
+                target_dir.display()
+            );
+        }
+        existing.install_dir = target_dir.to_string_lossy().to_string();
+        existing.updated_at = Utc::now().timestamp();
+        state
+            .db
+            .save_download_state(&existing)
+            .map_err(|err| err.to_string())?;
+        moved_dir = Some(target_dir);
+    }
+
+    state.files.set_game_install_root(&slug, Some(root.clone()));
+    persist_game_install_roots(&state.db, &state.files).map_err(|err| err.to_string())?;
+    let game_dir = moved_dir.unwrap_or_else(|| state.files.get_game_dir(&slug));
+    Ok(game_dir.to_string_lossy().to_string())
+}
+
+fn parse_install_root(path: &str) -> Result<PathBuf, String> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return Err("install root cannot be empty".to_string());
+    }
+    let root = PathBuf::from(trimmed);
+    if !root.is_absolute() {
+        return Err("install root must be an absolute path".to_string());
+    }
+    Ok(root)
+}
+
+fn save_global_install_root(state: &AppState, root: &Path) -> Result<(), String> {
+    pin_installed_game_roots(state)?;
+    state
+        .db
+        .set_setting(INSTALL_ROOT_SETTING, &root.to_string_lossy())
+        .map_err(|err| err.to_string())?;
+    state.files.set_install_dir(root.to_path_buf());
+    invalidate_installed_games();
+    tracing::info!("default install root set to {}", root.display());
+    Ok(())
+}
+
+/// Keeps every game installed under the current default root there before the
+/// default moves, so it still launches and updates from where it is.
+fn pin_installed_game_roots(state: &AppState) -> Result<(), String> {
+    let default_root = state.files.install_dir();
+    let mut installs: Vec<(String, PathBuf)> = installed_games_in(&default_root)
+        .into_iter()
+        .map(|slug| (slug.clone(), default_root.join(slug)))
+        .collect();
+    for download in state
+        .db
+        .list_download_states()
+        .map_err(|err| err.to_string())?
+    {
+        let install_dir = download.install_dir.trim();
+        if !install_dir.is_empty() {
+            installs.push((download.slug, PathBuf::from(install_dir)));
+        }
+    }
+    if state.files.pin_install_roots(installs) {
+        persist_game_install_roots(&state.db, &state.files).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+/// A folder games can be installed into, with what is installed there.
+#[derive(Serialize)]
+pub struct LibraryFolderInfo {
+    pub path: String,
+    /// The default install root; it can't be removed.
+    pub is_default: bool,
+    /// `false` when the folder is missing, e.g. an unplugged drive.
+    pub available: bool,
+    pub free_bytes: Option<u64>,
+    /// Slugs of the games installed in the folder.
+    pub games: Vec<String>,
+}
+
+#[tauri::command]
+pub async fn list_library_folders(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<LibraryFolderInfo>, String> {
+    Ok(library_folder_infos(&state.files))
+}
+
+/// Adds a library folder; new installs go to the folder with the most free
+/// space unless a game has its own root.
+#[tauri::command]
+pub async fn add_library_folder(
+    path: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<LibraryFolderInfo>, String> {
+    let root = parse_install_root(&path)?;
+    fs::create_dir_all(&root).map_err(|err| err.to_string())?;
+    state
+        .db
+        .add_library_folder(&root.to_string_lossy())
+        .map_err(|err| err.to_string())?;
+    restore_library_folders(&state.db, &state.files);
+    invalidate_installed_games();
+    Ok(library_folder_infos(&state.files))
+}
+
+/// Removes a library folder. Games still installed there must be moved or
+/// uninstalled first; nothing on disk is deleted.
+#[tauri::command]
+pub async fn remove_library_folder(
+    path: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<LibraryFolderInfo>, String> {
+    let root = parse_install_root(&path)?;
+    let mut installed = installed_games_in(&root);
+    for download in state
+        .db
+        .list_download_states()
+        .map_err(|err| err.to_string())?
+    {
+        if Path::new(download.install_dir.trim()).starts_with(&root)
+            && !installed.contains(&download.slug)
+        {
+            installed.push(download.slug);
+        }
+    }
+    if !installed.is_empty() {
+        return Err(format!(
+            "{} still has games installed: {}",
+            root.display(),
+            installed.join(", ")
+        ));
+    }
+    let removed = state
+        .db
+        .remove_library_folder(&root.to_string_lossy())
+        .map_err(|err| err.to_string())?;
+    if !removed {
+        return Err(format!("{} is not a library folder", root.display()));
+    }
+    restore_library_folders(&state.db, &state.files);
+    invalidate_installed_games();
+    Ok(library_folder_infos(&state.files))
+}
+
+/// Finds games already installed in `path` by their `manifest.json` and
+/// records the complete ones as installed, so they aren't downloaded again.
+#[tauri::command]
+pub async fn scan_library_folder(
+    path: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<LibraryScanEntry>, String> {
+    let root = parse_install_root(&path)?;
+    if !root.is_dir() {
+        return Err(format!("{} does not exist", root.display()));
+    }
+    let entries = state
+        .download_manager
+        .scan_library_folder(&root)
+        .await
+        .map_err(|err| err.to_string())?;
+    invalidate_installed_games();
+    Ok(entries)
+}
+
+/// A game installed on this machine, by the launcher or another store.
+#[derive(Clone, Serialize)]
+pub struct InstalledGame {
+    /// `otoshi`, `steam`, `epic` or `gog`.
+    pub store: String,
+    pub id: Option<String>,
+    pub name: String,
+    pub path: String,
+    /// Whether the launcher has the install recorded and keeps it updated.
+    pub managed: bool,
+}
+
+/// Lists the games installed through Steam, Epic and GOG plus those in the
+/// launcher's library folders, one entry per folder. The result is cached
+/// for a few minutes unless `refresh` is set.
+#[tauri::command]
+pub async fn scan_installed_games(
+    refresh: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<InstalledGame>, String> {
+    if !refresh.unwrap_or(false) {
+        let cached = INSTALLED_GAMES_CACHE
+            .lock()
+            .ok()
+            .and_then(|cache| cache.clone());
+        if let Some((scanned_at, games)) = cached {
+            if scanned_at.elapsed() < INSTALLED_GAMES_CACHE_TTL {
+                return Ok(games);
+            }
+        }
+    }
+
+    // Installs the launcher has recorded, keyed by folder.
+    let mut managed: HashMap<String, (String, String, PathBuf)> = HashMap::new();
+    for game in state.db.get_games().map_err(|err| err.to_string())? {
+        if let Some(install_path) = game.install_path.as_deref() {
+            let path = PathBuf::from(install_path.trim());
+            managed.insert(game_stores::path_key(&path), (game.id, game.title, path));
+        }
+    }
+    for download in state
+        .db
+        .list_download_states()
+        .map_err(|err| err.to_string())?
+    {
+        if download.status != "completed" {
+            continue;
+        }
+        let path = PathBuf::from(download.install_dir.trim());
+        managed.entry(game_stores::path_key(&path)).or_insert((
+            download.game_id,
+            download.slug,
+            path,
+        ));
+    }
+    let roots = install_roots(&state.files);
+
+    let games = tauri::async_runtime::spawn_blocking(move || {
+        let store_games = game_stores::steam_games()
+            .into_iter()
+            .chain(game_stores::epic_games())
+            .chain(game_stores::gog_games())
+            .collect();
+        collect_installed_games(store_games, &roots, &managed)
+    })
+    .await
+    .map_err(|err| err.to_string())?;
+    if let Ok(mut cache) = INSTALLED_GAMES_CACHE.lock() {
+        *cache = Some((Instant::now(), games.clone()));
+    }
+    Ok(games)
+}
+
+/// Drops the cached `scan_installed_games` result so the next call rescans.
+pub(crate) fn invalidate_installed_games() {
+    if let Ok(mut cache) = INSTALLED_GAMES_CACHE.lock() {
+        *cache = None;
+    }
+}
+
+/// Store installs come first so a folder the launcher also tracks keeps the
+/// store it came from.
+fn collect_installed_games(
+    store_games: Vec<StoreGame>,
+    roots: &[PathBuf],
+    managed: &HashMap<String, (String, String, PathBuf)>,
+) -> Vec<InstalledGame> {
+    let mut found: Vec<(Option<String>, String, String, PathBuf)> = Vec::new();
+    for StoreGame {
+        store,
+        id,
+        name,
+        install_path,
+    } in store_games
+    {
+        found.push((Some(id), name, store.as_str().to_string(), install_path));
+    }
+    for (id, name, path) in managed.values() {
+        if path.is_dir() {
+            found.push((
+                Some(id.clone()),
+                name.clone(),
+                "otoshi".to_string(),
+                path.clone(),
+            ));
+        }
+    }
+    for root in roots {
+        for slug in installed_games_in(root) {
+            let path = root.join(&slug);
+            let game_id = fs::read_to_string(path.join("manifest.json"))
+                .ok()
+                .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+                .and_then(|manifest| manifest.get("game_id")?.as_str().map(str::to_string));
+            found.push((game_id, slug, "otoshi".to_string(), path));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut games: Vec<InstalledGame> = found
+        .into_iter()
+        .filter_map(|(id, name, store, path)| {
+            let key = game_stores::path_key(&path);
+            if !seen.insert(key.clone()) {
+                return None;
+            }
+            let name = if name.trim().is_empty() {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            } else {
+                name
+            };
+            Some(InstalledGame {
+                store,
+                id,
+                name,
+                path: path.to_string_lossy().to_string(),
+                managed: managed.contains_key(&key),
+            })
+        })
+        .collect();
+    games.sort_by_key(|game| game.name.to_lowercase());
+    games
+}
+
+/// The default install root followed by the other library folders.
+fn install_roots(files: &FileManager) -> Vec<PathBuf> {
+    let default_root = files.install_dir();
+    let mut roots = vec![default_root.clone()];
+    roots.extend(
+        files
+            .library_roots()
+            .into_iter()
+            .filter(|root| *root != default_root),
+    );
+    roots
+}
+
+fn library_folder_infos(files: &FileManager) -> Vec<LibraryFolderInfo> {
+    let default_root = files.install_dir();
+    install_roots(files)
+        .into_iter()
+        .map(|root| {
+            let available = root.is_dir();
+            LibraryFolderInfo {
+                path: root.to_string_lossy().to_string(),
+                is_default: root == default_root,
+                available,
+                free_bytes: available.then(|| available_disk_space(&root)).flatten(),
+                games: installed_games_in(&root),
+            }
+        })
+        .collect()
+}
+
+/// Game folders directly under `root` that hold a launcher `manifest.json`.
+fn installed_games_in(root: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut games: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("manifest.json").is_file())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    games.sort();
+    games
+}
+
+/// Loads the library folders and points games found in them at their
+/// folder, so installs on other drives are picked up at startup.
+fn restore_library_folders(db: &Database, files: &FileManager) {
+    let folders = match db.list_library_folders() {
+        Ok(folders) => folders,
+        Err(err) => {
+            tracing::warn!("failed to load library folders: {err}");
+            return;
+        }
+    };
+    let roots: Vec<PathBuf> = folders
+        .into_iter()
+        .map(|folder| PathBuf::from(folder.path))
+        .collect();
+    let explicit = files.game_install_roots();
+    let mut found = 0;
+    for root in &roots {
+        for slug in installed_games_in(root) {
+            if !explicit.contains_key(&slug) {
+                files.set_game_install_root(&slug, Some(root.clone()));
+                found += 1;
+            }
+        }
+    }
+    if !roots.is_empty() {
+        tracing::info!(
+            "{} library folders, {} installed games found in them",
+            roots.len(),
+            found
+        );
+    }
+    files.set_library_roots(roots);
+}
+
+/// Applies install roots persisted by `set_default_install_root`, then the
+/// library folders.
+pub(crate) fn restore_install_roots(db: &Database, files: &FileManager) {
+    if let Ok(Some(root)) = db.get_setting(INSTALL_ROOT_SETTING) {
+        let root = root.trim();
+        if !root.is_empty() {
+            files.set_install_dir(PathBuf::from(root));
+        }
+    }
+    if let Ok(Some(raw)) = db.get_setting(GAME_INSTALL_ROOTS_SETTING) {
+        match serde_json::from_str::<HashMap<String, String>>(&raw) {
+            Ok(roots) => {
+                for (slug, path) in roots {
+                    files.set_game_install_root(&slug, Some(PathBuf::from(path)));
+                }
+            }
+            Err(err) => tracing::warn!("ignoring invalid {GAME_INSTALL_ROOTS_SETTING}: {err}"),
+        }
+    }
+    restore_library_folders(db, files);
+}
+
+/// Contents of a settings export file.
+#[derive(Serialize, Deserialize)]
+pub struct SettingsExport {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: i64,
+    pub settings: HashMap<String, String>,
+    #[serde(default)]
+    pub launch_prefs: Vec<GameLaunchPref>,
+    #[serde(default)]
+    pub library_folders: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct SettingsImportReport {
+    pub settings: usize,
+    pub launch_prefs: usize,
+    pub library_folders: usize,
+    /// Settings and library folders left out, with why.
+    pub skipped: Vec<String>,
+    /// Some services only read their settings at startup.
+    pub restart_required: bool,
+}
+
+fn is_portable_setting(key: &str) -> bool {
+    PORTABLE_SETTINGS.contains(&key) || PORTABLE_PATH_SETTINGS.contains(&key)
+}
+
+/// Why an imported directory can't be used on this machine, if it can't.
+fn unusable_import_dir(path: &str) -> Option<String> {
+    let dir = match parse_install_root(path) {
+        Ok(dir) => dir,
+        Err(err) => return Some(err),
+    };
+    if !dir.is_dir() {
+        return Some(format!("{} does not exist", dir.display()));
+    }
+    None
+}
+
+fn parse_settings_export(raw: &str) -> Result<SettingsExport, String> {
+    let export: SettingsExport =
+        serde_json::from_str(raw).map_err(|err| format!("not a settings export: {err}"))?;
+    if export.format != SETTINGS_EXPORT_FORMAT {
+        return Err(format!("unknown export format {:?}", export.format));
+    }
+    if export.version > SETTINGS_EXPORT_VERSION {
+        return Err(format!(
+            "settings export version {} is newer than this launcher supports",
+            export.version
+        ));
+    }
+    Ok(export)
+}
+
+/// Writes settings, launch preferences and library folders to `dest` as
+/// JSON. Only `PORTABLE_SETTINGS` and `PORTABLE_PATH_SETTINGS` are written;
+/// the login never is.
+#[tauri::command]
+pub async fn export_settings(
+    dest: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    let dest = PathBuf::from(dest.trim());
+    if !dest.is_absolute() {
+        return Err("export path must be absolute".to_string());
+    }
+    let settings = state
+        .db
+        .list_settings()
+        .map_err(|err| err.to_string())?
+        .into_iter()
+        .filter(|(key, _)| is_portable_setting(key))
+        .collect();
+    let export = SettingsExport {
+        format: SETTINGS_EXPORT_FORMAT.to_string(),
+        version: SETTINGS_EXPORT_VERSION,
+        exported_at: Utc::now().timestamp(),
+        settings,
+        launch_prefs: state
+            .db
+            .list_launch_prefs()
+            .map_err(|err| err.to_string())?,
+        library_folders: state
+            .db
+            .list_library_folders()
+            .map_err(|err| err.to_string())?
+            .into_iter()
+            .map(|folder| folder.path)
+            .collect(),
+    };
+    let encoded = serde_json::to_vec_pretty(&export).map_err(|err| err.to_string())?;
+    state
+        .files
+        .write_atomic(&dest, &encoded)
+        .map_err(|err| err.to_string())?;
+    tracing::info!(
+        "exported {} settings to {}",
+        export.settings.len(),
+        dest.display()
+    );
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Applies a file written by `export_settings`. Imported values overwrite
+/// the current ones; nothing that isn't in the file is removed. Unknown
+/// settings and directories missing on this machine are skipped.
+#[tauri::command]
+pub async fn import_settings(
+    src: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<SettingsImportReport, String> {
+    let raw = fs::read_to_string(src.trim()).map_err(|err| err.to_string())?;
+    let export = parse_settings_export(&raw)?;
+
+    let mut report = SettingsImportReport {
+        settings: 0,
+        launch_prefs: 0,
+        library_folders: 0,
+        skipped: Vec::new(),
+        restart_required: true,
+    };
+    for (key, value) in &export.settings {
+        if !is_portable_setting(key) {
+            tracing::warn!("skipping non-portable setting {key} in import");
+            report
+                .skipped
+                .push(format!("{key}: not a portable setting"));
+            continue;
+        }
+        if PORTABLE_PATH_SETTINGS.contains(&key.as_str()) {
+            if let Some(reason) = unusable_import_dir(value) {
+                tracing::warn!("skipping imported {key}: {reason}");
+                report.skipped.push(format!("{key}: {reason}"));
+                continue;
+            }
+        }
+        state
+            .db
+            .set_setting(key, value)
+            .map_err(|err| err.to_string())?;
+        report.settings += 1;
+    }
+    for pref in &export.launch_prefs {
+        state
+            .db
+            .upsert_launch_pref(pref)
+            .map_err(|err| err.to_string())?;
+        report.launch_prefs += 1;
+    }
+    for folder in &export.library_folders {
+        if let Some(reason) = unusable_import_dir(folder) {
+            tracing::warn!("skipping imported library folder: {reason}");
+            report.skipped.push(format!("library folder: {reason}"));
+            continue;
+        }
+        state
+            .db
+            .add_library_folder(folder.trim())
+            .map_err(|err| err.to_string())?;
+        report.library_folders += 1;
+    }
+    restore_install_roots(&state.db, &state.files);
+    tracing::info!(
+        "imported {} settings, {} launch prefs, {} library folders",
+        report.settings,
+        report.launch_prefs,
+        report.library_folders
+    );
+    Ok(report)
 }
 
 #[tauri::command]
@@ -450,6 +2077,7 @@ pub async fn runtime_tuning_apply(
     consent: bool,
     profile: Option<String>,
     app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
 ) -> Result<RuntimeTuningApplyResult, String> {
     if !consent {
         return Err("runtime tuning requires explicit opt-in".to_string());
@@ -457,6 +2085,13 @@ pub async fn runtime_tuning_apply(
 
     let capabilities = collect_cpu_capabilities();
     let recommendation = recommendation_from_capabilities(&capabilities, true, profile.as_deref());
+    let tuning = state
+        .download_manager
+        .set_download_tuning_override(Some(DownloadTuning {
+            max_concurrent_chunks: recommendation.max_concurrent_chunks,
+            integrity_workers: recommendation.integrity_workers,
+        }))
+        .map_err(|err| err.to_string())?;
     let applied_at = Utc::now().to_rfc3339();
     let file_payload = RuntimeTuningStateFile {
         enabled: true,
@@ -466,6 +2101,8 @@ pub async fn runtime_tuning_apply(
         polling_fast_ms: recommendation.polling_fast_ms,
         polling_idle_ms: recommendation.polling_idle_ms,
         animation_level: recommendation.animation_level.clone(),
+        max_concurrent_chunks: tuning.max_concurrent_chunks,
+        integrity_workers: tuning.integrity_workers,
         fallback_used: recommendation.fallback_used,
         applied_at: applied_at.clone(),
     };
@@ -481,6 +2118,8 @@ pub async fn runtime_tuning_apply(
         polling_fast_ms: recommendation.polling_fast_ms,
         polling_idle_ms: recommendation.polling_idle_ms,
         animation_level: recommendation.animation_level,
+        max_concurrent_chunks: tuning.max_concurrent_chunks,
+        integrity_workers: tuning.integrity_workers,
         fallback_used: recommendation.fallback_used,
         settings_path: settings_path.to_string_lossy().to_string(),
         applied_at,
@@ -488,10 +2127,242 @@ pub async fn runtime_tuning_apply(
 }
 
 #[tauri::command]
-pub async fn runtime_tuning_rollback(app: tauri::AppHandle) -> Result<bool, String> {
+pub async fn runtime_tuning_rollback(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool, String> {
+    state
+        .download_manager
+        .set_download_tuning_override(None)
+        .map_err(|err| err.to_string())?;
     let settings_path = runtime_tuning_path(&app)?;
     if settings_path.exists() {
         fs::remove_file(settings_path).map_err(|err| err.to_string())?;
     }
     Ok(true)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::game_stores::GameStore;
+
+    #[test]
+    fn settings_export_skips_secrets_and_checks_format() {
+        assert!(is_portable_setting("data_cap"));
+        assert!(!is_portable_setting("refresh_token"));
+        assert!(!is_portable_setting("steam_api_secret"));
+        assert!(!is_portable_setting("library_update_report"));
+        assert!(!is_portable_setting("post_install_scripts_allowed"));
+        assert!(!is_portable_setting("some_future_setting"));
+
+        let valid = format!(
+            r#"{{"format":"{SETTINGS_EXPORT_FORMAT}","version":1,"exported_at":0,"settings":{{"data_cap":"{{}}"}}}}"#
+        );
+        let export = parse_settings_export(&valid).unwrap();
+        assert_eq!(export.settings.len(), 1);
+
+        // Files from before the login was dropped from exports still parse.
+        let with_login = valid.replace(
+            r#""exported_at":0"#,
+            r#""exported_at":0,"refresh_token":"t""#,
+        );
+        assert!(parse_settings_export(&with_login).is_ok());
+
+        assert!(parse_settings_export(
+            r#"{"format":"other","version":1,"exported_at":0,"settings":{}}"#
+        )
+        .is_err());
+        let future = valid.replace(r#""version":1"#, r#""version":99"#);
+        assert!(parse_settings_export(&future).is_err());
+    }
+
+    #[test]
+    fn imported_directories_must_exist_here() {
+        let dir = std::env::temp_dir().join(format!("otoshi-import-{}", uuid::Uuid::new_v4()));
+        assert!(unusable_import_dir(&dir.to_string_lossy()).is_some());
+        fs::create_dir_all(&dir).unwrap();
+        assert!(unusable_import_dir(&dir.to_string_lossy()).is_none());
+        assert!(unusable_import_dir("relative/games").is_some());
+        assert!(unusable_import_dir("  ").is_some());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn installed_games_list_each_folder_once_and_flag_managed_ones() {
+        let root = std::env::temp_dir().join(format!("otoshi-installed-{}", uuid::Uuid::new_v4()));
+        for (slug, game_id) in [("alpha", "g-alpha"), ("beta", "g-beta")] {
+            fs::create_dir_all(root.join(slug)).unwrap();
+            fs::write(
+                root.join(slug).join("manifest.json"),
+                format!(r#"{{"game_id":"{game_id}"}}"#),
+            )
+            .unwrap();
+        }
+        let mut managed = HashMap::new();
+        for (slug, game_id, title) in [("beta", "g-beta", "Beta"), ("gone", "g-gone", "Gone")] {
+            let path = root.join(slug);
+            managed.insert(
+                game_stores::path_key(&path),
+                (game_id.to_string(), title.to_string(), path),
+            );
+        }
+        let store_games = vec![
+            StoreGame {
+                store: GameStore::Steam,
+                id: "400".to_string(),
+                name: "Beta Steam".to_string(),
+                install_path: root.join("beta/"),
+            },
+            StoreGame {
+                store: GameStore::Epic,
+                id: "zeta-id".to_string(),
+                name: " ".to_string(),
+                install_path: root.join("zeta"),
+            },
+        ];
+
+        let games = collect_installed_games(store_games, &[root.clone()], &managed);
+
+        let summary: Vec<(&str, &str, Option<&str>, bool)> = games
+            .iter()
+            .map(|game| {
+                (
+                    game.name.as_str(),
+                    game.store.as_str(),
+                    game.id.as_deref(),
+                    game.managed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("alpha", "otoshi", Some("g-alpha"), false),
+                ("Beta Steam", "steam", Some("400"), true),
+                ("zeta", "epic", Some("zeta-id"), false),
+            ]
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn measures_and_removes_benchmark_file() {
+        let dir = std::env::temp_dir().join(format!("otoshi-bench-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let size = BENCHMARK_BLOCK_BYTES as u64 + 123;
+        let report = measure_sequential_write(&dir, size).unwrap();
+        assert_eq!(report.bytes_written, size);
+        assert!(report.write_mbps > 0.0);
+        assert!(!Path::new(&report.path).exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        // A failed run leaves nothing behind either.
+        let missing = dir.join("missing");
+        assert!(measure_sequential_write(&missing, 1024).is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn bandwidth_usage_groups_days_and_games() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 14).unwrap();
+        let (from, to) = bandwidth_range_bounds("month", today).unwrap();
+        assert_eq!(from, NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+        assert_eq!(to, today);
+        assert!(bandwidth_range_bounds("fortnight", today).is_err());
+
+        let record = |game_id: &str, date: &str, network_bytes, peer_bytes| BandwidthUsageRecord {
+            game_id: game_id.to_string(),
+            date: date.to_string(),
+            bytes: BandwidthUsageTotals {
+                network_bytes,
+                peer_bytes,
+                depotcache_bytes: 0,
+            },
+        };
+        let report = summarize_bandwidth_usage(
+            "month",
+            from,
+            to,
+            vec![
+                record("a", "2026-03-02", 100, 10),
+                record("b", "2026-03-02", 300, 0),
+                record("a", "2026-03-05", 50, 0),
+            ],
+        );
+
+        assert_eq!(report.total.network_bytes, 450);
+        assert_eq!(report.total.peer_bytes, 10);
+        assert_eq!(report.days.len(), 2);
+        assert_eq!(report.days[0].bytes.network_bytes, 400);
+        assert_eq!(report.games[0].game_id, "b");
+        assert_eq!(report.games[1].bytes.network_bytes, 150);
+    }
+
+    #[test]
+    fn bulk_apply_validates_each_field_separately() {
+        let root = std::env::temp_dir().join("otoshi-games");
+        let patch = SettingsPatch {
+            download_limit_mbps: Some(-1.0),
+            download_method: Some("aria2c".to_string()),
+            download_schedule: Some(Some(DownloadSchedule {
+                start_hour: 22,
+                end_hour: 22,
+            })),
+            install_root: Some(root.to_string_lossy().to_string()),
+            peer_source_policy: Some("nearest".to_string()),
+            peer_fanout: Some(3),
+            write_strategy: Some("auto".to_string()),
+            storage: None,
+            telemetry_enabled: Some(false),
+            workshop_storage_dir: Some("relative/mods".to_string()),
+            overlay_hotkey: None,
+            locale: Some("fr".to_string()),
+        };
+        let (changes, failures) = validate_settings_patch(&patch);
+
+        assert_eq!(
+            changes,
+            vec![
+                SettingChange::DownloadMethod("max_speed".to_string()),
+                SettingChange::InstallRoot(root),
+                SettingChange::WriteStrategy(None),
+                SettingChange::Telemetry(false),
+            ]
+        );
+        let failed: Vec<&str> = failures.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(
+            failed,
+            vec![
+                "download_limit_mbps",
+                "download_schedule",
+                "peer_source_policy",
+                "workshop_storage_dir",
+                "locale",
+            ]
+        );
+
+        let patch: SettingsPatch = serde_json::from_str(
+            r#"{"download_schedule":null,"peer_fanout":2,"workshop_storage_dir":"  ","locale":"vi_VN"}"#,
+        )
+        .unwrap();
+        let (changes, failures) = validate_settings_patch(&patch);
+        assert!(failures.is_empty());
+        assert_eq!(
+            changes,
+            vec![
+                SettingChange::DownloadSchedule(None),
+                SettingChange::PeerSource {
+                    policy: None,
+                    fanout: Some(2),
+                },
+                SettingChange::WorkshopStorageDir(None),
+                SettingChange::Locale("vi"),
+            ]
+        );
+        let (changes, _) = validate_settings_patch(&serde_json::from_str("{}").unwrap());
+        assert!(changes.is_empty());
+    }
+}
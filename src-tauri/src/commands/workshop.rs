@@ -1,13 +1,17 @@
 use std::collections::HashSet;
-use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use reqwest::Method;
 use serde::Serialize;
 use tauri::State;
 
-use crate::services::workshop_service::{WorkshopItem, WorkshopSubscription, WorkshopVersion};
+use crate::services::workshop_service::{
+    self, WorkshopConflict, WorkshopItem, WorkshopService, WorkshopSubscription,
+    WorkshopUpdateResult, WorkshopVerifyReport, WorkshopVersion,
+};
+use crate::utils::game_stores;
 use crate::AppState;
 
 #[derive(Clone, Serialize, Debug)]
@@ -26,89 +30,7 @@ pub struct WorkshopSyncResult {
     pub items_total: usize,
     pub items_synced: usize,
     pub errors: Vec<String>,
-}
-
-#[cfg(target_os = "windows")]
-fn default_steam_roots() -> Vec<PathBuf> {
-    let mut roots = vec![
-        PathBuf::from("C:\\Program Files (x86)\\Steam"),
-        PathBuf::from("C:\\Program Files\\Steam"),
-    ];
-    if let Ok(p) = env::var("ProgramFiles(x86)") {
-        roots.push(PathBuf::from(p).join("Steam"));
-    }
-    if let Ok(p) = env::var("ProgramFiles") {
-        roots.push(PathBuf::from(p).join("Steam"));
-    }
-    roots
-}
-
-fn home_dir_from_env() -> Option<PathBuf> {
-    env::var("HOME").ok().map(PathBuf::from)
-}
-
-#[cfg(target_os = "macos")]
-fn default_steam_roots() -> Vec<PathBuf> {
-    let mut roots = Vec::new();
-    if let Some(home) = home_dir_from_env() {
-        roots.push(
-            home.join("Library")
-                .join("Application Support")
-                .join("Steam"),
-        );
-    }
-    roots
-}
-
-#[cfg(target_os = "linux")]
-fn default_steam_roots() -> Vec<PathBuf> {
-    let mut roots = Vec::new();
-    if let Some(home) = home_dir_from_env() {
-        roots.push(home.join(".steam").join("steam"));
-        roots.push(home.join(".local").join("share").join("Steam"));
-    }
-    roots
-}
-
-#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
-fn default_steam_roots() -> Vec<PathBuf> {
-    Vec::new()
-}
-
-fn parse_library_folders(content: &str) -> Vec<PathBuf> {
-    let mut libraries = Vec::new();
-    for line in content.lines() {
-        if line.contains("\"path\"") {
-            if let Some(path) = line.split('"').nth(3) {
-                let normalized = path.replace("\\\\", "\\");
-                libraries.push(PathBuf::from(normalized));
-            }
-        }
-    }
-    libraries
-}
-
-fn find_steam_libraries() -> Vec<PathBuf> {
-    let mut libs = Vec::new();
-    let mut seen = HashSet::new();
-
-    for root in default_steam_roots() {
-        let root_str = root.to_string_lossy().to_string();
-        if seen.insert(root_str.to_lowercase()) {
-            libs.push(root.clone());
-        }
-        let library_file = root.join("steamapps").join("libraryfolders.vdf");
-        if let Ok(content) = fs::read_to_string(&library_file) {
-            for lib in parse_library_folders(&content) {
-                let lib_str = lib.to_string_lossy().to_string();
-                if seen.insert(lib_str.to_lowercase()) {
-                    libs.push(lib);
-                }
-            }
-        }
-    }
-
-    libs
+    pub conflicts: Vec<WorkshopConflict>,
 }
 
 fn collect_workshop_installs(app_ids: &[String]) -> Vec<LocalWorkshopInstall> {
@@ -116,7 +38,7 @@ fn collect_workshop_installs(app_ids: &[String]) -> Vec<LocalWorkshopInstall> {
         return Vec::new();
     }
 
-    let libraries = find_steam_libraries();
+    let libraries = game_stores::steam_libraries();
     if libraries.is_empty() {
         return Vec::new();
     }
@@ -184,28 +106,6 @@ fn find_mod_dir(game_path: &PathBuf) -> PathBuf {
     fallback
 }
 
-fn copy_dir_recursive(src: &PathBuf, dest: &PathBuf) -> std::io::Result<()> {
-    if dest.exists() {
-        fs::remove_dir_all(dest)?;
-    }
-    fs::create_dir_all(dest)?;
-
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let path = entry.path();
-        let target = dest.join(entry.file_name());
-        if path.is_dir() {
-            copy_dir_recursive(&path, &target)?;
-        } else {
-            if let Some(parent) = target.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            fs::copy(&path, &target)?;
-        }
-    }
-    Ok(())
-}
-
 #[tauri::command]
 pub async fn list_workshop_items(
     game_id: Option<String>,
@@ -231,6 +131,45 @@ pub async fn list_workshop_versions(
         .map_err(|err| err.to_string())
 }
 
+/// Brings an installed item up to its newest version, patching in place when
+/// the backend offers a delta from `installed_version`.
+#[tauri::command]
+pub async fn update_workshop_item(
+    item_id: String,
+    install_dir: String,
+    installed_version: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<WorkshopUpdateResult, String> {
+    let versions = state
+        .workshop
+        .list_versions(&item_id)
+        .await
+        .map_err(|err| err.to_string())?;
+    let latest = versions
+        .into_iter()
+        .max_by(|a, b| a.created_at.cmp(&b.created_at))
+        .ok_or_else(|| format!("workshop item {} has no versions", item_id))?;
+    if installed_version.as_deref() == Some(latest.version.as_str()) {
+        return Ok(WorkshopUpdateResult {
+            item_id,
+            version: latest.version,
+            mode: "current".to_string(),
+            patched_files: 0,
+            fallback_reason: None,
+        });
+    }
+    state
+        .workshop
+        .update_item(
+            &item_id,
+            installed_version.as_deref(),
+            &latest,
+            &PathBuf::from(install_dir),
+        )
+        .await
+        .map_err(|err| err.to_string())
+}
+
 #[tauri::command]
 pub async fn list_workshop_subscriptions(
     state: State<'_, Arc<AppState>>,
@@ -242,28 +181,71 @@ pub async fn list_workshop_subscriptions(
         .map_err(|err| err.to_string())
 }
 
+/// Queues a subscribe (`POST`) or unsubscribe (`DELETE`) made while the
+/// backend is unreachable; the latest one per item wins.
+fn queue_subscription_change(
+    state: &AppState,
+    item_id: &str,
+    method: Method,
+) -> Result<(), String> {
+    state
+        .connectivity
+        .enqueue(
+            &format!("workshop-subscription:{item_id}"),
+            method,
+            &format!("/workshop/items/{item_id}/subscribe"),
+            serde_json::json!({}),
+        )
+        .map_err(|err| err.to_string())
+}
+
+/// Subscribes to an item. Offline, the subscription is queued and a
+/// provisional one without an id is returned.
 #[tauri::command]
 pub async fn subscribe_workshop_item(
     item_id: String,
     state: State<'_, Arc<AppState>>,
 ) -> Result<WorkshopSubscription, String> {
-    state
-        .workshop
-        .subscribe(&item_id)
-        .await
-        .map_err(|err| err.to_string())
+    match state.workshop.subscribe(&item_id).await {
+        Ok(subscription) => {
+            state
+                .connectivity
+                .supersede(&format!("workshop-subscription:{item_id}"));
+            Ok(subscription)
+        }
+        Err(err) if !state.connectivity.is_online() => {
+            tracing::warn!("subscribe to {} deferred: {}", item_id, err);
+            queue_subscription_change(&state, &item_id, Method::POST)?;
+            Ok(WorkshopSubscription {
+                id: String::new(),
+                workshop_item_id: item_id,
+                subscribed_at: chrono::Utc::now().to_rfc3339(),
+                auto_update: true,
+                item: None,
+            })
+        }
+        Err(err) => Err(err.to_string()),
+    }
 }
 
+/// Unsubscribes from an item, queuing the change while offline.
 #[tauri::command]
 pub async fn unsubscribe_workshop_item(
     item_id: String,
     state: State<'_, Arc<AppState>>,
 ) -> Result<bool, String> {
-    state
-        .workshop
-        .unsubscribe(&item_id)
-        .await
-        .map_err(|err| err.to_string())?;
+    match state.workshop.unsubscribe(&item_id).await {
+        Ok(()) => {
+            state
+                .connectivity
+                .supersede(&format!("workshop-subscription:{item_id}"));
+        }
+        Err(err) if !state.connectivity.is_online() => {
+            tracing::warn!("unsubscribe from {} deferred: {}", item_id, err);
+            queue_subscription_change(&state, &item_id, Method::DELETE)?;
+        }
+        Err(err) => return Err(err.to_string()),
+    }
     Ok(true)
 }
 
@@ -289,42 +271,37 @@ pub async fn sync_workshop_to_game(
     item_ids: Option<Vec<String>>,
     state: State<'_, Arc<AppState>>,
 ) -> Result<WorkshopSyncResult, String> {
-    let install_info = state
-        .crack_manager
-        .check_game_installed(&app_id)
-        .await
-        .map_err(|err| err.to_string())?;
-
-    let install_path = install_info
-        .install_path
-        .ok_or_else(|| "Game is not installed on this machine.".to_string())?;
-
-    let game_path = PathBuf::from(&install_path);
-    let mod_dir = find_mod_dir(&game_path);
-
-    let app_ids = vec![app_id.clone()];
-    let local_items = tokio::task::spawn_blocking(move || collect_workshop_installs(&app_ids))
-        .await
-        .map_err(|err| err.to_string())?;
-
-    let target_ids = item_ids.unwrap_or_default();
-    let filter_set: HashSet<String> = target_ids.into_iter().collect();
+    let mod_dir = game_mod_dir(&app_id, &state).await?;
+    let local_items = select_local_items(&app_id, item_ids).await?;
+    let conflicts = match find_conflicts(&local_items, &mod_dir).await {
+        Ok(conflicts) => conflicts,
+        Err(err) => {
+            tracing::warn!("workshop conflict check failed for {}: {}", app_id, err);
+            Vec::new()
+        }
+    };
+    for conflict in &conflicts {
+        tracing::warn!(
+            "workshop items {} and {} share {} file(s)",
+            conflict.first_item,
+            conflict.second_item,
+            conflict.paths.len()
+        );
+    }
+    let storage_root = state.workshop.storage_dir();
 
     let mut items_total = 0usize;
     let mut items_synced = 0usize;
     let mut errors = Vec::new();
 
     for item in local_items {
-        if item.app_id != app_id {
-            continue;
-        }
-        if !filter_set.is_empty() && !filter_set.contains(&item.item_id) {
-            continue;
-        }
         items_total += 1;
         let src = PathBuf::from(&item.path);
         let dest = mod_dir.join(&item.item_id);
-        match copy_dir_recursive(&src, &dest) {
+        match state
+            .workshop
+            .sync_item(storage_root.as_deref(), &app_id, &item.item_id, &src, &dest)
+        {
             Ok(_) => items_synced += 1,
             Err(err) => errors.push(format!("{}: {}", item.item_id, err)),
         }
@@ -336,5 +313,136 @@ pub async fn sync_workshop_to_game(
         items_total,
         items_synced,
         errors,
+        conflicts,
     })
 }
+
+/// Locally installed items for `app_id`. When `item_ids` is given only those
+/// are returned, in that order, so the list doubles as a priority order.
+async fn select_local_items(
+    app_id: &str,
+    item_ids: Option<Vec<String>>,
+) -> Result<Vec<LocalWorkshopInstall>, String> {
+    let app_ids = vec![app_id.to_string()];
+    let mut local_items = tokio::task::spawn_blocking(move || collect_workshop_installs(&app_ids))
+        .await
+        .map_err(|err| err.to_string())?;
+    local_items.retain(|item| item.app_id == app_id);
+
+    let target_ids = item_ids.unwrap_or_default();
+    if target_ids.is_empty() {
+        return Ok(local_items);
+    }
+    let filter_set: HashSet<&String> = target_ids.iter().collect();
+    local_items.retain(|item| filter_set.contains(&item.item_id));
+    local_items.sort_by_key(|item| target_ids.iter().position(|id| *id == item.item_id));
+    Ok(local_items)
+}
+
+/// The folder synced items are placed in for an installed game.
+async fn game_mod_dir(app_id: &str, state: &AppState) -> Result<PathBuf, String> {
+    let install_info = state
+        .crack_manager
+        .check_game_installed(app_id)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let install_path = install_info
+        .install_path
+        .ok_or_else(|| "Game is not installed on this machine.".to_string())?;
+
+    Ok(find_mod_dir(&PathBuf::from(&install_path)))
+}
+
+async fn find_conflicts(
+    items: &[LocalWorkshopInstall],
+    mod_dir: &Path,
+) -> Result<Vec<WorkshopConflict>, String> {
+    let dirs = items
+        .iter()
+        .map(|item| (item.item_id.clone(), PathBuf::from(&item.path)))
+        .collect::<Vec<_>>();
+    let mod_dir = mod_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || WorkshopService::detect_conflicts(&dirs, &mod_dir))
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Lists items that ship the same files as each other or as folders already in
+/// the game's mod dir, so the UI can ask for a priority order before syncing.
+/// Pass `item_ids` in the intended order.
+#[tauri::command]
+pub async fn detect_workshop_conflicts(
+    app_id: String,
+    item_ids: Option<Vec<String>>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<WorkshopConflict>, String> {
+    let mod_dir = game_mod_dir(&app_id, &state).await?;
+    let local_items = select_local_items(&app_id, item_ids).await?;
+    find_conflicts(&local_items, &mod_dir).await
+}
+
+/// Removes synced items from the game directory. Only items placed through
+/// the workshop storage dir are tracked; their stored copy is kept unless
+/// `delete_content` is set.
+#[tauri::command]
+pub async fn remove_workshop_from_game(
+    app_id: String,
+    item_ids: Vec<String>,
+    delete_content: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<usize, String> {
+    let storage_root = state
+        .workshop
+        .storage_dir()
+        .ok_or_else(|| "Workshop storage dir is not configured.".to_string())?;
+    let delete_content = delete_content.unwrap_or(false);
+    let mut removed = 0usize;
+    for item_id in item_ids {
+        if workshop_service::remove_placement(&storage_root, &app_id, &item_id, delete_content)
+            .map_err(|err| err.to_string())?
+        {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Sets where synced workshop content is stored, moving items already placed
+/// under the previous folder. An empty path goes back to copying items
+/// straight into the game directory.
+#[tauri::command]
+pub async fn set_workshop_storage_dir(
+    path: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Option<String>, String> {
+    let dir = path
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from);
+    let workshop = state.workshop.clone();
+    tokio::task::spawn_blocking(move || workshop.set_storage_dir(dir))
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())?;
+    Ok(state
+        .workshop
+        .storage_dir()
+        .map(|dir| dir.to_string_lossy().to_string()))
+}
+
+/// Checks a synced item in the game's mod directory against the sizes and
+/// hashes recorded when it was synced.
+#[tauri::command]
+pub async fn verify_workshop_item(
+    item_id: String,
+    game_dir: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<WorkshopVerifyReport, String> {
+    let item_dir = find_mod_dir(&PathBuf::from(game_dir)).join(&item_id);
+    let workshop = state.workshop.clone();
+    tokio::task::spawn_blocking(move || workshop.verify_item(&item_id, &item_dir))
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())
+}
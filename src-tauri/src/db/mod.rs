@@ -1,7 +1,10 @@
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use rusqlite::Connection;
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use serde::Serialize;
 use tauri::AppHandle;
 
 use crate::errors::{LauncherError, Result};
@@ -9,33 +12,70 @@ use crate::utils::paths::{resolve_cache_dir, resolve_data_dir};
 
 pub mod queries;
 
+const DEFAULT_POOL_SIZE: u32 = 8;
+const POOL_CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tables a restored database must contain before it is swapped in.
+const REQUIRED_TABLES: [&str; 5] = [
+    "settings",
+    "games",
+    "downloads",
+    "download_states",
+    "download_chunks",
+];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VacuumReport {
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+}
+
+/// Rows removed by `prune_download_states`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DownloadPruneReport {
+    pub states: usize,
+    pub chunks: usize,
+    pub downloads: usize,
+}
+
+pub type DbConnection = PooledConnection<SqliteConnectionManager>;
+
+/// Pool of SQLite connections in WAL mode, so readers don't queue behind
+/// download progress writes. Writers still serialize inside SQLite and wait
+/// on `busy_timeout` instead of failing.
 #[derive(Clone)]
 pub struct Database {
-    conn: Arc<Mutex<Connection>>,
+    pool: Pool<SqliteConnectionManager>,
     path: PathBuf,
 }
 
 impl Database {
     pub fn new(path: PathBuf) -> Result<Self> {
-        let conn = Connection::open(&path)?;
-        conn.execute_batch(
-            "PRAGMA journal_mode = WAL;
-             PRAGMA synchronous = NORMAL;
-             PRAGMA cache_size = 100000;
-             PRAGMA temp_store = MEMORY;",
-        )?;
+        let pool_size = std::env::var("LAUNCHER_DB_POOL_SIZE")
+            .ok()
+            .and_then(|value| value.trim().parse::<u32>().ok())
+            .unwrap_or(DEFAULT_POOL_SIZE)
+            .clamp(1, 32);
+        let manager = SqliteConnectionManager::file(&path).with_init(|conn| {
+            conn.execute_batch(
+                "PRAGMA journal_mode = WAL;
+                 PRAGMA synchronous = NORMAL;
+                 PRAGMA busy_timeout = 5000;
+                 PRAGMA cache_size = 100000;
+                 PRAGMA temp_store = MEMORY;",
+            )
+        });
+        let pool = Pool::builder()
+            .max_size(pool_size)
+            .connection_timeout(POOL_CONNECTION_TIMEOUT)
+            .build(manager)?;
 
-        Ok(Self {
-            conn: Arc::new(Mutex::new(conn)),
-            path,
-        })
+        Ok(Self { pool, path })
     }
 
+    /// Runs once from `init`, before any other connection is handed out.
     pub fn run_migrations(&self) -> Result<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|_| LauncherError::Config("database lock poisoned".to_string()))?;
+        let conn = self.connection()?;
 
         conn.execute_batch(include_str!("../../migrations/001_initial.sql"))?;
         conn.execute_batch(include_str!("../../migrations/002_downloads.sql"))?;
@@ -43,19 +83,151 @@ impl Database {
         conn.execute_batch(include_str!("../../migrations/004_download_runtime.sql"))?;
         conn.execute_batch(include_str!("../../migrations/005_download_v2.sql"))?;
         conn.execute_batch(include_str!("../../migrations/006_self_heal_v2.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/007_telemetry_queue.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/008_workshop_files.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/009_cloud_save_snapshots.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/010_achievement_queue.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/011_game_updates.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/012_discovery_queue.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/013_inventory_cache.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/014_bandwidth_usage.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/015_library_folders.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/016_offline_queue.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/017_library_cache.sql"))?;
         ensure_download_runtime_columns(&conn)?;
+        ensure_column(&conn, "download_states", "engine", "TEXT")?;
+        ensure_column(&conn, "download_states", "method", "TEXT")?;
+        ensure_column(&conn, "download_states", "preflight_checkpoint", "TEXT")?;
+        ensure_column(
+            &conn,
+            "game_launch_prefs",
+            "verify_before_launch",
+            "INTEGER",
+        )?;
+        ensure_column(
+            &conn,
+            "integrity_events_v2",
+            "event_kind",
+            "TEXT NOT NULL DEFAULT 'scan'",
+        )?;
+        ensure_column(
+            &conn,
+            "game_updates",
+            "changed_files",
+            "INTEGER NOT NULL DEFAULT 0",
+        )?;
+        ensure_column(
+            &conn,
+            "game_updates",
+            "delta_bytes",
+            "INTEGER NOT NULL DEFAULT 0",
+        )?;
         Ok(())
     }
 
-    pub fn connection(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
-        self.conn
-            .lock()
-            .map_err(|_| LauncherError::Config("database lock poisoned".to_string()))
+    pub fn connection(&self) -> Result<DbConnection> {
+        Ok(self.pool.get()?)
     }
 
     pub fn path(&self) -> &PathBuf {
         &self.path
     }
+
+    /// Snapshots the live database to `dest` with SQLite's online backup API,
+    /// so writers only block for the duration of each page step.
+    pub fn backup_to(&self, dest: &Path) -> Result<()> {
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = self.connection()?;
+        conn.backup(DatabaseName::Main, dest, None)?;
+        Ok(())
+    }
+
+    /// Replaces the live database with the contents of `src` after checking
+    /// it is intact and carries the launcher schema. The current database is
+    /// kept next to it as `launcher.db.pre-restore`.
+    pub fn restore_from(&self, src: &Path) -> Result<()> {
+        validate_backup(src)?;
+        let safety_copy = self.path.with_extension("db.pre-restore");
+        self.backup_to(&safety_copy)?;
+        {
+            // The backup step holds SQLite's write lock on the live file, so
+            // other pooled connections wait on busy_timeout until it is done.
+            let mut conn = self.connection()?;
+            conn.restore(
+                DatabaseName::Main,
+                src,
+                None::<fn(rusqlite::backup::Progress)>,
+            )?;
+        }
+        self.run_migrations()
+    }
+
+    /// Checkpoints the WAL and rebuilds the file to reclaim free pages.
+    pub fn vacuum(&self) -> Result<VacuumReport> {
+        let before_bytes = self.disk_usage();
+        {
+            let conn = self.connection()?;
+            conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE); VACUUM;")?;
+            conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+        }
+        Ok(VacuumReport {
+            before_bytes,
+            after_bytes: self.disk_usage(),
+        })
+    }
+
+    fn disk_usage(&self) -> u64 {
+        ["", "-wal", "-shm"]
+            .iter()
+            .filter_map(|suffix| {
+                let mut path = self.path.clone().into_os_string();
+                path.push(suffix);
+                std::fs::metadata(PathBuf::from(path)).ok()
+            })
+            .map(|meta| meta.len())
+            .sum()
+    }
+}
+
+fn validate_backup(src: &Path) -> Result<()> {
+    if !src.is_file() {
+        return Err(LauncherError::NotFound(format!(
+            "database backup not found: {}",
+            src.display()
+        )));
+    }
+    let conn = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if integrity != "ok" {
+        return Err(LauncherError::Integrity(format!(
+            "database backup failed integrity check: {integrity}"
+        )));
+    }
+    for table in REQUIRED_TABLES {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [table],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(LauncherError::Config(format!(
+                "database backup is missing table {table}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// A migrated database in a fresh temp folder.
+#[cfg(test)]
+pub fn open_temp() -> Database {
+    let dir = std::env::temp_dir().join(format!("otoshi-db-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = Database::new(dir.join("launcher.db")).unwrap();
+    db.run_migrations().unwrap();
+    db
 }
 
 pub fn init(app: &AppHandle) -> Result<Database> {
@@ -1,15 +1,19 @@
 use rusqlite::{params, OptionalExtension};
 
-use crate::db::Database;
+use crate::db::{Database, DownloadPruneReport};
 use crate::errors::Result;
 use crate::models::{
     DownloadChunk, DownloadState, GameLaunchPref, LocalDownload, LocalGame, PlaySessionLocal,
+    BandwidthUsageRecord, BandwidthUsageTotals, CloudSaveSnapshot, FileIndexEntry, Game,
+    GameUpdateStatus, LibraryEntry, LibraryFolder, PendingAchievement, QueuedRequest,
+    TelemetryEvent, WorkshopFileRecord,
 };
 
 pub trait SettingsQueries {
     fn set_setting(&self, key: &str, value: &str) -> Result<()>;
     fn get_setting(&self, key: &str) -> Result<Option<String>>;
     fn delete_setting(&self, key: &str) -> Result<()>;
+    fn list_settings(&self) -> Result<Vec<(String, String)>>;
 }
 
 pub trait GameQueries {
@@ -27,6 +31,7 @@ pub trait DownloadQueries {
 pub trait LaunchPrefQueries {
     fn upsert_launch_pref(&self, pref: &GameLaunchPref) -> Result<()>;
     fn get_launch_pref(&self, game_id: &str) -> Result<Option<GameLaunchPref>>;
+    fn list_launch_prefs(&self) -> Result<Vec<GameLaunchPref>>;
 }
 
 pub trait PlaySessionQueries {
@@ -36,10 +41,138 @@ pub trait PlaySessionQueries {
     fn mark_play_session_synced(&self, session_id: &str) -> Result<()>;
 }
 
+pub trait TelemetryQueueQueries {
+    fn enqueue_telemetry(&self, event: &TelemetryEvent, max_queued: usize) -> Result<()>;
+    fn list_telemetry(&self, limit: usize) -> Result<Vec<(i64, TelemetryEvent)>>;
+    fn delete_telemetry_through(&self, id: i64) -> Result<()>;
+    fn clear_telemetry(&self) -> Result<()>;
+}
+
+/// Writes held while the backend is unreachable. `key` names what a write
+/// is about; a newer write with the same key replaces the queued one.
+pub trait OfflineQueueQueries {
+    fn enqueue_offline_request(
+        &self,
+        key: &str,
+        method: &str,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<()>;
+    fn list_offline_requests(&self) -> Result<Vec<QueuedRequest>>;
+    fn count_offline_requests(&self) -> Result<usize>;
+    fn remove_offline_request(&self, id: i64) -> Result<()>;
+    /// Drops queued writes for `key`, e.g. once a newer one reached the
+    /// backend directly.
+    fn remove_offline_requests_for(&self, key: &str) -> Result<usize>;
+}
+
+/// The last library fetched from the backend, served while it's offline.
+pub trait LibraryCacheQueries {
+    fn replace_library_cache(&self, entries: &[LibraryEntry], fetched_at: i64) -> Result<()>;
+    fn get_cached_library(&self) -> Result<Vec<LibraryEntry>>;
+}
+
+pub trait WorkshopFileQueries {
+    fn replace_workshop_files(&self, item_id: &str, files: &[WorkshopFileRecord]) -> Result<()>;
+    fn list_workshop_files(&self, item_id: &str) -> Result<Vec<WorkshopFileRecord>>;
+}
+
+pub trait CloudSaveSnapshotQueries {
+    fn save_cloud_snapshot(&self, snapshot: &CloudSaveSnapshot) -> Result<()>;
+    fn get_cloud_snapshot(&self, game_id: &str) -> Result<Option<CloudSaveSnapshot>>;
+}
+
+pub trait AchievementQueueQueries {
+    fn enqueue_achievement(
+        &self,
+        game_id: &str,
+        achievement_key: &str,
+    ) -> Result<PendingAchievement>;
+    fn list_pending_achievements(&self) -> Result<Vec<PendingAchievement>>;
+    fn bump_achievement_attempts(&self, game_id: &str, achievement_key: &str) -> Result<()>;
+    fn remove_pending_achievement(&self, game_id: &str, achievement_key: &str) -> Result<()>;
+}
+
+pub trait GameUpdateQueries {
+    fn get_game_update(&self, game_id: &str) -> Result<Option<GameUpdateStatus>>;
+    fn upsert_game_update(&self, status: &GameUpdateStatus) -> Result<()>;
+    fn list_available_updates(&self) -> Result<Vec<GameUpdateStatus>>;
+    /// When the most recent check of any game ran, if one ever did.
+    fn last_update_check(&self) -> Result<Option<i64>>;
+}
+
+pub trait DiscoveryQueueQueries {
+    /// Stores one page of the queue starting at `offset`, dropping anything
+    /// cached at or after it.
+    fn save_discovery_page(&self, offset: usize, games: &[Game], fetched_at: i64) -> Result<()>;
+    /// Returns the cached queue and the time its oldest page was fetched.
+    fn get_cached_discovery_queue(&self) -> Result<(Vec<Game>, Option<i64>)>;
+}
+
+/// Inventory items and trades cached as JSON payloads, grouped by `kind`.
+pub trait InventoryCacheQueries {
+    fn replace_inventory_cache(&self, kind: &str, entries: &[(String, String)]) -> Result<()>;
+    fn list_inventory_cache(&self, kind: &str) -> Result<Vec<String>>;
+    fn get_inventory_cache_entry(&self, kind: &str, id: &str) -> Result<Option<String>>;
+    fn upsert_inventory_cache(&self, kind: &str, id: &str, payload: &str) -> Result<()>;
+    fn remove_inventory_cache(&self, kind: &str, id: &str) -> Result<()>;
+    fn clear_inventory_cache(&self) -> Result<()>;
+}
+
+pub trait BandwidthUsageQueries {
+    /// Adds `bytes` to the running totals of `game_id` on `date`.
+    fn add_bandwidth_usage(
+        &self,
+        game_id: &str,
+        date: &str,
+        bytes: &BandwidthUsageTotals,
+    ) -> Result<()>;
+    /// Rows with `from <= date <= to`, oldest first.
+    fn list_bandwidth_usage(&self, from: &str, to: &str) -> Result<Vec<BandwidthUsageRecord>>;
+    fn sum_network_bytes(&self, from: &str, to: &str) -> Result<u64>;
+}
+
+/// Verified files in `file_index_v2`, shared with the self-heal scanner.
+pub trait FileIndexQueries {
+    fn list_verified_file_index(
+        &self,
+        game_id: &str,
+        install_path: &str,
+    ) -> Result<Vec<FileIndexEntry>>;
+    fn upsert_verified_file_index(
+        &self,
+        game_id: &str,
+        install_path: &str,
+        entries: &[FileIndexEntry],
+    ) -> Result<()>;
+}
+
+pub trait LibraryFolderQueries {
+    fn list_library_folders(&self) -> Result<Vec<LibraryFolder>>;
+    fn add_library_folder(&self, path: &str) -> Result<()>;
+    fn remove_library_folder(&self, path: &str) -> Result<bool>;
+}
+
 pub trait DownloadStateQueries {
     fn save_download_state(&self, state: &DownloadState) -> Result<()>;
     fn get_download_state(&self, download_id: &str) -> Result<Option<DownloadState>>;
+    fn get_download_state_by_slug(&self, slug: &str) -> Result<Option<DownloadState>>;
+    fn list_completed_download_states(&self) -> Result<Vec<DownloadState>>;
+    fn list_download_states(&self) -> Result<Vec<DownloadState>>;
+    /// Deletes the given states with their chunks and download rows, plus
+    /// chunks left without a state and terminal download rows older than
+    /// `cutoff` that have no state.
+    fn prune_download_states(
+        &self,
+        download_ids: &[String],
+        cutoff: i64,
+    ) -> Result<DownloadPruneReport>;
     fn update_download_status(&self, download_id: &str, status: &str) -> Result<()>;
+    fn set_download_engine(&self, download_id: &str, engine: &str) -> Result<()>;
+    /// Last preflight scan of the download. Saving the state keeps it; the
+    /// scan's fingerprint tells whether it still applies.
+    fn get_preflight_checkpoint(&self, download_id: &str) -> Result<Option<String>>;
+    fn set_preflight_checkpoint(&self, download_id: &str, checkpoint: &str) -> Result<()>;
     fn clear_download_state(&self, download_id: &str) -> Result<()>;
     fn upsert_download_chunk(&self, chunk: &DownloadChunk) -> Result<()>;
     fn list_completed_chunks(&self, download_id: &str) -> Result<Vec<DownloadChunk>>;
@@ -73,6 +206,18 @@ impl SettingsQueries for Database {
         conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?;
         Ok(())
     }
+
+    fn list_settings(&self) -> Result<Vec<(String, String)>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key ASC")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut settings = Vec::new();
+        for item in rows {
+            settings.push(item?);
+        }
+        Ok(settings)
+    }
 }
 
 impl GameQueries for Database {
@@ -218,12 +363,14 @@ impl LaunchPrefQueries for Database {
     fn upsert_launch_pref(&self, pref: &GameLaunchPref) -> Result<()> {
         let conn = self.connection()?;
         conn.execute(
-            "INSERT OR REPLACE INTO game_launch_prefs (game_id, require_admin, ask_every_time, updated_at)
-             VALUES (?1, ?2, ?3, ?4)",
+            "INSERT OR REPLACE INTO game_launch_prefs
+             (game_id, require_admin, ask_every_time, verify_before_launch, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
             params![
                 pref.game_id,
                 if pref.require_admin { 1 } else { 0 },
                 if pref.ask_every_time { 1 } else { 0 },
+                pref.verify_before_launch.map(i64::from),
                 pref.updated_at,
             ],
         )?;
@@ -234,7 +381,7 @@ impl LaunchPrefQueries for Database {
         let conn = self.connection()?;
         let pref = conn
             .query_row(
-                "SELECT game_id, require_admin, ask_every_time, updated_at
+                "SELECT game_id, require_admin, ask_every_time, verify_before_launch, updated_at
                  FROM game_launch_prefs WHERE game_id = ?1",
                 params![game_id],
                 |row| {
@@ -242,13 +389,37 @@ impl LaunchPrefQueries for Database {
                         game_id: row.get(0)?,
                         require_admin: row.get::<_, i64>(1)? > 0,
                         ask_every_time: row.get::<_, i64>(2)? > 0,
-                        updated_at: row.get(3)?,
+                        verify_before_launch: row.get::<_, Option<i64>>(3)?.map(|value| value > 0),
+                        updated_at: row.get(4)?,
                     })
                 },
             )
             .optional()?;
         Ok(pref)
     }
+
+    fn list_launch_prefs(&self) -> Result<Vec<GameLaunchPref>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT game_id, require_admin, ask_every_time, verify_before_launch, updated_at
+             FROM game_launch_prefs ORDER BY game_id ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(GameLaunchPref {
+                game_id: row.get(0)?,
+                require_admin: row.get::<_, i64>(1)? > 0,
+                ask_every_time: row.get::<_, i64>(2)? > 0,
+                verify_before_launch: row.get::<_, Option<i64>>(3)?.map(|value| value > 0),
+                updated_at: row.get(4)?,
+            })
+        })?;
+
+        let mut prefs = Vec::new();
+        for item in rows {
+            prefs.push(item?);
+        }
+        Ok(prefs)
+    }
 }
 
 impl PlaySessionQueries for Database {
@@ -341,8 +512,18 @@ impl DownloadStateQueries for Database {
     fn save_download_state(&self, state: &DownloadState) -> Result<()> {
         let conn = self.connection()?;
         conn.execute(
-            "INSERT OR REPLACE INTO download_states (id, game_id, slug, status, install_dir, manifest_json, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO download_states
+                (id, game_id, slug, status, install_dir, manifest_json, updated_at, engine, method)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(id) DO UPDATE SET
+                game_id = excluded.game_id,
+                slug = excluded.slug,
+                status = excluded.status,
+                install_dir = excluded.install_dir,
+                manifest_json = excluded.manifest_json,
+                updated_at = excluded.updated_at,
+                engine = excluded.engine,
+                method = excluded.method",
             params![
                 state.id,
                 state.game_id,
@@ -351,6 +532,8 @@ impl DownloadStateQueries for Database {
                 state.install_dir,
                 state.manifest_json,
                 state.updated_at,
+                state.engine,
+                state.method,
             ],
         )?;
         Ok(())
@@ -360,7 +543,8 @@ impl DownloadStateQueries for Database {
         let conn = self.connection()?;
         let state = conn
             .query_row(
-                "SELECT id, game_id, slug, status, install_dir, manifest_json, updated_at
+                "SELECT id, game_id, slug, status, install_dir, manifest_json, updated_at,
+                        engine, method
                  FROM download_states WHERE id = ?1",
                 params![download_id],
                 |row| {
@@ -372,6 +556,35 @@ impl DownloadStateQueries for Database {
                         install_dir: row.get(4)?,
                         manifest_json: row.get(5)?,
                         updated_at: row.get(6)?,
+                        engine: row.get(7)?,
+                        method: row.get(8)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(state)
+    }
+
+    fn get_download_state_by_slug(&self, slug: &str) -> Result<Option<DownloadState>> {
+        let conn = self.connection()?;
+        let state = conn
+            .query_row(
+                "SELECT id, game_id, slug, status, install_dir, manifest_json, updated_at,
+                        engine, method
+                 FROM download_states WHERE slug = ?1
+                 ORDER BY updated_at DESC LIMIT 1",
+                params![slug],
+                |row| {
+                    Ok(DownloadState {
+                        id: row.get(0)?,
+                        game_id: row.get(1)?,
+                        slug: row.get(2)?,
+                        status: row.get(3)?,
+                        install_dir: row.get(4)?,
+                        manifest_json: row.get(5)?,
+                        updated_at: row.get(6)?,
+                        engine: row.get(7)?,
+                        method: row.get(8)?,
                     })
                 },
             )
@@ -379,6 +592,36 @@ impl DownloadStateQueries for Database {
         Ok(state)
     }
 
+    /// Latest completed state per slug, i.e. one row per installed game.
+    fn list_completed_download_states(&self) -> Result<Vec<DownloadState>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, game_id, slug, status, install_dir, manifest_json, MAX(updated_at),
+                    engine, method
+             FROM download_states WHERE status = 'completed'
+             GROUP BY slug",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(DownloadState {
+                id: row.get(0)?,
+                game_id: row.get(1)?,
+                slug: row.get(2)?,
+                status: row.get(3)?,
+                install_dir: row.get(4)?,
+                manifest_json: row.get(5)?,
+                updated_at: row.get(6)?,
+                engine: row.get(7)?,
+                method: row.get(8)?,
+            })
+        })?;
+
+        let mut states = Vec::new();
+        for item in rows {
+            states.push(item?);
+        }
+        Ok(states)
+    }
+
     fn update_download_status(&self, download_id: &str, status: &str) -> Result<()> {
         let conn = self.connection()?;
         conn.execute(
@@ -388,6 +631,100 @@ impl DownloadStateQueries for Database {
         Ok(())
     }
 
+    fn set_download_engine(&self, download_id: &str, engine: &str) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE download_states SET engine = ?1 WHERE id = ?2",
+            params![engine, download_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_preflight_checkpoint(&self, download_id: &str) -> Result<Option<String>> {
+        let conn = self.connection()?;
+        let checkpoint = conn
+            .query_row(
+                "SELECT preflight_checkpoint FROM download_states WHERE id = ?1",
+                params![download_id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?;
+        Ok(checkpoint.flatten())
+    }
+
+    fn set_preflight_checkpoint(&self, download_id: &str, checkpoint: &str) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE download_states SET preflight_checkpoint = ?1 WHERE id = ?2",
+            params![checkpoint, download_id],
+        )?;
+        Ok(())
+    }
+
+    fn list_download_states(&self) -> Result<Vec<DownloadState>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, game_id, slug, status, install_dir, manifest_json, updated_at,
+                    engine, method
+             FROM download_states ORDER BY updated_at ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(DownloadState {
+                id: row.get(0)?,
+                game_id: row.get(1)?,
+                slug: row.get(2)?,
+                status: row.get(3)?,
+                install_dir: row.get(4)?,
+                manifest_json: row.get(5)?,
+                updated_at: row.get(6)?,
+                engine: row.get(7)?,
+                method: row.get(8)?,
+            })
+        })?;
+
+        let mut states = Vec::new();
+        for item in rows {
+            states.push(item?);
+        }
+        Ok(states)
+    }
+
+    fn prune_download_states(
+        &self,
+        download_ids: &[String],
+        cutoff: i64,
+    ) -> Result<DownloadPruneReport> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        let mut report = DownloadPruneReport::default();
+        for download_id in download_ids {
+            report.chunks += tx.execute(
+                "DELETE FROM download_chunks WHERE download_id = ?1",
+                params![download_id],
+            )?;
+            report.states += tx.execute(
+                "DELETE FROM download_states WHERE id = ?1",
+                params![download_id],
+            )?;
+            report.downloads +=
+                tx.execute("DELETE FROM downloads WHERE id = ?1", params![download_id])?;
+        }
+        report.chunks += tx.execute(
+            "DELETE FROM download_chunks
+             WHERE download_id NOT IN (SELECT id FROM download_states)",
+            [],
+        )?;
+        report.downloads += tx.execute(
+            "DELETE FROM downloads
+             WHERE status IN ('completed', 'cancelled', 'failed')
+               AND updated_at < ?1
+               AND id NOT IN (SELECT id FROM download_states)",
+            params![cutoff],
+        )?;
+        tx.commit()?;
+        Ok(report)
+    }
+
     fn clear_download_state(&self, download_id: &str) -> Result<()> {
         let conn = self.connection()?;
         conn.execute(
@@ -449,3 +786,719 @@ impl DownloadStateQueries for Database {
         Ok(())
     }
 }
+
+impl TelemetryQueueQueries for Database {
+    /// Queues an event, dropping the oldest ones beyond `max_queued`.
+    fn enqueue_telemetry(&self, event: &TelemetryEvent, max_queued: usize) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO telemetry_queue (name, payload, created_at) VALUES (?1, ?2, ?3)",
+            params![
+                event.name,
+                serde_json::to_string(&event.payload)?,
+                chrono::Utc::now().timestamp(),
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM telemetry_queue WHERE id NOT IN
+             (SELECT id FROM telemetry_queue ORDER BY id DESC LIMIT ?1)",
+            params![max_queued as i64],
+        )?;
+        Ok(())
+    }
+
+    fn list_telemetry(&self, limit: usize) -> Result<Vec<(i64, TelemetryEvent)>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, name, payload FROM telemetry_queue ORDER BY id ASC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| {
+            let payload: String = row.get(2)?;
+            Ok((
+                row.get(0)?,
+                TelemetryEvent {
+                    name: row.get(1)?,
+                    payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+                },
+            ))
+        })?;
+
+        let mut events = Vec::new();
+        for item in rows {
+            events.push(item?);
+        }
+        Ok(events)
+    }
+
+    fn delete_telemetry_through(&self, id: i64) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute("DELETE FROM telemetry_queue WHERE id <= ?1", params![id])?;
+        Ok(())
+    }
+
+    fn clear_telemetry(&self) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute("DELETE FROM telemetry_queue", [])?;
+        Ok(())
+    }
+}
+
+impl OfflineQueueQueries for Database {
+    fn enqueue_offline_request(
+        &self,
+        key: &str,
+        method: &str,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<()> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM offline_queue WHERE key = ?1", params![key])?;
+        tx.execute(
+            "INSERT INTO offline_queue (key, method, path, body, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                key,
+                method,
+                path,
+                serde_json::to_string(body)?,
+                chrono::Utc::now().timestamp(),
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn list_offline_requests(&self) -> Result<Vec<QueuedRequest>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, method, path, body, created_at FROM offline_queue ORDER BY id ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let body: String = row.get(3)?;
+            Ok(QueuedRequest {
+                id: row.get(0)?,
+                method: row.get(1)?,
+                path: row.get(2)?,
+                body: serde_json::from_str(&body).unwrap_or(serde_json::Value::Null),
+                created_at: row.get(4)?,
+            })
+        })?;
+
+        let mut requests = Vec::new();
+        for item in rows {
+            requests.push(item?);
+        }
+        Ok(requests)
+    }
+
+    fn count_offline_requests(&self) -> Result<usize> {
+        let conn = self.connection()?;
+        let count: i64 =
+            conn.query_row("SELECT COUNT(*) FROM offline_queue", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    fn remove_offline_request(&self, id: i64) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute("DELETE FROM offline_queue WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn remove_offline_requests_for(&self, key: &str) -> Result<usize> {
+        let conn = self.connection()?;
+        let removed = conn.execute("DELETE FROM offline_queue WHERE key = ?1", params![key])?;
+        Ok(removed)
+    }
+}
+
+impl LibraryCacheQueries for Database {
+    fn replace_library_cache(&self, entries: &[LibraryEntry], fetched_at: i64) -> Result<()> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM library_cache", [])?;
+        for (index, entry) in entries.iter().enumerate() {
+            let entry_json = serde_json::to_string(entry)?;
+            tx.execute(
+                "INSERT INTO library_cache (position, entry_id, entry_json, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![index as i64, entry.id, entry_json, fetched_at],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_cached_library(&self) -> Result<Vec<LibraryEntry>> {
+        let conn = self.connection()?;
+        let mut stmt =
+            conn.prepare("SELECT entry_json FROM library_cache ORDER BY position ASC")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut entries = Vec::new();
+        for item in rows {
+            match serde_json::from_str::<LibraryEntry>(&item?) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => tracing::warn!("skipping unreadable cached library entry: {err}"),
+            }
+        }
+        Ok(entries)
+    }
+}
+
+impl WorkshopFileQueries for Database {
+    fn replace_workshop_files(&self, item_id: &str, files: &[WorkshopFileRecord]) -> Result<()> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM workshop_files WHERE item_id = ?1", params![item_id])?;
+        let now = chrono::Utc::now().timestamp();
+        for file in files {
+            tx.execute(
+                "INSERT INTO workshop_files (item_id, relative_path, size_bytes, sha256, recorded_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![item_id, file.path, file.size as i64, file.sha256, now],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn list_workshop_files(&self, item_id: &str) -> Result<Vec<WorkshopFileRecord>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT relative_path, size_bytes, sha256 FROM workshop_files
+             WHERE item_id = ?1 ORDER BY relative_path",
+        )?;
+        let rows = stmt.query_map(params![item_id], |row| {
+            let size: i64 = row.get(1)?;
+            Ok(WorkshopFileRecord {
+                path: row.get(0)?,
+                size: size.max(0) as u64,
+                sha256: row.get(2)?,
+            })
+        })?;
+
+        let mut files = Vec::new();
+        for item in rows {
+            files.push(item?);
+        }
+        Ok(files)
+    }
+}
+
+impl CloudSaveSnapshotQueries for Database {
+    fn save_cloud_snapshot(&self, snapshot: &CloudSaveSnapshot) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO cloud_save_snapshots (game_id, local_hash, remote_version, synced_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                snapshot.game_id,
+                snapshot.local_hash,
+                snapshot.remote_version,
+                snapshot.synced_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_cloud_snapshot(&self, game_id: &str) -> Result<Option<CloudSaveSnapshot>> {
+        let conn = self.connection()?;
+        let snapshot = conn
+            .query_row(
+                "SELECT game_id, local_hash, remote_version, synced_at
+                 FROM cloud_save_snapshots WHERE game_id = ?1",
+                params![game_id],
+                |row| {
+                    Ok(CloudSaveSnapshot {
+                        game_id: row.get(0)?,
+                        local_hash: row.get(1)?,
+                        remote_version: row.get(2)?,
+                        synced_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(snapshot)
+    }
+}
+
+impl AchievementQueueQueries for Database {
+    /// Keeps the first unlock time if the achievement is already queued.
+    fn enqueue_achievement(
+        &self,
+        game_id: &str,
+        achievement_key: &str,
+    ) -> Result<PendingAchievement> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT OR IGNORE INTO achievement_queue (game_id, achievement_key, unlocked_at, attempts)
+             VALUES (?1, ?2, ?3, 0)",
+            params![game_id, achievement_key, chrono::Utc::now().timestamp()],
+        )?;
+        let pending = conn.query_row(
+            "SELECT game_id, achievement_key, unlocked_at, attempts FROM achievement_queue
+             WHERE game_id = ?1 AND achievement_key = ?2",
+            params![game_id, achievement_key],
+            |row| {
+                Ok(PendingAchievement {
+                    game_id: row.get(0)?,
+                    achievement_key: row.get(1)?,
+                    unlocked_at: row.get(2)?,
+                    attempts: row.get(3)?,
+                })
+            },
+        )?;
+        Ok(pending)
+    }
+
+    fn list_pending_achievements(&self) -> Result<Vec<PendingAchievement>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT game_id, achievement_key, unlocked_at, attempts FROM achievement_queue
+             ORDER BY unlocked_at ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(PendingAchievement {
+                game_id: row.get(0)?,
+                achievement_key: row.get(1)?,
+                unlocked_at: row.get(2)?,
+                attempts: row.get(3)?,
+            })
+        })?;
+
+        let mut pending = Vec::new();
+        for item in rows {
+            pending.push(item?);
+        }
+        Ok(pending)
+    }
+
+    fn bump_achievement_attempts(&self, game_id: &str, achievement_key: &str) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE achievement_queue SET attempts = attempts + 1
+             WHERE game_id = ?1 AND achievement_key = ?2",
+            params![game_id, achievement_key],
+        )?;
+        Ok(())
+    }
+
+    fn remove_pending_achievement(&self, game_id: &str, achievement_key: &str) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "DELETE FROM achievement_queue WHERE game_id = ?1 AND achievement_key = ?2",
+            params![game_id, achievement_key],
+        )?;
+        Ok(())
+    }
+}
+
+fn game_update_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<GameUpdateStatus> {
+    Ok(GameUpdateStatus {
+        game_id: row.get(0)?,
+        slug: row.get(1)?,
+        installed_version: row.get(2)?,
+        latest_version: row.get(3)?,
+        etag: row.get(4)?,
+        update_available: row.get::<_, i64>(5)? != 0,
+        checked_at: row.get(6)?,
+        changed_files: row.get::<_, i64>(7)?.max(0) as usize,
+        delta_bytes: row.get::<_, i64>(8)?.max(0) as u64,
+    })
+}
+
+impl GameUpdateQueries for Database {
+    fn get_game_update(&self, game_id: &str) -> Result<Option<GameUpdateStatus>> {
+        let conn = self.connection()?;
+        let status = conn
+            .query_row(
+                "SELECT game_id, slug, installed_version, latest_version, etag, update_available, checked_at,
+                        changed_files, delta_bytes
+                 FROM game_updates WHERE game_id = ?1",
+                params![game_id],
+                game_update_from_row,
+            )
+            .optional()?;
+        Ok(status)
+    }
+
+    fn upsert_game_update(&self, status: &GameUpdateStatus) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO game_updates (game_id, slug, installed_version, latest_version, etag, update_available, checked_at,
+                                                  changed_files, delta_bytes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                status.game_id,
+                status.slug,
+                status.installed_version,
+                status.latest_version,
+                status.etag,
+                status.update_available as i64,
+                status.checked_at,
+                status.changed_files as i64,
+                status.delta_bytes as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn list_available_updates(&self) -> Result<Vec<GameUpdateStatus>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT game_id, slug, installed_version, latest_version, etag, update_available, checked_at,
+                    changed_files, delta_bytes
+             FROM game_updates WHERE update_available = 1 ORDER BY slug ASC",
+        )?;
+        let rows = stmt.query_map([], game_update_from_row)?;
+
+        let mut updates = Vec::new();
+        for item in rows {
+            updates.push(item?);
+        }
+        Ok(updates)
+    }
+
+    fn last_update_check(&self) -> Result<Option<i64>> {
+        let conn = self.connection()?;
+        let checked_at = conn.query_row("SELECT MAX(checked_at) FROM game_updates", [], |row| {
+            row.get::<_, Option<i64>>(0)
+        })?;
+        Ok(checked_at)
+    }
+}
+
+impl DiscoveryQueueQueries for Database {
+    fn save_discovery_page(&self, offset: usize, games: &[Game], fetched_at: i64) -> Result<()> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM discovery_queue WHERE position >= ?1",
+            params![offset as i64],
+        )?;
+        for (index, game) in games.iter().enumerate() {
+            let game_json = serde_json::to_string(game)?;
+            tx.execute(
+                "INSERT INTO discovery_queue (position, game_id, game_json, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![(offset + index) as i64, game.id, game_json, fetched_at],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_cached_discovery_queue(&self) -> Result<(Vec<Game>, Option<i64>)> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare("SELECT game_json, fetched_at FROM discovery_queue ORDER BY position ASC")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+
+        let mut games = Vec::new();
+        let mut fetched_at: Option<i64> = None;
+        for item in rows {
+            let (game_json, row_fetched_at) = item?;
+            match serde_json::from_str::<Game>(&game_json) {
+                Ok(game) => games.push(game),
+                Err(err) => {
+                    tracing::warn!("skipping unreadable cached discovery entry: {err}");
+                    continue;
+                }
+            }
+            fetched_at = Some(fetched_at.map_or(row_fetched_at, |value| value.min(row_fetched_at)));
+        }
+        Ok((games, fetched_at))
+    }
+}
+
+impl InventoryCacheQueries for Database {
+    fn replace_inventory_cache(&self, kind: &str, entries: &[(String, String)]) -> Result<()> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM inventory_cache WHERE kind = ?1", params![kind])?;
+        let now = chrono::Utc::now().timestamp();
+        for (id, payload) in entries {
+            tx.execute(
+                "INSERT OR REPLACE INTO inventory_cache (kind, id, payload, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![kind, id, payload, now],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn list_inventory_cache(&self, kind: &str) -> Result<Vec<String>> {
+        let conn = self.connection()?;
+        let mut stmt =
+            conn.prepare("SELECT payload FROM inventory_cache WHERE kind = ?1 ORDER BY rowid ASC")?;
+        let rows = stmt.query_map(params![kind], |row| row.get::<_, String>(0))?;
+
+        let mut payloads = Vec::new();
+        for item in rows {
+            payloads.push(item?);
+        }
+        Ok(payloads)
+    }
+
+    fn get_inventory_cache_entry(&self, kind: &str, id: &str) -> Result<Option<String>> {
+        let conn = self.connection()?;
+        let payload = conn
+            .query_row(
+                "SELECT payload FROM inventory_cache WHERE kind = ?1 AND id = ?2",
+                params![kind, id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(payload)
+    }
+
+    fn upsert_inventory_cache(&self, kind: &str, id: &str, payload: &str) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO inventory_cache (kind, id, payload, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(kind, id) DO UPDATE SET payload = excluded.payload, updated_at = excluded.updated_at",
+            params![kind, id, payload, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    fn remove_inventory_cache(&self, kind: &str, id: &str) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "DELETE FROM inventory_cache WHERE kind = ?1 AND id = ?2",
+            params![kind, id],
+        )?;
+        Ok(())
+    }
+
+    fn clear_inventory_cache(&self) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute("DELETE FROM inventory_cache", [])?;
+        Ok(())
+    }
+}
+
+impl BandwidthUsageQueries for Database {
+    fn add_bandwidth_usage(
+        &self,
+        game_id: &str,
+        date: &str,
+        bytes: &BandwidthUsageTotals,
+    ) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO bandwidth_usage (game_id, usage_date, network_bytes, peer_bytes, depotcache_bytes, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(game_id, usage_date) DO UPDATE SET
+                network_bytes = network_bytes + excluded.network_bytes,
+                peer_bytes = peer_bytes + excluded.peer_bytes,
+                depotcache_bytes = depotcache_bytes + excluded.depotcache_bytes,
+                updated_at = excluded.updated_at",
+            params![
+                game_id,
+                date,
+                bytes.network_bytes as i64,
+                bytes.peer_bytes as i64,
+                bytes.depotcache_bytes as i64,
+                chrono::Utc::now().timestamp(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn list_bandwidth_usage(&self, from: &str, to: &str) -> Result<Vec<BandwidthUsageRecord>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT game_id, usage_date, network_bytes, peer_bytes, depotcache_bytes
+             FROM bandwidth_usage
+             WHERE usage_date >= ?1 AND usage_date <= ?2
+             ORDER BY usage_date ASC, game_id ASC",
+        )?;
+        let rows = stmt.query_map(params![from, to], |row| {
+            Ok(BandwidthUsageRecord {
+                game_id: row.get(0)?,
+                date: row.get(1)?,
+                bytes: BandwidthUsageTotals {
+                    network_bytes: row.get::<_, i64>(2)?.max(0) as u64,
+                    peer_bytes: row.get::<_, i64>(3)?.max(0) as u64,
+                    depotcache_bytes: row.get::<_, i64>(4)?.max(0) as u64,
+                },
+            })
+        })?;
+
+        let mut records = Vec::new();
+        for item in rows {
+            records.push(item?);
+        }
+        Ok(records)
+    }
+
+    fn sum_network_bytes(&self, from: &str, to: &str) -> Result<u64> {
+        let conn = self.connection()?;
+        let total: i64 = conn.query_row(
+            "SELECT COALESCE(SUM(network_bytes), 0) FROM bandwidth_usage
+             WHERE usage_date >= ?1 AND usage_date <= ?2",
+            params![from, to],
+            |row| row.get(0),
+        )?;
+        Ok(total.max(0) as u64)
+    }
+}
+
+impl FileIndexQueries for Database {
+    fn list_verified_file_index(
+        &self,
+        game_id: &str,
+        install_path: &str,
+    ) -> Result<Vec<FileIndexEntry>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT relative_path, size_bytes, modified_at, fast_hash, canonical_hash
+             FROM file_index_v2
+             WHERE game_id = ?1 AND install_path = ?2 AND status = 'ok'
+               AND fast_hash IS NOT NULL AND canonical_hash IS NOT NULL",
+        )?;
+        let rows = stmt.query_map(params![game_id, install_path], |row| {
+            Ok(FileIndexEntry {
+                relative_path: row.get(0)?,
+                size_bytes: row.get::<_, i64>(1)?.max(0) as u64,
+                modified_at: row.get(2)?,
+                fast_hash: row.get(3)?,
+                canonical_hash: row.get(4)?,
+            })
+        })?;
+
+        let mut entries = Vec::new();
+        for item in rows {
+            entries.push(item?);
+        }
+        Ok(entries)
+    }
+
+    fn upsert_verified_file_index(
+        &self,
+        game_id: &str,
+        install_path: &str,
+        entries: &[FileIndexEntry],
+    ) -> Result<()> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        let now = chrono::Utc::now().timestamp();
+        for entry in entries {
+            tx.execute(
+                "INSERT OR REPLACE INTO file_index_v2
+                    (game_id, install_path, relative_path, size_bytes, modified_at, fast_hash, canonical_hash, status, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'ok', ?8)",
+                params![
+                    game_id,
+                    install_path,
+                    entry.relative_path,
+                    entry.size_bytes as i64,
+                    entry.modified_at,
+                    entry.fast_hash,
+                    entry.canonical_hash,
+                    now,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl LibraryFolderQueries for Database {
+    fn list_library_folders(&self) -> Result<Vec<LibraryFolder>> {
+        let conn = self.connection()?;
+        let mut stmt =
+            conn.prepare("SELECT path, added_at FROM library_folders ORDER BY added_at ASC")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(LibraryFolder {
+                path: row.get(0)?,
+                added_at: row.get(1)?,
+            })
+        })?;
+
+        let mut folders = Vec::new();
+        for item in rows {
+            folders.push(item?);
+        }
+        Ok(folders)
+    }
+
+    fn add_library_folder(&self, path: &str) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT OR IGNORE INTO library_folders (path, added_at) VALUES (?1, ?2)",
+            params![path, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    fn remove_library_folder(&self, path: &str) -> Result<bool> {
+        let conn = self.connection()?;
+        let removed = conn.execute("DELETE FROM library_folders WHERE path = ?1", params![path])?;
+        Ok(removed > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download_state(status: &str) -> DownloadState {
+        DownloadState {
+            id: "dl-1".to_string(),
+            game_id: "game".to_string(),
+            slug: "game".to_string(),
+            status: status.to_string(),
+            install_dir: "/games/game".to_string(),
+            manifest_json: "{}".to_string(),
+            updated_at: 1,
+            engine: None,
+            method: Some("chunks".to_string()),
+        }
+    }
+
+    #[test]
+    fn saving_a_download_state_keeps_its_preflight_checkpoint() {
+        let db = crate::db::open_temp();
+        db.save_download_state(&download_state("queued")).unwrap();
+        db.set_preflight_checkpoint("dl-1", r#"{"fingerprint":"f"}"#)
+            .unwrap();
+
+        db.save_download_state(&download_state("downloading"))
+            .unwrap();
+
+        let state = db.get_download_state("dl-1").unwrap().unwrap();
+        assert_eq!(state.status, "downloading");
+        assert_eq!(
+            db.get_preflight_checkpoint("dl-1").unwrap().as_deref(),
+            Some(r#"{"fingerprint":"f"}"#)
+        );
+    }
+
+    #[test]
+    fn offline_writes_replace_queued_ones_for_the_same_key() {
+        let db = crate::db::open_temp();
+        let body = serde_json::json!({});
+        db.enqueue_offline_request("download:1", "POST", "downloads/1/pause", &body)
+            .unwrap();
+        db.enqueue_offline_request("workshop:7", "DELETE", "/workshop/items/7/subscribe", &body)
+            .unwrap();
+        db.enqueue_offline_request("download:1", "POST", "downloads/1/resume", &body)
+            .unwrap();
+
+        let queued = db.list_offline_requests().unwrap();
+        let paths: Vec<&str> = queued.iter().map(|item| item.path.as_str()).collect();
+        assert_eq!(paths, ["/workshop/items/7/subscribe", "downloads/1/resume"]);
+        assert_eq!(db.count_offline_requests().unwrap(), 2);
+
+        assert_eq!(db.remove_offline_requests_for("download:1").unwrap(), 1);
+        db.remove_offline_request(queued[0].id).unwrap();
+        assert_eq!(db.count_offline_requests().unwrap(), 0);
+    }
+}
@@ -0,0 +1,154 @@
+//! Handling for `otoshi://` links, whether they arrive through the deep-link
+//! plugin or as an argument to a second launcher instance.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands::download_v2::begin_download_v2;
+use crate::services::StartDownloadV2Request;
+use crate::AppState;
+
+const SCHEME: &str = "otoshi://";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct InstallRequest {
+    pub slug: String,
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct InstallRequestFailed {
+    pub slug: String,
+    pub error: String,
+}
+
+/// Returns the first `otoshi://` argument a second instance was started with.
+pub fn find_link_arg(args: &[String]) -> Option<&str> {
+    args.iter()
+        .map(|arg| arg.trim().trim_matches('"'))
+        .find(|arg| has_scheme(arg))
+}
+
+fn has_scheme(value: &str) -> bool {
+    value
+        .get(..SCHEME.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(SCHEME))
+}
+
+/// Extracts the slug from `otoshi://install/<slug>`.
+pub fn parse_install_link(url: &str) -> Option<String> {
+    let url = url.trim();
+    if !has_scheme(url) {
+        return None;
+    }
+    let rest = url[SCHEME.len()..]
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default();
+    let mut parts = rest.trim_end_matches('/').split('/');
+    if !parts.next()?.eq_ignore_ascii_case("install") {
+        return None;
+    }
+    let slug = urlencoding::decode(parts.next()?).ok()?.trim().to_string();
+    let valid = !slug.is_empty()
+        && parts.next().is_none()
+        && slug
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.'))
+        && slug != "."
+        && slug != "..";
+    valid.then_some(slug)
+}
+
+/// Routes one `otoshi://` URL: OAuth callbacks go to the frontend, install
+/// links emit `install-request` and start the download.
+pub fn handle_url(app: &AppHandle, url: &str) {
+    tracing::info!("Deep link received: {}", url);
+    if url.starts_with("otoshi://oauth") || url.starts_with("otoshi://callback") {
+        if let Err(e) = app.emit("oauth-callback", url) {
+            tracing::error!("Failed to emit oauth-callback event: {}", e);
+        }
+        return;
+    }
+
+    let Some(slug) = parse_install_link(url) else {
+        tracing::warn!("ignoring unsupported deep link: {}", url);
+        return;
+    };
+    let _ = app.emit(
+        "install-request",
+        InstallRequest {
+            slug: slug.clone(),
+            url: url.to_string(),
+        },
+    );
+
+    let Some(state) = app.try_state::<Arc<AppState>>() else {
+        tracing::warn!("install link for {} arrived before startup finished", slug);
+        return;
+    };
+    let state = state.inner().clone();
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(error) = start_install(&state, &slug).await {
+            tracing::warn!("install link for {} failed: {}", slug, error);
+            let _ = app.emit(
+                "install-request-failed",
+                InstallRequestFailed { slug, error },
+            );
+        }
+    });
+}
+
+async fn start_install(state: &Arc<AppState>, slug: &str) -> Result<(), String> {
+    let game = state
+        .library
+        .get_game_details(slug)
+        .await
+        .map_err(|err| err.to_string())?;
+    let payload = StartDownloadV2Request {
+        game_id: game.id,
+        slug: game.slug,
+        download_id: None,
+        method: None,
+        version: None,
+        channel: None,
+        install_path: None,
+        expected_file_bytes: None,
+    };
+    begin_download_v2(state, payload)
+        .await
+        .map(|_| ())
+        .map_err(|err| err.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_install_links() {
+        assert_eq!(
+            parse_install_link("otoshi://install/hollow-knight"),
+            Some("hollow-knight".to_string())
+        );
+        assert_eq!(
+            parse_install_link("OTOSHI://install/celeste/?from=web"),
+            Some("celeste".to_string())
+        );
+        assert_eq!(parse_install_link("otoshi://install/"), None);
+        assert_eq!(parse_install_link("otoshi://install/a/b"), None);
+        assert_eq!(parse_install_link("otoshi://install/..%2Fetc"), None);
+        assert_eq!(parse_install_link("otoshi://oauth/callback"), None);
+        assert_eq!(parse_install_link("https://install/celeste"), None);
+
+        let args = vec![
+            "C:\\Otoshi\\otoshi.exe".to_string(),
+            "\"otoshi://install/celeste\"".to_string(),
+        ];
+        assert_eq!(find_link_arg(&args), Some("otoshi://install/celeste"));
+        assert_eq!(find_link_arg(&args[..1]), None);
+    }
+}
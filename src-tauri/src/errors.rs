@@ -1,15 +1,20 @@
 use std::io;
+use std::sync::Arc;
 
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum LauncherError {
+    /// Shared so coalesced requests can hand every waiter the same error.
     #[error("Network error: {0}")]
-    Network(#[from] reqwest::Error),
+    Network(#[source] Arc<reqwest::Error>),
     #[error("HTTP error: {0}")]
     Http(String),
     #[error("Database error: {0}")]
     Database(#[from] rusqlite::Error),
+    #[error("Database pool error: {0}")]
+    Pool(#[from] r2d2::Error),
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
     #[error("Serialization error: {0}")]
@@ -22,6 +27,93 @@ pub enum LauncherError {
     NotFound(String),
     #[error("Config error: {0}")]
     Config(String),
+    #[error("Insufficient storage: {0}")]
+    Storage(String),
+    #[error("Integrity error: {0}")]
+    Integrity(String),
+    #[error("Data cap reached: {0}")]
+    DataCap(String),
+}
+
+impl LauncherError {
+    /// Stable machine-readable code for the UI to pick a remediation.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Network(_) => "network",
+            Self::Http(_) => "http",
+            Self::Database(_) | Self::Pool(_) => "database",
+            Self::Io(err) if is_disk_full(err) => "insufficient_storage",
+            Self::Io(_) => "io",
+            Self::Serde(_) => "serialization",
+            Self::Crypto(_) => "crypto",
+            Self::Auth(_) => "auth",
+            Self::NotFound(_) => "not_found",
+            Self::Config(_) => "config",
+            Self::Storage(_) => "insufficient_storage",
+            Self::Integrity(_) => "integrity",
+            Self::DataCap(_) => "data_cap_reached",
+        }
+    }
+
+    /// Whether retrying the same operation unchanged can succeed.
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Network(_) | Self::Http(_) | Self::Pool(_) | Self::Integrity(_)
+        )
+    }
+}
+
+impl From<reqwest::Error> for LauncherError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Network(Arc::new(err))
+    }
+}
+
+fn is_disk_full(err: &io::Error) -> bool {
+    #[cfg(target_os = "windows")]
+    const DISK_FULL_CODES: [i32; 2] = [112, 39];
+    #[cfg(not(target_os = "windows"))]
+    const DISK_FULL_CODES: [i32; 1] = [28];
+    err.raw_os_error()
+        .is_some_and(|code| DISK_FULL_CODES.contains(&code))
+}
+
+/// Error shape returned to the frontend by the download commands
+/// (`commands::download` and `commands::download_v2`) and carried by the
+/// `download-runtime-error` event, where the code picks the remediation the
+/// UI offers. Other commands still reject with a plain message string.
+#[derive(Clone, Debug, Serialize)]
+pub struct ErrorPayload {
+    pub code: String,
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl ErrorPayload {
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            message: message.into(),
+            retryable: false,
+        }
+    }
+}
+
+impl From<LauncherError> for ErrorPayload {
+    fn from(err: LauncherError) -> Self {
+        Self {
+            code: err.code().to_string(),
+            message: err.to_string(),
+            retryable: err.retryable(),
+        }
+    }
+}
+
+impl From<String> for ErrorPayload {
+    fn from(message: String) -> Self {
+        Self::new("unknown", message)
+    }
 }
 
 pub type Result<T> = std::result::Result<T, LauncherError>;
@@ -1,21 +1,74 @@
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
 use once_cell::sync::OnceCell;
-use tracing_appender::non_blocking::WorkerGuard;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_appender::rolling::RollingFileAppender;
 use tracing_subscriber::fmt::Subscriber;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
 use tracing_subscriber::EnvFilter;
 
-use crate::errors::Result;
+use crate::db::queries::SettingsQueries;
+use crate::db::Database;
+use crate::errors::{LauncherError, Result};
 
-static LOG_GUARD: OnceCell<WorkerGuard> = OnceCell::new();
+const TEXT_LOG_PREFIX: &str = "launcher.log";
+/// Machine-readable copy of `launcher.log`, one `LogEntry` per line.
+const JSON_LOG_PREFIX: &str = "launcher.jsonl";
+const LOG_RETENTION_SETTING: &str = "log_retention";
+const DEFAULT_MAX_LOG_FILES: usize = 7;
+const DEFAULT_MAX_LOG_TOTAL_MB: u64 = 50;
+
+static LOG_GUARDS: OnceCell<[WorkerGuard; 2]> = OnceCell::new();
+static MAX_LOG_FILES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_LOG_FILES);
+static MAX_LOG_TOTAL_BYTES: AtomicU64 = AtomicU64::new(DEFAULT_MAX_LOG_TOTAL_MB * 1024 * 1024);
+
+/// How much of the daily rotated logs to keep. Old files are pruned on
+/// startup and whenever a log rolls over to a new day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogRetention {
+    /// Newest files kept per log, the active one included.
+    pub max_files: usize,
+    /// Cap on all launcher logs together; the oldest files go first. The
+    /// active files are never removed.
+    pub max_total_mb: u64,
+}
+
+impl Default for LogRetention {
+    fn default() -> Self {
+        Self {
+            max_files: DEFAULT_MAX_LOG_FILES,
+            max_total_mb: DEFAULT_MAX_LOG_TOTAL_MB,
+        }
+    }
+}
+
+impl LogRetention {
+    fn normalized(self) -> Self {
+        Self {
+            max_files: self.max_files.clamp(1, 365),
+            max_total_mb: self.max_total_mb.clamp(1, 10 * 1024),
+        }
+    }
+}
 
 pub fn init(log_dir: &Path) -> Result<()> {
     fs::create_dir_all(log_dir)?;
 
-    let file_appender = tracing_appender::rolling::daily(log_dir, "launcher.log");
+    let file_appender = PruningAppender::daily(log_dir, TEXT_LOG_PREFIX);
     let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
-    let _ = LOG_GUARD.set(guard);
+    let json_appender = PruningAppender::daily(log_dir, JSON_LOG_PREFIX);
+    let (json_writer, json_guard) = tracing_appender::non_blocking(json_appender);
+    let _ = LOG_GUARDS.set([guard, json_guard]);
 
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
 
@@ -24,10 +77,380 @@ pub fn init(log_dir: &Path) -> Result<()> {
         .with_writer(non_blocking)
         .with_file(true)
         .with_line_number(true)
-        .finish();
+        .finish()
+        .with(JsonLogLayer {
+            writer: json_writer,
+        });
 
     tracing::subscriber::set_global_default(subscriber)
-        .map_err(|err| crate::errors::LauncherError::Config(err.to_string()))?;
+        .map_err(|err| LauncherError::Config(err.to_string()))?;
+
+    Ok(())
+}
+
+/// One event from the JSON log.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// RFC 3339, UTC.
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// Structured fields other than the message.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LogQuery {
+    /// Least severe level to include, e.g. `warn` also returns errors.
+    pub level: Option<Level>,
+    /// Unix seconds; older entries are left out.
+    pub since: Option<i64>,
+    /// Case-insensitive text the message or target must contain.
+    pub contains: Option<String>,
+    pub limit: usize,
+}
+
+pub fn load_retention(db: &Database) -> LogRetention {
+    match db.get_setting(LOG_RETENTION_SETTING) {
+        Ok(Some(raw)) => serde_json::from_str::<LogRetention>(&raw)
+            .map(LogRetention::normalized)
+            .unwrap_or_else(|err| {
+                tracing::warn!("ignoring invalid {LOG_RETENTION_SETTING}: {err}");
+                LogRetention::default()
+            }),
+        _ => LogRetention::default(),
+    }
+}
 
+/// Saves `retention` and prunes `log_dir` to it right away.
+pub fn set_retention(
+    db: &Database,
+    log_dir: &Path,
+    retention: LogRetention,
+) -> Result<LogRetention> {
+    let retention = retention.normalized();
+    db.set_setting(LOG_RETENTION_SETTING, &serde_json::to_string(&retention)?)?;
+    apply_retention(log_dir, retention);
+    Ok(retention)
+}
+
+/// Makes `retention` the limit for later rollovers and prunes now.
+pub fn apply_retention(log_dir: &Path, retention: LogRetention) {
+    MAX_LOG_FILES.store(retention.max_files, Ordering::Relaxed);
+    MAX_LOG_TOTAL_BYTES.store(retention.max_total_mb * 1024 * 1024, Ordering::Relaxed);
+    if let Err(err) = prune(log_dir) {
+        tracing::warn!("failed to prune logs in {}: {}", log_dir.display(), err);
+    }
+}
+
+/// The file `launcher.log` is currently written to.
+pub fn active_log_file(log_dir: &Path) -> Option<PathBuf> {
+    log_files(log_dir, TEXT_LOG_PREFIX).ok()?.pop()
+}
+
+/// Files of the log named `prefix`, oldest first. Daily files end in their
+/// date, so the name orders them.
+fn log_files(log_dir: &Path, prefix: &str) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<_> = fs::read_dir(log_dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(prefix))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn prune(log_dir: &Path) -> io::Result<()> {
+    let max_files = MAX_LOG_FILES.load(Ordering::Relaxed);
+    let max_total_bytes = MAX_LOG_TOTAL_BYTES.load(Ordering::Relaxed);
+    let mut total_bytes = 0u64;
+    // Rotated files of every log as (date suffix, path, size).
+    let mut rotated = Vec::new();
+    for prefix in [TEXT_LOG_PREFIX, JSON_LOG_PREFIX] {
+        let mut files = log_files(log_dir, prefix)?;
+        let excess = files.len().saturating_sub(max_files);
+        for path in files.drain(..excess) {
+            fs::remove_file(&path)?;
+        }
+        for (index, path) in files.iter().enumerate() {
+            let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+            total_bytes += size;
+            if index + 1 < files.len() {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                rotated.push((name[prefix.len()..].to_string(), path.clone(), size));
+            }
+        }
+    }
+    rotated.sort();
+    for (_, path, size) in rotated {
+        if total_bytes <= max_total_bytes {
+            break;
+        }
+        fs::remove_file(&path)?;
+        total_bytes = total_bytes.saturating_sub(size);
+    }
     Ok(())
 }
+
+/// Daily appender that prunes old logs whenever it moves on to a new file.
+struct PruningAppender {
+    inner: RollingFileAppender,
+    log_dir: PathBuf,
+    day: NaiveDate,
+}
+
+impl PruningAppender {
+    fn daily(log_dir: &Path, prefix: &str) -> Self {
+        Self {
+            inner: tracing_appender::rolling::daily(log_dir, prefix),
+            log_dir: log_dir.to_path_buf(),
+            day: Utc::now().date_naive(),
+        }
+    }
+}
+
+impl Write for PruningAppender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        // The inner appender rolls over on UTC dates as well.
+        let today = Utc::now().date_naive();
+        if today != self.day {
+            self.day = today;
+            // Logging from the writer would feed back into it.
+            let _ = prune(&self.log_dir);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the newest `query.limit` entries matching `query`, oldest first.
+pub fn query(log_dir: &Path, query: &LogQuery) -> Result<Vec<LogEntry>> {
+    let files = log_files(log_dir, JSON_LOG_PREFIX)?;
+    let since = query
+        .since
+        .and_then(|seconds| DateTime::<Utc>::from_timestamp(seconds, 0));
+    let contains = query.contains.as_ref().map(|text| text.to_lowercase());
+    let mut newest_first = Vec::new();
+    for path in files.iter().rev() {
+        let file = BufReader::new(fs::File::open(path)?);
+        let mut matched: Vec<LogEntry> = file
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| serde_json::from_str::<LogEntry>(&line).ok())
+            .filter(|entry| entry_matches(entry, query.level, since, contains.as_deref()))
+            .collect();
+        let room = query.limit.saturating_sub(newest_first.len());
+        newest_first.extend(matched.drain(matched.len().saturating_sub(room)..).rev());
+        if newest_first.len() >= query.limit {
+            break;
+        }
+    }
+    newest_first.reverse();
+    Ok(newest_first)
+}
+
+pub fn parse_level(value: &str) -> Result<Level> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| LauncherError::Config(format!("unknown log level: {value}")))
+}
+
+fn entry_matches(
+    entry: &LogEntry,
+    level: Option<Level>,
+    since: Option<DateTime<Utc>>,
+    contains: Option<&str>,
+) -> bool {
+    if let Some(level) = level {
+        // More verbose levels compare greater.
+        if !entry
+            .level
+            .parse::<Level>()
+            .is_ok_and(|found| found <= level)
+        {
+            return false;
+        }
+    }
+    if let Some(since) = since {
+        if !DateTime::parse_from_rfc3339(&entry.timestamp).is_ok_and(|logged| logged >= since) {
+            return false;
+        }
+    }
+    match contains {
+        Some(text) => {
+            entry.message.to_lowercase().contains(text)
+                || entry.target.to_lowercase().contains(text)
+        }
+        None => true,
+    }
+}
+
+/// Writes every event that passes the filter to the JSON log.
+struct JsonLogLayer {
+    writer: NonBlocking,
+}
+
+impl<S: tracing::Subscriber> Layer<S> for JsonLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        let entry = LogEntry {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.message,
+            fields: visitor.fields,
+        };
+        if let Ok(mut line) = serde_json::to_vec(&entry) {
+            line.push(b'\n');
+            let _ = self.writer.clone().write_all(&line);
+        }
+    }
+}
+
+#[derive(Default)]
+struct JsonVisitor {
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl JsonVisitor {
+    fn record(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = match value {
+                Value::String(text) => text,
+                other => other.to_string(),
+            };
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for JsonVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, Value::String(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record(field, Value::from(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(timestamp: &str, level: &str, message: &str) -> String {
+        let entry = LogEntry {
+            timestamp: timestamp.to_string(),
+            level: level.to_string(),
+            target: "otoshi_launcher::services".to_string(),
+            message: message.to_string(),
+            fields: Map::new(),
+        };
+        serde_json::to_string(&entry).unwrap() + "\n"
+    }
+
+    #[test]
+    fn query_filters_and_keeps_the_newest_entries() {
+        let dir = std::env::temp_dir().join(format!("otoshi-logs-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("launcher.jsonl.2026-03-01"),
+            line("2026-03-01T10:00:00.000Z", "WARN", "disk slow")
+                + &line("2026-03-01T11:00:00.000Z", "INFO", "download started"),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("launcher.jsonl.2026-03-02"),
+            line("2026-03-02T09:00:00.000Z", "ERROR", "Disk full")
+                + "not json\n"
+                + &line("2026-03-02T09:30:00.000Z", "WARN", "disk recovered"),
+        )
+        .unwrap();
+
+        let warnings = query(
+            &dir,
+            &LogQuery {
+                level: Some(Level::WARN),
+                contains: Some("DISK".to_string()),
+                limit: 2,
+                ..LogQuery::default()
+            },
+        )
+        .unwrap();
+        let messages: Vec<_> = warnings
+            .iter()
+            .map(|entry| entry.message.as_str())
+            .collect();
+        assert_eq!(messages, ["Disk full", "disk recovered"]);
+
+        let since = DateTime::parse_from_rfc3339("2026-03-01T10:30:00Z")
+            .unwrap()
+            .timestamp();
+        let recent = query(
+            &dir,
+            &LogQuery {
+                since: Some(since),
+                limit: 10,
+                ..LogQuery::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[0].message, "download started");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn prune_keeps_the_newest_files_of_each_log() {
+        let dir = std::env::temp_dir().join(format!("otoshi-logs-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        for day in 1..=9 {
+            fs::write(dir.join(format!("launcher.log.2026-03-0{day}")), "text").unwrap();
+        }
+        fs::write(dir.join("launcher.jsonl.2026-03-09"), "{}").unwrap();
+        fs::write(dir.join("backend.log"), "sidecar").unwrap();
+
+        prune(&dir).unwrap();
+
+        let text = log_files(&dir, TEXT_LOG_PREFIX).unwrap();
+        assert_eq!(text.len(), DEFAULT_MAX_LOG_FILES);
+        assert!(text[0].ends_with("launcher.log.2026-03-03"));
+        assert_eq!(active_log_file(&dir), text.last().cloned());
+        assert!(dir.join("launcher.jsonl.2026-03-09").exists());
+        assert!(dir.join("backend.log").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
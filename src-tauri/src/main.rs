@@ -3,37 +3,36 @@
 mod backend_sidecar;
 mod commands;
 mod db;
+mod deep_link;
 mod errors;
 mod logging;
 mod lua_bundler;
 mod models;
+mod runtime_integrity;
+mod safe_mode;
 mod services;
 mod utils;
+mod web_assets;
 
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{Duration, UNIX_EPOCH};
-use std::{
-    fs,
-    io::Read,
-    path::{Component, Path, PathBuf},
-};
-
-use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::Duration;
 
 use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 use tauri::webview::PageLoadEvent;
-use tauri::{Emitter, Manager, WindowEvent};
+use tauri::{Emitter, Listener, Manager, RunEvent, WindowEvent};
 
 use crate::db::Database;
+use crate::safe_mode::StartupPlan;
 use crate::errors::{LauncherError, Result};
 use crate::services::{
-    AchievementService, ApiClient, ArtworkCacheService, AuthService, CloudSaveService, CrackManager,
-    DiscoveryService, DownloadManager, DownloadManagerV2, DownloadService, GameRuntimeService,
-    InventoryService, LibraryService, LicenseService, ManifestService, OverlayService,
-    RemoteDownloadService, SecurityGuardService, SelfHealService, StreamingService, TelemetryService,
-    WorkshopService,
+    AchievementService, ApiClient, ArtworkCacheService, AuthService, CloudSaveService,
+    ConnectivityService, CrackManager, DiscoveryService, DownloadManager, DownloadManagerV2,
+    DownloadService, GameRuntimeService, InventoryService, LibraryService, LicenseService,
+    ManifestService, OverlayService, RemoteDownloadService, SecurityGuardService, SelfHealService,
+    StreamingService, TelemetryService, UpdateCheckService, UpdateService, WorkshopService,
 };
 use crate::services::steam_prefetch_worker::spawn_locale_prefetch_worker;
 use crate::utils::file::FileManager;
@@ -44,6 +43,7 @@ pub struct AppState {
     pub db: Database,
     pub auth: AuthService,
     pub api: ApiClient,
+    pub connectivity: ConnectivityService,
     pub library: LibraryService,
     pub downloads: DownloadService,
     pub download_manager: DownloadManager,
@@ -53,6 +53,8 @@ pub struct AppState {
     pub security_guard_v2: SecurityGuardService,
     pub crack_manager: CrackManager,
     pub telemetry: TelemetryService,
+    pub update_checks: UpdateCheckService,
+    pub launcher_updates: UpdateService,
     pub manifests: ManifestService,
     pub license: LicenseService,
     pub achievements: AchievementService,
@@ -67,8 +69,6 @@ pub struct AppState {
     pub files: FileManager,
 }
 
-const WEB_PACK_STAMP_FILE: &str = ".web-pack.stamp";
-
 #[derive(Default)]
 struct AppLifecycle {
     quitting: AtomicBool,
@@ -214,6 +214,9 @@ fn setup_system_tray(app: &tauri::AppHandle) -> Result<()> {
                 "tray_check_updates" => {
                     show_main_window(app);
                     emit_tray_action(app, "check_updates", None);
+                    if let Some(state) = app.try_state::<Arc<AppState>>() {
+                        state.launcher_updates.spawn_check();
+                    }
                 }
                 "tray_language_en" => {
                     emit_tray_action(app, "set_language", Some("en"));
@@ -264,11 +267,17 @@ fn setup_system_tray(app: &tauri::AppHandle) -> Result<()> {
 fn build_state(app: &tauri::AppHandle) -> Result<AppState> {
     let app_data = resolve_data_dir(app);
     // logging is initialized in main() setup early
+    utils::client_identity::init(&app_data);
 
     let db = db::init(app)?;
+    logging::apply_retention(
+        &utils::paths::resolve_log_dir(app),
+        logging::load_retention(&db),
+    );
     let install_dir = resolve_games_dir(app);
 
     let files = FileManager::new(app_data.clone(), install_dir);
+    commands::system::restore_install_roots(&db, &files);
 
     let api_url =
         std::env::var("LAUNCHER_API_URL").unwrap_or_else(|_| "http://127.0.0.1:8000".to_string());
@@ -278,9 +287,14 @@ fn build_state(app: &tauri::AppHandle) -> Result<AppState> {
     let artwork_cache = ArtworkCacheService::new(resolve_cache_dir(app), &key)?;
 
     let auth = AuthService::new(api_url.clone(), db.clone(), key);
-    let api = ApiClient::new(api_url, auth.clone());
+    let api = ApiClient::new(
+        api_url,
+        auth.clone(),
+        services::api_client::load_api_client_config(&db),
+    );
 
-    let library = LibraryService::new(api.clone());
+    let connectivity = ConnectivityService::new(api.clone(), db.clone());
+    let library = LibraryService::new(api.clone(), db.clone());
     let downloads = DownloadService::new(api.clone());
     let download_manager = DownloadManager::new(
         app.clone(),
@@ -289,29 +303,37 @@ fn build_state(app: &tauri::AppHandle) -> Result<AppState> {
         downloads.clone(),
         files.clone(),
     );
-    let download_manager_v2 =
-        DownloadManagerV2::new(download_manager.clone(), downloads.clone(), db.clone());
+    let download_manager_v2 = DownloadManagerV2::new(
+        download_manager.clone(),
+        api.clone(),
+        downloads.clone(),
+        db.clone(),
+    );
     let game_runtime = GameRuntimeService::new();
     let self_heal = SelfHealService::new(db.clone());
     let security_guard_v2 = SecurityGuardService::new();
     let crack_manager = CrackManager::new(db.clone(), api.clone());
-    let telemetry = TelemetryService::new(api.clone());
+    let telemetry = TelemetryService::new(api.clone(), db.clone());
+    let update_checks = UpdateCheckService::new(app.clone(), api.clone(), db.clone());
+    let launcher_updates =
+        UpdateService::new(app.clone(), api.clone(), app_data.join("launcher-updates"));
     let manifests = ManifestService::new();
     let license_pem = std::env::var("LICENSE_PUBLIC_KEY_PEM").ok();
     let license = LicenseService::new(license_pem);
-    let achievements = AchievementService::new(api.clone());
-    let cloud_saves = CloudSaveService::new(api.clone());
-    let workshop = WorkshopService::new(api.clone());
-    let discovery = DiscoveryService::new(api.clone());
-    let inventory = InventoryService::new(api.clone());
-    let remote_downloads = RemoteDownloadService::new(api.clone());
-    let streaming = StreamingService::new(api.clone());
+    let achievements = AchievementService::new(api.clone(), db.clone());
+    let cloud_saves = CloudSaveService::new(api.clone(), db.clone());
+    let workshop = WorkshopService::new(api.clone(), db.clone());
+    let discovery = DiscoveryService::new(api.clone(), db.clone());
+    let inventory = InventoryService::new(api.clone(), db.clone());
+    let remote_downloads = RemoteDownloadService::new(app.clone(), api.clone());
+    let streaming = StreamingService::new(app.clone(), api.clone());
     let overlay = OverlayService::new();
 
     Ok(AppState {
         db,
         auth,
         api,
+        connectivity,
         library,
         downloads,
         download_manager,
@@ -321,6 +343,8 @@ fn build_state(app: &tauri::AppHandle) -> Result<AppState> {
         security_guard_v2,
         crack_manager,
         telemetry,
+        update_checks,
+        launcher_updates,
         manifests,
         license,
         achievements,
@@ -336,248 +360,6 @@ fn build_state(app: &tauri::AppHandle) -> Result<AppState> {
     })
 }
 
-fn ensure_web_assets(app: &tauri::AppHandle) -> Result<()> {
-    let resource_dir = app
-        .path()
-        .resource_dir()
-        .map_err(|_| LauncherError::Config("resource dir unavailable".to_string()))?;
-    let web_dir = resource_dir.join("web");
-    let index_path = web_dir.join("index.html");
-
-    let pack_candidates = [
-        resource_dir.join("web.pack"),
-        resource_dir.join("resources").join("web.pack"),
-    ];
-    let pack_path = pack_candidates
-        .iter()
-        .find(|candidate| candidate.exists())
-        .cloned();
-    let Some(pack_path) = pack_path else {
-        if !index_path.exists() {
-            tracing::warn!(
-                "web assets missing and web.pack not found; checked {:?}",
-                pack_candidates
-                    .iter()
-                    .map(|value| value.display().to_string())
-                    .collect::<Vec<_>>()
-            );
-        }
-        return Ok(());
-    };
-
-    let pack_stamp = pack_signature(&pack_path)?;
-    let current_stamp = read_web_stamp(&web_dir);
-    let force_restore = std::env::var("OTOSHI_FORCE_WEB_RESTORE")
-        .map(|value| {
-            let normalized = value.trim().to_ascii_lowercase();
-            normalized == "1" || normalized == "true" || normalized == "yes"
-        })
-        .unwrap_or(!cfg!(debug_assertions));
-    let needs_restore = force_restore
-        || !index_path.exists()
-        || current_stamp.as_deref() != Some(pack_stamp.as_str());
-    if !needs_restore {
-        return Ok(());
-    }
-
-    tracing::info!(
-        "Restoring web assets from {:?} (stamp={} previous={:?} force_restore={})",
-        pack_path,
-        pack_stamp,
-        current_stamp,
-        force_restore
-    );
-    if web_dir.exists() {
-        if let Err(err) = fs::remove_dir_all(&web_dir) {
-            tracing::warn!(
-                "failed to clear stale web assets at {}: {}",
-                web_dir.display(),
-                err
-            );
-        }
-    }
-    extract_pack(&pack_path, &web_dir)?;
-    write_web_stamp(&web_dir, &pack_stamp)?;
-
-    #[cfg(target_os = "windows")]
-    {
-        let _ = std::process::Command::new("cmd")
-            .args([
-                "/c",
-                "attrib",
-                "+h",
-                "+s",
-                web_dir.to_string_lossy().as_ref(),
-            ])
-            .status();
-        let _ = std::process::Command::new("cmd")
-            .args([
-                "/c",
-                "attrib",
-                "+h",
-                "+s",
-                pack_path.to_string_lossy().as_ref(),
-            ])
-            .status();
-    }
-
-    Ok(())
-}
-
-fn pack_signature(pack_path: &Path) -> Result<String> {
-    let metadata = fs::metadata(pack_path)?;
-    let modified = metadata
-        .modified()
-        .ok()
-        .and_then(|value| value.duration_since(UNIX_EPOCH).ok())
-        .map(|value| value.as_secs())
-        .unwrap_or(0);
-    Ok(format!("{}:{}", metadata.len(), modified))
-}
-
-fn read_web_stamp(web_dir: &Path) -> Option<String> {
-    let stamp_path = web_dir.join(WEB_PACK_STAMP_FILE);
-    fs::read_to_string(stamp_path)
-        .ok()
-        .map(|value| value.trim().to_string())
-        .filter(|value| !value.is_empty())
-}
-
-fn write_web_stamp(web_dir: &Path, stamp: &str) -> Result<()> {
-    fs::create_dir_all(web_dir)?;
-    fs::write(web_dir.join(WEB_PACK_STAMP_FILE), format!("{stamp}\n"))?;
-    Ok(())
-}
-
-fn extract_pack(pack_path: &Path, dest: &Path) -> Result<()> {
-    let file = fs::File::open(pack_path)?;
-    let mut archive = zip::ZipArchive::new(file)
-        .map_err(|e| LauncherError::Config(format!("invalid web.pack: {e}")))?;
-
-    for i in 0..archive.len() {
-        let mut entry = archive
-            .by_index(i)
-            .map_err(|e| LauncherError::Config(format!("web.pack entry error: {e}")))?;
-        let name = entry.name().replace('\\', "/");
-        let Some(out_path) = safe_pack_path(dest, &name) else {
-            continue;
-        };
-
-        if entry.is_dir() {
-            fs::create_dir_all(&out_path)?;
-        } else {
-            if let Some(parent) = out_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            let mut out_file = fs::File::create(&out_path)?;
-            let mut buffer = Vec::new();
-            entry.read_to_end(&mut buffer)?;
-            std::io::Write::write_all(&mut out_file, &buffer)?;
-        }
-    }
-
-    Ok(())
-}
-
-fn safe_pack_path(base: &Path, name: &str) -> Option<PathBuf> {
-    let path = Path::new(name);
-    let mut out = PathBuf::from(base);
-    for component in path.components() {
-        match component {
-            Component::Normal(part) => out.push(part),
-            Component::CurDir => {}
-            _ => return None,
-        }
-    }
-    Some(out)
-}
-
-fn resolve_integrity_target(base: &Path, relative: &str) -> Option<PathBuf> {
-    let mut output = PathBuf::from(base);
-    for component in Path::new(relative).components() {
-        match component {
-            Component::Normal(value) => output.push(value),
-            Component::CurDir => {}
-            _ => return None,
-        }
-    }
-    Some(output)
-}
-
-fn sha256_file(path: &Path) -> Result<String> {
-    let mut file = fs::File::open(path)?;
-    let mut hasher = Sha256::new();
-    let mut buffer = [0_u8; 8192];
-
-    loop {
-        let bytes = file.read(&mut buffer)?;
-        if bytes == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes]);
-    }
-
-    Ok(hex::encode(hasher.finalize()))
-}
-
-fn verify_runtime_integrity() -> Result<()> {
-    let exe_dir = std::env::current_exe()
-        .ok()
-        .and_then(|value| value.parent().map(Path::to_path_buf))
-        .ok_or_else(|| LauncherError::Config("unable to resolve executable directory".to_string()))?;
-
-    let checksum_file = exe_dir.join("checksums.sha256");
-    if !checksum_file.exists() {
-        return Err(LauncherError::Config(format!(
-            "integrity manifest missing: {}",
-            checksum_file.display()
-        )));
-    }
-
-    let content = fs::read_to_string(&checksum_file)?;
-    for (index, line) in content.lines().enumerate() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-
-        let parts: Vec<&str> = trimmed.split_whitespace().collect();
-        if parts.len() < 2 {
-            return Err(LauncherError::Config(format!(
-                "invalid integrity manifest line {}",
-                index + 1
-            )));
-        }
-
-        let expected_hash = parts[0].trim().to_ascii_lowercase();
-        let relative = parts[1..].join(" ");
-        let target = resolve_integrity_target(&exe_dir, &relative).ok_or_else(|| {
-            LauncherError::Config(format!(
-                "invalid integrity target path at line {}: {}",
-                index + 1,
-                relative
-            ))
-        })?;
-
-        if !target.exists() {
-            return Err(LauncherError::Config(format!(
-                "integrity target missing: {}",
-                target.display()
-            )));
-        }
-
-        let actual_hash = sha256_file(&target)?;
-        if actual_hash.to_ascii_lowercase() != expected_hash {
-            return Err(LauncherError::Config(format!(
-                "integrity mismatch: {}",
-                relative
-            )));
-        }
-    }
-
-    Ok(())
-}
-
 fn configure_native_guard_env(app: &tauri::AppHandle) {
     let mut candidates: Vec<PathBuf> = Vec::new();
 
@@ -606,20 +388,77 @@ fn configure_native_guard_env(app: &tauri::AppHandle) {
     }
 }
 
+/// Points `LAUNCHER_XDELTA3_PATH` / `LAUNCHER_ARIA2C_PATH` at copies of the
+/// tools shipped next to the executable or in the resource dir (the bundle's
+/// `tools/`, staged by `scripts/stage-bundled-tools.mjs`), so packaged builds
+/// don't depend on PATH. An explicit env value still wins, and a
+/// missing bundle leaves the PATH lookup in place.
+fn configure_bundled_tools_env(app: &tauri::AppHandle) {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Some(parent) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(PathBuf::from))
+    {
+        dirs.push(parent.join("tools"));
+        dirs.push(parent.clone());
+    }
+    if let Ok(resource_dir) = app.path().resource_dir() {
+        dirs.push(resource_dir.join("tools"));
+        dirs.push(resource_dir);
+    }
+
+    for (env_key, binary) in [
+        ("LAUNCHER_XDELTA3_PATH", "xdelta3"),
+        ("LAUNCHER_ARIA2C_PATH", "aria2c"),
+    ] {
+        if std::env::var(env_key).is_ok_and(|value| !value.trim().is_empty()) {
+            continue;
+        }
+        let file_name = format!("{}{}", binary, std::env::consts::EXE_SUFFIX);
+        match dirs
+            .iter()
+            .map(|dir| dir.join(&file_name))
+            .find(|path| path.is_file())
+        {
+            Some(found) => {
+                std::env::set_var(env_key, found.to_string_lossy().to_string());
+                tracing::info!("{} set to bundled {}", env_key, found.display());
+            }
+            None => tracing::info!("no bundled {}; using PATH", binary),
+        }
+    }
+}
+
 fn main() {
     tauri::Builder::default()
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+        .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
             show_main_window(app);
             if let Some(silentui) = app.get_webview_window("silentui") {
                 let _ = silentui.close();
             }
+            // A second launch through an `otoshi://` link hands the link to us.
+            if let Some(url) = deep_link::find_link_arg(&args) {
+                deep_link::handle_url(app, url);
+            }
         }))
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_deep_link::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(commands::overlay::handle_overlay_hotkey)
+                .build(),
+        )
         .on_page_load(|webview, payload| {
             if payload.event() == PageLoadEvent::Finished && webview.label() == "main" {
                 show_main_window(&webview.app_handle());
+                if let Some(startup) = webview.try_state::<StartupPlan>() {
+                    if startup.safe_mode {
+                        let _ = webview
+                            .app_handle()
+                            .emit("safe-mode-active", startup.inner().clone());
+                    }
+                }
                 if let Some(silentui) = webview.get_webview_window("silentui") {
                     let _ = silentui.close();
                 }
@@ -650,6 +489,7 @@ fn main() {
             if let Some(silentui) = app.get_webview_window("silentui") {
                 let _ = silentui.close();
             }
+            commands::debug::apply_saved_api_base(&resolve_data_dir(&handle));
             // Optional: use hosted backend in production when explicitly requested.
             if !cfg!(debug_assertions)
                 && std::env::var("LAUNCHER_API_URL").is_err()
@@ -674,28 +514,36 @@ fn main() {
             // Initialize logging as early as possible so setup failures are recorded.
             let log_dir = utils::paths::resolve_log_dir(&handle);
             logging::init(&log_dir)?;
-            configure_native_guard_env(&handle);
-            verify_runtime_integrity()?;
-            ensure_web_assets(&handle)?;
+            let startup = StartupPlan::from_environment(&resolve_data_dir(&handle));
+            if startup.safe_mode {
+                tracing::warn!(
+                    "starting in safe mode ({:?}); skipping {:?}",
+                    startup.reason,
+                    startup.skipped
+                );
+            }
+            if startup.runs(safe_mode::SUBSYSTEM_NATIVE_GUARD) {
+                configure_native_guard_env(&handle);
+            }
+            configure_bundled_tools_env(&handle);
+            if !startup.runs(safe_mode::SUBSYSTEM_P2P) {
+                std::env::set_var("OTOSHI_P2P_ENABLED", "0");
+            }
+            let deferred_integrity = runtime_integrity::verify_critical()?;
+            match web_assets::check(&handle) {
+                Ok(Some(pending)) => web_assets::spawn_restore(&handle, pending),
+                Ok(None) => {}
+                Err(err) => tracing::warn!("web asset check failed: {}", err),
+            }
 
-            // Register deep link handler for OAuth callbacks
+            // Register deep link handler for OAuth callbacks and install links
             #[cfg(desktop)]
             {
                 use tauri_plugin_deep_link::DeepLinkExt;
                 let handle_clone = handle.clone();
                 app.deep_link().on_open_url(move |event| {
-                    let urls = event.urls();
-                    for url in urls {
-                        let url_str = url.as_str();
-                        tracing::info!("Deep link received: {}", url_str);
-                        // Emit event to frontend for OAuth handling
-                        if url_str.starts_with("otoshi://oauth")
-                            || url_str.starts_with("otoshi://callback")
-                        {
-                            if let Err(e) = handle_clone.emit("oauth-callback", url_str) {
-                                tracing::error!("Failed to emit oauth-callback event: {}", e);
-                            }
-                        }
+                    for url in event.urls() {
+                        deep_link::handle_url(&handle_clone, url.as_str());
                     }
                 });
             }
@@ -705,16 +553,74 @@ fn main() {
 
             // Start the bundled backend (if present) for the packaged desktop app.
             // If LAUNCHER_API_URL is set, we assume user manages backend themselves.
-            let backend_child = backend_sidecar::spawn_backend(&handle)?;
+            let backend_child = if startup.runs(safe_mode::SUBSYSTEM_BACKEND) {
+                backend_sidecar::spawn_backend(&handle)?
+            } else {
+                None
+            };
 
             let state = Arc::new(build_state(&handle)?);
-            spawn_locale_prefetch_worker(state.clone());
+            state.launcher_updates.finish_previous();
+            let auth_handle = handle.clone();
+            state.auth.on_session_expired(move || {
+                let _ = auth_handle.emit("auth-expired", ());
+            });
+            if startup.runs(safe_mode::SUBSYSTEM_BACKGROUND_WORKERS) {
+                spawn_locale_prefetch_worker(state.clone());
+                state.telemetry.spawn_flush_worker();
+                state.achievements.spawn_flush_worker();
+                state.update_checks.spawn_worker();
+                state.auth.spawn_refresh_worker();
+                let connectivity_handle = handle.clone();
+                state.connectivity.spawn_monitor(move |status| {
+                    let _ = connectivity_handle.emit("connectivity-changed", status);
+                });
+                let trade_handle = handle.clone();
+                state.inventory.spawn_expiry_watcher(move |trade| {
+                    let _ = trade_handle.emit("trade-expired", trade);
+                });
+                let remote_state = state.clone();
+                state.remote_downloads.spawn_watcher(move |download| {
+                    commands::remote::start_ready_remote_download(remote_state.clone(), download)
+                });
+            }
+            commands::overlay::register_saved_overlay_hotkey(&handle, &state);
+            handle.listen_any("download-completed-summary", |_| {
+                commands::system::invalidate_installed_games();
+            });
             app.manage(state);
 
+            // Links the launcher was started with are handled once state exists.
+            #[cfg(desktop)]
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
+                    for url in urls {
+                        deep_link::handle_url(&handle, url.as_str());
+                    }
+                }
+            }
+            app.manage(startup);
+            runtime_integrity::spawn_deferred_check(&handle, deferred_integrity);
+
+            // A startup only counts as clean once the app has stayed up for a while.
+            let data_dir = resolve_data_dir(&handle);
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(safe_mode::STABLE_AFTER).await;
+                safe_mode::mark_stable(&data_dir);
+            });
+
             // Keep the backend process alive for the lifetime of the app.
             // The BackendProcess guard will kill it when the app exits (Drop).
             if let Some(child) = backend_child {
                 app.manage(backend_sidecar::BackendProcess::new(child));
+                let supervisor_handle = handle.clone();
+                backend_sidecar::BackendProcess::supervise(&handle, move || {
+                    supervisor_handle
+                        .try_state::<AppLifecycle>()
+                        .map(|lifecycle| lifecycle.quitting.load(Ordering::SeqCst))
+                        .unwrap_or(true)
+                });
             }
             Ok(())
         })
@@ -728,19 +634,29 @@ fn main() {
             commands::game::get_library,
             commands::game::get_game_details,
             commands::game::get_cached_library,
+            commands::game::get_available_updates,
+            commands::game::check_game_updates,
+            commands::game::check_library_updates,
+            commands::game::get_library_updates,
             commands::game::update_playtime,
             commands::game::get_game_launch_pref,
             commands::game::set_game_launch_pref,
+            commands::game::get_verify_before_launch,
+            commands::game::set_verify_before_launch,
             commands::game::launch_game,
             commands::game::get_running_games,
             commands::game::stop_game,
             commands::download::start_download,
             commands::download::start_steam_download,
+            commands::download::import_local_install,
             commands::download::pause_download,
             commands::download::resume_download,
             commands::download::cancel_download,
+            commands::download::cancel_verify,
             commands::download::get_download_progress,
             commands::download::get_cached_downloads,
+            commands::download::run_post_install,
+            commands::download::get_manifest_info,
             commands::download_v2::start_download_v2,
             commands::download_v2::control_download_v2,
             commands::download_v2::get_download_state_v2,
@@ -749,11 +665,56 @@ fn main() {
             commands::crack::get_crack_progress,
             commands::crack::cancel_crack_download,
             commands::crack::uninstall_crack,
+            commands::crack::restore_crack_backup,
             commands::crack::is_crack_installed,
             commands::crack::verify_game_integrity_after_uninstall,
+            commands::crack::get_crack_extract_workers,
+            commands::crack::set_crack_extract_workers,
             commands::system::build_local_manifest,
             commands::system::set_download_limit,
             commands::system::get_default_install_root,
+            commands::system::set_default_install_root,
+            commands::system::list_library_folders,
+            commands::system::add_library_folder,
+            commands::system::remove_library_folder,
+            commands::system::scan_library_folder,
+            commands::system::scan_installed_games,
+            commands::system::export_settings,
+            commands::system::import_settings,
+            commands::system::get_peer_stats,
+            commands::system::get_p2p_status,
+            commands::system::get_connectivity_status,
+            commands::system::get_api_client_config,
+            commands::system::set_api_client_config,
+            commands::system::set_p2p_enabled,
+            commands::system::set_p2p_upload_limit,
+            commands::system::get_peer_source_policy,
+            commands::system::set_peer_source_policy,
+            commands::system::get_write_strategy,
+            commands::system::set_write_strategy,
+            commands::system::get_extract_workers,
+            commands::system::set_extract_workers,
+            commands::system::get_storage_options,
+            commands::system::set_storage_options,
+            commands::system::get_bandwidth_usage,
+            commands::system::get_data_cap,
+            commands::system::set_data_cap,
+            commands::system::override_data_cap,
+            commands::system::get_download_http3,
+            commands::system::set_download_http3,
+            commands::system::get_post_install_allowed,
+            commands::system::set_post_install_allowed,
+            commands::system::get_save_backup_dir,
+            commands::system::set_save_backup_dir,
+            commands::system::set_telemetry_enabled,
+            commands::system::get_all_settings,
+            commands::system::apply_settings,
+            commands::system::reset_install_id,
+            commands::system::backup_database,
+            commands::system::restore_database,
+            commands::system::vacuum_database,
+            commands::system::prune_download_state,
+            commands::system::benchmark_disk,
             commands::system::artwork_get,
             commands::system::artwork_prefetch,
             commands::system::artwork_release,
@@ -764,34 +725,56 @@ fn main() {
             commands::system::runtime_tuning_rollback,
             commands::security::get_hardware_id,
             commands::security::validate_license,
+            commands::security::hardware_id_changed,
+            commands::security::request_license_rebind,
             commands::security_v2::inspect_security_v2,
             commands::security_v2::enforce_security_v2,
             commands::social::unlock_achievement,
             commands::social::list_achievements,
             commands::social::upload_cloud_save,
             commands::social::fetch_cloud_save,
+            commands::social::upload_cloud_save_incremental,
+            commands::social::mark_cloud_save_synced,
+            commands::social::resolve_cloud_save,
+            commands::social::get_save_sync_after_play,
+            commands::social::set_save_sync_after_play,
             commands::workshop::list_workshop_items,
             commands::workshop::list_workshop_versions,
+            commands::workshop::update_workshop_item,
             commands::workshop::list_workshop_subscriptions,
             commands::workshop::subscribe_workshop_item,
             commands::workshop::unsubscribe_workshop_item,
             commands::workshop::list_local_workshop_items,
             commands::workshop::sync_workshop_to_game,
+            commands::workshop::remove_workshop_from_game,
+            commands::workshop::set_workshop_storage_dir,
+            commands::workshop::verify_workshop_item,
+            commands::workshop::detect_workshop_conflicts,
             commands::discovery::get_discovery_queue,
             commands::discovery::refresh_discovery_queue,
             commands::discovery::get_similar_games,
             commands::inventory::list_inventory,
+            commands::inventory::sync_inventory,
+            commands::inventory::refresh_inventory,
             commands::inventory::card_drop,
             commands::inventory::craft_badge,
+            commands::inventory::preview_craft_badge,
+            commands::inventory::can_craft_badge,
             commands::inventory::list_trades,
             commands::inventory::create_trade,
             commands::inventory::accept_trade,
             commands::inventory::decline_trade,
             commands::inventory::cancel_trade,
+            commands::inventory::get_trade_expiry_hours,
+            commands::inventory::set_trade_expiry_hours,
+            commands::launcher_update::check_launcher_update,
+            commands::launcher_update::download_launcher_update,
             commands::remote::list_remote_downloads,
             commands::remote::queue_remote_download,
             commands::remote::update_remote_download_status,
             commands::overlay::toggle_overlay,
+            commands::overlay::get_overlay_hotkey,
+            commands::overlay::set_overlay_hotkey,
             commands::overlay::set_overlay_visible,
             commands::overlay::is_overlay_visible,
             commands::overlay::capture_overlay_screenshot,
@@ -801,6 +784,7 @@ fn main() {
             commands::streaming::set_streaming_offer,
             commands::streaming::set_streaming_answer,
             commands::streaming::add_streaming_ice_candidate,
+            commands::streaming::restart_streaming_ice,
             commands::policy::get_privacy_policy,
             commands::policy::get_terms_of_service,
             commands::distribute::get_distribute_stats,
@@ -810,6 +794,7 @@ fn main() {
             commands::properties::get_game_install_info,
             commands::properties::verify_game_files,
             commands::properties::uninstall_game,
+            commands::properties::uninstall_game_with_backup,
             commands::properties::move_game_folder,
             commands::properties::sync_cloud_saves,
             commands::properties::properties_get,
@@ -817,13 +802,24 @@ fn main() {
             commands::properties::save_sync_preview,
             commands::properties::save_sync_apply,
             commands::properties::open_folder,
+            commands::properties::repair_game,
             commands::self_heal::run_self_heal_scan_v2,
             commands::self_heal::apply_self_heal_v2,
+            commands::self_heal::list_integrity_events,
+            commands::self_heal::get_volatile_paths,
+            commands::self_heal::set_volatile_paths,
             commands::debug::get_app_logs,
+            commands::debug::query_logs,
+            commands::debug::get_log_retention,
+            commands::debug::set_log_retention,
             commands::debug::get_backend_status,
             commands::debug::open_logs_folder,
             commands::debug::toggle_devtools,
             commands::debug::get_runtime_api_base,
+            commands::debug::set_runtime_api_base,
+            commands::debug::get_safe_mode_status,
+            commands::debug::set_safe_mode_next_start,
+            commands::debug::restore_web_assets,
             commands::lua::get_lua_files_path,
             commands::lua::verify_lua_files,
             commands::lua::get_lua_files_count,
@@ -831,7 +827,22 @@ fn main() {
             commands::lua::read_lua_file,
             commands::lua::list_lua_files,
         ])
-        .run(tauri::generate_context!())
+        .build(tauri::generate_context!())
+        .map(|app| {
+            app.run(|app, event| {
+                // A staged launcher update takes over on the next start,
+                // however the launcher was closed.
+                if let RunEvent::Exit = event {
+                    // Quitting cleanly, however soon, isn't a failed start.
+                    safe_mode::mark_stable(&resolve_data_dir(app));
+                    if let Some(state) = app.try_state::<Arc<AppState>>() {
+                        if let Err(err) = state.launcher_updates.apply_staged() {
+                            tracing::warn!("staged launcher update not applied: {}", err);
+                        }
+                    }
+                }
+            })
+        })
         .unwrap_or_else(|error| {
             tracing::error!("error while running tauri application: {error}");
             eprintln!("error while running tauri application: {error}");
@@ -14,6 +14,8 @@ pub struct AuthResponse {
     pub token_type: String,
     pub user: UserProfile,
     pub refresh_token: Option<String>,
+    /// Seconds until `access_token` expires.
+    pub expires_in: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -65,6 +67,10 @@ pub struct DownloadTask {
     pub written_bytes: i64,
     #[serde(default)]
     pub remaining_bytes: i64,
+    #[serde(default)]
+    pub engine: Option<String>,
+    #[serde(default)]
+    pub method: Option<String>,
     pub game: Game,
 }
 
@@ -92,6 +98,129 @@ pub struct TelemetryEvent {
     pub payload: serde_json::Value,
 }
 
+/// An achievement unlocked while the API was unreachable.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PendingAchievement {
+    pub game_id: String,
+    pub achievement_key: String,
+    pub unlocked_at: i64,
+    pub attempts: i64,
+}
+
+/// A write made while the backend was unreachable, replayed once it's back.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QueuedRequest {
+    pub id: i64,
+    pub method: String,
+    pub path: String,
+    pub body: serde_json::Value,
+    pub created_at: i64,
+}
+
+/// State of a game's saves at the last successful cloud sync.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CloudSaveSnapshot {
+    pub game_id: String,
+    pub local_hash: String,
+    pub remote_version: String,
+    pub synced_at: i64,
+}
+
+/// Result of the last background update check for an installed game.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GameUpdateStatus {
+    pub game_id: String,
+    pub slug: String,
+    pub installed_version: String,
+    pub latest_version: String,
+    pub etag: Option<String>,
+    pub update_available: bool,
+    pub checked_at: i64,
+    /// Files that are new or changed in the latest manifest; zero without an
+    /// update.
+    pub changed_files: usize,
+    /// Size of those files, i.e. roughly what the update downloads.
+    pub delta_bytes: u64,
+}
+
+/// An installed game whose remote manifest differs from its local one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LibraryUpdate {
+    pub game_id: String,
+    pub slug: String,
+    pub installed_version: String,
+    pub installed_build_id: String,
+    pub latest_version: String,
+    pub latest_build_id: String,
+    /// Files that are new or changed in the remote manifest.
+    pub changed_files: usize,
+    /// Size of those files, i.e. roughly what the update downloads.
+    pub delta_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LibraryUpdateReport {
+    pub checked_at: i64,
+    pub updates: Vec<LibraryUpdate>,
+}
+
+/// Expected size and hash of one file in a synced workshop item.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WorkshopFileRecord {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// A file in `file_index_v2` that last passed a full SHA-256 check, with the
+/// blake3 hash taken in the same pass for quick change detection.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FileIndexEntry {
+    pub relative_path: String,
+    pub size_bytes: u64,
+    pub modified_at: i64,
+    pub fast_hash: String,
+    pub canonical_hash: String,
+}
+
+/// Extra folder games can be installed into, next to the default root.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LibraryFolder {
+    pub path: String,
+    pub added_at: i64,
+}
+
+/// Downloaded bytes by where they came from. Only `network_bytes` counts
+/// against a metered connection; peer bytes come from the LAN and depotcache
+/// bytes from disk.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BandwidthUsageTotals {
+    pub network_bytes: u64,
+    pub peer_bytes: u64,
+    pub depotcache_bytes: u64,
+}
+
+impl BandwidthUsageTotals {
+    pub fn is_empty(&self) -> bool {
+        self.network_bytes == 0 && self.peer_bytes == 0 && self.depotcache_bytes == 0
+    }
+
+    pub fn add(&mut self, other: &BandwidthUsageTotals) {
+        self.network_bytes = self.network_bytes.saturating_add(other.network_bytes);
+        self.peer_bytes = self.peer_bytes.saturating_add(other.peer_bytes);
+        self.depotcache_bytes = self.depotcache_bytes.saturating_add(other.depotcache_bytes);
+    }
+}
+
+/// One game's usage on one local calendar day (`YYYY-MM-DD`).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BandwidthUsageRecord {
+    pub game_id: String,
+    pub date: String,
+    #[serde(flatten)]
+    pub bytes: BandwidthUsageTotals,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LocalGame {
     pub id: String,
@@ -129,6 +258,10 @@ pub struct GameLaunchPref {
     pub game_id: String,
     pub require_admin: bool,
     pub ask_every_time: bool,
+    /// Size-check the install before launching; `None` follows the global
+    /// `verify_before_launch` setting.
+    #[serde(default)]
+    pub verify_before_launch: Option<bool>,
     pub updated_at: i64,
 }
 
@@ -153,6 +286,11 @@ pub struct DownloadState {
     pub install_dir: String,
     pub manifest_json: String,
     pub updated_at: i64,
+    /// Transfer engine the last run used (`aria2c-rpc`, `aria2c`, `reqwest`).
+    #[serde(default)]
+    pub engine: Option<String>,
+    #[serde(default)]
+    pub method: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
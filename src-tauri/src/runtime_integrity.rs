@@ -0,0 +1,381 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter};
+
+use crate::errors::{LauncherError, Result};
+
+pub(crate) const CHECKSUM_FILE: &str = "checksums.sha256";
+/// Base64 Ed25519 signature over the exact bytes of `CHECKSUM_FILE`.
+pub(crate) const SIGNATURE_FILE: &str = "checksums.sha256.sig";
+/// SPKI PEM of the key release builds sign the checksum file with, embedded
+/// at build time. Release builds don't compile without it; the packaging
+/// step `npm run integrity:sign` signs with the matching private key.
+#[cfg(not(debug_assertions))]
+const INTEGRITY_PUBLIC_KEY_PEM: Option<&str> = Some(env!(
+    "OTOSHI_INTEGRITY_PUBLIC_KEY_PEM",
+    "release builds must embed OTOSHI_INTEGRITY_PUBLIC_KEY_PEM"
+));
+#[cfg(debug_assertions)]
+const INTEGRITY_PUBLIC_KEY_PEM: Option<&str> = option_env!("OTOSHI_INTEGRITY_PUBLIC_KEY_PEM");
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo; the raw key follows it.
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+const MAX_WORKERS: usize = 8;
+/// Code the launcher loads, plus `web.pack`: the UI is extracted from it on
+/// startup, before any deferred check could catch a tampered copy.
+const CRITICAL_EXTENSIONS: [&str; 6] = ["exe", "dll", "sys", "so", "dylib", "pack"];
+
+/// One line of `checksums.sha256`.
+#[derive(Clone, Debug)]
+pub struct RuntimeChecksum {
+    relative: String,
+    expected_hash: String,
+    target: PathBuf,
+}
+
+/// Verifies the launcher's own files against `checksums.sha256`, hashing on a
+/// bounded worker pool. Executables and libraries must pass before startup
+/// continues; the remaining entries are returned for `spawn_deferred_check`
+/// unless `LAUNCHER_INTEGRITY_DEFER=0` asks for everything up front.
+pub fn verify_critical() -> Result<Vec<RuntimeChecksum>> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|value| value.parent().map(Path::to_path_buf))
+        .ok_or_else(|| {
+            LauncherError::Config("unable to resolve executable directory".to_string())
+        })?;
+
+    let checksum_file = exe_dir.join(CHECKSUM_FILE);
+    if !checksum_file.exists() {
+        return Err(LauncherError::Config(format!(
+            "integrity manifest missing: {}",
+            checksum_file.display()
+        )));
+    }
+
+    let content = fs::read(&checksum_file)?;
+    verify_manifest_signature(&exe_dir, &content)?;
+    let content = String::from_utf8(content)
+        .map_err(|_| LauncherError::Config("integrity manifest is not valid UTF-8".to_string()))?;
+    let entries = parse_checksums(&exe_dir, &content)?;
+    let defer = std::env::var("LAUNCHER_INTEGRITY_DEFER")
+        .map(|value| !matches!(value.trim(), "0" | "false" | "off"))
+        .unwrap_or(true);
+    let (critical, deferred): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|entry| !defer || is_critical(&entry.relative));
+
+    let started = Instant::now();
+    let critical_count = critical.len();
+    verify_entries(critical)?;
+    tracing::info!(
+        "runtime integrity verified {} critical files in {} ms ({} deferred)",
+        critical_count,
+        started.elapsed().as_millis(),
+        deferred.len()
+    );
+    Ok(deferred)
+}
+
+/// Checks the non-critical entries after the window is up. A failure is
+/// logged and reported to the UI as `runtime-integrity-failed`.
+pub fn spawn_deferred_check(app: &AppHandle, deferred: Vec<RuntimeChecksum>) {
+    if deferred.is_empty() {
+        return;
+    }
+    let app = app.clone();
+    thread::spawn(move || {
+        let count = deferred.len();
+        match verify_entries(deferred) {
+            Ok(()) => tracing::info!("runtime integrity verified {} deferred files", count),
+            Err(err) => {
+                tracing::error!("deferred runtime integrity check failed: {}", err);
+                let _ = app.emit("runtime-integrity-failed", err.to_string());
+            }
+        }
+    });
+}
+
+/// Checks `SIGNATURE_FILE` against the embedded key before the manifest is
+/// trusted. Release builds refuse to start without a valid signature; debug
+/// builds only warn when the key or the signature is absent.
+fn verify_manifest_signature(exe_dir: &Path, content: &[u8]) -> Result<()> {
+    let signature_file = exe_dir.join(SIGNATURE_FILE);
+    let signature = match fs::read_to_string(&signature_file) {
+        Ok(value) => Some(value),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err.into()),
+    };
+    let (Some(public_key_pem), Some(signature)) = (INTEGRITY_PUBLIC_KEY_PEM, signature) else {
+        if cfg!(debug_assertions) {
+            tracing::warn!("integrity manifest signature not checked in this debug build");
+            return Ok(());
+        }
+        return Err(LauncherError::Crypto(format!(
+            "integrity manifest signature missing: {}",
+            signature_file.display()
+        )));
+    };
+    verify_signature(public_key_pem, content, &signature)
+}
+
+/// Checks a detached signature made with the same key, such as the one on a
+/// launcher update. Debug builds without an embedded key skip the check.
+pub fn verify_signed(content: &[u8], signature: &str) -> Result<()> {
+    match INTEGRITY_PUBLIC_KEY_PEM {
+        Some(public_key_pem) => verify_signature(public_key_pem, content, signature),
+        None if cfg!(debug_assertions) => {
+            tracing::warn!("no integrity public key embedded; signature not checked");
+            Ok(())
+        }
+        None => Err(LauncherError::Crypto(
+            "no integrity public key embedded".to_string(),
+        )),
+    }
+}
+
+/// Checks a release's own signed `checksums.sha256` before it replaces the
+/// installed one, and that it lists `relative` with the hash `sha256`.
+pub fn verify_release_manifest(
+    content: &[u8],
+    signature: &str,
+    relative: &str,
+    sha256: &str,
+) -> Result<()> {
+    verify_signed(content, signature)?;
+    let content = std::str::from_utf8(content).map_err(|_| {
+        LauncherError::Config("release integrity manifest is not valid UTF-8".to_string())
+    })?;
+    let listed = parse_checksums(Path::new(""), content)?
+        .iter()
+        .any(|entry| {
+            entry.relative == relative && entry.expected_hash == sha256.trim().to_ascii_lowercase()
+        });
+    if !listed {
+        return Err(LauncherError::Integrity(format!(
+            "release integrity manifest does not list {relative} with the release hash"
+        )));
+    }
+    Ok(())
+}
+
+fn verify_signature(public_key_pem: &str, content: &[u8], signature: &str) -> Result<()> {
+    let public_key = ed25519_public_key(public_key_pem)?;
+    let signature = base64::engine::general_purpose::STANDARD
+        .decode(signature.trim())
+        .map_err(|err| LauncherError::Crypto(format!("invalid manifest signature: {err}")))?;
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(content, &signature)
+        .map_err(|_| LauncherError::Crypto("integrity manifest signature mismatch".to_string()))
+}
+
+fn ed25519_public_key(pem: &str) -> Result<Vec<u8>> {
+    let body: String = pem
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let der = base64::engine::general_purpose::STANDARD
+        .decode(body)
+        .map_err(|err| LauncherError::Crypto(format!("invalid integrity public key: {err}")))?;
+    match der.strip_prefix(&ED25519_SPKI_PREFIX[..]) {
+        Some(key) if key.len() == 32 => Ok(key.to_vec()),
+        _ => Err(LauncherError::Crypto(
+            "integrity public key is not an Ed25519 key".to_string(),
+        )),
+    }
+}
+
+fn parse_checksums(base: &Path, content: &str) -> Result<Vec<RuntimeChecksum>> {
+    let mut entries = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let parts: Vec<&str> = trimmed.split_whitespace().collect();
+        if parts.len() < 2 {
+            return Err(LauncherError::Config(format!(
+                "invalid integrity manifest line {}",
+                index + 1
+            )));
+        }
+
+        let relative = parts[1..].join(" ");
+        let target = resolve_integrity_target(base, &relative).ok_or_else(|| {
+            LauncherError::Config(format!(
+                "invalid integrity target path at line {}: {}",
+                index + 1,
+                relative
+            ))
+        })?;
+        entries.push(RuntimeChecksum {
+            expected_hash: parts[0].trim().to_ascii_lowercase(),
+            relative,
+            target,
+        });
+    }
+    Ok(entries)
+}
+
+fn is_critical(relative: &str) -> bool {
+    Path::new(relative)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            CRITICAL_EXTENSIONS
+                .iter()
+                .any(|critical| ext.eq_ignore_ascii_case(critical))
+        })
+}
+
+fn resolve_workers(entries: usize) -> usize {
+    let available = thread::available_parallelism()
+        .map(|value| value.get())
+        .unwrap_or(4);
+    std::env::var("LAUNCHER_INTEGRITY_WORKERS")
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(available)
+        .clamp(1, MAX_WORKERS)
+        .min(entries.max(1))
+}
+
+/// Hashes `entries` across worker threads and returns the first failure in
+/// manifest order.
+fn verify_entries(entries: Vec<RuntimeChecksum>) -> Result<()> {
+    let worker_count = resolve_workers(entries.len());
+    let entries = Arc::new(entries);
+    let next_index = Arc::new(AtomicUsize::new(0));
+    let failures = Arc::new(Mutex::new(Vec::<(usize, String)>::new()));
+
+    let mut workers = Vec::new();
+    for _ in 0..worker_count {
+        let entries_ref = Arc::clone(&entries);
+        let index_ref = Arc::clone(&next_index);
+        let failures_ref = Arc::clone(&failures);
+        workers.push(thread::spawn(move || loop {
+            let index = index_ref.fetch_add(1, Ordering::SeqCst);
+            if index >= entries_ref.len() {
+                break;
+            }
+            if let Err(reason) = verify_entry(&entries_ref[index]) {
+                if let Ok(mut guard) = failures_ref.lock() {
+                    guard.push((index, reason));
+                }
+            }
+        }));
+    }
+
+    for handle in workers {
+        let _ = handle.join();
+    }
+
+    let mut failures = failures
+        .lock()
+        .map_err(|_| LauncherError::Config("integrity results lock poisoned".to_string()))?
+        .clone();
+    failures.sort_by_key(|(index, _)| *index);
+    match failures.into_iter().next() {
+        Some((_, reason)) => Err(LauncherError::Config(reason)),
+        None => Ok(()),
+    }
+}
+
+fn verify_entry(entry: &RuntimeChecksum) -> std::result::Result<(), String> {
+    if !entry.target.exists() {
+        return Err(format!(
+            "integrity target missing: {}",
+            entry.target.display()
+        ));
+    }
+    let actual_hash = sha256_file(&entry.target).map_err(|err| err.to_string())?;
+    if actual_hash != entry.expected_hash {
+        return Err(format!("integrity mismatch: {}", entry.relative));
+    }
+    Ok(())
+}
+
+fn resolve_integrity_target(base: &Path, relative: &str) -> Option<PathBuf> {
+    let mut output = PathBuf::from(base);
+    for component in Path::new(relative).components() {
+        match component {
+            Component::Normal(value) => output.push(value),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(output)
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0_u8; 1024 * 1024];
+
+    loop {
+        let bytes = file.read(&mut buffer)?;
+        if bytes == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_checksums_and_flags_critical_files() {
+        let base = Path::new("/opt/otoshi");
+        let entries = parse_checksums(
+            base,
+            "ABCD  otoshi-launcher.exe\n\nef01  resources/backend/app data.json\n",
+        )
+        .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].expected_hash, "abcd");
+        assert!(is_critical(&entries[0].relative));
+        assert_eq!(entries[1].relative, "resources/backend/app data.json");
+        assert!(!is_critical(&entries[1].relative));
+        assert!(is_critical("resources/web.pack"));
+        assert!(is_critical("web.PACK"));
+        assert!(parse_checksums(base, "ffff  ../escape.dll").is_err());
+    }
+
+    #[test]
+    fn manifest_signature_must_match_the_content() {
+        use ring::signature::{Ed25519KeyPair, KeyPair};
+
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let engine = base64::engine::general_purpose::STANDARD;
+        let der = [&ED25519_SPKI_PREFIX[..], key_pair.public_key().as_ref()].concat();
+        let pem = format!(
+            "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+            engine.encode(der)
+        );
+        let content = b"abcd  otoshi-launcher.exe\n";
+        let signature = engine.encode(key_pair.sign(content).as_ref());
+
+        assert!(verify_signature(&pem, content, &signature).is_ok());
+        assert!(verify_signature(&pem, b"ffff  otoshi-launcher.exe\n", &signature).is_err());
+        assert!(verify_signature(&pem, content, "not base64").is_err());
+    }
+}
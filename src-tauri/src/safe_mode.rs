@@ -0,0 +1,246 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+
+const SAFE_MODE_ARG: &str = "--safe-mode";
+const SAFE_MODE_MARKER_FILE: &str = "safe_mode.next";
+const STARTUP_ATTEMPTS_FILE: &str = "startup_attempts";
+const DEFAULT_CRASH_LOOP_THRESHOLD: u32 = 3;
+
+/// How long the app has to stay up before a startup counts as clean.
+pub const STABLE_AFTER: Duration = Duration::from_secs(20);
+
+pub const SUBSYSTEM_NATIVE_GUARD: &str = "native_guard";
+pub const SUBSYSTEM_P2P: &str = "p2p";
+pub const SUBSYSTEM_BACKEND: &str = "backend";
+pub const SUBSYSTEM_BACKGROUND_WORKERS: &str = "background_workers";
+
+const OPTIONAL_SUBSYSTEMS: [&str; 4] = [
+    SUBSYSTEM_NATIVE_GUARD,
+    SUBSYSTEM_P2P,
+    SUBSYSTEM_BACKEND,
+    SUBSYSTEM_BACKGROUND_WORKERS,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafeModeReason {
+    CommandLine,
+    Environment,
+    Requested,
+    CrashLoop,
+}
+
+/// Which optional subsystems setup should bring up. Managed as app state so
+/// the UI can show what was skipped.
+#[derive(Clone, Debug, Serialize)]
+pub struct StartupPlan {
+    pub safe_mode: bool,
+    pub reason: Option<SafeModeReason>,
+    pub skipped: Vec<&'static str>,
+}
+
+impl StartupPlan {
+    pub fn resolve(args: &[String], env_flag: bool, requested: bool, crash_loop: bool) -> Self {
+        let reason = if args.iter().any(|arg| arg == SAFE_MODE_ARG) {
+            Some(SafeModeReason::CommandLine)
+        } else if env_flag {
+            Some(SafeModeReason::Environment)
+        } else if crash_loop {
+            Some(SafeModeReason::CrashLoop)
+        } else if requested {
+            Some(SafeModeReason::Requested)
+        } else {
+            None
+        };
+        let skipped = if reason.is_some() {
+            OPTIONAL_SUBSYSTEMS.to_vec()
+        } else {
+            Vec::new()
+        };
+        Self {
+            safe_mode: reason.is_some(),
+            reason,
+            skipped,
+        }
+    }
+
+    /// Resolves the plan for this process, consumes a pending "safe mode next
+    /// start" request and records this run as an unfinished startup attempt.
+    pub fn from_environment(data_dir: &Path) -> Self {
+        let args = std::env::args().collect::<Vec<_>>();
+        let env_flag = std::env::var("LAUNCHER_SAFE_MODE")
+            .map(|value| matches!(value.trim(), "1" | "true" | "yes" | "on"))
+            .unwrap_or(false);
+        let requested = take_next_start_request(data_dir);
+
+        let threshold = std::env::var("LAUNCHER_CRASH_LOOP_THRESHOLD")
+            .ok()
+            .and_then(|value| value.trim().parse::<u32>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(DEFAULT_CRASH_LOOP_THRESHOLD);
+        let mut attempts = StartupAttempts::load(data_dir);
+        let crash_loop = attempts.begin(threshold);
+        if let Err(err) = attempts.store(data_dir) {
+            tracing::warn!("failed to record startup attempt: {}", err);
+        }
+        if crash_loop {
+            tracing::warn!(
+                "{} unfinished startups in a row, enabling safe mode",
+                attempts.unfinished - 1
+            );
+        }
+
+        Self::resolve(&args, env_flag, requested, crash_loop)
+    }
+
+    pub fn runs(&self, subsystem: &str) -> bool {
+        !self.skipped.contains(&subsystem)
+    }
+}
+
+/// Count of startups that never reached a stable running state. Incremented
+/// at the start of setup and cleared by `mark_stable`, so a crash during
+/// startup leaves it raised.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StartupAttempts {
+    pub unfinished: u32,
+}
+
+impl StartupAttempts {
+    pub fn load(data_dir: &Path) -> Self {
+        let unfinished = fs::read_to_string(data_dir.join(STARTUP_ATTEMPTS_FILE))
+            .ok()
+            .and_then(|value| value.trim().parse::<u32>().ok())
+            .unwrap_or(0);
+        Self { unfinished }
+    }
+
+    pub fn store(&self, data_dir: &Path) -> std::io::Result<()> {
+        fs::create_dir_all(data_dir)?;
+        fs::write(
+            data_dir.join(STARTUP_ATTEMPTS_FILE),
+            format!("{}\n", self.unfinished),
+        )
+    }
+
+    /// Records a new attempt. Returns true when the previous `threshold`
+    /// attempts all ended before reaching a stable state.
+    pub fn begin(&mut self, threshold: u32) -> bool {
+        let tripped = self.unfinished >= threshold;
+        self.unfinished = self.unfinished.saturating_add(1);
+        tripped
+    }
+
+    pub fn mark_stable(&mut self) {
+        self.unfinished = 0;
+    }
+}
+
+/// Clears the unfinished-startup counter once the app is running normally.
+pub fn mark_stable(data_dir: &Path) {
+    let mut attempts = StartupAttempts::load(data_dir);
+    attempts.mark_stable();
+    if let Err(err) = attempts.store(data_dir) {
+        tracing::warn!("failed to reset startup attempts: {}", err);
+    }
+}
+
+fn marker_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SAFE_MODE_MARKER_FILE)
+}
+
+/// Asks the next launch to start in safe mode.
+pub fn request_next_start(data_dir: &Path, enabled: bool) -> std::io::Result<()> {
+    let path = marker_path(data_dir);
+    if enabled {
+        fs::create_dir_all(data_dir)?;
+        fs::write(path, b"1\n")
+    } else if path.exists() {
+        fs::remove_file(path)
+    } else {
+        Ok(())
+    }
+}
+
+fn take_next_start_request(data_dir: &Path) -> bool {
+    let path = marker_path(data_dir);
+    if !path.exists() {
+        return false;
+    }
+    let _ = fs::remove_file(&path);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_mode_skips_optional_subsystems() {
+        let normal = StartupPlan::resolve(&["otoshi".to_string()], false, false, false);
+        assert!(!normal.safe_mode);
+        assert!(normal.runs(SUBSYSTEM_BACKEND));
+        assert!(normal.runs(SUBSYSTEM_P2P));
+
+        let flagged = StartupPlan::resolve(
+            &["otoshi".to_string(), "--safe-mode".to_string()],
+            false,
+            true,
+            false,
+        );
+        assert_eq!(flagged.reason, Some(SafeModeReason::CommandLine));
+        for subsystem in OPTIONAL_SUBSYSTEMS {
+            assert!(!flagged.runs(subsystem));
+        }
+
+        let requested = StartupPlan::resolve(&[], false, true, false);
+        assert_eq!(requested.reason, Some(SafeModeReason::Requested));
+        assert!(!requested.runs(SUBSYSTEM_NATIVE_GUARD));
+    }
+
+    #[test]
+    fn next_start_request_is_consumed_once() {
+        let dir = std::env::temp_dir().join(format!("otoshi-safe-mode-{}", uuid::Uuid::new_v4()));
+        request_next_start(&dir, true).expect("write marker");
+
+        assert!(StartupPlan::resolve(&[], false, take_next_start_request(&dir), false).safe_mode);
+        assert!(!StartupPlan::resolve(&[], false, take_next_start_request(&dir), false).safe_mode);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn crash_loop_trips_after_threshold_and_resets_when_stable() {
+        let mut attempts = StartupAttempts::default();
+        assert!(!attempts.begin(3));
+        assert!(!attempts.begin(3));
+        assert!(!attempts.begin(3));
+        // Fourth launch after three startups that never became stable.
+        assert!(attempts.begin(3));
+        assert!(attempts.begin(3));
+
+        attempts.mark_stable();
+        assert!(!attempts.begin(3));
+        assert_eq!(attempts.unfinished, 1);
+
+        let plan = StartupPlan::resolve(&[], false, false, true);
+        assert_eq!(plan.reason, Some(SafeModeReason::CrashLoop));
+        assert!(!plan.runs(SUBSYSTEM_BACKEND));
+    }
+
+    #[test]
+    fn startup_attempts_persist_between_runs() {
+        let dir = std::env::temp_dir().join(format!("otoshi-attempts-{}", uuid::Uuid::new_v4()));
+        let mut attempts = StartupAttempts::load(&dir);
+        attempts.begin(3);
+        attempts.begin(3);
+        attempts.store(&dir).expect("store attempts");
+        assert_eq!(StartupAttempts::load(&dir).unfinished, 2);
+
+        mark_stable(&dir);
+        assert_eq!(StartupAttempts::load(&dir), StartupAttempts::default());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -1,28 +1,175 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
-use crate::errors::Result;
+use crate::db::queries::AchievementQueueQueries;
+use crate::db::Database;
+use crate::errors::{LauncherError, Result};
+use crate::models::PendingAchievement;
 use crate::services::ApiClient;
 
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Clone)]
 pub struct AchievementService {
     api: ApiClient,
+    db: Database,
 }
 
 impl AchievementService {
-    pub fn new(api: ApiClient) -> Self {
-        Self { api }
+    pub fn new(api: ApiClient, db: Database) -> Self {
+        Self { api, db }
     }
 
+    /// Unlocks an achievement. If the API can't be reached the unlock is
+    /// queued locally and returned as pending.
     pub async fn unlock(&self, game_id: &str, achievement_key: &str) -> Result<UserAchievement> {
+        match self.post_unlock(&self.api, game_id, achievement_key).await {
+            Ok(unlocked) => Ok(unlocked),
+            Err(err) if is_transient(&err) => {
+                tracing::info!(
+                    "achievement {}:{} queued for later sync: {}",
+                    game_id,
+                    achievement_key,
+                    err
+                );
+                let pending = self.db.enqueue_achievement(game_id, achievement_key)?;
+                Ok(UserAchievement::pending(&pending))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The user's achievements, including unlocks still waiting to sync. When
+    /// offline only the pending ones are returned.
+    pub async fn list_user(&self) -> Result<Vec<UserAchievement>> {
+        let pending = self.db.list_pending_achievements()?;
+        let mut achievements = match self
+            .api
+            .get::<Vec<UserAchievement>>("/achievements/me", true)
+            .await
+        {
+            Ok(achievements) => achievements,
+            Err(err) if is_transient(&err) && !pending.is_empty() => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        for entry in &pending {
+            let synced = achievements.iter().any(|unlocked| {
+                unlocked.achievement.game_id == entry.game_id
+                    && unlocked.achievement.key == entry.achievement_key
+            });
+            if !synced {
+                achievements.push(UserAchievement::pending(entry));
+            }
+        }
+        Ok(achievements)
+    }
+
+    /// Sends queued unlocks. Stops at the first transient or auth failure;
+    /// only unlocks the backend rejects as invalid are dropped, anything else
+    /// stays queued for the next flush. Returns how many were synced.
+    pub async fn flush_queue(&self) -> Result<usize> {
+        let api = self.api.background();
+        let mut synced = 0usize;
+        for entry in self.db.list_pending_achievements()? {
+            match self
+                .post_unlock(&api, &entry.game_id, &entry.achievement_key)
+                .await
+            {
+                Ok(_) => synced += 1,
+                Err(err) => match queued_failure(&err) {
+                    QueuedFailure::Drop => tracing::warn!(
+                        "dropping queued achievement {}:{}: {}",
+                        entry.game_id,
+                        entry.achievement_key,
+                        err
+                    ),
+                    QueuedFailure::Keep => {
+                        tracing::debug!(
+                            "keeping queued achievement {}:{}: {}",
+                            entry.game_id,
+                            entry.achievement_key,
+                            err
+                        );
+                        self.db
+                            .bump_achievement_attempts(&entry.game_id, &entry.achievement_key)?;
+                        continue;
+                    }
+                    QueuedFailure::Stop => {
+                        self.db
+                            .bump_achievement_attempts(&entry.game_id, &entry.achievement_key)?;
+                        return Err(err);
+                    }
+                },
+            }
+            self.db
+                .remove_pending_achievement(&entry.game_id, &entry.achievement_key)?;
+        }
+        Ok(synced)
+    }
+
+    pub fn spawn_flush_worker(&self) {
+        let service = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(FLUSH_INTERVAL).await;
+                match service.flush_queue().await {
+                    Ok(0) => {}
+                    Ok(synced) => tracing::info!("synced {} queued achievements", synced),
+                    Err(err) => tracing::debug!("achievement queue flush deferred: {}", err),
+                }
+            }
+        });
+    }
+
+    async fn post_unlock(
+        &self,
+        api: &ApiClient,
+        game_id: &str,
+        achievement_key: &str,
+    ) -> Result<UserAchievement> {
         let payload = AchievementUnlockRequest {
             game_id: game_id.to_string(),
             achievement_key: achievement_key.to_string(),
         };
-        self.api.post("/achievements/unlock", payload, true).await
+        api.post("/achievements/unlock", payload, true).await
+    }
+}
+
+/// What a failed sync does to a queued unlock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum QueuedFailure {
+    /// The backend says the unlock is invalid; retrying can't help.
+    Drop,
+    /// Kept for a later flush, and the next unlock is tried.
+    Keep,
+    /// Kept, and the flush ends: the rest would fail the same way.
+    Stop,
+}
+
+fn queued_failure(err: &LauncherError) -> QueuedFailure {
+    match err {
+        LauncherError::Http(message)
+            if ["HTTP 400", "HTTP 404", "HTTP 422"]
+                .iter()
+                .any(|prefix| message.starts_with(prefix)) =>
+        {
+            QueuedFailure::Drop
+        }
+        LauncherError::Auth(_) => QueuedFailure::Stop,
+        err if is_transient(err) => QueuedFailure::Stop,
+        _ => QueuedFailure::Keep,
     }
+}
 
-    pub async fn list_user(&self) -> Result<Vec<UserAchievement>> {
-        self.api.get("/achievements/me", true).await
+/// Failures worth retrying later: no connection, rate limits and 5xx.
+fn is_transient(err: &LauncherError) -> bool {
+    match err {
+        LauncherError::Network(_) => true,
+        LauncherError::Http(message) => {
+            message.starts_with("HTTP 5") || message.starts_with("HTTP 429")
+        }
+        _ => false,
     }
 }
 
@@ -48,4 +195,60 @@ pub struct UserAchievement {
     pub id: String,
     pub achievement: Achievement,
     pub unlocked_at: String,
+    /// Unlocked locally but not yet accepted by the backend.
+    #[serde(default)]
+    pub pending: bool,
+}
+
+impl UserAchievement {
+    fn pending(entry: &PendingAchievement) -> Self {
+        let unlocked_at = chrono::DateTime::from_timestamp(entry.unlocked_at, 0)
+            .unwrap_or_default()
+            .to_rfc3339();
+        Self {
+            id: format!("pending:{}:{}", entry.game_id, entry.achievement_key),
+            achievement: Achievement {
+                id: String::new(),
+                game_id: entry.game_id.clone(),
+                key: entry.achievement_key.clone(),
+                title: entry.achievement_key.clone(),
+                description: None,
+                points: 0,
+                icon_url: None,
+            },
+            unlocked_at,
+            pending: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_invalid_unlocks_leave_the_queue() {
+        let http = |message: &str| LauncherError::Http(message.to_string());
+        for message in [
+            "HTTP 400: bad key",
+            "HTTP 404: no such game",
+            "HTTP 422: locked",
+        ] {
+            assert_eq!(queued_failure(&http(message)), QueuedFailure::Drop);
+        }
+        for message in ["HTTP 401: expired", "HTTP 403: banned", "HTTP 409: busy"] {
+            assert_eq!(queued_failure(&http(message)), QueuedFailure::Keep);
+        }
+        for message in ["HTTP 429: slow down", "HTTP 503: maintenance"] {
+            assert_eq!(queued_failure(&http(message)), QueuedFailure::Stop);
+        }
+        assert_eq!(
+            queued_failure(&LauncherError::Auth("not logged in".to_string())),
+            QueuedFailure::Stop
+        );
+        assert_eq!(
+            queued_failure(&LauncherError::Config("bad response".to_string())),
+            QueuedFailure::Keep
+        );
+    }
 }
@@ -1,46 +1,244 @@
+use futures_util::future::{BoxFuture, FutureExt, Shared};
 use reqwest::Method;
 use reqwest::StatusCode;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
+use crate::db::queries::SettingsQueries;
+use crate::db::Database;
 use crate::errors::{LauncherError, Result};
 use crate::services::AuthService;
+use crate::utils::client_identity;
+
+const DEFAULT_API_RPS: f64 = 10.0;
+const DEFAULT_API_BURST: f64 = 20.0;
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+pub const API_CLIENT_SETTING: &str = "api_client_config";
+
+/// Timeouts and retries for calls to the launcher backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiClientConfig {
+    pub timeout_seconds: u64,
+    pub connect_timeout_seconds: u64,
+    /// Extra attempts for idempotent requests that time out or get a 5xx.
+    pub max_retries: u32,
+    /// Wait before the first retry; doubles with each one after.
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for ApiClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: 20,
+            connect_timeout_seconds: 6,
+            max_retries: 2,
+            retry_backoff_ms: 500,
+        }
+    }
+}
+
+impl ApiClientConfig {
+    pub fn clamped(self) -> Self {
+        Self {
+            timeout_seconds: self.timeout_seconds.clamp(1, 300),
+            connect_timeout_seconds: self.connect_timeout_seconds.clamp(1, 60),
+            max_retries: self.max_retries.min(5),
+            retry_backoff_ms: self.retry_backoff_ms.min(10_000),
+        }
+    }
+
+    fn build_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(self.timeout_seconds))
+            .connect_timeout(Duration::from_secs(self.connect_timeout_seconds))
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new())
+    }
+
+    /// How long to wait before retry number `retry` (1-based).
+    pub fn retry_delay(&self, retry: u32) -> Duration {
+        let factor = 1u64 << retry.saturating_sub(1).min(16);
+        Duration::from_millis(self.retry_backoff_ms.saturating_mul(factor)).min(MAX_RETRY_DELAY)
+    }
+}
+
+pub fn load_api_client_config(db: &Database) -> ApiClientConfig {
+    match db.get_setting(API_CLIENT_SETTING) {
+        Ok(Some(raw)) => serde_json::from_str::<ApiClientConfig>(&raw)
+            .map(ApiClientConfig::clamped)
+            .unwrap_or_else(|err| {
+                tracing::warn!("ignoring invalid {API_CLIENT_SETTING}: {err}");
+                ApiClientConfig::default()
+            }),
+        _ => ApiClientConfig::default(),
+    }
+}
+
+/// The HTTP client together with the config it was built from, swapped as
+/// one by `set_config`.
+struct Transport {
+    client: reqwest::Client,
+    config: ApiClientConfig,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestPriority {
+    /// Calls made because the user did something; never held back for
+    /// background work.
+    Interactive,
+    /// Syncs, flushes and prefetches; they leave part of the bucket for
+    /// interactive calls and yield while any are waiting.
+    Background,
+}
 
 #[derive(Clone)]
 pub struct ApiClient {
-    client: reqwest::Client,
-    base_url: String,
+    transport: Arc<RwLock<Transport>>,
+    /// Shared by every clone so `set_base_url` reaches all services.
+    base_url: Arc<RwLock<String>>,
     auth: AuthService,
+    limiter: Option<Arc<RateLimiter>>,
+    priority: RequestPriority,
+    inflight: RequestCoalescer,
+    /// Whether the last request or health check reached the backend.
+    online: Arc<AtomicBool>,
 }
 
 impl ApiClient {
-    pub fn new(base_url: String, auth: AuthService) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(20))
-            .connect_timeout(Duration::from_secs(6))
-            .build()
-            .unwrap_or_else(|_| reqwest::Client::new());
+    pub fn new(base_url: String, auth: AuthService, config: ApiClientConfig) -> Self {
+        let transport = Transport {
+            client: config.build_client(),
+            config,
+        };
+        let rps = env_f64("LAUNCHER_API_RPS").unwrap_or(DEFAULT_API_RPS);
+        let burst = env_f64("LAUNCHER_API_BURST").unwrap_or(DEFAULT_API_BURST);
+        let limiter = (rps > 0.0).then(|| Arc::new(RateLimiter::new(rps, burst.max(1.0))));
         Self {
-            client,
-            base_url,
+            transport: Arc::new(RwLock::new(transport)),
+            base_url: Arc::new(RwLock::new(base_url)),
             auth,
+            limiter,
+            priority: RequestPriority::Interactive,
+            inflight: RequestCoalescer::default(),
+            online: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// A handle sharing this client's rate limiter whose calls run at
+    /// background priority.
+    pub fn background(&self) -> Self {
+        Self {
+            priority: RequestPriority::Background,
+            ..self.clone()
         }
     }
 
     /// Get the underlying reqwest client for custom requests
-    pub fn client(&self) -> &reqwest::Client {
-        &self.client
+    pub fn client(&self) -> reqwest::Client {
+        match self.transport.read() {
+            Ok(transport) => transport.client.clone(),
+            Err(poisoned) => poisoned.into_inner().client.clone(),
+        }
+    }
+
+    pub fn config(&self) -> ApiClientConfig {
+        match self.transport.read() {
+            Ok(transport) => transport.config,
+            Err(poisoned) => poisoned.into_inner().config,
+        }
+    }
+
+    /// Rebuilds the HTTP client with `config` for this client and every
+    /// clone. Requests already in flight keep their old timeouts.
+    pub fn set_config(&self, config: ApiClientConfig) -> ApiClientConfig {
+        let config = config.clamped();
+        let transport = Transport {
+            client: config.build_client(),
+            config,
+        };
+        match self.transport.write() {
+            Ok(mut current) => *current = transport,
+            Err(poisoned) => *poisoned.into_inner() = transport,
+        }
+        config
     }
 
     /// Get the base URL for the API
-    pub fn base_url(&self) -> &str {
-        &self.base_url
+    pub fn base_url(&self) -> String {
+        match self.base_url.read() {
+            Ok(url) => url.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
     }
 
-    pub async fn get<T: DeserializeOwned>(&self, path: &str, auth: bool) -> Result<T> {
-        self.request(Method::GET, path, Option::<()>::None, auth)
+    /// Switches this client, every clone of it and the auth service to
+    /// another backend.
+    pub fn set_base_url(&self, base_url: &str) {
+        match self.base_url.write() {
+            Ok(mut url) => *url = base_url.to_string(),
+            Err(poisoned) => *poisoned.into_inner() = base_url.to_string(),
+        }
+        self.auth.set_base_url(base_url);
+    }
+
+    /// False once a request couldn't connect or timed out, until the backend
+    /// answers again.
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::SeqCst)
+    }
+
+    /// Probes `/health`, updating and returning the online state.
+    pub async fn check_health(&self) -> bool {
+        let url = format!("{}/health", self.base_url().trim_end_matches('/'));
+        let online = self
+            .client()
+            .get(&url)
+            .headers(client_identity::request_headers())
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
             .await
+            .is_ok_and(|response| response.status().is_success());
+        self.set_online(online);
+        online
+    }
+
+    fn set_online(&self, online: bool) {
+        if self.online.swap(online, Ordering::SeqCst) != online {
+            if online {
+                tracing::info!("backend reachable again at {}", self.base_url());
+            } else {
+                tracing::warn!("backend unreachable at {}", self.base_url());
+            }
+        }
+    }
+
+    /// Concurrent identical GETs to the same backend share one network call
+    /// and its result.
+    pub async fn get<T: DeserializeOwned>(&self, path: &str, auth: bool) -> Result<T> {
+        let key = format!(
+            "GET {}/{} auth={}",
+            self.base_url().trim_end_matches('/'),
+            path.trim_start_matches('/'),
+            auth
+        );
+        let client = self.clone();
+        let path = path.to_string();
+        let value = self
+            .inflight
+            .run(key, async move {
+                client
+                    .request::<serde_json::Value, ()>(Method::GET, &path, None, auth, true)
+                    .await
+            })
+            .await?;
+        Ok(serde_json::from_value((*value).clone())?)
     }
 
     pub async fn get_auth_first<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
@@ -63,22 +261,63 @@ impl ApiClient {
         body: B,
         auth: bool,
     ) -> Result<T> {
-        self.request(Method::POST, path, Some(body), auth).await
+        self.request(Method::POST, path, Some(body), auth, false)
+            .await
+    }
+
+    /// A POST the backend treats as idempotent, so it is retried like a GET
+    /// when it times out or gets a 5xx.
+    pub async fn post_idempotent<T: DeserializeOwned, B: Serialize + Clone>(
+        &self,
+        path: &str,
+        body: B,
+        auth: bool,
+    ) -> Result<T> {
+        self.request(Method::POST, path, Some(body), auth, true)
+            .await
     }
 
     pub async fn delete<T: DeserializeOwned>(&self, path: &str, auth: bool) -> Result<T> {
-        self.request(Method::DELETE, path, Option::<()>::None, auth)
+        self.request(Method::DELETE, path, Option::<()>::None, auth, false)
             .await
     }
 
+    /// Builds a rate-limited request for bodies the JSON helpers can't send,
+    /// such as raw file uploads. The caller checks the response status.
+    pub async fn raw_request(
+        &self,
+        method: Method,
+        path: &str,
+        auth: bool,
+    ) -> Result<reqwest::RequestBuilder> {
+        if let Some(limiter) = self.limiter.as_ref() {
+            limiter.acquire(self.priority).await;
+        }
+        let url = format!(
+            "{}/{}",
+            self.base_url().trim_end_matches('/'),
+            path.trim_start_matches('/')
+        );
+        let mut request = self
+            .client()
+            .request(method, &url)
+            .headers(client_identity::request_headers());
+        if auth {
+            let token = self.auth.ensure_access_token().await?;
+            request = request.bearer_auth(token);
+        }
+        Ok(request)
+    }
+
     async fn request<T: DeserializeOwned, B: Serialize + Clone>(
         &self,
         method: Method,
         path: &str,
         body: Option<B>,
         auth_required: bool,
+        idempotent: bool,
     ) -> Result<T> {
-        self.request_with_retry(method, path, body, auth_required, true)
+        self.request_with_retry(method, path, body, auth_required, true, idempotent)
             .await
     }
 
@@ -89,24 +328,38 @@ impl ApiClient {
         body: Option<B>,
         auth_required: bool,
         allow_refresh: bool,
+        idempotent: bool,
     ) -> Result<T> {
         let url = format!(
             "{}/{}",
-            self.base_url.trim_end_matches('/'),
+            self.base_url().trim_end_matches('/'),
             path.trim_start_matches('/')
         );
         let mut refreshed = false;
+        let mut retries = 0u32;
 
         loop {
-            let mut request = self.client.request(method.clone(), &url);
+            if let Some(limiter) = self.limiter.as_ref() {
+                limiter.acquire(self.priority).await;
+            }
+            let (client, config) = {
+                let transport = self
+                    .transport
+                    .read()
+                    .map_err(|_| LauncherError::Config("api transport locked".to_string()))?;
+                (transport.client.clone(), transport.config)
+            };
+            let mut request = client
+                .request(method.clone(), &url)
+                .headers(client_identity::request_headers());
 
             if auth_required {
-                let token = match self.auth.access_token() {
-                    Some(token) => token,
-                    None if allow_refresh => self.auth.refresh_access_token().await?,
-                    None => {
-                        return Err(LauncherError::Auth("no access token available".to_string()))
-                    }
+                let token = if allow_refresh {
+                    self.auth.ensure_access_token().await?
+                } else {
+                    self.auth.access_token().ok_or_else(|| {
+                        LauncherError::Auth("no access token available".to_string())
+                    })?
                 };
                 request = request.bearer_auth(token);
             }
@@ -115,7 +368,24 @@ impl ApiClient {
                 request = request.json(payload);
             }
 
-            let response = request.send().await?;
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(err) => {
+                    if err.is_connect() || err.is_timeout() {
+                        self.set_online(false);
+                    }
+                    // Refused connections aren't retried so offline
+                    // fallbacks kick in straight away.
+                    if idempotent && err.is_timeout() && retries < config.max_retries {
+                        retries += 1;
+                        tracing::debug!("api {} {} timed out; retry {}", method, path, retries);
+                        tokio::time::sleep(config.retry_delay(retries)).await;
+                        continue;
+                    }
+                    return Err(err.into());
+                }
+            };
+            self.set_online(true);
             if response.status() == StatusCode::UNAUTHORIZED
                 && auth_required
                 && allow_refresh
@@ -126,6 +396,19 @@ impl ApiClient {
                 continue;
             }
 
+            if idempotent && response.status().is_server_error() && retries < config.max_retries {
+                retries += 1;
+                tracing::debug!(
+                    "api {} {} got HTTP {}; retry {}",
+                    method,
+                    path,
+                    response.status().as_u16(),
+                    retries
+                );
+                tokio::time::sleep(config.retry_delay(retries)).await;
+                continue;
+            }
+
             if !response.status().is_success() {
                 let status = response.status();
                 let text = response.text().await.unwrap_or_default();
@@ -141,3 +424,310 @@ impl ApiClient {
         }
     }
 }
+
+type SharedResponse =
+    Shared<BoxFuture<'static, std::result::Result<Arc<serde_json::Value>, Arc<LauncherError>>>>;
+
+/// Single-flight map for idempotent requests, keyed by method and path.
+#[derive(Clone, Default)]
+pub struct RequestCoalescer {
+    inflight: Arc<Mutex<HashMap<String, SharedResponse>>>,
+}
+
+impl RequestCoalescer {
+    /// Runs `fetch` unless a request with the same key is already in flight,
+    /// in which case its result (or error) is shared.
+    pub async fn run<F>(&self, key: String, fetch: F) -> Result<Arc<serde_json::Value>>
+    where
+        F: Future<Output = Result<serde_json::Value>> + Send + 'static,
+    {
+        let shared = {
+            let mut inflight = self
+                .inflight
+                .lock()
+                .map_err(|_| LauncherError::Config("request coalescer locked".to_string()))?;
+            inflight
+                .entry(key.clone())
+                .or_insert_with(|| {
+                    fetch
+                        .map(|result| result.map(Arc::new).map_err(Arc::new))
+                        .boxed()
+                        .shared()
+                })
+                .clone()
+        };
+        let result = shared.clone().await;
+        if let Ok(mut inflight) = self.inflight.lock() {
+            if inflight
+                .get(&key)
+                .is_some_and(|current| current.ptr_eq(&shared))
+            {
+                inflight.remove(&key);
+            }
+        }
+        result.map_err(|err| replicate_error(&err))
+    }
+}
+
+/// Every waiter on a coalesced request gets its own copy of the error.
+/// Network errors share the original, so callers can still tell an
+/// unreachable backend apart; other variants that can't be cloned keep their
+/// message and retry semantics.
+fn replicate_error(err: &LauncherError) -> LauncherError {
+    match err {
+        LauncherError::Http(message) => LauncherError::Http(message.clone()),
+        LauncherError::Auth(message) => LauncherError::Auth(message.clone()),
+        LauncherError::NotFound(message) => LauncherError::NotFound(message.clone()),
+        LauncherError::Crypto(message) => LauncherError::Crypto(message.clone()),
+        LauncherError::Storage(message) => LauncherError::Storage(message.clone()),
+        LauncherError::Integrity(message) => LauncherError::Integrity(message.clone()),
+        LauncherError::Io(io) => LauncherError::Io(std::io::Error::new(io.kind(), io.to_string())),
+        LauncherError::Network(inner) => LauncherError::Network(Arc::clone(inner)),
+        LauncherError::Pool(_) => LauncherError::Http(err.to_string()),
+        _ => LauncherError::Config(err.to_string()),
+    }
+}
+
+fn env_f64(key: &str) -> Option<f64> {
+    std::env::var(key)
+        .ok()
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|value| value.is_finite() && *value >= 0.0)
+}
+
+/// Token bucket refilled at `rate` tokens per second up to `burst`.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, burst: f64, now: Instant) -> Self {
+        Self {
+            rate,
+            burst,
+            tokens: burst,
+            updated: now,
+        }
+    }
+
+    /// Takes one token if more than `reserve` are available, otherwise returns
+    /// how long until one is.
+    pub fn try_take(&mut self, now: Instant, reserve: f64) -> std::result::Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.updated = now;
+
+        let needed = 1.0 + reserve;
+        if self.tokens >= needed {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((needed - self.tokens) / self.rate))
+    }
+}
+
+/// Client-side limiter shared by every `ApiClient` clone.
+pub struct RateLimiter {
+    bucket: Mutex<TokenBucket>,
+    background_reserve: f64,
+    interactive_waiting: AtomicUsize,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            bucket: Mutex::new(TokenBucket::new(rate, burst, Instant::now())),
+            background_reserve: (burst / 4.0).floor(),
+            interactive_waiting: AtomicUsize::new(0),
+        }
+    }
+
+    pub async fn acquire(&self, priority: RequestPriority) {
+        let interactive = priority == RequestPriority::Interactive;
+        let _waiting = interactive.then(|| InteractiveWaiting::new(&self.interactive_waiting));
+        loop {
+            let wait = {
+                let Ok(mut bucket) = self.bucket.lock() else {
+                    break;
+                };
+                let now = Instant::now();
+                if !interactive && self.interactive_waiting.load(Ordering::SeqCst) > 0 {
+                    Err(Duration::from_secs_f64(1.0 / bucket.rate))
+                } else {
+                    let reserve = if interactive {
+                        0.0
+                    } else {
+                        self.background_reserve
+                    };
+                    bucket.try_take(now, reserve)
+                }
+            };
+            match wait {
+                Ok(()) => break,
+                Err(delay) => tokio::time::sleep(delay).await,
+            }
+        }
+    }
+}
+
+/// Counts an interactive request as waiting until dropped, so one cancelled
+/// mid-wait doesn't hold background requests back for good.
+struct InteractiveWaiting<'a>(&'a AtomicUsize);
+
+impl<'a> InteractiveWaiting<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for InteractiveWaiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_up_to_a_cap() {
+        let config = ApiClientConfig::default();
+        assert_eq!(config.retry_delay(1), Duration::from_millis(500));
+        assert_eq!(config.retry_delay(2), Duration::from_millis(1000));
+        assert_eq!(config.retry_delay(3), Duration::from_millis(2000));
+        assert_eq!(config.retry_delay(30), MAX_RETRY_DELAY);
+
+        let clamped = ApiClientConfig {
+            timeout_seconds: 0,
+            connect_timeout_seconds: 600,
+            max_retries: 50,
+            retry_backoff_ms: u64::MAX,
+        }
+        .clamped();
+        assert_eq!(clamped.timeout_seconds, 1);
+        assert_eq!(clamped.connect_timeout_seconds, 60);
+        assert_eq!(clamped.max_retries, 5);
+        assert_eq!(clamped.retry_delay(5), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn bucket_spaces_out_a_burst() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(10.0, 5.0, start);
+        let mut now = start;
+        let mut granted_at = Vec::new();
+        while granted_at.len() < 12 {
+            match bucket.try_take(now, 0.0) {
+                Ok(()) => granted_at.push(now.duration_since(start)),
+                Err(wait) => now += wait,
+            }
+        }
+
+        // The burst goes out immediately, the rest at the refill rate.
+        assert!(granted_at[..5].iter().all(|at| at.is_zero()));
+        for pair in granted_at[5..].windows(2) {
+            let gap = pair[1] - pair[0];
+            assert!(gap >= Duration::from_millis(99), "gap {gap:?}");
+        }
+        assert!(granted_at[11] >= Duration::from_millis(690));
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_interactive_wait_releases_background_requests() {
+        let limiter = RateLimiter::new(20.0, 4.0);
+        for _ in 0..4 {
+            limiter.acquire(RequestPriority::Interactive).await;
+        }
+        let cancelled = tokio::time::timeout(
+            Duration::from_millis(5),
+            limiter.acquire(RequestPriority::Interactive),
+        )
+        .await;
+        assert!(cancelled.is_err());
+        assert_eq!(limiter.interactive_waiting.load(Ordering::SeqCst), 0);
+        let background = tokio::time::timeout(
+            Duration::from_secs(2),
+            limiter.acquire(RequestPriority::Background),
+        )
+        .await;
+        assert!(background.is_ok());
+    }
+
+    #[tokio::test]
+    async fn concurrent_identical_gets_share_one_request() {
+        let coalescer = RequestCoalescer::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let waiters = (0..8).map(|_| {
+            let calls = calls.clone();
+            coalescer.run("GET games/demo auth=false".to_string(), async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok(serde_json::json!({ "slug": "demo" }))
+            })
+        });
+        let results = futures_util::future::join_all(waiters).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(results
+            .iter()
+            .all(|result| result.as_ref().unwrap()["slug"] == "demo"));
+
+        let failing = (0..3).map(|_| {
+            let calls = calls.clone();
+            coalescer.run("GET games/missing auth=false".to_string(), async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Err(LauncherError::Http("HTTP 404: missing".to_string()))
+            })
+        });
+        let results = futures_util::future::join_all(failing).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(results
+            .iter()
+            .all(|result| matches!(result, Err(LauncherError::Http(message)) if message.starts_with("HTTP 404"))));
+
+        // Once finished, the next call goes to the network again.
+        let value = coalescer
+            .run("GET games/demo auth=false".to_string(), async {
+                Ok(serde_json::json!(1))
+            })
+            .await
+            .unwrap();
+        assert_eq!(*value, serde_json::json!(1));
+    }
+
+    #[test]
+    fn background_calls_leave_a_reserve() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(1.0, 4.0, now);
+        for _ in 0..3 {
+            assert!(bucket.try_take(now, 1.0).is_ok());
+        }
+        assert!(bucket.try_take(now, 1.0).is_err());
+        assert!(bucket.try_take(now, 0.0).is_ok());
+    }
+
+    #[tokio::test]
+    async fn coalesced_waiters_keep_the_network_error_kind() {
+        let coalescer = RequestCoalescer::default();
+        let fetch = || async {
+            let err = reqwest::Client::new().get("not a url").build().unwrap_err();
+            Err::<serde_json::Value, _>(LauncherError::from(err))
+        };
+        let (first, second) = tokio::join!(
+            coalescer.run("GET /games".to_string(), fetch()),
+            coalescer.run("GET /games".to_string(), fetch())
+        );
+        assert!(matches!(first, Err(LauncherError::Network(_))));
+        assert!(matches!(second, Err(LauncherError::Network(_))));
+        assert!(matches!(
+            replicate_error(&LauncherError::Http("HTTP 503: busy".to_string())),
+            LauncherError::Http(message) if message == "HTTP 503: busy"
+        ));
+    }
+}
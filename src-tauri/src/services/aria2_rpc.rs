@@ -0,0 +1,192 @@
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tokio::time::sleep;
+
+use crate::errors::{LauncherError, Result};
+
+const RPC_READY_TIMEOUT: Duration = Duration::from_secs(5);
+const RPC_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(150);
+
+/// A single `aria2c --enable-rpc` process that chunk downloads are submitted to
+/// over JSON-RPC, instead of spawning one `aria2c` per chunk. The process is
+/// shut down when the daemon is dropped.
+pub struct Aria2RpcDaemon {
+    child: Mutex<Option<Child>>,
+    client: reqwest::Client,
+    endpoint: String,
+    token: String,
+    request_id: AtomicU64,
+}
+
+impl Aria2RpcDaemon {
+    /// Starts the daemon with `global_args` and waits until the RPC endpoint
+    /// answers. Fails if no local port can be bound or aria2c never responds.
+    pub async fn start(binary: &str, global_args: &[String]) -> Result<Self> {
+        let port = reserve_local_port()?;
+        let secret = uuid::Uuid::new_v4().simple().to_string();
+
+        let mut command = Command::new(binary);
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            command.creation_flags(0x08000000);
+        }
+        command
+            .arg("--enable-rpc=true")
+            .arg("--rpc-listen-all=false")
+            .arg(format!("--rpc-listen-port={port}"))
+            .arg(format!("--rpc-secret={secret}"))
+            .arg("--rpc-max-request-size=4M")
+            .arg("--daemon=false")
+            .arg("--quiet=true")
+            .args(global_args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let child = command.spawn()?;
+
+        let daemon = Self {
+            child: Mutex::new(Some(child)),
+            client: reqwest::Client::builder()
+                .no_proxy()
+                .timeout(RPC_CALL_TIMEOUT)
+                .build()?,
+            endpoint: format!("http://127.0.0.1:{port}/jsonrpc"),
+            token: format!("token:{secret}"),
+            request_id: AtomicU64::new(1),
+        };
+
+        let started = Instant::now();
+        loop {
+            if daemon.call("aria2.getVersion", Vec::new()).await.is_ok() {
+                tracing::info!("aria2c rpc daemon ready on port {}", port);
+                return Ok(daemon);
+            }
+            if daemon.has_exited() || started.elapsed() >= RPC_READY_TIMEOUT {
+                return Err(LauncherError::Config(format!(
+                    "aria2c rpc daemon did not come up on port {port}"
+                )));
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Downloads `uris` (mirrors of the same content) to `dir/out` and returns
+    /// once aria2 reports the download complete.
+    pub async fn download(&self, uris: &[String], dir: &Path, out: &str) -> Result<()> {
+        let options = json!({
+            "dir": dir.to_string_lossy(),
+            "out": out,
+        });
+        let gid = self
+            .call("aria2.addUri", vec![json!(uris), options])
+            .await?
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| LauncherError::Http("aria2 rpc returned no gid".to_string()))?;
+
+        let result = self.wait_for(&gid).await;
+        let _ = self
+            .call("aria2.removeDownloadResult", vec![json!(gid)])
+            .await;
+        result
+    }
+
+    async fn wait_for(&self, gid: &str) -> Result<()> {
+        loop {
+            let status = self
+                .call(
+                    "aria2.tellStatus",
+                    vec![json!(gid), json!(["status", "errorCode", "errorMessage"])],
+                )
+                .await?;
+            match status.get("status").and_then(Value::as_str) {
+                Some("complete") => return Ok(()),
+                Some("error") | Some("removed") => {
+                    let code = status
+                        .get("errorCode")
+                        .and_then(Value::as_str)
+                        .unwrap_or("?");
+                    let message = status
+                        .get("errorMessage")
+                        .and_then(Value::as_str)
+                        .unwrap_or("download failed");
+                    return Err(LauncherError::Http(format!(
+                        "aria2 rpc failed (code {code}): {message}"
+                    )));
+                }
+                _ => sleep(STATUS_POLL_INTERVAL).await,
+            }
+        }
+    }
+
+    async fn call(&self, method: &str, params: Vec<Value>) -> Result<Value> {
+        let mut all_params = Vec::with_capacity(params.len() + 1);
+        all_params.push(json!(self.token));
+        all_params.extend(params);
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": self.request_id.fetch_add(1, Ordering::Relaxed).to_string(),
+            "method": method,
+            "params": all_params,
+        });
+        let response: Value = self
+            .client
+            .post(&self.endpoint)
+            .json(&body)
+            .send()
+            .await?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown aria2 rpc error");
+            return Err(LauncherError::Http(format!(
+                "aria2 rpc {method}: {message}"
+            )));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    fn has_exited(&self) -> bool {
+        self.child
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.as_mut().map(|child| child.try_wait()))
+            .map(|status| !matches!(status, Ok(None)))
+            .unwrap_or(true)
+    }
+}
+
+impl Drop for Aria2RpcDaemon {
+    fn drop(&mut self) {
+        if let Ok(mut guard) = self.child.lock() {
+            if let Some(mut child) = guard.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+fn reserve_local_port() -> Result<u16> {
+    if let Some(port) = std::env::var("LAUNCHER_ARIA2C_RPC_PORT")
+        .ok()
+        .and_then(|value| value.trim().parse::<u16>().ok())
+        .filter(|value| *value > 0)
+    {
+        TcpListener::bind(("127.0.0.1", port))?;
+        return Ok(port);
+    }
+    let listener = TcpListener::bind(("127.0.0.1", 0))?;
+    Ok(listener.local_addr()?.port())
+}
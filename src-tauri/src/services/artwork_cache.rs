@@ -19,6 +19,7 @@ use sha2::{Digest, Sha256};
 use sysinfo::System;
 
 use crate::errors::{LauncherError, Result};
+use crate::utils::client_identity;
 
 const CACHE_MAGIC: &[u8; 6] = b"OTART2";
 const CACHE_VERSION: u8 = 2;
@@ -173,9 +174,10 @@ impl ArtworkCacheService {
             .unwrap_or(DEFAULT_RAM_LRU_MAX_BYTES);
 
         let client = Client::builder()
+            .user_agent(client_identity::user_agent())
             .timeout(Duration::from_secs(16))
             .build()
-            .map_err(LauncherError::Network)?;
+            .map_err(LauncherError::from)?;
 
         Ok(Self {
             cache_root,
@@ -250,7 +252,7 @@ impl ArtworkCacheService {
             .get(source_url)
             .send()
             .await
-            .map_err(LauncherError::Network)?;
+            .map_err(LauncherError::from)?;
         if !response.status().is_success() {
             self.bump_metric(|metrics| metrics.misses = metrics.misses.saturating_add(1));
             return Ok(None);
@@ -258,7 +260,7 @@ impl ArtworkCacheService {
         let raw = response
             .bytes()
             .await
-            .map_err(LauncherError::Network)?
+            .map_err(LauncherError::from)?
             .to_vec();
         let upload_elapsed = downloaded_at.elapsed().as_millis() as u64;
         self.bump_metric(|metrics| metrics.upload_ms = metrics.upload_ms.saturating_add(upload_elapsed));
@@ -1,5 +1,7 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
+use base64::Engine;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 
@@ -7,12 +9,23 @@ use crate::db::queries::SettingsQueries;
 use crate::db::Database;
 use crate::errors::{LauncherError, Result};
 use crate::models::{AuthResponse, UserProfile};
-use crate::utils::crypto;
+use crate::utils::{client_identity, crypto};
+
+/// Access tokens this close to expiry are renewed before they are used.
+const REFRESH_MARGIN_SECS: i64 = 60;
+const REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const REFRESH_TOKEN_SETTING: &str = "refresh_token";
+
+type SessionExpiredCallback = Arc<dyn Fn() + Send + Sync>;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TokenPair {
     pub access_token: Option<String>,
     pub refresh_token: Option<String>,
+    /// Unix time the access token expires, when the backend or the token
+    /// itself says.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
 }
 
 #[derive(Clone)]
@@ -22,9 +35,12 @@ pub struct AuthService {
 
 struct AuthServiceInner {
     client: reqwest::Client,
-    base_url: String,
+    base_url: RwLock<String>,
     store: TokenStore,
     tokens: Mutex<TokenPair>,
+    /// Held while refreshing so concurrent callers share one renewal.
+    refresh_lock: tokio::sync::Mutex<()>,
+    on_session_expired: RwLock<Option<SessionExpiredCallback>>,
 }
 
 #[derive(Clone)]
@@ -40,12 +56,12 @@ impl TokenStore {
 
     fn save_refresh_token(&self, token: &str) -> Result<()> {
         let encrypted = crypto::encrypt_to_base64(&self.key, token.as_bytes())?;
-        self.db.set_setting("refresh_token", &encrypted)?;
+        self.db.set_setting(REFRESH_TOKEN_SETTING, &encrypted)?;
         Ok(())
     }
 
     fn load_refresh_token(&self) -> Result<Option<String>> {
-        let value = self.db.get_setting("refresh_token")?;
+        let value = self.db.get_setting(REFRESH_TOKEN_SETTING)?;
         if let Some(payload) = value {
             let decrypted = crypto::decrypt_from_base64(&self.key, &payload)?;
             let token = String::from_utf8(decrypted)
@@ -57,7 +73,7 @@ impl TokenStore {
     }
 
     fn clear(&self) -> Result<()> {
-        self.db.delete_setting("refresh_token")?;
+        self.db.delete_setting(REFRESH_TOKEN_SETTING)?;
         Ok(())
     }
 }
@@ -65,31 +81,57 @@ impl TokenStore {
 impl AuthService {
     pub fn new(base_url: String, db: Database, key: Vec<u8>) -> Self {
         let store = TokenStore::new(db, key);
-        let refresh_token = store.load_refresh_token().ok().flatten();
+        let refresh_token = match store.load_refresh_token() {
+            Ok(token) => token,
+            Err(err) => {
+                tracing::warn!("stored refresh token is unreadable: {}", err);
+                None
+            }
+        };
         let tokens = TokenPair {
             access_token: None,
             refresh_token,
+            expires_at: None,
         };
 
         Self {
             inner: Arc::new(AuthServiceInner {
                 client: reqwest::Client::new(),
-                base_url,
+                base_url: RwLock::new(base_url),
                 store,
                 tokens: Mutex::new(tokens),
+                refresh_lock: tokio::sync::Mutex::new(()),
+                on_session_expired: RwLock::new(None),
             }),
         }
     }
 
+    fn base_url(&self) -> String {
+        match self.inner.base_url.read() {
+            Ok(url) => url.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Points auth calls at another backend. Tokens are kept; they are only
+    /// valid there if both backends share a signing key.
+    pub fn set_base_url(&self, base_url: &str) {
+        match self.inner.base_url.write() {
+            Ok(mut url) => *url = base_url.to_string(),
+            Err(poisoned) => *poisoned.into_inner() = base_url.to_string(),
+        }
+    }
+
     pub async fn login(&self, email_or_username: &str, password: &str) -> Result<AuthResponse> {
         let response = self
             .inner
             .client
-            .post(format!("{}/auth/login", self.inner.base_url))
+            .post(format!("{}/auth/login", self.base_url()))
             .json(&serde_json::json!({
                 "email_or_username": email_or_username,
                 "password": password
             }))
+            .headers(client_identity::request_headers())
             .send()
             .await?;
 
@@ -101,7 +143,11 @@ impl AuthService {
         }
 
         let auth: AuthResponse = response.json().await?;
-        self.set_tokens(Some(auth.access_token.clone()), auth.refresh_token.clone())?;
+        self.store_tokens(
+            Some(auth.access_token.clone()),
+            auth.refresh_token.clone(),
+            auth.expires_in,
+        )?;
         Ok(auth)
     }
 
@@ -114,9 +160,22 @@ impl AuthService {
             .map_err(|_| LauncherError::Config("auth lock poisoned".to_string()))?;
         guard.access_token = None;
         guard.refresh_token = None;
+        guard.expires_at = None;
         Ok(())
     }
 
+    /// Called when the session can't be renewed any more and the user has to
+    /// sign in again. Network failures while refreshing don't count.
+    pub fn on_session_expired<F>(&self, callback: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        match self.inner.on_session_expired.write() {
+            Ok(mut slot) => *slot = Some(Arc::new(callback)),
+            Err(poisoned) => *poisoned.into_inner() = Some(Arc::new(callback)),
+        }
+    }
+
     pub async fn get_current_user(&self) -> Result<Option<UserProfile>> {
         for attempt in 0..2 {
             let token = self
@@ -126,8 +185,9 @@ impl AuthService {
             let response = self
                 .inner
                 .client
-                .get(format!("{}/auth/me", self.inner.base_url))
+                .get(format!("{}/auth/me", self.base_url()))
                 .bearer_auth(token)
+                .headers(client_identity::request_headers())
                 .send()
                 .await?;
 
@@ -165,12 +225,61 @@ impl AuthService {
         self.access_token().is_some()
     }
 
-    /// Ensure we have a valid access token, refreshing if needed.
+    /// Ensure we have a valid access token, refreshing if needed: when there
+    /// is none, or it expires within a minute and a refresh token exists. A
+    /// token that is still valid is used as is if renewing it fails.
     pub async fn ensure_access_token(&self) -> Result<String> {
-        if let Some(token) = self.access_token() {
+        let Some(token) = self.access_token() else {
+            return self.refresh_access_token().await;
+        };
+        if !self.needs_refresh() {
             return Ok(token);
         }
-        self.refresh_access_token().await
+        match self.refresh_access_token().await {
+            Ok(token) => Ok(token),
+            Err(err) if !self.is_expired() && self.access_token().is_some() => {
+                tracing::warn!("early token refresh failed: {}", err);
+                Ok(token)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Renews the access token shortly before it expires, so the next call
+    /// after an idle stretch doesn't have to wait on a refresh.
+    pub fn spawn_refresh_worker(&self) {
+        let service = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(REFRESH_CHECK_INTERVAL).await;
+                if service.access_token().is_none() || !service.needs_refresh() {
+                    continue;
+                }
+                if let Err(err) = service.refresh_access_token().await {
+                    tracing::debug!("scheduled token refresh failed: {}", err);
+                }
+            }
+        });
+    }
+
+    fn expires_at(&self) -> Option<i64> {
+        self.inner
+            .tokens
+            .lock()
+            .ok()
+            .and_then(|guard| guard.expires_at)
+    }
+
+    fn needs_refresh(&self) -> bool {
+        self.refresh_token().is_some()
+            && self.expires_at().is_some_and(|expires_at| {
+                expires_at - chrono::Utc::now().timestamp() <= REFRESH_MARGIN_SECS
+            })
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_at()
+            .is_some_and(|expires_at| expires_at <= chrono::Utc::now().timestamp())
     }
 
     /// Validate current token and check if it needs refresh
@@ -180,8 +289,9 @@ impl AuthService {
             let response = self
                 .inner
                 .client
-                .get(format!("{}/auth/validate", self.inner.base_url))
+                .get(format!("{}/auth/validate", self.base_url()))
                 .bearer_auth(token)
+                .headers(client_identity::request_headers())
                 .send()
                 .await?;
 
@@ -191,34 +301,78 @@ impl AuthService {
         }
     }
 
+    /// Renews the access token. Callers racing each other share one refresh:
+    /// whoever waits on the lock gets the token the first one fetched.
     pub async fn refresh_access_token(&self) -> Result<String> {
-        let refresh_token = self
-            .refresh_token()
-            .ok_or_else(|| LauncherError::Auth("no refresh token available".to_string()))?;
+        let stale = self.access_token();
+        let _refreshing = self.inner.refresh_lock.lock().await;
+        if let Some(token) = self.access_token() {
+            if stale.as_deref() != Some(token.as_str()) && !self.needs_refresh() {
+                return Ok(token);
+            }
+        }
+
+        let Some(refresh_token) = self.refresh_token() else {
+            if stale.is_some() {
+                self.expire_session();
+            }
+            return Err(LauncherError::Auth(
+                "no refresh token available".to_string(),
+            ));
+        };
 
         let response = self
             .inner
             .client
-            .post(format!("{}/auth/refresh", self.inner.base_url))
+            .post(format!("{}/auth/refresh", self.base_url()))
             .json(&serde_json::json!({ "refresh_token": refresh_token }))
+            .headers(client_identity::request_headers())
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            return Err(LauncherError::Auth(format!(
-                "refresh failed: {}",
-                response.status()
-            )));
+        let status = response.status();
+        if matches!(
+            status,
+            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            self.expire_session();
+        }
+        if !status.is_success() {
+            return Err(LauncherError::Auth(format!("refresh failed: {}", status)));
         }
 
         let payload: AuthResponse = response.json().await?;
-        self.set_tokens(
+        self.store_tokens(
             Some(payload.access_token.clone()),
             payload.refresh_token.clone(),
+            payload.expires_in,
         )?;
         Ok(payload.access_token)
     }
 
+    /// Drops tokens the backend no longer accepts and tells the UI to ask
+    /// for a new login.
+    fn expire_session(&self) {
+        tracing::warn!("session expired; sign-in required");
+        if let Err(err) = self.inner.store.clear() {
+            tracing::warn!("failed to clear stored refresh token: {}", err);
+        }
+        if let Ok(mut guard) = self.inner.tokens.lock() {
+            guard.access_token = None;
+            guard.refresh_token = None;
+            guard.expires_at = None;
+        }
+        let callback = self
+            .inner
+            .on_session_expired
+            .read()
+            .ok()
+            .and_then(|slot| slot.clone());
+        if let Some(callback) = callback {
+            callback();
+        }
+    }
+
     fn refresh_token(&self) -> Option<String> {
         self.inner
             .tokens
@@ -227,17 +381,26 @@ impl AuthService {
             .and_then(|guard| guard.refresh_token.clone())
     }
 
-    fn set_tokens(
+    /// `expires_in` is seconds from now; without it the expiry comes from
+    /// the token's own `exp` claim.
+    fn store_tokens(
         &self,
         access_token: Option<String>,
         refresh_token: Option<String>,
+        expires_in: Option<i64>,
     ) -> Result<()> {
+        let expires_at = match (expires_in, access_token.as_deref()) {
+            (Some(seconds), Some(_)) => Some(chrono::Utc::now().timestamp() + seconds),
+            (None, Some(token)) => jwt_expiry(token),
+            (_, None) => None,
+        };
         let mut guard = self
             .inner
             .tokens
             .lock()
             .map_err(|_| LauncherError::Config("auth lock poisoned".to_string()))?;
         guard.access_token = access_token;
+        guard.expires_at = expires_at;
         if let Some(refresh) = refresh_token {
             self.inner.store.save_refresh_token(&refresh)?;
             guard.refresh_token = Some(refresh);
@@ -251,6 +414,61 @@ impl AuthService {
         access_token: Option<String>,
         refresh_token: Option<String>,
     ) -> Result<()> {
-        self.set_tokens(access_token, refresh_token)
+        self.store_tokens(access_token, refresh_token, None)
+    }
+}
+
+/// The `exp` claim of a JWT, read without checking the signature; the
+/// backend still validates the token on every call.
+fn jwt_expiry(token: &str) -> Option<i64> {
+    let payload = token.split('.').nth(1)?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()?
+        .get("exp")?
+        .as_i64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_expiry_claim_of_a_jwt() {
+        let encode = |json: &str| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json);
+        let token = format!(
+            "{}.{}.signature",
+            encode(r#"{"alg":"HS256","typ":"JWT"}"#),
+            encode(r#"{"sub":"42","exp":1767225600}"#)
+        );
+        assert_eq!(jwt_expiry(&token), Some(1767225600));
+        assert_eq!(jwt_expiry("opaque-token"), None);
+        assert_eq!(
+            jwt_expiry(&format!("{}.{}.", encode("{}"), encode(r#"{"sub":"42"}"#))),
+            None
+        );
+    }
+
+    #[test]
+    fn refresh_tokens_are_only_stored_encrypted() {
+        let db = crate::db::open_temp();
+        let store = TokenStore::new(db.clone(), vec![7; 32]);
+        store.save_refresh_token("refresh-secret").unwrap();
+
+        let stored = db.get_setting(REFRESH_TOKEN_SETTING).unwrap().unwrap();
+        assert!(!stored.contains("refresh-secret"));
+        assert_eq!(
+            store.load_refresh_token().unwrap().as_deref(),
+            Some("refresh-secret")
+        );
+
+        // A new key can't read the token, and loading doesn't rewrite it.
+        let rekeyed = TokenStore::new(db.clone(), vec![8; 32]);
+        assert!(rekeyed.load_refresh_token().is_err());
+        let service = AuthService::new("http://127.0.0.1:9".to_string(), db.clone(), vec![8; 32]);
+        assert!(service.refresh_token().is_none());
+        assert_eq!(db.get_setting(REFRESH_TOKEN_SETTING).unwrap(), Some(stored));
     }
 }
@@ -0,0 +1,142 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Token bucket shared by every chunk task. Tokens refill continuously at
+/// the limit and a caller that takes more than are available goes into
+/// debt, sleeping exactly until its bytes are paid for. The lock is only held
+/// for the arithmetic, so blocking threads such as the peer server's can
+/// share it with async tasks.
+#[derive(Clone)]
+pub struct BandwidthThrottler {
+    bucket: Arc<Mutex<TokenBucket>>,
+}
+
+struct TokenBucket {
+    /// Bytes per second; `0` is unlimited.
+    limit: u64,
+    /// May go negative while callers wait for their reservation.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Burst allowed after an idle period, as a fraction of one second.
+    const BURST_SECS: f64 = 0.1;
+
+    fn capacity(&self) -> f64 {
+        self.limit as f64 * Self::BURST_SECS
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit as f64).min(self.capacity());
+        self.refilled_at = now;
+    }
+
+    /// Takes `bytes` and returns how long the caller must wait for them.
+    fn reserve(&mut self, bytes: u64, now: Instant) -> Duration {
+        if self.limit == 0 {
+            return Duration::ZERO;
+        }
+        self.refill(now);
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.limit as f64)
+        }
+    }
+}
+
+impl BandwidthThrottler {
+    pub fn new(max_bps: u64) -> Self {
+        let mut bucket = TokenBucket {
+            limit: max_bps,
+            tokens: 0.0,
+            refilled_at: Instant::now(),
+        };
+        bucket.tokens = bucket.capacity();
+        Self {
+            bucket: Arc::new(Mutex::new(bucket)),
+        }
+    }
+
+    /// Changes the limit without a burst: the bucket restarts from at most
+    /// the new capacity and debt from the old limit is forgiven.
+    pub fn set_limit(&self, max_bps: u64) {
+        let Ok(mut bucket) = self.bucket.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        bucket.refill(now);
+        bucket.limit = max_bps;
+        bucket.tokens = bucket.tokens.clamp(0.0, bucket.capacity());
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.bucket.lock().map(|bucket| bucket.limit).unwrap_or(0)
+    }
+
+    fn reserve(&self, bytes: u64) -> Duration {
+        self.bucket
+            .lock()
+            .map(|mut bucket| bucket.reserve(bytes, Instant::now()))
+            .unwrap_or(Duration::ZERO)
+    }
+
+    pub async fn acquire(&self, bytes: u64) {
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// `acquire` for plain threads; only the calling thread sleeps.
+    pub fn acquire_blocking(&self, bytes: u64) {
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn throttler_holds_the_configured_rate() {
+        const LIMIT: u64 = 512 * 1024;
+        const CHUNK: u64 = 16 * 1024;
+        let throttle = BandwidthThrottler::new(LIMIT);
+        let started = Instant::now();
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let throttle = throttle.clone();
+                tokio::spawn(async move {
+                    for _ in 0..24 {
+                        throttle.acquire(CHUNK).await;
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.await.unwrap();
+        }
+        let elapsed = started.elapsed().as_secs_f64();
+
+        // 4 * 24 * 16 KiB = 3 s at the limit, minus the initial 0.1 s burst.
+        let total = (4 * 24 * CHUNK) as f64;
+        let rate = (total - LIMIT as f64 * TokenBucket::BURST_SECS) / elapsed;
+        let deviation = (rate - LIMIT as f64).abs() / LIMIT as f64;
+        assert!(deviation < 0.1, "rate {rate:.0} B/s over {elapsed:.2}s");
+
+        throttle.set_limit(0);
+        let unlimited = Instant::now();
+        throttle.acquire(100 * LIMIT).await;
+        assert!(unlimited.elapsed() < Duration::from_millis(50));
+    }
+}
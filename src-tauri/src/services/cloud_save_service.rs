@@ -1,16 +1,156 @@
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use reqwest::{Method, StatusCode};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::db::queries::{CloudSaveSnapshotQueries, SettingsQueries};
+use crate::db::Database;
+use crate::errors::{LauncherError, Result};
+use crate::models::CloudSaveSnapshot;
+use crate::services::api_client::ApiClientConfig;
+use crate::services::{patch_engine, ApiClient};
+use crate::utils::file::relative_files;
 
-use crate::errors::Result;
-use crate::services::ApiClient;
+const BACKUP_DIR_SETTING: &str = "save_backup_dir";
+const AFTER_PLAY_SETTING: &str = "save_sync_after_play";
+/// Upload sessions of large save files that haven't completed, so a failed
+/// upload resumes where the server last acknowledged.
+const PENDING_UPLOADS_SETTING: &str = "cloud_save_pending_uploads";
+/// Files at least this large are sent in chunks through an upload session.
+const CHUNKED_UPLOAD_THRESHOLD: u64 = 32 * 1024 * 1024;
+const UPLOAD_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+/// Times an upload may ask the server where to continue before giving up.
+const MAX_UPLOAD_RESYNCS: u32 = 3;
 
 #[derive(Clone)]
 pub struct CloudSaveService {
     api: ApiClient,
+    db: Database,
+    /// Save folder fingerprints taken when each running game launched.
+    launch_fingerprints: Arc<Mutex<HashMap<String, HashMap<PathBuf, String>>>>,
 }
 
 impl CloudSaveService {
-    pub fn new(api: ApiClient) -> Self {
-        Self { api }
+    pub fn new(api: ApiClient, db: Database) -> Self {
+        Self {
+            api,
+            db,
+            launch_fingerprints: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// What happens when a game exits with changed saves.
+    pub fn after_play_sync(&self) -> AfterPlaySync {
+        match self
+            .db
+            .get_setting(AFTER_PLAY_SETTING)
+            .ok()
+            .flatten()
+            .as_deref()
+        {
+            Some("off") => AfterPlaySync::Off,
+            Some("upload") => AfterPlaySync::Upload,
+            _ => AfterPlaySync::Notify,
+        }
+    }
+
+    pub fn set_after_play_sync(&self, mode: AfterPlaySync) -> Result<()> {
+        self.db.set_setting(AFTER_PLAY_SETTING, mode.as_str())
+    }
+
+    /// Fingerprints the save folders of `game_id` as it launches, so the
+    /// exit check only has to compare file sizes and mtimes.
+    pub async fn record_launch(&self, game_id: &str, locations: Vec<PathBuf>) -> Result<()> {
+        if self.after_play_sync() == AfterPlaySync::Off {
+            return Ok(());
+        }
+        let fingerprints = tokio::task::spawn_blocking(move || fingerprint_all(&locations))
+            .await
+            .map_err(|err| LauncherError::Config(format!("save scan join error: {err}")))?;
+        self.fingerprints()
+            .insert(game_id.to_string(), fingerprints);
+        Ok(())
+    }
+
+    /// Compares the save folders of `game_id` against its launch fingerprint.
+    /// Returns `None` when nothing changed or no fingerprint was taken. In
+    /// upload mode a game with a single save folder is uploaded right away,
+    /// but only when the cloud copy hasn't changed since the last sync.
+    pub async fn check_after_play(
+        &self,
+        game_id: &str,
+        locations: Vec<PathBuf>,
+    ) -> Result<Option<SavesChanged>> {
+        let Some(before) = self.fingerprints().remove(game_id) else {
+            return Ok(None);
+        };
+        let mode = self.after_play_sync();
+        if mode == AfterPlaySync::Off {
+            return Ok(None);
+        }
+        let after = tokio::task::spawn_blocking(move || fingerprint_all(&locations))
+            .await
+            .map_err(|err| LauncherError::Config(format!("save scan join error: {err}")))?;
+        let changed = changed_locations(&before, &after);
+        if changed.is_empty() {
+            return Ok(None);
+        }
+
+        let mut uploaded = None;
+        let mut sync_status = None;
+        if mode == AfterPlaySync::Upload {
+            if let [location] = after.keys().collect::<Vec<_>>().as_slice() {
+                match self.resolve(game_id, location).await {
+                    Ok(resolution) if resolution.status != SaveSyncStatus::LocalNewer => {
+                        tracing::info!(
+                            "not uploading saves of {game_id} automatically: {:?}",
+                            resolution.status
+                        );
+                        sync_status = Some(resolution.status);
+                    }
+                    Ok(resolution) => {
+                        sync_status = Some(resolution.status);
+                        let no_progress = |_: &SaveUploadProgress| {};
+                        match self
+                            .upload_incremental(game_id, location, false, &no_progress)
+                            .await
+                        {
+                            Ok(report) => uploaded = Some(report),
+                            Err(err) => {
+                                tracing::warn!("automatic save upload for {game_id} failed: {err}")
+                            }
+                        }
+                    }
+                    Err(err) => tracing::warn!("save sync check for {game_id} failed: {err}"),
+                }
+            } else {
+                tracing::info!(
+                    "{game_id} has {} save folders; not uploading them automatically",
+                    after.len()
+                );
+            }
+        }
+        Ok(Some(SavesChanged {
+            game_id: game_id.to_string(),
+            locations: changed
+                .iter()
+                .map(|location| location.to_string_lossy().to_string())
+                .collect(),
+            uploaded,
+            sync_status,
+        }))
+    }
+
+    fn fingerprints(&self) -> std::sync::MutexGuard<'_, HashMap<String, HashMap<PathBuf, String>>> {
+        match self.launch_fingerprints.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
     }
 
     pub async fn upload_save(
@@ -29,6 +169,858 @@ impl CloudSaveService {
     pub async fn fetch_save(&self, game_id: &str) -> Result<CloudSave> {
         self.api.get(&format!("/cloud-saves/{game_id}"), true).await
     }
+
+    /// Per-file manifest of the cloud copy; empty when nothing is stored yet.
+    pub async fn remote_files(&self, game_id: &str) -> Result<Vec<CloudSaveFile>> {
+        let path = format!("/cloud-saves/{}/files", urlencoding::encode(game_id));
+        match self.api.get(&path, true).await {
+            Ok(files) => Ok(files),
+            Err(LauncherError::Http(message)) if message.starts_with("HTTP 404") => Ok(Vec::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Uploads only the files whose hash differs from the cloud manifest and
+    /// deletes remote files that no longer exist locally. With `dry_run` the
+    /// plan is returned without transferring anything. A missing or empty
+    /// save folder is refused while the cloud holds files, since it would
+    /// otherwise wipe the cloud copy.
+    ///
+    /// Large files go up in chunks and resume from the last acknowledged
+    /// offset if an earlier attempt failed. `on_progress` is called after
+    /// every file and chunk.
+    pub async fn upload_incremental(
+        &self,
+        game_id: &str,
+        save_dir: &Path,
+        dry_run: bool,
+        on_progress: &(dyn Fn(&SaveUploadProgress) + Send + Sync),
+    ) -> Result<SaveUploadReport> {
+        if !save_dir.is_dir() {
+            return Err(LauncherError::NotFound(format!(
+                "save folder not found: {}",
+                save_dir.display()
+            )));
+        }
+        let dir = save_dir.to_path_buf();
+        let local = tokio::task::spawn_blocking(move || local_manifest(&dir))
+            .await
+            .map_err(|err| LauncherError::Config(format!("save scan join error: {err}")))??;
+        let remote = self.remote_files(game_id).await?;
+        if local.is_empty() && !remote.is_empty() {
+            return Err(LauncherError::Config(format!(
+                "save folder {} is empty; refusing to delete {} cloud files",
+                save_dir.display(),
+                remote.len()
+            )));
+        }
+        let plan = plan_upload(&local, &remote);
+        let report = SaveUploadReport {
+            game_id: game_id.to_string(),
+            dry_run,
+            upload: plan.upload.iter().map(|file| file.path.clone()).collect(),
+            delete: plan.delete.clone(),
+            unchanged: plan.unchanged,
+            upload_bytes: plan.upload.iter().map(|file| file.size).sum(),
+        };
+        if dry_run {
+            return Ok(report);
+        }
+
+        let mut progress = SaveUploadProgress {
+            game_id: game_id.to_string(),
+            path: String::new(),
+            uploaded_bytes: 0,
+            total_bytes: report.upload_bytes,
+        };
+        for file in &plan.upload {
+            progress.path = file.path.clone();
+            let done_before = progress.uploaded_bytes;
+            if file.size >= CHUNKED_UPLOAD_THRESHOLD {
+                self.upload_chunked(game_id, save_dir, file, |offset| {
+                    progress.uploaded_bytes = done_before + offset;
+                    on_progress(&progress);
+                })
+                .await?;
+            } else {
+                self.upload_file(game_id, save_dir, file).await?;
+            }
+            progress.uploaded_bytes = done_before + file.size;
+            on_progress(&progress);
+        }
+        for relative in &plan.delete {
+            let path = format!(
+                "/cloud-saves/{}/files/{}",
+                urlencoding::encode(game_id),
+                urlencoding::encode(relative)
+            );
+            let _: serde_json::Value = self.api.delete(&path, true).await?;
+        }
+
+        match self.fetch_save(game_id).await {
+            Ok(remote) => self.mark_synced(game_id, save_dir, &remote).await?,
+            Err(err) => tracing::warn!("cloud save uploaded but sync state not recorded: {}", err),
+        }
+        Ok(report)
+    }
+
+    async fn upload_file(
+        &self,
+        game_id: &str,
+        save_dir: &Path,
+        file: &CloudSaveFile,
+    ) -> Result<()> {
+        let bytes = tokio::fs::read(save_dir.join(&file.path)).await?;
+        let path = format!(
+            "/cloud-saves/{}/files/{}",
+            urlencoding::encode(game_id),
+            urlencoding::encode(&file.path)
+        );
+        let (api, path, bytes) = (&self.api, &path, &bytes);
+        with_retry(self.api.config(), &file.path, || async move {
+            let response = api
+                .raw_request(Method::PUT, path, true)
+                .await?
+                .header("X-Content-Sha256", &file.sha256)
+                .body(bytes.clone())
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                return Err(LauncherError::Http(format!(
+                    "HTTP {}: save upload failed for {}",
+                    response.status().as_u16(),
+                    file.path
+                )));
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Sends `file` through an upload session, picking up an unfinished
+    /// session for the same content. `on_offset` gets each acknowledged
+    /// offset.
+    async fn upload_chunked(
+        &self,
+        game_id: &str,
+        save_dir: &Path,
+        file: &CloudSaveFile,
+        mut on_offset: impl FnMut(u64),
+    ) -> Result<()> {
+        let key = format!("{game_id}/{}", file.path);
+        let base = format!("/cloud-saves/{}/uploads", urlencoding::encode(game_id));
+        let resumed = match resumable_upload(pending_uploads(&self.db).remove(&key), file) {
+            Some(pending) => {
+                let status_path = format!("{base}/{}", urlencoding::encode(&pending.upload_id));
+                match self.api.get::<UploadSession>(&status_path, true).await {
+                    Ok(session) => Some(session),
+                    Err(LauncherError::Http(message)) if message.starts_with("HTTP 404") => None,
+                    Err(err) => return Err(err),
+                }
+            }
+            None => None,
+        };
+        let mut session = match resumed {
+            Some(session) => {
+                tracing::info!(
+                    "resuming save upload of {} at {} of {} bytes",
+                    file.path,
+                    session.offset,
+                    file.size
+                );
+                session
+            }
+            None => {
+                let request = serde_json::json!({
+                    "path": file.path,
+                    "size": file.size,
+                    "sha256": file.sha256,
+                    "chunk_size": UPLOAD_CHUNK_SIZE,
+                });
+                self.api.post(&base, request, true).await?
+            }
+        };
+        set_pending_upload(
+            &self.db,
+            &key,
+            Some(PendingUpload {
+                upload_id: session.upload_id.clone(),
+                sha256: file.sha256.clone(),
+                size: file.size,
+            }),
+        )?;
+
+        let session_path = format!("{base}/{}", urlencoding::encode(&session.upload_id));
+        let mut reader = tokio::fs::File::open(save_dir.join(&file.path)).await?;
+        on_offset(session.offset);
+        let mut resyncs = 0u32;
+        while session.offset < file.size {
+            let start = session.offset;
+            let len = UPLOAD_CHUNK_SIZE.min(file.size - start);
+            let mut chunk = vec![0u8; len as usize];
+            reader.seek(SeekFrom::Start(start)).await?;
+            reader.read_exact(&mut chunk).await?;
+            let acked = with_retry(self.api.config(), &file.path, || {
+                self.put_chunk(&session_path, start, &chunk, file.size)
+            })
+            .await?;
+            session.offset = match acked {
+                Some(offset) => acknowledged_offset(file, start, offset)?,
+                // The server is at a different offset; ask where to continue.
+                None => {
+                    resyncs += 1;
+                    if resyncs > MAX_UPLOAD_RESYNCS {
+                        return Err(LauncherError::Http(format!(
+                            "save upload of {} kept losing its place on the server",
+                            file.path
+                        )));
+                    }
+                    let offset = self
+                        .api
+                        .get::<UploadSession>(&session_path, true)
+                        .await?
+                        .offset;
+                    if offset > file.size {
+                        return Err(LauncherError::Http(format!(
+                            "save upload of {} acknowledged past its end",
+                            file.path
+                        )));
+                    }
+                    offset
+                }
+            };
+            on_offset(session.offset);
+        }
+
+        let _: serde_json::Value = self
+            .api
+            .post(
+                &format!("{session_path}/complete"),
+                serde_json::json!({}),
+                true,
+            )
+            .await?;
+        set_pending_upload(&self.db, &key, None)
+    }
+
+    /// The offset the server acknowledged, or `None` when it rejected
+    /// `start` as out of step with its own.
+    async fn put_chunk(
+        &self,
+        session_path: &str,
+        start: u64,
+        chunk: &[u8],
+        total: u64,
+    ) -> Result<Option<u64>> {
+        let end = start + chunk.len() as u64 - 1;
+        let response = self
+            .api
+            .raw_request(Method::PUT, session_path, true)
+            .await?
+            .header("Content-Range", format!("bytes {start}-{end}/{total}"))
+            .header("X-Content-Sha256", hex::encode(Sha256::digest(chunk)))
+            .body(chunk.to_vec())
+            .send()
+            .await?;
+        let status = response.status();
+        if status == StatusCode::CONFLICT || status == StatusCode::RANGE_NOT_SATISFIABLE {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(LauncherError::Http(format!(
+                "HTTP {}: {}",
+                status.as_u16(),
+                text
+            )));
+        }
+        Ok(Some(response.json::<UploadSession>().await?.offset))
+    }
+
+    /// Records `save_dir` and `remote` as in sync. Call after an upload or
+    /// after a download has been written to disk.
+    pub async fn mark_synced(
+        &self,
+        game_id: &str,
+        save_dir: &Path,
+        remote: &CloudSave,
+    ) -> Result<()> {
+        let local = scan_local(save_dir).await?;
+        self.db.save_cloud_snapshot(&CloudSaveSnapshot {
+            game_id: game_id.to_string(),
+            local_hash: local.map(|state| state.hash).unwrap_or_default(),
+            remote_version: remote.version.clone(),
+            synced_at: chrono::Utc::now().timestamp(),
+        })
+    }
+
+    /// Three-way comparison of the local saves, the cloud copy and the state
+    /// recorded at the last sync.
+    pub async fn resolve(&self, game_id: &str, save_dir: &Path) -> Result<SaveSyncResolution> {
+        let remote = match self.fetch_save(game_id).await {
+            Ok(remote) => Some(remote),
+            Err(LauncherError::Http(message)) if message.starts_with("HTTP 404") => None,
+            Err(err) => return Err(err),
+        };
+        let local = scan_local(save_dir).await?;
+        let snapshot = self.db.get_cloud_snapshot(game_id)?;
+
+        let status = classify(
+            local.as_ref().map(|state| state.hash.as_str()),
+            remote.as_ref().map(|save| save.version.as_str()),
+            snapshot.as_ref(),
+        );
+        let local_newer = match (&local, &remote) {
+            (Some(local), Some(remote)) => chrono::DateTime::parse_from_rfc3339(&remote.updated_at)
+                .map(|updated| local.modified_at > updated.timestamp())
+                .unwrap_or(true),
+            _ => local.is_some(),
+        };
+        Ok(SaveSyncResolution {
+            game_id: game_id.to_string(),
+            status,
+            recommended: status.recommended_action(local_newer),
+            local_hash: local.as_ref().map(|state| state.hash.clone()),
+            local_modified_at: local.as_ref().map(|state| state.modified_at),
+            remote_version: remote.as_ref().map(|save| save.version.clone()),
+            remote_updated_at: remote.map(|save| save.updated_at),
+            last_synced_at: snapshot.map(|snapshot| snapshot.synced_at),
+        })
+    }
+
+    /// Folder save backups go to, if the user picked one.
+    pub fn backup_dir(&self) -> Result<Option<PathBuf>> {
+        Ok(self
+            .db
+            .get_setting(BACKUP_DIR_SETTING)?
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from))
+    }
+
+    /// `None` goes back to the default folder.
+    pub fn set_backup_dir(&self, dir: Option<&str>) -> Result<()> {
+        match dir.map(str::trim).filter(|dir| !dir.is_empty()) {
+            Some(dir) => self.db.set_setting(BACKUP_DIR_SETTING, dir),
+            None => self.db.delete_setting(BACKUP_DIR_SETTING),
+        }
+    }
+
+    /// Copies `locations` of `game_id` into a new timestamped folder under
+    /// `backup_root`. Returns `None` when the game has no local saves.
+    pub async fn backup_saves(
+        &self,
+        game_id: &str,
+        locations: Vec<PathBuf>,
+        backup_root: &Path,
+    ) -> Result<Option<SaveBackup>> {
+        if locations.is_empty() {
+            return Ok(None);
+        }
+        let target = backup_root
+            .join(sanitize_component(game_id))
+            .join(chrono::Local::now().format("%Y%m%d-%H%M%S").to_string());
+        tokio::task::spawn_blocking(move || {
+            let mut backup = SaveBackup {
+                path: target.to_string_lossy().to_string(),
+                locations: locations.len(),
+                files: 0,
+                bytes: 0,
+            };
+            for (index, location) in locations.iter().enumerate() {
+                let name = location
+                    .file_name()
+                    .map(|name| sanitize_component(&name.to_string_lossy()))
+                    .unwrap_or_else(|| "saves".to_string());
+                copy_tree(
+                    location,
+                    &target.join(format!("{index}-{name}")),
+                    &mut backup,
+                )?;
+            }
+            Ok(Some(backup))
+        })
+        .await
+        .map_err(|err| LauncherError::Config(format!("save backup join error: {err}")))?
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AfterPlaySync {
+    Off,
+    /// Emit `saves-changed` so the UI can offer a sync.
+    Notify,
+    /// Upload changed saves, then emit `saves-changed`.
+    Upload,
+}
+
+impl AfterPlaySync {
+    fn as_str(self) -> &'static str {
+        match self {
+            AfterPlaySync::Off => "off",
+            AfterPlaySync::Notify => "notify",
+            AfterPlaySync::Upload => "upload",
+        }
+    }
+}
+
+/// Emitted as `saves-changed` when a game exits with different saves than
+/// it launched with.
+#[derive(Serialize, Clone, Debug)]
+pub struct SavesChanged {
+    pub game_id: String,
+    pub locations: Vec<String>,
+    /// Set when the saves were uploaded automatically.
+    pub uploaded: Option<SaveUploadReport>,
+    /// Sync state found before an automatic upload; anything but
+    /// `local_newer` leaves the saves for the user to resolve.
+    pub sync_status: Option<SaveSyncStatus>,
+}
+
+/// Where `backup_saves` put a game's saves.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveBackup {
+    pub path: String,
+    pub locations: usize,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Save folders as the backend lists them, with environment variables
+/// expanded. Folders that don't exist locally are left out.
+pub fn resolve_save_locations(raw: &[String]) -> Vec<PathBuf> {
+    raw.iter()
+        .map(|location| PathBuf::from(expand_env_vars(location)))
+        .filter(|location| location.is_dir())
+        .collect()
+}
+
+/// Expands `%VAR%`, `$VAR`/`${VAR}` and a leading `~`. Unknown variables are
+/// kept as written.
+fn expand_env_vars(raw: &str) -> String {
+    let mut value = raw.trim().to_string();
+    if let Some(rest) = value.strip_prefix('~') {
+        if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+            value = format!("{}{}", home.to_string_lossy(), rest);
+        }
+    }
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value.as_str();
+    while let Some(start) = rest.find(['%', '$']) {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let (name, consumed) = if let Some(body) = tail.strip_prefix('%') {
+            match body.find('%') {
+                Some(end) => (&body[..end], end + 2),
+                None => ("", 0),
+            }
+        } else if let Some(body) = tail.strip_prefix("${") {
+            match body.find('}') {
+                Some(end) => (&body[..end], end + 3),
+                None => ("", 0),
+            }
+        } else {
+            let body = &tail[1..];
+            let end = body
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+                .unwrap_or(body.len());
+            (&body[..end], end + 1)
+        };
+        match std::env::var(name).ok().filter(|_| !name.is_empty()) {
+            Some(expanded) => out.push_str(&expanded),
+            None => out.push_str(&tail[..consumed.max(1)]),
+        }
+        rest = &tail[consumed.max(1)..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn sanitize_component(value: &str) -> String {
+    value
+        .chars()
+        .map(|ch| match ch {
+            '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            _ => ch,
+        })
+        .collect()
+}
+
+fn copy_tree(source: &Path, target: &Path, backup: &mut SaveBackup) -> Result<()> {
+    std::fs::create_dir_all(target)?;
+    for entry in std::fs::read_dir(source)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        if path.is_dir() {
+            copy_tree(&path, &target.join(name), backup)?;
+        } else {
+            backup.bytes += std::fs::copy(&path, target.join(name))?;
+            backup.files += 1;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveSyncStatus {
+    UpToDate,
+    LocalNewer,
+    RemoteNewer,
+    /// Both sides changed since the last sync, or there is no sync record to
+    /// tell which one did.
+    Conflict,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveSyncAction {
+    None,
+    Upload,
+    Download,
+    /// Ask the user; `prefer_local` says which copy was modified last.
+    Choose {
+        prefer_local: bool,
+    },
+}
+
+impl SaveSyncStatus {
+    pub fn recommended_action(self, local_newer: bool) -> SaveSyncAction {
+        match self {
+            Self::UpToDate => SaveSyncAction::None,
+            Self::LocalNewer => SaveSyncAction::Upload,
+            Self::RemoteNewer => SaveSyncAction::Download,
+            Self::Conflict => SaveSyncAction::Choose {
+                prefer_local: local_newer,
+            },
+        }
+    }
+}
+
+/// Classifies the sync state from the local tree hash, the remote version
+/// and the snapshot taken at the last sync.
+pub fn classify(
+    local_hash: Option<&str>,
+    remote_version: Option<&str>,
+    snapshot: Option<&CloudSaveSnapshot>,
+) -> SaveSyncStatus {
+    match (local_hash, remote_version) {
+        (None, None) => SaveSyncStatus::UpToDate,
+        (Some(_), None) => SaveSyncStatus::LocalNewer,
+        (None, Some(_)) => SaveSyncStatus::RemoteNewer,
+        (Some(local), Some(remote)) => {
+            let Some(snapshot) = snapshot else {
+                return SaveSyncStatus::Conflict;
+            };
+            let local_changed = snapshot.local_hash != local;
+            let remote_changed = snapshot.remote_version != remote;
+            match (local_changed, remote_changed) {
+                (false, false) => SaveSyncStatus::UpToDate,
+                (true, false) => SaveSyncStatus::LocalNewer,
+                (false, true) => SaveSyncStatus::RemoteNewer,
+                (true, true) => SaveSyncStatus::Conflict,
+            }
+        }
+    }
+}
+
+/// Retries `op` on timeouts, connection failures and 408/429/5xx replies
+/// with the API client's retry count and doubling backoff.
+async fn with_retry<T, F, Fut>(config: ApiClientConfig, what: &str, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let mut retries = 0;
+    loop {
+        match op().await {
+            Err(err) if retries < config.max_retries && is_retryable(&err) => {
+                retries += 1;
+                tracing::warn!(
+                    "save upload of {} failed [retry {}/{}]: {}",
+                    what,
+                    retries,
+                    config.max_retries,
+                    err
+                );
+                tokio::time::sleep(config.retry_delay(retries)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_retryable(err: &LauncherError) -> bool {
+    match err {
+        LauncherError::Network(err) => err.is_timeout() || err.is_connect(),
+        LauncherError::Http(message) => ["HTTP 408", "HTTP 429", "HTTP 5"]
+            .iter()
+            .any(|prefix| message.starts_with(prefix)),
+        _ => false,
+    }
+}
+
+fn pending_uploads(db: &Database) -> HashMap<String, PendingUpload> {
+    let Some(raw) = db.get_setting(PENDING_UPLOADS_SETTING).ok().flatten() else {
+        return HashMap::new();
+    };
+    serde_json::from_str(&raw).unwrap_or_else(|err| {
+        tracing::warn!("ignoring invalid {PENDING_UPLOADS_SETTING}: {err}");
+        HashMap::new()
+    })
+}
+
+fn set_pending_upload(db: &Database, key: &str, pending: Option<PendingUpload>) -> Result<()> {
+    let mut uploads = pending_uploads(db);
+    match pending {
+        Some(pending) => uploads.insert(key.to_string(), pending),
+        None => uploads.remove(key),
+    };
+    if uploads.is_empty() {
+        return db.delete_setting(PENDING_UPLOADS_SETTING);
+    }
+    db.set_setting(PENDING_UPLOADS_SETTING, &serde_json::to_string(&uploads)?)
+}
+
+/// The unfinished session to pick up for `file`, if it was for the same
+/// content.
+fn resumable_upload(pending: Option<PendingUpload>, file: &CloudSaveFile) -> Option<PendingUpload> {
+    pending.filter(|pending| pending.sha256 == file.sha256 && pending.size == file.size)
+}
+
+/// Checks the offset the server acknowledged for a chunk sent at `start`:
+/// it has to move forward and stay within the file.
+fn acknowledged_offset(file: &CloudSaveFile, start: u64, acked: u64) -> Result<u64> {
+    if acked > file.size {
+        return Err(LauncherError::Http(format!(
+            "save upload of {} acknowledged past its end",
+            file.path
+        )));
+    }
+    if acked <= start {
+        return Err(LauncherError::Http(format!(
+            "save upload of {} stopped advancing at {} bytes",
+            file.path, acked
+        )));
+    }
+    Ok(acked)
+}
+
+/// One file in a save directory, relative to its root.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CloudSaveFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Files to send and to remove so the cloud copy matches `local`.
+#[derive(Clone, Debug, Default)]
+pub struct SaveUploadPlan {
+    pub upload: Vec<CloudSaveFile>,
+    pub delete: Vec<String>,
+    pub unchanged: usize,
+}
+
+/// Compares the local manifest with the cloud one. An empty `local` never
+/// plans deletes: a save folder that yields nothing is more
+/// likely moved or unreadable than cleared on purpose.
+pub fn plan_upload(local: &[CloudSaveFile], remote: &[CloudSaveFile]) -> SaveUploadPlan {
+    let remote_by_path = remote
+        .iter()
+        .map(|file| (file.path.as_str(), file))
+        .collect::<HashMap<_, _>>();
+    let mut plan = SaveUploadPlan::default();
+    for file in local {
+        match remote_by_path.get(file.path.as_str()) {
+            Some(existing)
+                if existing.size == file.size
+                    && existing.sha256.eq_ignore_ascii_case(&file.sha256) =>
+            {
+                plan.unchanged += 1
+            }
+            _ => plan.upload.push(file.clone()),
+        }
+    }
+    if local.is_empty() {
+        return plan;
+    }
+    let local_paths = local
+        .iter()
+        .map(|file| file.path.as_str())
+        .collect::<std::collections::HashSet<_>>();
+    plan.delete = remote
+        .iter()
+        .filter(|file| !local_paths.contains(file.path.as_str()))
+        .map(|file| file.path.clone())
+        .collect();
+    plan
+}
+
+/// Sorted per-file manifest of a save directory; empty if it doesn't exist.
+fn local_manifest(save_dir: &Path) -> Result<Vec<CloudSaveFile>> {
+    if !save_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    relative_files(save_dir)?
+        .into_iter()
+        .map(|relative| {
+            let path = save_dir.join(&relative);
+            Ok(CloudSaveFile {
+                size: std::fs::metadata(&path)?.len(),
+                sha256: patch_engine::sha256_file(&path)?,
+                path: relative,
+            })
+        })
+        .collect()
+}
+
+/// Fingerprint of every save folder; a folder without files gets an empty
+/// one.
+fn fingerprint_all(locations: &[PathBuf]) -> HashMap<PathBuf, String> {
+    locations
+        .iter()
+        .map(|location| {
+            let fingerprint = fingerprint_dir(location).unwrap_or_else(|err| {
+                tracing::warn!("failed to scan saves in {}: {}", location.display(), err);
+                String::new()
+            });
+            (location.clone(), fingerprint)
+        })
+        .collect()
+}
+
+/// Save folders whose fingerprint differs from the one taken at launch,
+/// sorted.
+fn changed_locations(
+    before: &HashMap<PathBuf, String>,
+    after: &HashMap<PathBuf, String>,
+) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = after
+        .iter()
+        .filter(|(location, fingerprint)| before.get(*location) != Some(*fingerprint))
+        .map(|(location, _)| location.clone())
+        .collect();
+    changed.sort();
+    changed
+}
+
+/// Hash of every file's path, size and mtime; cheap enough to take on each
+/// launch and exit.
+fn fingerprint_dir(dir: &Path) -> Result<String> {
+    let paths = relative_files(dir)?;
+    if paths.is_empty() {
+        return Ok(String::new());
+    }
+    let mut hasher = Sha256::new();
+    for relative in &paths {
+        let metadata = std::fs::metadata(dir.join(relative))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|value| value.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|value| value.as_nanos())
+            .unwrap_or_default();
+        hasher.update(relative.as_bytes());
+        hasher.update([0]);
+        hasher.update(metadata.len().to_le_bytes());
+        hasher.update(modified.to_le_bytes());
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hashes `save_dir` on the blocking pool.
+async fn scan_local(save_dir: &Path) -> Result<Option<LocalSaveState>> {
+    let dir = save_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || LocalSaveState::scan(&dir))
+        .await
+        .map_err(|err| LauncherError::Config(format!("save scan join error: {err}")))?
+}
+
+/// Content hash and newest mtime of a save directory.
+#[derive(Clone, Debug)]
+struct LocalSaveState {
+    hash: String,
+    modified_at: i64,
+}
+
+impl LocalSaveState {
+    /// `None` when the directory is missing or holds no files.
+    fn scan(save_dir: &Path) -> Result<Option<Self>> {
+        let files = local_manifest(save_dir)?;
+        if files.is_empty() {
+            return Ok(None);
+        }
+
+        let mut hasher = Sha256::new();
+        let mut modified_at = 0_i64;
+        for file in &files {
+            if let Some(modified) = std::fs::metadata(save_dir.join(&file.path))?
+                .modified()
+                .ok()
+                .and_then(|value| value.duration_since(std::time::UNIX_EPOCH).ok())
+            {
+                modified_at = modified_at.max(modified.as_secs() as i64);
+            }
+            hasher.update(file.path.as_bytes());
+            hasher.update([0]);
+            hasher.update(file.sha256.as_bytes());
+        }
+        Ok(Some(Self {
+            hash: hex::encode(hasher.finalize()),
+            modified_at,
+        }))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveUploadReport {
+    pub game_id: String,
+    pub dry_run: bool,
+    pub upload: Vec<String>,
+    pub delete: Vec<String>,
+    pub unchanged: usize,
+    pub upload_bytes: u64,
+}
+
+/// Emitted while changed save files upload.
+#[derive(Serialize, Clone, Debug)]
+pub struct SaveUploadProgress {
+    pub game_id: String,
+    /// The file being sent.
+    pub path: String,
+    pub uploaded_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// The server's view of a chunked upload.
+#[derive(Deserialize, Clone, Debug)]
+struct UploadSession {
+    upload_id: String,
+    #[serde(default)]
+    offset: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PendingUpload {
+    upload_id: String,
+    sha256: String,
+    size: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveSyncResolution {
+    pub game_id: String,
+    pub status: SaveSyncStatus,
+    pub recommended: SaveSyncAction,
+    pub local_hash: Option<String>,
+    pub local_modified_at: Option<i64>,
+    pub remote_version: Option<String>,
+    pub remote_updated_at: Option<String>,
+    pub last_synced_at: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -47,3 +1039,288 @@ pub struct CloudSave {
     pub version: String,
     pub updated_at: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_every_variable_syntax_and_keeps_unknown_ones() {
+        std::env::set_var("OTOSHI_TEST_SAVE_ROOT", "/saves");
+        assert_eq!(
+            expand_env_vars("%OTOSHI_TEST_SAVE_ROOT%/Game"),
+            "/saves/Game"
+        );
+        assert_eq!(expand_env_vars("$OTOSHI_TEST_SAVE_ROOT/a"), "/saves/a");
+        assert_eq!(expand_env_vars(" ${OTOSHI_TEST_SAVE_ROOT}x "), "/savesx");
+        assert_eq!(
+            expand_env_vars("%OTOSHI_TEST_UNSET_VAR%/a/$OTOSHI_TEST_UNSET_VAR"),
+            "%OTOSHI_TEST_UNSET_VAR%/a/$OTOSHI_TEST_UNSET_VAR"
+        );
+        assert_eq!(expand_env_vars("100% done $"), "100% done $");
+    }
+
+    #[test]
+    fn plans_deletes_only_against_a_non_empty_local_tree() {
+        let file = |path: &str, sha256: &str| CloudSaveFile {
+            path: path.to_string(),
+            size: 4,
+            sha256: sha256.to_string(),
+        };
+        let remote = vec![file("slot1.sav", "aa"), file("slot2.sav", "bb")];
+
+        let plan = plan_upload(&[file("slot1.sav", "AA"), file("slot3.sav", "cc")], &remote);
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.upload, vec![file("slot3.sav", "cc")]);
+        assert_eq!(plan.delete, vec!["slot2.sav".to_string()]);
+
+        let plan = plan_upload(&[], &remote);
+        assert!(plan.upload.is_empty());
+        assert!(plan.delete.is_empty());
+    }
+
+    fn big_file() -> CloudSaveFile {
+        CloudSaveFile {
+            path: "world.sav".to_string(),
+            size: 3 * UPLOAD_CHUNK_SIZE,
+            sha256: "abcd".to_string(),
+        }
+    }
+
+    #[test]
+    fn uploads_resume_only_sessions_for_the_same_content() {
+        let file = big_file();
+        let pending = |sha256: &str, size: u64| PendingUpload {
+            upload_id: "up-1".to_string(),
+            sha256: sha256.to_string(),
+            size,
+        };
+        assert_eq!(
+            resumable_upload(Some(pending("abcd", file.size)), &file)
+                .map(|pending| pending.upload_id),
+            Some("up-1".to_string())
+        );
+        assert!(resumable_upload(Some(pending("ffff", file.size)), &file).is_none());
+        assert!(resumable_upload(Some(pending("abcd", 1)), &file).is_none());
+        assert!(resumable_upload(None, &file).is_none());
+    }
+
+    #[test]
+    fn acknowledged_offsets_must_advance_within_the_file() {
+        let file = big_file();
+        assert_eq!(
+            acknowledged_offset(&file, 0, UPLOAD_CHUNK_SIZE).unwrap(),
+            UPLOAD_CHUNK_SIZE
+        );
+        assert!(acknowledged_offset(&file, UPLOAD_CHUNK_SIZE, UPLOAD_CHUNK_SIZE).is_err());
+        assert!(acknowledged_offset(&file, UPLOAD_CHUNK_SIZE, 0).is_err());
+        assert!(acknowledged_offset(&file, 0, file.size + 1).is_err());
+    }
+
+    #[test]
+    fn retries_only_transient_upload_failures() {
+        let http = |message: &str| LauncherError::Http(message.to_string());
+        assert!(is_retryable(&http("HTTP 408: timeout")));
+        assert!(is_retryable(&http("HTTP 429: slow down")));
+        assert!(is_retryable(&http("HTTP 503: busy")));
+        assert!(!is_retryable(&http("HTTP 404: gone")));
+        assert!(!is_retryable(&http("save upload stopped advancing")));
+        assert!(!is_retryable(&LauncherError::Auth("expired".to_string())));
+    }
+
+    #[test]
+    fn pending_uploads_are_kept_per_file_until_cleared() {
+        let db = crate::db::open_temp();
+        let pending = |upload_id: &str| PendingUpload {
+            upload_id: upload_id.to_string(),
+            sha256: "abcd".to_string(),
+            size: 4,
+        };
+        set_pending_upload(&db, "game/a.sav", Some(pending("up-a"))).unwrap();
+        set_pending_upload(&db, "game/b.sav", Some(pending("up-b"))).unwrap();
+        set_pending_upload(&db, "game/a.sav", Some(pending("up-a2"))).unwrap();
+
+        let uploads = pending_uploads(&db);
+        assert_eq!(uploads.len(), 2);
+        assert_eq!(uploads["game/a.sav"].upload_id, "up-a2");
+
+        set_pending_upload(&db, "game/a.sav", None).unwrap();
+        set_pending_upload(&db, "game/b.sav", None).unwrap();
+        assert!(pending_uploads(&db).is_empty());
+        assert!(db.get_setting(PENDING_UPLOADS_SETTING).unwrap().is_none());
+    }
+
+    fn temp_saves() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("otoshi-saves-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("slots")).unwrap();
+        dir
+    }
+
+    fn offline_service() -> CloudSaveService {
+        let db = crate::db::open_temp();
+        let auth = crate::services::AuthService::new(
+            "http://127.0.0.1:9".to_string(),
+            db.clone(),
+            vec![7; 32],
+        );
+        let config = ApiClientConfig {
+            max_retries: 0,
+            ..ApiClientConfig::default()
+        };
+        let api = ApiClient::new("http://127.0.0.1:9".to_string(), auth, config);
+        CloudSaveService::new(api, db)
+    }
+
+    #[test]
+    fn fingerprints_follow_file_sizes_and_names() {
+        let dir = temp_saves();
+        assert_eq!(fingerprint_dir(&dir).unwrap(), "");
+
+        std::fs::write(dir.join("slots/slot1.sav"), b"level 1").unwrap();
+        let first = fingerprint_dir(&dir).unwrap();
+        assert_eq!(fingerprint_dir(&dir).unwrap(), first);
+
+        std::fs::write(dir.join("slots/slot1.sav"), b"level 12").unwrap();
+        let resized = fingerprint_dir(&dir).unwrap();
+        assert_ne!(resized, first);
+
+        std::fs::rename(dir.join("slots/slot1.sav"), dir.join("slots/slot2.sav")).unwrap();
+        assert_ne!(fingerprint_dir(&dir).unwrap(), resized);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn classification_compares_both_sides_with_the_last_sync() {
+        let snapshot = CloudSaveSnapshot {
+            game_id: "game".to_string(),
+            local_hash: "local-1".to_string(),
+            remote_version: "v1".to_string(),
+            synced_at: 0,
+        };
+        let cases = [
+            (None, None, Some(&snapshot), SaveSyncStatus::UpToDate),
+            (
+                Some("local-1"),
+                None,
+                Some(&snapshot),
+                SaveSyncStatus::LocalNewer,
+            ),
+            (
+                None,
+                Some("v1"),
+                Some(&snapshot),
+                SaveSyncStatus::RemoteNewer,
+            ),
+            (
+                Some("local-1"),
+                Some("v1"),
+                Some(&snapshot),
+                SaveSyncStatus::UpToDate,
+            ),
+            (
+                Some("local-2"),
+                Some("v1"),
+                Some(&snapshot),
+                SaveSyncStatus::LocalNewer,
+            ),
+            (
+                Some("local-1"),
+                Some("v2"),
+                Some(&snapshot),
+                SaveSyncStatus::RemoteNewer,
+            ),
+            (
+                Some("local-2"),
+                Some("v2"),
+                Some(&snapshot),
+                SaveSyncStatus::Conflict,
+            ),
+            (Some("local-1"), Some("v1"), None, SaveSyncStatus::Conflict),
+        ];
+        for (local, remote, snapshot, expected) in cases {
+            assert_eq!(
+                classify(local, remote, snapshot),
+                expected,
+                "{local:?} {remote:?}"
+            );
+        }
+        assert_eq!(
+            SaveSyncStatus::Conflict.recommended_action(false),
+            SaveSyncAction::Choose {
+                prefer_local: false
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn marking_synced_records_the_hash_a_later_edit_changes() {
+        let service = offline_service();
+        let dir = temp_saves();
+        std::fs::write(dir.join("slots/slot1.sav"), b"level 1").unwrap();
+        let remote = CloudSave {
+            id: "save".to_string(),
+            user_id: "user".to_string(),
+            game_id: "game".to_string(),
+            payload: serde_json::Value::Null,
+            version: "v1".to_string(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+
+        service.mark_synced("game", &dir, &remote).await.unwrap();
+        let snapshot = service.db.get_cloud_snapshot("game").unwrap().unwrap();
+        let local = scan_local(&dir).await.unwrap().unwrap();
+        assert_eq!(snapshot.local_hash, local.hash);
+        assert_eq!(
+            classify(Some(&local.hash), Some("v1"), Some(&snapshot)),
+            SaveSyncStatus::UpToDate
+        );
+
+        std::fs::write(dir.join("slots/slot1.sav"), b"level 2").unwrap();
+        let edited = scan_local(&dir).await.unwrap().unwrap();
+        assert_eq!(
+            classify(Some(&edited.hash), Some("v1"), Some(&snapshot)),
+            SaveSyncStatus::LocalNewer
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn after_play_reports_changed_folders_and_never_uploads_blind() {
+        let service = offline_service();
+        let dir = temp_saves();
+        std::fs::write(dir.join("slots/slot1.sav"), b"level 1").unwrap();
+
+        assert!(service
+            .check_after_play("game", vec![dir.clone()])
+            .await
+            .unwrap()
+            .is_none());
+
+        service
+            .record_launch("game", vec![dir.clone()])
+            .await
+            .unwrap();
+        assert!(service
+            .check_after_play("game", vec![dir.clone()])
+            .await
+            .unwrap()
+            .is_none());
+
+        service.set_after_play_sync(AfterPlaySync::Upload).unwrap();
+        service
+            .record_launch("game", vec![dir.clone()])
+            .await
+            .unwrap();
+        std::fs::write(dir.join("slots/slot1.sav"), b"level 12").unwrap();
+        let changed = service
+            .check_after_play("game", vec![dir.clone()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(changed.locations, [dir.to_string_lossy().to_string()]);
+        // The cloud copy couldn't be checked, so nothing was uploaded.
+        assert!(changed.uploaded.is_none());
+        assert!(changed.sync_status.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,256 @@
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::Serialize;
+
+use crate::db::queries::OfflineQueueQueries;
+use crate::db::Database;
+use crate::errors::{LauncherError, Result};
+use crate::services::ApiClient;
+
+const ONLINE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const OFFLINE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConnectivityStatus {
+    pub online: bool,
+    /// Writes waiting for the backend to come back.
+    pub queued_writes: usize,
+}
+
+/// Tracks whether the backend is reachable and holds writes made while it
+/// isn't, replaying them in order once it is. Only the latest write per key
+/// is kept, and one that reaches the backend directly drops the queued ones.
+#[derive(Clone)]
+pub struct ConnectivityService {
+    api: ApiClient,
+    db: Database,
+}
+
+impl ConnectivityService {
+    pub fn new(api: ApiClient, db: Database) -> Self {
+        Self { api, db }
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.api.is_online()
+    }
+
+    pub fn status(&self) -> Result<ConnectivityStatus> {
+        Ok(ConnectivityStatus {
+            online: self.api.is_online(),
+            queued_writes: self.db.count_offline_requests()?,
+        })
+    }
+
+    /// Queues a JSON write to send once the backend is reachable, replacing
+    /// any write already queued for `key`.
+    pub fn enqueue(
+        &self,
+        key: &str,
+        method: Method,
+        path: &str,
+        body: serde_json::Value,
+    ) -> Result<()> {
+        tracing::info!("queued {} {} until the backend is reachable", method, path);
+        self.db
+            .enqueue_offline_request(key, method.as_str(), path, &body)
+    }
+
+    /// Drops writes queued for `key` after a newer one reached the backend,
+    /// so a replay can't undo it.
+    pub fn supersede(&self, key: &str) {
+        match self.db.remove_offline_requests_for(key) {
+            Ok(0) => {}
+            Ok(removed) => tracing::info!("dropped {} queued writes for {}", removed, key),
+            Err(err) => tracing::warn!("failed to drop queued writes for {}: {}", key, err),
+        }
+    }
+
+    /// Replays queued writes oldest first. Stops at the first transient
+    /// failure; writes the backend rejects are dropped. Returns how many were
+    /// sent.
+    pub async fn flush_queue(&self) -> Result<usize> {
+        let api = self.api.background();
+        let mut sent = 0usize;
+        for queued in self.db.list_offline_requests()? {
+            let Ok(method) = Method::from_bytes(queued.method.as_bytes()) else {
+                self.db.remove_offline_request(queued.id)?;
+                continue;
+            };
+            let response = api
+                .raw_request(method, &queued.path, true)
+                .await?
+                .json(&queued.body)
+                .send()
+                .await?;
+            let status = response.status();
+            if status.is_server_error() || status.as_u16() == 429 {
+                return Err(LauncherError::Http(format!(
+                    "HTTP {}: {}",
+                    status.as_u16(),
+                    response.text().await.unwrap_or_default()
+                )));
+            }
+            if status.is_success() {
+                sent += 1;
+            } else {
+                tracing::warn!(
+                    "dropping queued {} {}: HTTP {}",
+                    queued.method,
+                    queued.path,
+                    status.as_u16()
+                );
+            }
+            self.db.remove_offline_request(queued.id)?;
+        }
+        Ok(sent)
+    }
+
+    /// Polls the backend's health, more often while it's unreachable, calls
+    /// `on_change` whenever reachability flips and flushes the queue while
+    /// online.
+    pub fn spawn_monitor<F>(&self, on_change: F)
+    where
+        F: Fn(&ConnectivityStatus) + Send + Sync + 'static,
+    {
+        let service = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut last_online = true;
+            loop {
+                let online = service.api.check_health().await;
+                if online {
+                    match service.flush_queue().await {
+                        Ok(0) => {}
+                        Ok(sent) => tracing::info!("sent {} queued offline writes", sent),
+                        Err(err) => tracing::debug!("offline queue flush deferred: {}", err),
+                    }
+                }
+                if online != last_online {
+                    last_online = online;
+                    match service.status() {
+                        Ok(status) => on_change(&status),
+                        Err(err) => tracing::warn!("connectivity status unavailable: {}", err),
+                    }
+                }
+                let interval = if online {
+                    ONLINE_CHECK_INTERVAL
+                } else {
+                    OFFLINE_CHECK_INTERVAL
+                };
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::services::api_client::ApiClientConfig;
+    use crate::services::AuthService;
+
+    /// Answers each request with the next status and records its request
+    /// line.
+    fn spawn_backend(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind backend");
+        let base = format!("http://{}", listener.local_addr().expect("backend addr"));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = seen.clone();
+        std::thread::spawn(move || {
+            for (stream, status) in listener.incoming().flatten().zip(statuses) {
+                let mut stream = stream;
+                let mut buffer = [0_u8; 2048];
+                let read = stream.read(&mut buffer).unwrap_or(0);
+                let request = String::from_utf8_lossy(&buffer[..read]);
+                let line = request.lines().next().unwrap_or_default().to_string();
+                recorded.lock().unwrap().push(line);
+                let _ = stream.write_all(
+                    format!(
+                        "HTTP/1.1 {status} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    )
+                    .as_bytes(),
+                );
+            }
+        });
+        (base, seen)
+    }
+
+    fn service(base: &str) -> ConnectivityService {
+        let db = crate::db::open_temp();
+        let auth = AuthService::new(base.to_string(), db.clone(), vec![7; 32]);
+        auth.set_tokens_external(Some("token".to_string()), None)
+            .unwrap();
+        let config = ApiClientConfig {
+            max_retries: 0,
+            ..ApiClientConfig::default()
+        };
+        ConnectivityService::new(ApiClient::new(base.to_string(), auth, config), db)
+    }
+
+    #[tokio::test]
+    async fn flush_replays_the_latest_write_per_key_and_stops_on_server_errors() {
+        let (base, seen) = spawn_backend(vec![200, 404, 503]);
+        let service = service(&base);
+        let body = serde_json::json!({});
+        service
+            .enqueue(
+                "download:1",
+                Method::POST,
+                "downloads/1/pause",
+                body.clone(),
+            )
+            .unwrap();
+        service
+            .enqueue(
+                "download:1",
+                Method::POST,
+                "downloads/1/resume",
+                body.clone(),
+            )
+            .unwrap();
+        service
+            .enqueue(
+                "workshop:7",
+                Method::DELETE,
+                "/workshop/items/7/subscribe",
+                body.clone(),
+            )
+            .unwrap();
+        service
+            .enqueue(
+                "download:2",
+                Method::POST,
+                "downloads/2/cancel",
+                body.clone(),
+            )
+            .unwrap();
+        service
+            .enqueue(
+                "download:3",
+                Method::POST,
+                "downloads/3/pause",
+                body.clone(),
+            )
+            .unwrap();
+        service.supersede("download:3");
+
+        assert!(service.flush_queue().await.is_err());
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                "POST /downloads/1/resume HTTP/1.1",
+                "DELETE /workshop/items/7/subscribe HTTP/1.1",
+                "POST /downloads/2/cancel HTTP/1.1",
+            ]
+        );
+        // The rejected write was dropped; the one that hit a 503 waits.
+        let queued = service.db.list_offline_requests().unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].path, "downloads/2/cancel");
+    }
+}
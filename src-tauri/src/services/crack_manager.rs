@@ -1,22 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tokio::sync::watch;
+use zip::read::ZipFile;
 use zip::ZipArchive;
 
-use crate::db::queries::GameQueries;
+use crate::db::queries::{GameQueries, SettingsQueries};
 use crate::db::Database;
 use crate::errors::{LauncherError, Result};
 use crate::services::ApiClient;
+use crate::utils::{archive, client_identity, game_stores};
 
-const BACKUP_DIR_NAME: &str = ".otoshi-backup";
+/// Folder in the game dir holding the originals of files a crack replaced.
+pub const BACKUP_DIR_NAME: &str = ".otoshi-backup";
 const BACKUP_MANIFEST_FILE: &str = "backup_manifest.json";
+const EXTRACT_WORKERS_SETTING: &str = "crack_extract_workers";
+const MAX_EXTRACT_WORKERS: usize = 16;
+/// Suffix of a file being extracted, renamed over the target once complete.
+const PARTIAL_SUFFIX: &str = ".otoshi-part";
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct CrackDownloadProgress {
@@ -77,12 +86,25 @@ pub struct CrackUninstallResult {
     pub verification_passed: bool,
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct CrackRestoreResult {
+    pub success: bool,
+    pub message: String,
+    pub restored: Vec<String>,
+    /// Requested files with no usable backup: not in the manifest, missing
+    /// from the backup folder, or not matching their recorded hash.
+    pub missing: Vec<String>,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct GameInstallInfo {
     pub installed: bool,
     pub install_path: Option<String>,
     pub game_name: Option<String>,
     pub store_url: Option<String>,
+    /// Which client installed the game: `otoshi`, `steam`, `epic` or `gog`.
+    #[serde(default)]
+    pub store: Option<String>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -96,6 +118,14 @@ pub struct CrackOption {
     pub install_guide: Option<String>,
 }
 
+/// A file entry to extract, by its index in the archive.
+#[derive(Debug)]
+struct ExtractJob {
+    index: usize,
+    relative_path: PathBuf,
+    size: u64,
+}
+
 #[derive(Clone)]
 struct DownloadHandle {
     control: watch::Sender<DownloadControl>,
@@ -120,6 +150,7 @@ pub struct CrackManager {
 impl CrackManager {
     pub fn new(db: Database, api: ApiClient) -> Self {
         let client = reqwest::Client::builder()
+            .user_agent(client_identity::user_agent())
             .timeout(Duration::from_secs(300))
             .pool_max_idle_per_host(4)
             .build()
@@ -134,112 +165,57 @@ impl CrackManager {
         }
     }
 
-    /// Check if a game is installed and get its install path
+    /// Check if a game is installed and get its install path, trying the
+    /// launcher's own library first and then Steam, Epic and GOG.
     pub async fn check_game_installed(&self, app_id: &str) -> Result<GameInstallInfo> {
+        let store_url = Some(format!("/steam/{}", app_id));
+        let mut title = None;
         // Query database for installed games
         if let Ok(games) = self.db.get_games() {
-            for game in games {
-                if game.id == app_id || game.slug == app_id {
+            if let Some(game) = games
+                .into_iter()
+                .find(|game| game.id == app_id || game.slug == app_id)
+            {
+                if game.install_path.is_some() {
                     return Ok(GameInstallInfo {
-                        installed: game.install_path.is_some(),
+                        installed: true,
                         install_path: game.install_path,
                         game_name: Some(game.title),
-                        store_url: Some(format!("/steam/{}", app_id)),
+                        store_url,
+                        store: Some("otoshi".to_string()),
                     });
                 }
+                title = Some(game.title);
             }
         }
 
-        // Check Steam installation paths
-        if let Some(install_path) = self.find_steam_game_path(app_id).await? {
+        let lookup_id = app_id.to_string();
+        let lookup_title = title.clone();
+        let found = tokio::task::spawn_blocking(move || {
+            game_stores::find_installed(&lookup_id, lookup_title.as_deref())
+        })
+        .await
+        .map_err(|err| LauncherError::Config(err.to_string()))?;
+
+        if let Some(game) = found {
             return Ok(GameInstallInfo {
                 installed: true,
-                install_path: Some(install_path),
-                game_name: None,
-                store_url: Some(format!("/steam/{}", app_id)),
+                install_path: Some(game.install_path.to_string_lossy().to_string()),
+                game_name: Some(game.name).filter(|name| !name.is_empty()).or(title),
+                store_url,
+                store: Some(game.store.as_str().to_string()),
             });
         }
 
         Ok(GameInstallInfo {
             installed: false,
             install_path: None,
-            game_name: None,
-            store_url: Some(format!("/steam/{}", app_id)),
+            game_name: title,
+            store_url,
+            store: None,
         })
     }
 
-    /// Find Steam game installation path
-    async fn find_steam_game_path(&self, app_id: &str) -> Result<Option<String>> {
-        #[cfg(target_os = "windows")]
-        {
-            use std::env;
-            use std::fs;
-
-            // Common Steam installation paths on Windows
-            let steam_paths = vec![
-                PathBuf::from("C:\\Program Files (x86)\\Steam"),
-                PathBuf::from("C:\\Program Files\\Steam"),
-                env::var("ProgramFiles(x86)")
-                    .map(|p| PathBuf::from(p).join("Steam"))
-                    .unwrap_or_default(),
-            ];
-
-            for steam_path in steam_paths {
-                let library_folders = steam_path.join("steamapps").join("libraryfolders.vdf");
-                if library_folders.exists() {
-                    // Parse libraryfolders.vdf to find all library paths
-                    let content = fs::read_to_string(&library_folders).unwrap_or_default();
-                    let libraries = self.parse_library_folders(&content);
-
-                    for library in libraries {
-                        let app_manifest = PathBuf::from(&library)
-                            .join("steamapps")
-                            .join(format!("appmanifest_{}.acf", app_id));
-
-                        if app_manifest.exists() {
-                            // Parse appmanifest to get install directory
-                            if let Ok(manifest_content) = fs::read_to_string(&app_manifest) {
-                                if let Some(install_dir) = self.parse_install_dir(&manifest_content)
-                                {
-                                    let game_path = PathBuf::from(&library)
-                                        .join("steamapps")
-                                        .join("common")
-                                        .join(&install_dir);
-                                    if game_path.exists() {
-                                        return Ok(Some(game_path.to_string_lossy().to_string()));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(None)
-    }
-
-    fn parse_library_folders(&self, content: &str) -> Vec<String> {
-        let mut libraries = Vec::new();
-        for line in content.lines() {
-            if line.contains("\"path\"") {
-                if let Some(path) = line.split('"').nth(3) {
-                    libraries.push(path.replace("\\\\", "\\"));
-                }
-            }
-        }
-        libraries
-    }
-
-    fn parse_install_dir(&self, content: &str) -> Option<String> {
-        for line in content.lines() {
-            if line.contains("\"installdir\"") {
-                return line.split('"').nth(3).map(|s| s.to_string());
-            }
-        }
-        None
-    }
-
     /// Download and install crack files
     pub async fn download_crack(
         &self,
@@ -320,7 +296,7 @@ impl CrackManager {
         let strip_depth = self.determine_archive_root_strip_depth(&temp_archive, &game_path)?;
 
         // Backup original files before installing crack
-        let backup_count = self
+        let (backup_count, unchanged) = self
             .backup_original_files(app_id, &game_path, &temp_archive, strip_depth)
             .await?;
 
@@ -329,9 +305,28 @@ impl CrackManager {
 
         // Extract crack files to game directory
         let install_count = self
-            .extract_to_game_dir(&temp_archive, &game_path, app_id, strip_depth)
+            .extract_to_game_dir(
+                &temp_archive,
+                &game_path,
+                app_id,
+                strip_depth,
+                &unchanged,
+                &control,
+            )
             .await?;
 
+        if *control.borrow() == DownloadControl::Cancelled {
+            let _ = std::fs::remove_dir_all(&temp_dir);
+            self.set_status(app_id, CrackDownloadStatus::Cancelled);
+            return Ok(CrackInstallResult {
+                success: false,
+                message: "Extraction cancelled; original files can be restored from the backup"
+                    .to_string(),
+                files_installed: install_count,
+                files_backed_up: backup_count,
+            });
+        }
+
         // Cleanup temp files
         let _ = std::fs::remove_dir_all(&temp_dir);
 
@@ -340,8 +335,15 @@ impl CrackManager {
 
         Ok(CrackInstallResult {
             success: true,
-            message: "Crack installed successfully".to_string(),
-            files_installed: install_count,
+            message: if unchanged.is_empty() {
+                "Crack installed successfully".to_string()
+            } else {
+                format!(
+                    "Crack installed successfully ({} files already up to date)",
+                    unchanged.len()
+                )
+            },
+            files_installed: install_count + unchanged.len() as u32,
             files_backed_up: backup_count,
         })
     }
@@ -358,7 +360,7 @@ impl CrackManager {
             .get(url)
             .send()
             .await
-            .map_err(LauncherError::Network)?;
+            .map_err(LauncherError::from)?;
 
         let total_size = response.content_length().unwrap_or(0);
         let mut downloaded: u64 = 0;
@@ -377,7 +379,7 @@ impl CrackManager {
                 }
             }
 
-            let chunk = chunk_result.map_err(LauncherError::Network)?;
+            let chunk = chunk_result.map_err(LauncherError::from)?;
             file.write_all(&chunk).map_err(LauncherError::Io)?;
 
             downloaded += chunk.len() as u64;
@@ -412,15 +414,48 @@ impl CrackManager {
         Ok(())
     }
 
+    /// Copies the files the archive will overwrite into the backup folder and
+    /// returns how many were copied, plus the archive files already installed
+    /// byte for byte, which extraction skips. Originals the existing manifest
+    /// already preserves are not copied again, so reapplying or updating a
+    /// crack doesn't back the crack itself up over them.
     async fn backup_original_files(
         &self,
         app_id: &str,
         game_path: &Path,
         archive_path: &Path,
         strip_depth: usize,
-    ) -> Result<u32> {
+    ) -> Result<(u32, HashSet<PathBuf>)> {
+        let app_id = app_id.to_string();
+        let game_path = game_path.to_path_buf();
+        let archive_path = archive_path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            Self::back_up_originals(&app_id, &game_path, &archive_path, strip_depth)
+        })
+        .await
+        .map_err(|err| LauncherError::Config(err.to_string()))?
+    }
+
+    fn back_up_originals(
+        app_id: &str,
+        game_path: &Path,
+        archive_path: &Path,
+        strip_depth: usize,
+    ) -> Result<(u32, HashSet<PathBuf>)> {
         let backup_dir = game_path.join(BACKUP_DIR_NAME);
         std::fs::create_dir_all(&backup_dir).map_err(LauncherError::Io)?;
+        let manifest_path = backup_dir.join(BACKUP_MANIFEST_FILE);
+
+        let mut preserved: HashMap<String, BackupFileEntry> =
+            std::fs::read_to_string(&manifest_path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<BackupManifest>(&content).ok())
+                .map(|manifest| manifest.files)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|entry| entry.backed_up && backup_dir.join(&entry.relative_path).is_file())
+                .map(|entry| (entry.relative_path.clone(), entry))
+                .collect();
 
         // Read archive to get list of files that will be overwritten
         let archive_file = File::open(archive_path).map_err(LauncherError::Io)?;
@@ -428,27 +463,45 @@ impl CrackManager {
             ZipArchive::new(archive_file).map_err(|e| LauncherError::Config(e.to_string()))?;
 
         let mut backup_entries: Vec<BackupFileEntry> = Vec::new();
+        let mut unchanged = HashSet::new();
         let mut backup_count = 0u32;
 
         for i in 0..archive.len() {
-            let file = archive
+            let mut file = archive
                 .by_index(i)
                 .map_err(|e| LauncherError::Config(e.to_string()))?;
             if file.is_dir() {
                 continue;
             }
-            let file_path = file
-                .enclosed_name()
-                .ok_or_else(|| LauncherError::Config("Invalid file path in archive".to_string()))?;
-
-            let Some(relative_path) = self.map_archive_path(&file_path, strip_depth) else {
+            let Some(relative_path) = Self::archive_entry_path(&file, strip_depth)? else {
                 continue;
             };
             let target_path = game_path.join(&relative_path);
 
             if target_path.exists() && target_path.is_file() {
                 // Calculate hash of original file
-                let hash = self.calculate_file_hash(&target_path)?;
+                let hash =
+                    Self::hash_reader(&mut File::open(&target_path).map_err(LauncherError::Io)?)?;
+                let size = std::fs::metadata(&target_path)
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+                let identical = size == file.size() && Self::hash_reader(&mut file)? == hash;
+                // A later entry for the same path decides, as in extraction.
+                if identical {
+                    unchanged.insert(relative_path.clone());
+                } else {
+                    unchanged.remove(&relative_path);
+                }
+
+                let key = relative_path.to_string_lossy().to_string();
+                if let Some(entry) = preserved.remove(&key) {
+                    // The crack file is already in place, or the original the
+                    // backup holds is: either way the backup is still right.
+                    if identical || entry.original_hash == hash {
+                        backup_entries.push(entry);
+                        continue;
+                    }
+                }
 
                 // Backup the file
                 let backup_path = backup_dir.join(&relative_path);
@@ -457,12 +510,8 @@ impl CrackManager {
                 }
                 std::fs::copy(&target_path, &backup_path).map_err(LauncherError::Io)?;
 
-                let size = std::fs::metadata(&target_path)
-                    .map(|m| m.len())
-                    .unwrap_or(0);
-
                 backup_entries.push(BackupFileEntry {
-                    relative_path: relative_path.to_string_lossy().to_string(),
+                    relative_path: key,
                     original_hash: hash,
                     size,
                     backed_up: true,
@@ -470,6 +519,9 @@ impl CrackManager {
                 backup_count += 1;
             }
         }
+        // Originals from an earlier crack that this archive no longer touches
+        // still need restoring on uninstall.
+        backup_entries.extend(preserved.into_values());
 
         // Save backup manifest
         let manifest = BackupManifest {
@@ -480,21 +532,24 @@ impl CrackManager {
             files: backup_entries,
         };
 
-        let manifest_path = backup_dir.join(BACKUP_MANIFEST_FILE);
         let manifest_json = serde_json::to_string_pretty(&manifest)
             .map_err(|e| LauncherError::Config(e.to_string()))?;
         std::fs::write(&manifest_path, manifest_json).map_err(LauncherError::Io)?;
 
-        Ok(backup_count)
+        Ok((backup_count, unchanged))
     }
 
     fn calculate_file_hash(&self, path: &Path) -> Result<String> {
         let mut file = File::open(path).map_err(LauncherError::Io)?;
+        Self::hash_reader(&mut file)
+    }
+
+    fn hash_reader(reader: &mut impl Read) -> Result<String> {
         let mut hasher = Sha256::new();
         let mut buffer = [0u8; 65536];
 
         loop {
-            let bytes_read = file.read(&mut buffer).map_err(LauncherError::Io)?;
+            let bytes_read = reader.read(&mut buffer).map_err(LauncherError::Io)?;
             if bytes_read == 0 {
                 break;
             }
@@ -504,61 +559,209 @@ impl CrackManager {
         Ok(format!("{:x}", hasher.finalize()))
     }
 
+    /// Unpacks the archive on up to `extract_workers` threads. Only runs once
+    /// the backup step has finished, so every original is preserved before it
+    /// can be overwritten. Each file is written beside its target and renamed
+    /// into place, so a cancel leaves files either original or fully replaced
+    /// and `uninstall_crack` can still restore them.
     async fn extract_to_game_dir(
         &self,
         archive_path: &Path,
         game_path: &Path,
         app_id: &str,
         strip_depth: usize,
+        unchanged: &HashSet<PathBuf>,
+        control: &watch::Receiver<DownloadControl>,
     ) -> Result<u32> {
+        let (dirs, mut jobs) = Self::plan_extraction(archive_path, strip_depth)?;
+        jobs.retain(|job| !unchanged.contains(&job.relative_path));
+        for dir in &dirs {
+            std::fs::create_dir_all(game_path.join(dir)).map_err(LauncherError::Io)?;
+        }
+
+        let manager = self.clone();
+        let archive_path = archive_path.to_path_buf();
+        let game_path = game_path.to_path_buf();
+        let app_id = app_id.to_string();
+        let control = control.clone();
+        let workers = self.extract_workers();
+        tokio::task::spawn_blocking(move || {
+            let total_files = jobs.len();
+            let total_bytes: u64 = jobs.iter().map(|job| job.size).sum();
+            let start_time = Instant::now();
+            let extracted = Self::extract_entries(
+                &archive_path,
+                &game_path,
+                jobs,
+                workers,
+                || *control.borrow() == DownloadControl::Cancelled,
+                |relative_path, files, bytes| {
+                    let elapsed = start_time.elapsed().as_secs_f64();
+                    let speed = if elapsed > 0.0 {
+                        (bytes as f64 / elapsed) as u64
+                    } else {
+                        0
+                    };
+                    let remaining = total_bytes.saturating_sub(bytes);
+                    manager.update_progress(&CrackDownloadProgress {
+                        app_id: app_id.clone(),
+                        status: CrackDownloadStatus::Extracting,
+                        progress_percent: if total_bytes > 0 {
+                            (bytes as f64 / total_bytes as f64) * 100.0
+                        } else {
+                            (files as f64 / total_files as f64) * 100.0
+                        },
+                        downloaded_bytes: bytes,
+                        total_bytes,
+                        speed_bps: speed,
+                        eta_seconds: if speed > 0 { remaining / speed } else { 0 },
+                        current_file: Some(relative_path.to_string_lossy().to_string()),
+                    });
+                },
+            )?;
+            Ok(extracted as u32)
+        })
+        .await
+        .map_err(|err| LauncherError::Config(err.to_string()))?
+    }
+
+    /// Folders to create and files to write. When the archive holds the same
+    /// path twice the later entry wins, as it did when extracting in order.
+    fn plan_extraction(
+        archive_path: &Path,
+        strip_depth: usize,
+    ) -> Result<(Vec<PathBuf>, Vec<ExtractJob>)> {
         let archive_file = File::open(archive_path).map_err(LauncherError::Io)?;
         let mut archive =
             ZipArchive::new(archive_file).map_err(|e| LauncherError::Config(e.to_string()))?;
 
-        let total_files = archive.len();
-        let mut extracted = 0u32;
-
-        for i in 0..total_files {
-            let mut file = archive
-                .by_index(i)
+        let mut dirs = Vec::new();
+        let mut jobs: Vec<ExtractJob> = Vec::new();
+        let mut slots: HashMap<String, usize> = HashMap::new();
+        for index in 0..archive.len() {
+            let file = archive
+                .by_index(index)
                 .map_err(|e| LauncherError::Config(e.to_string()))?;
-
-            let file_path = file
-                .enclosed_name()
-                .ok_or_else(|| LauncherError::Config("Invalid file path in archive".to_string()))?;
-
-            let Some(relative_path) = self.map_archive_path(&file_path, strip_depth) else {
+            let Some(relative_path) = Self::archive_entry_path(&file, strip_depth)? else {
                 continue;
             };
-            let target_path = game_path.join(&relative_path);
-
-            // Update progress
-            let progress = CrackDownloadProgress {
-                app_id: app_id.to_string(),
-                status: CrackDownloadStatus::Extracting,
-                progress_percent: ((i + 1) as f64 / total_files as f64) * 100.0,
-                downloaded_bytes: 0,
-                total_bytes: 0,
-                speed_bps: 0,
-                eta_seconds: 0,
-                current_file: Some(relative_path.to_string_lossy().to_string()),
-            };
-            self.update_progress(&progress);
-
             if file.is_dir() {
-                std::fs::create_dir_all(&target_path).map_err(LauncherError::Io)?;
-            } else {
-                if let Some(parent) = target_path.parent() {
-                    std::fs::create_dir_all(parent).map_err(LauncherError::Io)?;
+                dirs.push(relative_path);
+                continue;
+            }
+            // Case-folded so two spellings never land on one file concurrently.
+            let key = relative_path.to_string_lossy().to_lowercase();
+            let job = ExtractJob {
+                index,
+                relative_path,
+                size: file.size(),
+            };
+            match slots.get(&key) {
+                Some(&slot) => jobs[slot] = job,
+                None => {
+                    slots.insert(key, jobs.len());
+                    jobs.push(job);
                 }
+            }
+        }
+        Ok((dirs, jobs))
+    }
 
-                let mut outfile = File::create(&target_path).map_err(LauncherError::Io)?;
-                std::io::copy(&mut file, &mut outfile).map_err(LauncherError::Io)?;
-                extracted += 1;
+    /// Writes `jobs` on up to `workers` threads, each with its own archive
+    /// handle. Stops taking new files once `cancelled` returns true and calls
+    /// `on_file` with the running file and byte totals after each file.
+    fn extract_entries(
+        archive_path: &Path,
+        game_path: &Path,
+        jobs: Vec<ExtractJob>,
+        workers: usize,
+        cancelled: impl Fn() -> bool + Sync,
+        on_file: impl Fn(&Path, usize, u64) + Sync,
+    ) -> Result<usize> {
+        let worker_count = workers.clamp(1, MAX_EXTRACT_WORKERS).min(jobs.len().max(1));
+        let queue = Mutex::new(jobs.into_iter().collect::<VecDeque<_>>());
+        let extracted_files = AtomicUsize::new(0);
+        let extracted_bytes = AtomicU64::new(0);
+        let failed = AtomicBool::new(false);
+
+        let work = || -> Result<()> {
+            let archive_file = File::open(archive_path).map_err(LauncherError::Io)?;
+            let mut archive =
+                ZipArchive::new(archive_file).map_err(|e| LauncherError::Config(e.to_string()))?;
+            while !failed.load(Ordering::Relaxed) && !cancelled() {
+                let Some(job) = queue.lock().ok().and_then(|mut jobs| jobs.pop_front()) else {
+                    break;
+                };
+                Self::extract_entry(&mut archive, &job, game_path)
+                    .inspect_err(|_| failed.store(true, Ordering::Relaxed))?;
+                let files = extracted_files.fetch_add(1, Ordering::Relaxed) + 1;
+                let bytes = extracted_bytes.fetch_add(job.size, Ordering::Relaxed) + job.size;
+                on_file(&job.relative_path, files, bytes);
             }
+            Ok(())
+        };
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..worker_count).map(|_| scope.spawn(work)).collect();
+            handles.into_iter().try_for_each(|handle| {
+                handle
+                    .join()
+                    .map_err(|_| LauncherError::Config("extraction worker panicked".to_string()))?
+            })
+        })?;
+        Ok(extracted_files.load(Ordering::Relaxed))
+    }
+
+    fn extract_entry(
+        archive: &mut ZipArchive<File>,
+        job: &ExtractJob,
+        game_path: &Path,
+    ) -> Result<()> {
+        let mut file = archive
+            .by_index(job.index)
+            .map_err(|e| LauncherError::Config(e.to_string()))?;
+        let target_path = game_path.join(&job.relative_path);
+        if let Some(parent) = target_path.parent() {
+            std::fs::create_dir_all(parent).map_err(LauncherError::Io)?;
+        }
+
+        let mut partial_name = target_path.as_os_str().to_owned();
+        partial_name.push(PARTIAL_SUFFIX);
+        let partial_path = PathBuf::from(partial_name);
+        let written = File::create(&partial_path)
+            .and_then(|mut outfile| std::io::copy(&mut file, &mut outfile));
+        if let Err(err) = written {
+            let _ = std::fs::remove_file(&partial_path);
+            return Err(LauncherError::Io(err));
         }
+        std::fs::rename(&partial_path, &target_path).map_err(LauncherError::Io)
+    }
 
-        Ok(extracted)
+    /// How many files a crack is extracted with at once.
+    pub fn extract_workers(&self) -> usize {
+        self.db
+            .get_setting(EXTRACT_WORKERS_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .map(|value| value.clamp(1, MAX_EXTRACT_WORKERS))
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|value| value.get())
+                    .unwrap_or(4)
+                    .clamp(1, MAX_EXTRACT_WORKERS)
+            })
+    }
+
+    pub fn set_extract_workers(&self, workers: Option<usize>) -> Result<usize> {
+        match workers {
+            Some(value) => self.db.set_setting(
+                EXTRACT_WORKERS_SETTING,
+                &value.clamp(1, MAX_EXTRACT_WORKERS).to_string(),
+            )?,
+            None => self.db.delete_setting(EXTRACT_WORKERS_SETTING)?,
+        }
+        Ok(self.extract_workers())
     }
 
     fn determine_archive_root_strip_depth(
@@ -583,10 +786,10 @@ impl CrackManager {
             let Some(path) = file.enclosed_name().map(|p| p.to_path_buf()) else {
                 continue;
             };
-            if self.is_ignored_archive_path(&path) {
+            if Self::is_ignored_archive_path(&path) {
                 continue;
             }
-            let components = self.normal_components(&path).count();
+            let components = Self::normal_components(&path).count();
             if components == 0 {
                 continue;
             }
@@ -644,10 +847,10 @@ impl CrackManager {
     fn score_strip_depth(&self, entries: &[PathBuf], game_path: &Path, depth: usize) -> i64 {
         let mut score = 0i64;
         for entry in entries {
-            let Some(mapped) = self.strip_components(entry, depth) else {
+            let Some(mapped) = Self::strip_components(entry, depth) else {
                 continue;
             };
-            if self.is_ignored_archive_path(&mapped) {
+            if Self::is_ignored_archive_path(&mapped) {
                 continue;
             }
 
@@ -671,15 +874,35 @@ impl CrackManager {
         score
     }
 
-    fn map_archive_path(&self, path: &Path, strip_depth: usize) -> Option<PathBuf> {
-        let mapped = self.strip_components(path, strip_depth)?;
-        if self.is_ignored_archive_path(&mapped) {
+    /// Where a zip entry lands relative to the game folder, or `None` for
+    /// entries that are skipped. Traversal, absolute or drive-prefixed names
+    /// and symlink entries fail the whole extraction.
+    fn archive_entry_path(entry: &ZipFile, strip_depth: usize) -> Result<Option<PathBuf>> {
+        let name = entry.name();
+        if archive::is_symlink(entry) {
+            return Err(LauncherError::Config(format!(
+                "Symlink entry in archive: {}",
+                name
+            )));
+        }
+        let file_path = entry
+            .enclosed_name()
+            .filter(|_| archive::is_contained_name(name))
+            .ok_or_else(|| {
+                LauncherError::Config(format!("Invalid file path in archive: {}", name))
+            })?;
+        Ok(Self::map_archive_path(file_path, strip_depth))
+    }
+
+    fn map_archive_path(path: &Path, strip_depth: usize) -> Option<PathBuf> {
+        let mapped = Self::strip_components(path, strip_depth)?;
+        if Self::is_ignored_archive_path(&mapped) {
             return None;
         }
         Some(mapped)
     }
 
-    fn strip_components(&self, path: &Path, depth: usize) -> Option<PathBuf> {
+    fn strip_components(path: &Path, depth: usize) -> Option<PathBuf> {
         let mut out = PathBuf::new();
         let mut skipped = 0usize;
         for component in path.components() {
@@ -699,7 +922,7 @@ impl CrackManager {
         }
     }
 
-    fn normal_components<'a>(&self, path: &'a Path) -> impl Iterator<Item = &'a std::ffi::OsStr> {
+    fn normal_components<'a>(path: &'a Path) -> impl Iterator<Item = &'a std::ffi::OsStr> {
         path.components().filter_map(|component| match component {
             Component::Normal(seg) => Some(seg),
             _ => None,
@@ -722,8 +945,8 @@ impl CrackManager {
         shared
     }
 
-    fn is_ignored_archive_path(&self, path: &Path) -> bool {
-        let first = match self.normal_components(path).next() {
+    fn is_ignored_archive_path(path: &Path) -> bool {
+        let first = match Self::normal_components(path).next() {
             Some(value) => value.to_string_lossy().to_ascii_lowercase(),
             None => return true,
         };
@@ -829,6 +1052,85 @@ impl CrackManager {
         })
     }
 
+    /// Puts original files back from the backup without uninstalling the
+    /// crack: the backup folder and manifest stay, so the crack can still be
+    /// reapplied or uninstalled later. `paths` limits the restore to those
+    /// game-relative files; `None` restores every backed-up file.
+    pub async fn restore_from_backup(
+        &self,
+        app_id: &str,
+        game_path: &str,
+        paths: Option<Vec<String>>,
+    ) -> Result<CrackRestoreResult> {
+        let game_path = PathBuf::from(game_path);
+        let backup_dir = game_path.join(BACKUP_DIR_NAME);
+        let manifest_path = backup_dir.join(BACKUP_MANIFEST_FILE);
+        let manifest_content = std::fs::read_to_string(&manifest_path).map_err(|_| {
+            LauncherError::NotFound(format!("No crack backup found for {}", app_id))
+        })?;
+        let manifest: BackupManifest = serde_json::from_str(&manifest_content)
+            .map_err(|e| LauncherError::Config(e.to_string()))?;
+
+        let mut requested: Option<HashSet<String>> =
+            paths.map(|paths| paths.iter().map(|path| Self::backup_key(path)).collect());
+        let mut restored = Vec::new();
+        let mut missing = Vec::new();
+
+        for entry in &manifest.files {
+            if let Some(requested) = requested.as_mut() {
+                if !requested.remove(&Self::backup_key(&entry.relative_path)) {
+                    continue;
+                }
+            }
+            let backup_path = backup_dir.join(&entry.relative_path);
+            let intact = entry.backed_up
+                && backup_path.is_file()
+                && self.calculate_file_hash(&backup_path)? == entry.original_hash;
+            if !intact {
+                missing.push(entry.relative_path.clone());
+                continue;
+            }
+
+            let target_path = game_path.join(&entry.relative_path);
+            if let Some(parent) = target_path.parent() {
+                std::fs::create_dir_all(parent).map_err(LauncherError::Io)?;
+            }
+            let mut partial_name = target_path.as_os_str().to_owned();
+            partial_name.push(PARTIAL_SUFFIX);
+            let partial_path = PathBuf::from(partial_name);
+            std::fs::copy(&backup_path, &partial_path).map_err(LauncherError::Io)?;
+            if self.calculate_file_hash(&partial_path)? != entry.original_hash {
+                let _ = std::fs::remove_file(&partial_path);
+                missing.push(entry.relative_path.clone());
+                continue;
+            }
+            std::fs::rename(&partial_path, &target_path).map_err(LauncherError::Io)?;
+            restored.push(entry.relative_path.clone());
+        }
+        // Requested paths the manifest doesn't list.
+        missing.extend(requested.into_iter().flatten());
+
+        Ok(CrackRestoreResult {
+            success: missing.is_empty(),
+            message: if missing.is_empty() {
+                format!("{} original files restored", restored.len())
+            } else {
+                format!("{} files could not be restored", missing.len())
+            },
+            restored,
+            missing,
+        })
+    }
+
+    /// Manifest paths are stored with the platform separator; requests may
+    /// use either.
+    fn backup_key(path: &str) -> String {
+        path.replace('\\', "/")
+            .trim_start_matches("./")
+            .trim_start_matches('/')
+            .to_string()
+    }
+
     /// Verify game integrity using stored hashes
     pub async fn verify_game_integrity(&self, app_id: &str, game_path: &Path) -> Result<bool> {
         let backup_dir = game_path.join(BACKUP_DIR_NAME);
@@ -920,3 +1222,134 @@ impl CrackManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry_paths(entries: &[(&str, &str, Option<&str>)]) -> Vec<Result<Option<PathBuf>>> {
+        let bytes = archive::build_test_zip(entries);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        (0..archive.len())
+            .map(|index| CrackManager::archive_entry_path(&archive.by_index(index).unwrap(), 1))
+            .collect()
+    }
+
+    #[test]
+    fn archive_entries_outside_the_game_folder_are_rejected() {
+        let results = entry_paths(&[
+            ("Crack/bin/steam_api64.dll", "dll", None),
+            ("Crack/__MACOSX/steam_api64.dll", "meta", None),
+            ("../escape.dll", "evil", None),
+            ("/abs.dll", "evil", None),
+            ("C:/Windows/evil.dll", "evil", None),
+            ("Crack/link.dll", "", Some("../../evil.dll")),
+        ]);
+
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &Some(PathBuf::from("bin").join("steam_api64.dll"))
+        );
+        assert_eq!(results[1].as_ref().unwrap(), &None);
+        assert!(results[2..].iter().all(|result| result.is_err()));
+    }
+
+    #[test]
+    fn reapplying_keeps_the_original_backup_and_skips_unchanged_files() {
+        let dir = std::env::temp_dir().join(format!("otoshi-crack-{}", uuid::Uuid::new_v4()));
+        let game_path = dir.join("game");
+        std::fs::create_dir_all(game_path.join("bin")).unwrap();
+        std::fs::write(game_path.join("steam_api64.dll"), "original").unwrap();
+        std::fs::write(game_path.join("bin/game.exe"), "exe").unwrap();
+        let archive_path = dir.join("crack.zip");
+        std::fs::write(
+            &archive_path,
+            archive::build_test_zip(&[
+                ("Crack/steam_api64.dll", "cracked", None),
+                ("Crack/bin/game.exe", "exe", None),
+            ]),
+        )
+        .unwrap();
+        let dll = PathBuf::from("steam_api64.dll");
+        let exe = PathBuf::from("bin").join("game.exe");
+        let backed_up_dll = || {
+            std::fs::read_to_string(game_path.join(BACKUP_DIR_NAME).join("steam_api64.dll"))
+                .unwrap()
+        };
+        let apply = || CrackManager::back_up_originals("10", &game_path, &archive_path, 1).unwrap();
+
+        let (copied, unchanged) = apply();
+        assert_eq!(copied, 2);
+        assert_eq!(unchanged, HashSet::from([exe.clone()]));
+
+        // Once the crack is in place, reapplying it copies nothing and leaves
+        // the original in the backup.
+        std::fs::write(game_path.join("steam_api64.dll"), "cracked").unwrap();
+        let (copied, unchanged) = apply();
+        assert_eq!(copied, 0);
+        assert_eq!(unchanged, HashSet::from([dll.clone(), exe.clone()]));
+        assert_eq!(backed_up_dll(), "original");
+
+        // A game update that replaced the file makes it the new original.
+        std::fs::write(game_path.join("steam_api64.dll"), "updated").unwrap();
+        let (copied, unchanged) = apply();
+        assert_eq!(copied, 1);
+        assert_eq!(unchanged, HashSet::from([exe]));
+        assert_eq!(backed_up_dll(), "updated");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parallel_extraction_writes_every_file() {
+        let dir = std::env::temp_dir().join(format!("otoshi-crack-{}", uuid::Uuid::new_v4()));
+        let game_path = dir.join("game");
+        std::fs::create_dir_all(&game_path).unwrap();
+        let archive_path = dir.join("crack.zip");
+        std::fs::write(
+            &archive_path,
+            archive::build_test_zip(&[
+                ("Crack/steam_api64.dll", "dll", None),
+                ("Crack/bin/game.exe", "exe", None),
+                ("Crack/bin/config.ini", "ini", None),
+                ("Crack/__MACOSX/steam_api64.dll", "meta", None),
+            ]),
+        )
+        .unwrap();
+
+        let (_, jobs) = CrackManager::plan_extraction(&archive_path, 1).unwrap();
+        assert_eq!(jobs.len(), 3);
+        let reported = AtomicUsize::new(0);
+        let extracted = CrackManager::extract_entries(
+            &archive_path,
+            &game_path,
+            jobs,
+            4,
+            || false,
+            |_, _, _| {
+                reported.fetch_add(1, Ordering::Relaxed);
+            },
+        )
+        .unwrap();
+
+        assert_eq!(extracted, 3);
+        assert_eq!(reported.load(Ordering::Relaxed), 3);
+        let read = |path: &str| std::fs::read_to_string(game_path.join(path)).unwrap();
+        assert_eq!(read("steam_api64.dll"), "dll");
+        assert_eq!(read("bin/game.exe"), "exe");
+        assert_eq!(read("bin/config.ini"), "ini");
+        assert!(!game_path.join("__MACOSX").exists());
+
+        let (_, jobs) = CrackManager::plan_extraction(&archive_path, 1).unwrap();
+        let cancelled = CrackManager::extract_entries(
+            &archive_path,
+            &game_path,
+            jobs,
+            2,
+            || true,
+            |_, _, _| {},
+        )
+        .unwrap();
+        assert_eq!(cancelled, 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,119 @@
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Monthly cap on metered network bytes. Peer and depotcache bytes don't
+/// count against it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataCapSettings {
+    /// `0` turns the cap off.
+    pub monthly_cap_bytes: u64,
+    /// Day of the month a new cycle starts, 1 to 28.
+    pub reset_day: u32,
+    /// Start of the cycle in which the user chose to keep downloading past
+    /// the cap. A new cycle clears it implicitly.
+    pub override_cycle: Option<NaiveDate>,
+}
+
+impl Default for DataCapSettings {
+    fn default() -> Self {
+        Self {
+            monthly_cap_bytes: 0,
+            reset_day: 1,
+            override_cycle: None,
+        }
+    }
+}
+
+/// Where the current cycle stands, emitted as `data-cap-reached`.
+#[derive(Clone, Debug, Serialize)]
+pub struct DataCapStatus {
+    pub monthly_cap_bytes: u64,
+    pub reset_day: u32,
+    pub cycle_start: NaiveDate,
+    /// Last day of the cycle, inclusive.
+    pub cycle_end: NaiveDate,
+    pub used_bytes: u64,
+    pub overridden: bool,
+    /// The cap is set, used up and not overridden: downloads stay paused.
+    pub reached: bool,
+}
+
+impl DataCapSettings {
+    pub fn normalized(mut self) -> Self {
+        self.reset_day = self.reset_day.clamp(1, 28);
+        self
+    }
+
+    pub fn status(&self, today: NaiveDate, used_bytes: u64) -> DataCapStatus {
+        let (cycle_start, cycle_end) = cycle_bounds(today, self.reset_day);
+        let overridden = self.override_cycle == Some(cycle_start);
+        DataCapStatus {
+            monthly_cap_bytes: self.monthly_cap_bytes,
+            reset_day: self.reset_day,
+            cycle_start,
+            cycle_end,
+            used_bytes,
+            overridden,
+            reached: self.monthly_cap_bytes > 0
+                && used_bytes >= self.monthly_cap_bytes
+                && !overridden,
+        }
+    }
+}
+
+/// First and last day of the cycle containing `today` for a cap that resets
+/// on `reset_day` of each month.
+pub fn cycle_bounds(today: NaiveDate, reset_day: u32) -> (NaiveDate, NaiveDate) {
+    let reset_day = reset_day.clamp(1, 28);
+    let this_month = today.with_day(reset_day).unwrap_or(today);
+    let start = if today.day() >= reset_day {
+        this_month
+    } else {
+        this_month
+            .checked_sub_months(Months::new(1))
+            .unwrap_or(this_month)
+    };
+    let end = start
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(start);
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn cycles_start_on_the_reset_day() {
+        assert_eq!(
+            cycle_bounds(date(2026, 3, 20), 15),
+            (date(2026, 3, 15), date(2026, 4, 14))
+        );
+        assert_eq!(
+            cycle_bounds(date(2026, 1, 3), 15),
+            (date(2025, 12, 15), date(2026, 1, 14))
+        );
+        assert_eq!(
+            cycle_bounds(date(2026, 2, 1), 1),
+            (date(2026, 2, 1), date(2026, 2, 28))
+        );
+    }
+
+    #[test]
+    fn override_only_covers_its_own_cycle() {
+        let settings = DataCapSettings {
+            monthly_cap_bytes: 100,
+            reset_day: 1,
+            override_cycle: Some(date(2026, 3, 1)),
+        };
+        assert!(!settings.status(date(2026, 3, 9), 150).reached);
+        assert!(settings.status(date(2026, 4, 2), 150).reached);
+        assert!(!settings.status(date(2026, 4, 2), 99).reached);
+    }
+}
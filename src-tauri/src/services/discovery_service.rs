@@ -1,25 +1,110 @@
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::db::queries::DiscoveryQueueQueries;
+use crate::db::Database;
 use crate::errors::Result;
 use crate::models::Game;
 use crate::services::ApiClient;
 
+/// How long a cached queue is served without asking the API again.
+const QUEUE_CACHE_TTL_SECS: i64 = 15 * 60;
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+/// A slice of the discovery queue and when it was fetched from the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveryQueuePage {
+    pub games: Vec<Game>,
+    pub offset: usize,
+    /// Offset to pass for the next page, or `None` when the queue is exhausted.
+    pub next_offset: Option<usize>,
+    pub fetched_at: Option<i64>,
+    pub from_cache: bool,
+}
+
 #[derive(Clone)]
 pub struct DiscoveryService {
     api: ApiClient,
+    db: Database,
 }
 
 impl DiscoveryService {
-    pub fn new(api: ApiClient) -> Self {
-        Self { api }
+    pub fn new(api: ApiClient, db: Database) -> Self {
+        Self { api, db }
     }
 
-    pub async fn queue(&self) -> Result<Vec<Game>> {
-        self.api.get("/discovery/queue", true).await
+    /// Serves the cached queue while it is fresh, and falls back to it when
+    /// the API can't be reached.
+    pub async fn queue(&self) -> Result<DiscoveryQueuePage> {
+        let (cached, fetched_at) = self.db.get_cached_discovery_queue()?;
+        let now = Utc::now().timestamp();
+        if !cached.is_empty() && fetched_at.is_some_and(|at| now - at < QUEUE_CACHE_TTL_SECS) {
+            return Ok(cached_page(cached, fetched_at));
+        }
+
+        match self.api.get::<Vec<Game>>("/discovery/queue", true).await {
+            Ok(games) => {
+                self.db.save_discovery_page(0, &games, now)?;
+                Ok(DiscoveryQueuePage {
+                    next_offset: (!games.is_empty()).then_some(games.len()),
+                    games,
+                    offset: 0,
+                    fetched_at: Some(now),
+                    from_cache: false,
+                })
+            }
+            Err(err) if !cached.is_empty() => {
+                tracing::warn!("discovery queue fetch failed, serving cache: {err}");
+                Ok(cached_page(cached, fetched_at))
+            }
+            Err(err) => Err(err),
+        }
     }
 
-    pub async fn refresh_queue(&self) -> Result<Vec<Game>> {
-        self.api
-            .post("/discovery/queue/refresh", serde_json::json!({}), true)
-            .await
+    /// Offset 0 asks the API for a fresh queue; later offsets load more of
+    /// the current one and extend the cache. While the backend is
+    /// unreachable the cached queue is paged instead.
+    pub async fn refresh_queue(
+        &self,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<DiscoveryQueuePage> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let fetched = if offset == 0 {
+            self.api
+                .post(
+                    "/discovery/queue/refresh",
+                    serde_json::json!({ "limit": limit }),
+                    true,
+                )
+                .await
+        } else {
+            let path = format!("/discovery/queue?offset={offset}&limit={limit}");
+            self.api.get(&path, true).await
+        };
+        let games: Vec<Game> = match fetched {
+            Ok(games) => games,
+            Err(err) if !self.api.is_online() => {
+                let (cached, fetched_at) = self.db.get_cached_discovery_queue()?;
+                if cached.is_empty() {
+                    return Err(err);
+                }
+                tracing::info!("backend offline; paging cached discovery queue");
+                return Ok(cached_slice(cached, fetched_at, offset, limit));
+            }
+            Err(err) => return Err(err),
+        };
+
+        let now = Utc::now().timestamp();
+        self.db.save_discovery_page(offset, &games, now)?;
+        Ok(DiscoveryQueuePage {
+            next_offset: (games.len() >= limit).then_some(offset + games.len()),
+            games,
+            offset,
+            fetched_at: Some(now),
+            from_cache: false,
+        })
     }
 
     pub async fn similar(&self, game_id: &str) -> Result<Vec<Game>> {
@@ -27,3 +112,32 @@ impl DiscoveryService {
         self.api.get(&path, false).await
     }
 }
+
+/// Up to `limit` cached games from `offset` on.
+fn cached_slice(
+    games: Vec<Game>,
+    fetched_at: Option<i64>,
+    offset: usize,
+    limit: usize,
+) -> DiscoveryQueuePage {
+    let total = games.len();
+    let games: Vec<Game> = games.into_iter().skip(offset).take(limit).collect();
+    let end = offset + games.len();
+    DiscoveryQueuePage {
+        next_offset: (end < total).then_some(end),
+        games,
+        offset,
+        fetched_at,
+        from_cache: true,
+    }
+}
+
+fn cached_page(games: Vec<Game>, fetched_at: Option<i64>) -> DiscoveryQueuePage {
+    DiscoveryQueuePage {
+        next_offset: (!games.is_empty()).then_some(games.len()),
+        games,
+        offset: 0,
+        fetched_at,
+        from_cache: true,
+    }
+}
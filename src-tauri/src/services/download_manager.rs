@@ -1,10 +1,10 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, Read};
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant, SystemTime};
@@ -12,23 +12,36 @@ use std::time::{Duration, Instant, SystemTime};
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use sysinfo::Disks;
+use sysinfo::DiskKind;
 use tauri::{AppHandle, Emitter};
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 use tokio::sync::{mpsc, watch, OwnedSemaphorePermit, Semaphore};
 use tokio::time::sleep;
 use zip::ZipArchive;
 
-use crate::db::queries::{DownloadQueries, DownloadStateQueries};
+use crate::db::queries::{
+    BandwidthUsageQueries, DownloadQueries, DownloadStateQueries, FileIndexQueries, GameQueries,
+    SettingsQueries,
+};
 use crate::db::Database;
 use crate::errors::{LauncherError, Result};
-use crate::models::{DownloadChunk, DownloadState, LocalDownload};
+use crate::models::{
+    BandwidthUsageTotals, DownloadChunk, DownloadState, FileIndexEntry, LocalDownload, LocalGame,
+};
+use crate::services::aria2_rpc::Aria2RpcDaemon;
+use crate::services::bandwidth::BandwidthThrottler;
+use crate::services::data_cap::{DataCapSettings, DataCapStatus};
 use crate::services::download_service::DownloadProgressUpdate;
+use crate::services::post_install::{self, PendingPostInstall, PostInstallReport, PostInstallStep};
+use crate::services::volatile_paths;
 use crate::services::{
-    build_chunk_peer_urls, peer_url_fingerprint, ApiClient, DownloadService, PeerCacheServer,
-    PeerCandidate, PeerCoordinator,
+    build_chunk_peer_urls, order_chunk_sources, peer_url_fingerprint, ApiClient, DownloadService,
+    MirrorRanker, PeerCacheServer, PeerCandidate, PeerCoordinator, PeerSourceConfig,
+    PeerSourcePolicy, PeerStats, PeerTransferStats,
 };
-use crate::utils::file::FileManager;
+use crate::utils::archive;
+use crate::utils::client_identity;
+use crate::utils::file::{available_disk_space, disk_kind_for_path, FileManager};
 
 const DEFAULT_CHUNK_SIZE: u64 = 1024 * 1024;
 const MANIFEST_FILE: &str = "manifest.json";
@@ -36,7 +49,30 @@ const DEFAULT_MAX_CONCURRENT_CHUNKS: usize = 24;
 const MAX_CONCURRENT_CHUNKS: usize = 64;
 const STORAGE_SAFETY_MARGIN_BYTES: u64 = 256 * 1024 * 1024;
 const MAX_STORAGE_SAFETY_MARGIN_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+const LOW_DISK_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const LOW_DISK_RESUME_POLL: Duration = Duration::from_secs(30);
 const DEPOTCACHE_PREFIX_LEN: usize = 2;
+const DEFAULT_P2P_FANOUT: usize = 3;
+const MAX_P2P_FANOUT: usize = 6;
+const P2P_SOURCE_POLICY_SETTING: &str = "p2p_source_policy";
+const P2P_FANOUT_SETTING: &str = "p2p_fanout";
+const WRITE_STRATEGY_SETTING: &str = "chunk_write_strategy";
+const STORAGE_OPTIONS_SETTING: &str = "storage_options";
+const POST_INSTALL_ALLOWED_SETTING: &str = "post_install_scripts_allowed";
+const DOWNLOAD_TUNING_SETTING: &str = "download_tuning";
+pub(crate) const GAME_INSTALL_ROOTS_SETTING: &str = "game_install_roots";
+const HTTP3_SETTING: &str = "download_http3";
+const DATA_CAP_SETTING: &str = "data_cap";
+const EXTRACT_WORKERS_SETTING: &str = "archive_extract_workers";
+const P2P_ENABLED_SETTING: &str = "p2p_enabled";
+const P2P_UPLOAD_LIMIT_SETTING: &str = "p2p_upload_limit_bps";
+const DOWNLOAD_METHOD_SETTING: &str = "download_method";
+const DOWNLOAD_SCHEDULE_SETTING: &str = "download_schedule";
+/// Method keys a download can be started with, after legacy names are mapped.
+pub const DOWNLOAD_METHODS: [&str; 4] = ["auto", "max_speed", "cdn", "balance"];
+const MAX_INTEGRITY_SCAN_WORKERS: usize = 64;
+const MAX_EXTRACT_WORKERS: usize = 16;
+const DEFAULT_WRITE_MERGE_BUFFER_MB: usize = 128;
 const DEFAULT_DEPOTCACHE_MAX_BYTES: u64 = 64 * 1024 * 1024 * 1024;
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
@@ -52,7 +88,7 @@ fn hide_console_window(command: &mut std::process::Command) {
 #[derive(Clone)]
 pub struct DownloadManager {
     app_handle: AppHandle,
-    client: reqwest::Client,
+    chunk_clients: ChunkClients,
     db: Database,
     api: ApiClient,
     downloads_api: DownloadService,
@@ -61,20 +97,137 @@ pub struct DownloadManager {
     throttle: BandwidthThrottler,
     max_concurrent_chunks: usize,
     depot_cache: DepotCache,
-    peer_server: Option<PeerCacheServer>,
-    peer_coordinator: Option<PeerCoordinator>,
+    peer: Arc<Mutex<PeerNetwork>>,
+    peer_sources: Arc<Mutex<PeerSourceConfig>>,
+    mirror_ranker: MirrorRanker,
+    peer_transfers: PeerTransferStats,
+    write_strategy: Arc<Mutex<Option<WriteStrategy>>>,
+    storage_options: Arc<Mutex<StorageOptions>>,
+    host_limits: HostConnectionLimiter,
+    imported_files: Arc<Mutex<HashMap<PathBuf, HashSet<String>>>>,
+    pending_repairs: Arc<Mutex<HashMap<PathBuf, PendingRepair>>>,
+    tuning: Arc<Mutex<Option<DownloadTuning>>>,
+    data_cap: DataCapMonitor,
 }
 
+/// Enforces the monthly data cap. Progress reports call `check`, which
+/// pauses every running download once the cycle's network bytes reach it.
 #[derive(Clone)]
-pub struct BandwidthThrottler {
-    max_bytes_per_second: Arc<tokio::sync::Mutex<u64>>,
-    current_window_bytes: Arc<tokio::sync::Mutex<u64>>,
-    reset_started: Arc<AtomicBool>,
+struct DataCapMonitor {
+    app_handle: AppHandle,
+    registry: Arc<Mutex<HashMap<String, DownloadHandle>>>,
+    settings: Arc<Mutex<DataCapSettings>>,
+    /// Set once `data-cap-reached` went out for the current overrun.
+    notified: Arc<AtomicBool>,
+}
+
+/// Concurrency knobs runtime tuning can set on the live manager. Without an
+/// override they come from the environment and the core count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadTuning {
+    pub max_concurrent_chunks: usize,
+    pub integrity_workers: usize,
+}
+
+/// How downloads reserve and lay out disk space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageOptions {
+    /// Fixed free-space reserve on top of the download's own needs. `None`
+    /// keeps the adaptive margin (5% of the download, 256 MiB to 2 GiB).
+    pub safety_margin_bytes: Option<u64>,
+    /// Size `.part` files up front. When off, files grow as chunks land.
+    pub preallocate: bool,
+    /// Mark preallocated files sparse so unwritten ranges take no space.
+    /// Only changes anything on Windows; Unix `set_len` already leaves holes.
+    pub sparse: bool,
+    /// Resume a download paused for low disk space once the drive has room
+    /// for the rest of it again.
+    pub auto_resume_low_disk: bool,
+}
+
+impl Default for StorageOptions {
+    fn default() -> Self {
+        Self {
+            safety_margin_bytes: None,
+            preallocate: true,
+            sparse: false,
+            auto_resume_low_disk: false,
+        }
+    }
+}
+
+/// The daily window, in local hours, the user wants downloads to run in.
+/// It wraps past midnight when `end_hour` comes before `start_hour`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadSchedule {
+    pub start_hour: u8,
+    pub end_hour: u8,
+}
+
+impl DownloadSchedule {
+    pub fn is_valid(&self) -> bool {
+        self.start_hour < 24 && self.end_hour < 24 && self.start_hour != self.end_hour
+    }
+}
+
+/// How completed chunks reach their `.part` file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WriteStrategy {
+    /// Seek to each chunk's offset as soon as it lands. Best on SSDs.
+    Direct,
+    /// Hold chunks until they can be written in offset order, so spinning
+    /// disks see long sequential runs instead of scattered seeks.
+    SequentialMerge,
+}
+
+impl WriteStrategy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "direct" | "direct-seek" => Some(Self::Direct),
+            "sequential-merge" | "sequential" | "merge" => Some(Self::SequentialMerge),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Direct => "direct",
+            Self::SequentialMerge => "sequential-merge",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WriteStrategyInfo {
+    /// Strategy downloads into `path` will use.
+    pub strategy: WriteStrategy,
+    /// Saved override, `None` when the strategy follows the detected disk.
+    pub configured: Option<WriteStrategy>,
+    pub disk_kind: &'static str,
+    pub path: String,
 }
 
 #[derive(Clone)]
 struct DownloadHandle {
     control: watch::Sender<DownloadControl>,
+    verify_cancel: watch::Sender<bool>,
+    discard_partial: Arc<AtomicBool>,
+    manifest_path: String,
+    refreshed_urls: RefreshedUrls,
+}
+
+/// Chunk URLs from a manifest re-fetched on resume, keyed by
+/// `(file_id, chunk index)`. Signed CDN links in the original plan may have
+/// expired while the download was paused.
+type RefreshedUrls = Arc<Mutex<HashMap<(String, u64), RefreshedChunkUrls>>>;
+
+#[derive(Clone, Debug)]
+struct RefreshedChunkUrls {
+    hash: String,
+    url: String,
+    fallback_urls: Vec<String>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -104,6 +257,10 @@ struct Manifest {
     archive_files: Vec<String>,
     #[serde(default)]
     total_original_size: Option<u64>,
+    #[serde(default)]
+    post_install: Option<PostInstallStep>,
+    #[serde(default)]
+    volatile_paths: Vec<String>,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -113,6 +270,10 @@ struct ManifestFile {
     hash: String,
     file_id: String,
     chunks: Vec<ManifestChunk>,
+    /// Set from the game's volatile paths for repair scans and launch checks;
+    /// download scans verify every file.
+    #[serde(skip)]
+    volatile: bool,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -142,8 +303,76 @@ fn archive_dir_name(manifest: &Manifest) -> String {
         .unwrap_or_else(|| ".chunks".to_string())
 }
 
+pub(crate) fn normalize_manifest_path(path: &str) -> String {
+    path.trim()
+        .replace('\\', "/")
+        .trim_start_matches('/')
+        .to_string()
+}
+
+fn is_under_archive_dir(path: &str, archive_dir: &str) -> bool {
+    let dir = archive_dir.trim_end_matches('/');
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Paths an archive-mode install owns. Older manifests ship without
+/// `archive_files`; for those the archives under `archive_dir` are all we know.
+fn archive_file_set(manifest: &Manifest) -> HashSet<String> {
+    if !manifest.archive_files.is_empty() {
+        return manifest
+            .archive_files
+            .iter()
+            .map(|path| normalize_manifest_path(path))
+            .collect();
+    }
+    let archive_dir = normalize_manifest_path(&archive_dir_name(manifest));
+    manifest
+        .files
+        .iter()
+        .map(|file| normalize_manifest_path(&file.path))
+        .filter(|path| is_under_archive_dir(path, &archive_dir))
+        .collect()
+}
+
+/// Rejects archive-mode manifests whose `archive_dir` and file lists don't
+/// line up, before anything is downloaded or deleted.
+fn validate_archive_manifest(manifest: &Manifest) -> Result<()> {
+    let incoherent =
+        |reason: String| LauncherError::Config(format!("incoherent archive manifest: {reason}"));
+    let archive_dir = normalize_manifest_path(&archive_dir_name(manifest));
+    let archive_dir = archive_dir.trim_end_matches('/');
+    if archive_dir.is_empty() || archive_dir == "." {
+        return Err(incoherent("archive_dir is empty".to_string()));
+    }
+    if !is_safe_relative_path(Path::new(archive_dir)) {
+        return Err(incoherent(format!(
+            "archive_dir {archive_dir} escapes the install dir"
+        )));
+    }
+    let has_archives = manifest
+        .files
+        .iter()
+        .any(|file| is_under_archive_dir(&normalize_manifest_path(&file.path), archive_dir));
+    if !has_archives {
+        return Err(incoherent(format!(
+            "no files under archive_dir {archive_dir}"
+        )));
+    }
+    if let Some(path) = manifest.archive_files.iter().find(|path| {
+        let normalized = normalize_manifest_path(path);
+        normalized.is_empty() || !is_safe_relative_path(Path::new(&normalized))
+    }) {
+        return Err(incoherent(format!("invalid archive_files entry {path:?}")));
+    }
+    Ok(())
+}
+
 fn is_safe_relative_path(path: &Path) -> bool {
     use std::path::Component;
+    if path.to_str().is_some_and(archive::has_drive_prefix) {
+        return false;
+    }
     for component in path.components() {
         match component {
             Component::Prefix(_) | Component::RootDir | Component::ParentDir => return false,
@@ -187,7 +416,7 @@ enum IntegrityScanMode {
     PostDownload,
 }
 
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
 struct IntegrityScanSummary {
     total_files: usize,
     verified_files: usize,
@@ -197,14 +426,265 @@ struct IntegrityScanSummary {
     hashed_files: usize,
     elapsed_ms: u128,
     first_failures: Vec<String>,
+    /// Set when `cancel_verify` stopped the scan before every file was seen.
+    incomplete: bool,
+    #[serde(skip)]
+    indexed: Vec<FileIndexEntry>,
+    /// Every missing, corrupt or unreadable file, not just the first few.
+    #[serde(skip)]
+    failed_paths: Vec<String>,
+}
+
+/// Preflight result stored with the download state. A later run whose
+/// install fingerprint still matches reuses it instead of scanning again.
+#[derive(Serialize, Deserialize)]
+struct PreflightCheckpoint {
+    fingerprint: String,
+    summary: IntegrityScanSummary,
+}
+
+/// Outcome of adopting an existing install from another folder.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LocalImportReport {
+    pub total_files: usize,
+    pub imported_files: usize,
+    pub imported_bytes: u64,
+    pub missing_files: usize,
+    pub corrupt_files: usize,
+    pub moved: bool,
+}
+
+/// One game folder found by `scan_library_folder`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LibraryScanEntry {
+    pub install_dir: String,
+    pub slug: Option<String>,
+    pub game_id: Option<String>,
+    pub version: Option<String>,
+    /// `installed` when it was recorded as installed; `incomplete`,
+    /// `unreadable`, `unverifiable`, `busy` or `invalid` when it was left
+    /// alone.
+    pub status: String,
+    pub missing_files: usize,
+    pub size_mismatches: usize,
+    /// Files whose size couldn't be read, e.g. for lack of permission.
+    pub unreadable_files: usize,
+}
+
+/// The peer cache server and coordinator while peer sharing is on.
+#[derive(Default)]
+struct PeerNetwork {
+    server: Option<PeerCacheServer>,
+    coordinator: Option<PeerCoordinator>,
+    /// Bytes served by servers stopped earlier this session.
+    served_before: u64,
+}
+
+/// Whether peer sharing runs and what it has done this session.
+#[derive(Clone, Debug, Serialize)]
+pub struct P2pStatus {
+    /// The user setting; sharing can still be off when the environment
+    /// disables it or the listener can't bind.
+    pub enabled: bool,
+    pub active: bool,
+    pub peer_id: Option<String>,
+    pub known_peers: usize,
+    pub bytes_served: u64,
+    /// Cap on bytes served to peers; `0` while the upload rate is measured.
+    pub upload_limit_bps: u64,
+    /// The cap follows the measured upload rate instead of a user setting.
+    pub upload_limit_auto: bool,
+}
+
+/// Result of the size-only check run before launching a game.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct InstallSizeCheck {
+    pub checked_files: usize,
+    pub missing: Vec<String>,
+    pub size_mismatches: Vec<String>,
+    /// Files that exist but whose size couldn't be read.
+    pub unreadable: Vec<String>,
+}
+
+impl InstallSizeCheck {
+    pub fn is_intact(&self) -> bool {
+        self.missing.is_empty() && self.size_mismatches.is_empty() && self.unreadable.is_empty()
+    }
+}
+
+/// Files a self-heal repair will refetch, as resolved against the manifest.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RepairTargets {
+    pub files: usize,
+    pub chunks: usize,
+    pub bytes: u64,
+    pub unknown_paths: Vec<String>,
+}
+
+/// A repair primed for an install dir and carried out by the next download
+/// into it. Nothing is deleted until that download has been accepted and
+/// starts running.
+#[derive(Clone)]
+struct PendingRepair {
+    /// Request path of the installed build's manifest, reused when chunk
+    /// urls are refreshed mid-download.
+    manifest_path: String,
+    manifest: Manifest,
+    /// Files to delete before refetching: the damaged ones and their
+    /// partial downloads.
+    stale: Vec<PathBuf>,
+    /// File ids outside the repair, treated as already in place.
+    verified: HashSet<String>,
+}
+
+/// Header of a game's current manifest, for showing an update before it is
+/// downloaded. The `installed_*` fields come from the local `manifest.json`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ManifestInfo {
+    pub game_id: String,
+    pub slug: String,
+    pub version: String,
+    pub build_id: String,
+    pub total_size: u64,
+    pub compressed_size: u64,
+    pub install_mode: String,
+    pub file_count: usize,
+    pub installed_version: Option<String>,
+    pub installed_build_id: Option<String>,
+    pub update_available: bool,
+}
+
+/// Result of scanning an installed game for `repair_game`. When `targets`
+/// is non-empty the install dir is already primed for the repair download.
+#[derive(Clone, Debug, Serialize)]
+pub struct GameRepairPlan {
+    pub game_id: String,
+    pub slug: String,
+    pub install_dir: String,
+    pub scanned_files: usize,
+    pub targets: RepairTargets,
+}
+
+/// Bytes a running download has read from and written to disk, shared with
+/// the writers so progress reports carry real disk throughput.
+#[derive(Clone, Default)]
+struct DiskIoCounters {
+    read: Arc<AtomicU64>,
+    written: Arc<AtomicU64>,
+}
+
+impl DiskIoCounters {
+    fn add_read(&self, bytes: u64) {
+        self.read.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn add_written(&self, bytes: u64) {
+        self.written.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn read_bytes(&self) -> u64 {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    fn written_bytes(&self) -> u64 {
+        self.written.load(Ordering::Relaxed)
+    }
+}
+
+/// Bytes a running download has received, counted as they arrive so
+/// retried and abandoned transfers count towards the data cap too.
+#[derive(Clone, Default)]
+struct ReceivedBytes {
+    network: Arc<AtomicU64>,
+    peer: Arc<AtomicU64>,
+}
+
+impl ReceivedBytes {
+    fn add(&self, source: ChunkSource, bytes: u64) {
+        let counter = match source {
+            ChunkSource::Cdn => &self.network,
+            ChunkSource::Peer => &self.peer,
+        };
+        counter.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Moves what arrived since the last call into `totals`.
+    fn drain_into(&self, totals: &mut BandwidthUsageTotals) {
+        totals.network_bytes = totals
+            .network_bytes
+            .saturating_add(self.network.swap(0, Ordering::Relaxed));
+        totals.peer_bytes = totals
+            .peer_bytes
+            .saturating_add(self.peer.swap(0, Ordering::Relaxed));
+    }
+}
+
+/// Per-file SHA-256 fed with chunk data as it is written. A file whose chunks
+/// all landed in offset order from zero ends up fully hashed, so the
+/// post-download scan can skip re-reading it.
+#[derive(Clone, Default)]
+struct IncrementalFileHashes {
+    files: Arc<Mutex<HashMap<String, IncrementalHash>>>,
+}
+
+struct IncrementalHash {
+    /// `None` once a chunk arrived out of order; the file is re-read instead.
+    hasher: Option<Sha256>,
+    next_offset: u64,
+}
+
+impl IncrementalFileHashes {
+    fn record(&self, file_id: &str, offset: u64, data: &[u8]) {
+        let Ok(mut files) = self.files.lock() else {
+            return;
+        };
+        let entry = files
+            .entry(file_id.to_string())
+            .or_insert_with(|| IncrementalHash {
+                hasher: Some(Sha256::new()),
+                next_offset: 0,
+            });
+        if entry.next_offset != offset {
+            entry.hasher = None;
+        }
+        if let Some(hasher) = entry.hasher.as_mut() {
+            hasher.update(data);
+            entry.next_offset = offset.saturating_add(data.len() as u64);
+        }
+    }
+
+    /// File ids whose incremental hash covers the whole file and matches the
+    /// manifest hash.
+    fn verified_files(&self, files: &[ManifestFile]) -> HashSet<String> {
+        let Ok(mut hashes) = self.files.lock() else {
+            return HashSet::new();
+        };
+        files
+            .iter()
+            .filter(|file| {
+                let Some(entry) = hashes.remove(&file.file_id) else {
+                    return false;
+                };
+                let (Some(hasher), Some(expected)) = (entry.hasher, sanitize_hash(&file.hash))
+                else {
+                    return false;
+                };
+                entry.next_offset == file.size && hex::encode(hasher.finalize()) == expected
+            })
+            .map(|file| file.file_id.clone())
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug)]
 struct IntegrityFileResult {
+    file_id: String,
     path: String,
     status: IntegrityFileStatus,
     reason: String,
     hashed: bool,
+    /// Set when the file passed a full SHA-256 check, for `file_index_v2`.
+    indexed: Option<FileIndexEntry>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -265,6 +745,94 @@ struct AdaptiveConcurrencyGovernor {
     max_permits: usize,
     last_pressure: Arc<tokio::sync::Mutex<Option<Instant>>>,
     last_relax: Arc<tokio::sync::Mutex<Instant>>,
+    host_limits: HostConnectionLimiter,
+}
+
+/// Caps in-flight chunk requests per URL host, shared by all downloads so
+/// one CDN host is never hit by more than `limit` requests at once. Unless
+/// `LAUNCHER_MAX_CONNECTIONS_PER_HOST` pins it, the limit follows the chunk
+/// concurrency so that setting keeps deciding how many requests run.
+#[derive(Clone)]
+struct HostConnectionLimiter {
+    limit: Arc<AtomicUsize>,
+    pinned: bool,
+    hosts: Arc<Mutex<HashMap<String, HostSlot>>>,
+}
+
+struct HostSlot {
+    semaphore: Arc<Semaphore>,
+    /// Permits held back from this host after it pushed back.
+    reserved: Vec<OwnedSemaphorePermit>,
+    target_reserved: usize,
+    last_pressure: Option<Instant>,
+    last_relax: Instant,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ChunkSource {
+    Cdn,
+    Peer,
+}
+
+/// Where the bytes of a finished download came from, emitted as
+/// `download-completed-summary`.
+#[derive(Clone, Default, Serialize)]
+struct DownloadCompletedSummary {
+    download_id: String,
+    game_id: String,
+    slug: String,
+    total_bytes: u64,
+    resumed_bytes: u64,
+    cdn_bytes: u64,
+    cdn_chunks: u64,
+    peer_bytes: u64,
+    peer_chunks: u64,
+    depotcache_bytes: u64,
+    depotcache_chunks: u64,
+}
+
+impl DownloadCompletedSummary {
+    fn record_chunk(&mut self, source: ChunkSource, size: u64) {
+        match source {
+            ChunkSource::Cdn => {
+                self.cdn_bytes = self.cdn_bytes.saturating_add(size);
+                self.cdn_chunks += 1;
+            }
+            ChunkSource::Peer => {
+                self.peer_bytes = self.peer_bytes.saturating_add(size);
+                self.peer_chunks += 1;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct VerifyCancelledPayload {
+    download_id: String,
+    slug: String,
+    summary: IntegrityScanSummary,
+}
+
+/// Emitted as `download-low-disk` when a running download is paused because
+/// its drive no longer has room for the rest of it.
+#[derive(Clone, Serialize)]
+struct LowDiskPayload {
+    download_id: String,
+    install_dir: String,
+    available_bytes: u64,
+    required_bytes: u64,
+    auto_resume: bool,
+}
+
+/// Emitted as `extract-progress` after each archive of an archive-mode
+/// install is unpacked.
+#[derive(Clone, Serialize)]
+struct ExtractProgressPayload {
+    download_id: String,
+    slug: String,
+    archive: String,
+    completed: usize,
+    total: usize,
 }
 
 #[derive(Clone, Serialize)]
@@ -273,6 +841,8 @@ struct DownloadRuntimeErrorPayload {
     game_id: String,
     slug: String,
     message: String,
+    code: &'static str,
+    retryable: bool,
 }
 
 impl ProgressTracker {
@@ -309,7 +879,12 @@ impl ProgressTracker {
 }
 
 impl AdaptiveConcurrencyGovernor {
-    fn new(method_key: &str, semaphore: Arc<Semaphore>, max_permits: usize) -> Self {
+    fn new(
+        method_key: &str,
+        semaphore: Arc<Semaphore>,
+        max_permits: usize,
+        host_limits: HostConnectionLimiter,
+    ) -> Self {
         let normalized = method_key.trim().to_ascii_lowercase();
         let enabled =
             normalized.eq("auto") || normalized.eq("max_speed") || normalized.eq("balance");
@@ -330,6 +905,7 @@ impl AdaptiveConcurrencyGovernor {
             max_permits,
             last_pressure: Arc::new(tokio::sync::Mutex::new(None)),
             last_relax: Arc::new(tokio::sync::Mutex::new(Instant::now())),
+            host_limits,
         }
     }
 
@@ -338,7 +914,12 @@ impl AdaptiveConcurrencyGovernor {
         self.max_permits.saturating_sub(reserved.len())
     }
 
-    async fn on_network_pressure(&self, source: &str, reason: &str) {
+    /// Narrows the offending host's own limit, then (in adaptive modes) the
+    /// overall concurrency.
+    async fn on_network_pressure(&self, source: &str, reason: &str, host: Option<&str>) {
+        if let Some(host) = host {
+            self.host_limits.tighten(host, reason);
+        }
         if !self.enabled {
             return;
         }
@@ -370,6 +951,7 @@ impl AdaptiveConcurrencyGovernor {
     }
 
     async fn maybe_relax(&self) {
+        self.host_limits.relax();
         if !self.enabled {
             return;
         }
@@ -402,122 +984,395 @@ impl AdaptiveConcurrencyGovernor {
     }
 }
 
-impl BandwidthThrottler {
-    pub fn new(max_bps: u64) -> Self {
+impl HostConnectionLimiter {
+    fn new(limit: usize, pinned: bool) -> Self {
         Self {
-            max_bytes_per_second: Arc::new(tokio::sync::Mutex::new(max_bps)),
-            current_window_bytes: Arc::new(tokio::sync::Mutex::new(0)),
-            reset_started: Arc::new(AtomicBool::new(false)),
+            limit: Arc::new(AtomicUsize::new(limit.max(1))),
+            pinned,
+            hosts: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    pub async fn set_limit(&self, max_bps: u64) {
-        let mut guard = self.max_bytes_per_second.lock().await;
-        *guard = max_bps;
+    fn from_env(max_concurrent_chunks: usize) -> Self {
+        match env_usize("LAUNCHER_MAX_CONNECTIONS_PER_HOST") {
+            Some(limit) => Self::new(limit.clamp(1, MAX_CONCURRENT_CHUNKS), true),
+            None => Self::new(per_host_default(max_concurrent_chunks), false),
+        }
     }
 
-    pub async fn acquire(&self, bytes: u64) {
-        loop {
-            let max = *self.max_bytes_per_second.lock().await;
-            if max == 0 {
-                return;
-            }
-            let mut current = self.current_window_bytes.lock().await;
-            if *current + bytes <= max {
-                *current += bytes;
-                return;
-            }
-            drop(current);
-            tokio::time::sleep(Duration::from_millis(100)).await;
-        }
+    fn limit(&self) -> usize {
+        self.limit.load(Ordering::Relaxed)
     }
 
-    pub fn start_reset_task(&self) {
-        if self.reset_started.swap(true, Ordering::SeqCst) {
+    /// Raises the limit to match a higher chunk concurrency. It never drops,
+    /// since a limit above what downloads request doesn't bind anyway.
+    fn follow_concurrency(&self, max_concurrent_chunks: usize) {
+        if self.pinned {
             return;
         }
-        if tokio::runtime::Handle::try_current().is_err() {
-            self.reset_started.store(false, Ordering::SeqCst);
+        let target = per_host_default(max_concurrent_chunks);
+        let previous = self.limit.fetch_max(target, Ordering::Relaxed);
+        if target <= previous {
             return;
         }
-        let counter = self.current_window_bytes.clone();
+        if let Ok(hosts) = self.hosts.lock() {
+            for slot in hosts.values() {
+                slot.semaphore.add_permits(target - previous);
+            }
+        }
+    }
+
+    /// Waits for a request slot on the URL's host. `None` for URLs without a
+    /// host, which are not limited.
+    async fn acquire(&self, url: &str) -> Option<OwnedSemaphorePermit> {
+        let host = url_host(url)?;
+        let semaphore = {
+            let mut hosts = self.hosts.lock().ok()?;
+            hosts
+                .entry(host)
+                .or_insert_with(|| HostSlot {
+                    semaphore: Arc::new(Semaphore::new(self.limit())),
+                    reserved: Vec::new(),
+                    target_reserved: 0,
+                    last_pressure: None,
+                    last_relax: Instant::now(),
+                })
+                .semaphore
+                .clone()
+        };
+        semaphore.acquire_owned().await.ok()
+    }
+
+    /// Holds back one more slot on `host`, never going below one. The slot
+    /// is taken as soon as an in-flight request on that host finishes.
+    fn tighten(&self, host: &str, reason: &str) {
+        let semaphore = {
+            let Ok(mut hosts) = self.hosts.lock() else {
+                return;
+            };
+            let Some(slot) = hosts.get_mut(host) else {
+                return;
+            };
+            slot.last_pressure = Some(Instant::now());
+            if self.limit().saturating_sub(slot.target_reserved) <= 1 {
+                return;
+            }
+            slot.target_reserved += 1;
+            tracing::warn!(
+                "per-host limit reduced host={} reason={} limit={}/{}",
+                host,
+                reason,
+                self.limit() - slot.target_reserved,
+                self.limit()
+            );
+            slot.semaphore.clone()
+        };
+        let hosts = self.hosts.clone();
+        let host = host.to_string();
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(1));
-            loop {
-                interval.tick().await;
-                let mut guard = counter.lock().await;
-                *guard = 0;
+            let Ok(permit) = semaphore.acquire_owned().await else {
+                return;
+            };
+            if let Ok(mut hosts) = hosts.lock() {
+                if let Some(slot) = hosts.get_mut(&host) {
+                    if slot.reserved.len() < slot.target_reserved {
+                        slot.reserved.push(permit);
+                    }
+                }
             }
         });
     }
-}
 
-fn sanitize_hash(hash: &str) -> Option<String> {
-    let normalized = hash.trim().to_ascii_lowercase();
-    if normalized.len() < 8 {
-        return None;
-    }
-    if !normalized.chars().all(|ch| ch.is_ascii_hexdigit()) {
-        return None;
+    /// Gives one slot back to each host that has been quiet for a while.
+    fn relax(&self) {
+        let Ok(mut hosts) = self.hosts.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        for (host, slot) in hosts.iter_mut() {
+            let quiet = slot
+                .last_pressure
+                .is_none_or(|at| now.saturating_duration_since(at) >= Duration::from_secs(4));
+            if slot.target_reserved == 0
+                || !quiet
+                || now.saturating_duration_since(slot.last_relax) < Duration::from_secs(2)
+            {
+                continue;
+            }
+            slot.target_reserved -= 1;
+            if slot.reserved.len() > slot.target_reserved {
+                slot.reserved.pop();
+            }
+            slot.last_relax = now;
+            tracing::info!(
+                "per-host limit restored host={} limit={}/{}",
+                host,
+                self.limit() - slot.target_reserved,
+                self.limit()
+            );
+        }
     }
-    Some(normalized)
 }
 
-fn compute_sha256_hex(data: &[u8]) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(data);
-    hex::encode(hasher.finalize())
+/// The most requests one download asks for at `max_concurrent_chunks`,
+/// whichever method it uses.
+fn per_host_default(max_concurrent_chunks: usize) -> usize {
+    resolve_method_concurrency("max_speed", max_concurrent_chunks)
 }
 
-fn compute_sha256_file(path: &Path) -> Result<String> {
-    let mut file = File::open(path)?;
-    let mut hasher = Sha256::new();
-    let mut buffer = vec![0_u8; 1024 * 1024];
-    loop {
-        let read = file.read(&mut buffer)?;
-        if read == 0 {
-            break;
+fn url_host(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    Some(match parsed.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host,
+    })
+}
+
+/// HTTP clients for chunk requests. The HTTP/2 client is the default; with
+/// the `http3` Cargo feature and the `download_http3` setting on, chunks are
+/// tried over QUIC first, and a host whose HTTP/3 request fails goes back to
+/// HTTP/2 for the rest of the session.
+#[derive(Clone)]
+struct ChunkClients {
+    http2: reqwest::Client,
+    http3: Option<reqwest::Client>,
+    http3_enabled: Arc<AtomicBool>,
+    http3_failed_hosts: Arc<Mutex<HashSet<String>>>,
+}
+
+/// Whether chunk downloads may use HTTP/3, and whether this build can.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Http3Status {
+    pub enabled: bool,
+    pub supported: bool,
+}
+
+impl ChunkClients {
+    async fn get(&self, url: &str, timeout: Duration) -> reqwest::Result<reqwest::Response> {
+        if let Some(http3) = self.http3_for(url) {
+            match http3.get(url).timeout(timeout).send().await {
+                Ok(response) => return Ok(response),
+                Err(err) => {
+                    tracing::warn!("HTTP/3 request failed for {}, using HTTP/2: {}", url, err);
+                    if let (Some(host), Ok(mut failed)) =
+                        (url_host(url), self.http3_failed_hosts.lock())
+                    {
+                        failed.insert(host);
+                    }
+                }
+            }
         }
-        hasher.update(&buffer[..read]);
+        self.http2.get(url).timeout(timeout).send().await
     }
-    Ok(hex::encode(hasher.finalize()))
-}
 
-fn resolve_integrity_scan_workers() -> usize {
-    let cores = std::thread::available_parallelism()
-        .map(|value| value.get())
-        .unwrap_or(8);
-    usize::min(32, usize::max(8, 2 * cores)).clamp(1, 64)
+    fn http3_for(&self, url: &str) -> Option<&reqwest::Client> {
+        if !self.http3_enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let host = url_host(url)?;
+        let failed = self
+            .http3_failed_hosts
+            .lock()
+            .map(|failed| failed.contains(&host))
+            .unwrap_or(true);
+        if failed {
+            return None;
+        }
+        self.http3.as_ref()
+    }
 }
 
-fn resolve_preflight_hash_limit_bytes() -> u64 {
-    std::env::var("LAUNCHER_PRE_SCAN_HASH_MAX_BYTES")
+#[cfg(feature = "http3")]
+fn build_http3_client(timeout: Duration, connect_timeout: Duration) -> Option<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(client_identity::user_agent())
+        .timeout(timeout)
+        .connect_timeout(connect_timeout)
+        .http3_prior_knowledge()
+        .build()
+        .map_err(|err| tracing::warn!("HTTP/3 client unavailable: {}", err))
         .ok()
-        .and_then(|value| value.trim().parse::<u64>().ok())
-        .map(|value| value.clamp(0, 1024 * 1024 * 1024))
-        .unwrap_or(32 * 1024 * 1024)
 }
 
-fn scan_manifest_file(
-    install_dir: &Path,
-    file: &ManifestFile,
-    mode: IntegrityScanMode,
-    preflight_hash_limit_bytes: u64,
-) -> IntegrityFileResult {
-    let relative = file
-        .path
-        .replace('\\', "/")
-        .trim_start_matches('/')
-        .to_string();
-    let target = install_dir.join(&relative);
+#[cfg(not(feature = "http3"))]
+fn build_http3_client(_timeout: Duration, _connect_timeout: Duration) -> Option<reqwest::Client> {
+    None
+}
+
+impl DataCapMonitor {
+    fn settings(&self) -> DataCapSettings {
+        self.settings
+            .lock()
+            .map(|settings| *settings)
+            .unwrap_or_default()
+    }
+
+    fn status(&self, db: &Database) -> Result<DataCapStatus> {
+        let settings = self.settings();
+        let today = chrono::Local::now().date_naive();
+        let (start, end) = crate::services::data_cap::cycle_bounds(today, settings.reset_day);
+        let used = if settings.monthly_cap_bytes > 0 {
+            db.sum_network_bytes(
+                &start.format("%Y-%m-%d").to_string(),
+                &end.format("%Y-%m-%d").to_string(),
+            )?
+        } else {
+            0
+        };
+        Ok(settings.status(today, used))
+    }
+
+    fn ensure_can_start(&self, db: &Database) -> Result<()> {
+        if self.settings().monthly_cap_bytes == 0 {
+            return Ok(());
+        }
+        let status = self.status(db)?;
+        if status.reached {
+            return Err(LauncherError::DataCap(format!(
+                "monthly data cap used up until {}",
+                status.cycle_end
+            )));
+        }
+        Ok(())
+    }
+
+    fn check(&self, db: &Database) {
+        if self.settings().monthly_cap_bytes == 0 {
+            return;
+        }
+        let status = match self.status(db) {
+            Ok(status) => status,
+            Err(err) => {
+                tracing::warn!("data cap check failed: {}", err);
+                return;
+            }
+        };
+        if !status.reached {
+            self.notified.store(false, Ordering::SeqCst);
+            return;
+        }
+        if self.notified.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let paused: Vec<String> = self
+            .registry
+            .lock()
+            .map(|registry| {
+                registry
+                    .iter()
+                    .filter(|(_, handle)| handle.control.send(DownloadControl::Paused).is_ok())
+                    .map(|(download_id, _)| download_id.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        for download_id in &paused {
+            let _ = db.update_download_status(download_id, "paused");
+        }
+        tracing::warn!(
+            "monthly data cap reached ({} of {} bytes), paused {} downloads",
+            status.used_bytes,
+            status.monthly_cap_bytes,
+            paused.len()
+        );
+        let _ = self.app_handle.emit("data-cap-reached", &status);
+    }
+}
+
+fn sanitize_hash(hash: &str) -> Option<String> {
+    let normalized = hash.trim().to_ascii_lowercase();
+    if normalized.len() < 8 {
+        return None;
+    }
+    if !normalized.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(normalized)
+}
+
+fn compute_sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hex::encode(hasher.finalize())
+}
+
+/// SHA-256 and blake3 of a file from a single read.
+fn compute_file_hashes(path: &Path) -> Result<(String, String)> {
+    let mut file = File::open(path)?;
+    let mut sha256 = Sha256::new();
+    let mut blake3 = blake3::Hasher::new();
+    let mut buffer = vec![0_u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        sha256.update(&buffer[..read]);
+        blake3.update(&buffer[..read]);
+    }
+    Ok((
+        hex::encode(sha256.finalize()),
+        blake3.finalize().to_hex().to_string(),
+    ))
+}
+
+fn compute_blake3_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0_u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn resolve_integrity_scan_workers() -> usize {
+    let cores = std::thread::available_parallelism()
+        .map(|value| value.get())
+        .unwrap_or(8);
+    usize::min(32, usize::max(8, 2 * cores)).clamp(1, MAX_INTEGRITY_SCAN_WORKERS)
+}
+
+fn default_extract_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|value| value.get())
+        .unwrap_or(4)
+        .clamp(1, MAX_EXTRACT_WORKERS)
+}
+
+fn resolve_preflight_hash_limit_bytes() -> u64 {
+    std::env::var("LAUNCHER_PRE_SCAN_HASH_MAX_BYTES")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|value| value.clamp(0, 1024 * 1024 * 1024))
+        .unwrap_or(32 * 1024 * 1024)
+}
+
+fn scan_manifest_file(
+    install_dir: &Path,
+    file: &ManifestFile,
+    mode: IntegrityScanMode,
+    preflight_hash_limit_bytes: u64,
+    prehashed: bool,
+    baseline: Option<&FileIndexEntry>,
+) -> IntegrityFileResult {
+    let relative = normalize_index_path(&file.path);
+    let target = install_dir.join(&relative);
     let expected_hash = sanitize_hash(&file.hash);
 
     if !target.exists() || !target.is_file() {
         return IntegrityFileResult {
+            file_id: file.file_id.clone(),
             path: relative,
             status: IntegrityFileStatus::Missing,
             reason: "missing_file".to_string(),
             hashed: false,
+            indexed: None,
         };
     }
 
@@ -525,20 +1380,48 @@ fn scan_manifest_file(
         Ok(value) => value,
         Err(_) => {
             return IntegrityFileResult {
+                file_id: file.file_id.clone(),
                 path: relative,
                 status: IntegrityFileStatus::Error,
                 reason: "metadata_failed".to_string(),
                 hashed: false,
+                indexed: None,
             };
         }
     };
 
+    // The game or the user may change these at will, so their content isn't
+    // checked; only a missing one still needs fetching.
+    if file.volatile {
+        return IntegrityFileResult {
+            file_id: file.file_id.clone(),
+            path: relative,
+            status: IntegrityFileStatus::Ok,
+            reason: "volatile_path".to_string(),
+            hashed: false,
+            indexed: None,
+        };
+    }
+
     if file.size > 0 && metadata.len() != file.size {
         return IntegrityFileResult {
+            file_id: file.file_id.clone(),
             path: relative,
             status: IntegrityFileStatus::Corrupt,
             reason: "size_mismatch".to_string(),
             hashed: false,
+            indexed: None,
+        };
+    }
+
+    if prehashed {
+        return IntegrityFileResult {
+            file_id: file.file_id.clone(),
+            path: relative,
+            status: IntegrityFileStatus::Ok,
+            reason: "incremental_hash_verified".to_string(),
+            hashed: false,
+            indexed: None,
         };
     }
 
@@ -551,21 +1434,50 @@ fn scan_manifest_file(
     };
     if !should_hash {
         return IntegrityFileResult {
+            file_id: file.file_id.clone(),
             path: relative,
             status: IntegrityFileStatus::Ok,
             reason: "size_verified".to_string(),
             hashed: false,
+            indexed: None,
+        };
+    }
+
+    // A file unchanged since it last matched this manifest hash only needs
+    // the much cheaper blake3 pass to confirm it during preflight.
+    let baseline = baseline.filter(|entry| {
+        mode == IntegrityScanMode::Preflight
+            && entry.size_bytes == metadata.len()
+            && expected_hash.as_deref() == Some(entry.canonical_hash.as_str())
+    });
+    if let Some(baseline) = baseline {
+        let (status, reason) = match compute_blake3_file(&target) {
+            Ok(value) if value == baseline.fast_hash => {
+                (IntegrityFileStatus::Ok, "fast_hash_verified")
+            }
+            Ok(_) => (IntegrityFileStatus::Corrupt, "fast_hash_mismatch"),
+            Err(_) => (IntegrityFileStatus::Error, "hash_read_failed"),
+        };
+        return IntegrityFileResult {
+            file_id: file.file_id.clone(),
+            path: relative,
+            status,
+            reason: reason.to_string(),
+            hashed: true,
+            indexed: None,
         };
     }
 
-    let actual_hash = match compute_sha256_file(&target) {
+    let (actual_hash, fast_hash) = match compute_file_hashes(&target) {
         Ok(value) => value,
         Err(_) => {
             return IntegrityFileResult {
+                file_id: file.file_id.clone(),
                 path: relative,
                 status: IntegrityFileStatus::Error,
                 reason: "hash_read_failed".to_string(),
                 hashed: true,
+                indexed: None,
             };
         }
     };
@@ -573,15 +1485,31 @@ fn scan_manifest_file(
     if let Some(expected) = expected_hash {
         if expected != actual_hash {
             return IntegrityFileResult {
+                file_id: file.file_id.clone(),
                 path: relative,
                 status: IntegrityFileStatus::Corrupt,
                 reason: "hash_mismatch".to_string(),
                 hashed: true,
+                indexed: None,
             };
         }
     }
 
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|value| value.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|value| value.as_secs() as i64)
+        .unwrap_or(0);
     IntegrityFileResult {
+        file_id: file.file_id.clone(),
+        indexed: Some(FileIndexEntry {
+            relative_path: relative.clone(),
+            size_bytes: metadata.len(),
+            modified_at,
+            fast_hash,
+            canonical_hash: actual_hash,
+        }),
         path: relative,
         status: IntegrityFileStatus::Ok,
         reason: "hash_verified".to_string(),
@@ -589,32 +1517,51 @@ fn scan_manifest_file(
     }
 }
 
-fn scan_manifest_integrity_blocking(
+/// Scans every manifest file under `install_dir` and returns the per-file
+/// results in no particular order.
+fn scan_manifest_files_blocking(
     install_dir: PathBuf,
     files: Vec<ManifestFile>,
     mode: IntegrityScanMode,
-) -> Result<IntegrityScanSummary> {
-    let started = Instant::now();
-    let worker_count = resolve_integrity_scan_workers();
+    prehashed: HashSet<String>,
+    baseline: HashMap<String, FileIndexEntry>,
+    worker_count: usize,
+    cancel: Option<watch::Receiver<bool>>,
+) -> Result<Vec<IntegrityFileResult>> {
     let preflight_hash_limit_bytes = resolve_preflight_hash_limit_bytes();
 
     let entries = Arc::new(files);
     let next_index = Arc::new(AtomicUsize::new(0));
     let results = Arc::new(Mutex::new(Vec::<IntegrityFileResult>::new()));
+    let prehashed = Arc::new(prehashed);
+    let baseline = Arc::new(baseline);
 
     let mut workers = Vec::new();
     for _ in 0..worker_count {
         let root = install_dir.clone();
+        let prehashed_ref = Arc::clone(&prehashed);
+        let baseline_ref = Arc::clone(&baseline);
         let files_ref = Arc::clone(&entries);
         let index_ref = Arc::clone(&next_index);
         let results_ref = Arc::clone(&results);
+        let cancel_ref = cancel.clone();
         workers.push(thread::spawn(move || loop {
+            if cancel_ref.as_ref().is_some_and(|signal| *signal.borrow()) {
+                break;
+            }
             let index = index_ref.fetch_add(1, Ordering::SeqCst);
             if index >= files_ref.len() {
                 break;
             }
             let file = &files_ref[index];
-            let scanned = scan_manifest_file(&root, file, mode, preflight_hash_limit_bytes);
+            let scanned = scan_manifest_file(
+                &root,
+                file,
+                mode,
+                preflight_hash_limit_bytes,
+                prehashed_ref.contains(&file.file_id),
+                baseline_ref.get(&normalize_index_path(&file.path)),
+            );
             if let Ok(mut guard) = results_ref.lock() {
                 guard.push(scanned);
             }
@@ -627,12 +1574,111 @@ fn scan_manifest_integrity_blocking(
 
     let scanned = results
         .lock()
-        .map_err(|_| LauncherError::Config("integrity scan results lock poisoned".to_string()))?
-        .clone();
+        .map_err(|_| LauncherError::Config("integrity scan results lock poisoned".to_string()))?;
+    Ok(scanned.clone())
+}
+
+/// Fully hashes `files` under `source_dir` and places the ones that match into
+/// `install_dir`. Returns the report and the file ids now verified in place.
+fn import_matching_files(
+    source_dir: &Path,
+    install_dir: &Path,
+    files: Vec<ManifestFile>,
+    move_files: bool,
+    workers: usize,
+) -> Result<(LocalImportReport, HashSet<String>)> {
+    let sizes: HashMap<String, u64> = files
+        .iter()
+        .map(|file| (file.file_id.clone(), file.size))
+        .collect();
+    let scanned = scan_manifest_files_blocking(
+        source_dir.to_path_buf(),
+        files,
+        IntegrityScanMode::PostDownload,
+        HashSet::new(),
+        HashMap::new(),
+        workers,
+        None,
+    )?;
+    let same_dir = paths_match(source_dir, install_dir);
+
+    let mut report = LocalImportReport {
+        total_files: scanned.len(),
+        moved: move_files && !same_dir,
+        ..LocalImportReport::default()
+    };
+    let mut verified = HashSet::new();
+    for item in scanned {
+        match item.status {
+            IntegrityFileStatus::Ok => {}
+            IntegrityFileStatus::Missing => {
+                report.missing_files += 1;
+                continue;
+            }
+            IntegrityFileStatus::Corrupt | IntegrityFileStatus::Error => {
+                report.corrupt_files += 1;
+                continue;
+            }
+        }
+        let relative = Path::new(&item.path);
+        if !is_safe_relative_path(relative) {
+            report.corrupt_files += 1;
+            continue;
+        }
+        if !same_dir {
+            let from = source_dir.join(relative);
+            let to = install_dir.join(relative);
+            if let Some(parent) = to.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // A rename fails across volumes; fall back to copy + delete.
+            let moved = move_files && std::fs::rename(&from, &to).is_ok();
+            if !moved {
+                std::fs::copy(&from, &to)?;
+                if move_files {
+                    let _ = std::fs::remove_file(&from);
+                }
+            }
+        }
+        report.imported_files += 1;
+        report.imported_bytes += sizes.get(&item.file_id).copied().unwrap_or(0);
+        verified.insert(item.file_id);
+    }
+    Ok((report, verified))
+}
+
+fn paths_match(left: &Path, right: &Path) -> bool {
+    match (left.canonicalize(), right.canonicalize()) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+fn scan_manifest_integrity_blocking(
+    install_dir: PathBuf,
+    files: Vec<ManifestFile>,
+    mode: IntegrityScanMode,
+    prehashed: HashSet<String>,
+    baseline: HashMap<String, FileIndexEntry>,
+    workers: usize,
+    cancel: Option<watch::Receiver<bool>>,
+) -> Result<IntegrityScanSummary> {
+    let started = Instant::now();
+    let expected_files = files.len();
+    let scanned = scan_manifest_files_blocking(
+        install_dir,
+        files,
+        mode,
+        prehashed,
+        baseline,
+        workers,
+        cancel,
+    )?;
 
     let mut summary = IntegrityScanSummary {
         total_files: scanned.len(),
         elapsed_ms: started.elapsed().as_millis(),
+        incomplete: scanned.len() < expected_files,
         ..IntegrityScanSummary::default()
     };
 
@@ -640,6 +1686,12 @@ fn scan_manifest_integrity_blocking(
         if item.hashed {
             summary.hashed_files += 1;
         }
+        if let Some(entry) = item.indexed {
+            summary.indexed.push(entry);
+        }
+        if item.status != IntegrityFileStatus::Ok {
+            summary.failed_paths.push(item.path.clone());
+        }
         match item.status {
             IntegrityFileStatus::Ok => summary.verified_files += 1,
             IntegrityFileStatus::Missing => {
@@ -672,16 +1724,38 @@ fn scan_manifest_integrity_blocking(
     Ok(summary)
 }
 
+/// `prehashed` lists file ids whose content hash was already verified while
+/// downloading; those only get the existence and size checks. `baseline`
+/// holds the `file_index_v2` rows preflight may confirm with blake3 alone.
+/// Workers stop picking up files once `cancel` turns true.
 async fn scan_manifest_integrity(
     install_dir: &Path,
     files: &[ManifestFile],
     mode: IntegrityScanMode,
+    prehashed: HashSet<String>,
+    baseline: HashMap<String, FileIndexEntry>,
+    workers: usize,
+    cancel: Option<watch::Receiver<bool>>,
 ) -> Result<IntegrityScanSummary> {
     let install_dir = install_dir.to_path_buf();
     let files = files.to_vec();
-    tokio::task::spawn_blocking(move || scan_manifest_integrity_blocking(install_dir, files, mode))
-        .await
-        .map_err(|err| LauncherError::Config(format!("integrity scan join error: {err}")))?
+    tokio::task::spawn_blocking(move || {
+        scan_manifest_integrity_blocking(
+            install_dir,
+            files,
+            mode,
+            prehashed,
+            baseline,
+            workers,
+            cancel,
+        )
+    })
+    .await
+    .map_err(|err| LauncherError::Config(format!("integrity scan join error: {err}")))?
+}
+
+fn normalize_index_path(path: &str) -> String {
+    path.replace('\\', "/").trim_start_matches('/').to_string()
 }
 
 fn resolve_depot_cache_max_bytes() -> u64 {
@@ -787,16 +1861,18 @@ impl DepotCache {
         Ok(Some(data))
     }
 
-    fn store_chunk(&self, hash: &str, data: &[u8]) -> Result<()> {
+    /// Saves a chunk under its hash. Returns whether anything was written;
+    /// chunks already cached are left alone.
+    fn store_chunk(&self, hash: &str, data: &[u8]) -> Result<bool> {
         let Some(path) = self.chunk_path(hash) else {
-            return Ok(());
+            return Ok(false);
         };
 
         if std::fs::metadata(&path)
             .map(|meta| meta.is_file() && meta.len() == data.len() as u64)
             .unwrap_or(false)
         {
-            return Ok(());
+            return Ok(false);
         }
 
         if let Some(parent) = path.parent() {
@@ -815,11 +1891,11 @@ impl DepotCache {
 
         std::fs::write(&temp_path, data)?;
         match std::fs::rename(&temp_path, &path) {
-            Ok(_) => Ok(()),
+            Ok(_) => Ok(true),
             Err(_) => {
                 if path.exists() {
                     let _ = std::fs::remove_file(&temp_path);
-                    Ok(())
+                    Ok(true)
                 } else {
                     let _ = std::fs::remove_file(&temp_path);
                     Err(LauncherError::Config(format!(
@@ -900,6 +1976,7 @@ impl DownloadManager {
             .clamp(5, 120) as u64;
 
         let mut client_builder = reqwest::Client::builder()
+            .user_agent(client_identity::user_agent())
             .timeout(Duration::from_secs(request_timeout_seconds))
             .connect_timeout(Duration::from_secs(connect_timeout_seconds))
             .pool_max_idle_per_host((max_concurrent_chunks * 2).clamp(8, 128))
@@ -910,12 +1987,12 @@ impl DownloadManager {
             client_builder = client_builder.no_proxy();
         }
 
-        if let Some(proxy_url) = std::env::var("LAUNCHER_PROXY")
+        let proxy_url = std::env::var("LAUNCHER_PROXY")
             .ok()
             .map(|value| value.trim().to_string())
-            .filter(|value| !value.is_empty())
-        {
-            match reqwest::Proxy::all(&proxy_url) {
+            .filter(|value| !value.is_empty());
+        if let Some(proxy_url) = proxy_url.as_ref() {
+            match reqwest::Proxy::all(proxy_url) {
                 Ok(proxy) => {
                     client_builder = client_builder.proxy(proxy);
                     tracing::info!("using launcher proxy: {}", proxy_url);
@@ -925,6 +2002,25 @@ impl DownloadManager {
         }
 
         let client = client_builder.build().expect("http client");
+        // QUIC can't go through the HTTP proxy, so a proxied setup stays on HTTP/2.
+        let http3_client = if proxy_url.is_none() {
+            build_http3_client(
+                Duration::from_secs(request_timeout_seconds),
+                Duration::from_secs(connect_timeout_seconds),
+            )
+        } else {
+            None
+        };
+        let http3_enabled = matches!(
+            db.get_setting(HTTP3_SETTING).ok().flatten().as_deref(),
+            Some("true")
+        );
+        let chunk_clients = ChunkClients {
+            http2: client.clone(),
+            http3: http3_client,
+            http3_enabled: Arc::new(AtomicBool::new(http3_enabled)),
+            http3_failed_hosts: Arc::new(Mutex::new(HashSet::new())),
+        };
 
         let max_bps = std::env::var("LAUNCHER_MAX_BPS")
             .ok()
@@ -932,30 +2028,64 @@ impl DownloadManager {
             .unwrap_or(0);
 
         let throttle = BandwidthThrottler::new(max_bps);
-        throttle.start_reset_task();
         let depot_cache = DepotCache::new(resolve_depot_cache_root(&file_manager));
-        let peer_server = PeerCacheServer::start(depot_cache.root.clone());
-        let peer_coordinator = peer_server
-            .as_ref()
-            .and_then(|server| PeerCoordinator::new(api.clone(), server.advertise_info()));
-        if let Some(coordination) = peer_coordinator.as_ref() {
-            coordination.start();
+        let mut peer = PeerNetwork::default();
+        if load_p2p_enabled(&db) {
+            peer.start(&api, &depot_cache.root, load_p2p_upload_limit(&db));
+        } else {
+            tracing::info!("p2p sharing disabled by setting");
         }
+        let peer_sources = load_peer_source_config(&db);
+        let write_strategy = load_write_strategy_override(&db);
+        let storage_options = load_storage_options(&db);
+        let tuning = load_download_tuning(&db);
+        let registry = Arc::new(Mutex::new(HashMap::new()));
+        let data_cap = DataCapMonitor {
+            app_handle: app_handle.clone(),
+            registry: registry.clone(),
+            settings: Arc::new(Mutex::new(load_data_cap(&db))),
+            notified: Arc::new(AtomicBool::new(false)),
+        };
+        let mirror_ranker = MirrorRanker::new(client.clone());
 
         Self {
             app_handle,
-            client,
+            chunk_clients,
             db,
             api,
             downloads_api,
             file_manager,
-            registry: Arc::new(Mutex::new(HashMap::new())),
+            registry,
             throttle,
             max_concurrent_chunks,
             depot_cache,
-            peer_server,
-            peer_coordinator,
+            peer: Arc::new(Mutex::new(peer)),
+            peer_sources: Arc::new(Mutex::new(peer_sources)),
+            mirror_ranker,
+            peer_transfers: PeerTransferStats::default(),
+            write_strategy: Arc::new(Mutex::new(write_strategy)),
+            storage_options: Arc::new(Mutex::new(storage_options)),
+            host_limits: HostConnectionLimiter::from_env(
+                tuning.map_or(max_concurrent_chunks, |value| value.max_concurrent_chunks),
+            ),
+            imported_files: Arc::new(Mutex::new(HashMap::new())),
+            pending_repairs: Arc::new(Mutex::new(HashMap::new())),
+            tuning: Arc::new(Mutex::new(tuning)),
+            data_cap,
+        }
+    }
+
+    /// Install dir for a new install of `slug`, on a root that stays put for
+    /// the rest of the install.
+    pub fn claim_game_dir(&self, slug: &str) -> PathBuf {
+        let (root, claimed) = self.file_manager.claim_install_root(slug);
+        if claimed {
+            tracing::info!("installing {} under {}", slug, root.display());
+            if let Err(err) = persist_game_install_roots(&self.db, &self.file_manager) {
+                tracing::warn!("failed to save the install root for {}: {}", slug, err);
+            }
         }
+        root.join(slug)
     }
 
     pub async fn start_download(
@@ -966,7 +2096,11 @@ impl DownloadManager {
         requested_method: Option<&str>,
         install_dir_override: Option<&str>,
     ) -> Result<()> {
-        self.throttle.start_reset_task();
+        self.data_cap.ensure_can_start(&self.db)?;
+        let default_method = self.download_method();
+        let requested_method = requested_method
+            .filter(|method| !method.trim().is_empty())
+            .or(Some(default_method.as_str()));
         if self
             .registry
             .lock()
@@ -977,7 +2111,24 @@ impl DownloadManager {
         }
 
         let (tx, rx) = watch::channel(DownloadControl::Running);
-        let handle = DownloadHandle { control: tx };
+        let discard_partial = Arc::new(AtomicBool::new(false));
+        let refreshed_urls = RefreshedUrls::default();
+        let repair_manifest_path = install_dir_override
+            .map(|dir| PathBuf::from(dir.trim()))
+            .and_then(|dir| {
+                self.pending_repairs
+                    .lock()
+                    .ok()
+                    .and_then(|guard| guard.get(&dir).map(|repair| repair.manifest_path.clone()))
+            });
+        let handle = DownloadHandle {
+            control: tx,
+            verify_cancel: watch::channel(false).0,
+            discard_partial: discard_partial.clone(),
+            manifest_path: repair_manifest_path
+                .unwrap_or_else(|| manifest_request_path(slug, requested_method)),
+            refreshed_urls: refreshed_urls.clone(),
+        };
         self.registry
             .lock()
             .map_err(|_| LauncherError::Config("download registry locked".to_string()))?
@@ -999,6 +2150,7 @@ impl DownloadManager {
                     requested_method.as_deref(),
                     install_dir_override.as_deref(),
                     rx,
+                    refreshed_urls,
                 )
                 .await;
             let _ = manager.depot_cache.gc_if_needed();
@@ -1040,6 +2192,15 @@ impl DownloadManager {
                     speed_history: Vec::new(),
                     updated_at: chrono::Utc::now().timestamp(),
                 });
+                if cancelled && discard_partial.load(Ordering::SeqCst) {
+                    if let Err(err) = manager.discard_partial_download(&download_id).await {
+                        tracing::warn!(
+                            "failed to discard partial data for {}: {}",
+                            download_id,
+                            err
+                        );
+                    }
+                }
                 if !cancelled {
                     let _ = manager.app_handle.emit(
                         "download-runtime-error",
@@ -1048,6 +2209,8 @@ impl DownloadManager {
                             game_id: game_id.clone(),
                             slug: slug.clone(),
                             message: err_message,
+                            code: err.code(),
+                            retryable: err.retryable(),
                         },
                     );
                 }
@@ -1068,13 +2231,99 @@ impl DownloadManager {
         Ok(())
     }
 
+    /// Resumes a paused download. The manifest is fetched again first so
+    /// chunks that haven't been downloaded yet use fresh URLs; completed
+    /// chunks are tracked by hash and are unaffected.
     pub async fn resume_download(&self, download_id: &str) -> Result<()> {
+        self.data_cap.ensure_can_start(&self.db)?;
+        if let Err(err) = self.refresh_chunk_urls(download_id).await {
+            tracing::warn!(
+                "resume of {} keeps the previous chunk URLs: {}",
+                download_id,
+                err
+            );
+        }
         self.set_control(download_id, DownloadControl::Running)?;
         let _ = self.db.update_download_status(download_id, "downloading");
         Ok(())
     }
 
-    pub async fn cancel_download(&self, download_id: &str) -> Result<()> {
+    fn pause_for_low_disk(&self, low: LowDiskPayload) {
+        if self
+            .set_control(&low.download_id, DownloadControl::Paused)
+            .is_err()
+        {
+            return;
+        }
+        let _ = self.db.update_download_status(&low.download_id, "paused");
+        tracing::warn!(
+            "paused download {}: {} free at {}, needs {}",
+            low.download_id,
+            format_bytes(low.available_bytes),
+            low.install_dir,
+            format_bytes(low.required_bytes)
+        );
+        let _ = self.app_handle.emit("download-low-disk", &low);
+        if low.auto_resume {
+            let manager = self.clone();
+            tokio::spawn(async move { manager.resume_when_space_frees(low).await });
+        }
+    }
+
+    /// Polls the drive of a download paused by `pause_for_low_disk` and
+    /// resumes it once there is room again. Any other pause, resume or cancel
+    /// in the meantime ends the watch.
+    async fn resume_when_space_frees(&self, low: LowDiskPayload) {
+        let Some(mut control) = self.registry.lock().ok().and_then(|guard| {
+            guard
+                .get(&low.download_id)
+                .map(|handle| handle.control.subscribe())
+        }) else {
+            return;
+        };
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(LOW_DISK_RESUME_POLL) => {}
+                _ = control.changed() => return,
+            }
+            let available = available_disk_space(Path::new(&low.install_dir)).unwrap_or(0);
+            if available < low.required_bytes {
+                continue;
+            }
+            match self.resume_download(&low.download_id).await {
+                Ok(()) => tracing::info!(
+                    "resumed download {} with {} free",
+                    low.download_id,
+                    format_bytes(available)
+                ),
+                Err(err) => tracing::warn!(
+                    "download {} stays paused after low disk: {}",
+                    low.download_id,
+                    err
+                ),
+            }
+            return;
+        }
+    }
+
+    /// Cancels a download. With `keep_partial` the `.part` files and completed
+    /// chunk rows are left in place so a later `start_download` resumes from
+    /// them; without it they are deleted together with the saved download state.
+    /// A running task removes itself from the registry once it has stopped, and
+    /// the cleanup runs after that point so no chunk write races the deletion.
+    pub async fn cancel_download(&self, download_id: &str, keep_partial: bool) -> Result<()> {
+        let running = self
+            .registry
+            .lock()
+            .ok()
+            .and_then(|guard| {
+                guard.get(download_id).map(|handle| {
+                    handle
+                        .discard_partial
+                        .store(!keep_partial, Ordering::SeqCst);
+                })
+            })
+            .is_some();
         if let Err(err) = self.set_control(download_id, DownloadControl::Cancelled) {
             tracing::warn!(
                 "cancel_download control signal skipped for {}: {}",
@@ -1083,47 +2332,1151 @@ impl DownloadManager {
             );
         }
         let _ = self.db.update_download_status(download_id, "cancelled");
+        if !running && !keep_partial {
+            self.discard_partial_download(download_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Stops the integrity scan a running download is in. Files checked so far
+    /// are kept; the download is paused instead of completing unverified.
+    pub async fn cancel_verify(&self, download_id: &str) -> Result<()> {
+        let guard = self
+            .registry
+            .lock()
+            .map_err(|_| LauncherError::Config("download registry locked".to_string()))?;
+        let handle = guard
+            .get(download_id)
+            .ok_or_else(|| LauncherError::NotFound("download not running".to_string()))?;
+        handle.verify_cancel.send_replace(true);
+        Ok(())
+    }
+
+    async fn discard_partial_download(&self, download_id: &str) -> Result<()> {
+        if let Some(state) = self.db.get_download_state(download_id)? {
+            let install_dir = PathBuf::from(state.install_dir.trim());
+            match serde_json::from_str::<Manifest>(&state.manifest_json) {
+                Ok(manifest) if !state.install_dir.trim().is_empty() => {
+                    let mut scratch_dirs = HashSet::new();
+                    for file in &manifest.files {
+                        let temp_path = partial_file_path(&install_dir, file);
+                        if let Some(parent) = temp_path.parent() {
+                            scratch_dirs.insert(parent.join(".aria2"));
+                        }
+                        if temp_path.exists() {
+                            tokio::fs::remove_file(&temp_path).await?;
+                        }
+                    }
+                    for dir in scratch_dirs {
+                        if dir.exists() {
+                            let _ = tokio::fs::remove_dir_all(&dir).await;
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => tracing::warn!(
+                    "cannot read manifest for {} while discarding partial files: {}",
+                    download_id,
+                    err
+                ),
+            }
+        }
+        self.db.clear_download_chunks(download_id)?;
+        self.db.clear_download_state(download_id)?;
+        tracing::info!("discarded partial data for download {}", download_id);
         Ok(())
     }
 
     pub async fn set_download_limit(&self, max_mbps: f64) -> Result<()> {
-        self.throttle.start_reset_task();
         let max_bps = if max_mbps <= 0.0 {
             0
         } else {
             (max_mbps * 1024.0 * 1024.0) as u64
         };
-        self.throttle.set_limit(max_bps).await;
+        self.throttle.set_limit(max_bps);
         Ok(())
     }
 
-    fn set_control(&self, download_id: &str, state: DownloadControl) -> Result<()> {
-        let guard = self
-            .registry
-            .lock()
-            .map_err(|_| LauncherError::Config("download registry locked".to_string()))?;
-        let handle = guard
-            .get(download_id)
-            .ok_or_else(|| LauncherError::NotFound("download not running".to_string()))?;
-        handle
-            .control
-            .send(state)
-            .map_err(|_| LauncherError::Config("download control channel closed".to_string()))?;
-        Ok(())
+    /// Current bandwidth cap in MB/s; 0 means unlimited.
+    pub async fn download_limit_mbps(&self) -> f64 {
+        self.throttle.limit() as f64 / (1024.0 * 1024.0)
     }
 
-    async fn run_download(
-        &self,
+    pub fn peer_stats(&self) -> PeerStats {
+        let server = self.peer_server();
+        let peers = self
+            .peer_coordinator()
+            .map(|coordination| coordination.peer_stats(&self.peer_transfers))
+            .unwrap_or_default();
+        PeerStats::from_parts(
+            server.as_ref().map(|server| server.peer_id().to_string()),
+            server.as_ref().map(PeerCacheServer::upload_stats),
+            peers,
+        )
+    }
+
+    fn peer_server(&self) -> Option<PeerCacheServer> {
+        self.peer.lock().ok().and_then(|peer| peer.server.clone())
+    }
+
+    fn peer_coordinator(&self) -> Option<PeerCoordinator> {
+        self.peer
+            .lock()
+            .ok()
+            .and_then(|peer| peer.coordinator.clone())
+    }
+
+    pub fn p2p_status(&self) -> P2pStatus {
+        let enabled = load_p2p_enabled(&self.db);
+        let Ok(peer) = self.peer.lock() else {
+            return P2pStatus {
+                enabled,
+                active: false,
+                peer_id: None,
+                known_peers: 0,
+                bytes_served: 0,
+                upload_limit_bps: 0,
+                upload_limit_auto: true,
+            };
+        };
+        let served_now = peer
+            .server
+            .as_ref()
+            .map(|server| server.upload_stats().bytes_served)
+            .unwrap_or(0);
+        P2pStatus {
+            enabled,
+            active: peer.server.is_some(),
+            peer_id: peer
+                .server
+                .as_ref()
+                .map(|server| server.peer_id().to_string()),
+            known_peers: peer
+                .coordinator
+                .as_ref()
+                .map(|coordination| coordination.known_peers().len())
+                .unwrap_or(0),
+            bytes_served: peer.served_before.saturating_add(served_now),
+            upload_limit_bps: peer
+                .server
+                .as_ref()
+                .map(PeerCacheServer::upload_limit_bps)
+                .unwrap_or(0),
+            upload_limit_auto: peer
+                .server
+                .as_ref()
+                .map(PeerCacheServer::upload_limit_is_auto)
+                .unwrap_or(true),
+        }
+    }
+
+    /// Caps the bytes per second served to peers, or with `None` goes back to
+    /// half of the upload rate measured while serving.
+    pub fn set_p2p_upload_limit(&self, max_bps: Option<u64>) -> Result<P2pStatus> {
+        match max_bps {
+            Some(value) => self
+                .db
+                .set_setting(P2P_UPLOAD_LIMIT_SETTING, &value.to_string())?,
+            None => self.db.delete_setting(P2P_UPLOAD_LIMIT_SETTING)?,
+        }
+        if let Some(server) = self.peer_server() {
+            server.set_upload_limit_bps(max_bps.unwrap_or(0));
+        }
+        Ok(self.p2p_status())
+    }
+
+    /// Turns peer sharing on or off and remembers the choice. Turning it off
+    /// closes the chunk server and stops heartbeats, so this launcher neither
+    /// serves nor is offered as a source; downloads already planned with peer
+    /// URLs fall back to the CDN as those fail.
+    pub fn set_p2p_enabled(&self, enabled: bool) -> Result<P2pStatus> {
+        self.db
+            .set_setting(P2P_ENABLED_SETTING, if enabled { "true" } else { "false" })?;
+        {
+            let mut peer = self
+                .peer
+                .lock()
+                .map_err(|_| LauncherError::Config("peer network locked".to_string()))?;
+            if enabled {
+                peer.start(
+                    &self.api,
+                    &self.depot_cache.root,
+                    load_p2p_upload_limit(&self.db),
+                );
+            } else {
+                peer.stop();
+            }
+        }
+        Ok(self.p2p_status())
+    }
+
+    pub fn peer_source_config(&self) -> PeerSourceConfig {
+        self.peer_sources
+            .lock()
+            .map(|config| *config)
+            .unwrap_or_else(|_| default_peer_source_config())
+    }
+
+    pub fn set_peer_source_config(
+        &self,
+        policy: PeerSourcePolicy,
+        fanout: Option<usize>,
+    ) -> Result<PeerSourceConfig> {
+        let mut config = self.peer_source_config();
+        config.policy = policy;
+        if let Some(value) = fanout {
+            config.fanout = value.clamp(1, MAX_P2P_FANOUT);
+        }
+        self.db
+            .set_setting(P2P_SOURCE_POLICY_SETTING, config.policy.as_str())?;
+        self.db
+            .set_setting(P2P_FANOUT_SETTING, &config.fanout.to_string())?;
+        *self
+            .peer_sources
+            .lock()
+            .map_err(|_| LauncherError::Config("peer source config locked".to_string()))? = config;
+        Ok(config)
+    }
+
+    /// Resolves the write strategy for an install path: saved override, then
+    /// `LAUNCHER_WRITE_STRATEGY`, then the detected disk type.
+    pub fn write_strategy_info(&self, path: &Path) -> WriteStrategyInfo {
+        let configured = self
+            .write_strategy
+            .lock()
+            .map(|value| *value)
+            .unwrap_or(None);
+        let disk_kind = disk_kind_for_path(path);
+        let strategy = configured
+            .or_else(|| {
+                std::env::var("LAUNCHER_WRITE_STRATEGY")
+                    .ok()
+                    .and_then(|value| WriteStrategy::parse(&value))
+            })
+            .unwrap_or_else(|| default_write_strategy(disk_kind));
+        WriteStrategyInfo {
+            strategy,
+            configured,
+            disk_kind: match disk_kind {
+                Some(DiskKind::HDD) => "hdd",
+                Some(DiskKind::SSD) => "ssd",
+                _ => "unknown",
+            },
+            path: path.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Saves a write strategy override, or clears it with `None` so the
+    /// strategy follows the detected disk again.
+    pub fn set_write_strategy_override(&self, strategy: Option<WriteStrategy>) -> Result<()> {
+        match strategy {
+            Some(value) => self
+                .db
+                .set_setting(WRITE_STRATEGY_SETTING, value.as_str())?,
+            None => self.db.delete_setting(WRITE_STRATEGY_SETTING)?,
+        }
+        *self
+            .write_strategy
+            .lock()
+            .map_err(|_| LauncherError::Config("write strategy locked".to_string()))? = strategy;
+        Ok(())
+    }
+
+    pub fn storage_options(&self) -> StorageOptions {
+        self.storage_options
+            .lock()
+            .map(|options| *options)
+            .unwrap_or_default()
+    }
+
+    /// Saves storage options for downloads started from now on.
+    pub fn set_storage_options(&self, mut options: StorageOptions) -> Result<StorageOptions> {
+        options.safety_margin_bytes = options
+            .safety_margin_bytes
+            .map(|bytes| bytes.min(MAX_STORAGE_SAFETY_MARGIN_BYTES));
+        self.db
+            .set_setting(STORAGE_OPTIONS_SETTING, &serde_json::to_string(&options)?)?;
+        *self
+            .storage_options
+            .lock()
+            .map_err(|_| LauncherError::Config("storage options locked".to_string()))? = options;
+        Ok(options)
+    }
+
+    /// Effective concurrency for new downloads and integrity scans: the applied
+    /// tuning when there is one, otherwise the environment-driven defaults.
+    pub fn download_tuning(&self) -> DownloadTuning {
+        self.tuning
+            .lock()
+            .ok()
+            .and_then(|value| *value)
+            .unwrap_or_else(|| DownloadTuning {
+                max_concurrent_chunks: self.max_concurrent_chunks,
+                integrity_workers: resolve_integrity_scan_workers(),
+            })
+    }
+
+    /// Applies `tuning` to downloads started from now on, or drops back to the
+    /// defaults with `None`. Returns the effective values.
+    pub fn set_download_tuning_override(
+        &self,
+        tuning: Option<DownloadTuning>,
+    ) -> Result<DownloadTuning> {
+        let tuning = tuning.map(|value| DownloadTuning {
+            max_concurrent_chunks: value.max_concurrent_chunks.clamp(1, MAX_CONCURRENT_CHUNKS),
+            integrity_workers: value.integrity_workers.clamp(1, MAX_INTEGRITY_SCAN_WORKERS),
+        });
+        match tuning {
+            Some(value) => self
+                .db
+                .set_setting(DOWNLOAD_TUNING_SETTING, &serde_json::to_string(&value)?)?,
+            None => self.db.delete_setting(DOWNLOAD_TUNING_SETTING)?,
+        }
+        *self
+            .tuning
+            .lock()
+            .map_err(|_| LauncherError::Config("download tuning locked".to_string()))? = tuning;
+        let effective = self.download_tuning();
+        self.host_limits
+            .follow_concurrency(effective.max_concurrent_chunks);
+        Ok(effective)
+    }
+
+    /// How many archives an archive-mode install unpacks at once: the saved
+    /// override, otherwise one per core.
+    pub fn extract_workers(&self) -> usize {
+        self.db
+            .get_setting(EXTRACT_WORKERS_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .map(|value| value.clamp(1, MAX_EXTRACT_WORKERS))
+            .unwrap_or_else(default_extract_workers)
+    }
+
+    /// Saves an extraction worker count, or drops back to the core count with
+    /// `None`. Returns the effective value.
+    pub fn set_extract_workers(&self, workers: Option<usize>) -> Result<usize> {
+        match workers {
+            Some(value) => self.db.set_setting(
+                EXTRACT_WORKERS_SETTING,
+                &value.clamp(1, MAX_EXTRACT_WORKERS).to_string(),
+            )?,
+            None => self.db.delete_setting(EXTRACT_WORKERS_SETTING)?,
+        }
+        Ok(self.extract_workers())
+    }
+
+    pub fn http3_status(&self) -> Http3Status {
+        Http3Status {
+            enabled: self.chunk_clients.http3_enabled.load(Ordering::Relaxed),
+            supported: self.chunk_clients.http3.is_some(),
+        }
+    }
+
+    /// Opts chunk downloads in or out of HTTP/3. Takes effect for requests
+    /// made from now on; builds without the `http3` feature stay on HTTP/2.
+    pub fn set_http3_enabled(&self, enabled: bool) -> Result<Http3Status> {
+        self.db
+            .set_setting(HTTP3_SETTING, if enabled { "true" } else { "false" })?;
+        self.chunk_clients
+            .http3_enabled
+            .store(enabled, Ordering::Relaxed);
+        if enabled {
+            if let Ok(mut failed) = self.chunk_clients.http3_failed_hosts.lock() {
+                failed.clear();
+            }
+        }
+        Ok(self.http3_status())
+    }
+
+    pub fn data_cap_status(&self) -> Result<DataCapStatus> {
+        self.data_cap.status(&self.db)
+    }
+
+    /// Fails with `LauncherError::DataCap` while the cap blocks new downloads.
+    pub fn ensure_within_data_cap(&self) -> Result<()> {
+        self.data_cap.ensure_can_start(&self.db)
+    }
+
+    /// Sets the monthly cap (`0` turns it off) and the day its cycle resets.
+    /// Any override for the current cycle is dropped.
+    pub fn set_data_cap(&self, monthly_cap_bytes: u64, reset_day: u32) -> Result<DataCapStatus> {
+        self.save_data_cap(
+            DataCapSettings {
+                monthly_cap_bytes,
+                reset_day,
+                override_cycle: None,
+            }
+            .normalized(),
+        )?;
+        self.data_cap_status()
+    }
+
+    /// Lets downloads run past the cap until the current cycle ends.
+    pub fn override_data_cap(&self) -> Result<DataCapStatus> {
+        let mut settings = self.data_cap.settings();
+        settings.override_cycle = Some(self.data_cap_status()?.cycle_start);
+        self.save_data_cap(settings)?;
+        self.data_cap_status()
+    }
+
+    fn save_data_cap(&self, settings: DataCapSettings) -> Result<()> {
+        self.db
+            .set_setting(DATA_CAP_SETTING, &serde_json::to_string(&settings)?)?;
+        *self
+            .data_cap
+            .settings
+            .lock()
+            .map_err(|_| LauncherError::Config("data cap settings locked".to_string()))? = settings;
+        self.data_cap.notified.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Method used for downloads started without one.
+    pub fn download_method(&self) -> String {
+        self.db
+            .get_setting(DOWNLOAD_METHOD_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|value| parse_download_method(&value))
+            .unwrap_or_else(|| "auto".to_string())
+    }
+
+    /// Saves the default download method. Returns the normalized key.
+    pub fn set_download_method(&self, method: &str) -> Result<String> {
+        let key = parse_download_method(method)
+            .ok_or_else(|| LauncherError::Config(format!("unknown download method: {method}")))?;
+        self.db.set_setting(DOWNLOAD_METHOD_SETTING, &key)?;
+        Ok(key)
+    }
+
+    pub fn download_schedule(&self) -> Option<DownloadSchedule> {
+        let raw = self
+            .db
+            .get_setting(DOWNLOAD_SCHEDULE_SETTING)
+            .ok()
+            .flatten()?;
+        serde_json::from_str::<DownloadSchedule>(&raw)
+            .map_err(|err| tracing::warn!("ignoring invalid {DOWNLOAD_SCHEDULE_SETTING}: {err}"))
+            .ok()
+            .filter(DownloadSchedule::is_valid)
+    }
+
+    /// Saves the download window, or clears it with `None`.
+    pub fn set_download_schedule(&self, schedule: Option<DownloadSchedule>) -> Result<()> {
+        match schedule {
+            Some(value) if !value.is_valid() => Err(LauncherError::Config(format!(
+                "invalid download schedule: {}-{}",
+                value.start_hour, value.end_hour
+            ))),
+            Some(value) => self
+                .db
+                .set_setting(DOWNLOAD_SCHEDULE_SETTING, &serde_json::to_string(&value)?),
+            None => self.db.delete_setting(DOWNLOAD_SCHEDULE_SETTING),
+        }
+    }
+
+    /// Whether manifests may run their post-install step without asking.
+    pub fn post_install_allowed(&self) -> bool {
+        matches!(
+            self.db
+                .get_setting(POST_INSTALL_ALLOWED_SETTING)
+                .ok()
+                .flatten()
+                .as_deref(),
+            Some("true")
+        )
+    }
+
+    pub fn set_post_install_allowed(&self, allowed: bool) -> Result<()> {
+        self.db.set_setting(
+            POST_INSTALL_ALLOWED_SETTING,
+            if allowed { "true" } else { "false" },
+        )
+    }
+
+    /// Fetches the manifest of `slug` and compares it with the installed one,
+    /// if any. No game data is downloaded.
+    pub async fn manifest_info(&self, slug: &str) -> Result<ManifestInfo> {
+        let manifest: Manifest = self
+            .api
+            .get_auth_first(&manifest_request_path(slug, None))
+            .await?;
+        let installed = self
+            .installed_dir(slug)
+            .ok()
+            .and_then(|dir| load_previous_manifest(&dir).ok());
+        let update_available = installed.as_ref().is_some_and(|installed| {
+            installed.build_id != manifest.build_id || installed.version != manifest.version
+        });
+        Ok(ManifestInfo {
+            install_mode: manifest
+                .install_mode
+                .clone()
+                .unwrap_or_else(|| "chunks".to_string()),
+            file_count: manifest.files.len(),
+            installed_version: installed.as_ref().map(|m| m.version.clone()),
+            installed_build_id: installed.map(|m| m.build_id),
+            update_available,
+            game_id: manifest.game_id,
+            slug: manifest.slug,
+            version: manifest.version,
+            build_id: manifest.build_id,
+            total_size: manifest.total_size,
+            compressed_size: manifest.compressed_size,
+        })
+    }
+
+    /// Runs the post-install step of the installed `slug` now, e.g. once the
+    /// user confirmed a `post-install-pending` prompt.
+    pub async fn run_post_install(&self, slug: &str) -> Result<PostInstallReport> {
+        let install_dir = self.installed_dir(slug)?;
+        let step = load_previous_manifest(&install_dir)?
+            .post_install
+            .ok_or_else(|| LauncherError::NotFound(format!("post-install step for {slug}")))?;
+        self.execute_post_install(slug, install_dir, step).await
+    }
+
+    /// Starts the manifest's post-install step after a completed download,
+    /// or asks the UI to confirm it while scripts aren't allowed.
+    fn schedule_post_install(
+        &self,
+        download_id: &str,
+        slug: &str,
+        install_dir: &Path,
+        manifest: &Manifest,
+    ) {
+        let Some(step) = manifest.post_install.clone() else {
+            return;
+        };
+        if post_install::already_ran(install_dir, &step) {
+            return;
+        }
+        if !self.post_install_allowed() {
+            let _ = self.app_handle.emit(
+                "post-install-pending",
+                PendingPostInstall {
+                    download_id: download_id.to_string(),
+                    slug: slug.to_string(),
+                    command: step.command.clone(),
+                    args: step.args.clone(),
+                },
+            );
+            return;
+        }
+
+        let manager = self.clone();
+        let slug = slug.to_string();
+        let install_dir = install_dir.to_path_buf();
+        tokio::spawn(async move {
+            if let Err(err) = manager.execute_post_install(&slug, install_dir, step).await {
+                tracing::warn!("post-install step for {} failed to run: {}", slug, err);
+                let _ = manager.app_handle.emit(
+                    "post-install-failed",
+                    serde_json::json!({ "slug": slug, "error": err.to_string() }),
+                );
+            }
+        });
+    }
+
+    async fn execute_post_install(
+        &self,
+        slug: &str,
+        install_dir: PathBuf,
+        step: PostInstallStep,
+    ) -> Result<PostInstallReport> {
+        let slug_owned = slug.to_string();
+        let report = tokio::task::spawn_blocking(move || {
+            post_install::run(&slug_owned, &install_dir, &step)
+        })
+        .await
+        .map_err(|err| LauncherError::Config(format!("post-install join error: {err}")))??;
+        tracing::info!(
+            "post-install slug={} command={} success={} exit={:?} timed_out={} elapsed_ms={}",
+            slug,
+            report.command,
+            report.success,
+            report.exit_code,
+            report.timed_out,
+            report.elapsed_ms
+        );
+        let _ = self.app_handle.emit("post-install-finished", &report);
+        Ok(report)
+    }
+
+    async fn refresh_chunk_urls(&self, download_id: &str) -> Result<()> {
+        let (manifest_path, refreshed_urls) = {
+            let guard = self
+                .registry
+                .lock()
+                .map_err(|_| LauncherError::Config("download registry locked".to_string()))?;
+            match guard.get(download_id) {
+                Some(handle) if *handle.control.borrow() == DownloadControl::Paused => {
+                    (handle.manifest_path.clone(), handle.refreshed_urls.clone())
+                }
+                _ => return Ok(()),
+            }
+        };
+        let manifest: Manifest = self.api.get_auth_first(&manifest_path).await?;
+        let urls = refreshed_chunk_urls(&manifest);
+        tracing::info!(
+            "refreshed {} chunk URLs for download {}",
+            urls.len(),
+            download_id
+        );
+        *refreshed_urls
+            .lock()
+            .map_err(|_| LauncherError::Config("refreshed URL map locked".to_string()))? = urls;
+        Ok(())
+    }
+
+    fn set_control(&self, download_id: &str, state: DownloadControl) -> Result<()> {
+        let guard = self
+            .registry
+            .lock()
+            .map_err(|_| LauncherError::Config("download registry locked".to_string()))?;
+        let handle = guard
+            .get(download_id)
+            .ok_or_else(|| LauncherError::NotFound("download not running".to_string()))?;
+        handle
+            .control
+            .send(state)
+            .map_err(|_| LauncherError::Config("download control channel closed".to_string()))?;
+        Ok(())
+    }
+
+    /// Adopts an existing copy of `slug` from `source_dir`. Files that match
+    /// the manifest are moved or copied into `install_dir`; the next
+    /// `start_download` for that dir then only fetches what is still missing
+    /// or corrupt.
+    pub async fn import_local_install(
+        &self,
+        slug: &str,
+        source_dir: &Path,
+        install_dir: &Path,
+        move_files: bool,
+    ) -> Result<LocalImportReport> {
+        if !source_dir.is_dir() {
+            return Err(LauncherError::NotFound(format!(
+                "import source {}",
+                source_dir.display()
+            )));
+        }
+        let manifest: Manifest = self
+            .api
+            .get_auth_first(&manifest_request_path(slug, None))
+            .await?;
+        if is_archive_mode(&manifest) {
+            return Err(LauncherError::Config(format!(
+                "{} is distributed as archives and can't be imported file by file",
+                slug
+            )));
+        }
+
+        let started = Instant::now();
+        let source = source_dir.to_path_buf();
+        let target = install_dir.to_path_buf();
+        let files = manifest.files.clone();
+        let workers = self.download_tuning().integrity_workers;
+        let (report, verified) = tokio::task::spawn_blocking(move || {
+            import_matching_files(&source, &target, files, move_files, workers)
+        })
+        .await
+        .map_err(|err| LauncherError::Config(format!("import join error: {err}")))??;
+
+        tracing::info!(
+            "local import slug={} source={} matched={} missing={} corrupt={} bytes={} elapsed_ms={}",
+            slug,
+            source_dir.display(),
+            report.imported_files,
+            report.missing_files,
+            report.corrupt_files,
+            report.imported_bytes,
+            started.elapsed().as_millis()
+        );
+        self.imported_files
+            .lock()
+            .map_err(|_| LauncherError::Config("import registry locked".to_string()))?
+            .insert(install_dir.to_path_buf(), verified);
+        Ok(report)
+    }
+
+    /// Preflight pass over the install's `manifest.json` that only checks file
+    /// presence and size, so it stays fast enough to run before every launch.
+    /// `None` when the folder has no manifest to check against, or is an
+    /// archive install that doesn't list its extracted files.
+    pub async fn check_install_sizes(
+        &self,
+        install_dir: &Path,
+    ) -> Result<Option<InstallSizeCheck>> {
+        if !install_dir.join(MANIFEST_FILE).is_file() {
+            return Ok(None);
+        }
+        let install_dir = install_dir.to_path_buf();
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || {
+            check_installed_sizes(&install_dir, &db).map(|(_, check)| check)
+        })
+        .await
+        .map_err(|err| LauncherError::Config(format!("install check join error: {err}")))?
+    }
+
+    /// Looks for launcher installs (folders with a `manifest.json`) directly
+    /// under `root` and records every one whose files are all present with
+    /// the expected size as installed, so it isn't downloaded again. Files
+    /// aren't hashed; a repair or the next update verifies them.
+    pub async fn scan_library_folder(&self, root: &Path) -> Result<Vec<LibraryScanEntry>> {
+        let mut entries = Vec::new();
+        let mut dirs = tokio::fs::read_dir(root).await?;
+        while let Some(entry) = dirs.next_entry().await? {
+            let install_dir = entry.path();
+            if !install_dir.join(MANIFEST_FILE).is_file() {
+                continue;
+            }
+            let dir = install_dir.clone();
+            let db = self.db.clone();
+            let checked = tokio::task::spawn_blocking(move || check_installed_sizes(&dir, &db))
+                .await
+                .map_err(|err| LauncherError::Config(format!("library scan join error: {err}")))?;
+            let mut scan = LibraryScanEntry {
+                install_dir: install_dir.to_string_lossy().to_string(),
+                ..LibraryScanEntry::default()
+            };
+            let (manifest, check) = match checked {
+                Ok(checked) => checked,
+                Err(err) => {
+                    tracing::warn!("unreadable manifest in {}: {}", install_dir.display(), err);
+                    scan.status = "invalid".to_string();
+                    entries.push(scan);
+                    continue;
+                }
+            };
+            scan.slug = Some(manifest.slug.clone());
+            scan.game_id = Some(manifest.game_id.clone());
+            scan.version = Some(manifest.version.clone());
+            let Some(check) = check else {
+                scan.status = "unverifiable".to_string();
+                entries.push(scan);
+                continue;
+            };
+            scan.missing_files = check.missing.len();
+            scan.size_mismatches = check.size_mismatches.len();
+            scan.unreadable_files = check.unreadable.len();
+            scan.status = if !check.missing.is_empty() || !check.size_mismatches.is_empty() {
+                "incomplete".to_string()
+            } else if !check.unreadable.is_empty() {
+                "unreadable".to_string()
+            } else if self.record_installed(&manifest, &install_dir)? {
+                "installed".to_string()
+            } else {
+                "busy".to_string()
+            };
+            entries.push(scan);
+        }
+        entries.sort_by(|a, b| a.install_dir.cmp(&b.install_dir));
+        tracing::info!(
+            "library scan {} found {} installs, {} recorded",
+            root.display(),
+            entries.len(),
+            entries
+                .iter()
+                .filter(|entry| entry.status == "installed")
+                .count()
+        );
+        Ok(entries)
+    }
+
+    /// Stores `install_dir` as the completed install of the manifest's game.
+    /// Returns `false` when the game has a download that hasn't completed,
+    /// running or not, since a paused or queued update keeps its saved
+    /// chunks in that state.
+    fn record_installed(&self, manifest: &Manifest, install_dir: &Path) -> Result<bool> {
+        let existing = self.db.get_download_state_by_slug(&manifest.slug)?;
+        if let Some(existing) = existing.as_ref() {
+            let active = self
+                .registry
+                .lock()
+                .map(|guard| guard.contains_key(&existing.id))
+                .unwrap_or(false);
+            if blocks_install_record(existing, active) {
+                return Ok(false);
+            }
+        }
+        let install_path = install_dir.to_string_lossy().to_string();
+        self.db.save_download_state(&DownloadState {
+            id: existing
+                .as_ref()
+                .map(|state| state.id.clone())
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            game_id: manifest.game_id.clone(),
+            slug: manifest.slug.clone(),
+            status: "completed".to_string(),
+            install_dir: install_path.clone(),
+            manifest_json: serde_json::to_string(manifest)?,
+            updated_at: chrono::Utc::now().timestamp(),
+            engine: existing.as_ref().and_then(|state| state.engine.clone()),
+            method: existing.and_then(|state| state.method),
+        })?;
+
+        let game = self
+            .db
+            .get_games()?
+            .into_iter()
+            .find(|game| game.id == manifest.game_id);
+        self.db.upsert_game(&match game {
+            Some(game) => LocalGame {
+                install_path: Some(install_path),
+                installed_version: Some(manifest.version.clone()),
+                ..game
+            },
+            None => LocalGame {
+                id: manifest.game_id.clone(),
+                slug: manifest.slug.clone(),
+                title: manifest.slug.clone(),
+                header_image: None,
+                install_path: Some(install_path),
+                installed_version: Some(manifest.version.clone()),
+                last_played: None,
+                playtime_seconds: 0,
+            },
+        })?;
+        Ok(true)
+    }
+
+    /// Primes a targeted repair of `paths` inside `install_dir` against the
+    /// installed build's manifest. The next `start_download` for that dir
+    /// deletes the damaged files, registers every other manifest file as
+    /// verified, fetches only the repaired files' chunks and re-verifies them
+    /// afterwards.
+    pub async fn prepare_repair(
+        &self,
+        slug: &str,
+        install_dir: &Path,
+        paths: &[String],
+    ) -> Result<RepairTargets> {
+        let (manifest_path, manifest) = self.installed_manifest(slug, install_dir).await?;
+        self.queue_repair(manifest_path, manifest, slug, install_dir, paths)
+    }
+
+    /// Scans the installed `slug` against its installed build's manifest and
+    /// primes a repair of every missing or corrupt file, so the following
+    /// `start_download` into the same dir fetches only those files' chunks.
+    pub async fn plan_game_repair(&self, slug: &str) -> Result<GameRepairPlan> {
+        let install_dir = self.installed_dir(slug)?;
+        if !install_dir.is_dir() {
+            return Err(LauncherError::NotFound(format!(
+                "install dir {}",
+                install_dir.display()
+            )));
+        }
+        let (manifest_path, manifest) = self.installed_manifest(slug, &install_dir).await?;
+        // Files the game or the user change on purpose aren't repaired. The
+        // download that follows still verifies everything it fetches.
+        let mut scanned = manifest.clone();
+        mark_volatile_files(&self.db, &mut scanned);
+        let scan = self
+            .scan_install(
+                None,
+                &manifest.game_id,
+                &install_dir,
+                &scanned.files,
+                IntegrityScanMode::PostDownload,
+                HashSet::new(),
+            )
+            .await?;
+        tracing::info!(
+            "repair scan slug={} total={} missing={} corrupt={} error={} elapsed_ms={}",
+            slug,
+            scan.total_files,
+            scan.missing_files,
+            scan.corrupt_files,
+            scan.error_files,
+            scan.elapsed_ms
+        );
+        let game_id = manifest.game_id.clone();
+        let targets = if scan.failed_paths.is_empty() {
+            RepairTargets::default()
+        } else {
+            self.queue_repair(
+                manifest_path,
+                manifest,
+                slug,
+                &install_dir,
+                &scan.failed_paths,
+            )?
+        };
+        Ok(GameRepairPlan {
+            game_id,
+            slug: slug.to_string(),
+            install_dir: install_dir.to_string_lossy().to_string(),
+            scanned_files: scan.total_files,
+            targets,
+        })
+    }
+
+    /// Deletes the installed files of `slug` and forgets its download state.
+    /// Only completed installs are removed, and never a library root or a
+    /// folder holding one. The shared depotcache is left alone even when it
+    /// sits inside the install dir. Returns the removed dir.
+    pub async fn uninstall(&self, slug: &str) -> Result<PathBuf> {
+        validate_slug(slug)?;
+        let state = self
+            .db
+            .get_download_state_by_slug(slug)?
+            .filter(|state| state.status == "completed" && !state.install_dir.trim().is_empty())
+            .ok_or_else(|| LauncherError::NotFound(format!("completed install of {}", slug)))?;
+        let active = self
+            .registry
+            .lock()
+            .map(|guard| guard.contains_key(&state.id))
+            .unwrap_or(false);
+        if active {
+            return Err(LauncherError::Config(format!(
+                "{} is still downloading; cancel it before uninstalling",
+                slug
+            )));
+        }
+        let install_dir = PathBuf::from(&state.install_dir);
+        let mut protected = vec![
+            self.file_manager.install_dir(),
+            self.file_manager.app_data_dir().to_path_buf(),
+        ];
+        protected.extend(self.file_manager.library_roots());
+        protected.extend(self.file_manager.game_install_roots().into_values());
+        ensure_removable(&install_dir, &protected)?;
+        if !install_dir.is_dir() {
+            return Err(LauncherError::NotFound(format!(
+                "install dir {}",
+                install_dir.display()
+            )));
+        }
+        let depot_root = self.depot_cache.root.clone();
+        if depot_root.starts_with(&install_dir) && depot_root != install_dir {
+            remove_dir_except(&install_dir, &depot_root).await?;
+        } else if install_dir.starts_with(&depot_root) {
+            return Err(LauncherError::Config(format!(
+                "refusing to remove {} inside the depotcache",
+                install_dir.display()
+            )));
+        } else {
+            tokio::fs::remove_dir_all(&install_dir).await?;
+        }
+
+        self.db.clear_download_chunks(&state.id)?;
+        self.db.clear_download_state(&state.id)?;
+        if let Ok(mut imported) = self.imported_files.lock() {
+            imported.remove(&install_dir);
+        }
+        tracing::info!("uninstalled slug={} dir={}", slug, install_dir.display());
+        Ok(install_dir)
+    }
+
+    /// Where `slug` is installed: the dir of its last download, otherwise the
+    /// default game folder.
+    fn installed_dir(&self, slug: &str) -> Result<PathBuf> {
+        Ok(match self.db.get_download_state_by_slug(slug)? {
+            Some(state) if !state.install_dir.trim().is_empty() => PathBuf::from(state.install_dir),
+            _ => self.file_manager.get_game_dir(slug),
+        })
+    }
+
+    /// The manifest of the build installed in `install_dir`, fetched fresh
+    /// for its chunk urls, with the request path that pins it. A repair never
+    /// mixes in files from a newer build.
+    async fn installed_manifest(
+        &self,
+        slug: &str,
+        install_dir: &Path,
+    ) -> Result<(String, Manifest)> {
+        let installed = load_previous_manifest(install_dir).map_err(|err| {
+            LauncherError::NotFound(format!(
+                "installed manifest in {}: {}",
+                install_dir.display(),
+                err
+            ))
+        })?;
+        let method = self
+            .db
+            .get_download_state_by_slug(slug)?
+            .and_then(|state| state.method);
+        let manifest_path = pinned_manifest_request_path(
+            slug,
+            method.as_deref(),
+            &installed.version,
+            &installed.build_id,
+        );
+        let manifest: Manifest = self.api.get_auth_first(&manifest_path).await?;
+        if manifest.version != installed.version || manifest.build_id != installed.build_id {
+            return Err(LauncherError::Config(format!(
+                "build {} of {} is no longer published; update the game instead of repairing it",
+                installed.version, slug
+            )));
+        }
+        Ok((manifest_path, manifest))
+    }
+
+    /// Resolves `paths` against `manifest` and primes the repair for the next
+    /// download into `install_dir`. Nothing on disk changes yet.
+    fn queue_repair(
+        &self,
+        manifest_path: String,
+        manifest: Manifest,
+        slug: &str,
+        install_dir: &Path,
+        paths: &[String],
+    ) -> Result<RepairTargets> {
+        if is_archive_mode(&manifest) {
+            return Err(LauncherError::Config(format!(
+                "{} is distributed as archives and can't be repaired file by file",
+                slug
+            )));
+        }
+        let (targets, stale, verified) = plan_repair_files(&manifest, install_dir, paths);
+        if targets.files == 0 {
+            return Err(LauncherError::NotFound(format!(
+                "none of the repair paths are in the {} manifest",
+                slug
+            )));
+        }
+
+        tracing::info!(
+            "repair queued slug={} dir={} files={} chunks={} bytes={} unknown={}",
+            slug,
+            install_dir.display(),
+            targets.files,
+            targets.chunks,
+            targets.bytes,
+            targets.unknown_paths.len()
+        );
+        self.pending_repairs
+            .lock()
+            .map_err(|_| LauncherError::Config("repair registry locked".to_string()))?
+            .insert(
+                install_dir.to_path_buf(),
+                PendingRepair {
+                    manifest_path,
+                    manifest,
+                    stale,
+                    verified,
+                },
+            );
+        Ok(targets)
+    }
+
+    /// Drops the repair primed for `install_dir`, for when its download
+    /// wasn't accepted.
+    pub fn discard_repair(&self, install_dir: &Path) {
+        if let Ok(mut repairs) = self.pending_repairs.lock() {
+            repairs.remove(install_dir);
+        }
+    }
+
+    fn take_pending_repair(&self, install_dir: &Path, game_id: &str) -> Option<PendingRepair> {
+        let mut repairs = self.pending_repairs.lock().ok()?;
+        if repairs.get(install_dir)?.manifest.game_id != game_id {
+            return None;
+        }
+        repairs.remove(install_dir)
+    }
+
+    /// Runs an integrity scan and records every file that passed a full
+    /// SHA-256 check in `file_index_v2`, so the next preflight over this
+    /// install can confirm it with blake3 instead. A `cancel_verify` for
+    /// `download_id` only affects the scan already underway; scans outside a
+    /// download pass `None` and run to the end.
+    async fn scan_install(
+        &self,
+        download_id: Option<&str>,
+        game_id: &str,
+        install_dir: &Path,
+        files: &[ManifestFile],
+        mode: IntegrityScanMode,
+        prehashed: HashSet<String>,
+    ) -> Result<IntegrityScanSummary> {
+        let install_path = install_dir.to_string_lossy().to_string();
+        let baseline = if mode == IntegrityScanMode::Preflight {
+            self.db
+                .list_verified_file_index(game_id, &install_path)
+                .unwrap_or_else(|err| {
+                    tracing::warn!("file index unavailable for {}: {}", install_path, err);
+                    Vec::new()
+                })
+                .into_iter()
+                .map(|entry| (normalize_index_path(&entry.relative_path), entry))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let cancel = download_id.and_then(|download_id| {
+            let guard = self.registry.lock().ok()?;
+            guard.get(download_id).map(|handle| {
+                handle.verify_cancel.send_replace(false);
+                handle.verify_cancel.subscribe()
+            })
+        });
+        let summary = scan_manifest_integrity(
+            install_dir,
+            files,
+            mode,
+            prehashed,
+            baseline,
+            self.download_tuning().integrity_workers,
+            cancel,
+        )
+        .await?;
+        if !summary.indexed.is_empty() {
+            if let Err(err) =
+                self.db
+                    .upsert_verified_file_index(game_id, &install_path, &summary.indexed)
+            {
+                tracing::warn!("failed to update file index for {}: {}", install_path, err);
+            }
+        }
+        Ok(summary)
+    }
+
+    /// File ids imported into `install_dir` that are still in place.
+    fn imported_files_in(&self, install_dir: &Path, manifest: &Manifest) -> HashSet<String> {
+        let Some(imported) = self
+            .imported_files
+            .lock()
+            .ok()
+            .and_then(|guard| guard.get(install_dir).cloned())
+        else {
+            return HashSet::new();
+        };
+        manifest
+            .files
+            .iter()
+            .filter(|file| imported.contains(&file.file_id))
+            .filter(|file| {
+                scan_manifest_file(
+                    install_dir,
+                    file,
+                    IntegrityScanMode::Preflight,
+                    0,
+                    true,
+                    None,
+                )
+                .status
+                    == IntegrityFileStatus::Ok
+            })
+            .map(|file| file.file_id.clone())
+            .collect()
+    }
+
+    async fn run_download(
+        &self,
         download_id: &str,
         game_id: &str,
         slug: &str,
         requested_method: Option<&str>,
         install_dir_override: Option<&str>,
         control_rx: watch::Receiver<DownloadControl>,
+        refreshed_urls: RefreshedUrls,
     ) -> Result<()> {
         let method_key = requested_method_text(requested_method);
-        let manifest_path = format!("manifests/{}?method={}", slug, method_key);
-        let manifest: Manifest = self.api.get_auth_first(&manifest_path).await?;
         let normalized_override = install_dir_override
             .map(str::trim)
             .filter(|value| !value.is_empty())
@@ -1138,12 +3491,38 @@ impl DownloadManager {
             if !stored.is_empty() {
                 PathBuf::from(stored)
             } else {
-                self.file_manager.get_game_dir(slug)
+                self.claim_game_dir(slug)
             }
         } else {
-            self.file_manager.get_game_dir(slug)
+            self.claim_game_dir(slug)
+        };
+        // A primed repair pins the installed build's manifest and only now,
+        // with the download under way, drops the damaged files.
+        let manifest = match self.take_pending_repair(&install_dir, game_id) {
+            Some(repair) => {
+                for stale in &repair.stale {
+                    match tokio::fs::remove_file(stale).await {
+                        Ok(()) => {}
+                        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                self.imported_files
+                    .lock()
+                    .map_err(|_| LauncherError::Config("import registry locked".to_string()))?
+                    .insert(install_dir.clone(), repair.verified);
+                repair.manifest
+            }
+            None => {
+                let manifest_path = manifest_request_path(slug, requested_method);
+                self.api.get_auth_first(&manifest_path).await?
+            }
         };
         let manifest_json = serde_json::to_string(&manifest)?;
+        let checkpoint = self
+            .db
+            .get_preflight_checkpoint(download_id)?
+            .and_then(|raw| serde_json::from_str::<PreflightCheckpoint>(&raw).ok());
 
         let state = DownloadState {
             id: download_id.to_string(),
@@ -1153,6 +3532,8 @@ impl DownloadManager {
             install_dir: install_dir.to_string_lossy().to_string(),
             manifest_json: manifest_json.clone(),
             updated_at: chrono::Utc::now().timestamp(),
+            engine: None,
+            method: Some(method_key.clone()),
         };
         self.db.save_download_state(&state)?;
 
@@ -1163,17 +3544,22 @@ impl DownloadManager {
             .map(|chunk| ((chunk.file_id, chunk.chunk_index), chunk.hash))
             .collect();
 
+        let verified_files = self.imported_files_in(&install_dir, &manifest);
         let mut plan = build_download_plan(
             &manifest,
             &install_dir,
             &completed_map,
             old_manifest.as_ref(),
+            &verified_files,
         )?;
+        if !env_truthy("LAUNCHER_DISABLE_MIRROR_RANKING") {
+            apply_mirror_ranking(&mut plan, &self.mirror_ranker).await;
+        }
         if method_allows_peer_assist(&method_key) {
-            if let Some(coordination) = self.peer_coordinator.as_ref() {
+            if let Some(coordination) = self.peer_coordinator() {
                 let peers = coordination.peers_for_game(game_id).await;
                 if !peers.is_empty() {
-                    apply_peer_sources(&mut plan, &peers);
+                    apply_peer_sources(&mut plan, &peers, self.peer_source_config());
                     tracing::info!(
                         "p2p peer assist enabled slug={} peers={} chunks={} method={}",
                         slug,
@@ -1191,11 +3577,53 @@ impl DownloadManager {
             );
         }
 
-        let preflight_scan =
-            scan_manifest_integrity(&install_dir, &manifest.files, IntegrityScanMode::Preflight)
-                .await?;
+        let fingerprint = {
+            let dir = install_dir.clone();
+            let identity = format!("{}+{}", manifest.version, manifest.build_id);
+            let files = manifest.files.clone();
+            tokio::task::spawn_blocking(move || install_fingerprint(&dir, &identity, &files))
+                .await
+                .ok()
+        };
+        let reusable = checkpoint.filter(|checkpoint| {
+            !checkpoint.summary.incomplete && Some(&checkpoint.fingerprint) == fingerprint.as_ref()
+        });
+        let preflight_scan = match reusable {
+            Some(checkpoint) => {
+                tracing::info!(
+                    "preflight scan skipped slug={}, files unchanged since the last scan",
+                    slug
+                );
+                checkpoint.summary
+            }
+            None => {
+                let scan = self
+                    .scan_install(
+                        Some(download_id),
+                        game_id,
+                        &install_dir,
+                        &manifest.files,
+                        IntegrityScanMode::Preflight,
+                        HashSet::new(),
+                    )
+                    .await?;
+                if let (Some(fingerprint), false) = (fingerprint, scan.incomplete) {
+                    let checkpoint = PreflightCheckpoint {
+                        fingerprint,
+                        summary: scan.clone(),
+                    };
+                    if let Err(err) = self
+                        .db
+                        .set_preflight_checkpoint(download_id, &serde_json::to_string(&checkpoint)?)
+                    {
+                        tracing::warn!("failed to store preflight checkpoint: {}", err);
+                    }
+                }
+                scan
+            }
+        };
         tracing::info!(
-            "preflight scan slug={} total={} ok={} missing={} corrupt={} error={} hashed={} elapsed_ms={}",
+            "preflight scan slug={} total={} ok={} missing={} corrupt={} error={} hashed={} elapsed_ms={} incomplete={}",
             slug,
             preflight_scan.total_files,
             preflight_scan.verified_files,
@@ -1203,7 +3631,8 @@ impl DownloadManager {
             preflight_scan.corrupt_files,
             preflight_scan.error_files,
             preflight_scan.hashed_files,
-            preflight_scan.elapsed_ms
+            preflight_scan.elapsed_ms,
+            preflight_scan.incomplete
         );
 
         for chunk in &plan.precompleted_chunks {
@@ -1213,19 +3642,21 @@ impl DownloadManager {
         }
 
         let cache_write_bytes = estimate_cache_write_bytes(&self.depot_cache, &plan.chunks);
+        let storage_options = self.storage_options();
         let storage = evaluate_storage_budget(
             &install_dir,
             &manifest,
             &plan,
             old_manifest.as_ref(),
             cache_write_bytes,
+            &storage_options,
         )?;
         let available_after_cleanup = storage
             .available_bytes
             .saturating_add(storage.reclaimable_bytes);
         if available_after_cleanup < storage.required_bytes {
             self.db.update_download_status(download_id, "failed")?;
-            return Err(LauncherError::Config(format!(
+            return Err(LauncherError::Storage(format!(
                 "Insufficient disk space at {}. Need at least {} free (pre-allocate {} + extraction {} + depotcache {} + safety {}), available {} ({} free + {} reclaimable).",
                 install_dir.display(),
                 format_bytes(storage.required_bytes),
@@ -1240,9 +3671,30 @@ impl DownloadManager {
         }
 
         delete_files(&plan.delete_files).await;
-        prepare_files(&plan.files_to_finalize).await?;
-        let hydrated_bytes =
-            hydrate_from_depot_cache(&mut plan, &self.depot_cache, &self.db, download_id).await?;
+        prepare_files(&plan.files_to_finalize, &storage_options).await?;
+        let mut summary = DownloadCompletedSummary {
+            download_id: download_id.to_string(),
+            game_id: game_id.to_string(),
+            slug: slug.to_string(),
+            total_bytes: plan.total_bytes,
+            resumed_bytes: plan.preexisting_bytes,
+            ..Default::default()
+        };
+        let chunks_before_hydration = plan.chunks.len();
+        let file_hashes = IncrementalFileHashes::default();
+        let disk_io = DiskIoCounters::default();
+        let hydrated_bytes = hydrate_from_depot_cache(
+            &mut plan,
+            &self.depot_cache,
+            &self.db,
+            download_id,
+            &file_hashes,
+            &disk_io,
+        )
+        .await?;
+        summary.depotcache_bytes = hydrated_bytes;
+        summary.depotcache_chunks =
+            chunks_before_hydration.saturating_sub(plan.chunks.len()) as u64;
         if hydrated_bytes > 0 {
             tracing::info!(
                 "reused {} from depotcache for slug={}",
@@ -1252,10 +3704,29 @@ impl DownloadManager {
         }
 
         let tracker = ProgressTracker::new(plan.total_bytes, plan.preexisting_bytes);
-        let mut reporter = ProgressReporter::new(plan.preexisting_bytes);
+        let received = ReceivedBytes::default();
+        let mut reporter = ProgressReporter::new(
+            plan.preexisting_bytes,
+            received.clone(),
+            self.data_cap.clone(),
+            disk_io.clone(),
+        );
+        reporter.pending_usage.depotcache_bytes = hydrated_bytes;
+        reporter.disk_watch = Some(DiskSpaceWatch {
+            manager: self.clone(),
+            install_dir: install_dir.clone(),
+            safety_bytes: storage.safety_bytes,
+            reserved: storage_options.preallocate
+                && !storage_options.sparse
+                && cfg!(target_os = "windows"),
+            auto_resume: storage_options.auto_resume_low_disk,
+            last_checked: Instant::now(),
+        });
         let requested_method_text = method_key;
-        let effective_concurrency =
-            resolve_method_concurrency(&requested_method_text, self.max_concurrent_chunks);
+        let effective_concurrency = resolve_method_concurrency(
+            &requested_method_text,
+            self.download_tuning().max_concurrent_chunks,
+        );
         let mut engine = resolve_download_engine(requested_method);
         let mut aria2_config = None;
         if engine == DownloadEngine::Aria2c {
@@ -1271,17 +3742,32 @@ impl DownloadManager {
                 }
             }
         }
+        let mut aria2_rpc = None;
+        if let Some(config) = aria2_config.as_ref().filter(|_| aria2_rpc_enabled()) {
+            let args = aria2_rpc_global_args(config, effective_concurrency);
+            match Aria2RpcDaemon::start(&config.binary, &args).await {
+                Ok(daemon) => aria2_rpc = Some(Arc::new(daemon)),
+                Err(err) => tracing::warn!(
+                    "aria2c rpc unavailable, spawning per chunk instead: {}",
+                    err
+                ),
+            }
+        }
+        let engine_label = if aria2_rpc.is_some() {
+            "aria2c-rpc"
+        } else if engine == DownloadEngine::Aria2c {
+            "aria2c"
+        } else {
+            "reqwest"
+        };
         tracing::info!(
             "download engine={} slug={} method={} concurrency={}",
-            if engine == DownloadEngine::Aria2c {
-                "aria2c"
-            } else {
-                "reqwest"
-            },
+            engine_label,
             slug,
             requested_method_text,
             effective_concurrency
         );
+        self.db.set_download_engine(download_id, engine_label)?;
 
         let (tx, mut rx) = mpsc::channel::<ChunkResult>(256);
         let semaphore = Arc::new(Semaphore::new(effective_concurrency));
@@ -1289,62 +3775,92 @@ impl DownloadManager {
             &requested_method_text,
             semaphore.clone(),
             effective_concurrency,
+            self.host_limits.clone(),
         );
         let session_peer_blacklist = Arc::new(Mutex::new(HashSet::<String>::new()));
-
-        for job in plan.chunks {
+        let write_strategy = self.write_strategy_info(&install_dir).strategy;
+        tracing::info!(
+            "chunk write strategy={} slug={}",
+            write_strategy.as_str(),
+            slug
+        );
+        let writer = Arc::new(ChunkWriter::new(
+            write_strategy,
+            &plan.chunks,
+            write_merge_buffer_bytes(),
+            file_hashes.clone(),
+            disk_io.clone(),
+        ));
+
+        for mut job in plan.chunks {
             let tx = tx.clone();
-            let client = self.client.clone();
+            let refreshed_urls = refreshed_urls.clone();
+            let client = self.chunk_clients.clone();
             let mut control = control_rx.clone();
             let semaphore = semaphore.clone();
             let throttle = self.throttle.clone();
             let aria2_config = aria2_config.clone();
+            let aria2_rpc = aria2_rpc.clone();
             let depot_cache = self.depot_cache.clone();
             let peer_blacklist = session_peer_blacklist.clone();
+            let peer_transfers = self.peer_transfers.clone();
+            let host_limits = self.host_limits.clone();
+            let writer = writer.clone();
+            let disk_io = disk_io.clone();
+            let received = received.clone();
 
             tokio::spawn(async move {
                 let _permit = semaphore.acquire().await.ok();
                 if let Err(err) = wait_for_running(&mut control).await {
+                    abandon_chunk(&writer, &job, &tx).await;
                     let _ = tx.send(ChunkResult::Error { error: err }).await;
                     return;
                 }
+                if let Ok(refreshed) = refreshed_urls.lock() {
+                    apply_refreshed_urls(&mut job, &refreshed);
+                }
 
                 match download_chunk(
                     &client,
                     &job,
                     engine,
                     aria2_config.as_ref(),
+                    aria2_rpc.as_deref(),
                     &tx,
                     &mut control,
                     &peer_blacklist,
+                    &peer_transfers,
+                    &host_limits,
+                    &received,
                 )
                 .await
                 {
                     Ok(payload) => {
                         let data = payload.data;
                         throttle.acquire(data.len() as u64).await;
-                        if let Err(err) = write_chunk(&job, &data).await {
-                            let _ = tx.send(ChunkResult::Error { error: err }).await;
-                            return;
-                        }
-                        if let Err(err) = depot_cache.store_chunk(&job.hash, &data) {
-                            tracing::warn!(
+                        match depot_cache.store_chunk(&job.hash, &data) {
+                            Ok(true) => disk_io.add_written(data.len() as u64),
+                            Ok(false) => {}
+                            Err(err) => tracing::warn!(
                                 "failed to store depotcache chunk {}: {}",
                                 job.hash,
                                 err
-                            );
+                            ),
+                        }
+                        let success = ChunkResult::Success {
+                            file_id: job.file_id.clone(),
+                            chunk_index: job.index,
+                            size: job.size,
+                            hash: job.hash.clone(),
+                            accounted_bytes: payload.accounted_bytes,
+                            source: payload.source,
+                        };
+                        if let Err(err) = writer.submit(&job, data, success, &tx).await {
+                            let _ = tx.send(ChunkResult::Error { error: err }).await;
                         }
-                        let _ = tx
-                            .send(ChunkResult::Success {
-                                file_id: job.file_id.clone(),
-                                chunk_index: job.index,
-                                size: job.size,
-                                hash: job.hash.clone(),
-                                accounted_bytes: payload.accounted_bytes,
-                            })
-                            .await;
                     }
                     Err(err) => {
+                        abandon_chunk(&writer, &job, &tx).await;
                         let _ = tx.send(ChunkResult::Error { error: err }).await;
                     }
                 }
@@ -1353,88 +3869,130 @@ impl DownloadManager {
 
         drop(tx);
 
-        while let Some(result) = rx.recv().await {
-            match result {
-                ChunkResult::Progress { bytes } => {
-                    if bytes == 0 {
+        // Usage is flushed however the loop ends, and on pause, so bytes
+        // already received count even if the download never completes.
+        let mut pause_watch = control_rx.clone();
+        let collected: Result<()> = async {
+            loop {
+                let result = tokio::select! {
+                    result = rx.recv() => result,
+                    Ok(()) = pause_watch.changed() => {
+                        if *pause_watch.borrow() == DownloadControl::Paused {
+                            reporter.flush_usage(&self.db, game_id);
+                        }
                         continue;
                     }
-                    governor.maybe_relax().await;
-                    tracker.add_bytes(bytes).await;
-                    let (progress, speed, eta, downloaded, total) = tracker.snapshot().await;
-                    reporter
-                        .maybe_report(
-                            &self.db,
-                            &self.downloads_api,
-                            download_id,
-                            game_id,
-                            progress,
-                            speed,
-                            eta,
-                            downloaded,
-                            total,
-                        )
-                        .await?;
-                }
-                ChunkResult::Success {
-                    file_id,
-                    chunk_index,
-                    size,
-                    hash,
-                    accounted_bytes,
-                } => {
-                    governor.maybe_relax().await;
-                    let remaining = size.saturating_sub(accounted_bytes);
-                    if remaining > 0 {
-                        tracker.add_bytes(remaining).await;
+                };
+                let Some(result) = result else {
+                    break;
+                };
+                match result {
+                    ChunkResult::Progress { bytes } => {
+                        if bytes == 0 {
+                            continue;
+                        }
+                        governor.maybe_relax().await;
+                        tracker.add_bytes(bytes).await;
+                        let (progress, speed, eta, downloaded, total) = tracker.snapshot().await;
+                        reporter
+                            .maybe_report(
+                                &self.db,
+                                &self.downloads_api,
+                                download_id,
+                                game_id,
+                                progress,
+                                speed,
+                                eta,
+                                downloaded,
+                                total,
+                            )
+                            .await?;
                     }
-                    self.db.upsert_download_chunk(&DownloadChunk {
-                        download_id: download_id.to_string(),
+                    ChunkResult::Success {
                         file_id,
-                        chunk_index: chunk_index as i32,
+                        chunk_index,
+                        size,
                         hash,
-                        size: size as i64,
-                        status: "completed".to_string(),
-                        updated_at: chrono::Utc::now().timestamp(),
-                    })?;
-
-                    let (progress, speed, eta, downloaded, total) = tracker.snapshot().await;
-                    reporter
-                        .maybe_report(
-                            &self.db,
-                            &self.downloads_api,
-                            download_id,
-                            game_id,
-                            progress,
-                            speed,
-                            eta,
-                            downloaded,
-                            total,
-                        )
-                        .await?;
-                }
-                ChunkResult::NetworkPressure { source, reason } => {
-                    governor.on_network_pressure(source, reason).await;
-                }
-                ChunkResult::Error { error } => {
-                    self.db.update_download_status(download_id, "failed")?;
-                    return Err(error);
+                        accounted_bytes,
+                        source,
+                    } => {
+                        governor.maybe_relax().await;
+                        summary.record_chunk(source, size);
+                        let remaining = size.saturating_sub(accounted_bytes);
+                        if remaining > 0 {
+                            tracker.add_bytes(remaining).await;
+                        }
+                        self.db.upsert_download_chunk(&DownloadChunk {
+                            download_id: download_id.to_string(),
+                            file_id,
+                            chunk_index: chunk_index as i32,
+                            hash,
+                            size: size as i64,
+                            status: "completed".to_string(),
+                            updated_at: chrono::Utc::now().timestamp(),
+                        })?;
+
+                        let (progress, speed, eta, downloaded, total) = tracker.snapshot().await;
+                        reporter
+                            .maybe_report(
+                                &self.db,
+                                &self.downloads_api,
+                                download_id,
+                                game_id,
+                                progress,
+                                speed,
+                                eta,
+                                downloaded,
+                                total,
+                            )
+                            .await?;
+                    }
+                    ChunkResult::NetworkPressure {
+                        source,
+                        reason,
+                        host,
+                    } => {
+                        governor
+                            .on_network_pressure(source, reason, host.as_deref())
+                            .await;
+                    }
+                    ChunkResult::Error { error } => {
+                        self.db.update_download_status(download_id, "failed")?;
+                        return Err(error);
+                    }
                 }
             }
+            Ok(())
         }
-
+        .await;
+        reporter.flush_usage(&self.db, game_id);
+        collected?;
         finalize_files(&plan.files_to_finalize).await?;
         self.db.update_download_status(download_id, "verifying")?;
         let _ = self
             .downloads_api
             .update_status(download_id, "verifying")
             .await;
-        let post_scan = scan_manifest_integrity(
-            &install_dir,
-            &manifest.files,
-            IntegrityScanMode::PostDownload,
-        )
-        .await?;
+        let mut prehashed = file_hashes.verified_files(&manifest.files);
+        prehashed.extend(verified_files.iter().cloned());
+        if !prehashed.is_empty() {
+            tracing::info!(
+                "incremental hashes verified {} of {} files for slug={}",
+                prehashed.len(),
+                manifest.files.len(),
+                slug
+            );
+        }
+        let post_scan = self
+            .scan_install(
+                Some(download_id),
+                game_id,
+                &install_dir,
+                &manifest.files,
+                IntegrityScanMode::PostDownload,
+                prehashed,
+            )
+            .await?;
         tracing::info!(
             "post-download scan slug={} total={} ok={} missing={} corrupt={} error={} hashed={} elapsed_ms={}",
             slug,
@@ -1446,6 +4004,28 @@ impl DownloadManager {
             post_scan.hashed_files,
             post_scan.elapsed_ms
         );
+        if post_scan.incomplete {
+            tracing::info!(
+                "post-download scan cancelled for slug={} after {} of {} files",
+                slug,
+                post_scan.total_files,
+                manifest.files.len()
+            );
+            self.db.update_download_status(download_id, "paused")?;
+            let _ = self
+                .downloads_api
+                .update_status(download_id, "paused")
+                .await;
+            let _ = self.app_handle.emit(
+                "verify-cancelled",
+                VerifyCancelledPayload {
+                    download_id: download_id.to_string(),
+                    slug: slug.to_string(),
+                    summary: post_scan,
+                },
+            );
+            return Ok(());
+        }
         if post_scan.missing_files > 0 || post_scan.corrupt_files > 0 || post_scan.error_files > 0 {
             let details = if post_scan.first_failures.is_empty() {
                 "no file details".to_string()
@@ -1453,16 +4033,41 @@ impl DownloadManager {
                 post_scan.first_failures.join(", ")
             };
             self.db.update_download_status(download_id, "failed")?;
-            return Err(LauncherError::Config(format!(
+            return Err(LauncherError::Integrity(format!(
                 "post-download verification failed (missing={}, corrupt={}, error={}): {}",
                 post_scan.missing_files, post_scan.corrupt_files, post_scan.error_files, details
             )));
         }
         if is_archive_mode(&manifest) {
-            extract_archives(&install_dir, &manifest, old_manifest.as_ref()).await?;
+            let app_handle = self.app_handle.clone();
+            let download_id_owned = download_id.to_string();
+            let slug_owned = slug.to_string();
+            extract_archives(
+                &install_dir,
+                &manifest,
+                old_manifest.as_ref(),
+                self.extract_workers(),
+                move |archive, completed, total| {
+                    let _ = app_handle.emit(
+                        "extract-progress",
+                        ExtractProgressPayload {
+                            download_id: download_id_owned.clone(),
+                            slug: slug_owned.clone(),
+                            archive: archive.to_string(),
+                            completed,
+                            total,
+                        },
+                    );
+                },
+            )
+            .await?;
         }
         write_manifest(&install_dir, &manifest_json).await?;
+        self.schedule_post_install(download_id, slug, &install_dir, &manifest);
         self.db.update_download_status(download_id, "completed")?;
+        if let Ok(mut imported) = self.imported_files.lock() {
+            imported.remove(&install_dir);
+        }
         self.db.upsert_download(&LocalDownload {
             id: download_id.to_string(),
             game_id: game_id.to_string(),
@@ -1475,8 +4080,8 @@ impl DownloadManager {
             network_bps: 0,
             disk_read_bps: 0,
             disk_write_bps: 0,
-            read_bytes: manifest.total_size as i64,
-            written_bytes: manifest.total_size as i64,
+            read_bytes: disk_io.read_bytes() as i64,
+            written_bytes: disk_io.written_bytes() as i64,
             remaining_bytes: 0,
             speed_history: Vec::new(),
             updated_at: chrono::Utc::now().timestamp(),
@@ -1493,8 +4098,8 @@ impl DownloadManager {
                     network_bps: Some(0),
                     disk_read_bps: Some(0),
                     disk_write_bps: Some(0),
-                    read_bytes: Some(manifest.total_size as i64),
-                    written_bytes: Some(manifest.total_size as i64),
+                    read_bytes: Some(disk_io.read_bytes() as i64),
+                    written_bytes: Some(disk_io.written_bytes() as i64),
                     remaining_bytes: Some(0),
                     speed_mbps: Some(0.0),
                     eta_minutes: Some(0),
@@ -1502,6 +4107,16 @@ impl DownloadManager {
             )
             .await;
 
+        tracing::info!(
+            "download summary slug={} cdn={} peer={} depotcache={} resumed={}",
+            slug,
+            format_bytes(summary.cdn_bytes),
+            format_bytes(summary.peer_bytes),
+            format_bytes(summary.depotcache_bytes),
+            format_bytes(summary.resumed_bytes)
+        );
+        let _ = self.app_handle.emit("download-completed-summary", summary);
+
         Ok(())
     }
 }
@@ -1511,15 +4126,86 @@ struct ProgressReporter {
     last_progress: i32,
     last_downloaded: u64,
     speed_history: Vec<f64>,
+    /// Bytes not yet added to `bandwidth_usage`, written with each report.
+    pending_usage: BandwidthUsageTotals,
+    received: ReceivedBytes,
+    data_cap: DataCapMonitor,
+    disk_io: DiskIoCounters,
+    last_read: u64,
+    last_written: u64,
+    disk_watch: Option<DiskSpaceWatch>,
+}
+
+/// Re-checks free space on the install drive while chunks download, so a
+/// drive filled by something else pauses the download instead of failing a
+/// write mid-chunk.
+struct DiskSpaceWatch {
+    manager: DownloadManager,
+    install_dir: PathBuf,
+    safety_bytes: u64,
+    /// Preallocated `.part` files already hold the bytes still to come.
+    reserved: bool,
+    auto_resume: bool,
+    last_checked: Instant,
+}
+
+impl DiskSpaceWatch {
+    fn check(&mut self, download_id: &str, remaining_bytes: u64) {
+        if self.last_checked.elapsed() < LOW_DISK_CHECK_INTERVAL {
+            return;
+        }
+        self.last_checked = Instant::now();
+        let Some(available_bytes) = available_disk_space(&self.install_dir) else {
+            return;
+        };
+        let still_needed = if self.reserved { 0 } else { remaining_bytes };
+        let required_bytes = still_needed.saturating_add(self.safety_bytes);
+        if available_bytes >= required_bytes {
+            return;
+        }
+        self.manager.pause_for_low_disk(LowDiskPayload {
+            download_id: download_id.to_string(),
+            install_dir: self.install_dir.to_string_lossy().to_string(),
+            available_bytes,
+            required_bytes,
+            auto_resume: self.auto_resume,
+        });
+    }
 }
 
 impl ProgressReporter {
-    fn new(initial_downloaded: u64) -> Self {
+    fn new(
+        initial_downloaded: u64,
+        received: ReceivedBytes,
+        data_cap: DataCapMonitor,
+        disk_io: DiskIoCounters,
+    ) -> Self {
         Self {
             last_sent: Instant::now() - Duration::from_secs(5),
             last_progress: -1,
             last_downloaded: initial_downloaded,
             speed_history: Vec::new(),
+            pending_usage: BandwidthUsageTotals::default(),
+            received,
+            data_cap,
+            disk_io,
+            // Hydration runs before the reporter exists; starting from zero
+            // lets the first report include it.
+            last_read: 0,
+            last_written: 0,
+            disk_watch: None,
+        }
+    }
+
+    fn flush_usage(&mut self, db: &Database, game_id: &str) {
+        self.received.drain_into(&mut self.pending_usage);
+        if self.pending_usage.is_empty() {
+            return;
+        }
+        let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+        match db.add_bandwidth_usage(game_id, &date, &self.pending_usage) {
+            Ok(()) => self.pending_usage = BandwidthUsageTotals::default(),
+            Err(err) => tracing::warn!("failed to record bandwidth usage: {}", err),
         }
     }
 
@@ -1561,6 +4247,11 @@ impl ProgressReporter {
             || now.duration_since(self.last_sent) > Duration::from_millis(500)
         {
             let remaining_bytes = total_bytes.saturating_sub(downloaded_bytes);
+            let read_bytes = self.disk_io.read_bytes();
+            let written_bytes = self.disk_io.written_bytes();
+            let disk_read_bps = (read_bytes.saturating_sub(self.last_read) as f64 / elapsed) as i64;
+            let disk_write_bps =
+                (written_bytes.saturating_sub(self.last_written) as f64 / elapsed) as i64;
             let entry = LocalDownload {
                 id: download_id.to_string(),
                 game_id: game_id.to_string(),
@@ -1575,10 +4266,10 @@ impl ProgressReporter {
                 downloaded_bytes: downloaded_bytes as i64,
                 total_bytes: total_bytes as i64,
                 network_bps: effective_speed_bps as i64,
-                disk_read_bps: 0,
-                disk_write_bps: effective_speed_bps as i64,
-                read_bytes: downloaded_bytes as i64,
-                written_bytes: downloaded_bytes as i64,
+                disk_read_bps,
+                disk_write_bps,
+                read_bytes: read_bytes as i64,
+                written_bytes: written_bytes as i64,
                 remaining_bytes: remaining_bytes as i64,
                 speed_history: self.speed_history.clone(),
                 updated_at: chrono::Utc::now().timestamp(),
@@ -1592,19 +4283,26 @@ impl ProgressReporter {
                         downloaded_bytes: Some(downloaded_bytes as i64),
                         total_bytes: Some(total_bytes as i64),
                         network_bps: Some(effective_speed_bps as i64),
-                        disk_read_bps: Some(0),
-                        disk_write_bps: Some(effective_speed_bps as i64),
-                        read_bytes: Some(downloaded_bytes as i64),
-                        written_bytes: Some(downloaded_bytes as i64),
+                        disk_read_bps: Some(disk_read_bps),
+                        disk_write_bps: Some(disk_write_bps),
+                        read_bytes: Some(read_bytes as i64),
+                        written_bytes: Some(written_bytes as i64),
                         remaining_bytes: Some(remaining_bytes as i64),
                         speed_mbps: Some((effective_speed_bps as f64) / (1024.0 * 1024.0)),
                         eta_minutes: Some((eta_seconds / 60) as i32),
                     },
                 )
                 .await;
+            self.flush_usage(db, game_id);
+            self.data_cap.check(db);
+            if let Some(disk_watch) = self.disk_watch.as_mut() {
+                disk_watch.check(download_id, remaining_bytes);
+            }
             self.last_progress = progress_int;
             self.last_sent = now;
             self.last_downloaded = downloaded_bytes;
+            self.last_read = read_bytes;
+            self.last_written = written_bytes;
         }
 
         Ok(())
@@ -1640,6 +4338,12 @@ fn normalize_download_method(requested_method: Option<&str>) -> String {
     }
 }
 
+/// Normalizes `value` and accepts it only if it names a known method.
+pub(crate) fn parse_download_method(value: &str) -> Option<String> {
+    let key = normalize_download_method(Some(value));
+    DOWNLOAD_METHODS.contains(&key.as_str()).then_some(key)
+}
+
 fn method_allows_peer_assist(method_key: &str) -> bool {
     !method_key.eq_ignore_ascii_case("cdn")
 }
@@ -1675,6 +4379,111 @@ fn requested_method_text(requested_method: Option<&str>) -> String {
     normalize_download_method(requested_method)
 }
 
+pub(crate) fn manifest_request_path(slug: &str, requested_method: Option<&str>) -> String {
+    format!(
+        "manifests/{}?method={}",
+        slug,
+        requested_method_text(requested_method)
+    )
+}
+
+/// Like `manifest_request_path`, but for one specific build instead of the
+/// latest.
+fn pinned_manifest_request_path(
+    slug: &str,
+    requested_method: Option<&str>,
+    version: &str,
+    build_id: &str,
+) -> String {
+    format!(
+        "{}&version={}&build_id={}",
+        manifest_request_path(slug, requested_method),
+        urlencoding::encode(version),
+        urlencoding::encode(build_id)
+    )
+}
+
+/// Splits `manifest` for a repair of `paths`: what will be refetched, the
+/// files to delete first (each repaired file and its partial download) and
+/// the ids of the files left as they are.
+fn plan_repair_files(
+    manifest: &Manifest,
+    install_dir: &Path,
+    paths: &[String],
+) -> (RepairTargets, Vec<PathBuf>, HashSet<String>) {
+    let wanted: HashSet<String> = paths
+        .iter()
+        .map(|path| normalize_manifest_path(path))
+        .collect();
+    let mut targets = RepairTargets::default();
+    let mut matched = HashSet::new();
+    let mut stale = Vec::new();
+    let mut verified = HashSet::new();
+    for file in &manifest.files {
+        let key = normalize_manifest_path(&file.path);
+        if !wanted.contains(&key) {
+            verified.insert(file.file_id.clone());
+            continue;
+        }
+        stale.push(install_dir.join(&file.path));
+        stale.push(partial_file_path(install_dir, file));
+        targets.files += 1;
+        targets.chunks += file.chunks.len();
+        targets.bytes += file.size;
+        matched.insert(key);
+    }
+    targets.unknown_paths = wanted.difference(&matched).cloned().collect();
+    targets.unknown_paths.sort();
+    (targets, stale, verified)
+}
+
+fn refreshed_chunk_urls(manifest: &Manifest) -> HashMap<(String, u64), RefreshedChunkUrls> {
+    manifest
+        .files
+        .iter()
+        .flat_map(|file| {
+            file.chunks.iter().map(|chunk| {
+                (
+                    (file.file_id.clone(), chunk.index),
+                    RefreshedChunkUrls {
+                        hash: chunk.hash.clone(),
+                        url: chunk.url.clone(),
+                        fallback_urls: chunk.fallback_urls.clone(),
+                    },
+                )
+            })
+        })
+        .collect()
+}
+
+/// Swaps a job's CDN URLs for refreshed ones when the chunk content is
+/// unchanged. Peer URLs added to the plan are kept in their original
+/// position relative to the CDN URLs.
+fn apply_refreshed_urls(
+    job: &mut ChunkJob,
+    refreshed: &HashMap<(String, u64), RefreshedChunkUrls>,
+) {
+    let Some(fresh) = refreshed.get(&(job.file_id.clone(), job.index)) else {
+        return;
+    };
+    if fresh.hash != job.hash || fresh.url.is_empty() {
+        return;
+    }
+    let peers_first = peer_url_fingerprint(&job.url).is_some();
+    let peers: Vec<String> = std::iter::once(std::mem::take(&mut job.url))
+        .chain(std::mem::take(&mut job.fallback_urls))
+        .filter(|url| peer_url_fingerprint(url).is_some())
+        .collect();
+    let cdn = std::iter::once(fresh.url.clone()).chain(fresh.fallback_urls.iter().cloned());
+    let mut urls: Vec<String> = if peers_first {
+        peers.into_iter().chain(cdn).collect()
+    } else {
+        cdn.chain(peers).collect()
+    };
+    job.url = urls.remove(0);
+    job.fallback_urls = urls;
+}
+
 fn resolve_aria2_config(max_concurrent_chunks: usize) -> Aria2Config {
     let split_default = max_concurrent_chunks.clamp(8, 32);
     let split = env_usize("LAUNCHER_ARIA2C_SPLIT")
@@ -1719,6 +4528,46 @@ fn resolve_aria2_config(max_concurrent_chunks: usize) -> Aria2Config {
     }
 }
 
+fn aria2_rpc_enabled() -> bool {
+    std::env::var("LAUNCHER_ARIA2C_RPC")
+        .map(|value| {
+            let normalized = value.trim().to_ascii_lowercase();
+            !matches!(normalized.as_str(), "0" | "false" | "no" | "off")
+        })
+        .unwrap_or(true)
+}
+
+/// Options shared by every chunk submitted to the RPC daemon; the per-chunk
+/// `dir`/`out` are passed with each `aria2.addUri` call.
+fn aria2_rpc_global_args(config: &Aria2Config, concurrency: usize) -> Vec<String> {
+    let mut args = vec![
+        "--allow-overwrite=true".to_string(),
+        "--auto-file-renaming=false".to_string(),
+        "--console-log-level=warn".to_string(),
+        "--file-allocation=none".to_string(),
+        "--continue=true".to_string(),
+        "--always-resume=true".to_string(),
+        "--min-split-size=1M".to_string(),
+        format!("--split={}", config.split),
+        format!(
+            "--max-connection-per-server={}",
+            config.max_connections_per_server
+        ),
+        format!("--max-tries={}", config.max_tries),
+        format!("--retry-wait={}", config.retry_wait_seconds),
+        format!("--timeout={}", config.timeout_seconds),
+        format!("--connect-timeout={}", config.connect_timeout_seconds),
+        format!("--max-concurrent-downloads={}", concurrency.max(1)),
+    ];
+    if let Some(proxy) = config.proxy.as_ref() {
+        args.push(format!("--all-proxy={proxy}"));
+    }
+    if env_truthy("LAUNCHER_DISABLE_SYSTEM_PROXY") {
+        args.push("--all-proxy=".to_string());
+    }
+    args
+}
+
 fn ensure_aria2_available(config: &Aria2Config) -> Result<()> {
     let mut command = std::process::Command::new(&config.binary);
     hide_console_window(&mut command);
@@ -1756,35 +4605,66 @@ fn format_bytes(bytes: u64) -> String {
     }
 }
 
-fn nearest_existing_path(path: &Path) -> PathBuf {
-    let mut candidate = path.to_path_buf();
-    while !candidate.exists() {
-        if !candidate.pop() {
-            return PathBuf::from(".");
-        }
+/// Saves the per-game install roots so picked and moved roots survive a
+/// restart.
+pub(crate) fn persist_game_install_roots(db: &Database, files: &FileManager) -> Result<()> {
+    let roots: HashMap<String, String> = files
+        .game_install_roots()
+        .into_iter()
+        .map(|(slug, path)| (slug, path.to_string_lossy().to_string()))
+        .collect();
+    db.set_setting(GAME_INSTALL_ROOTS_SETTING, &serde_json::to_string(&roots)?)
+}
+
+fn default_write_strategy(disk_kind: Option<DiskKind>) -> WriteStrategy {
+    match disk_kind {
+        Some(DiskKind::HDD) => WriteStrategy::SequentialMerge,
+        _ => WriteStrategy::Direct,
     }
-    candidate
 }
 
-fn available_disk_space(path: &Path) -> Option<u64> {
-    let target = nearest_existing_path(path);
-    let target = std::fs::canonicalize(&target).unwrap_or(target);
-    let disks = Disks::new_with_refreshed_list();
+fn load_storage_options(db: &Database) -> StorageOptions {
+    match db.get_setting(STORAGE_OPTIONS_SETTING) {
+        Ok(Some(raw)) => serde_json::from_str(&raw).unwrap_or_else(|err| {
+            tracing::warn!("ignoring invalid {STORAGE_OPTIONS_SETTING}: {err}");
+            StorageOptions::default()
+        }),
+        _ => StorageOptions::default(),
+    }
+}
 
-    let mut best: Option<(usize, u64)> = None;
-    for disk in disks.list() {
-        let mount = disk.mount_point();
-        if target.starts_with(mount) {
-            let score = mount.as_os_str().to_string_lossy().len();
-            match best {
-                Some((best_score, _)) if best_score >= score => {}
-                _ => best = Some((score, disk.available_space())),
-            }
-        }
+fn load_data_cap(db: &Database) -> DataCapSettings {
+    match db.get_setting(DATA_CAP_SETTING) {
+        Ok(Some(raw)) => serde_json::from_str::<DataCapSettings>(&raw)
+            .map(DataCapSettings::normalized)
+            .unwrap_or_else(|err| {
+                tracing::warn!("ignoring invalid {DATA_CAP_SETTING}: {err}");
+                DataCapSettings::default()
+            }),
+        _ => DataCapSettings::default(),
     }
+}
+
+fn load_download_tuning(db: &Database) -> Option<DownloadTuning> {
+    let raw = db.get_setting(DOWNLOAD_TUNING_SETTING).ok().flatten()?;
+    serde_json::from_str(&raw)
+        .map_err(|err| tracing::warn!("ignoring invalid {DOWNLOAD_TUNING_SETTING}: {err}"))
+        .ok()
+}
+
+fn load_write_strategy_override(db: &Database) -> Option<WriteStrategy> {
+    db.get_setting(WRITE_STRATEGY_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|value| WriteStrategy::parse(&value))
+}
 
-    best.map(|(_, available)| available)
-        .or_else(|| disks.list().first().map(|disk| disk.available_space()))
+fn write_merge_buffer_bytes() -> u64 {
+    env_usize("LAUNCHER_WRITE_MERGE_BUFFER_MB")
+        .unwrap_or(DEFAULT_WRITE_MERGE_BUFFER_MB)
+        .clamp(8, 2048) as u64
+        * 1024
+        * 1024
 }
 
 fn estimate_reclaimable_bytes(paths: &[PathBuf]) -> u64 {
@@ -1885,6 +4765,7 @@ fn evaluate_storage_budget(
     plan: &DownloadPlan,
     old_manifest: Option<&Manifest>,
     cache_write_bytes: u64,
+    options: &StorageOptions,
 ) -> Result<StorageBudget> {
     let available_bytes = available_disk_space(install_dir).ok_or_else(|| {
         LauncherError::Config(format!(
@@ -1898,9 +4779,7 @@ fn evaluate_storage_budget(
     let base_required = preallocate_bytes
         .saturating_add(extraction_bytes)
         .saturating_add(cache_write_bytes);
-    let safety_bytes = STORAGE_SAFETY_MARGIN_BYTES
-        .max(base_required / 20)
-        .min(MAX_STORAGE_SAFETY_MARGIN_BYTES);
+    let safety_bytes = storage_safety_bytes(base_required, options.safety_margin_bytes);
     let required_bytes = base_required.saturating_add(safety_bytes);
 
     Ok(StorageBudget {
@@ -1914,11 +4793,22 @@ fn evaluate_storage_budget(
     })
 }
 
+fn storage_safety_bytes(base_required: u64, configured: Option<u64>) -> u64 {
+    match configured {
+        Some(bytes) => bytes.min(MAX_STORAGE_SAFETY_MARGIN_BYTES),
+        None => STORAGE_SAFETY_MARGIN_BYTES
+            .max(base_required / 20)
+            .min(MAX_STORAGE_SAFETY_MARGIN_BYTES),
+    }
+}
+
 async fn hydrate_from_depot_cache(
     plan: &mut DownloadPlan,
     depot_cache: &DepotCache,
     db: &Database,
     download_id: &str,
+    file_hashes: &IncrementalFileHashes,
+    disk_io: &DiskIoCounters,
 ) -> Result<u64> {
     let mut pending = Vec::with_capacity(plan.chunks.len());
     let mut restored = 0u64;
@@ -1926,7 +4816,10 @@ async fn hydrate_from_depot_cache(
     for job in plan.chunks.drain(..) {
         let cached = depot_cache.load_valid_chunk(&job.hash, job.size)?;
         if let Some(data) = cached {
+            disk_io.add_read(data.len() as u64);
             write_chunk(&job, &data).await?;
+            disk_io.add_written(data.len() as u64);
+            file_hashes.record(&job.file_id, job.offset, &data);
             restored = restored.saturating_add(job.size);
             db.upsert_download_chunk(&DownloadChunk {
                 download_id: download_id.to_string(),
@@ -1954,6 +4847,7 @@ enum ChunkResult {
     NetworkPressure {
         source: &'static str,
         reason: &'static str,
+        host: Option<String>,
     },
     Success {
         file_id: String,
@@ -1961,6 +4855,7 @@ enum ChunkResult {
         size: u64,
         hash: String,
         accounted_bytes: u64,
+        source: ChunkSource,
     },
     Error {
         error: LauncherError,
@@ -1970,6 +4865,7 @@ enum ChunkResult {
 struct DownloadChunkPayload {
     data: Vec<u8>,
     accounted_bytes: u64,
+    source: ChunkSource,
 }
 
 async fn wait_for_running(control: &mut watch::Receiver<DownloadControl>) -> Result<()> {
@@ -1990,33 +4886,70 @@ async fn wait_for_running(control: &mut watch::Receiver<DownloadControl>) -> Res
     }
 }
 
+/// Waits out a pause mid-transfer without holding a host slot, taking one
+/// again before the transfer goes on.
+async fn pause_without_host_slot(
+    control: &mut watch::Receiver<DownloadControl>,
+    host_limits: &HostConnectionLimiter,
+    url: &str,
+    permit: &mut Option<OwnedSemaphorePermit>,
+) -> Result<()> {
+    if *control.borrow() == DownloadControl::Running {
+        return Ok(());
+    }
+    permit.take();
+    wait_for_running(control).await?;
+    *permit = host_limits.acquire(url).await;
+    Ok(())
+}
+
 async fn download_chunk(
-    client: &reqwest::Client,
+    client: &ChunkClients,
     job: &ChunkJob,
     engine: DownloadEngine,
     aria2_config: Option<&Aria2Config>,
+    aria2_rpc: Option<&Aria2RpcDaemon>,
     progress_tx: &mpsc::Sender<ChunkResult>,
     control: &mut watch::Receiver<DownloadControl>,
     peer_blacklist: &Arc<Mutex<HashSet<String>>>,
+    peer_transfers: &PeerTransferStats,
+    host_limits: &HostConnectionLimiter,
+    received: &ReceivedBytes,
 ) -> Result<DownloadChunkPayload> {
     wait_for_running(control).await?;
+    let mut reported = 0u64;
     if engine == DownloadEngine::Aria2c {
         if let Some(config) = aria2_config {
-            match download_chunk_with_aria2(job, config).await {
+            let attempt = match aria2_rpc {
+                Some(daemon) => download_chunk_with_aria2_rpc(job, daemon).await,
+                None => {
+                    download_chunk_with_aria2(job, config, progress_tx, control, &mut reported)
+                        .await
+                }
+            };
+            match attempt {
                 Ok(mut data) => {
+                    let source = if peer_url_fingerprint(&job.url).is_some() {
+                        ChunkSource::Peer
+                    } else {
+                        ChunkSource::Cdn
+                    };
+                    received.add(source, data.len() as u64);
                     decompress_if_needed(job, &mut data)?;
                     if !verify_chunk(&data, &job.hash) {
-                        return Err(LauncherError::Config("chunk hash mismatch".to_string()));
+                        return Err(LauncherError::Integrity("chunk hash mismatch".to_string()));
                     }
                     return Ok(DownloadChunkPayload {
                         data,
-                        accounted_bytes: 0,
+                        accounted_bytes: reported,
+                        source,
                     });
                 }
                 Err(err) => {
                     if env_truthy("LAUNCHER_ARIA2C_STRICT") {
                         return Err(err);
                     }
+                    wait_for_running(control).await?;
                     tracing::warn!(
                         "aria2 chunk failed for file={} chunk={}, fallback to reqwest: {}",
                         job.file_id,
@@ -2056,19 +4989,24 @@ async fn download_chunk(
 
         let (max_attempts, retry_wait_ms, timeout_ms) =
             resolve_http_retry_policy(peer_key.is_some());
+        let source = if peer_key.is_some() {
+            ChunkSource::Peer
+        } else {
+            ChunkSource::Cdn
+        };
         let mut last_failure: Option<String> = None;
         for attempt in 1..=max_attempts {
-            let response = client
-                .get(&url)
-                .timeout(Duration::from_millis(timeout_ms))
-                .send()
-                .await;
+            // Taken per attempt and given back for backoff and pauses, so a
+            // waiting chunk never holds one of the host's slots.
+            wait_for_running(control).await?;
+            let mut host_permit = host_limits.acquire(&url).await;
+            let response = client.get(&url, Duration::from_millis(timeout_ms)).await;
             match response {
                 Ok(resp) => {
                     if resp.status().is_success() {
                         let mut stream = resp.bytes_stream();
                         let mut data = Vec::with_capacity(job.size.min(16 * 1024 * 1024) as usize);
-                        let mut accounted = 0u64;
+                        let mut accounted = reported;
 
                         loop {
                             tokio::select! {
@@ -2078,7 +5016,7 @@ async fn download_chunk(
                                     match control_state {
                                         DownloadControl::Running => {}
                                         DownloadControl::Paused => {
-                                            wait_for_running(control).await?;
+                                            pause_without_host_slot(control, host_limits, &url, &mut host_permit).await?;
                                         }
                                         DownloadControl::Cancelled => {
                                             return Err(LauncherError::Config("download cancelled".to_string()));
@@ -2087,8 +5025,9 @@ async fn download_chunk(
                                 }
                                 next = stream.next() => {
                                     let Some(next) = next else { break; };
-                                    wait_for_running(control).await?;
+                                    pause_without_host_slot(control, host_limits, &url, &mut host_permit).await?;
                                     let bytes = next?;
+                                    received.add(source, bytes.len() as u64);
                                     data.extend_from_slice(&bytes);
 
                                     let room = job.size.saturating_sub(accounted);
@@ -2124,14 +5063,20 @@ async fn download_chunk(
                             break;
                         }
 
-                        if accounted > 0 {
+                        if accounted > reported {
                             let _ = progress_tx
-                                .send(ChunkResult::Progress { bytes: accounted })
+                                .send(ChunkResult::Progress {
+                                    bytes: accounted - reported,
+                                })
                                 .await;
                         }
+                        if let Some(key) = peer_key.as_ref() {
+                            peer_transfers.record_received(key, data.len() as u64);
+                        }
                         return Ok(DownloadChunkPayload {
                             data,
                             accounted_bytes: accounted,
+                            source,
                         });
                     }
                     let status = resp.status();
@@ -2159,8 +5104,13 @@ async fn download_chunk(
                             "http_retryable"
                         };
                         let _ = progress_tx
-                            .send(ChunkResult::NetworkPressure { source, reason })
+                            .send(ChunkResult::NetworkPressure {
+                                source,
+                                reason,
+                                host: url_host(&url),
+                            })
                             .await;
+                        drop(host_permit);
                         sleep(Duration::from_millis(retry_wait_ms * attempt as u64)).await;
                         continue;
                     }
@@ -2180,8 +5130,13 @@ async fn download_chunk(
                             "socket_connect"
                         };
                         let _ = progress_tx
-                            .send(ChunkResult::NetworkPressure { source, reason })
+                            .send(ChunkResult::NetworkPressure {
+                                source,
+                                reason,
+                                host: url_host(&url),
+                            })
                             .await;
+                        drop(host_permit);
                         sleep(Duration::from_millis(retry_wait_ms * attempt as u64)).await;
                         continue;
                     }
@@ -2190,6 +5145,9 @@ async fn download_chunk(
             }
         }
         if let Some(failure) = last_failure {
+            if let Some(key) = peer_key.as_ref() {
+                peer_transfers.record_failure(key);
+            }
             failures.push(failure);
         }
     }
@@ -2276,91 +5234,225 @@ fn aria2_temp_paths(job: &ChunkJob) -> Result<(PathBuf, String)> {
     Ok((path, filename))
 }
 
-async fn download_chunk_with_aria2(job: &ChunkJob, config: &Aria2Config) -> Result<Vec<u8>> {
+/// Runs aria2c for one chunk, turning its periodic summary into progress
+/// updates. Pausing or cancelling kills the child; after a pause it is started
+/// again and resumes from its control file. `reported` holds the bytes already
+/// sent as progress so a fallback engine doesn't count them twice.
+async fn download_chunk_with_aria2(
+    job: &ChunkJob,
+    config: &Aria2Config,
+    progress_tx: &mpsc::Sender<ChunkResult>,
+    control: &mut watch::Receiver<DownloadControl>,
+    reported: &mut u64,
+) -> Result<Vec<u8>> {
     let (scratch_path, scratch_name) = aria2_temp_paths(job)?;
     let control_path = scratch_path.with_extension("part.aria2");
-
-    let mut urls = Vec::new();
-    urls.push(job.url.clone());
-    urls.extend(job.fallback_urls.clone());
-
-    let binary = config.binary.clone();
-    let split = config.split;
-    let max_connections_per_server = config.max_connections_per_server;
-    let max_tries = config.max_tries;
-    let retry_wait_seconds = config.retry_wait_seconds;
-    let timeout_seconds = config.timeout_seconds;
-    let connect_timeout_seconds = config.connect_timeout_seconds;
-    let proxy = config.proxy.clone();
-
     let scratch_dir = scratch_path
         .parent()
         .ok_or_else(|| LauncherError::Config("aria2 scratch dir unavailable".to_string()))?
         .to_path_buf();
-    let scratch_name_for_cmd = scratch_name.clone();
-
-    let data = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
-        let mut command = std::process::Command::new(&binary);
-        hide_console_window(&mut command);
-        command
-            .arg("--allow-overwrite=true")
-            .arg("--auto-file-renaming=false")
-            .arg("--summary-interval=0")
-            .arg("--console-log-level=warn")
-            .arg("--file-allocation=none")
-            .arg("--continue=true")
-            .arg("--always-resume=true")
-            .arg(format!("--split={split}"))
-            .arg(format!(
-                "--max-connection-per-server={max_connections_per_server}"
-            ))
-            .arg(format!("--max-tries={max_tries}"))
-            .arg(format!("--retry-wait={retry_wait_seconds}"))
-            .arg(format!("--timeout={timeout_seconds}"))
-            .arg(format!("--connect-timeout={connect_timeout_seconds}"))
-            .arg("--min-split-size=1M")
-            .arg("--dir")
-            .arg(&scratch_dir)
-            .arg("--out")
-            .arg(&scratch_name_for_cmd);
-
-        if let Some(proxy) = proxy {
-            command.arg(format!("--all-proxy={proxy}"));
-        }
-        if env_truthy("LAUNCHER_DISABLE_SYSTEM_PROXY") {
-            command.arg("--all-proxy=");
-        }
 
-        for url in urls {
-            command.arg(url);
+    loop {
+        wait_for_running(control).await?;
+        let mut child = aria2_command(job, config, &scratch_dir, &scratch_name)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()?;
+        let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+        let stdout = child
+            .stdout
+            .take()
+            .map(|pipe| read_aria2_output(pipe, Some(done_tx)));
+        let stderr = child
+            .stderr
+            .take()
+            .map(|pipe| read_aria2_output(pipe, None));
+
+        let mut control_closed = false;
+        let mut interrupted = false;
+        loop {
+            tokio::select! {
+                changed = control.changed() => {
+                    control_closed = changed.is_err();
+                    if control_closed || *control.borrow() != DownloadControl::Running {
+                        let _ = child.kill();
+                        interrupted = true;
+                        break;
+                    }
+                }
+                done = done_rx.recv() => {
+                    let Some(done) = done else { break; };
+                    let delta = done.min(job.size).saturating_sub(*reported);
+                    if delta > 0 {
+                        *reported += delta;
+                        let _ = progress_tx.send(ChunkResult::Progress { bytes: delta }).await;
+                    }
+                }
+            }
         }
 
-        let output = command.output()?;
-        if !output.status.success() {
-            let stderr = trim_output_snippet(&output.stderr);
-            let stdout = trim_output_snippet(&output.stdout);
-            let details = if !stderr.is_empty() {
-                stderr
-            } else if !stdout.is_empty() {
-                stdout
+        let (status, stdout, stderr) = tokio::task::spawn_blocking(move || {
+            let status = child.wait();
+            let collect = |handle: Option<thread::JoinHandle<String>>| {
+                handle
+                    .and_then(|handle| handle.join().ok())
+                    .unwrap_or_default()
+            };
+            (status, collect(stdout), collect(stderr))
+        })
+        .await
+        .map_err(|err| LauncherError::Config(format!("aria2c worker failed: {err}")))?;
+        let status = status?;
+
+        if control_closed {
+            return Err(LauncherError::Config("download control closed".to_string()));
+        }
+        if interrupted {
+            continue;
+        }
+        if !status.success() {
+            let details = if !stderr.trim().is_empty() {
+                trim_output_snippet(stderr.as_bytes())
+            } else if !stdout.trim().is_empty() {
+                trim_output_snippet(stdout.as_bytes())
             } else {
                 "aria2c returned non-zero status".to_string()
             };
             return Err(LauncherError::Http(format!(
                 "aria2c failed ({}): {}",
-                output.status, details
+                status, details
             )));
         }
 
-        let bytes = std::fs::read(&scratch_path)?;
-        let _ = std::fs::remove_file(&scratch_path);
-        let _ = std::fs::remove_file(&control_path);
-        Ok(bytes)
+        let bytes = tokio::fs::read(&scratch_path).await?;
+        let _ = tokio::fs::remove_file(&scratch_path).await;
+        let _ = tokio::fs::remove_file(&control_path).await;
+        return Ok(bytes);
+    }
+}
+
+fn aria2_command(
+    job: &ChunkJob,
+    config: &Aria2Config,
+    scratch_dir: &Path,
+    scratch_name: &str,
+) -> std::process::Command {
+    let mut command = std::process::Command::new(&config.binary);
+    hide_console_window(&mut command);
+    command
+        .arg("--allow-overwrite=true")
+        .arg("--auto-file-renaming=false")
+        .arg("--summary-interval=1")
+        .arg("--console-log-level=warn")
+        .arg("--file-allocation=none")
+        .arg("--continue=true")
+        .arg("--always-resume=true")
+        .arg(format!("--split={}", config.split))
+        .arg(format!(
+            "--max-connection-per-server={}",
+            config.max_connections_per_server
+        ))
+        .arg(format!("--max-tries={}", config.max_tries))
+        .arg(format!("--retry-wait={}", config.retry_wait_seconds))
+        .arg(format!("--timeout={}", config.timeout_seconds))
+        .arg(format!(
+            "--connect-timeout={}",
+            config.connect_timeout_seconds
+        ))
+        .arg("--min-split-size=1M")
+        .arg("--dir")
+        .arg(scratch_dir)
+        .arg("--out")
+        .arg(scratch_name);
+
+    if let Some(proxy) = config.proxy.as_ref() {
+        command.arg(format!("--all-proxy={proxy}"));
+    }
+    if env_truthy("LAUNCHER_DISABLE_SYSTEM_PROXY") {
+        command.arg("--all-proxy=");
+    }
+
+    command.arg(&job.url);
+    command.args(&job.fallback_urls);
+    command
+}
+
+/// Drains an aria2c pipe on its own thread. Progress readouts are parsed and
+/// sent to `progress` when given; the other lines are kept for error reports.
+fn read_aria2_output<R: Read + Send + 'static>(
+    pipe: R,
+    progress: Option<mpsc::UnboundedSender<u64>>,
+) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut reader = io::BufReader::new(pipe);
+        let mut kept = String::new();
+        let mut buffer = Vec::new();
+        loop {
+            buffer.clear();
+            match io::BufRead::read_until(&mut reader, b'\n', &mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let text = String::from_utf8_lossy(&buffer);
+            for line in text.split('\r') {
+                if let Some(done) = parse_aria2_progress(line) {
+                    if let Some(progress) = progress.as_ref() {
+                        let _ = progress.send(done);
+                    }
+                } else if kept.len() < 16 * 1024 && !line.trim().is_empty() {
+                    kept.push_str(line.trim_end());
+                    kept.push('\n');
+                }
+            }
+        }
+        kept
     })
-    .await
-    .map_err(|err| LauncherError::Config(format!("aria2c worker failed: {err}")))??;
+}
+
+/// Completed bytes from a readout like `[#2089b0 1.2MiB/4.0MiB(30%) CN:2 DL:3.1MiB]`.
+fn parse_aria2_progress(line: &str) -> Option<u64> {
+    let readout = line.trim().strip_prefix("[#")?;
+    let sizes = readout.split_whitespace().nth(1)?;
+    let (done, _) = sizes.split_once('/')?;
+    parse_aria2_size(done)
+}
+
+fn parse_aria2_size(value: &str) -> Option<u64> {
+    let units: [(&str, u64); 5] = [
+        ("TiB", 1 << 40),
+        ("GiB", 1 << 30),
+        ("MiB", 1 << 20),
+        ("KiB", 1 << 10),
+        ("B", 1),
+    ];
+    let (number, scale) = units
+        .iter()
+        .find_map(|(suffix, scale)| value.strip_suffix(suffix).map(|number| (number, *scale)))?;
+    let number: f64 = number.parse().ok()?;
+    if !number.is_finite() || number < 0.0 {
+        return None;
+    }
+    Some((number * scale as f64) as u64)
+}
+
+async fn download_chunk_with_aria2_rpc(job: &ChunkJob, daemon: &Aria2RpcDaemon) -> Result<Vec<u8>> {
+    let (scratch_path, scratch_name) = aria2_temp_paths(job)?;
+    let control_path = scratch_path.with_extension("part.aria2");
+    let scratch_dir = scratch_path
+        .parent()
+        .ok_or_else(|| LauncherError::Config("aria2 scratch dir unavailable".to_string()))?
+        .to_path_buf();
+
+    let mut urls = Vec::new();
+    urls.push(job.url.clone());
+    urls.extend(job.fallback_urls.clone());
 
-    Ok(data)
+    daemon.download(&urls, &scratch_dir, &scratch_name).await?;
+    let bytes = tokio::fs::read(&scratch_path).await?;
+    let _ = tokio::fs::remove_file(&scratch_path).await;
+    let _ = tokio::fs::remove_file(&control_path).await;
+    Ok(bytes)
 }
 
 fn decompress_if_needed(job: &ChunkJob, data: &mut Vec<u8>) -> Result<()> {
@@ -2398,6 +5490,201 @@ async fn write_chunk(job: &ChunkJob, data: &[u8]) -> Result<()> {
     Ok(())
 }
 
+/// Writes downloaded chunks into their `.part` files according to a
+/// `WriteStrategy`. A chunk's `Success` result is only sent once its bytes are
+/// on disk, so resume state never claims data that is still buffered.
+struct ChunkWriter {
+    strategy: WriteStrategy,
+    max_buffered_bytes: u64,
+    merge: tokio::sync::Mutex<MergeState>,
+    file_hashes: IncrementalFileHashes,
+    disk_io: DiskIoCounters,
+}
+
+#[derive(Default)]
+struct MergeState {
+    /// Offsets per `.part` file that have not been written yet.
+    pending_offsets: HashMap<PathBuf, BTreeSet<u64>>,
+    buffered: BTreeMap<(PathBuf, u64), BufferedChunk>,
+    buffered_bytes: u64,
+    remaining: usize,
+}
+
+struct BufferedChunk {
+    file_id: String,
+    data: Vec<u8>,
+    result: ChunkResult,
+}
+
+impl ChunkWriter {
+    fn new(
+        strategy: WriteStrategy,
+        jobs: &[ChunkJob],
+        max_buffered_bytes: u64,
+        file_hashes: IncrementalFileHashes,
+        disk_io: DiskIoCounters,
+    ) -> Self {
+        let mut merge = MergeState::default();
+        if strategy == WriteStrategy::SequentialMerge {
+            for job in jobs {
+                merge
+                    .pending_offsets
+                    .entry(job.temp_path.clone())
+                    .or_default()
+                    .insert(job.offset);
+            }
+            merge.remaining = jobs.len();
+        }
+        Self {
+            strategy,
+            max_buffered_bytes,
+            merge: tokio::sync::Mutex::new(merge),
+            file_hashes,
+            disk_io,
+        }
+    }
+
+    async fn submit(
+        &self,
+        job: &ChunkJob,
+        data: Vec<u8>,
+        result: ChunkResult,
+        tx: &mpsc::Sender<ChunkResult>,
+    ) -> Result<()> {
+        if self.strategy == WriteStrategy::Direct {
+            write_chunk(job, &data).await?;
+            self.disk_io.add_written(data.len() as u64);
+            self.file_hashes.record(&job.file_id, job.offset, &data);
+            let _ = tx.send(result).await;
+            return Ok(());
+        }
+
+        let results = {
+            let mut state = self.merge.lock().await;
+            state.buffered_bytes = state.buffered_bytes.saturating_add(data.len() as u64);
+            state.buffered.insert(
+                (job.temp_path.clone(), job.offset),
+                BufferedChunk {
+                    file_id: job.file_id.clone(),
+                    data,
+                    result,
+                },
+            );
+            self.flush_ready(&mut state, &job.temp_path).await?
+        };
+
+        for result in results {
+            let _ = tx.send(result).await;
+        }
+        Ok(())
+    }
+
+    /// Gives up on a chunk that failed, so the chunks buffered behind it are
+    /// written and reported now instead of waiting on it forever.
+    async fn abandon(&self, job: &ChunkJob, tx: &mpsc::Sender<ChunkResult>) -> Result<()> {
+        if self.strategy == WriteStrategy::Direct {
+            return Ok(());
+        }
+        let results = {
+            let mut state = self.merge.lock().await;
+            if let Some(offsets) = state.pending_offsets.get_mut(&job.temp_path) {
+                offsets.remove(&job.offset);
+            }
+            self.flush_ready(&mut state, &job.temp_path).await?
+        };
+        for result in results {
+            let _ = tx.send(result).await;
+        }
+        Ok(())
+    }
+
+    /// Counts one chunk as settled and writes what that made ready. Chunks
+    /// taken for writing are released even when the write fails.
+    async fn flush_ready(&self, state: &mut MergeState, path: &Path) -> Result<Vec<ChunkResult>> {
+        state.remaining = state.remaining.saturating_sub(1);
+        // Flush everything once the last chunk settles or the buffer is full,
+        // otherwise only the run that is now contiguous for this file.
+        let ready = if state.remaining == 0 || state.buffered_bytes >= self.max_buffered_bytes {
+            std::mem::take(&mut state.buffered).into_iter().collect()
+        } else {
+            state.take_contiguous(path)
+        };
+        for ((path, offset), chunk) in &ready {
+            state.buffered_bytes = state.buffered_bytes.saturating_sub(chunk.data.len() as u64);
+            if let Some(offsets) = state.pending_offsets.get_mut(path) {
+                offsets.remove(offset);
+            }
+        }
+        // Written under the lock so runs from different tasks never interleave.
+        write_merged_runs(&ready).await?;
+        for ((_, offset), chunk) in &ready {
+            self.disk_io.add_written(chunk.data.len() as u64);
+            self.file_hashes
+                .record(&chunk.file_id, *offset, &chunk.data);
+        }
+        Ok(ready.into_iter().map(|(_, chunk)| chunk.result).collect())
+    }
+}
+
+impl MergeState {
+    fn take_contiguous(&mut self, path: &Path) -> Vec<((PathBuf, u64), BufferedChunk)> {
+        let mut ready = Vec::new();
+        let Some(offsets) = self.pending_offsets.get(path) else {
+            return ready;
+        };
+        for offset in offsets {
+            match self.buffered.remove(&(path.to_path_buf(), *offset)) {
+                Some(chunk) => ready.push(((path.to_path_buf(), *offset), chunk)),
+                None => break,
+            }
+        }
+        ready
+    }
+}
+
+/// Lets the chunks buffered behind a failed one reach disk before its
+/// error is reported.
+async fn abandon_chunk(writer: &ChunkWriter, job: &ChunkJob, tx: &mpsc::Sender<ChunkResult>) {
+    if let Err(err) = writer.abandon(job, tx).await {
+        tracing::warn!(
+            "failed to flush chunks buffered behind {}: {}",
+            job.hash,
+            err
+        );
+    }
+}
+
+/// Writes chunks sorted by (path, offset), opening each file once and only
+/// seeking when a run has a gap.
+async fn write_merged_runs(chunks: &[((PathBuf, u64), BufferedChunk)]) -> Result<()> {
+    let mut current: Option<(&Path, tokio::fs::File, u64)> = None;
+    for ((path, offset), chunk) in chunks {
+        let reuse = matches!(&current, Some((open_path, _, _)) if *open_path == path.as_path());
+        if !reuse {
+            if let Some((_, mut file, _)) = current.take() {
+                file.flush().await?;
+            }
+            let file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .open(path)
+                .await?;
+            current = Some((path.as_path(), file, u64::MAX));
+        }
+        if let Some((_, file, cursor)) = current.as_mut() {
+            if *cursor != *offset {
+                file.seek(std::io::SeekFrom::Start(*offset)).await?;
+            }
+            file.write_all(&chunk.data).await?;
+            *cursor = offset.saturating_add(chunk.data.len() as u64);
+        }
+    }
+    if let Some((_, mut file, _)) = current {
+        file.flush().await?;
+    }
+    Ok(())
+}
+
 fn chunk_region_exists(path: &Path, offset: u64, size: u64) -> bool {
     match std::fs::metadata(path) {
         Ok(metadata) => metadata.len() >= offset.saturating_add(size),
@@ -2410,6 +5697,7 @@ fn build_download_plan(
     install_dir: &Path,
     completed: &HashMap<(String, i32), String>,
     old_manifest: Option<&Manifest>,
+    verified_files: &HashSet<String>,
 ) -> Result<DownloadPlan> {
     let mut chunks = Vec::new();
     let mut total_bytes = 0u64;
@@ -2425,21 +5713,17 @@ fn build_download_plan(
     };
 
     if is_archive_mode(manifest) {
-        let archive_dir = archive_dir_name(manifest);
-        let mut new_files = HashSet::new();
-        for path in &manifest.archive_files {
-            new_files.insert(path.replace("\\", "/"));
-        }
-        if let Some(old_manifest) = old_manifest {
-            for old_path in &old_manifest.archive_files {
-                let normalized = old_path.replace("\\", "/");
-                if new_files.contains(&normalized) {
-                    continue;
-                }
-                if normalized.is_empty() {
-                    continue;
-                }
-                if normalized.starts_with(&archive_dir) {
+        validate_archive_manifest(manifest)?;
+        let mut keep = archive_file_set(manifest);
+        keep.extend(
+            manifest
+                .files
+                .iter()
+                .map(|file| normalize_manifest_path(&file.path)),
+        );
+        if let Some(old_manifest) = old_manifest.filter(|old| is_archive_mode(old)) {
+            for normalized in archive_file_set(old_manifest) {
+                if keep.contains(&normalized) || normalized.is_empty() {
                     continue;
                 }
                 if normalized.eq_ignore_ascii_case(MANIFEST_FILE) {
@@ -2463,14 +5747,24 @@ fn build_download_plan(
     }
 
     for file in &manifest.files {
+        // Already in place and verified, e.g. copied over by a local import.
+        if verified_files.contains(&file.file_id) {
+            let size: u64 = file.chunks.iter().map(|chunk| chunk.size).sum();
+            total_bytes += size;
+            preexisting += size;
+            continue;
+        }
+
         let final_path = install_dir.join(&file.path);
-        let temp_path = final_path.with_extension("part");
+        let temp_path = partial_file_path(install_dir, file);
         let mut needs_finalize = false;
 
         for chunk in &file.chunks {
             total_bytes += chunk.size;
             let offset = chunk.index * chunk_size;
 
+            // Progress is matched on the chunk hash, never its URL, so a
+            // manifest re-fetched with new signed links keeps it.
             let key = (file.file_id.clone(), chunk.index as i32);
             if let Some(hash) = completed.get(&key) {
                 if hash == &chunk.hash && chunk_region_exists(&temp_path, offset, chunk.size) {
@@ -2502,38 +5796,149 @@ fn build_download_plan(
             });
         }
 
-        if needs_finalize {
-            files_to_finalize.push(FilePlan {
-                final_path,
-                temp_path,
-                size: file.size,
-            });
+        if needs_finalize {
+            files_to_finalize.push(FilePlan {
+                final_path,
+                temp_path,
+                size: file.size,
+            });
+        }
+    }
+
+    Ok(DownloadPlan {
+        chunks,
+        total_bytes,
+        preexisting_bytes: preexisting,
+        files_to_finalize,
+        delete_files,
+        precompleted_chunks,
+    })
+}
+
+/// Orders each chunk's CDN URLs by measured mirror latency. Runs before
+/// `apply_peer_sources` so peer ordering still follows the source policy.
+async fn apply_mirror_ranking(plan: &mut DownloadPlan, ranker: &MirrorRanker) {
+    let mut hosts_seen = HashSet::new();
+    let mut multi_source = false;
+    for job in &plan.chunks {
+        if !job.fallback_urls.is_empty() {
+            multi_source = true;
+        }
+        hosts_seen.insert(job.url.as_str());
+        hosts_seen.extend(job.fallback_urls.iter().map(String::as_str));
+    }
+    if !multi_source {
+        return;
+    }
+    ranker.probe_hosts(hosts_seen).await;
+
+    for job in &mut plan.chunks {
+        if job.fallback_urls.is_empty() {
+            continue;
+        }
+        let mut urls = Vec::with_capacity(1 + job.fallback_urls.len());
+        urls.push(std::mem::take(&mut job.url));
+        urls.append(&mut job.fallback_urls);
+        let mut ranked = ranker.rank_urls(urls).into_iter();
+        job.url = ranked.next().unwrap_or_default();
+        job.fallback_urls = ranked.collect();
+    }
+}
+
+fn default_peer_source_config() -> PeerSourceConfig {
+    let policy = std::env::var("LAUNCHER_P2P_SOURCE_POLICY")
+        .ok()
+        .and_then(|value| PeerSourcePolicy::parse(&value))
+        .unwrap_or(PeerSourcePolicy::PreferPeer);
+    let fanout = env_usize("LAUNCHER_P2P_FANOUT")
+        .unwrap_or(DEFAULT_P2P_FANOUT)
+        .clamp(1, MAX_P2P_FANOUT);
+    PeerSourceConfig { policy, fanout }
+}
+
+/// Whether peer sharing is on, defaulting to on when it was never saved.
+fn load_p2p_enabled(db: &Database) -> bool {
+    db.get_setting(P2P_ENABLED_SETTING)
+        .ok()
+        .flatten()
+        .is_none_or(|value| value != "false")
+}
+
+fn load_p2p_upload_limit(db: &Database) -> Option<u64> {
+    db.get_setting(P2P_UPLOAD_LIMIT_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|value| *value > 0)
+}
+
+impl PeerNetwork {
+    fn start(&mut self, api: &ApiClient, depot_root: &Path, upload_limit_bps: Option<u64>) {
+        if self.server.is_some() {
+            return;
+        }
+        self.server = PeerCacheServer::start(depot_root.to_path_buf(), upload_limit_bps);
+        self.coordinator = self
+            .server
+            .as_ref()
+            .and_then(|server| PeerCoordinator::new(api.clone(), server.advertise_info()));
+        if let Some(coordination) = self.coordinator.as_ref() {
+            coordination.start();
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(coordination) = self.coordinator.take() {
+            coordination.stop();
+        }
+        if let Some(server) = self.server.take() {
+            self.served_before = self
+                .served_before
+                .saturating_add(server.upload_stats().bytes_served);
+            server.stop();
         }
     }
+}
 
-    Ok(DownloadPlan {
-        chunks,
-        total_bytes,
-        preexisting_bytes: preexisting,
-        files_to_finalize,
-        delete_files,
-        precompleted_chunks,
-    })
+/// Env defaults overridden by values saved through `set_peer_source_config`.
+fn load_peer_source_config(db: &Database) -> PeerSourceConfig {
+    let mut config = default_peer_source_config();
+    if let Some(policy) = db
+        .get_setting(P2P_SOURCE_POLICY_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|value| PeerSourcePolicy::parse(&value))
+    {
+        config.policy = policy;
+    }
+    if let Some(fanout) = db
+        .get_setting(P2P_FANOUT_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+    {
+        config.fanout = fanout.clamp(1, MAX_P2P_FANOUT);
+    }
+    config
 }
 
-fn apply_peer_sources(plan: &mut DownloadPlan, peers: &[PeerCandidate]) {
-    let fanout = env_usize("LAUNCHER_P2P_FANOUT").unwrap_or(3).clamp(1, 6);
+fn apply_peer_sources(plan: &mut DownloadPlan, peers: &[PeerCandidate], config: PeerSourceConfig) {
+    let lead_reputation = peers.iter().map(|peer| peer.reputation).max().unwrap_or(0);
     for job in &mut plan.chunks {
-        let peer_urls = build_chunk_peer_urls(&job.hash, peers, fanout);
+        let peer_urls = build_chunk_peer_urls(&job.hash, peers, config.fanout);
         if peer_urls.is_empty() {
             continue;
         }
 
-        let mut merged = Vec::with_capacity(peer_urls.len() + 1 + job.fallback_urls.len());
-        merged.extend(peer_urls);
-        merged.push(job.url.clone());
-        merged.extend(job.fallback_urls.clone());
-        let merged = dedupe_url_list(merged);
+        let mut cdn_urls = Vec::with_capacity(1 + job.fallback_urls.len());
+        cdn_urls.push(job.url.clone());
+        cdn_urls.extend(job.fallback_urls.clone());
+        let merged = order_chunk_sources(
+            peer_urls,
+            dedupe_url_list(cdn_urls),
+            lead_reputation,
+            config.policy,
+        );
         if merged.is_empty() {
             continue;
         }
@@ -2558,7 +5963,39 @@ fn dedupe_url_list(urls: Vec<String>) -> Vec<String> {
     out
 }
 
-async fn prepare_files(files: &[FilePlan]) -> Result<()> {
+/// Cheap summary of the installed files: size and mtime of every manifest
+/// file. Downloads only write `.part` files until they finalize, so a paused
+/// download leaves it unchanged.
+fn install_fingerprint(install_dir: &Path, identity: &str, files: &[ManifestFile]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(identity.as_bytes());
+    hasher.update(install_dir.to_string_lossy().as_bytes());
+    for file in files {
+        hasher.update(file.path.as_bytes());
+        match std::fs::metadata(install_dir.join(&file.path)) {
+            Ok(metadata) => {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                    .map(|since| since.as_nanos())
+                    .unwrap_or(0);
+                hasher.update(&metadata.len().to_le_bytes());
+                hasher.update(&modified.to_le_bytes());
+            }
+            Err(_) => {
+                hasher.update(b"-");
+            }
+        }
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+fn partial_file_path(install_dir: &Path, file: &ManifestFile) -> PathBuf {
+    install_dir.join(&file.path).with_extension("part")
+}
+
+async fn prepare_files(files: &[FilePlan], options: &StorageOptions) -> Result<()> {
     for plan in files {
         if let Some(parent) = plan.temp_path.parent() {
             tokio::fs::create_dir_all(parent).await?;
@@ -2569,17 +6006,91 @@ async fn prepare_files(files: &[FilePlan]) -> Result<()> {
             .write(true)
             .open(&plan.temp_path)
             .await?;
+        if !options.preallocate {
+            // Chunk writes seek past the end and extend the file as needed.
+            continue;
+        }
         let current_len = file.metadata().await.map(|meta| meta.len()).unwrap_or(0);
         if current_len != plan.size {
+            if options.sparse {
+                let std_file = file.try_clone().await?.into_std().await;
+                if let Err(err) = mark_sparse(&std_file) {
+                    tracing::debug!(
+                        "could not mark {} sparse: {}",
+                        plan.temp_path.display(),
+                        err
+                    );
+                }
+            }
             file.set_len(plan.size).await?;
         }
     }
     Ok(())
 }
 
+#[cfg(target_os = "windows")]
+fn mark_sparse(file: &std::fs::File) -> std::io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+
+    const FSCTL_SET_SPARSE: u32 = 0x0009_00C4;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn DeviceIoControl(
+            h_device: *mut std::ffi::c_void,
+            dw_io_control_code: u32,
+            lp_in_buffer: *mut std::ffi::c_void,
+            n_in_buffer_size: u32,
+            lp_out_buffer: *mut std::ffi::c_void,
+            n_out_buffer_size: u32,
+            lp_bytes_returned: *mut u32,
+            lp_overlapped: *mut std::ffi::c_void,
+        ) -> i32;
+    }
+
+    let mut bytes_returned = 0u32;
+    let ok = unsafe {
+        DeviceIoControl(
+            file.as_raw_handle(),
+            FSCTL_SET_SPARSE,
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+            0,
+            &mut bytes_returned,
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn mark_sparse(_file: &std::fs::File) -> std::io::Result<()> {
+    Ok(())
+}
+
 async fn finalize_files(files: &[FilePlan]) -> Result<()> {
     for plan in files {
         if plan.temp_path.exists() {
+            // Without preallocation a file whose tail was never written
+            // (e.g. trailing zeros) ends short, and a `.part` left by an
+            // earlier, larger build of the file ends long; either way it is
+            // cut to its real size.
+            let current_len = tokio::fs::metadata(&plan.temp_path)
+                .await
+                .map(|meta| meta.len())
+                .unwrap_or(plan.size);
+            if current_len != plan.size {
+                tokio::fs::OpenOptions::new()
+                    .write(true)
+                    .open(&plan.temp_path)
+                    .await?
+                    .set_len(plan.size)
+                    .await?;
+            }
             if plan.final_path.exists() {
                 let _ = tokio::fs::remove_file(&plan.final_path).await;
             }
@@ -2589,13 +6100,16 @@ async fn finalize_files(files: &[FilePlan]) -> Result<()> {
     Ok(())
 }
 
+/// Unpacks the manifest's changed zip archives on up to `workers` threads,
+/// calling `on_progress` with the archive path and counts as each one finishes.
 async fn extract_archives(
     install_dir: &Path,
     manifest: &Manifest,
     old_manifest: Option<&Manifest>,
+    workers: usize,
+    on_progress: impl Fn(&str, usize, usize) + Send + Sync + 'static,
 ) -> Result<()> {
     let install_dir = install_dir.to_path_buf();
-    let files = manifest.files.clone();
     let archive_dir = archive_dir_name(manifest);
     let cleanup = manifest.archive_cleanup;
     let mut old_hashes = HashMap::new();
@@ -2604,33 +6118,111 @@ async fn extract_archives(
             old_hashes.insert(file.path.clone(), file.hash.clone());
         }
     }
+    let archives: Vec<String> = manifest
+        .files
+        .iter()
+        .filter(|file| file.path.starts_with(&archive_dir))
+        .filter(|file| file.path.to_lowercase().ends_with(".zip"))
+        .filter(|file| old_hashes.get(&file.path) != Some(&file.hash))
+        .filter(|file| install_dir.join(&file.path).exists())
+        .map(|file| file.path.clone())
+        .collect();
+    if archives.is_empty() {
+        return Ok(());
+    }
 
     tokio::task::spawn_blocking(move || -> Result<()> {
-        for file in files {
-            if !file.path.starts_with(&archive_dir) {
-                continue;
-            }
-            if !file.path.to_lowercase().ends_with(".zip") {
-                continue;
-            }
-            if let Some(old_hash) = old_hashes.get(&file.path) {
-                if old_hash == &file.hash {
-                    continue;
+        let total = archives.len();
+        let lanes = extraction_lanes(&install_dir, &archives)?;
+        let queue = Mutex::new(lanes.into_iter().collect::<std::collections::VecDeque<_>>());
+        let completed = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let worker_count = workers.clamp(1, MAX_EXTRACT_WORKERS).min(total);
+
+        let work = || -> Result<()> {
+            while !failed.load(Ordering::Relaxed) {
+                let Some(lane) = queue.lock().ok().and_then(|mut lanes| lanes.pop_front()) else {
+                    break;
+                };
+                for relative in lane {
+                    let archive_path = install_dir.join(&relative);
+                    extract_zip_archive(&archive_path, &install_dir)
+                        .inspect_err(|_| failed.store(true, Ordering::Relaxed))?;
+                    if cleanup {
+                        let _ = std::fs::remove_file(&archive_path);
+                    }
+                    let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                    on_progress(&relative, done, total);
                 }
             }
-            let archive_path = install_dir.join(&file.path);
-            if !archive_path.exists() {
+            Ok(())
+        };
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..worker_count).map(|_| scope.spawn(work)).collect();
+            handles.into_iter().try_for_each(|handle| {
+                handle
+                    .join()
+                    .map_err(|_| LauncherError::Config("extraction worker panicked".to_string()))?
+            })
+        })
+    })
+    .await
+    .map_err(|err| LauncherError::Config(err.to_string()))?
+}
+
+/// Splits `archives` into lanes that can be unpacked side by side. Archives
+/// writing any of the same paths share a lane and keep their manifest order,
+/// so overlapping files are never written concurrently and the later archive
+/// still wins.
+fn extraction_lanes(install_dir: &Path, archives: &[String]) -> Result<Vec<Vec<String>>> {
+    let mut parent: Vec<usize> = (0..archives.len()).collect();
+    fn root(parent: &mut [usize], mut index: usize) -> usize {
+        while parent[index] != index {
+            parent[index] = parent[parent[index]];
+            index = parent[index];
+        }
+        index
+    }
+
+    let mut owners: HashMap<String, usize> = HashMap::new();
+    for (index, relative) in archives.iter().enumerate() {
+        let file = File::open(install_dir.join(relative))?;
+        let mut archive =
+            ZipArchive::new(file).map_err(|err| LauncherError::Config(err.to_string()))?;
+        for entry_index in 0..archive.len() {
+            let entry = archive
+                .by_index_raw(entry_index)
+                .map_err(|err| LauncherError::Config(err.to_string()))?;
+            if entry.is_dir() {
                 continue;
             }
-            extract_zip_archive(&archive_path, &install_dir)?;
-            if cleanup {
-                let _ = std::fs::remove_file(&archive_path);
+            // Case-folded so archives that only differ in case still share a
+            // lane on case-insensitive filesystems.
+            let name = entry.name().replace('\\', "/").to_lowercase();
+            match owners.get(&name) {
+                Some(&owner) => {
+                    let (a, b) = (root(&mut parent, owner), root(&mut parent, index));
+                    parent[a.max(b)] = a.min(b);
+                }
+                None => {
+                    owners.insert(name, index);
+                }
             }
         }
-        Ok(())
-    })
-    .await
-    .map_err(|err| LauncherError::Config(err.to_string()))?
+    }
+
+    let mut lanes: Vec<Vec<String>> = Vec::new();
+    let mut lane_of_root: HashMap<usize, usize> = HashMap::new();
+    for (index, relative) in archives.iter().enumerate() {
+        let lane_root = root(&mut parent, index);
+        let lane = *lane_of_root.entry(lane_root).or_insert_with(|| {
+            lanes.push(Vec::new());
+            lanes.len() - 1
+        });
+        lanes[lane].push(relative.clone());
+    }
+    Ok(lanes)
 }
 
 fn extract_zip_archive(archive_path: &Path, install_dir: &Path) -> Result<()> {
@@ -2645,6 +6237,13 @@ fn extract_zip_archive(archive_path: &Path, install_dir: &Path) -> Result<()> {
         if name.is_empty() {
             continue;
         }
+        if archive::is_symlink(&entry) {
+            return Err(LauncherError::Integrity(format!(
+                "archive {} contains a symlink entry: {}",
+                archive_path.display(),
+                name
+            )));
+        }
         let entry_path = Path::new(&name);
         if !is_safe_relative_path(entry_path) {
             continue;
@@ -2669,6 +6268,128 @@ async fn delete_files(files: &[PathBuf]) {
     }
 }
 
+/// Reads the `manifest.json` in `install_dir` and checks its files for
+/// presence and size without hashing them; volatile files only need to be
+/// present. Archive installs check their extracted `archive_files` for
+/// presence only, since those carry no sizes, and the archives themselves
+/// unless `archive_cleanup` removed them. The check is `None` for an archive
+/// manifest that doesn't list its extracted files.
+fn check_installed_sizes(
+    install_dir: &Path,
+    db: &Database,
+) -> Result<(Manifest, Option<InstallSizeCheck>)> {
+    let mut manifest = load_previous_manifest(install_dir)?;
+    mark_volatile_files(db, &mut manifest);
+    let mut check = InstallSizeCheck::default();
+    let archive_mode = is_archive_mode(&manifest);
+    if archive_mode {
+        if manifest.archive_files.is_empty() {
+            return Ok((manifest, None));
+        }
+        let archive_dir = normalize_manifest_path(&archive_dir_name(&manifest));
+        for path in &manifest.archive_files {
+            let normalized = normalize_manifest_path(path);
+            // Archives are checked below with their sizes, if still kept.
+            if is_under_archive_dir(&normalized, &archive_dir) {
+                continue;
+            }
+            check.checked_files += 1;
+            if !install_dir.join(&normalized).exists() {
+                check.missing.push(normalized);
+            }
+        }
+    }
+    if !archive_mode || !manifest.archive_cleanup {
+        for file in &manifest.files {
+            check.checked_files += 1;
+            // Marked prehashed so the scan stops after the size check.
+            let result = scan_manifest_file(
+                install_dir,
+                file,
+                IntegrityScanMode::Preflight,
+                0,
+                true,
+                None,
+            );
+            match result.status {
+                IntegrityFileStatus::Ok => {}
+                IntegrityFileStatus::Missing => check.missing.push(result.path),
+                IntegrityFileStatus::Corrupt => check.size_mismatches.push(result.path),
+                IntegrityFileStatus::Error => check.unreadable.push(result.path),
+            }
+        }
+    }
+    Ok((manifest, Some(check)))
+}
+
+/// Whether a game's saved download must be left alone rather than replaced
+/// by an install found on disk.
+fn blocks_install_record(state: &DownloadState, active: bool) -> bool {
+    active || state.status != "completed"
+}
+
+/// Slugs name a single folder under a library root: no separators, no `.`
+/// or `..`.
+fn validate_slug(slug: &str) -> Result<()> {
+    let trimmed = slug.trim();
+    if trimmed.is_empty()
+        || trimmed.contains(['/', '\\'])
+        || trimmed.contains("..")
+        || trimmed == "."
+    {
+        return Err(LauncherError::Config(format!(
+            "invalid game slug: {:?}",
+            slug
+        )));
+    }
+    Ok(())
+}
+
+/// Refuses to delete a filesystem root, a library root or any folder one of
+/// them lives in.
+fn ensure_removable(install_dir: &Path, protected: &[PathBuf]) -> Result<()> {
+    let normalize =
+        |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let target = normalize(install_dir);
+    if target.parent().is_none() {
+        return Err(LauncherError::Config(format!(
+            "refusing to remove {}",
+            install_dir.display()
+        )));
+    }
+    if let Some(root) = protected
+        .iter()
+        .find(|root| normalize(root).starts_with(&target))
+    {
+        return Err(LauncherError::Config(format!(
+            "refusing to remove {}: it holds {}",
+            install_dir.display(),
+            root.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Removes everything under `dir` except `keep` and the folders leading to
+/// it. `dir` itself stays when `keep` is inside it.
+async fn remove_dir_except(dir: &Path, keep: &Path) -> Result<()> {
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path == keep {
+            continue;
+        }
+        if keep.starts_with(&path) {
+            Box::pin(remove_dir_except(&path, keep)).await?;
+        } else if entry.file_type().await?.is_dir() {
+            tokio::fs::remove_dir_all(&path).await?;
+        } else {
+            tokio::fs::remove_file(&path).await?;
+        }
+    }
+    Ok(())
+}
+
 fn load_previous_manifest(install_dir: &Path) -> Result<Manifest> {
     let manifest_path = install_dir.join(MANIFEST_FILE);
     let data = std::fs::read_to_string(manifest_path)?;
@@ -2676,6 +6397,15 @@ fn load_previous_manifest(install_dir: &Path) -> Result<Manifest> {
     Ok(manifest)
 }
 
+/// Flags the files integrity scans should leave alone: the ones matching the
+/// game's volatile path override, or the manifest's own list without one.
+fn mark_volatile_files(db: &Database, manifest: &mut Manifest) {
+    let volatile = volatile_paths::resolve(db, &manifest.game_id, &manifest.volatile_paths);
+    for file in &mut manifest.files {
+        file.volatile = volatile.matches(&file.path);
+    }
+}
+
 async fn write_manifest(install_dir: &Path, payload: &str) -> Result<()> {
     let path = install_dir.join(MANIFEST_FILE);
     if let Some(parent) = path.parent() {
@@ -2686,3 +6416,647 @@ async fn write_manifest(install_dir: &Path, payload: &str) -> Result<()> {
     tokio::fs::rename(temp_path, path).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uninstall_rejects_slugs_outside_one_folder() {
+        for slug in ["", " ", ".", "..", "a/b", "a\\b", "../x", "x.."] {
+            assert!(validate_slug(slug).is_err(), "{slug:?}");
+        }
+        assert!(validate_slug("half-life-2").is_ok());
+    }
+
+    #[test]
+    fn uninstall_refuses_library_roots_and_their_parents() {
+        let base = std::env::temp_dir().join(format!("otoshi-uninstall-{}", uuid::Uuid::new_v4()));
+        let root = base.join("Games");
+        let game = root.join("game");
+        std::fs::create_dir_all(&game).unwrap();
+        let protected = vec![root.clone()];
+
+        assert!(ensure_removable(&game, &protected).is_ok());
+        assert!(ensure_removable(&root, &protected).is_err());
+        assert!(ensure_removable(&base, &protected).is_err());
+        assert!(ensure_removable(Path::new("/"), &[]).is_err());
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[tokio::test]
+    async fn remove_dir_except_keeps_the_nested_folder() {
+        let dir = std::env::temp_dir().join(format!("otoshi-remove-{}", uuid::Uuid::new_v4()));
+        let keep = dir.join("cache").join("depotcache");
+        std::fs::create_dir_all(keep.join("chunks")).unwrap();
+        std::fs::write(keep.join("chunks").join("a"), b"chunk").unwrap();
+        std::fs::write(dir.join("cache").join("other.bin"), b"x").unwrap();
+        std::fs::create_dir_all(dir.join("bin")).unwrap();
+        std::fs::write(dir.join("bin").join("game.exe"), b"x").unwrap();
+        std::fs::write(dir.join("readme.txt"), b"x").unwrap();
+
+        remove_dir_except(&dir, &keep).await.unwrap();
+
+        assert!(keep.join("chunks").join("a").is_file());
+        assert!(!dir.join("cache").join("other.bin").exists());
+        assert!(!dir.join("bin").exists());
+        assert!(!dir.join("readme.txt").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn manifest_file(file_id: &str, data: &[u8]) -> ManifestFile {
+        ManifestFile {
+            path: format!("{file_id}.bin"),
+            size: data.len() as u64,
+            hash: compute_sha256_hex(data),
+            file_id: file_id.to_string(),
+            chunks: Vec::new(),
+            volatile: false,
+        }
+    }
+
+    #[test]
+    fn incremental_hash_verifies_files_written_in_order() {
+        let ordered = b"0123456789abcdef".to_vec();
+        let shuffled = b"fedcba9876543210".to_vec();
+        let resumed = b"resumed-file-data".to_vec();
+        let hashes = IncrementalFileHashes::default();
+
+        hashes.record("ordered", 0, &ordered[..8]);
+        hashes.record("ordered", 8, &ordered[8..]);
+        hashes.record("shuffled", 8, &shuffled[8..]);
+        hashes.record("shuffled", 0, &shuffled[..8]);
+        // Only the tail was downloaded this session.
+        hashes.record("resumed", 8, &resumed[8..]);
+
+        let mut corrupt = manifest_file("corrupt", b"expected");
+        hashes.record("corrupt", 0, b"received");
+        corrupt.size = 8;
+
+        let verified = hashes.verified_files(&[
+            manifest_file("ordered", &ordered),
+            manifest_file("shuffled", &shuffled),
+            manifest_file("resumed", &resumed),
+            manifest_file("untouched", b"untouched"),
+            corrupt,
+        ]);
+        assert_eq!(verified, HashSet::from(["ordered".to_string()]));
+    }
+
+    fn archive_manifest(files: &[&str], archive_files: &[&str]) -> Manifest {
+        Manifest {
+            game_id: "game".to_string(),
+            slug: "game".to_string(),
+            version: "1".to_string(),
+            build_id: "1".to_string(),
+            chunk_size: 0,
+            total_size: 0,
+            compressed_size: 0,
+            files: files
+                .iter()
+                .map(|path| ManifestFile {
+                    path: path.to_string(),
+                    ..manifest_file(path, path.as_bytes())
+                })
+                .collect(),
+            install_mode: Some("archive_chunks".to_string()),
+            archive_dir: None,
+            archive_cleanup: false,
+            archive_files: archive_files.iter().map(|path| path.to_string()).collect(),
+            total_original_size: None,
+            post_install: None,
+            volatile_paths: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn repair_plans_leave_the_install_untouched() {
+        let install_dir =
+            std::env::temp_dir().join(format!("otoshi-repair-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&install_dir).unwrap();
+        let manifest = Manifest {
+            install_mode: None,
+            ..archive_manifest(&["bin/game.exe", "data.pak"], &[])
+        };
+        std::fs::create_dir_all(install_dir.join("bin")).unwrap();
+        std::fs::write(install_dir.join("bin/game.exe"), b"damaged").unwrap();
+
+        let (targets, stale, verified) = plan_repair_files(
+            &manifest,
+            &install_dir,
+            &["bin\\game.exe".to_string(), "gone.dll".to_string()],
+        );
+        assert_eq!(targets.files, 1);
+        assert_eq!(targets.bytes, manifest.files[0].size);
+        assert_eq!(targets.unknown_paths, vec!["gone.dll".to_string()]);
+        assert_eq!(
+            stale,
+            vec![
+                install_dir.join("bin/game.exe"),
+                partial_file_path(&install_dir, &manifest.files[0]),
+            ]
+        );
+        assert_eq!(verified, HashSet::from([manifest.files[1].file_id.clone()]));
+        // Deleting waits for the repair download to start.
+        assert!(install_dir.join("bin/game.exe").exists());
+        let _ = std::fs::remove_dir_all(&install_dir);
+    }
+
+    #[test]
+    fn repairs_request_the_installed_build() {
+        assert_eq!(
+            pinned_manifest_request_path("game", None, "1.2 beta", "42"),
+            format!(
+                "{}&version=1.2%20beta&build_id=42",
+                manifest_request_path("game", None)
+            )
+        );
+    }
+
+    fn planned_deletes(manifest: &Manifest, old: &Manifest) -> Vec<String> {
+        let install_dir = Path::new("install");
+        let plan = build_download_plan(
+            manifest,
+            install_dir,
+            &HashMap::new(),
+            Some(old),
+            &HashSet::new(),
+        )
+        .unwrap();
+        let mut deletes: Vec<String> = plan
+            .delete_files
+            .iter()
+            .map(|path| {
+                path.strip_prefix(install_dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        deletes.sort();
+        deletes.dedup();
+        deletes
+    }
+
+    fn chunked_manifest(url_prefix: &str) -> Manifest {
+        let chunks = (0..2u64)
+            .map(|index| ManifestChunk {
+                index,
+                hash: format!("hash-{index}"),
+                size: 4,
+                url: format!("{url_prefix}/chunk-{index}"),
+                fallback_urls: vec![format!("{url_prefix}-mirror/chunk-{index}")],
+                compression: default_compression(),
+            })
+            .collect();
+        Manifest {
+            game_id: "game".to_string(),
+            slug: "game".to_string(),
+            version: "1".to_string(),
+            build_id: "1".to_string(),
+            chunk_size: 4,
+            total_size: 8,
+            compressed_size: 8,
+            files: vec![ManifestFile {
+                path: "data.bin".to_string(),
+                size: 8,
+                hash: String::new(),
+                file_id: "data".to_string(),
+                chunks,
+                volatile: false,
+            }],
+            install_mode: None,
+            archive_dir: None,
+            archive_cleanup: false,
+            archive_files: Vec::new(),
+            total_original_size: None,
+            post_install: None,
+            volatile_paths: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn resume_with_changed_urls_keeps_progress() {
+        let install_dir =
+            std::env::temp_dir().join(format!("otoshi-resume-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&install_dir).unwrap();
+
+        // Chunk 0 finished before the pause.
+        let before = chunked_manifest("https://cdn.example/sig-old");
+        std::fs::write(partial_file_path(&install_dir, &before.files[0]), [0u8; 4]).unwrap();
+        let completed = HashMap::from([(("data".to_string(), 0), "hash-0".to_string())]);
+
+        // The resumed manifest carries freshly signed URLs.
+        let after = chunked_manifest("https://cdn.example/sig-new");
+        let plan =
+            build_download_plan(&after, &install_dir, &completed, None, &HashSet::new()).unwrap();
+        assert_eq!(plan.preexisting_bytes, 4);
+        assert_eq!(plan.precompleted_chunks.len(), 1);
+        assert_eq!(plan.chunks.len(), 1);
+        assert_eq!(plan.chunks[0].index, 1);
+        assert_eq!(plan.chunks[0].url, "https://cdn.example/sig-new/chunk-1");
+
+        // A job planned before the pause picks up the new URLs and keeps its
+        // peer source in front.
+        let stale_plan =
+            build_download_plan(&before, &install_dir, &completed, None, &HashSet::new()).unwrap();
+        let peer_url = "http://10.0.0.2:47800/chunks/hash-1".to_string();
+        let mut job = stale_plan.chunks[0].clone();
+        job.fallback_urls
+            .insert(0, std::mem::replace(&mut job.url, peer_url.clone()));
+        apply_refreshed_urls(&mut job, &refreshed_chunk_urls(&after));
+        assert_eq!(job.url, peer_url);
+        assert_eq!(
+            job.fallback_urls,
+            vec![
+                "https://cdn.example/sig-new/chunk-1".to_string(),
+                "https://cdn.example/sig-new-mirror/chunk-1".to_string(),
+            ]
+        );
+
+        // Changed content is never given another chunk's URLs.
+        let mut changed = after.clone();
+        changed.files[0].chunks[1].hash = "hash-other".to_string();
+        let mut job = stale_plan.chunks[0].clone();
+        apply_refreshed_urls(&mut job, &refreshed_chunk_urls(&changed));
+        assert_eq!(job.url, "https://cdn.example/sig-old/chunk-1");
+
+        let _ = std::fs::remove_dir_all(install_dir);
+    }
+
+    #[test]
+    fn archive_set_falls_back_to_archive_dir_files() {
+        let manifest =
+            archive_manifest(&[".chunks/a.zip", ".chunksextra/b.zip", "readme.txt"], &[]);
+        assert_eq!(
+            archive_file_set(&manifest),
+            HashSet::from([".chunks/a.zip".to_string()])
+        );
+    }
+
+    #[test]
+    fn empty_archive_files_still_cleans_stale_archives() {
+        let old = archive_manifest(&[".chunks/a.zip", ".chunks/b.zip"], &[]);
+        let new = archive_manifest(&[".chunks/a.zip"], &[]);
+        assert_eq!(
+            planned_deletes(&new, &old),
+            vec![".chunks/b.zip".to_string()]
+        );
+    }
+
+    #[test]
+    fn partial_archive_files_keep_paths_still_in_manifest() {
+        let old = archive_manifest(
+            &[".chunks/a.zip"],
+            &["bin/game.exe", "data/old.pak", ".chunks/a.zip"],
+        );
+        let new = archive_manifest(&[".chunks/a.zip"], &["bin/game.exe"]);
+        assert_eq!(
+            planned_deletes(&new, &old),
+            vec!["data/old.pak".to_string()]
+        );
+    }
+
+    #[test]
+    fn archive_installs_are_checked_against_their_extracted_files() {
+        let db = crate::db::open_temp();
+        let install_dir =
+            std::env::temp_dir().join(format!("otoshi-archive-check-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(install_dir.join("bin")).unwrap();
+        std::fs::create_dir_all(install_dir.join(".chunks")).unwrap();
+        std::fs::write(install_dir.join("bin/game.exe"), b"exe").unwrap();
+        std::fs::write(install_dir.join(".chunks/a.zip"), b"short").unwrap();
+        let write = |manifest: &Manifest| {
+            std::fs::write(
+                install_dir.join(MANIFEST_FILE),
+                serde_json::to_string(manifest).unwrap(),
+            )
+            .unwrap();
+        };
+        let check = || check_installed_sizes(&install_dir, &db).unwrap().1;
+
+        let mut manifest = archive_manifest(&[".chunks/a.zip"], &["bin/game.exe", ".chunks/a.zip"]);
+        manifest.archive_files.push("data/level.pak".to_string());
+        write(&manifest);
+        let found = check().unwrap();
+        assert_eq!(found.checked_files, 3);
+        assert_eq!(found.missing, vec!["data/level.pak".to_string()]);
+        assert_eq!(found.size_mismatches, vec![".chunks/a.zip".to_string()]);
+        assert!(found.unreadable.is_empty());
+
+        // Cleaned-up archives aren't expected to still be there.
+        std::fs::remove_file(install_dir.join(".chunks/a.zip")).unwrap();
+        manifest.archive_files.pop();
+        manifest.archive_cleanup = true;
+        write(&manifest);
+        let found = check().unwrap();
+        assert!(found.is_intact());
+        assert_eq!(found.checked_files, 1);
+
+        manifest.archive_files.clear();
+        write(&manifest);
+        let unlisted = check();
+        let _ = std::fs::remove_dir_all(&install_dir);
+        assert!(unlisted.is_none());
+    }
+
+    #[test]
+    fn only_completed_idle_downloads_give_way_to_a_found_install() {
+        let state = |status: &str| DownloadState {
+            id: "d1".to_string(),
+            game_id: "game".to_string(),
+            slug: "game".to_string(),
+            status: status.to_string(),
+            install_dir: String::new(),
+            manifest_json: String::new(),
+            updated_at: 0,
+            engine: None,
+            method: None,
+        };
+        assert!(!blocks_install_record(&state("completed"), false));
+        assert!(blocks_install_record(&state("completed"), true));
+        for status in ["paused", "queued", "failed", "downloading", "verifying"] {
+            assert!(blocks_install_record(&state(status), false), "{status}");
+        }
+    }
+
+    #[test]
+    fn incoherent_archive_manifests_are_rejected() {
+        let no_archives = archive_manifest(&["bin/game.exe"], &["bin/game.exe"]);
+        let err = validate_archive_manifest(&no_archives).unwrap_err();
+        assert!(err.to_string().contains("no files under archive_dir"));
+
+        let mut empty_dir = archive_manifest(&[".chunks/a.zip"], &[]);
+        empty_dir.archive_dir = Some("  ".to_string());
+        assert!(validate_archive_manifest(&empty_dir).is_err());
+
+        let mut escaping = archive_manifest(&["../chunks/a.zip"], &[]);
+        escaping.archive_dir = Some("../chunks".to_string());
+        assert!(validate_archive_manifest(&escaping).is_err());
+
+        let unsafe_entry = archive_manifest(&[".chunks/a.zip"], &["../../evil.dll"]);
+        assert!(validate_archive_manifest(&unsafe_entry).is_err());
+
+        let ok = archive_manifest(&[".chunks/a.zip"], &["bin/game.exe"]);
+        assert!(validate_archive_manifest(&ok).is_ok());
+    }
+
+    #[test]
+    fn zip_extraction_skips_escaping_names_and_rejects_symlinks() {
+        assert!(is_safe_relative_path(Path::new("bin/game.exe")));
+        assert!(!is_safe_relative_path(Path::new("../escape.dll")));
+        assert!(!is_safe_relative_path(Path::new("/etc/passwd")));
+        assert!(!is_safe_relative_path(Path::new("C:/Windows/evil.dll")));
+
+        let root = std::env::temp_dir().join(format!("otoshi-zip-slip-{}", uuid::Uuid::new_v4()));
+        let install_dir = root.join("install");
+        std::fs::create_dir_all(&install_dir).unwrap();
+        let archive_path = root.join("a.zip");
+        std::fs::write(
+            &archive_path,
+            archive::build_test_zip(&[
+                ("bin/game.exe", "game", None),
+                ("../escape.dll", "evil", None),
+                ("/abs.dll", "evil", None),
+                ("C:/Windows/evil.dll", "evil", None),
+            ]),
+        )
+        .unwrap();
+        extract_zip_archive(&archive_path, &install_dir).unwrap();
+        assert!(install_dir.join("bin/game.exe").is_file());
+        assert!(!root.join("escape.dll").exists());
+        assert!(!install_dir.join("abs.dll").exists());
+        assert!(!install_dir.join("C:").exists());
+
+        std::fs::write(
+            &archive_path,
+            archive::build_test_zip(&[("bin/link", "", Some("../../etc/passwd"))]),
+        )
+        .unwrap();
+        let result = extract_zip_archive(&archive_path, &install_dir);
+        let _ = std::fs::remove_dir_all(&root);
+        assert!(matches!(result, Err(LauncherError::Integrity(_))));
+    }
+
+    #[test]
+    fn archives_sharing_outputs_extract_in_one_lane() {
+        let root = std::env::temp_dir().join(format!("otoshi-lanes-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        for (name, path) in [
+            ("a.zip", "bin/game.exe"),
+            ("b.zip", "data/one.pak"),
+            ("c.zip", "BIN/Game.exe"),
+        ] {
+            let zip = archive::build_test_zip(&[(path, name, None)]);
+            std::fs::write(root.join(name), zip).unwrap();
+        }
+        let archives = ["a.zip", "b.zip", "c.zip"].map(String::from);
+        let lanes = extraction_lanes(&root, &archives);
+        let _ = std::fs::remove_dir_all(&root);
+        assert_eq!(
+            lanes.unwrap(),
+            vec![
+                vec!["a.zip".to_string(), "c.zip".to_string()],
+                vec!["b.zip".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn received_bytes_drain_once_per_source() {
+        let received = ReceivedBytes::default();
+        let worker = received.clone();
+        // A retried chunk counts both attempts.
+        worker.add(ChunkSource::Cdn, 700);
+        worker.add(ChunkSource::Cdn, 1_000);
+        worker.add(ChunkSource::Peer, 300);
+
+        let mut totals = BandwidthUsageTotals {
+            depotcache_bytes: 50,
+            ..BandwidthUsageTotals::default()
+        };
+        received.drain_into(&mut totals);
+        assert_eq!(
+            (
+                totals.network_bytes,
+                totals.peer_bytes,
+                totals.depotcache_bytes
+            ),
+            (1_700, 300, 50)
+        );
+
+        received.drain_into(&mut totals);
+        assert_eq!((totals.network_bytes, totals.peer_bytes), (1_700, 300));
+    }
+
+    #[tokio::test]
+    async fn finalized_files_take_their_planned_size() {
+        let dir = std::env::temp_dir().join(format!("otoshi-finalize-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let plans: Vec<FilePlan> = [("short.bin", 2_u64), ("long.bin", 8)]
+            .into_iter()
+            .map(|(name, size)| {
+                let final_path = dir.join(name);
+                let temp_path = final_path.with_extension("part");
+                FilePlan {
+                    final_path,
+                    temp_path,
+                    size,
+                }
+            })
+            .collect();
+        std::fs::write(&plans[0].temp_path, b"a").unwrap();
+        std::fs::write(&plans[1].temp_path, b"newbytes-stale-tail").unwrap();
+
+        finalize_files(&plans).await.unwrap();
+
+        assert_eq!(std::fs::read(dir.join("short.bin")).unwrap(), b"a\0");
+        assert_eq!(std::fs::read(dir.join("long.bin")).unwrap(), b"newbytes");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn host_limiter_caps_each_host_separately() {
+        let limits = HostConnectionLimiter::new(2, true);
+        let first = limits.acquire("https://cdn-a.example/c/1").await;
+        let second = limits.acquire("https://CDN-A.example/c/2").await;
+        assert!(first.is_some() && second.is_some());
+
+        let third = tokio::time::timeout(
+            Duration::from_millis(50),
+            limits.acquire("https://cdn-a.example/c/3"),
+        )
+        .await;
+        assert!(third.is_err(), "a third request on the host must wait");
+        // Other hosts and ports have their own slots; host-less URLs none.
+        assert!(limits.acquire("https://cdn-b.example/c/1").await.is_some());
+        assert!(limits
+            .acquire("https://cdn-a.example:8443/c/1")
+            .await
+            .is_some());
+        assert!(limits.acquire("not a url").await.is_none());
+
+        drop(first);
+        assert!(limits.acquire("https://cdn-a.example/c/3").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn host_limiter_backs_off_under_pressure_and_recovers() {
+        let limits = HostConnectionLimiter::new(3, true);
+        let held = limits.acquire("https://cdn.example/c/1").await;
+        drop(held);
+        limits.tighten("cdn.example", "rate_limited");
+        limits.tighten("cdn.example", "rate_limited");
+        limits.tighten("cdn.example", "rate_limited");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let available = |limits: &HostConnectionLimiter| {
+            let hosts = limits.hosts.lock().unwrap();
+            let slot = &hosts["cdn.example"];
+            (slot.target_reserved, slot.semaphore.available_permits())
+        };
+        // Never below one slot.
+        assert_eq!(available(&limits), (2, 1));
+
+        {
+            let mut hosts = limits.hosts.lock().unwrap();
+            let slot = hosts.get_mut("cdn.example").unwrap();
+            slot.last_pressure = Some(Instant::now() - Duration::from_secs(5));
+            slot.last_relax = Instant::now() - Duration::from_secs(3);
+        }
+        limits.relax();
+        assert_eq!(available(&limits), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn host_limit_follows_chunk_concurrency_unless_pinned() {
+        let limits = HostConnectionLimiter::new(per_host_default(8), false);
+        assert!(limits.limit() >= resolve_method_concurrency("auto", 8));
+        let held = limits.acquire("https://cdn.example/c/1").await;
+        let before = limits.limit();
+
+        limits.follow_concurrency(32);
+        let raised = limits.limit();
+        assert!(raised > before);
+        assert_eq!(
+            limits.hosts.lock().unwrap()["cdn.example"]
+                .semaphore
+                .available_permits(),
+            raised - 1
+        );
+        limits.follow_concurrency(4);
+        assert_eq!(limits.limit(), raised);
+        drop(held);
+
+        let pinned = HostConnectionLimiter::new(4, true);
+        pinned.follow_concurrency(32);
+        assert_eq!(pinned.limit(), 4);
+    }
+
+    #[tokio::test]
+    async fn merged_chunks_behind_a_failed_one_still_reach_disk() {
+        let root = std::env::temp_dir().join(format!("otoshi-merge-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let part = root.join("game.pak.part");
+        let jobs: Vec<ChunkJob> = (0..3)
+            .map(|index| ChunkJob {
+                file_id: "pak".to_string(),
+                temp_path: part.clone(),
+                index,
+                offset: index * 10,
+                size: 10,
+                hash: format!("chunk-{index}"),
+                url: String::new(),
+                fallback_urls: Vec::new(),
+                compression: "none".to_string(),
+            })
+            .collect();
+        let writer = ChunkWriter::new(
+            WriteStrategy::SequentialMerge,
+            &jobs,
+            u64::MAX,
+            IncrementalFileHashes::default(),
+            DiskIoCounters::default(),
+        );
+        let (tx, mut rx) = mpsc::channel(8);
+        let success = |job: &ChunkJob| ChunkResult::Success {
+            file_id: job.file_id.clone(),
+            chunk_index: job.index,
+            size: job.size,
+            hash: job.hash.clone(),
+            accounted_bytes: job.size,
+            source: ChunkSource::Cdn,
+        };
+
+        for job in &jobs[1..] {
+            let data = vec![job.index as u8; 10];
+            writer.submit(job, data, success(job), &tx).await.unwrap();
+        }
+        // Both wait on the first chunk's offset.
+        assert!(rx.try_recv().is_err());
+
+        writer.abandon(&jobs[0], &tx).await.unwrap();
+        let mut flushed = Vec::new();
+        while let Ok(ChunkResult::Success { chunk_index, .. }) = rx.try_recv() {
+            flushed.push(chunk_index);
+        }
+        let bytes = std::fs::read(&part).unwrap();
+        let buffered = writer.merge.lock().await.buffered_bytes;
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(flushed, vec![1, 2]);
+        assert_eq!(buffered, 0);
+        assert_eq!(&bytes[10..], &[[1_u8; 10], [2_u8; 10]].concat()[..]);
+    }
+
+    #[test]
+    fn aria2_readouts_parse_completed_bytes() {
+        assert_eq!(
+            parse_aria2_progress("[#2089b0 1.5MiB/4.0MiB(37%) CN:2 DL:3.1MiB ETA:1s]"),
+            Some(3 << 19)
+        );
+        assert_eq!(parse_aria2_progress(" [#2089b0 0B/0B CN:1 DL:0B]"), Some(0));
+        assert_eq!(parse_aria2_progress("FILE: /tmp/chunk.part"), None);
+        assert_eq!(parse_aria2_size("12KiB"), Some(12 * 1024));
+    }
+}
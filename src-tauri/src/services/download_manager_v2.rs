@@ -1,15 +1,14 @@
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
-use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::path::{Component, Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use futures_util::StreamExt;
+use reqwest::{Method, StatusCode};
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
 use tokio::time::sleep;
 use uuid::Uuid;
 
@@ -17,20 +16,11 @@ use crate::db::queries::{DownloadQueries, DownloadStateQueries};
 use crate::db::Database;
 use crate::errors::{LauncherError, Result};
 use crate::models::LocalDownload;
-use crate::services::{DownloadManager, DownloadService};
+use crate::services::{patch_engine, ApiClient, DownloadManager, DownloadService};
 
 const XDELTA_MIN_BYTES: i64 = 64 * 1024 * 1024;
+const XDELTA_PATCH_TIMEOUT_SECS: u64 = 30 * 60;
 const PIPELINE_POLL_MS: u64 = 750;
-#[cfg(target_os = "windows")]
-const CREATE_NO_WINDOW: u32 = 0x08000000;
-
-#[inline]
-fn hide_console_window(command: &mut Command) {
-    #[cfg(target_os = "windows")]
-    {
-        command.creation_flags(CREATE_NO_WINDOW);
-    }
-}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -74,6 +64,9 @@ pub struct DownloadSessionV2 {
     pub stage: String,
     pub install_path: Option<String>,
     pub xdelta_mode: String,
+    /// Transfer engine recorded by the runtime for `download_id`.
+    #[serde(default)]
+    pub engine: Option<String>,
     #[serde(default)]
     pub telemetry: DownloadTelemetryV2,
     pub created_at: i64,
@@ -121,20 +114,32 @@ struct XdeltaPatchEntry {
     expected_sha256: Option<String>,
     #[serde(default)]
     expected_size: Option<u64>,
+    /// Where to fetch the patch from when the plan comes from the API.
+    #[serde(default, alias = "patch_url")]
+    url: Option<String>,
+    #[serde(default, alias = "patch_sha256")]
+    patch_sha256: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct DownloadManagerV2 {
     inner: DownloadManager,
+    api: ApiClient,
     downloads_api: DownloadService,
     db: Database,
     sessions: Arc<Mutex<HashMap<String, DownloadSessionV2>>>,
 }
 
 impl DownloadManagerV2 {
-    pub fn new(inner: DownloadManager, downloads_api: DownloadService, db: Database) -> Self {
+    pub fn new(
+        inner: DownloadManager,
+        api: ApiClient,
+        downloads_api: DownloadService,
+        db: Database,
+    ) -> Self {
         Self {
             inner,
+            api,
             downloads_api,
             db,
             sessions: Arc::new(Mutex::new(HashMap::new())),
@@ -173,6 +178,7 @@ impl DownloadManagerV2 {
             stage: "manifest_fetch".to_string(),
             install_path: request.install_path.clone(),
             xdelta_mode: Self::resolve_xdelta_mode(request.expected_file_bytes),
+            engine: None,
             telemetry: DownloadTelemetryV2::default(),
             created_at: now,
             updated_at: now,
@@ -251,7 +257,9 @@ impl DownloadManagerV2 {
                 session.stage = "chunk_transfer".to_string();
             }
             "cancel" => {
-                self.inner.cancel_download(&session.download_id).await?;
+                self.inner
+                    .cancel_download(&session.download_id, true)
+                    .await?;
                 let _ = self.downloads_api.cancel_download(&session.download_id).await;
                 session.status = "cancelled".to_string();
                 session.stage = "cancelled".to_string();
@@ -279,7 +287,7 @@ impl DownloadManagerV2 {
             .get(session_id)
             .cloned()
         {
-            return Ok(Some(value));
+            return Ok(Some(self.with_engine(value)));
         }
 
         let conn = self.db.connection()?;
@@ -317,6 +325,7 @@ impl DownloadManagerV2 {
                     stage: row.get(8)?,
                     install_path: row.get(9)?,
                     xdelta_mode,
+                    engine: None,
                     telemetry,
                     created_at: row.get(11)?,
                     updated_at: row.get(12)?,
@@ -327,7 +336,14 @@ impl DownloadManagerV2 {
         if let Some(item) = &session {
             self.cache_session(item)?;
         }
-        Ok(session)
+        Ok(session.map(|item| self.with_engine(item)))
+    }
+
+    fn with_engine(&self, mut session: DownloadSessionV2) -> DownloadSessionV2 {
+        if let Ok(Some(saved)) = self.db.get_download_state(&session.download_id) {
+            session.engine = saved.engine;
+        }
+        session
     }
 
     async fn run_session_pipeline(&self, session_id: &str) -> Result<()> {
@@ -403,7 +419,7 @@ impl DownloadManagerV2 {
             return Ok(());
         }
 
-        if !Self::has_xdelta3() {
+        if !patch_engine::xdelta3_available() {
             telemetry.xdelta_fallback_reason = Some("xdelta3_unavailable".to_string());
             telemetry.updated_at = chrono::Utc::now().timestamp();
             self.update_telemetry(session_id, telemetry)?;
@@ -420,10 +436,24 @@ impl DownloadManagerV2 {
             }
         };
 
-        let plan = match self.resolve_xdelta_plan(&install_root)? {
-            Some(plan) => plan,
-            None => {
-                telemetry.xdelta_fallback_reason = Some("xdelta_plan_missing".to_string());
+        let (plan, fetched_patches) = match self.fetch_xdelta_plan(&session, &install_root).await {
+            Ok(Some(fetched)) => fetched,
+            Ok(None) => match self.resolve_xdelta_plan(&install_root)? {
+                Some(plan) => (plan, Vec::new()),
+                None => {
+                    telemetry.xdelta_fallback_reason = Some("xdelta_plan_missing".to_string());
+                    telemetry.updated_at = chrono::Utc::now().timestamp();
+                    self.update_telemetry(session_id, telemetry)?;
+                    return Ok(());
+                }
+            },
+            Err(err) => {
+                tracing::warn!(
+                    "xdelta patch fetch failed for session {} (fallback to chunk result): {}",
+                    session_id,
+                    err
+                );
+                telemetry.xdelta_fallback_reason = Some(format!("patch_fetch_failed: {}", err));
                 telemetry.updated_at = chrono::Utc::now().timestamp();
                 self.update_telemetry(session_id, telemetry)?;
                 return Ok(());
@@ -462,12 +492,125 @@ impl DownloadManagerV2 {
             }
         }
 
+        remove_files(&fetched_patches);
+
         telemetry.xdelta_duration_ms = start.elapsed().as_millis() as u64;
         telemetry.updated_at = chrono::Utc::now().timestamp();
         self.update_telemetry(session_id, telemetry)?;
         Ok(())
     }
 
+    /// Fetches the session's xdelta plan and its patches from the API. `None`
+    /// means the API has no plan for this build; any failed or mismatched
+    /// patch removes what was fetched so far and returns the error.
+    async fn fetch_xdelta_plan(
+        &self,
+        session: &DownloadSessionV2,
+        install_root: &Path,
+    ) -> Result<Option<(XdeltaPlan, Vec<PathBuf>)>> {
+        let path = format!(
+            "manifests/{}/xdelta?version={}",
+            session.slug,
+            urlencoding::encode(&session.version)
+        );
+        let response = self
+            .api
+            .raw_request(Method::GET, &path, true)
+            .await?
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(LauncherError::Http(format!(
+                "HTTP {}: xdelta plan for {}",
+                response.status(),
+                session.slug
+            )));
+        }
+        let plan: XdeltaPlan = response.json().await?;
+
+        let mut fetched = Vec::with_capacity(plan.patches.len());
+        for patch in &plan.patches {
+            match self.fetch_xdelta_patch(install_root, patch).await {
+                Ok(path) => fetched.push(path),
+                Err(err) => {
+                    remove_files(&fetched);
+                    return Err(err);
+                }
+            }
+        }
+        tracing::info!(
+            "fetched {} xdelta patches for {} {}",
+            fetched.len(),
+            session.slug,
+            session.version
+        );
+        Ok(Some((plan, fetched)))
+    }
+
+    async fn fetch_xdelta_patch(
+        &self,
+        install_root: &Path,
+        patch: &XdeltaPatchEntry,
+    ) -> Result<PathBuf> {
+        let url = patch
+            .url
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| {
+                LauncherError::Config(format!("xdelta patch {} has no url", patch.patch))
+            })?;
+        let expected = patch
+            .patch_sha256
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| {
+                LauncherError::Config(format!("xdelta patch {} has no hash", patch.patch))
+            })?;
+        for raw in [&patch.source, &patch.patch, &patch.output] {
+            if !is_contained_plan_path(raw) {
+                return Err(LauncherError::Config(format!(
+                    "xdelta plan path escapes the install dir: {}",
+                    raw
+                )));
+            }
+        }
+
+        let request = if url.starts_with("http://") || url.starts_with("https://") {
+            self.api.client().get(url)
+        } else {
+            self.api.raw_request(Method::GET, url, true).await?
+        };
+        let response = request
+            .timeout(Duration::from_secs(XDELTA_PATCH_TIMEOUT_SECS))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(LauncherError::Http(format!(
+                "HTTP {}: xdelta patch {}",
+                response.status(),
+                patch.patch
+            )));
+        }
+
+        let patch_path = resolve_plan_path(install_root, &patch.patch);
+        if let Some(parent) = patch_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let part_path = patch_path.with_extension("part");
+        let written = write_patch_stream(response, &part_path, expected).await;
+        if let Err(err) = written {
+            let _ = tokio::fs::remove_file(&part_path).await;
+            return Err(err);
+        }
+        tokio::fs::rename(&part_path, &patch_path).await?;
+        Ok(patch_path)
+    }
+
     fn resolve_install_root(&self, session: &DownloadSessionV2) -> Result<Option<PathBuf>> {
         if let Some(path) = session
             .install_path
@@ -541,55 +684,12 @@ impl DownloadManagerV2 {
                 std::fs::create_dir_all(parent)?;
             }
 
-            let mut command = Command::new("xdelta3");
-            hide_console_window(&mut command);
-            let status = command
-                .args([
-                    "-f",
-                    "-d",
-                    "-s",
-                    source_path.to_string_lossy().as_ref(),
-                    patch_path.to_string_lossy().as_ref(),
-                    output_path.to_string_lossy().as_ref(),
-                ])
-                .status()
-                .map_err(|err| LauncherError::Config(format!("failed to execute xdelta3: {err}")))?;
-            if !status.success() {
-                return Err(LauncherError::Config(format!(
-                    "xdelta3 non-zero exit for output {} (status={})",
-                    output_path.display(),
-                    status
-                )));
-            }
-
-            if let Some(expected_size) = patch.expected_size {
-                let size = std::fs::metadata(&output_path)?.len();
-                if size != expected_size {
-                    return Err(LauncherError::Config(format!(
-                        "xdelta output size mismatch {} expected={} actual={}",
-                        output_path.display(),
-                        expected_size,
-                        size
-                    )));
-                }
-            }
-
-            if let Some(expected_hash) = patch
-                .expected_sha256
-                .as_ref()
-                .map(|value| value.trim().to_ascii_lowercase())
-                .filter(|value| !value.is_empty())
-            {
-                let actual = hash_sha256_file(&output_path)?;
-                if actual != expected_hash {
-                    return Err(LauncherError::Config(format!(
-                        "xdelta output hash mismatch {} expected={} actual={}",
-                        output_path.display(),
-                        expected_hash,
-                        actual
-                    )));
-                }
-            }
+            patch_engine::apply_xdelta(&source_path, &patch_path, &output_path)?;
+            patch_engine::verify_output(
+                &output_path,
+                patch.expected_size,
+                patch.expected_sha256.as_deref(),
+            )?;
 
             if let Some(target_raw) = patch
                 .target
@@ -770,20 +870,52 @@ impl DownloadManagerV2 {
         if bytes < XDELTA_MIN_BYTES {
             return "chunk_only".to_string();
         }
-        if !Self::has_xdelta3() {
+        if !patch_engine::xdelta3_available() {
             return "chunk_only".to_string();
         }
         "chunk_plus_xdelta".to_string()
     }
+}
+
+async fn write_patch_stream(
+    response: reqwest::Response,
+    path: &Path,
+    expected: &str,
+) -> Result<()> {
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut hasher = Sha256::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+
+    let actual = hex::encode(hasher.finalize());
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(LauncherError::Integrity(format!(
+            "xdelta patch hash mismatch {} expected={} actual={}",
+            path.display(),
+            expected,
+            actual
+        )));
+    }
+    Ok(())
+}
+
+/// Plans from the API may only name paths inside the install dir.
+fn is_contained_plan_path(raw: &str) -> bool {
+    let path = Path::new(raw.trim());
+    !raw.trim().is_empty()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
 
-    fn has_xdelta3() -> bool {
-        let mut command = Command::new("xdelta3");
-        hide_console_window(&mut command);
-        command
-            .arg("-V")
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false)
+fn remove_files(paths: &[PathBuf]) {
+    for path in paths {
+        let _ = std::fs::remove_file(path);
     }
 }
 
@@ -795,22 +927,6 @@ fn resolve_plan_path(install_root: &Path, raw: &str) -> PathBuf {
     install_root.join(path)
 }
 
-fn hash_sha256_file(path: &Path) -> Result<String> {
-    use sha2::{Digest, Sha256};
-
-    let mut file = File::open(path)?;
-    let mut hasher = Sha256::new();
-    let mut buffer = vec![0_u8; 1024 * 1024];
-    loop {
-        let read = file.read(&mut buffer)?;
-        if read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..read]);
-    }
-    Ok(hex::encode(hasher.finalize()))
-}
-
 trait OptionalRowExt<T> {
     fn optional(self) -> rusqlite::Result<Option<T>>;
 }
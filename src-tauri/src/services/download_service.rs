@@ -8,7 +8,9 @@ pub struct DownloadProgressUpdate {
     pub downloaded_bytes: Option<i64>,
     pub total_bytes: Option<i64>,
     pub network_bps: Option<i64>,
+    /// Measured from depotcache reads during hydration.
     pub disk_read_bps: Option<i64>,
+    /// Measured from chunks flushed to `.part` files and the depotcache.
     pub disk_write_bps: Option<i64>,
     pub read_bytes: Option<i64>,
     pub written_bytes: Option<i64>,
@@ -53,7 +55,7 @@ impl DownloadService {
 
     pub async fn pause_download(&self, download_id: &str) -> Result<DownloadTask> {
         self.api
-            .post(
+            .post_idempotent(
                 &format!("downloads/{}/pause", download_id),
                 serde_json::json!({}),
                 true,
@@ -63,7 +65,7 @@ impl DownloadService {
 
     pub async fn resume_download(&self, download_id: &str) -> Result<DownloadTask> {
         self.api
-            .post(
+            .post_idempotent(
                 &format!("downloads/{}/resume", download_id),
                 serde_json::json!({}),
                 true,
@@ -73,7 +75,7 @@ impl DownloadService {
 
     pub async fn cancel_download(&self, download_id: &str) -> Result<DownloadTask> {
         self.api
-            .post(
+            .post_idempotent(
                 &format!("downloads/{}/cancel", download_id),
                 serde_json::json!({}),
                 true,
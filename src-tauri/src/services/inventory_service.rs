@@ -1,56 +1,493 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::errors::Result;
+use crate::db::queries::{InventoryCacheQueries, SettingsQueries};
+use crate::db::Database;
+use crate::errors::{LauncherError, Result};
+use crate::models::UserProfile;
 use crate::services::ApiClient;
 
+const SYNC_TTL: Duration = Duration::from_secs(30);
+const ITEM_KIND: &str = "item";
+const TRADE_KIND: &str = "trade";
+const CACHE_SYNCED_SETTING: &str = "inventory_cache_synced_at";
+const TRADE_EXPIRY_SETTING: &str = "trade_expiry_hours";
+const DEFAULT_TRADE_EXPIRY_HOURS: u32 = 72;
+const MAX_TRADE_EXPIRY_HOURS: u32 = 720;
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Clone)]
 pub struct InventoryService {
     api: ApiClient,
+    db: Database,
+    sync_cache: Arc<Mutex<SyncCache>>,
 }
 
 impl InventoryService {
-    pub fn new(api: ApiClient) -> Self {
-        Self { api }
+    pub fn new(api: ApiClient, db: Database) -> Self {
+        Self {
+            api,
+            db,
+            sync_cache: Arc::new(Mutex::new(SyncCache::default())),
+        }
+    }
+
+    /// Inventory, pending trades and unclaimed drops in one refresh. Uses the
+    /// batched backend endpoint when it exists and otherwise fetches the three
+    /// lists concurrently. Results are reused for `SYNC_TTL` unless `force`.
+    pub async fn sync_all(&self, force: bool) -> Result<InventorySnapshot> {
+        if !force {
+            if let Some(snapshot) = self.cache()?.fresh(Instant::now(), SYNC_TTL) {
+                return Ok(snapshot);
+            }
+        }
+
+        let snapshot = match self
+            .api
+            .get::<InventorySnapshot>("/inventory/sync", true)
+            .await
+        {
+            Ok(snapshot) => snapshot.with_pending_trades(),
+            Err(err) if is_missing_endpoint(&err) => {
+                let (inventory, trades, drops) = tokio::try_join!(
+                    self.fetch_inventory(),
+                    self.fetch_trades(),
+                    self.fetch_drops(),
+                )?;
+                InventorySnapshot::combine(inventory, trades, drops)
+            }
+            Err(err) => return Err(err),
+        };
+        self.cache()?.store(Instant::now(), snapshot.clone());
+        if let Err(err) = self.store_cached(ITEM_KIND, &snapshot.inventory, |item| &item.id) {
+            tracing::warn!("failed to cache inventory items: {err}");
+        }
+        Ok(snapshot)
+    }
+
+    /// What crafting `badge_id` would consume and whether the user has it all.
+    /// Owned counts come from the cached inventory snapshot.
+    pub async fn preview_craft(&self, badge_id: &str) -> Result<CraftPreview> {
+        let path = format!(
+            "/inventory/badges/{}/requirements",
+            urlencoding::encode(badge_id)
+        );
+        let requirements: Vec<BadgeRequirement> = self.api.get(&path, true).await?;
+        let snapshot = self.sync_all(false).await?;
+        Ok(CraftPreview::compute(
+            badge_id,
+            &requirements,
+            &snapshot.inventory,
+        ))
+    }
+
+    /// Whether the user owns every card `badge_id` needs.
+    pub async fn can_craft_badge(&self, badge_id: &str) -> Result<bool> {
+        Ok(self.preview_craft(badge_id).await?.can_craft)
     }
 
+    fn cache(&self) -> Result<std::sync::MutexGuard<'_, SyncCache>> {
+        self.sync_cache
+            .lock()
+            .map_err(|_| LauncherError::Config("inventory cache locked".to_string()))
+    }
+
+    fn invalidate_sync(&self) {
+        if let Ok(mut cache) = self.cache() {
+            cache.invalidate();
+        }
+    }
+
+    /// Cached items, or a server fetch the first time.
     pub async fn list_inventory(&self) -> Result<Vec<InventoryItem>> {
-        self.api.get("/inventory", true).await
+        if self.cache_synced()? {
+            return self.cached(ITEM_KIND);
+        }
+        Ok(self.refresh_inventory().await?.inventory)
+    }
+
+    /// Cached trades, or a server fetch the first time. Trades the server
+    /// sent without an expiry get one from the configured window.
+    pub async fn list_trades(&self) -> Result<Vec<TradeOffer>> {
+        let trades = if self.cache_synced()? {
+            self.cached(TRADE_KIND)?
+        } else {
+            self.refresh_inventory().await?.trades
+        };
+        let window = self.trade_expiry();
+        Ok(trades
+            .into_iter()
+            .map(|trade| trade.with_expiry(window))
+            .collect())
+    }
+
+    /// How long a trade offer stays open, in hours.
+    pub fn trade_expiry_hours(&self) -> u32 {
+        self.db
+            .get_setting(TRADE_EXPIRY_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|value| value.trim().parse::<u32>().ok())
+            .map(|hours| hours.clamp(1, MAX_TRADE_EXPIRY_HOURS))
+            .unwrap_or(DEFAULT_TRADE_EXPIRY_HOURS)
+    }
+
+    pub fn set_trade_expiry_hours(&self, hours: u32) -> Result<u32> {
+        if !(1..=MAX_TRADE_EXPIRY_HOURS).contains(&hours) {
+            return Err(LauncherError::Config(format!(
+                "trade expiry must be between 1 and {MAX_TRADE_EXPIRY_HOURS} hours"
+            )));
+        }
+        self.db
+            .set_setting(TRADE_EXPIRY_SETTING, &hours.to_string())?;
+        Ok(hours)
+    }
+
+    fn trade_expiry(&self) -> ChronoDuration {
+        ChronoDuration::hours(i64::from(self.trade_expiry_hours()))
+    }
+
+    /// Closes pending trades once they pass their expiry, checking the
+    /// cached trades every minute. `on_expired` gets each closed trade.
+    pub fn spawn_expiry_watcher<F>(&self, on_expired: F)
+    where
+        F: Fn(&TradeOffer) + Send + Sync + 'static,
+    {
+        let service = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(EXPIRY_CHECK_INTERVAL).await;
+                match service.expire_trades().await {
+                    Ok(expired) => {
+                        for trade in &expired {
+                            on_expired(trade);
+                        }
+                    }
+                    Err(err) => tracing::debug!("trade expiry check failed: {err}"),
+                }
+            }
+        });
+    }
+
+    /// Cancels the user's own offers and declines received ones among the
+    /// cached pending trades past their expiry. A trade the server call fails
+    /// for stays pending, so the next sweep tries it again.
+    async fn expire_trades(&self) -> Result<Vec<TradeOffer>> {
+        if !self.cache_synced()? {
+            return Ok(Vec::new());
+        }
+        let trades: Vec<TradeOffer> = self.cached(TRADE_KIND)?;
+        let due = expired_trades(trades, Utc::now(), self.trade_expiry());
+        if due.is_empty() {
+            return Ok(Vec::new());
+        }
+        let user: UserProfile = self.api.get("/auth/me", true).await?;
+        let mut expired = Vec::new();
+        for trade in due {
+            let closed = if trade.sent_by(&user.id) {
+                self.cancel_trade(&trade.id).await
+            } else {
+                self.decline_trade(&trade.id).await
+            };
+            match closed {
+                Ok(closed) => {
+                    tracing::info!("trade {} expired", closed.id);
+                    expired.push(closed.with_expiry(self.trade_expiry()));
+                }
+                Err(err) => tracing::warn!("failed to close expired trade {}: {err}", trade.id),
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Replaces the local item and trade cache with the server's state.
+    pub async fn refresh_inventory(&self) -> Result<CachedInventory> {
+        let (inventory, trades) = tokio::try_join!(self.fetch_inventory(), self.fetch_trades())?;
+        self.store_cached(ITEM_KIND, &inventory, |item| &item.id)?;
+        self.store_cached(TRADE_KIND, &trades, |trade| &trade.id)?;
+        let synced_at = Utc::now().timestamp();
+        self.db
+            .set_setting(CACHE_SYNCED_SETTING, &synced_at.to_string())?;
+        self.invalidate_sync();
+        Ok(CachedInventory {
+            inventory,
+            trades,
+            synced_at,
+        })
+    }
+
+    /// Drops cached inventory so the next listing fetches from the server.
+    pub fn clear_cache(&self) -> Result<()> {
+        self.db.clear_inventory_cache()?;
+        self.db.delete_setting(CACHE_SYNCED_SETTING)?;
+        self.invalidate_sync();
+        Ok(())
     }
 
     pub async fn card_drop(&self, game_id: &str) -> Result<InventoryItem> {
+        let placeholder = InventoryItem {
+            id: format!("pending-drop-{}", Uuid::new_v4()),
+            user_id: String::new(),
+            game_id: Some(game_id.to_string()),
+            item_type: "card".to_string(),
+            name: String::new(),
+            rarity: String::new(),
+            quantity: 1,
+            metadata: serde_json::json!({ "pending": true }),
+            created_at: Utc::now().to_rfc3339(),
+        };
+        let mut undo = CacheUndo::default();
+        self.stage(&mut undo, ITEM_KIND, &placeholder.id, Some(&placeholder))?;
+
         let path = format!("/inventory/cards/drop/{}", game_id);
-        self.api.post(&path, serde_json::json!({}), true).await
+        let item: InventoryItem = self
+            .reconcile(undo, self.api.post(&path, serde_json::json!({}), true))
+            .await?;
+        self.db.remove_inventory_cache(ITEM_KIND, &placeholder.id)?;
+        self.put_cached(ITEM_KIND, &item.id, &item)?;
+        self.invalidate_sync();
+        Ok(item)
     }
 
+    /// The materials a craft consumes are only known server-side, so the
+    /// cache is resynced afterwards instead of updated optimistically. A
+    /// game's badge shares its id, so an incomplete card set is refused
+    /// before the craft request is sent.
     pub async fn craft_badge(&self, game_id: &str) -> Result<InventoryItem> {
+        match self.preview_craft(game_id).await {
+            Ok(preview) if !preview.can_craft => {
+                let missing = preview.missing_count();
+                return Err(LauncherError::Config(format!(
+                    "missing {} card{} to craft this badge",
+                    missing,
+                    if missing == 1 { "" } else { "s" }
+                )));
+            }
+            Ok(_) => {}
+            Err(LauncherError::Http(message)) if message.starts_with("HTTP 404") => {
+                tracing::debug!("no badge requirements for {game_id}; crafting unchecked");
+            }
+            Err(err) => return Err(err),
+        }
         let path = format!("/inventory/badges/craft/{}", game_id);
-        self.api.post(&path, serde_json::json!({}), true).await
+        let item: InventoryItem = self.api.post(&path, serde_json::json!({}), true).await?;
+        self.put_cached(ITEM_KIND, &item.id, &item)?;
+        self.resync_after_change().await;
+        Ok(item)
     }
 
-    pub async fn list_trades(&self) -> Result<Vec<TradeOffer>> {
-        self.api.get("/inventory/trades", true).await
-    }
+    pub async fn create_trade(&self, mut request: TradeOfferRequest) -> Result<TradeOffer> {
+        let created_at = Utc::now();
+        let expires_at = request
+            .expires_at
+            .clone()
+            .unwrap_or_else(|| (created_at + self.trade_expiry()).to_rfc3339());
+        request.expires_at = Some(expires_at.clone());
+        let placeholder = TradeOffer {
+            id: format!("pending-trade-{}", Uuid::new_v4()),
+            from_user_id: String::new(),
+            to_user_id: request.to_user_id.clone(),
+            offered_item_ids: request.offered_item_ids.clone(),
+            requested_item_ids: request.requested_item_ids.clone(),
+            status: "pending".to_string(),
+            created_at: created_at.to_rfc3339(),
+            expires_at: Some(expires_at.clone()),
+        };
+        let mut undo = CacheUndo::default();
+        self.stage(&mut undo, TRADE_KIND, &placeholder.id, Some(&placeholder))?;
 
-    pub async fn create_trade(&self, request: TradeOfferRequest) -> Result<TradeOffer> {
-        self.api.post("/inventory/trades", request, true).await
+        let mut trade: TradeOffer = self
+            .reconcile(undo, self.api.post("/inventory/trades", request, true))
+            .await?;
+        trade.expires_at.get_or_insert(expires_at);
+        self.db
+            .remove_inventory_cache(TRADE_KIND, &placeholder.id)?;
+        self.put_cached(TRADE_KIND, &trade.id, &trade)?;
+        self.invalidate_sync();
+        Ok(trade)
     }
 
+    /// Accepting hands over the requested items right away; what the user
+    /// receives is picked up by the resync once the server confirms.
     pub async fn accept_trade(&self, trade_id: &str) -> Result<TradeOffer> {
-        let path = format!("/inventory/trades/{}/accept", trade_id);
-        self.api.post(&path, serde_json::json!({}), true).await
+        let trade = self
+            .transition_trade(trade_id, "accept", "accepted")
+            .await?;
+        self.resync_after_change().await;
+        Ok(trade)
     }
 
     pub async fn decline_trade(&self, trade_id: &str) -> Result<TradeOffer> {
-        let path = format!("/inventory/trades/{}/decline", trade_id);
-        self.api.post(&path, serde_json::json!({}), true).await
+        self.transition_trade(trade_id, "decline", "declined").await
     }
 
     pub async fn cancel_trade(&self, trade_id: &str) -> Result<TradeOffer> {
-        let path = format!("/inventory/trades/{}/cancel", trade_id);
-        self.api.post(&path, serde_json::json!({}), true).await
+        self.transition_trade(trade_id, "cancel", "cancelled").await
+    }
+
+    async fn transition_trade(
+        &self,
+        trade_id: &str,
+        action: &str,
+        status: &str,
+    ) -> Result<TradeOffer> {
+        let mut undo = CacheUndo::default();
+        if let Some(mut cached) = self.cached_entry::<TradeOffer>(TRADE_KIND, trade_id)? {
+            cached.status = status.to_string();
+            if action == "accept" {
+                for item_id in &cached.requested_item_ids {
+                    self.stage::<InventoryItem>(&mut undo, ITEM_KIND, item_id, None)?;
+                }
+            }
+            self.stage(&mut undo, TRADE_KIND, trade_id, Some(&cached))?;
+        }
+
+        let path = format!("/inventory/trades/{}/{}", trade_id, action);
+        let trade: TradeOffer = self
+            .reconcile(undo, self.api.post(&path, serde_json::json!({}), true))
+            .await?;
+        self.put_cached(TRADE_KIND, &trade.id, &trade)?;
+        self.invalidate_sync();
+        Ok(trade)
+    }
+
+    /// Waits for the server call and rolls the staged cache changes back if
+    /// it fails.
+    async fn reconcile<T>(
+        &self,
+        undo: CacheUndo,
+        request: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        match request.await {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                if let Err(rollback_err) = self.rollback(undo) {
+                    tracing::warn!("failed to roll back inventory cache: {rollback_err}");
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Writes `value` (or removes the entry when `None`) and remembers what
+    /// was there before.
+    fn stage<T: Serialize>(
+        &self,
+        undo: &mut CacheUndo,
+        kind: &'static str,
+        id: &str,
+        value: Option<&T>,
+    ) -> Result<()> {
+        let previous = self.db.get_inventory_cache_entry(kind, id)?;
+        undo.entries.push((kind, id.to_string(), previous));
+        match value {
+            Some(value) => self.put_cached(kind, id, value),
+            None => self.db.remove_inventory_cache(kind, id),
+        }
+    }
+
+    fn rollback(&self, undo: CacheUndo) -> Result<()> {
+        for (kind, id, previous) in undo.entries.into_iter().rev() {
+            match previous {
+                Some(payload) => self.db.upsert_inventory_cache(kind, &id, &payload)?,
+                None => self.db.remove_inventory_cache(kind, &id)?,
+            }
+        }
+        Ok(())
+    }
+
+    async fn resync_after_change(&self) {
+        if let Err(err) = self.refresh_inventory().await {
+            tracing::warn!("inventory resync failed: {err}");
+            self.invalidate_sync();
+        }
+    }
+
+    async fn fetch_inventory(&self) -> Result<Vec<InventoryItem>> {
+        self.api.get("/inventory", true).await
+    }
+
+    async fn fetch_trades(&self) -> Result<Vec<TradeOffer>> {
+        self.api.get("/inventory/trades", true).await
+    }
+
+    /// Unclaimed drops; none on a backend without the endpoint.
+    async fn fetch_drops(&self) -> Result<Vec<InventoryItem>> {
+        match self.api.get("/inventory/drops", true).await {
+            Err(err) if is_missing_endpoint(&err) => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    fn cache_synced(&self) -> Result<bool> {
+        Ok(self.db.get_setting(CACHE_SYNCED_SETTING)?.is_some())
+    }
+
+    fn cached<T: DeserializeOwned>(&self, kind: &str) -> Result<Vec<T>> {
+        let mut values = Vec::new();
+        for payload in self.db.list_inventory_cache(kind)? {
+            match serde_json::from_str(&payload) {
+                Ok(value) => values.push(value),
+                Err(err) => tracing::warn!("skipping unreadable cached {kind}: {err}"),
+            }
+        }
+        Ok(values)
+    }
+
+    fn cached_entry<T: DeserializeOwned>(&self, kind: &str, id: &str) -> Result<Option<T>> {
+        match self.db.get_inventory_cache_entry(kind, id)? {
+            Some(payload) => Ok(Some(serde_json::from_str(&payload)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn put_cached<T: Serialize>(&self, kind: &str, id: &str, value: &T) -> Result<()> {
+        let payload = serde_json::to_string(value)?;
+        self.db.upsert_inventory_cache(kind, id, &payload)
+    }
+
+    fn store_cached<T: Serialize>(
+        &self,
+        kind: &str,
+        values: &[T],
+        id_of: impl Fn(&T) -> &String,
+    ) -> Result<()> {
+        let mut entries = Vec::with_capacity(values.len());
+        for value in values {
+            entries.push((id_of(value).clone(), serde_json::to_string(value)?));
+        }
+        self.db.replace_inventory_cache(kind, &entries)
+    }
+}
+
+/// A 404 from the backend, i.e. an endpoint this backend doesn't have.
+fn is_missing_endpoint(err: &LauncherError) -> bool {
+    match err {
+        LauncherError::Http(message) => message.starts_with("HTTP 404"),
+        LauncherError::NotFound(_) => true,
+        _ => false,
     }
 }
 
+/// Cache entries as they were before an optimistic update.
+#[derive(Default)]
+struct CacheUndo {
+    entries: Vec<(&'static str, String, Option<String>)>,
+}
+
+/// Local inventory cache contents after a server sync.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CachedInventory {
+    pub inventory: Vec<InventoryItem>,
+    pub trades: Vec<TradeOffer>,
+    pub synced_at: i64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct InventoryItem {
     pub id: String,
@@ -76,9 +513,320 @@ pub struct TradeOffer {
     pub expires_at: Option<String>,
 }
 
+impl TradeOffer {
+    /// `expires_at`, or `created_at` plus `window` when the server sent none.
+    fn expiry(&self, window: ChronoDuration) -> Option<DateTime<Utc>> {
+        let parse = |value: &str| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|time| time.with_timezone(&Utc))
+        };
+        match self.expires_at.as_deref() {
+            Some(expires_at) => parse(expires_at),
+            None => parse(&self.created_at).map(|created_at| created_at + window),
+        }
+    }
+
+    /// Whether `user_id` made the offer, so it is cancelled rather than
+    /// declined.
+    fn sent_by(&self, user_id: &str) -> bool {
+        self.from_user_id == user_id
+    }
+
+    fn with_expiry(mut self, window: ChronoDuration) -> Self {
+        if self.expires_at.is_none() {
+            self.expires_at = self.expiry(window).map(|time| time.to_rfc3339());
+        }
+        self
+    }
+}
+
+/// Pending trades whose expiry is at or before `now`.
+fn expired_trades(
+    trades: Vec<TradeOffer>,
+    now: DateTime<Utc>,
+    window: ChronoDuration,
+) -> Vec<TradeOffer> {
+    trades
+        .into_iter()
+        .filter(|trade| trade.status.eq_ignore_ascii_case("pending"))
+        .filter(|trade| !trade.id.starts_with("pending-trade-"))
+        .filter(|trade| trade.expiry(window).is_some_and(|expiry| expiry <= now))
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InventorySnapshot {
+    pub inventory: Vec<InventoryItem>,
+    #[serde(default)]
+    pub pending_trades: Vec<TradeOffer>,
+    #[serde(default)]
+    pub unclaimed_drops: Vec<InventoryItem>,
+}
+
+impl InventorySnapshot {
+    pub fn combine(
+        inventory: Vec<InventoryItem>,
+        trades: Vec<TradeOffer>,
+        unclaimed_drops: Vec<InventoryItem>,
+    ) -> Self {
+        Self {
+            inventory,
+            pending_trades: trades,
+            unclaimed_drops,
+        }
+        .with_pending_trades()
+    }
+
+    fn with_pending_trades(mut self) -> Self {
+        self.pending_trades
+            .retain(|trade| trade.status.eq_ignore_ascii_case("pending"));
+        self
+    }
+}
+
+/// One material a badge needs. `game_id` narrows the match to cards from a
+/// specific game when set.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BadgeRequirement {
+    pub item_type: String,
+    pub name: String,
+    #[serde(default)]
+    pub game_id: Option<String>,
+    pub quantity: i32,
+}
+
+impl BadgeRequirement {
+    fn matches(&self, item: &InventoryItem) -> bool {
+        item.item_type.eq_ignore_ascii_case(&self.item_type)
+            && item.name == self.name
+            && self
+                .game_id
+                .as_deref()
+                .map_or(true, |game_id| item.game_id.as_deref() == Some(game_id))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CraftRequirementStatus {
+    pub requirement: BadgeRequirement,
+    pub owned: i32,
+    pub missing: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CraftPreview {
+    pub badge_id: String,
+    pub requirements: Vec<CraftRequirementStatus>,
+    pub can_craft: bool,
+}
+
+impl CraftPreview {
+    pub fn compute(
+        badge_id: &str,
+        requirements: &[BadgeRequirement],
+        inventory: &[InventoryItem],
+    ) -> Self {
+        let requirements = requirements
+            .iter()
+            .map(|requirement| {
+                let owned = inventory
+                    .iter()
+                    .filter(|item| requirement.matches(item))
+                    .map(|item| item.quantity.max(0))
+                    .sum::<i32>();
+                CraftRequirementStatus {
+                    requirement: requirement.clone(),
+                    owned,
+                    missing: (requirement.quantity - owned).max(0),
+                }
+            })
+            .collect::<Vec<_>>();
+        let can_craft = requirements.iter().all(|status| status.missing == 0);
+        Self {
+            badge_id: badge_id.to_string(),
+            requirements,
+            can_craft,
+        }
+    }
+
+    /// Cards still needed across all requirements.
+    pub fn missing_count(&self) -> i32 {
+        self.requirements.iter().map(|status| status.missing).sum()
+    }
+}
+
+#[derive(Default)]
+struct SyncCache {
+    entry: Option<(Instant, InventorySnapshot)>,
+}
+
+impl SyncCache {
+    fn fresh(&self, now: Instant, ttl: Duration) -> Option<InventorySnapshot> {
+        self.entry
+            .as_ref()
+            .filter(|(fetched_at, _)| now.saturating_duration_since(*fetched_at) < ttl)
+            .map(|(_, snapshot)| snapshot.clone())
+    }
+
+    fn store(&mut self, now: Instant, snapshot: InventorySnapshot) {
+        self.entry = Some((now, snapshot));
+    }
+
+    fn invalidate(&mut self) {
+        self.entry = None;
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TradeOfferRequest {
     pub to_user_id: String,
     pub offered_item_ids: Vec<String>,
     pub requested_item_ids: Vec<String>,
+    /// Filled in from the expiry setting when not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str) -> InventoryItem {
+        InventoryItem {
+            id: id.to_string(),
+            user_id: "user".to_string(),
+            game_id: Some("game".to_string()),
+            item_type: "card".to_string(),
+            name: id.to_string(),
+            rarity: "common".to_string(),
+            quantity: 1,
+            metadata: serde_json::json!({}),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    fn trade(id: &str, status: &str) -> TradeOffer {
+        TradeOffer {
+            id: id.to_string(),
+            from_user_id: "a".to_string(),
+            to_user_id: "b".to_string(),
+            offered_item_ids: vec![],
+            requested_item_ids: vec![],
+            status: status.to_string(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn craft_preview_compares_owned_against_required() {
+        let mut card_a = item("card-a");
+        card_a.name = "Card A".to_string();
+        card_a.quantity = 2;
+        let mut card_a_other_game = card_a.clone();
+        card_a_other_game.game_id = Some("other".to_string());
+        let mut card_b = item("card-b");
+        card_b.name = "Card B".to_string();
+
+        let requirements = vec![
+            BadgeRequirement {
+                item_type: "card".to_string(),
+                name: "Card A".to_string(),
+                game_id: Some("game".to_string()),
+                quantity: 2,
+            },
+            BadgeRequirement {
+                item_type: "card".to_string(),
+                name: "Card B".to_string(),
+                game_id: None,
+                quantity: 3,
+            },
+        ];
+        let inventory = vec![card_a, card_a_other_game, card_b.clone()];
+
+        let preview = CraftPreview::compute("badge-1", &requirements, &inventory);
+        assert!(!preview.can_craft);
+        assert_eq!(preview.requirements[0].owned, 2);
+        assert_eq!(preview.requirements[0].missing, 0);
+        assert_eq!(preview.requirements[1].owned, 1);
+        assert_eq!(preview.requirements[1].missing, 2);
+        assert_eq!(preview.missing_count(), 2);
+
+        card_b.quantity = 3;
+        let inventory = vec![inventory[0].clone(), card_b];
+        assert!(CraftPreview::compute("badge-1", &requirements, &inventory).can_craft);
+    }
+
+    #[test]
+    fn pending_trades_expire_after_the_window() {
+        let window = ChronoDuration::hours(72);
+        let now = DateTime::parse_from_rfc3339("2024-01-04T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut explicit = trade("explicit", "pending");
+        explicit.expires_at = Some("2024-01-05T00:00:00Z".to_string());
+        let trades = vec![
+            trade("old", "pending"),
+            trade("done", "accepted"),
+            explicit.clone(),
+        ];
+
+        let expired = expired_trades(trades.clone(), now, window);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, "old");
+        assert!(expired_trades(trades, now - ChronoDuration::seconds(1), window).is_empty());
+        assert_eq!(
+            trade("t", "pending")
+                .with_expiry(window)
+                .expires_at
+                .as_deref(),
+            Some("2024-01-04T00:00:00+00:00")
+        );
+        assert_eq!(explicit.with_expiry(window).expires_at, explicit.expires_at);
+        assert!(explicit.sent_by("a"));
+        assert!(!explicit.sent_by("b"));
+    }
+
+    #[test]
+    fn only_a_404_counts_as_a_missing_endpoint() {
+        assert!(is_missing_endpoint(&LauncherError::Http(
+            "HTTP 404: Not Found".to_string()
+        )));
+        assert!(is_missing_endpoint(&LauncherError::NotFound(
+            "/inventory/drops".to_string()
+        )));
+        assert!(!is_missing_endpoint(&LauncherError::Http(
+            "HTTP 500: boom".to_string()
+        )));
+        assert!(!is_missing_endpoint(&LauncherError::Auth(
+            "expired".to_string()
+        )));
+    }
+
+    #[test]
+    fn combined_refresh_keeps_pending_trades_and_cache_is_reused() {
+        let snapshot = InventorySnapshot::combine(
+            vec![item("card-1"), item("badge-1")],
+            vec![trade("t1", "pending"), trade("t2", "accepted")],
+            vec![item("drop-1")],
+        );
+        assert_eq!(snapshot.inventory.len(), 2);
+        assert_eq!(snapshot.pending_trades.len(), 1);
+        assert_eq!(snapshot.pending_trades[0].id, "t1");
+        assert_eq!(snapshot.unclaimed_drops[0].id, "drop-1");
+
+        let ttl = Duration::from_secs(30);
+        let start = Instant::now();
+        let mut cache = SyncCache::default();
+        assert!(cache.fresh(start, ttl).is_none());
+
+        cache.store(start, snapshot);
+        let reused = cache.fresh(start + Duration::from_secs(10), ttl).unwrap();
+        assert_eq!(reused.inventory.len(), 2);
+        assert!(cache.fresh(start + ttl, ttl).is_none());
+
+        cache.invalidate();
+        assert!(cache.fresh(start, ttl).is_none());
+    }
 }
@@ -1,3 +1,5 @@
+use crate::db::queries::LibraryCacheQueries;
+use crate::db::Database;
 use crate::errors::Result;
 use crate::models::{Game, LibraryEntry};
 use crate::services::ApiClient;
@@ -5,15 +7,34 @@ use crate::services::ApiClient;
 #[derive(Clone)]
 pub struct LibraryService {
     api: ApiClient,
+    db: Database,
 }
 
 impl LibraryService {
-    pub fn new(api: ApiClient) -> Self {
-        Self { api }
+    pub fn new(api: ApiClient, db: Database) -> Self {
+        Self { api, db }
     }
 
+    /// The user's library. While the backend is unreachable the last fetched
+    /// copy is returned instead, if there is one.
     pub async fn get_library(&self) -> Result<Vec<LibraryEntry>> {
-        self.api.get("library", true).await
+        match self.api.get::<Vec<LibraryEntry>>("library", true).await {
+            Ok(library) => {
+                let fetched_at = chrono::Utc::now().timestamp();
+                if let Err(err) = self.db.replace_library_cache(&library, fetched_at) {
+                    tracing::warn!("failed to cache library: {}", err);
+                }
+                Ok(library)
+            }
+            Err(err) if !self.api.is_online() => match self.db.get_cached_library() {
+                Ok(library) if !library.is_empty() => {
+                    tracing::info!("backend offline; serving cached library");
+                    Ok(library)
+                }
+                _ => Err(err),
+            },
+            Err(err) => Err(err),
+        }
     }
 
     pub async fn get_games(&self) -> Result<Vec<Game>> {
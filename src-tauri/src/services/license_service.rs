@@ -4,13 +4,44 @@ use rsa::pkcs1v15::{Signature, VerifyingKey};
 use rsa::pkcs8::DecodePublicKey;
 use rsa::signature::Verifier;
 use rsa::RsaPublicKey;
+use serde::Serialize;
 use sha2::{Digest, Sha256};
 use sysinfo::System;
 
 use crate::errors::{LauncherError, Result};
 use crate::models::LicenseInfo;
+use crate::services::ApiClient;
 
 const DEFAULT_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----\nMIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA0Wf13/yMzpLYcdCa2QKk\n00wf0ehHks1iOtdFcK4ErkF38sESIIpteFqNvSYGImO4YE2N1nGiAnzQYlza4Gnt\niEQm9Smdi8ePlu4gwBOOGJLiBFMS9QNW3KXZ4+lNsYETuY9MGrzdEjiMsk+87fAZ\nhdIDCT9ojkFMeUQGRl/r5HK5FB3eUs6OkUJA1GK60NTsjsPljRye1xxGnMm29K6S\neMGf42ICyA08hEcwtk/goDst9LM/l92IXrPxVjzT7OCeKiQiLTHfW74Hgh6vHFlo\nhkYAs0dEEcs0tmAtqBTKThDC+VHZkFA2wLWJtr6q11d1JxJxkG+EyyHynso3UM+0\nOQIDAQAB\n-----END PUBLIC KEY-----";
+const HARDWARE_ID_PREFIX: &str = "hw3";
+/// Parts of the machine the hardware ID is made of, in ID order, and how much
+/// each counts when telling a changed machine from a different one. Nothing
+/// that changes in normal use (kernel updates, plugged-in or added drives) is
+/// part of it.
+const HARDWARE_COMPONENTS: [(&str, u32); 4] = [("cpu", 3), ("host", 2), ("memory", 1), ("os", 1)];
+/// The earlier component ID, which also hashed disk sizes and the kernel
+/// version into `disks` and `os`. Only its other components still compare.
+const HW2_PREFIX: &str = "hw2";
+const HW2_COMPONENTS: [&str; 5] = ["cpu", "host", "disks", "memory", "os"];
+const HW2_COMPARABLE: [&str; 3] = ["cpu", "host", "memory"];
+/// Most component weight that may drift before a license has to be
+/// activated again instead of rebound: a new CPU, or a reinstall that also
+/// renamed the machine.
+const MAX_REBIND_DRIFT_WEIGHT: u32 = 3;
+
+/// How this machine compares with the hardware a license is bound to.
+#[derive(Clone, Debug, Serialize)]
+pub struct HardwareDrift {
+    pub changed: bool,
+    /// Components that no longer match. Empty for a license bound with the
+    /// older single-hash ID, which can't be compared part by part.
+    pub drifted: Vec<String>,
+    pub drifted_weight: u32,
+    pub total_weight: u32,
+    /// Few enough parts changed for `request_rebind` to move the license.
+    pub rebindable: bool,
+    pub current_hardware_id: String,
+}
 
 #[derive(Clone)]
 pub struct LicenseService {
@@ -25,33 +56,62 @@ impl LicenseService {
         }
     }
 
+    /// `hw3-` followed by a short hash per entry of `HARDWARE_COMPONENTS`.
     pub fn get_hardware_id(&self) -> String {
-        let mut sys = System::new_all();
-        sys.refresh_all();
-
-        let mut parts: Vec<String> = vec![
-            System::name().unwrap_or_default(),
-            System::kernel_version().unwrap_or_default(),
-            System::host_name().unwrap_or_default(),
-        ];
-        if let Some(cpu) = sys.cpus().first() {
-            parts.push(cpu.brand().to_string());
-        }
+        MachineInfo::probe().hardware_id()
+    }
 
-        parts.retain(|item| !item.is_empty());
-        let payload = parts.join("|");
+    pub fn validate_license(&self, license_json: &str) -> Result<LicenseInfo> {
+        self.validate(serde_json::from_str(license_json)?, &MachineInfo::probe())
+    }
 
-        let mut hasher = Sha256::new();
-        hasher.update(payload.as_bytes());
-        hex::encode(hasher.finalize())
+    /// Compares this machine with the hardware the signed license is bound to.
+    pub fn hardware_id_changed(&self, license_json: &str) -> Result<HardwareDrift> {
+        let license: LicenseInfo = serde_json::from_str(license_json)?;
+        self.verify_signature(&license)?;
+        Ok(hardware_drift(&license, &MachineInfo::probe()))
     }
 
-    pub fn validate_license(&self, license_json: &str) -> Result<LicenseInfo> {
+    /// Asks the server to bind the license to this machine, which it allows
+    /// while no more than `MAX_REBIND_DRIFT_WEIGHT` of the hardware changed.
+    /// Returns the license as it is now, validated.
+    pub async fn request_rebind(&self, api: &ApiClient, license_json: &str) -> Result<LicenseInfo> {
         let license: LicenseInfo = serde_json::from_str(license_json)?;
+        self.verify_signature(&license)?;
+        let machine = MachineInfo::probe();
+        let drift = hardware_drift(&license, &machine);
+        if !drift.changed {
+            return self.validate(license, &machine);
+        }
+        if !drift.rebindable {
+            return Err(LauncherError::Crypto(format!(
+                "hardware changed too much to rebind ({}); activate the license again",
+                if drift.drifted.is_empty() {
+                    "unknown components".to_string()
+                } else {
+                    drift.drifted.join(", ")
+                }
+            )));
+        }
+        let rebound: LicenseInfo = api
+            .post(
+                &format!("/licenses/{}/rebind", license.license_id),
+                serde_json::json!({
+                    "hardware_id": drift.current_hardware_id,
+                    "previous_hardware_id": license.hardware_id,
+                    "drifted": drift.drifted,
+                }),
+                true,
+            )
+            .await?;
+        self.validate(rebound, &machine)
+    }
+
+    fn validate(&self, license: LicenseInfo, machine: &MachineInfo) -> Result<LicenseInfo> {
         self.verify_signature(&license)?;
         self.verify_expiration(&license)?;
         self.verify_activation(&license)?;
-        self.verify_hardware(&license)?;
+        self.verify_hardware(&license, machine)?;
         Ok(license)
     }
 
@@ -91,13 +151,243 @@ impl LicenseService {
         Ok(())
     }
 
-    fn verify_hardware(&self, license: &LicenseInfo) -> Result<()> {
-        if let Some(hardware_id) = &license.hardware_id {
-            let local = self.get_hardware_id();
-            if local != *hardware_id {
-                return Err(LauncherError::Crypto("hardware mismatch".to_string()));
-            }
+    /// Accepts a machine that drifted no further than a rebind would allow,
+    /// the same bound the server applies.
+    fn verify_hardware(&self, license: &LicenseInfo, machine: &MachineInfo) -> Result<()> {
+        let drift = hardware_drift(license, machine);
+        if drift.changed && !drift.rebindable {
+            return Err(LauncherError::Crypto("hardware mismatch".to_string()));
+        }
+        if drift.changed {
+            tracing::info!(
+                "license {} accepted with drifted hardware: {}",
+                license.license_id,
+                drift.drifted.join(", ")
+            );
         }
         Ok(())
     }
 }
+
+/// What the hardware ID is computed from, read from the system once.
+#[derive(Clone, Debug, Default)]
+struct MachineInfo {
+    cpu_brand: String,
+    cpu_count: usize,
+    host: String,
+    memory_bytes: u64,
+    os_name: String,
+    kernel: String,
+}
+
+impl MachineInfo {
+    fn probe() -> Self {
+        let sys = System::new_all();
+        Self {
+            cpu_brand: sys
+                .cpus()
+                .first()
+                .map(|cpu| cpu.brand().to_string())
+                .unwrap_or_default(),
+            cpu_count: sys.cpus().len(),
+            host: System::host_name().unwrap_or_default(),
+            memory_bytes: sys.total_memory(),
+            os_name: System::name().unwrap_or_default(),
+            kernel: System::kernel_version().unwrap_or_default(),
+        }
+    }
+
+    /// Short hash per entry of `HARDWARE_COMPONENTS`, in ID order.
+    fn component_hashes(&self) -> Vec<String> {
+        let gib = 1024 * 1024 * 1024;
+        let cpu = if self.cpu_brand.is_empty() {
+            String::new()
+        } else {
+            format!("{}|{}", self.cpu_brand, self.cpu_count)
+        };
+        let values = [
+            cpu,
+            self.host.clone(),
+            ((self.memory_bytes + gib / 2) / gib).to_string(),
+            self.os_name.clone(),
+        ];
+        HARDWARE_COMPONENTS
+            .iter()
+            .zip(values)
+            .map(|((name, _), value)| component_hash(name, &value))
+            .collect()
+    }
+
+    fn hardware_id(&self) -> String {
+        std::iter::once(HARDWARE_ID_PREFIX.to_string())
+            .chain(self.component_hashes())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// The ID licenses were bound to before it was split into components.
+    fn legacy_hardware_id(&self) -> String {
+        let mut parts = vec![
+            self.os_name.clone(),
+            self.kernel.clone(),
+            self.host.clone(),
+            self.cpu_brand.clone(),
+        ];
+        parts.retain(|item| !item.is_empty());
+        hex::encode(Sha256::digest(parts.join("|").as_bytes()))
+    }
+}
+
+fn component_hash(name: &str, value: &str) -> String {
+    hex::encode(Sha256::digest(format!("{name}:{value}").as_bytes()))[..8].to_string()
+}
+
+/// How `machine` compares with the hardware `license` is bound to.
+fn hardware_drift(license: &LicenseInfo, machine: &MachineInfo) -> HardwareDrift {
+    let current = machine.hardware_id();
+    let total_weight = HARDWARE_COMPONENTS.iter().map(|(_, weight)| weight).sum();
+    let mut drift = HardwareDrift {
+        changed: false,
+        drifted: Vec::new(),
+        drifted_weight: 0,
+        total_weight,
+        rebindable: false,
+        current_hardware_id: current.clone(),
+    };
+    let Some(bound) = license.hardware_id.as_deref() else {
+        return drift;
+    };
+    if bound == current || bound == machine.legacy_hardware_id() {
+        return drift;
+    }
+    drift.drifted_weight = total_weight;
+    match drifted_components(bound, &current) {
+        Some(drifted) => {
+            drift.drifted_weight = drifted.iter().map(|(_, weight)| weight).sum();
+            drift.drifted = drifted
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect();
+            drift.changed = !drift.drifted.is_empty();
+            drift.rebindable = drift.drifted_weight <= MAX_REBIND_DRIFT_WEIGHT;
+        }
+        None => drift.changed = true,
+    }
+    drift
+}
+
+/// Components whose hash differs between a bound ID and the current one;
+/// `None` when the bound ID isn't a component ID. A `hw2` ID only compares
+/// the components it hashed the same way.
+fn drifted_components(bound: &str, current: &str) -> Option<Vec<(&'static str, u32)>> {
+    let mut bound_parts = bound.split('-');
+    let prefix = bound_parts.next()?;
+    let bound_hashes: Vec<&str> = bound_parts.collect();
+    let bound_by_name: Vec<(&str, &str)> = match prefix {
+        HARDWARE_ID_PREFIX if bound_hashes.len() == HARDWARE_COMPONENTS.len() => {
+            HARDWARE_COMPONENTS
+                .iter()
+                .map(|(name, _)| *name)
+                .zip(bound_hashes)
+                .collect()
+        }
+        HW2_PREFIX if bound_hashes.len() == HW2_COMPONENTS.len() => HW2_COMPONENTS
+            .into_iter()
+            .zip(bound_hashes)
+            .filter(|(name, _)| HW2_COMPARABLE.contains(name))
+            .collect(),
+        _ => return None,
+    };
+    let current_hashes: Vec<&str> = current
+        .strip_prefix(HARDWARE_ID_PREFIX)?
+        .strip_prefix('-')?
+        .split('-')
+        .collect();
+    Some(
+        HARDWARE_COMPONENTS
+            .iter()
+            .zip(current_hashes)
+            .filter(|((name, _), current)| {
+                bound_by_name
+                    .iter()
+                    .any(|(bound_name, bound)| bound_name == name && bound != current)
+            })
+            .map(|(component, _)| *component)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machine() -> MachineInfo {
+        MachineInfo {
+            cpu_brand: "Ryzen 7 5800X".to_string(),
+            cpu_count: 16,
+            host: "desk".to_string(),
+            memory_bytes: 32 * 1024 * 1024 * 1024,
+            os_name: "Windows".to_string(),
+            kernel: "10.0.22631".to_string(),
+        }
+    }
+
+    fn license_for(hardware_id: &str) -> LicenseInfo {
+        serde_json::from_value(serde_json::json!({
+            "license_id": "lic",
+            "user_id": "user",
+            "game_id": "game",
+            "issued_at": "2026-01-01T00:00:00Z",
+            "expires_at": null,
+            "max_activations": 2,
+            "current_activations": 1,
+            "hardware_id": hardware_id,
+            "signature": "",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn drift_is_reported_per_component() {
+        let bound = "hw3-aaaaaaaa-bbbbbbbb-dddddddd-eeeeeeee";
+        let new_cpu = "hw3-11111111-bbbbbbbb-dddddddd-eeeeeeee";
+        let drifted = drifted_components(bound, new_cpu).unwrap();
+        assert_eq!(drifted, [("cpu", 3)]);
+        assert!(drifted_components(bound, bound).unwrap().is_empty());
+        assert!(drifted_components(&"0".repeat(64), new_cpu).is_none());
+
+        // A hw2 ID ignores its disk and kernel-bearing components.
+        let hw2 = "hw2-aaaaaaaa-bbbbbbbb-cccccccc-dddddddd-ffffffff";
+        assert!(drifted_components(hw2, bound).unwrap().is_empty());
+        assert_eq!(drifted_components(hw2, new_cpu).unwrap(), [("cpu", 3)]);
+    }
+
+    #[test]
+    fn kernel_updates_and_small_drift_keep_the_license_valid() {
+        let bound = machine();
+        let license = license_for(&bound.hardware_id());
+
+        let updated = MachineInfo {
+            kernel: "10.0.26100".to_string(),
+            ..machine()
+        };
+        assert!(!hardware_drift(&license, &updated).changed);
+
+        let renamed = MachineInfo {
+            host: "new-desk".to_string(),
+            os_name: "Windows 11".to_string(),
+            ..machine()
+        };
+        let drift = hardware_drift(&license, &renamed);
+        assert!(drift.changed && drift.rebindable);
+        assert_eq!(drift.drifted_weight, 3);
+
+        let other = MachineInfo {
+            cpu_brand: "Core i9".to_string(),
+            host: "laptop".to_string(),
+            ..machine()
+        };
+        let drift = hardware_drift(&license, &other);
+        assert!(drift.changed && !drift.rebindable);
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+use reqwest::header::RANGE;
+
+const MIRROR_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Measures CDN host latency once per session and orders chunk URLs so the
+/// fastest reachable mirror is tried first.
+#[derive(Clone)]
+pub struct MirrorRanker {
+    client: reqwest::Client,
+    latencies: Arc<Mutex<HashMap<String, Option<Duration>>>>,
+}
+
+impl MirrorRanker {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            latencies: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Probes every host in `urls` that has not been measured yet, using the
+    /// first URL seen for that host as the probe target.
+    pub async fn probe_hosts<'a, I>(&self, urls: I)
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut targets: HashMap<String, String> = HashMap::new();
+        if let Ok(locked) = self.latencies.lock() {
+            for url in urls {
+                let Some(key) = mirror_host_key(url) else {
+                    continue;
+                };
+                if !locked.contains_key(&key) {
+                    targets.entry(key).or_insert_with(|| url.to_string());
+                }
+            }
+        }
+        if targets.is_empty() {
+            return;
+        }
+
+        let probes = targets.into_iter().map(|(key, url)| async move {
+            let latency = probe_mirror(&self.client, &url).await;
+            (key, latency)
+        });
+        let results = join_all(probes).await;
+        if let Ok(mut locked) = self.latencies.lock() {
+            for (key, latency) in results {
+                match latency {
+                    Some(value) => {
+                        tracing::debug!("mirror {} latency {}ms", key, value.as_millis())
+                    }
+                    None => tracing::debug!("mirror {} probe failed", key),
+                }
+                locked.insert(key, latency);
+            }
+        }
+    }
+
+    /// Stable-sorts `urls` by measured latency. Unmeasured hosts keep their
+    /// position behind measured ones; hosts that failed the probe go last.
+    pub fn rank_urls(&self, urls: Vec<String>) -> Vec<String> {
+        let Ok(locked) = self.latencies.lock() else {
+            return urls;
+        };
+        let mut keyed = urls
+            .into_iter()
+            .map(|url| {
+                let rank = match mirror_host_key(&url).and_then(|key| locked.get(&key).copied()) {
+                    Some(Some(latency)) => (0, latency),
+                    None => (1, Duration::ZERO),
+                    Some(None) => (2, Duration::ZERO),
+                };
+                (rank, url)
+            })
+            .collect::<Vec<_>>();
+        keyed.sort_by_key(|(rank, _)| *rank);
+        keyed.into_iter().map(|(_, url)| url).collect()
+    }
+}
+
+async fn probe_mirror(client: &reqwest::Client, url: &str) -> Option<Duration> {
+    let started = Instant::now();
+    let response = client
+        .get(url)
+        .header(RANGE, "bytes=0-0")
+        .timeout(MIRROR_PROBE_TIMEOUT)
+        .send()
+        .await
+        .ok()?;
+    if response.status().is_success() {
+        Some(started.elapsed())
+    } else {
+        None
+    }
+}
+
+fn mirror_host_key(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    Some(format!("{}://{host}:{port}", parsed.scheme()))
+}
@@ -1,9 +1,13 @@
 pub mod achievement_service;
 pub mod api_client;
+pub mod aria2_rpc;
 pub mod artwork_cache;
 pub mod auth_service;
+pub mod bandwidth;
 pub mod cloud_save_service;
+pub mod connectivity_service;
 pub mod crack_manager;
+pub mod data_cap;
 pub mod discovery_service;
 pub mod download_manager;
 pub mod download_manager_v2;
@@ -13,15 +17,21 @@ pub mod inventory_service;
 pub mod library_service;
 pub mod license_service;
 pub mod manifest_service;
+pub mod mirror_ranker;
 pub mod overlay_service;
+pub mod patch_engine;
 pub mod peer_cache_server;
 pub mod peer_coordination;
+pub mod post_install;
 pub mod remote_download_service;
 pub mod security_guard;
 pub mod self_heal;
 pub mod steam_prefetch_worker;
 pub mod streaming_service;
 pub mod telemetry_service;
+pub mod update_check_service;
+pub mod update_service;
+pub mod volatile_paths;
 pub mod workshop_service;
 
 pub use achievement_service::AchievementService;
@@ -29,9 +39,14 @@ pub use api_client::ApiClient;
 pub use artwork_cache::{ArtworkCacheService, ArtworkPrefetchItem, ArtworkSources};
 pub use auth_service::AuthService;
 pub use cloud_save_service::CloudSaveService;
+pub use connectivity_service::{ConnectivityService, ConnectivityStatus};
 pub use crack_manager::CrackManager;
-pub use discovery_service::DiscoveryService;
-pub use download_manager::DownloadManager;
+pub use discovery_service::{DiscoveryQueuePage, DiscoveryService};
+pub use download_manager::{
+    DownloadManager, DownloadTuning, GameRepairPlan, InstallSizeCheck, LibraryScanEntry,
+    LocalImportReport, ManifestInfo, P2pStatus, RepairTargets, StorageOptions, WriteStrategy,
+    WriteStrategyInfo,
+};
 pub use download_manager_v2::{DownloadManagerV2, DownloadSessionV2, StartDownloadV2Request};
 pub use download_service::DownloadService;
 pub use game_runtime_service::{GameRuntimeService, RunningGame};
@@ -39,16 +54,21 @@ pub use inventory_service::InventoryService;
 pub use library_service::LibraryService;
 pub use license_service::LicenseService;
 pub use manifest_service::ManifestService;
+pub use mirror_ranker::MirrorRanker;
 pub use overlay_service::OverlayService;
 pub use peer_cache_server::PeerCacheServer;
 pub use peer_coordination::{
-    build_chunk_peer_urls, peer_url_fingerprint, PeerCandidate, PeerCoordinator,
+    build_chunk_peer_urls, order_chunk_sources, peer_url_fingerprint, PeerCandidate,
+    PeerCoordinator, PeerSourceConfig, PeerSourcePolicy, PeerStats, PeerTransferStats,
 };
 pub use remote_download_service::RemoteDownloadService;
 pub use security_guard::{SecurityGuardService, SecurityVerdictV2};
 pub use self_heal::{
-    SelfHealRepairPlanV2, SelfHealReportV2, SelfHealScanRequestV2, SelfHealService,
+    IntegrityHistoryV2, SelfHealRepairPlanV2, SelfHealReportV2, SelfHealScanRequestV2,
+    SelfHealService,
 };
 pub use streaming_service::StreamingService;
 pub use telemetry_service::TelemetryService;
+pub use update_check_service::UpdateCheckService;
+pub use update_service::UpdateService;
 pub use workshop_service::WorkshopService;
@@ -1,16 +1,80 @@
 use std::fs;
-use std::path::PathBuf;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use serde::Deserialize;
+
 #[derive(Clone)]
 pub struct OverlayService {
     state: Arc<Mutex<OverlayState>>,
 }
 
+/// Shift+Tab is taken by Steam's overlay and by many games' menus.
+pub const DEFAULT_OVERLAY_HOTKEY: &str = "Shift+F1";
+
+const MODIFIERS: [(&str, &str); 8] = [
+    ("ctrl", "Ctrl"),
+    ("control", "Ctrl"),
+    ("alt", "Alt"),
+    ("option", "Alt"),
+    ("shift", "Shift"),
+    ("super", "Super"),
+    ("cmd", "Super"),
+    ("meta", "Super"),
+];
+
+/// Keys games commonly bind alone or with Shift/Ctrl (movement, sprint,
+/// crouch, interact), so the overlay must not grab them.
+const GAME_KEYS: [&str; 16] = [
+    "W", "A", "S", "D", "Q", "E", "R", "F", "C", "Z", "X", "Space", "Escape", "Enter", "Up", "Down",
+];
+
+/// Combos the OS or nearly every game reserves.
+const RESERVED_COMBOS: [&str; 5] = [
+    "Alt+Tab",
+    "Alt+F4",
+    "Ctrl+Alt+Delete",
+    "Super+L",
+    "Ctrl+Shift+Escape",
+];
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenshotFormat {
+    #[default]
+    Png,
+    #[serde(alias = "jpg")]
+    Jpeg,
+}
+
+impl ScreenshotFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ScreenshotOptions {
+    pub format: ScreenshotFormat,
+    /// JPEG quality from 1 to 100; ignored for PNG.
+    pub jpeg_quality: Option<u8>,
+    /// Output folder, created if missing. Defaults to the pictures dir.
+    pub directory: Option<String>,
+    pub copy_to_clipboard: bool,
+}
+
 struct OverlayState {
     visible: bool,
     last_capture: Option<PathBuf>,
+    hotkey: Option<String>,
 }
 
 impl OverlayService {
@@ -19,6 +83,7 @@ impl OverlayService {
             state: Arc::new(Mutex::new(OverlayState {
                 visible: false,
                 last_capture: None,
+                hotkey: None,
             })),
         }
     }
@@ -39,16 +104,236 @@ impl OverlayService {
         state.visible
     }
 
-    pub fn capture_screenshot(&self) -> Result<String, String> {
+    /// The currently registered toggle hotkey, if any.
+    pub fn hotkey(&self) -> Option<String> {
+        let state = self.state.lock().expect("overlay lock");
+        state.hotkey.clone()
+    }
+
+    pub fn set_hotkey(&self, hotkey: Option<String>) {
+        let mut state = self.state.lock().expect("overlay lock");
+        state.hotkey = hotkey;
+    }
+
+    /// Captures the primary monitor and saves it under `options.directory`,
+    /// or `default_dir` when unset. Returns the saved path.
+    pub fn capture_screenshot(
+        &self,
+        options: &ScreenshotOptions,
+        default_dir: PathBuf,
+    ) -> Result<String, String> {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map_err(|_| "time error".to_string())?
             .as_secs();
-        let filename = format!("overlay-capture-{}.txt", timestamp);
-        let path = std::env::temp_dir().join(filename);
-        fs::write(&path, b"overlay capture placeholder\n").map_err(|err| err.to_string())?;
+        let directory = options
+            .directory
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or(default_dir);
+        fs::create_dir_all(&directory).map_err(|err| {
+            format!(
+                "Failed to create screenshot folder {}: {err}",
+                directory.display()
+            )
+        })?;
+
+        let image = capture_primary_monitor()?;
+        let filename = format!(
+            "overlay-capture-{}.{}",
+            timestamp,
+            options.format.extension()
+        );
+        let path = directory.join(filename);
+        encode_screenshot(&image, options, &path)?;
+
+        if options.copy_to_clipboard {
+            if let Err(err) = copy_image_to_clipboard(&image) {
+                tracing::warn!("screenshot saved but not copied to clipboard: {}", err);
+            }
+        }
+
         let mut state = self.state.lock().expect("overlay lock");
         state.last_capture = Some(path.clone());
         Ok(path.to_string_lossy().to_string())
     }
 }
+
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+fn capture_primary_monitor() -> Result<RgbaImage, String> {
+    let monitors = xcap::Monitor::all().map_err(|err| format!("Failed to list monitors: {err}"))?;
+    let monitor = monitors
+        .iter()
+        .find(|monitor| monitor.is_primary())
+        .or_else(|| monitors.first())
+        .ok_or_else(|| "No monitor to capture".to_string())?;
+    monitor
+        .capture_image()
+        .map_err(|err| format!("Screen capture failed: {err}"))
+}
+
+fn encode_screenshot(
+    image: &RgbaImage,
+    options: &ScreenshotOptions,
+    path: &Path,
+) -> Result<(), String> {
+    let file = fs::File::create(path).map_err(|err| err.to_string())?;
+    let mut writer = BufWriter::new(file);
+    let result = match options.format {
+        ScreenshotFormat::Png => image.write_to(&mut writer, ImageFormat::Png),
+        ScreenshotFormat::Jpeg => {
+            let quality = options
+                .jpeg_quality
+                .unwrap_or(DEFAULT_JPEG_QUALITY)
+                .clamp(1, 100);
+            // JPEG has no alpha channel.
+            let rgb = DynamicImage::ImageRgba8(image.clone()).to_rgb8();
+            JpegEncoder::new_with_quality(&mut writer, quality).encode_image(&rgb)
+        }
+    };
+    result.map_err(|err| format!("Failed to encode screenshot: {err}"))
+}
+
+fn copy_image_to_clipboard(image: &RgbaImage) -> Result<(), String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|err| err.to_string())?;
+    clipboard
+        .set_image(arboard::ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: std::borrow::Cow::Borrowed(image.as_raw()),
+        })
+        .map_err(|err| err.to_string())
+}
+
+/// Normalizes a combo like `ctrl + shift + o` to `Ctrl+Shift+O`. Rejects
+/// malformed combos and ones that clash with the OS or common game keys.
+pub fn normalize_hotkey(combo: &str) -> Result<String, String> {
+    let parts: Vec<&str> = combo.split('+').map(str::trim).collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(format!("Invalid hotkey \"{combo}\""));
+    }
+    let Some((key, modifier_parts)) = parts.split_last() else {
+        return Err("Hotkey is empty".to_string());
+    };
+
+    let mut modifiers = Vec::new();
+    for part in modifier_parts {
+        let lowered = part.to_ascii_lowercase();
+        let Some((_, name)) = MODIFIERS.iter().find(|(alias, _)| *alias == lowered) else {
+            return Err(format!("Unknown modifier \"{part}\""));
+        };
+        if modifiers.contains(name) {
+            return Err(format!("Modifier {name} is repeated"));
+        }
+        modifiers.push(*name);
+    }
+    modifiers.sort_by_key(|name| {
+        ["Ctrl", "Alt", "Shift", "Super"]
+            .iter()
+            .position(|m| m == name)
+    });
+
+    let key = normalize_key(key).ok_or_else(|| format!("Unknown key \"{key}\""))?;
+    let normalized = modifiers
+        .iter()
+        .copied()
+        .chain(std::iter::once(key.as_str()))
+        .collect::<Vec<_>>()
+        .join("+");
+
+    let is_function_key =
+        key.len() > 1 && key.starts_with('F') && key[1..].chars().all(|ch| ch.is_ascii_digit());
+    if modifiers.is_empty() && !is_function_key {
+        return Err(format!(
+            "{normalized} needs a modifier (Ctrl, Alt, Shift or Super)"
+        ));
+    }
+    if RESERVED_COMBOS.contains(&normalized.as_str()) {
+        return Err(format!("{normalized} is reserved by the system"));
+    }
+    let only_common_modifiers = modifiers
+        .iter()
+        .all(|name| *name == "Shift" || *name == "Ctrl");
+    if only_common_modifiers && GAME_KEYS.contains(&key.as_str()) {
+        return Err(format!("{normalized} conflicts with common game controls"));
+    }
+    Ok(normalized)
+}
+
+fn normalize_key(key: &str) -> Option<String> {
+    let lowered = key.to_ascii_lowercase();
+    let named = match lowered.as_str() {
+        "tab" => "Tab",
+        "space" | "spacebar" => "Space",
+        "esc" | "escape" => "Escape",
+        "enter" | "return" => "Enter",
+        "backspace" => "Backspace",
+        "delete" | "del" => "Delete",
+        "insert" | "ins" => "Insert",
+        "home" => "Home",
+        "end" => "End",
+        "pageup" => "PageUp",
+        "pagedown" => "PageDown",
+        "up" => "Up",
+        "down" => "Down",
+        "left" => "Left",
+        "right" => "Right",
+        "`" | "backquote" => "Backquote",
+        _ => "",
+    };
+    if !named.is_empty() {
+        return Some(named.to_string());
+    }
+    if lowered.len() == 1 {
+        let ch = lowered.chars().next()?;
+        return ch
+            .is_ascii_alphanumeric()
+            .then(|| ch.to_ascii_uppercase().to_string());
+    }
+    let number = lowered.strip_prefix('f')?.parse::<u8>().ok()?;
+    (1..=24).contains(&number).then(|| format!("F{number}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotkeys_are_normalized_in_modifier_order() {
+        assert_eq!(
+            normalize_hotkey("shift + ctrl + o").unwrap(),
+            "Ctrl+Shift+O"
+        );
+        assert_eq!(
+            normalize_hotkey("cmd+Alt+backquote").unwrap(),
+            "Alt+Super+Backquote"
+        );
+        assert_eq!(normalize_hotkey("f12").unwrap(), "F12");
+        assert_eq!(
+            normalize_hotkey(DEFAULT_OVERLAY_HOTKEY).unwrap(),
+            DEFAULT_OVERLAY_HOTKEY
+        );
+    }
+
+    #[test]
+    fn hotkeys_that_clash_or_are_malformed_are_rejected() {
+        for combo in [
+            "",
+            "ctrl+",
+            "ctrl+ctrl+o",
+            "hyper+o",
+            "ctrl+f25",
+            "o",
+            "alt+f4",
+            "ctrl+alt+del",
+            "shift+w",
+            "ctrl+space",
+        ] {
+            assert!(normalize_hotkey(combo).is_err(), "{combo} was accepted");
+        }
+        assert!(normalize_hotkey("alt+w").is_ok());
+    }
+}
@@ -0,0 +1,168 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+
+use sha2::{Digest, Sha256};
+
+use crate::errors::{LauncherError, Result};
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+const VCDIFF_MAGIC: [u8; 3] = [0xD6, 0xC3, 0xC4];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Binary patch formats the launcher can apply against an installed file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatchFormat {
+    /// VCDIFF produced by `xdelta3 -e -s <old> <new>`; needs the xdelta3 binary.
+    Xdelta,
+    /// `zstd --patch-from=<old> <new>`; decoded in-process.
+    Zstd,
+}
+
+impl PatchFormat {
+    pub fn detect(patch_path: &Path) -> Result<Self> {
+        let mut header = [0_u8; 4];
+        let read = File::open(patch_path)?.read(&mut header)?;
+        if read >= 4 && header == ZSTD_MAGIC {
+            return Ok(Self::Zstd);
+        }
+        if read >= 3 && header[..3] == VCDIFF_MAGIC {
+            return Ok(Self::Xdelta);
+        }
+        Err(LauncherError::Config(format!(
+            "unrecognized patch format: {}",
+            patch_path.display()
+        )))
+    }
+}
+
+#[inline]
+fn hide_console_window(command: &mut Command) {
+    #[cfg(target_os = "windows")]
+    {
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+}
+
+/// The xdelta3 binary to run: `LAUNCHER_XDELTA3_PATH` (set to the bundled
+/// copy at startup when there is one), else whatever PATH resolves.
+fn xdelta3_binary() -> String {
+    std::env::var("LAUNCHER_XDELTA3_PATH")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "xdelta3".to_string())
+}
+
+pub fn xdelta3_available() -> bool {
+    let mut command = Command::new(xdelta3_binary());
+    hide_console_window(&mut command);
+    command
+        .arg("-V")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Applies `patch` to `source`, writing the result to `output`. The format is
+/// picked from the patch header.
+pub fn apply_patch(source: &Path, patch: &Path, output: &Path) -> Result<PatchFormat> {
+    let format = PatchFormat::detect(patch)?;
+    match format {
+        PatchFormat::Xdelta => apply_xdelta(source, patch, output)?,
+        PatchFormat::Zstd => apply_zstd(source, patch, output)?,
+    }
+    Ok(format)
+}
+
+pub fn apply_xdelta(source: &Path, patch: &Path, output: &Path) -> Result<()> {
+    let mut command = Command::new(xdelta3_binary());
+    hide_console_window(&mut command);
+    let status = command
+        .args([
+            "-f",
+            "-d",
+            "-s",
+            source.to_string_lossy().as_ref(),
+            patch.to_string_lossy().as_ref(),
+            output.to_string_lossy().as_ref(),
+        ])
+        .status()
+        .map_err(|err| LauncherError::Config(format!("failed to execute xdelta3: {err}")))?;
+    if !status.success() {
+        return Err(LauncherError::Config(format!(
+            "xdelta3 non-zero exit for output {} (status={})",
+            output.display(),
+            status
+        )));
+    }
+    Ok(())
+}
+
+/// Decodes a zstd patch using the old file as the raw-content dictionary.
+pub fn apply_zstd(source: &Path, patch: &Path, output: &Path) -> Result<()> {
+    let dictionary = std::fs::read(source)?;
+    let mut decoder = zstd::stream::read::Decoder::with_dictionary(
+        BufReader::new(File::open(patch)?),
+        &dictionary,
+    )?;
+    // --patch-from frames use a window large enough to reach back into the
+    // whole source file.
+    decoder.window_log_max(31)?;
+    let mut out = File::create(output)?;
+    io::copy(&mut decoder, &mut out)?;
+    out.sync_all()?;
+    Ok(())
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0_u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Checks a patched file against the expected size and SHA-256, when given.
+pub fn verify_output(
+    output: &Path,
+    expected_size: Option<u64>,
+    expected_sha256: Option<&str>,
+) -> Result<()> {
+    if let Some(expected_size) = expected_size {
+        let size = std::fs::metadata(output)?.len();
+        if size != expected_size {
+            return Err(LauncherError::Integrity(format!(
+                "patch output size mismatch {} expected={} actual={}",
+                output.display(),
+                expected_size,
+                size
+            )));
+        }
+    }
+    if let Some(expected_hash) = expected_sha256
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|value| !value.is_empty())
+    {
+        let actual = sha256_file(output)?;
+        if actual != expected_hash {
+            return Err(LauncherError::Integrity(format!(
+                "patch output hash mismatch {} expected={} actual={}",
+                output.display(),
+                expected_hash,
+                actual
+            )));
+        }
+    }
+    Ok(())
+}
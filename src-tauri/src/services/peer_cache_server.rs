@@ -1,14 +1,23 @@
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Write};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
 use serde::Serialize;
 
+use crate::services::bandwidth::BandwidthThrottler;
+
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(5);
+/// Share of the measured upload rate peers get when no cap is set.
+const AUTO_UPLOAD_SHARE: f64 = 0.5;
+/// Bytes served without a cap before the automatic cap is derived.
+const UPLOAD_CALIBRATION_BYTES: u64 = 8 * 1024 * 1024;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum PeerNetworkMode {
     LanOnly,
@@ -37,71 +46,101 @@ struct PeerCacheServerState {
     port: u16,
     depot_root: PathBuf,
     share_enabled: bool,
+    /// Cap set by the user; `0` derives one from the measured upload rate.
     upload_limit_bps: AtomicU64,
+    /// Highest sustained upload rate seen while calibrating; `0` until then.
+    measured_upload_bps: AtomicU64,
+    calibration: Mutex<UploadCalibration>,
     advertise_addresses: Vec<String>,
-    limiter: UploadLimiter,
+    throttle: BandwidthThrottler,
+    bytes_served: AtomicU64,
+    chunks_served: AtomicU64,
+    active_uploads: AtomicUsize,
+    upload_meter: ThroughputMeter,
 }
 
-#[derive(Default)]
-struct UploadLimiter {
-    inner: Mutex<UploadWindow>,
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct PeerUploadStats {
+    pub bytes_served: u64,
+    pub chunks_served: u64,
+    pub active_uploads: usize,
+    pub upload_bps: u64,
 }
 
-struct UploadWindow {
-    started_at: Instant,
-    sent_bytes: u64,
+/// Rolling byte counter over `THROUGHPUT_WINDOW`, used for live throughput.
+#[derive(Default)]
+pub struct ThroughputMeter {
+    samples: Mutex<VecDeque<(Instant, u64)>>,
 }
 
-#[derive(Serialize)]
-struct HealthPayload {
-    ok: bool,
-    peer_id: String,
-    version: &'static str,
+impl ThroughputMeter {
+    pub fn record(&self, bytes: u64) {
+        if bytes == 0 {
+            return;
+        }
+        if let Ok(mut samples) = self.samples.lock() {
+            let now = Instant::now();
+            samples.push_back((now, bytes));
+            prune_samples(&mut samples, now);
+        }
+    }
+
+    pub fn bytes_per_second(&self) -> u64 {
+        let Ok(mut samples) = self.samples.lock() else {
+            return 0;
+        };
+        prune_samples(&mut samples, Instant::now());
+        let total: u64 = samples.iter().map(|(_, bytes)| *bytes).sum();
+        total / THROUGHPUT_WINDOW.as_secs().max(1)
+    }
 }
 
-impl Default for UploadWindow {
-    fn default() -> Self {
-        Self {
-            started_at: Instant::now(),
-            sent_bytes: 0,
+fn prune_samples(samples: &mut VecDeque<(Instant, u64)>, now: Instant) {
+    while let Some((at, _)) = samples.front() {
+        if now.saturating_duration_since(*at) <= THROUGHPUT_WINDOW {
+            break;
         }
+        samples.pop_front();
     }
 }
 
-impl UploadLimiter {
-    fn wait_for_budget(&self, bytes: u64, max_bps: u64) {
-        if max_bps == 0 || bytes == 0 {
-            return;
-        }
-        loop {
-            let sleep_for = {
-                let mut guard = match self.inner.lock() {
-                    Ok(locked) => locked,
-                    Err(_) => return,
-                };
-                let elapsed = guard.started_at.elapsed();
-                if elapsed >= Duration::from_secs(1) {
-                    guard.started_at = Instant::now();
-                    guard.sent_bytes = 0;
-                }
-                if guard.sent_bytes.saturating_add(bytes) <= max_bps {
-                    guard.sent_bytes = guard.sent_bytes.saturating_add(bytes);
-                    None
-                } else {
-                    Some(Duration::from_millis(25))
-                }
-            };
-            if let Some(delay) = sleep_for {
-                thread::sleep(delay);
-                continue;
-            }
-            return;
+/// Measures the upload rate from the throughput meter, which averages every
+/// upload over `THROUGHPUT_WINDOW`, rather than from single chunks, whose
+/// writes mostly land in socket buffers.
+#[derive(Default)]
+struct UploadCalibration {
+    started_at: Option<Instant>,
+    served: u64,
+    best_bps: u64,
+}
+
+impl UploadCalibration {
+    /// Adds a finished upload and the meter's rate at that moment. Rates only
+    /// count once the meter has seen a full window of uploads; returns the
+    /// measured rate once enough has been served.
+    fn observe(&mut self, bytes: u64, sustained_bps: u64, now: Instant) -> Option<u64> {
+        let started_at = *self.started_at.get_or_insert(now);
+        self.served = self.served.saturating_add(bytes);
+        if now.saturating_duration_since(started_at) < THROUGHPUT_WINDOW {
+            return None;
         }
+        self.best_bps = self.best_bps.max(sustained_bps);
+        (self.served >= UPLOAD_CALIBRATION_BYTES && self.best_bps > 0).then_some(self.best_bps)
     }
 }
 
+#[derive(Serialize)]
+struct HealthPayload {
+    ok: bool,
+    peer_id: String,
+    version: &'static str,
+}
+
 impl PeerCacheServer {
-    pub fn start(depot_root: PathBuf) -> Option<Self> {
+    /// Binds the chunk server. `upload_limit_bps` overrides the
+    /// `OTOSHI_P2P_UPLOAD_LIMIT_BPS` cap; with neither, uploads are capped at a
+    /// share of the rate measured while serving the first chunks.
+    pub fn start(depot_root: PathBuf, upload_limit_bps: Option<u64>) -> Option<Self> {
         if !resolve_enabled_default_true() {
             return None;
         }
@@ -128,9 +167,12 @@ impl PeerCacheServer {
             .unwrap_or(preferred_port);
         let mode = resolve_mode();
         let share_enabled = !env_falsey("OTOSHI_P2P_SHARE_ENABLED");
-        let upload_limit_bps = std::env::var("OTOSHI_P2P_UPLOAD_LIMIT_BPS")
-            .ok()
-            .and_then(|value| value.trim().parse::<u64>().ok())
+        let upload_limit_bps = upload_limit_bps
+            .or_else(|| {
+                std::env::var("OTOSHI_P2P_UPLOAD_LIMIT_BPS")
+                    .ok()
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+            })
             .unwrap_or(0);
 
         let mut advertise_addresses = resolve_advertise_addresses();
@@ -148,8 +190,14 @@ impl PeerCacheServer {
             depot_root,
             share_enabled,
             upload_limit_bps: AtomicU64::new(upload_limit_bps),
+            measured_upload_bps: AtomicU64::new(0),
+            calibration: Mutex::new(UploadCalibration::default()),
             advertise_addresses,
-            limiter: UploadLimiter::default(),
+            throttle: BandwidthThrottler::new(upload_limit_bps),
+            bytes_served: AtomicU64::new(0),
+            chunks_served: AtomicU64::new(0),
+            active_uploads: AtomicUsize::new(0),
+            upload_meter: ThroughputMeter::default(),
         });
 
         let server = Self {
@@ -166,27 +214,90 @@ impl PeerCacheServer {
             peer_id: self.state.peer_id.clone(),
             port: self.state.port,
             addresses: self.state.advertise_addresses.clone(),
-            upload_limit_bps: self.state.upload_limit_bps.load(Ordering::Relaxed),
+            upload_limit_bps: self.upload_limit_bps(),
         }
     }
 
+    /// The cap uploads run under right now; `0` while still measuring.
     pub fn upload_limit_bps(&self) -> u64 {
-        self.state.upload_limit_bps.load(Ordering::Relaxed)
+        self.state.throttle.limit()
     }
 
+    /// Whether the cap comes from the measured upload rate rather than the user.
+    pub fn upload_limit_is_auto(&self) -> bool {
+        self.state.upload_limit_bps.load(Ordering::Relaxed) == 0
+    }
+
+    /// Sets the upload cap; `0` goes back to a share of the measured rate.
     pub fn set_upload_limit_bps(&self, value: u64) {
         self.state.upload_limit_bps.store(value, Ordering::Relaxed);
+        self.state.apply_upload_limit();
+    }
+
+    pub fn upload_stats(&self) -> PeerUploadStats {
+        PeerUploadStats {
+            bytes_served: self.state.bytes_served.load(Ordering::Relaxed),
+            chunks_served: self.state.chunks_served.load(Ordering::Relaxed),
+            active_uploads: self.state.active_uploads.load(Ordering::Relaxed),
+            upload_bps: self.state.upload_meter.bytes_per_second(),
+        }
     }
 
     pub fn peer_id(&self) -> &str {
         &self.state.peer_id
     }
 
+    /// Stops accepting connections and releases the port. Uploads already in
+    /// flight finish on their own threads.
+    pub fn stop(&self) {
+        self.state.running.store(false, Ordering::Relaxed);
+    }
+
     pub fn addresses(&self) -> &[String] {
         &self.state.advertise_addresses
     }
 }
 
+impl PeerCacheServerState {
+    fn effective_upload_limit(&self) -> u64 {
+        let explicit = self.upload_limit_bps.load(Ordering::Relaxed);
+        if explicit > 0 {
+            return explicit;
+        }
+        match self.measured_upload_bps.load(Ordering::Relaxed) {
+            0 => 0,
+            measured => (measured as f64 * AUTO_UPLOAD_SHARE) as u64,
+        }
+    }
+
+    fn apply_upload_limit(&self) {
+        self.throttle.set_limit(self.effective_upload_limit());
+    }
+
+    /// Feeds one finished upload into the calibration. Only uncapped uploads
+    /// count, and it stops once a rate has been measured.
+    fn record_upload(&self, bytes: u64) {
+        if self.upload_limit_bps.load(Ordering::Relaxed) > 0
+            || self.measured_upload_bps.load(Ordering::Relaxed) > 0
+        {
+            return;
+        }
+        let Ok(mut calibration) = self.calibration.lock() else {
+            return;
+        };
+        let sustained_bps = self.upload_meter.bytes_per_second();
+        if let Some(measured) = calibration.observe(bytes, sustained_bps, Instant::now()) {
+            self.measured_upload_bps.store(measured, Ordering::Relaxed);
+            self.apply_upload_limit();
+            tracing::info!(
+                "p2p upload measured at {} B/s, capping peers at {} B/s",
+                measured,
+                self.throttle.limit()
+            );
+        }
+    }
+}
+
 fn serve_loop(listener: TcpListener, state: Arc<PeerCacheServerState>) {
     let bound_port = listener.local_addr().map(|addr| addr.port()).unwrap_or(0);
     tracing::info!(
@@ -215,6 +326,7 @@ fn serve_loop(listener: TcpListener, state: Arc<PeerCacheServerState>) {
             }
         }
     }
+    tracing::info!("p2p peer cache server stopped peer_id={}", state.peer_id);
 }
 
 fn handle_connection(
@@ -240,6 +352,12 @@ fn handle_connection(
     let raw_path = parts.next().unwrap_or("/");
     let path = raw_path.split('?').next().unwrap_or("/");
 
+    if method == "HEAD" && path == "/health" {
+        // Liveness probes from other coordinators only need the status line.
+        write_head_ok(&mut stream)?;
+        return Ok(());
+    }
+
     if method != "GET" {
         write_status(&mut stream, 405, "Method Not Allowed", "method not allowed")?;
         return Ok(());
@@ -271,17 +389,11 @@ fn handle_connection(
         let file_size = file.metadata().map(|meta| meta.len()).unwrap_or(0);
         write_binary_headers(&mut stream, file_size)?;
 
-        let mut buffer = [0u8; 64 * 1024];
-        loop {
-            let read = file.read(&mut buffer)?;
-            if read == 0 {
-                break;
-            }
-            let upload_limit = state.upload_limit_bps.load(Ordering::Relaxed);
-            state.limiter.wait_for_budget(read as u64, upload_limit);
-            stream.write_all(&buffer[..read])?;
-        }
-        let _ = stream.flush();
+        state.active_uploads.fetch_add(1, Ordering::Relaxed);
+        let result = stream_chunk(&mut file, &mut stream, state);
+        state.active_uploads.fetch_sub(1, Ordering::Relaxed);
+        result?;
+        state.chunks_served.fetch_add(1, Ordering::Relaxed);
         return Ok(());
     }
 
@@ -289,6 +401,30 @@ fn handle_connection(
     Ok(())
 }
 
+fn stream_chunk(
+    file: &mut File,
+    stream: &mut TcpStream,
+    state: &PeerCacheServerState,
+) -> std::io::Result<()> {
+    let mut buffer = [0u8; 64 * 1024];
+    let mut sent = 0u64;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        // Sleeps only this connection's thread; other peers keep being served.
+        state.throttle.acquire_blocking(read as u64);
+        stream.write_all(&buffer[..read])?;
+        sent += read as u64;
+        state.bytes_served.fetch_add(read as u64, Ordering::Relaxed);
+        state.upload_meter.record(read as u64);
+    }
+    let _ = stream.flush();
+    state.record_upload(sent);
+    Ok(())
+}
+
 fn read_request_line(stream: &TcpStream) -> std::io::Result<Option<String>> {
     let clone = stream.try_clone()?;
     let mut reader = BufReader::new(clone);
@@ -337,6 +473,13 @@ fn write_json(stream: &mut TcpStream, code: u16, status: &str, body: &[u8]) -> s
     stream.flush()
 }
 
+fn write_head_ok(stream: &mut TcpStream) -> std::io::Result<()> {
+    stream.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+    )?;
+    stream.flush()
+}
+
 fn write_binary_headers(stream: &mut TcpStream, content_length: u64) -> std::io::Result<()> {
     let response = format!(
         "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {content_length}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n"
@@ -445,3 +588,46 @@ fn env_falsey(key: &str) -> bool {
         })
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calibration_ignores_bursts_before_the_meter_window_fills() {
+        let start = Instant::now();
+        let mut calibration = UploadCalibration::default();
+
+        // Chunks that vanish into socket buffers look very fast at first.
+        assert_eq!(
+            calibration.observe(UPLOAD_CALIBRATION_BYTES, 1_000_000_000, start),
+            None
+        );
+        assert_eq!(
+            calibration.observe(64 * 1024, 900_000_000, start + Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(
+            calibration.observe(64 * 1024, 2_000_000, start + THROUGHPUT_WINDOW),
+            Some(2_000_000)
+        );
+    }
+
+    #[test]
+    fn calibration_waits_for_enough_bytes_and_keeps_the_best_sustained_rate() {
+        let start = Instant::now();
+        let window_full = start + THROUGHPUT_WINDOW;
+        let mut calibration = UploadCalibration::default();
+
+        assert_eq!(calibration.observe(1024, 0, start), None);
+        assert_eq!(calibration.observe(1024, 3_000_000, window_full), None);
+        assert_eq!(
+            calibration.observe(
+                UPLOAD_CALIBRATION_BYTES,
+                1_000_000,
+                window_full + Duration::from_secs(2)
+            ),
+            Some(3_000_000)
+        );
+    }
+}
@@ -1,15 +1,25 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::{IpAddr, Ipv4Addr};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+use futures_util::future::join_all;
 use serde::{Deserialize, Serialize};
 
 use crate::services::api_client::ApiClient;
-use crate::services::peer_cache_server::PeerAdvertiseInfo;
+use crate::services::peer_cache_server::{PeerAdvertiseInfo, PeerUploadStats, ThroughputMeter};
 
 const PEER_LIST_CACHE_TTL: Duration = Duration::from_secs(20);
+const PEER_PROBE_TIMEOUT: Duration = Duration::from_millis(800);
+const PEER_LIVENESS_TTL: Duration = Duration::from_secs(30);
+const DEAD_PEER_BACKOFF: Duration = Duration::from_secs(120);
+const REPUTATION_MAX: i32 = 10;
+const REPUTATION_MIN: i32 = -10;
+const REPUTATION_ALIVE_REWARD: i32 = 1;
+const REPUTATION_DEAD_PENALTY: i32 = 3;
+const REPUTATION_DEMOTE_THRESHOLD: i32 = -6;
+const BALANCED_PEER_LEAD_REPUTATION: i32 = 2;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PeerScope {
@@ -24,6 +34,41 @@ pub struct PeerCandidate {
     pub base_urls: Vec<String>,
     pub upload_limit_bps: u64,
     pub scope: PeerScope,
+    pub reputation: i32,
+}
+
+/// How peer URLs are ordered against CDN URLs for each chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PeerSourcePolicy {
+    PreferCdn,
+    PreferPeer,
+    Balanced,
+}
+
+impl PeerSourcePolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "prefer-cdn" | "cdn" => Some(Self::PreferCdn),
+            "prefer-peer" | "peer" => Some(Self::PreferPeer),
+            "balanced" => Some(Self::Balanced),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PreferCdn => "prefer-cdn",
+            Self::PreferPeer => "prefer-peer",
+            Self::Balanced => "balanced",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PeerSourceConfig {
+    pub policy: PeerSourcePolicy,
+    pub fanout: usize,
 }
 
 #[derive(Clone)]
@@ -33,6 +78,95 @@ pub struct PeerCoordinator {
     device_id: String,
     started: Arc<AtomicBool>,
     state: Arc<Mutex<PeerCoordinatorState>>,
+    reaper: PeerReaper,
+}
+
+/// Probes advertised peers and tracks a reputation score per peer id so that
+/// offline peers never reach `apply_peer_sources`.
+#[derive(Clone)]
+pub struct PeerReaper {
+    client: reqwest::Client,
+    reputation: Arc<Mutex<HashMap<String, PeerReputation>>>,
+}
+
+#[derive(Clone, Debug)]
+struct PeerReputation {
+    score: i32,
+    consecutive_failures: u32,
+    last_checked: Instant,
+    alive: bool,
+}
+
+/// Bytes pulled from each peer during this session, keyed by the peer's
+/// `host:port` fingerprint (see `peer_url_fingerprint`).
+#[derive(Clone, Default)]
+pub struct PeerTransferStats {
+    inner: Arc<Mutex<HashMap<String, PeerTransfer>>>,
+}
+
+#[derive(Default)]
+struct PeerTransfer {
+    bytes_received: u64,
+    chunks_received: u64,
+    failures: u64,
+    meter: ThroughputMeter,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PeerTransferSnapshot {
+    pub bytes_received: u64,
+    pub chunks_received: u64,
+    pub failures: u64,
+    pub download_bps: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PeerStatsEntry {
+    pub peer_id: String,
+    pub addresses: Vec<String>,
+    pub reputation: i32,
+    pub alive: bool,
+    pub bytes_received: u64,
+    pub chunks_received: u64,
+    pub failures: u64,
+    pub download_bps: u64,
+}
+
+/// Session P2P totals surfaced by `get_peer_stats`.
+#[derive(Clone, Debug, Serialize)]
+pub struct PeerStats {
+    pub enabled: bool,
+    pub peer_id: Option<String>,
+    pub connected_peers: usize,
+    pub bytes_served: u64,
+    pub chunks_served: u64,
+    pub active_uploads: usize,
+    pub upload_bps: u64,
+    pub bytes_received: u64,
+    pub download_bps: u64,
+    pub peers: Vec<PeerStatsEntry>,
+}
+
+impl PeerStats {
+    pub fn from_parts(
+        peer_id: Option<String>,
+        upload: Option<PeerUploadStats>,
+        peers: Vec<PeerStatsEntry>,
+    ) -> Self {
+        let upload = upload.unwrap_or_default();
+        Self {
+            enabled: peer_id.is_some(),
+            peer_id,
+            connected_peers: peers.iter().filter(|peer| peer.alive).count(),
+            bytes_served: upload.bytes_served,
+            chunks_served: upload.chunks_served,
+            active_uploads: upload.active_uploads,
+            upload_bps: upload.upload_bps,
+            bytes_received: peers.iter().map(|peer| peer.bytes_received).sum(),
+            download_bps: peers.iter().map(|peer| peer.download_bps).sum(),
+            peers,
+        }
+    }
 }
 
 struct PeerCoordinatorState {
@@ -95,6 +229,7 @@ impl PeerCoordinator {
         if !advertise.enabled {
             return None;
         }
+        let reaper = PeerReaper::new(api.client());
         Some(Self {
             api,
             advertise,
@@ -105,6 +240,7 @@ impl PeerCoordinator {
                 heartbeat_interval_s: 20,
                 peers_cache: HashMap::new(),
             })),
+            reaper,
         })
     }
 
@@ -118,6 +254,12 @@ impl PeerCoordinator {
         });
     }
 
+    /// Ends the heartbeat loop after its current sleep. The backend drops the
+    /// peer once heartbeats stop.
+    pub fn stop(&self) {
+        self.started.store(false, Ordering::SeqCst);
+    }
+
     async fn run_heartbeat_loop(self) {
         if let Err(err) = self.register().await {
             tracing::warn!("p2p register failed: {}", err);
@@ -125,10 +267,43 @@ impl PeerCoordinator {
         loop {
             let delay_secs = self.current_heartbeat_interval().max(8).min(120);
             tokio::time::sleep(Duration::from_secs(delay_secs)).await;
+            if !self.started.load(Ordering::SeqCst) {
+                break;
+            }
             if let Err(err) = self.heartbeat().await {
                 tracing::warn!("p2p heartbeat failed: {}", err);
                 let _ = self.register().await;
             }
+            self.reap_cached_peers().await;
+        }
+    }
+
+    async fn reap_cached_peers(&self) {
+        let unique = self.known_peers();
+        if unique.is_empty() {
+            return;
+        }
+
+        let total = unique.len();
+        let alive = self
+            .reaper
+            .prune(unique)
+            .await
+            .into_iter()
+            .map(|peer| peer.peer_id)
+            .collect::<HashSet<_>>();
+        if alive.len() < total {
+            tracing::info!(
+                "p2p reaper pruned {} unresponsive peers ({} alive)",
+                total - alive.len(),
+                alive.len()
+            );
+        }
+
+        if let Ok(mut locked) = self.state.lock() {
+            for (_, peers) in locked.peers_cache.values_mut() {
+                peers.retain(|peer| alive.contains(&peer.peer_id));
+            }
         }
     }
 
@@ -168,6 +343,7 @@ impl PeerCoordinator {
             .into_iter()
             .filter_map(|peer| peer_to_candidate(peer))
             .collect::<Vec<_>>();
+        let peers = self.reaper.prune(peers).await;
 
         if let Ok(mut locked) = self.state.lock() {
             locked
@@ -177,6 +353,30 @@ impl PeerCoordinator {
         peers
     }
 
+    /// Unique peers across all cached game peer lists.
+    pub fn known_peers(&self) -> Vec<PeerCandidate> {
+        let mut unique: HashMap<String, PeerCandidate> = HashMap::new();
+        if let Ok(locked) = self.state.lock() {
+            for (_, peers) in locked.peers_cache.values() {
+                for peer in peers {
+                    unique
+                        .entry(peer.peer_id.clone())
+                        .or_insert_with(|| peer.clone());
+                }
+            }
+        }
+        unique.into_values().collect()
+    }
+
+    pub fn peer_stats(&self, transfers: &PeerTransferStats) -> Vec<PeerStatsEntry> {
+        aggregate_peer_stats(&self.known_peers(), &transfers.snapshot(), |peer_id| {
+            (
+                self.reaper.reputation(peer_id),
+                self.reaper.is_alive(peer_id),
+            )
+        })
+    }
+
     async fn register(&self) -> crate::errors::Result<()> {
         let payload = RegisterPayload {
             device_id: self.device_id.clone(),
@@ -230,6 +430,232 @@ impl PeerCoordinator {
     }
 }
 
+impl PeerReaper {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            reputation: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Drops peers that fail a liveness probe and returns the survivors ordered
+    /// by reputation. Recent probe results are reused for `PEER_LIVENESS_TTL`;
+    /// demoted peers are not probed again until `DEAD_PEER_BACKOFF` expires.
+    pub async fn prune(&self, peers: Vec<PeerCandidate>) -> Vec<PeerCandidate> {
+        let now = Instant::now();
+        let mut alive = Vec::new();
+        let mut to_probe = Vec::new();
+
+        match self.reputation.lock() {
+            Ok(locked) => {
+                for peer in peers {
+                    match locked.get(&peer.peer_id) {
+                        Some(entry)
+                            if now.saturating_duration_since(entry.last_checked)
+                                < PEER_LIVENESS_TTL =>
+                        {
+                            if entry.alive {
+                                alive.push(peer);
+                            }
+                        }
+                        Some(entry)
+                            if entry.score <= REPUTATION_DEMOTE_THRESHOLD
+                                && now.saturating_duration_since(entry.last_checked)
+                                    < DEAD_PEER_BACKOFF => {}
+                        _ => to_probe.push(peer),
+                    }
+                }
+            }
+            Err(_) => to_probe = peers,
+        }
+
+        let probes = to_probe.into_iter().map(|peer| async move {
+            let reachable = probe_peer(&self.client, &peer).await;
+            (peer, reachable)
+        });
+        for (peer, reachable) in join_all(probes).await {
+            self.record(&peer.peer_id, reachable);
+            if reachable {
+                alive.push(peer);
+            } else {
+                tracing::debug!("p2p peer {} failed liveness probe", peer.peer_id);
+            }
+        }
+
+        if let Ok(locked) = self.reputation.lock() {
+            for peer in &mut alive {
+                peer.reputation = locked
+                    .get(&peer.peer_id)
+                    .map(|entry| entry.score)
+                    .unwrap_or(0);
+            }
+        }
+        alive.sort_by_key(|peer| std::cmp::Reverse(peer.reputation));
+        alive
+    }
+
+    pub fn reputation(&self, peer_id: &str) -> i32 {
+        self.reputation
+            .lock()
+            .ok()
+            .and_then(|locked| locked.get(peer_id).map(|entry| entry.score))
+            .unwrap_or(0)
+    }
+
+    pub fn is_alive(&self, peer_id: &str) -> bool {
+        self.reputation
+            .lock()
+            .ok()
+            .and_then(|locked| locked.get(peer_id).map(|entry| entry.alive))
+            .unwrap_or(false)
+    }
+
+    fn record(&self, peer_id: &str, reachable: bool) {
+        let Ok(mut locked) = self.reputation.lock() else {
+            return;
+        };
+        let entry = locked
+            .entry(peer_id.to_string())
+            .or_insert_with(|| PeerReputation {
+                score: 0,
+                consecutive_failures: 0,
+                last_checked: Instant::now(),
+                alive: reachable,
+            });
+        entry.last_checked = Instant::now();
+        entry.alive = reachable;
+        if reachable {
+            entry.consecutive_failures = 0;
+            entry.score = (entry.score + REPUTATION_ALIVE_REWARD).min(REPUTATION_MAX);
+        } else {
+            entry.consecutive_failures = entry.consecutive_failures.saturating_add(1);
+            let penalty = REPUTATION_DEAD_PENALTY * entry.consecutive_failures.min(3) as i32;
+            entry.score = (entry.score - penalty).max(REPUTATION_MIN);
+        }
+    }
+}
+
+impl PeerTransferStats {
+    pub fn record_received(&self, fingerprint: &str, bytes: u64) {
+        if let Ok(mut locked) = self.inner.lock() {
+            let entry = locked.entry(fingerprint.to_string()).or_default();
+            entry.bytes_received = entry.bytes_received.saturating_add(bytes);
+            entry.chunks_received = entry.chunks_received.saturating_add(1);
+            entry.meter.record(bytes);
+        }
+    }
+
+    pub fn record_failure(&self, fingerprint: &str) {
+        if let Ok(mut locked) = self.inner.lock() {
+            let entry = locked.entry(fingerprint.to_string()).or_default();
+            entry.failures = entry.failures.saturating_add(1);
+        }
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, PeerTransferSnapshot> {
+        let Ok(locked) = self.inner.lock() else {
+            return HashMap::new();
+        };
+        locked
+            .iter()
+            .map(|(key, entry)| {
+                (
+                    key.clone(),
+                    PeerTransferSnapshot {
+                        bytes_received: entry.bytes_received,
+                        chunks_received: entry.chunks_received,
+                        failures: entry.failures,
+                        download_bps: entry.meter.bytes_per_second(),
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+/// Folds per-address transfer counters into one entry per known peer. Traffic
+/// from addresses that no longer map to a known peer is reported under the
+/// address itself so totals still add up.
+fn aggregate_peer_stats<F>(
+    peers: &[PeerCandidate],
+    transfers: &HashMap<String, PeerTransferSnapshot>,
+    status: F,
+) -> Vec<PeerStatsEntry>
+where
+    F: Fn(&str) -> (i32, bool),
+{
+    let mut claimed = HashSet::new();
+    let mut entries = Vec::new();
+    for peer in peers {
+        let (reputation, alive) = status(&peer.peer_id);
+        let mut entry = PeerStatsEntry {
+            peer_id: peer.peer_id.clone(),
+            addresses: peer.base_urls.clone(),
+            reputation,
+            alive,
+            bytes_received: 0,
+            chunks_received: 0,
+            failures: 0,
+            download_bps: 0,
+        };
+        let fingerprints = peer
+            .base_urls
+            .iter()
+            .filter_map(|base_url| host_port_key(base_url))
+            .collect::<HashSet<_>>();
+        for fingerprint in fingerprints {
+            if let Some(transfer) = transfers.get(&fingerprint) {
+                claimed.insert(fingerprint);
+                entry.bytes_received += transfer.bytes_received;
+                entry.chunks_received += transfer.chunks_received;
+                entry.failures += transfer.failures;
+                entry.download_bps += transfer.download_bps;
+            }
+        }
+        entries.push(entry);
+    }
+
+    for (fingerprint, transfer) in transfers {
+        if claimed.contains(fingerprint) {
+            continue;
+        }
+        entries.push(PeerStatsEntry {
+            peer_id: fingerprint.clone(),
+            addresses: vec![fingerprint.clone()],
+            reputation: 0,
+            alive: false,
+            bytes_received: transfer.bytes_received,
+            chunks_received: transfer.chunks_received,
+            failures: transfer.failures,
+            download_bps: transfer.download_bps,
+        });
+    }
+    entries.sort_by(|left, right| {
+        right
+            .bytes_received
+            .cmp(&left.bytes_received)
+            .then_with(|| left.peer_id.cmp(&right.peer_id))
+    });
+    entries
+}
+
+async fn probe_peer(client: &reqwest::Client, peer: &PeerCandidate) -> bool {
+    for base_url in &peer.base_urls {
+        let url = format!("{}/health", base_url.trim_end_matches('/'));
+        let reachable = client
+            .head(&url)
+            .timeout(PEER_PROBE_TIMEOUT)
+            .send()
+            .await
+            .map(|response| response.status().is_success())
+            .unwrap_or(false);
+        if reachable {
+            return true;
+        }
+    }
+    false
+}
+
 pub fn build_chunk_peer_urls(
     chunk_hash: &str,
     peers: &[PeerCandidate],
@@ -258,6 +684,7 @@ pub fn build_chunk_peer_urls(
         left_peer
             .scope
             .cmp(&right_peer.scope)
+            .then_with(|| right_peer.reputation.cmp(&left_peer.reputation))
             .then_with(|| right_score.cmp(left_score))
             .then_with(|| left_peer.peer_id.cmp(&right_peer.peer_id))
     });
@@ -275,11 +702,55 @@ pub fn build_chunk_peer_urls(
     dedupe_urls(urls)
 }
 
+/// Merges a chunk's peer URLs with its CDN URLs according to `policy`.
+/// `Balanced` alternates sources and only lets a peer lead when the best peer
+/// has earned a positive reputation.
+pub fn order_chunk_sources(
+    peer_urls: Vec<String>,
+    cdn_urls: Vec<String>,
+    lead_peer_reputation: i32,
+    policy: PeerSourcePolicy,
+) -> Vec<String> {
+    let mut merged = Vec::with_capacity(peer_urls.len() + cdn_urls.len());
+    match policy {
+        PeerSourcePolicy::PreferPeer => {
+            merged.extend(peer_urls);
+            merged.extend(cdn_urls);
+        }
+        PeerSourcePolicy::PreferCdn => {
+            merged.extend(cdn_urls);
+            merged.extend(peer_urls);
+        }
+        PeerSourcePolicy::Balanced => {
+            let (mut first, mut second) = if lead_peer_reputation >= BALANCED_PEER_LEAD_REPUTATION {
+                (peer_urls.into_iter(), cdn_urls.into_iter())
+            } else {
+                (cdn_urls.into_iter(), peer_urls.into_iter())
+            };
+            loop {
+                let left = first.next();
+                let right = second.next();
+                if left.is_none() && right.is_none() {
+                    break;
+                }
+                merged.extend(left);
+                merged.extend(right);
+            }
+        }
+    }
+    dedupe_urls(merged)
+}
+
 pub fn peer_url_fingerprint(url: &str) -> Option<String> {
     let parsed = reqwest::Url::parse(url).ok()?;
     if !parsed.path().starts_with("/chunks/") {
         return None;
     }
+    host_port_key(url)
+}
+
+fn host_port_key(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
     let host = parsed.host_str()?.to_ascii_lowercase();
     let port = parsed.port_or_known_default().unwrap_or(80);
     Some(format!("{host}:{port}"))
@@ -311,6 +782,7 @@ fn peer_to_candidate(peer: PeerOut) -> Option<PeerCandidate> {
         base_urls,
         upload_limit_bps: peer.upload_limit_bps,
         scope: best_scope,
+        reputation: 0,
     })
 }
 
@@ -406,3 +878,155 @@ fn is_cgnat(ip: Ipv4Addr) -> bool {
     let octets = ip.octets();
     octets[0] == 100 && (64..=127).contains(&octets[1])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    fn candidate(peer_id: &str, port: u16) -> PeerCandidate {
+        PeerCandidate {
+            peer_id: peer_id.to_string(),
+            base_urls: vec![format!("http://127.0.0.1:{port}")],
+            upload_limit_bps: 0,
+            scope: PeerScope::Lan,
+            reputation: 0,
+        }
+    }
+
+    fn unused_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind probe port");
+        listener.local_addr().expect("probe addr").port()
+    }
+
+    fn spawn_health_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind health server");
+        let port = listener.local_addr().expect("health addr").port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let mut stream = stream;
+                let mut buffer = [0_u8; 512];
+                let _ = stream.read(&mut buffer);
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn prunes_unreachable_peer_and_demotes_it() {
+        let reaper = PeerReaper::new(reqwest::Client::new());
+        let live_port = spawn_health_server();
+        let peers = vec![
+            candidate("dead", unused_port()),
+            candidate("live", live_port),
+        ];
+
+        let survivors = reaper.prune(peers).await;
+
+        assert_eq!(survivors.len(), 1);
+        assert_eq!(survivors[0].peer_id, "live");
+        assert!(reaper.reputation("dead") < 0);
+        assert!(reaper.reputation("live") > 0);
+    }
+
+    #[test]
+    fn source_policy_reorders_peer_and_cdn_urls() {
+        let peers = vec![
+            "http://peer-a/chunks/x".to_string(),
+            "http://peer-b/chunks/x".to_string(),
+        ];
+        let cdn = vec!["https://cdn-a/x".to_string(), "https://cdn-b/x".to_string()];
+
+        let prefer_peer =
+            order_chunk_sources(peers.clone(), cdn.clone(), 0, PeerSourcePolicy::PreferPeer);
+        assert_eq!(prefer_peer[0], "http://peer-a/chunks/x");
+        assert_eq!(prefer_peer[2], "https://cdn-a/x");
+
+        let prefer_cdn =
+            order_chunk_sources(peers.clone(), cdn.clone(), 10, PeerSourcePolicy::PreferCdn);
+        assert_eq!(prefer_cdn[0], "https://cdn-a/x");
+        assert_eq!(prefer_cdn[2], "http://peer-a/chunks/x");
+
+        let balanced_untrusted =
+            order_chunk_sources(peers.clone(), cdn.clone(), 0, PeerSourcePolicy::Balanced);
+        assert_eq!(
+            balanced_untrusted,
+            vec![
+                "https://cdn-a/x",
+                "http://peer-a/chunks/x",
+                "https://cdn-b/x",
+                "http://peer-b/chunks/x"
+            ]
+        );
+
+        let balanced_trusted = order_chunk_sources(peers, cdn, 5, PeerSourcePolicy::Balanced);
+        assert_eq!(balanced_trusted[0], "http://peer-a/chunks/x");
+        assert_eq!(balanced_trusted[1], "https://cdn-a/x");
+        assert_eq!(balanced_trusted.len(), 4);
+    }
+
+    #[test]
+    fn chunk_peer_urls_rank_by_reputation_within_scope() {
+        let mut trusted = candidate("trusted", 1);
+        trusted.reputation = 8;
+        let mut shaky = candidate("shaky", 2);
+        shaky.reputation = -2;
+
+        let urls = build_chunk_peer_urls("abcd", &[shaky, trusted], 1);
+        assert_eq!(urls, vec!["http://127.0.0.1:1/chunks/abcd"]);
+    }
+
+    #[test]
+    fn aggregates_transfer_counters_per_peer() {
+        let mut multi_homed = candidate("multi", 7001);
+        multi_homed
+            .base_urls
+            .push("http://192.168.1.20:7001".to_string());
+        let peers = vec![multi_homed, candidate("idle", 7002)];
+
+        let stats = PeerTransferStats::default();
+        stats.record_received("127.0.0.1:7001", 1_000);
+        stats.record_received("192.168.1.20:7001", 500);
+        stats.record_failure("192.168.1.20:7001");
+        stats.record_received("10.0.0.9:7942", 250);
+
+        let entries = aggregate_peer_stats(&peers, &stats.snapshot(), |peer_id| {
+            if peer_id == "multi" {
+                (4, true)
+            } else {
+                (0, false)
+            }
+        });
+
+        assert_eq!(entries.len(), 3);
+        let multi = &entries[0];
+        assert_eq!(multi.peer_id, "multi");
+        assert_eq!(multi.bytes_received, 1_500);
+        assert_eq!(multi.chunks_received, 2);
+        assert_eq!(multi.failures, 1);
+        assert_eq!(multi.reputation, 4);
+        assert!(multi.alive);
+
+        let orphan = entries
+            .iter()
+            .find(|entry| entry.peer_id == "10.0.0.9:7942")
+            .expect("unmatched traffic is kept");
+        assert_eq!(orphan.bytes_received, 250);
+        let idle = entries
+            .iter()
+            .find(|entry| entry.peer_id == "idle")
+            .expect("idle peer listed");
+        assert_eq!(idle.bytes_received, 0);
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry.bytes_received)
+                .sum::<u64>(),
+            1_750
+        );
+    }
+}
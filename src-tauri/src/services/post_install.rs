@@ -0,0 +1,500 @@
+use std::io::Read;
+use std::path::{Component, Path};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::{LauncherError, Result};
+
+const MARKER_FILE: &str = ".otoshi-post-install.done";
+const OUTPUT_LIMIT_BYTES: usize = 16 * 1024;
+const DEFAULT_TIMEOUT_SECS: u64 = 10 * 60;
+const MAX_TIMEOUT_SECS: u64 = 60 * 60;
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+/// Variables passed through to the script; everything else is dropped.
+/// Installers and redistributables look up the profile, program and system
+/// folders, so those stay.
+const PASSTHROUGH_ENV: [&str; 20] = [
+    "PATH",
+    "PATHEXT",
+    "SYSTEMROOT",
+    "WINDIR",
+    "SystemDrive",
+    "COMSPEC",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "ProgramData",
+    "ProgramFiles",
+    "ProgramFiles(x86)",
+    "ProgramW6432",
+    "CommonProgramFiles",
+    "CommonProgramFiles(x86)",
+    "PROCESSOR_ARCHITECTURE",
+    "NUMBER_OF_PROCESSORS",
+    "HOME",
+];
+
+/// One-time setup step a manifest can declare, run from the install dir.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PostInstallStep {
+    /// Path of the program or script, relative to the install dir.
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Outcome of a post-install run, emitted as `post-install-finished`.
+#[derive(Clone, Debug, Serialize)]
+pub struct PostInstallReport {
+    pub slug: String,
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+    pub elapsed_ms: u64,
+}
+
+/// Emitted as `post-install-pending` when a step is waiting for the user to
+/// allow it.
+#[derive(Clone, Debug, Serialize)]
+pub struct PendingPostInstall {
+    pub download_id: String,
+    pub slug: String,
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl PostInstallStep {
+    fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.command.as_bytes());
+        for arg in &self.args {
+            hasher.update([0]);
+            hasher.update(arg.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Whether this exact step already ran successfully in `install_dir`.
+pub fn already_ran(install_dir: &Path, step: &PostInstallStep) -> bool {
+    std::fs::read_to_string(install_dir.join(MARKER_FILE))
+        .is_ok_and(|marker| marker.trim() == step.fingerprint())
+}
+
+/// Runs `step` with `install_dir` as its working directory and a stripped
+/// environment, inside a [`Sandbox`]. The program must live inside the
+/// install dir; it and everything it started are killed when it outlives
+/// its timeout. A successful run is recorded so it won't run again for the
+/// same install.
+pub fn run(slug: &str, install_dir: &Path, step: &PostInstallStep) -> Result<PostInstallReport> {
+    let relative = step.command.trim().replace('\\', "/");
+    let contained = !relative.is_empty()
+        && Path::new(&relative)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !contained {
+        return Err(LauncherError::Config(format!(
+            "post-install command must be inside the install dir: {}",
+            step.command
+        )));
+    }
+    let program = install_dir.join(&relative);
+    if !program.is_file() {
+        return Err(LauncherError::NotFound(format!(
+            "post-install command {}",
+            program.display()
+        )));
+    }
+
+    let mut command = Command::new(&program);
+    command
+        .args(&step.args)
+        .current_dir(install_dir)
+        .env_clear()
+        .envs(
+            PASSTHROUGH_ENV
+                .iter()
+                .filter_map(|key| std::env::var(key).ok().map(|value| (*key, value))),
+        )
+        .env("OTOSHI_INSTALL_DIR", install_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW);
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let started = Instant::now();
+    let mut child = command.spawn().map_err(|err| {
+        LauncherError::Config(format!("failed to start post-install command: {err}"))
+    })?;
+    let sandbox = match Sandbox::contain(&child) {
+        Ok(sandbox) => sandbox,
+        Err(err) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(LauncherError::Config(format!(
+                "failed to sandbox post-install command: {err}"
+            )));
+        }
+    };
+    let stdout = child.stdout.take().map(capture);
+    let stderr = child.stderr.take().map(capture);
+
+    let timeout = Duration::from_secs(
+        step.timeout_secs
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .clamp(1, MAX_TIMEOUT_SECS),
+    );
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if started.elapsed() >= timeout {
+            timed_out = true;
+            sandbox.terminate(&mut child);
+            break None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let collect = |handle: Option<thread::JoinHandle<String>>| {
+        handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default()
+    };
+    // Closing the sandbox also ends anything the script left running, which
+    // would otherwise hold the pipes open.
+    drop(sandbox);
+    let exit_code = status.and_then(|status| status.code());
+    let success = status.is_some_and(|status| status.success());
+    if success {
+        std::fs::write(install_dir.join(MARKER_FILE), step.fingerprint())?;
+    }
+    Ok(PostInstallReport {
+        slug: slug.to_string(),
+        command: step.command.clone(),
+        success,
+        exit_code,
+        timed_out,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Drains a pipe on its own thread so a chatty script can't block on a full
+/// buffer; only the first `OUTPUT_LIMIT_BYTES` are kept.
+fn capture<R: Read + Send + 'static>(mut pipe: R) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut kept = Vec::new();
+        let mut buffer = [0_u8; 4096];
+        while let Ok(read) = pipe.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            let room = OUTPUT_LIMIT_BYTES.saturating_sub(kept.len());
+            kept.extend_from_slice(&buffer[..read.min(room)]);
+        }
+        String::from_utf8_lossy(&kept).into_owned()
+    })
+}
+
+/// Keeps a post-install script and every process it starts together. On
+/// Windows that is a job object which kills the whole tree when closed and
+/// denies access to the clipboard, desktops, display and system settings
+/// and shutdown; elsewhere the script leads its own process group.
+struct Sandbox {
+    #[cfg(target_os = "windows")]
+    job: *mut std::ffi::c_void,
+    #[cfg(unix)]
+    group: u32,
+}
+
+#[cfg(target_os = "windows")]
+mod job {
+    use std::ffi::c_void;
+
+    pub const EXTENDED_LIMIT_INFORMATION: i32 = 9;
+    pub const BASIC_UI_RESTRICTIONS: i32 = 4;
+    pub const LIMIT_DIE_ON_UNHANDLED_EXCEPTION: u32 = 0x0000_0400;
+    pub const LIMIT_KILL_ON_JOB_CLOSE: u32 = 0x0000_2000;
+    /// Everything but `HANDLES`, which would stop installer windows from
+    /// talking to each other.
+    pub const UI_RESTRICTIONS: u32 = 0x02 | 0x04 | 0x08 | 0x10 | 0x20 | 0x40 | 0x80;
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct BasicLimitInformation {
+        pub per_process_user_time_limit: i64,
+        pub per_job_user_time_limit: i64,
+        pub limit_flags: u32,
+        pub minimum_working_set_size: usize,
+        pub maximum_working_set_size: usize,
+        pub active_process_limit: u32,
+        pub affinity: usize,
+        pub priority_class: u32,
+        pub scheduling_class: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct ExtendedLimitInformation {
+        pub basic: BasicLimitInformation,
+        pub io_counters: [u64; 6],
+        pub process_memory_limit: usize,
+        pub job_memory_limit: usize,
+        pub peak_process_memory_used: usize,
+        pub peak_job_memory_used: usize,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn CreateJobObjectW(attributes: *mut c_void, name: *const u16) -> *mut c_void;
+        pub fn SetInformationJobObject(
+            job: *mut c_void,
+            class: i32,
+            info: *const c_void,
+            length: u32,
+        ) -> i32;
+        pub fn AssignProcessToJobObject(job: *mut c_void, process: *mut c_void) -> i32;
+        pub fn TerminateJobObject(job: *mut c_void, exit_code: u32) -> i32;
+        pub fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Sandbox {
+    fn contain(child: &Child) -> std::io::Result<Self> {
+        use std::os::windows::io::AsRawHandle;
+
+        let job = unsafe { job::CreateJobObjectW(std::ptr::null_mut(), std::ptr::null()) };
+        if job.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+        // Owned from here on, so an early return closes the handle.
+        let sandbox = Self { job };
+        let mut limits = job::ExtendedLimitInformation::default();
+        limits.basic.limit_flags =
+            job::LIMIT_KILL_ON_JOB_CLOSE | job::LIMIT_DIE_ON_UNHANDLED_EXCEPTION;
+        let ui = job::UI_RESTRICTIONS;
+        let ok = unsafe {
+            job::SetInformationJobObject(
+                job,
+                job::EXTENDED_LIMIT_INFORMATION,
+                &limits as *const _ as *const std::ffi::c_void,
+                std::mem::size_of::<job::ExtendedLimitInformation>() as u32,
+            ) != 0
+                && job::SetInformationJobObject(
+                    job,
+                    job::BASIC_UI_RESTRICTIONS,
+                    &ui as *const u32 as *const std::ffi::c_void,
+                    std::mem::size_of::<u32>() as u32,
+                ) != 0
+                && job::AssignProcessToJobObject(job, child.as_raw_handle()) != 0
+        };
+        if !ok {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(sandbox)
+    }
+
+    fn terminate(&self, child: &mut Child) {
+        unsafe {
+            job::TerminateJobObject(self.job, 1);
+        }
+        let _ = child.wait();
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        unsafe {
+            job::CloseHandle(self.job);
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Sandbox {
+    fn contain(child: &Child) -> std::io::Result<Self> {
+        Ok(Self { group: child.id() })
+    }
+
+    fn terminate(&self, child: &mut Child) {
+        self.kill_group();
+        let _ = child.wait();
+    }
+
+    fn kill_group(&self) {
+        let _ = Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", self.group)])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        self.kill_group();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install_dir() -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("otoshi-post-install-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes an executable script into `dir` and returns the step that runs
+    /// it.
+    fn script(dir: &Path, unix: &str, windows: &str) -> PostInstallStep {
+        let (name, body) = if cfg!(target_os = "windows") {
+            ("setup.cmd", format!("@echo off\r\n{windows}\r\n"))
+        } else {
+            ("setup.sh", format!("#!/bin/sh\n{unix}\n"))
+        };
+        let path = dir.join(name);
+        std::fs::write(&path, body).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        PostInstallStep {
+            command: name.to_string(),
+            args: Vec::new(),
+            timeout_secs: None,
+        }
+    }
+
+    #[test]
+    fn commands_outside_the_install_dir_are_refused() {
+        let dir = install_dir();
+        for command in ["../setup.sh", "/bin/sh", "", "sub/../../setup.sh"] {
+            let step = PostInstallStep {
+                command: command.to_string(),
+                args: Vec::new(),
+                timeout_secs: None,
+            };
+            assert!(run("game", &dir, &step).is_err(), "{command}");
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_successful_run_sees_the_install_env_and_is_recorded_once() {
+        let dir = install_dir();
+        std::env::set_var("OTOSHI_POST_INSTALL_SECRET", "leak");
+        let step = script(
+            &dir,
+            "echo \"dir=$OTOSHI_INSTALL_DIR secret=$OTOSHI_POST_INSTALL_SECRET\"",
+            "echo dir=%OTOSHI_INSTALL_DIR% secret=%OTOSHI_POST_INSTALL_SECRET%",
+        );
+        assert!(!already_ran(&dir, &step));
+
+        let report = run("game", &dir, &step).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(report.success, "{report:?}");
+        assert!(report.stdout.contains(&format!("dir={}", dir.display())));
+        assert!(!report.stdout.contains("leak"));
+        assert!(!report.timed_out);
+    }
+
+    #[test]
+    fn marker_matches_only_the_same_step() {
+        let dir = install_dir();
+        let step = script(&dir, "exit 0", "exit /b 0");
+        run("game", &dir, &step).unwrap();
+        let mut changed = step.clone();
+        changed.args.push("--repair".to_string());
+
+        assert!(already_ran(&dir, &step));
+        assert!(!already_ran(&dir, &changed));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failures_are_reported_and_not_recorded() {
+        let dir = install_dir();
+        let step = script(
+            &dir,
+            "echo broken >&2; exit 3",
+            "echo broken 1>&2\r\nexit /b 3",
+        );
+
+        let report = run("game", &dir, &step).unwrap();
+        let ran = already_ran(&dir, &step);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(!report.success);
+        assert_eq!(report.exit_code, Some(3));
+        assert!(report.stderr.contains("broken"));
+        assert!(!ran);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_on_both_pipes_is_drained_and_truncated() {
+        let dir = install_dir();
+        // Far more than a pipe buffer on stderr before anything on stdout.
+        let step = script(
+            &dir,
+            "head -c 1000000 /dev/zero | tr '\\0' e >&2; head -c 1000000 /dev/zero | tr '\\0' o",
+            "",
+        );
+
+        let report = run("game", &dir, &step).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(report.success, "{report:?}");
+        assert_eq!(report.stdout.len(), OUTPUT_LIMIT_BYTES);
+        assert_eq!(report.stderr.len(), OUTPUT_LIMIT_BYTES);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeouts_kill_the_script_and_what_it_started() {
+        let dir = install_dir();
+        let mut step = script(&dir, "sleep 30 &\nsleep 30", "");
+        step.timeout_secs = Some(1);
+
+        let started = Instant::now();
+        let report = run("game", &dir, &step).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(report.timed_out);
+        assert!(!report.success);
+        // The background sleep holds the pipes; returning means it died too.
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -1,17 +1,34 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
 
 use crate::errors::Result;
 use crate::models::Game;
 use crate::services::ApiClient;
 
+const DEFAULT_POLL_SECS: u64 = 15;
+const MIN_POLL_SECS: u64 = 5;
+const READY_STATUS: &str = "ready";
+
 #[derive(Clone)]
 pub struct RemoteDownloadService {
+    app_handle: AppHandle,
     api: ApiClient,
+    /// Last seen status per remote download id.
+    known: Arc<Mutex<HashMap<String, String>>>,
 }
 
 impl RemoteDownloadService {
-    pub fn new(api: ApiClient) -> Self {
-        Self { api }
+    pub fn new(app_handle: AppHandle, api: ApiClient) -> Self {
+        Self {
+            app_handle,
+            api,
+            known: Arc::new(Mutex::new(HashMap::new())),
+        }
     }
 
     pub async fn list(&self) -> Result<Vec<RemoteDownload>> {
@@ -30,8 +47,93 @@ impl RemoteDownloadService {
 
     pub async fn update_status(&self, download_id: &str, status: &str) -> Result<RemoteDownload> {
         let path = format!("/remote-downloads/{}/status?status={}", download_id, status);
-        self.api.post(&path, serde_json::json!({}), true).await
+        let download: RemoteDownload = self.api.post(&path, serde_json::json!({}), true).await?;
+        self.record(&download);
+        Ok(download)
+    }
+
+    /// Polls the remote queue and emits `remote-download-updated` for every
+    /// entry whose status changed. Entries that turn `ready` for this device
+    /// are handed to `on_ready`.
+    pub fn spawn_watcher<F, Fut>(&self, on_ready: F)
+    where
+        F: Fn(RemoteDownload) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let interval = Duration::from_secs(
+            std::env::var("LAUNCHER_REMOTE_DOWNLOAD_POLL_SECS")
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .unwrap_or(DEFAULT_POLL_SECS)
+                .max(MIN_POLL_SECS),
+        );
+        let service = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                match service.list().await {
+                    Ok(downloads) => {
+                        for download in service.changed(downloads) {
+                            let _ = service
+                                .app_handle
+                                .emit("remote-download-updated", &download);
+                            if download.status.eq_ignore_ascii_case(READY_STATUS)
+                                && targets_this_device(&download.target_device)
+                            {
+                                on_ready(download).await;
+                            }
+                        }
+                    }
+                    Err(err) => tracing::debug!("remote download poll failed: {}", err),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    /// Entries that are new or changed status since the last poll. Everything
+    /// counts as new on the first poll, so entries marked ready while the app
+    /// was closed still start.
+    fn changed(&self, downloads: Vec<RemoteDownload>) -> Vec<RemoteDownload> {
+        match self.known.lock() {
+            Ok(mut known) => diff_statuses(&mut known, downloads),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn record(&self, download: &RemoteDownload) {
+        if let Ok(mut known) = self.known.lock() {
+            known.insert(download.id.clone(), download.status.clone());
+        }
+    }
+}
+
+/// Updates `known` to the statuses in `downloads` and returns the entries
+/// that differ. Entries no longer listed are forgotten.
+fn diff_statuses(
+    known: &mut HashMap<String, String>,
+    downloads: Vec<RemoteDownload>,
+) -> Vec<RemoteDownload> {
+    let mut next = HashMap::with_capacity(downloads.len());
+    let mut changed = Vec::new();
+    for download in downloads {
+        next.insert(download.id.clone(), download.status.clone());
+        if known.get(&download.id) != Some(&download.status) {
+            changed.push(download);
+        }
+    }
+    *known = next;
+    changed
+}
+
+/// A remote entry targets this machine when its device matches the install
+/// id or the host name.
+fn targets_this_device(target_device: &str) -> bool {
+    let target = target_device.trim();
+    if target.is_empty() {
+        return false;
     }
+    crate::utils::client_identity::install_id().is_some_and(|id| id.eq_ignore_ascii_case(target))
+        || sysinfo::System::host_name().is_some_and(|host| host.eq_ignore_ascii_case(target))
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
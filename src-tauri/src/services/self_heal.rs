@@ -13,6 +13,7 @@ use uuid::Uuid;
 
 use crate::db::Database;
 use crate::errors::{LauncherError, Result};
+use crate::services::volatile_paths;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -54,6 +55,9 @@ pub struct SelfHealSummaryV2 {
     pub missing_files: usize,
     pub corrupt_files: usize,
     pub error_files: usize,
+    /// Present files under a volatile path, reported but not verified.
+    #[serde(default)]
+    pub volatile_files: usize,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -91,6 +95,57 @@ pub struct SelfHealRepairPlanV2 {
     pub queue: Vec<SelfHealRepairQueueItemV2>,
     pub strategy: String,
     pub generated_at: i64,
+    /// Download run refetching the queued files, once the plan is applied.
+    #[serde(default)]
+    pub download_id: Option<String>,
+    #[serde(default)]
+    pub refetch_bytes: u64,
+}
+
+/// One stored scan or repair, with the summary parsed back out of its report.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityEventV2 {
+    pub id: String,
+    pub game_id: String,
+    pub install_path: String,
+    /// `scan` or `repair`.
+    pub kind: String,
+    pub engine: String,
+    pub report_id: Option<String>,
+    pub version: Option<String>,
+    pub summary: SelfHealSummaryV2,
+    pub repair_queue_count: i64,
+    pub created_at: i64,
+}
+
+/// Corrupt and missing files found by scans on one UTC day.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityDayTotalV2 {
+    pub day: String,
+    pub scans: i64,
+    pub corrupt_files: i64,
+    pub missing_files: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityHistoryV2 {
+    pub events: Vec<IntegrityEventV2>,
+    /// Corrupt files found across every scan matching the filter, not just
+    /// the returned page.
+    pub total_corrupt_files: i64,
+    pub daily: Vec<IntegrityDayTotalV2>,
+}
+
+/// The parts of a stored `SelfHealReportV2` the history view needs.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredReportV2 {
+    report_id: Option<String>,
+    version: Option<String>,
+    summary: Option<SelfHealSummaryV2>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -103,6 +158,8 @@ struct ManifestV2 {
     version: Option<String>,
     #[serde(default)]
     files: Vec<ManifestFileV2>,
+    #[serde(default)]
+    volatile_paths: Vec<String>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -112,6 +169,8 @@ struct ManifestFileV2 {
     size: u64,
     #[serde(default)]
     hash: String,
+    #[serde(skip)]
+    volatile: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -144,7 +203,7 @@ impl SelfHealService {
         let queue: Vec<SelfHealRepairQueueItemV2> = report
             .files
             .iter()
-            .filter(|item| item.status != "ok")
+            .filter(|item| needs_repair(&item.status))
             .map(|item| SelfHealRepairQueueItemV2 {
                 path: item.path.clone(),
                 reason: item.reason.clone(),
@@ -165,8 +224,10 @@ impl SelfHealService {
             queue,
             strategy,
             generated_at: chrono::Utc::now().timestamp(),
+            download_id: None,
+            refetch_bytes: 0,
         };
-        self.persist_integrity_event(&report, plan.queue_count as i64)?;
+        self.persist_integrity_event(&report, "repair", plan.queue_count as i64)?;
         Ok(plan)
     }
 
@@ -179,12 +240,16 @@ impl SelfHealService {
             )));
         }
 
-        let manifest = self.resolve_manifest(&install_path, &request)?;
+        let mut manifest = self.resolve_manifest(&install_path, &request)?;
         let game_id = request
             .game_id
             .clone()
             .or(manifest.game_id.clone())
             .unwrap_or_else(|| "unknown".to_string());
+        let volatile = volatile_paths::resolve(&self.db, &game_id, &manifest.volatile_paths);
+        for entry in &mut manifest.files {
+            entry.volatile = volatile.matches(&entry.path);
+        }
         let version = request
             .version
             .clone()
@@ -235,10 +300,14 @@ impl SelfHealService {
                 .iter()
                 .filter(|item| item.status == "error")
                 .count(),
+            volatile_files: scanned_files
+                .iter()
+                .filter(|item| item.status == "volatile")
+                .count(),
         };
         let hot_fix_queue = scanned_files
             .iter()
-            .filter(|item| item.status != "ok")
+            .filter(|item| needs_repair(&item.status))
             .map(|item| item.path.clone())
             .collect::<Vec<_>>();
 
@@ -262,7 +331,7 @@ impl SelfHealService {
             scanned_at: chrono::Utc::now().timestamp(),
         };
         self.persist_file_index(&report)?;
-        self.persist_integrity_event(&report, report.hot_fix_queue.len() as i64)?;
+        self.persist_integrity_event(&report, "scan", report.hot_fix_queue.len() as i64)?;
         Ok(report)
     }
 
@@ -341,14 +410,20 @@ impl SelfHealService {
         Ok(())
     }
 
-    fn persist_integrity_event(&self, report: &SelfHealReportV2, queue_count: i64) -> Result<()> {
+    fn persist_integrity_event(
+        &self,
+        report: &SelfHealReportV2,
+        kind: &str,
+        queue_count: i64,
+    ) -> Result<()> {
         let conn = self.db.connection()?;
         let report_json = serde_json::to_string(report)?;
         conn.execute(
             "INSERT INTO integrity_events_v2
                 (id, game_id, install_path, scan_engine, total_files, verified_files,
-                 missing_files, corrupt_files, repair_queue_count, report_json, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                 missing_files, corrupt_files, repair_queue_count, report_json, created_at,
+                 event_kind)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             params![
                 Uuid::new_v4().to_string(),
                 report.game_id,
@@ -361,11 +436,106 @@ impl SelfHealService {
                 queue_count,
                 report_json,
                 chrono::Utc::now().timestamp(),
+                kind,
             ],
         )?;
         Ok(())
     }
 
+    /// Recent scan and repair events for a game, newest first, optionally
+    /// narrowed to one install path.
+    pub fn list_integrity_events(
+        &self,
+        game_id: &str,
+        install_path: Option<&str>,
+        limit: usize,
+    ) -> Result<IntegrityHistoryV2> {
+        let conn = self.db.connection()?;
+        let install_path = install_path
+            .map(str::trim)
+            .filter(|value| !value.is_empty());
+        let filter = "game_id = ?1 AND (?2 IS NULL OR install_path = ?2)";
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, game_id, install_path, event_kind, scan_engine, total_files,
+                    verified_files, missing_files, corrupt_files, repair_queue_count,
+                    report_json, created_at
+             FROM integrity_events_v2 WHERE {filter}
+             ORDER BY created_at DESC LIMIT ?3"
+        ))?;
+        let rows = stmt.query_map(params![game_id, install_path, limit as i64], |row| {
+            let report_json: String = row.get(10)?;
+            let stored = serde_json::from_str::<StoredReportV2>(&report_json).ok();
+            let columns = SelfHealSummaryV2 {
+                total_files: row.get::<_, i64>(5)?.max(0) as usize,
+                verified_files: row.get::<_, i64>(6)?.max(0) as usize,
+                missing_files: row.get::<_, i64>(7)?.max(0) as usize,
+                corrupt_files: row.get::<_, i64>(8)?.max(0) as usize,
+                error_files: 0,
+                volatile_files: 0,
+            };
+            let (report_id, version, summary) = match stored {
+                Some(report) => (
+                    report.report_id,
+                    report.version,
+                    report.summary.unwrap_or(columns),
+                ),
+                None => (None, None, columns),
+            };
+            Ok(IntegrityEventV2 {
+                id: row.get(0)?,
+                game_id: row.get(1)?,
+                install_path: row.get(2)?,
+                kind: row.get(3)?,
+                engine: row.get(4)?,
+                report_id,
+                version,
+                summary,
+                repair_queue_count: row.get(9)?,
+                created_at: row.get(11)?,
+            })
+        })?;
+        let mut events = Vec::new();
+        for item in rows {
+            events.push(item?);
+        }
+
+        // Repairs store the report they were planned from, so only scans
+        // count towards what was found.
+        let total_corrupt_files: i64 = conn.query_row(
+            &format!(
+                "SELECT COALESCE(SUM(corrupt_files), 0) FROM integrity_events_v2
+                 WHERE {filter} AND event_kind = 'scan'"
+            ),
+            params![game_id, install_path],
+            |row| row.get(0),
+        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT date(created_at, 'unixepoch') AS day, COUNT(*),
+                    SUM(corrupt_files), SUM(missing_files)
+             FROM integrity_events_v2 WHERE {filter} AND event_kind = 'scan'
+             GROUP BY day ORDER BY day ASC"
+        ))?;
+        let rows = stmt.query_map(params![game_id, install_path], |row| {
+            Ok(IntegrityDayTotalV2 {
+                day: row.get(0)?,
+                scans: row.get(1)?,
+                corrupt_files: row.get(2)?,
+                missing_files: row.get(3)?,
+            })
+        })?;
+        let mut daily = Vec::new();
+        for item in rows {
+            daily.push(item?);
+        }
+
+        Ok(IntegrityHistoryV2 {
+            events,
+            total_corrupt_files,
+            daily,
+        })
+    }
+
     fn resolve_workers(value: Option<usize>) -> usize {
         let cores = std::thread::available_parallelism()
             .map(|value| value.get())
@@ -379,6 +549,11 @@ fn normalize_relative_path(path: &str) -> String {
     path.replace('\\', "/").trim_start_matches('/').to_string()
 }
 
+/// Volatile files are informational; only real failures get refetched.
+fn needs_repair(status: &str) -> bool {
+    status != "ok" && status != "volatile"
+}
+
 fn scan_with_usn_delta(
     db: &Database,
     install_path: &Path,
@@ -401,7 +576,7 @@ fn scan_with_usn_delta(
             continue;
         }
 
-        if !changed_paths.contains(&relative) {
+        if !entry.volatile && !changed_paths.contains(&relative) {
             if let Some(snapshot) = index_map.get(&relative) {
                 if let Some(cached) = try_reuse_cached_entry(install_path, entry, &relative, snapshot) {
                     immediate.push(cached);
@@ -605,6 +780,20 @@ fn scan_entry(install_path: &Path, entry: &ManifestFileV2) -> SelfHealFileEntryV
         .map(|value| value.as_secs() as i64)
         .unwrap_or(0);
 
+    if entry.volatile {
+        return SelfHealFileEntryV2 {
+            path: relative,
+            expected_size: entry.size,
+            actual_size,
+            expected_sha256: expected_hash,
+            actual_sha256: None,
+            fast_hash_blake3: None,
+            status: "volatile".to_string(),
+            reason: "volatile_path".to_string(),
+            modified_at,
+        };
+    }
+
     if entry.size > 0 && actual_size != entry.size {
         return SelfHealFileEntryV2 {
             path: relative,
@@ -35,11 +35,11 @@ impl WorkerConfig {
 }
 
 #[derive(Debug, Deserialize)]
-struct LocaleSettingsResponse {
+pub(crate) struct LocaleSettingsResponse {
     #[serde(default)]
-    locale: Option<String>,
+    pub(crate) locale: Option<String>,
     #[serde(default, alias = "systemLocale")]
-    system_locale: Option<String>,
+    pub(crate) system_locale: Option<String>,
 }
 
 fn read_env_bool(key: &str, default: bool) -> bool {
@@ -1,21 +1,82 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
 
 use crate::errors::Result;
 use crate::services::ApiClient;
 
+const DEFAULT_NEGOTIATION_TIMEOUT_SECS: u64 = 30;
+
 #[derive(Clone)]
 pub struct StreamingService {
+    app_handle: AppHandle,
     api: ApiClient,
+    negotiation_timeout: Duration,
+    pending: Arc<Mutex<PendingTimeouts>>,
+}
+
+/// Sessions waiting on the remote peer, each with the token of its latest
+/// timeout. Tokens come from one counter that never resets, so a timer
+/// armed before a disarm can't match one armed after it.
+#[derive(Default)]
+struct PendingTimeouts {
+    next_token: u64,
+    armed: HashMap<String, u64>,
+}
+
+impl PendingTimeouts {
+    fn arm(&mut self, session_id: &str) -> u64 {
+        self.next_token += 1;
+        self.armed.insert(session_id.to_string(), self.next_token);
+        self.next_token
+    }
+
+    fn disarm(&mut self, session_id: &str) {
+        self.armed.remove(session_id);
+    }
+
+    /// Whether the timer holding `token` is still the latest for the
+    /// session; if so the session stops waiting.
+    fn expire(&mut self, session_id: &str, token: u64) -> bool {
+        let current = self.armed.get(session_id) == Some(&token);
+        if current {
+            self.armed.remove(session_id);
+        }
+        current
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StreamingSessionFailed {
+    pub session_id: String,
+    pub reason: String,
 }
 
 impl StreamingService {
-    pub fn new(api: ApiClient) -> Self {
-        Self { api }
+    /// `LAUNCHER_STREAMING_TIMEOUT_SECS` sets how long a session may wait for
+    /// an offer or answer before it is failed.
+    pub fn new(app_handle: AppHandle, api: ApiClient) -> Self {
+        let timeout_secs = std::env::var("LAUNCHER_STREAMING_TIMEOUT_SECS")
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(DEFAULT_NEGOTIATION_TIMEOUT_SECS);
+        Self {
+            app_handle,
+            api,
+            negotiation_timeout: Duration::from_secs(timeout_secs),
+            pending: Arc::new(Mutex::new(PendingTimeouts::default())),
+        }
     }
 
     pub async fn create_session(&self, game_id: Option<&str>) -> Result<StreamingSession> {
         let payload = serde_json::json!({ "game_id": game_id });
-        self.api.post("/streaming/sessions", payload, true).await
+        let session: StreamingSession = self.api.post("/streaming/sessions", payload, true).await?;
+        self.arm_timeout(&session.id, "offer_timeout");
+        Ok(session)
     }
 
     pub async fn get_session(&self, session_id: &str) -> Result<StreamingSession> {
@@ -29,7 +90,9 @@ impl StreamingService {
         offer: serde_json::Value,
     ) -> Result<StreamingSession> {
         let path = format!("/streaming/sessions/{}/offer", session_id);
-        self.api.post(&path, offer, true).await
+        let session = self.api.post(&path, offer, true).await?;
+        self.arm_timeout(session_id, "answer_timeout");
+        Ok(session)
     }
 
     pub async fn set_answer(
@@ -38,7 +101,68 @@ impl StreamingService {
         answer: serde_json::Value,
     ) -> Result<StreamingSession> {
         let path = format!("/streaming/sessions/{}/answer", session_id);
-        self.api.post(&path, answer, true).await
+        let session = self.api.post(&path, answer, true).await?;
+        self.disarm_timeout(session_id);
+        Ok(session)
+    }
+
+    /// Asks both peers to renegotiate ICE on the existing session, e.g. after
+    /// a network change. The frontend answers the `streaming-ice-restart`
+    /// event with a new offer; the answer timeout applies again.
+    pub async fn restart_ice(&self, session_id: &str) -> Result<StreamingSession> {
+        let path = format!("/streaming/sessions/{}/ice/restart", session_id);
+        let session: StreamingSession = self.api.post(&path, serde_json::json!({}), true).await?;
+        self.arm_timeout(session_id, "ice_restart_timeout");
+        let _ = self.app_handle.emit("streaming-ice-restart", &session);
+        Ok(session)
+    }
+
+    /// Fails the session unless the remote side responds within the timeout.
+    /// Re-arming replaces any earlier timeout for the same session.
+    fn arm_timeout(&self, session_id: &str, reason: &'static str) {
+        let token = match self.pending.lock() {
+            Ok(mut pending) => pending.arm(session_id),
+            Err(_) => return,
+        };
+        let service = self.clone();
+        let session_id = session_id.to_string();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(service.negotiation_timeout).await;
+            let expired = service
+                .pending
+                .lock()
+                .map(|mut pending| pending.expire(&session_id, token))
+                .unwrap_or(false);
+            if expired {
+                service.fail_session(&session_id, reason).await;
+            }
+        });
+    }
+
+    fn disarm_timeout(&self, session_id: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.disarm(session_id);
+        }
+    }
+
+    async fn fail_session(&self, session_id: &str, reason: &str) {
+        tracing::warn!("streaming session {} failed: {}", session_id, reason);
+        let path = format!("/streaming/sessions/{}/status", session_id);
+        let payload = serde_json::json!({ "status": "failed", "reason": reason });
+        if let Err(err) = self
+            .api
+            .post::<StreamingSession, _>(&path, payload, true)
+            .await
+        {
+            tracing::debug!("failed to report streaming session failure: {}", err);
+        }
+        let _ = self.app_handle.emit(
+            "streaming-session-failed",
+            StreamingSessionFailed {
+                session_id: session_id.to_string(),
+                reason: reason.to_string(),
+            },
+        );
     }
 
     pub async fn add_ice_candidate(
@@ -63,3 +187,25 @@ pub struct StreamingSession {
     pub created_at: String,
     pub updated_at: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timers_from_before_a_disarm_never_fire_after_a_rearm() {
+        let mut pending = PendingTimeouts::default();
+        let offer = pending.arm("s1");
+        pending.disarm("s1");
+        let restart = pending.arm("s1");
+
+        assert!(!pending.expire("s1", offer));
+        assert!(pending.expire("s1", restart));
+        assert!(!pending.expire("s1", restart));
+
+        let first = pending.arm("s2");
+        let second = pending.arm("s2");
+        assert!(!pending.expire("s2", first));
+        assert!(pending.expire("s2", second));
+    }
+}
@@ -1,26 +1,120 @@
-use crate::errors::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db::queries::{SettingsQueries, TelemetryQueueQueries};
+use crate::db::Database;
+use crate::errors::{LauncherError, Result};
 use crate::models::TelemetryEvent;
 use crate::services::ApiClient;
 
+const TELEMETRY_ENABLED_SETTING: &str = "telemetry_enabled";
+const MAX_QUEUED_EVENTS: usize = 500;
+const FLUSH_BATCH_SIZE: usize = 100;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(120);
+
 #[derive(Clone)]
 pub struct TelemetryService {
     api: ApiClient,
+    db: Database,
+    enabled: Arc<AtomicBool>,
 }
 
 impl TelemetryService {
-    pub fn new(api: ApiClient) -> Self {
-        Self { api }
+    /// Telemetry stays off until the user has opted in.
+    pub fn new(api: ApiClient, db: Database) -> Self {
+        let enabled = db
+            .get_setting(TELEMETRY_ENABLED_SETTING)
+            .ok()
+            .flatten()
+            .map(|value| value == "1")
+            .unwrap_or(false);
+        Self {
+            api: api.background(),
+            db,
+            enabled: Arc::new(AtomicBool::new(enabled)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Persists the consent choice. Opting out also discards anything still
+    /// waiting in the local queue.
+    pub fn set_enabled(&self, enabled: bool) -> Result<()> {
+        self.db
+            .set_setting(TELEMETRY_ENABLED_SETTING, if enabled { "1" } else { "0" })?;
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.db.clear_telemetry()?;
+        }
+        Ok(())
     }
 
+    /// Sends an event, or drops it when telemetry is disabled. Events that
+    /// can't reach the backend are queued and sent with the next flush.
     pub async fn send_event(&self, name: &str, payload: serde_json::Value) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
         let event = TelemetryEvent {
             name: name.to_string(),
             payload,
         };
-        let _: Vec<serde_json::Value> = self
-            .api
-            .post("telemetry/events", vec![event], false)
-            .await?;
+        match self.post(vec![event.clone()]).await {
+            Ok(()) => {
+                if let Err(err) = self.flush_queue().await {
+                    tracing::debug!("telemetry queue flush failed: {}", err);
+                }
+                Ok(())
+            }
+            Err(LauncherError::Network(err)) => {
+                tracing::debug!("telemetry offline, queueing {}: {}", event.name, err);
+                self.db.enqueue_telemetry(&event, MAX_QUEUED_EVENTS)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Sends queued events oldest first. Returns how many were delivered.
+    pub async fn flush_queue(&self) -> Result<usize> {
+        let mut sent = 0usize;
+        while self.is_enabled() {
+            let batch = self.db.list_telemetry(FLUSH_BATCH_SIZE)?;
+            let Some(last_id) = batch.last().map(|(id, _)| *id) else {
+                break;
+            };
+            let count = batch.len();
+            self.post(batch.into_iter().map(|(_, event)| event).collect())
+                .await?;
+            self.db.delete_telemetry_through(last_id)?;
+            sent += count;
+        }
+        Ok(sent)
+    }
+
+    /// Retries queued events periodically so they go out once the backend is
+    /// reachable again, even if no new event triggers a flush.
+    pub fn spawn_flush_worker(&self) {
+        let service = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(FLUSH_INTERVAL).await;
+                if !service.is_enabled() {
+                    continue;
+                }
+                match service.flush_queue().await {
+                    Ok(0) => {}
+                    Ok(sent) => tracing::info!("flushed {} queued telemetry events", sent),
+                    Err(err) => tracing::debug!("telemetry queue flush failed: {}", err),
+                }
+            }
+        });
+    }
+
+    async fn post(&self, events: Vec<TelemetryEvent>) -> Result<()> {
+        let _: Vec<serde_json::Value> = self.api.post("telemetry/events", events, false).await?;
         Ok(())
     }
 }
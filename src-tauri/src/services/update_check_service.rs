@@ -0,0 +1,561 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use reqwest::header::{ETAG, IF_NONE_MATCH, RETRY_AFTER};
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::db::queries::{DownloadStateQueries, GameUpdateQueries};
+use crate::db::Database;
+use crate::errors::{LauncherError, Result};
+use crate::models::{DownloadState, GameUpdateStatus, LibraryUpdate, LibraryUpdateReport};
+use crate::services::download_manager::{manifest_request_path, normalize_manifest_path};
+use crate::services::ApiClient;
+
+const STARTUP_DELAY: Duration = Duration::from_secs(90);
+const DEFAULT_INTERVAL_SECS: u64 = 6 * 60 * 60;
+const MIN_INTERVAL_SECS: u64 = 5 * 60;
+const MAX_BACKOFF_SECS: u64 = 24 * 60 * 60;
+
+/// Periodically compares installed games against the latest manifest and
+/// flags the ones with an update, with how much it would download. Nothing is
+/// downloaded.
+#[derive(Clone)]
+pub struct UpdateCheckService {
+    app_handle: AppHandle,
+    api: ApiClient,
+    db: Database,
+}
+
+/// Why a check cycle stopped before covering every game.
+enum CheckStop {
+    Offline,
+    Throttled(Option<Duration>),
+}
+
+enum ManifestFetch {
+    Unchanged,
+    Changed {
+        manifest: ManifestSummary,
+        etag: Option<String>,
+    },
+}
+
+/// The parts of a manifest needed to tell whether and how much changed.
+#[derive(Deserialize, Default)]
+struct ManifestSummary {
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    build_id: String,
+    #[serde(default)]
+    files: Vec<ManifestSummaryFile>,
+}
+
+#[derive(Deserialize)]
+struct ManifestSummaryFile {
+    path: String,
+    size: u64,
+    #[serde(default)]
+    hash: String,
+}
+
+impl ManifestSummary {
+    /// `version+build_id`, or just the version when there's no build id.
+    fn label(&self) -> String {
+        let version = self.version.trim();
+        let build = self.build_id.trim();
+        if build.is_empty() {
+            version.to_string()
+        } else {
+            format!("{version}+{build}")
+        }
+    }
+}
+
+impl UpdateCheckService {
+    pub fn new(app_handle: AppHandle, api: ApiClient, db: Database) -> Self {
+        Self {
+            app_handle,
+            api: api.background(),
+            db,
+        }
+    }
+
+    /// Checks every installed game once and emits `update-available` for
+    /// games that were newly flagged. Returns those games.
+    pub async fn check_all(&self) -> Result<Vec<GameUpdateStatus>> {
+        let (flagged, _) = self.run_cycle().await?;
+        Ok(flagged)
+    }
+
+    pub fn list_available(&self) -> Result<Vec<GameUpdateStatus>> {
+        self.db.list_available_updates()
+    }
+
+    /// Checks every installed game now and returns the library's view of the
+    /// result.
+    pub async fn check_library(&self) -> Result<LibraryUpdateReport> {
+        self.run_cycle().await?;
+        Ok(self
+            .library_report()?
+            .unwrap_or_else(|| LibraryUpdateReport {
+                checked_at: chrono::Utc::now().timestamp(),
+                updates: Vec::new(),
+            }))
+    }
+
+    /// The games flagged by the last checks, with their download sizes.
+    /// `None` until a check has run.
+    pub fn library_report(&self) -> Result<Option<LibraryUpdateReport>> {
+        let Some(checked_at) = self.db.last_update_check()? else {
+            return Ok(None);
+        };
+        Ok(Some(LibraryUpdateReport {
+            checked_at,
+            updates: self
+                .db
+                .list_available_updates()?
+                .iter()
+                .map(library_update)
+                .collect(),
+        }))
+    }
+
+    pub fn spawn_worker(&self) {
+        let interval = Duration::from_secs(
+            std::env::var("LAUNCHER_UPDATE_CHECK_INTERVAL_SECS")
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .unwrap_or(DEFAULT_INTERVAL_SECS)
+                .max(MIN_INTERVAL_SECS),
+        );
+        let service = self.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(STARTUP_DELAY).await;
+            let mut delay = interval;
+            loop {
+                delay = match service.run_cycle().await {
+                    Ok((flagged, None)) => {
+                        if !flagged.is_empty() {
+                            tracing::info!("{} games have updates available", flagged.len());
+                        }
+                        interval
+                    }
+                    Ok((_, Some(CheckStop::Offline))) => interval,
+                    Ok((_, Some(CheckStop::Throttled(retry_after)))) => {
+                        let backoff = (delay * 2).min(Duration::from_secs(MAX_BACKOFF_SECS));
+                        retry_after.map_or(backoff, |wait| wait.max(interval))
+                    }
+                    Err(err) => {
+                        tracing::debug!("update check failed: {}", err);
+                        interval
+                    }
+                };
+                tokio::time::sleep(delay).await;
+            }
+        });
+    }
+
+    async fn run_cycle(&self) -> Result<(Vec<GameUpdateStatus>, Option<CheckStop>)> {
+        let mut flagged = Vec::new();
+        for installed in self.db.list_completed_download_states()? {
+            match self.check_game(&installed).await {
+                Ok(Some(status)) => {
+                    let _ = self.app_handle.emit("update-available", &status);
+                    flagged.push(status);
+                }
+                Ok(None) => {}
+                Err(LauncherError::Network(err)) => {
+                    tracing::debug!("update check skipped, backend unreachable: {}", err);
+                    return Ok((flagged, Some(CheckStop::Offline)));
+                }
+                Err(LauncherError::Http(message)) if is_back_pressure(&message) => {
+                    tracing::debug!("update check throttled: {}", message);
+                    let retry_after = message
+                        .rsplit_once("retry-after=")
+                        .and_then(|(_, secs)| secs.trim().parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    return Ok((flagged, Some(CheckStop::Throttled(retry_after))));
+                }
+                Err(err) => tracing::warn!("update check failed slug={}: {}", installed.slug, err),
+            }
+        }
+        Ok((flagged, None))
+    }
+
+    /// Returns the status when this check newly flags an update.
+    async fn check_game(&self, installed: &DownloadState) -> Result<Option<GameUpdateStatus>> {
+        let installed_manifest =
+            serde_json::from_str::<ManifestSummary>(&installed.manifest_json).unwrap_or_default();
+        let previous = self.db.get_game_update(&installed.game_id)?;
+        let (latest_version, etag, delta) = match self
+            .fetch_manifest(&installed.slug, previous.as_ref())
+            .await?
+        {
+            ManifestFetch::Changed { manifest, etag } => (
+                manifest.label(),
+                etag,
+                manifest_delta(&installed_manifest, &manifest),
+            ),
+            ManifestFetch::Unchanged => match previous.as_ref() {
+                Some(previous) => (
+                    previous.latest_version.clone(),
+                    previous.etag.clone(),
+                    (previous.changed_files, previous.delta_bytes),
+                ),
+                None => return Ok(None),
+            },
+        };
+
+        let (status, newly_flagged) = evaluate_update(
+            previous.as_ref(),
+            installed,
+            installed_manifest.label(),
+            latest_version,
+            etag,
+            delta,
+            chrono::Utc::now().timestamp(),
+        );
+        self.db.upsert_game_update(&status)?;
+        Ok(newly_flagged.then_some(status))
+    }
+
+    /// Conditional GET on the manifest so unchanged games cost a 304.
+    async fn fetch_manifest(
+        &self,
+        slug: &str,
+        previous: Option<&GameUpdateStatus>,
+    ) -> Result<ManifestFetch> {
+        let path = manifest_request_path(slug, None);
+        let mut request = self.api.raw_request(Method::GET, &path, true).await?;
+        if let Some(etag) = previous.and_then(|status| status.etag.as_deref()) {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(ManifestFetch::Unchanged);
+        }
+        if !status.is_success() {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| format!(" retry-after={}", value.trim()))
+                .unwrap_or_default();
+            return Err(LauncherError::Http(format!("HTTP {status}:{retry_after}")));
+        }
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok(ManifestFetch::Changed {
+            manifest: response.json().await?,
+            etag,
+        })
+    }
+}
+
+fn is_back_pressure(message: &str) -> bool {
+    message.starts_with("HTTP 429") || message.starts_with("HTTP 503")
+}
+
+/// Builds the stored status for one check. The flag is reported as new only
+/// when it turns on or the latest version moves on while it's already on, so
+/// the UI isn't re-notified every cycle.
+fn evaluate_update(
+    previous: Option<&GameUpdateStatus>,
+    installed: &DownloadState,
+    installed_version: String,
+    latest_version: String,
+    etag: Option<String>,
+    (changed_files, delta_bytes): (usize, u64),
+    now: i64,
+) -> (GameUpdateStatus, bool) {
+    let update_available = is_newer_version(&installed_version, &latest_version);
+    let (changed_files, delta_bytes) = if update_available {
+        (changed_files, delta_bytes)
+    } else {
+        (0, 0)
+    };
+    let newly_flagged = update_available
+        && previous.is_none_or(|previous| {
+            !previous.update_available || previous.latest_version != latest_version
+        });
+    let status = GameUpdateStatus {
+        game_id: installed.game_id.clone(),
+        slug: installed.slug.clone(),
+        installed_version,
+        latest_version,
+        etag,
+        update_available,
+        checked_at: now,
+        changed_files,
+        delta_bytes,
+    };
+    (status, newly_flagged)
+}
+
+/// Files of `latest` that are new or differ from `installed`, and their
+/// total size.
+fn manifest_delta(installed: &ManifestSummary, latest: &ManifestSummary) -> (usize, u64) {
+    let existing: HashMap<String, (&str, u64)> = installed
+        .files
+        .iter()
+        .map(|file| {
+            (
+                normalize_manifest_path(&file.path),
+                (file.hash.as_str(), file.size),
+            )
+        })
+        .collect();
+    latest
+        .files
+        .iter()
+        .filter(|file| {
+            existing
+                .get(&normalize_manifest_path(&file.path))
+                .is_none_or(|(hash, size)| {
+                    *size != file.size || hash.is_empty() || !hash.eq_ignore_ascii_case(&file.hash)
+                })
+        })
+        .fold((0, 0), |(count, bytes), file| {
+            (count + 1, bytes + file.size)
+        })
+}
+
+/// A flagged game as the library shows it, with the labels split back into
+/// version and build id.
+fn library_update(status: &GameUpdateStatus) -> LibraryUpdate {
+    let (installed_version, installed_build_id) = split_label(&status.installed_version);
+    let (latest_version, latest_build_id) = split_label(&status.latest_version);
+    LibraryUpdate {
+        game_id: status.game_id.clone(),
+        slug: status.slug.clone(),
+        installed_version: installed_version.to_string(),
+        installed_build_id: installed_build_id.to_string(),
+        latest_version: latest_version.to_string(),
+        latest_build_id: latest_build_id.to_string(),
+        changed_files: status.changed_files,
+        delta_bytes: status.delta_bytes,
+    }
+}
+
+/// Whether `latest` is an update over `installed`. Dotted numeric versions
+/// are compared numerically so a rollback isn't flagged; anything else counts
+/// as an update when it differs. Equal versions fall back to the build id.
+pub(crate) fn is_newer_version(installed: &str, latest: &str) -> bool {
+    let (installed_version, installed_build) = split_label(installed);
+    let (latest_version, latest_build) = split_label(latest);
+    if latest_version.is_empty() {
+        return false;
+    }
+    match (
+        parse_numeric(installed_version),
+        parse_numeric(latest_version),
+    ) {
+        (Some(current), Some(candidate)) => match compare_numeric(&current, &candidate) {
+            Ordering::Less => return true,
+            Ordering::Greater => return false,
+            Ordering::Equal => {}
+        },
+        _ if installed_version != latest_version => return true,
+        _ => {}
+    }
+    !installed_build.is_empty() && !latest_build.is_empty() && installed_build != latest_build
+}
+
+fn split_label(label: &str) -> (&str, &str) {
+    let label = label.trim();
+    label.split_once('+').unwrap_or((label, ""))
+}
+
+fn parse_numeric(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim_start_matches(['v', 'V']);
+    if version.is_empty() {
+        return None;
+    }
+    version
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect()
+}
+
+/// Compares with missing trailing parts treated as zero, so `1.2 == 1.2.0`.
+fn compare_numeric(left: &[u64], right: &[u64]) -> Ordering {
+    let len = left.len().max(right.len());
+    (0..len)
+        .map(|index| {
+            let a = left.get(index).copied().unwrap_or(0);
+            let b = right.get(index).copied().unwrap_or(0);
+            a.cmp(&b)
+        })
+        .find(|order| order.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installed_state() -> DownloadState {
+        DownloadState {
+            id: "dl-1".to_string(),
+            game_id: "game-1".to_string(),
+            slug: "game".to_string(),
+            status: "completed".to_string(),
+            install_dir: "/games/game".to_string(),
+            manifest_json: r#"{"version":"1.2.0","build_id":"100"}"#.to_string(),
+            updated_at: 0,
+            engine: None,
+            method: None,
+        }
+    }
+
+    #[test]
+    fn compares_versions_and_builds() {
+        assert!(is_newer_version("1.2.0", "1.10.0"));
+        assert!(is_newer_version("v1.2", "1.2.1"));
+        assert!(!is_newer_version("1.2", "1.2.0"));
+        assert!(!is_newer_version("2.0.0", "1.9.9"));
+        assert!(is_newer_version("1.2.0+100", "1.2.0+101"));
+        assert!(!is_newer_version("1.2.0+100", "1.2.0+100"));
+        assert!(!is_newer_version("1.2.0", "1.2.0+101"));
+        assert!(is_newer_version("beta-3", "beta-4"));
+        assert!(!is_newer_version("beta-3", ""));
+    }
+
+    #[test]
+    fn flags_updates_once_per_latest_version() {
+        let installed = installed_state();
+        let installed_version = serde_json::from_str::<ManifestSummary>(&installed.manifest_json)
+            .unwrap()
+            .label();
+        assert_eq!(installed_version, "1.2.0+100");
+
+        let (current, flagged) = evaluate_update(
+            None,
+            &installed,
+            installed_version.clone(),
+            "1.2.0+100".to_string(),
+            Some("\"a\"".to_string()),
+            (3, 90),
+            1,
+        );
+        assert!(!current.update_available);
+        assert!(!flagged);
+
+        let (first, flagged) = evaluate_update(
+            Some(&current),
+            &installed,
+            installed_version.clone(),
+            "1.3.0+120".to_string(),
+            Some("\"b\"".to_string()),
+            (3, 90),
+            2,
+        );
+        assert!(first.update_available);
+        assert!(flagged);
+        assert_eq!((first.changed_files, first.delta_bytes), (3, 90));
+
+        // Same latest version on the next cycle: still flagged, not re-emitted.
+        let (repeat, flagged) = evaluate_update(
+            Some(&first),
+            &installed,
+            installed_version.clone(),
+            "1.3.0+120".to_string(),
+            Some("\"b\"".to_string()),
+            (3, 90),
+            3,
+        );
+        assert!(repeat.update_available);
+        assert!(!flagged);
+
+        let (newer, flagged) = evaluate_update(
+            Some(&repeat),
+            &installed,
+            installed_version,
+            "1.4.0+130".to_string(),
+            None,
+            (3, 90),
+            4,
+        );
+        assert!(newer.update_available);
+        assert!(flagged);
+
+        // After the game is updated the flag clears.
+        let (cleared, flagged) = evaluate_update(
+            Some(&newer),
+            &installed,
+            "1.4.0+130".to_string(),
+            "1.4.0+130".to_string(),
+            None,
+            (3, 90),
+            5,
+        );
+        assert!(!cleared.update_available);
+        assert!(!flagged);
+        assert_eq!((cleared.changed_files, cleared.delta_bytes), (0, 0));
+        assert_eq!((current.changed_files, current.delta_bytes), (0, 0));
+    }
+
+    fn manifest(version: &str, files: &[(&str, u64, &str)]) -> ManifestSummary {
+        ManifestSummary {
+            version: version.to_string(),
+            build_id: String::new(),
+            files: files
+                .iter()
+                .map(|(path, size, hash)| ManifestSummaryFile {
+                    path: path.to_string(),
+                    size: *size,
+                    hash: hash.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn delta_counts_only_new_or_changed_files() {
+        let local = manifest(
+            "1.0",
+            &[("bin/game.exe", 100, "aa"), ("data/a.pak", 50, "bb")],
+        );
+        let remote = manifest(
+            "1.1",
+            &[
+                ("bin\\game.exe", 100, "AA"),
+                ("data/a.pak", 60, "cc"),
+                ("data/b.pak", 30, "dd"),
+            ],
+        );
+        assert_eq!(manifest_delta(&local, &remote), (2, 90));
+    }
+
+    #[test]
+    fn library_entries_split_versions_from_build_ids() {
+        let (status, _) = evaluate_update(
+            None,
+            &installed_state(),
+            "1.2.0+100".to_string(),
+            "1.3.0".to_string(),
+            None,
+            (2, 90),
+            1,
+        );
+        let update = library_update(&status);
+        assert_eq!(
+            (
+                update.installed_version.as_str(),
+                update.installed_build_id.as_str(),
+                update.latest_version.as_str(),
+                update.latest_build_id.as_str(),
+            ),
+            ("1.2.0", "100", "1.3.0", "")
+        );
+        assert_eq!((update.changed_files, update.delta_bytes), (2, 90));
+    }
+}
@@ -0,0 +1,751 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter};
+
+use crate::errors::{LauncherError, Result};
+use crate::runtime_integrity;
+use crate::services::patch_engine;
+use crate::services::update_check_service::is_newer_version;
+use crate::services::ApiClient;
+
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const PENDING_FILE: &str = "pending.json";
+const PATCH_FILE: &str = "update.patch";
+const CHUNK_ATTEMPTS: u32 = 3;
+const CHUNK_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A launcher release as published on the update feed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LauncherRelease {
+    pub version: String,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub published_at: Option<String>,
+    pub target: ReleaseTarget,
+    pub full: UpdatePackage,
+    /// Patches onto this version from earlier ones, usually just the last.
+    #[serde(default)]
+    pub deltas: Vec<DeltaPackage>,
+}
+
+/// The new executable. The signature is an Ed25519 signature over its
+/// bytes, made with the runtime integrity key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReleaseTarget {
+    pub size: u64,
+    pub sha256: String,
+    pub signature: String,
+    /// The release's `checksums.sha256` and its signature. They replace the
+    /// installed pair along with the executable, so the integrity check on
+    /// the next start matches the new build.
+    pub checksums: String,
+    pub checksums_signature: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpdatePackage {
+    pub size: u64,
+    pub sha256: String,
+    pub chunks: Vec<PackageChunk>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PackageChunk {
+    pub url: String,
+    pub offset: u64,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// An xdelta3 or zstd patch turning `from_version`'s executable into the
+/// release's.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeltaPackage {
+    pub from_version: String,
+    pub package: UpdatePackage,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LauncherUpdateStatus {
+    pub current_version: String,
+    pub latest_version: Option<String>,
+    pub update_available: bool,
+    pub notes: Option<String>,
+    /// Bytes the update costs to download: the delta when one applies to
+    /// the running version, otherwise the full package.
+    pub download_bytes: u64,
+    pub delta_available: bool,
+    /// Version waiting to replace the executable when the launcher quits.
+    pub staged_version: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct UpdateProgressPayload {
+    version: String,
+    stage: &'static str,
+    downloaded_bytes: u64,
+    total_bytes: u64,
+}
+
+/// A verified executable in the staging folder.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingUpdate {
+    version: String,
+    path: PathBuf,
+    size: u64,
+    sha256: String,
+}
+
+/// Updates the launcher itself: checks the release feed, downloads the next
+/// build (as a delta from the running version when the feed has one),
+/// verifies it and stages it. The staged build replaces the executable when
+/// the launcher quits, so the next start runs it.
+#[derive(Clone)]
+pub struct UpdateService {
+    app_handle: AppHandle,
+    api: ApiClient,
+    updates_dir: PathBuf,
+    staging: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl UpdateService {
+    pub fn new(app_handle: AppHandle, api: ApiClient, updates_dir: PathBuf) -> Self {
+        Self {
+            app_handle,
+            api,
+            updates_dir,
+            staging: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    pub async fn check(&self) -> Result<LauncherUpdateStatus> {
+        let release = self.fetch_release().await?;
+        Ok(self.status(release.as_ref()))
+    }
+
+    /// Downloads and stages the latest release when it's newer than both the
+    /// running and the already staged version.
+    pub async fn download_and_stage(&self) -> Result<LauncherUpdateStatus> {
+        let Ok(_staging) = self.staging.try_lock() else {
+            return Err(LauncherError::Config(
+                "a launcher update is already being downloaded".to_string(),
+            ));
+        };
+        let release = self.fetch_release().await?;
+        if let Some(release) = release.as_ref() {
+            let staged = load_pending(&self.updates_dir).map(|pending| pending.version);
+            let newer_than_staged = staged
+                .as_deref()
+                .is_none_or(|staged| is_newer_version(staged, &release.version));
+            if is_newer_version(CURRENT_VERSION, &release.version) && newer_than_staged {
+                self.stage(release).await?;
+            }
+        }
+        Ok(self.status(release.as_ref()))
+    }
+
+    /// Checks, then stages anything new in the background. Used by the tray.
+    pub fn spawn_check(&self) {
+        let service = self.clone();
+        tauri::async_runtime::spawn(async move {
+            match service.download_and_stage().await {
+                Ok(status) => {
+                    let _ = service.app_handle.emit("launcher-update-status", &status);
+                }
+                Err(err) => tracing::warn!("launcher update check failed: {}", err),
+            }
+        });
+    }
+
+    /// Moves the staged executable and its integrity manifest over the
+    /// installed ones; the running image is renamed aside first, which
+    /// Windows allows. Returns the version put in place.
+    pub fn apply_staged(&self) -> Result<Option<String>> {
+        apply_pending(&self.updates_dir, &std::env::current_exe()?)
+    }
+
+    /// Startup cleanup: removes the files an applied update replaced and
+    /// drops a staged build that's no longer newer than this one.
+    pub fn finish_previous(&self) {
+        match std::env::current_exe() {
+            Ok(exe) => clean_previous(&self.updates_dir, &exe, CURRENT_VERSION),
+            Err(err) => tracing::debug!("launcher executable not resolved: {}", err),
+        }
+    }
+
+    async fn fetch_release(&self) -> Result<Option<LauncherRelease>> {
+        let path = format!(
+            "launcher/releases/latest?platform={}&arch={}&current={}",
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            CURRENT_VERSION
+        );
+        let response = self
+            .api
+            .raw_request(Method::GET, &path, false)
+            .await?
+            .send()
+            .await?;
+        let status = response.status();
+        if status == StatusCode::NO_CONTENT || status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(LauncherError::Http(format!("HTTP {status}")));
+        }
+        Ok(Some(response.json().await?))
+    }
+
+    fn status(&self, release: Option<&LauncherRelease>) -> LauncherUpdateStatus {
+        let update_available =
+            release.is_some_and(|release| is_newer_version(CURRENT_VERSION, &release.version));
+        let delta = release.and_then(matching_delta);
+        LauncherUpdateStatus {
+            current_version: CURRENT_VERSION.to_string(),
+            latest_version: release.map(|release| release.version.clone()),
+            update_available,
+            notes: release.map(|release| release.notes.clone()),
+            download_bytes: match (release, delta) {
+                (_, Some(delta)) => delta.package.size,
+                (Some(release), None) => release.full.size,
+                (None, None) => 0,
+            },
+            delta_available: delta.is_some(),
+            staged_version: load_pending(&self.updates_dir).map(|pending| pending.version),
+        }
+    }
+
+    async fn stage(&self, release: &LauncherRelease) -> Result<()> {
+        let version_dir = self.updates_dir.join(sanitize_version(&release.version)?);
+        fs::create_dir_all(&version_dir)?;
+        let exe = std::env::current_exe()?;
+        let file_name = exe
+            .file_name()
+            .ok_or_else(|| LauncherError::Config("launcher executable has no name".to_string()))?;
+        let staged = version_dir.join(file_name);
+        let relative = file_name.to_string_lossy();
+
+        let mut patched = false;
+        if let Some(delta) = matching_delta(release) {
+            let patch = version_dir.join(PATCH_FILE);
+            let result = async {
+                self.download_package(&release.version, &delta.package, &patch)
+                    .await?;
+                self.emit_progress(&release.version, "patching", 0, release.target.size);
+                let (source, patch_path, output) = (exe.clone(), patch.clone(), staged.clone());
+                tauri::async_runtime::spawn_blocking(move || {
+                    patch_engine::apply_patch(&source, &patch_path, &output)
+                })
+                .await
+                .map_err(|err| LauncherError::Config(err.to_string()))??;
+                patch_engine::verify_output(
+                    &staged,
+                    Some(release.target.size),
+                    Some(release.target.sha256.as_str()),
+                )
+            }
+            .await;
+            let _ = fs::remove_file(&patch);
+            match result {
+                Ok(()) => patched = true,
+                Err(err) => tracing::warn!(
+                    "delta update from {} failed, downloading the full build: {}",
+                    delta.from_version,
+                    err
+                ),
+            }
+        }
+        if !patched {
+            self.download_package(&release.version, &release.full, &staged)
+                .await?;
+            patch_engine::verify_output(
+                &staged,
+                Some(release.target.size),
+                Some(release.target.sha256.as_str()),
+            )?;
+        }
+
+        self.emit_progress(&release.version, "verifying", 0, release.target.size);
+        runtime_integrity::verify_signed(&fs::read(&staged)?, &release.target.signature)?;
+        runtime_integrity::verify_release_manifest(
+            release.target.checksums.as_bytes(),
+            &release.target.checksums_signature,
+            &relative,
+            &release.target.sha256,
+        )?;
+        fs::write(
+            version_dir.join(runtime_integrity::CHECKSUM_FILE),
+            &release.target.checksums,
+        )?;
+        fs::write(
+            version_dir.join(runtime_integrity::SIGNATURE_FILE),
+            &release.target.checksums_signature,
+        )?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+        }
+
+        let pending = PendingUpdate {
+            version: release.version.clone(),
+            path: staged,
+            size: release.target.size,
+            sha256: release.target.sha256.trim().to_ascii_lowercase(),
+        };
+        let pending_path = self.updates_dir.join(PENDING_FILE);
+        let temp_path = pending_path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec_pretty(&pending)?)?;
+        fs::rename(&temp_path, &pending_path)?;
+        tracing::info!(
+            "launcher update {} staged ({})",
+            release.version,
+            if patched { "delta" } else { "full" }
+        );
+        let _ = self
+            .app_handle
+            .emit("launcher-update-staged", &pending.version);
+        Ok(())
+    }
+
+    /// Fetches a package chunk by chunk into `dest`. Chunks already on disk
+    /// with the right hash are kept, so an interrupted download resumes.
+    async fn download_package(
+        &self,
+        version: &str,
+        package: &UpdatePackage,
+        dest: &Path,
+    ) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(dest)?;
+        file.set_len(package.size)?;
+        let mut downloaded = 0_u64;
+        for chunk in &package.chunks {
+            if chunk.offset.saturating_add(chunk.size) > package.size {
+                return Err(LauncherError::Integrity(format!(
+                    "update chunk at {} runs past the package end",
+                    chunk.offset
+                )));
+            }
+            if !chunk_on_disk(&mut file, chunk)? {
+                let data = self.fetch_chunk(chunk).await?;
+                file.seek(SeekFrom::Start(chunk.offset))?;
+                file.write_all(&data)?;
+            }
+            downloaded += chunk.size;
+            self.emit_progress(version, "downloading", downloaded, package.size);
+        }
+        file.sync_all()?;
+        drop(file);
+        patch_engine::verify_output(dest, Some(package.size), Some(package.sha256.as_str()))
+    }
+
+    async fn fetch_chunk(&self, chunk: &PackageChunk) -> Result<Vec<u8>> {
+        let url = if chunk.url.starts_with("http://") || chunk.url.starts_with("https://") {
+            chunk.url.clone()
+        } else {
+            format!(
+                "{}/{}",
+                self.api.base_url().trim_end_matches('/'),
+                chunk.url.trim_start_matches('/')
+            )
+        };
+        let mut last_error = None;
+        for attempt in 1..=CHUNK_ATTEMPTS {
+            match self.fetch_chunk_once(&url, chunk).await {
+                Ok(data) => return Ok(data),
+                Err(err) => {
+                    tracing::debug!("update chunk attempt {} failed {}: {}", attempt, url, err);
+                    last_error = Some(err);
+                    tokio::time::sleep(Duration::from_secs(u64::from(attempt))).await;
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| LauncherError::Http("update chunk failed".to_string())))
+    }
+
+    async fn fetch_chunk_once(&self, url: &str, chunk: &PackageChunk) -> Result<Vec<u8>> {
+        let response = self
+            .api
+            .client()
+            .get(url)
+            .timeout(CHUNK_TIMEOUT)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(LauncherError::Http(format!("HTTP {status}")));
+        }
+        let data = response.bytes().await?.to_vec();
+        if data.len() as u64 != chunk.size || !hash_matches(&data, &chunk.sha256) {
+            return Err(LauncherError::Integrity(format!(
+                "update chunk at {} failed verification",
+                chunk.offset
+            )));
+        }
+        Ok(data)
+    }
+
+    fn emit_progress(&self, version: &str, stage: &'static str, downloaded: u64, total: u64) {
+        let _ = self.app_handle.emit(
+            "launcher-update-progress",
+            UpdateProgressPayload {
+                version: version.to_string(),
+                stage,
+                downloaded_bytes: downloaded,
+                total_bytes: total,
+            },
+        );
+    }
+}
+
+fn matching_delta(release: &LauncherRelease) -> Option<&DeltaPackage> {
+    release
+        .deltas
+        .iter()
+        .find(|delta| delta.from_version.trim() == CURRENT_VERSION)
+}
+
+fn chunk_on_disk(file: &mut File, chunk: &PackageChunk) -> Result<bool> {
+    let mut data = vec![0_u8; chunk.size as usize];
+    file.seek(SeekFrom::Start(chunk.offset))?;
+    if file.read_exact(&mut data).is_err() {
+        return Ok(false);
+    }
+    Ok(hash_matches(&data, &chunk.sha256))
+}
+
+fn hash_matches(data: &[u8], expected: &str) -> bool {
+    hex::encode(Sha256::digest(data)).eq_ignore_ascii_case(expected.trim())
+}
+
+fn load_pending(updates_dir: &Path) -> Option<PendingUpdate> {
+    let raw = fs::read(updates_dir.join(PENDING_FILE)).ok()?;
+    serde_json::from_slice::<PendingUpdate>(&raw)
+        .inspect_err(|err| tracing::warn!("ignoring invalid {PENDING_FILE}: {err}"))
+        .ok()
+        .filter(|pending| pending.path.is_file())
+}
+
+fn discard(updates_dir: &Path, pending: &PendingUpdate) {
+    if let Some(version_dir) = pending.path.parent() {
+        let _ = fs::remove_dir_all(version_dir);
+    }
+    let _ = fs::remove_file(updates_dir.join(PENDING_FILE));
+}
+
+/// The staged files paired with the installed ones they replace: the
+/// executable, then the integrity manifest and its signature.
+fn replacements(pending: &PendingUpdate, exe: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let (Some(staged_dir), Some(exe_dir)) = (pending.path.parent(), exe.parent()) else {
+        return Err(LauncherError::Config(
+            "launcher executable has no folder".to_string(),
+        ));
+    };
+    let mut pairs = vec![(pending.path.clone(), exe.to_path_buf())];
+    for name in [
+        runtime_integrity::CHECKSUM_FILE,
+        runtime_integrity::SIGNATURE_FILE,
+    ] {
+        pairs.push((staged_dir.join(name), exe_dir.join(name)));
+    }
+    Ok(pairs)
+}
+
+fn apply_pending(updates_dir: &Path, exe: &Path) -> Result<Option<String>> {
+    let Some(pending) = load_pending(updates_dir) else {
+        return Ok(None);
+    };
+    patch_engine::verify_output(
+        &pending.path,
+        Some(pending.size),
+        Some(pending.sha256.as_str()),
+    )?;
+    let pairs = replacements(&pending, exe)?;
+    if let Some((missing, _)) = pairs.iter().find(|(staged, _)| !staged.is_file()) {
+        // Staged by a build that didn't carry the manifest; the executable
+        // alone would fail the integrity check on the next start.
+        discard(updates_dir, &pending);
+        return Err(LauncherError::Integrity(format!(
+            "staged launcher update is missing {}",
+            missing.display()
+        )));
+    }
+    replace_files(&pairs)?;
+    discard(updates_dir, &pending);
+    tracing::info!("launcher update {} applied", pending.version);
+    Ok(Some(pending.version))
+}
+
+/// Moves each staged file over its target, keeping the target aside as
+/// `.old`. When one move fails, every target already replaced is put back,
+/// so the install never mixes the old and new builds.
+fn replace_files(pairs: &[(PathBuf, PathBuf)]) -> Result<()> {
+    let mut replaced: Vec<(&Path, bool)> = Vec::new();
+    for (staged, target) in pairs {
+        match replace_file(staged, target) {
+            Ok(had_previous) => replaced.push((target, had_previous)),
+            Err(err) => {
+                for (target, had_previous) in replaced.into_iter().rev() {
+                    let restored = if had_previous {
+                        fs::rename(previous_path(target), target)
+                    } else {
+                        fs::remove_file(target)
+                    };
+                    if let Err(restore_err) = restored {
+                        tracing::error!(
+                            "launcher update rollback failed for {}: {}",
+                            target.display(),
+                            restore_err
+                        );
+                    }
+                }
+                return Err(err);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Moves `staged` over `target`, keeping the target aside. Returns whether
+/// there was a target to keep.
+fn replace_file(staged: &Path, target: &Path) -> Result<bool> {
+    let previous = previous_path(target);
+    if previous.exists() {
+        fs::remove_file(&previous)?;
+    }
+    let had_previous = target.exists();
+    if had_previous {
+        fs::rename(target, &previous)?;
+    }
+    if let Err(err) = fs::rename(staged, target).or_else(|_| fs::copy(staged, target).map(|_| ())) {
+        if had_previous {
+            let _ = fs::rename(&previous, target);
+        }
+        return Err(err.into());
+    }
+    Ok(had_previous)
+}
+
+fn clean_previous(updates_dir: &Path, exe: &Path, current_version: &str) {
+    let mut targets = vec![exe.to_path_buf()];
+    if let Some(exe_dir) = exe.parent() {
+        targets.push(exe_dir.join(runtime_integrity::CHECKSUM_FILE));
+        targets.push(exe_dir.join(runtime_integrity::SIGNATURE_FILE));
+    }
+    for target in targets {
+        let previous = previous_path(&target);
+        if previous.exists() {
+            if let Err(err) = fs::remove_file(&previous) {
+                tracing::debug!("replaced launcher file not removed yet: {}", err);
+            }
+        }
+    }
+    if let Some(pending) = load_pending(updates_dir) {
+        if !is_newer_version(current_version, &pending.version) {
+            discard(updates_dir, &pending);
+        }
+    }
+}
+
+fn previous_path(target: &Path) -> PathBuf {
+    let mut name = target.as_os_str().to_owned();
+    name.push(".old");
+    PathBuf::from(name)
+}
+
+/// The version becomes a folder name, so it may not contain separators.
+fn sanitize_version(version: &str) -> Result<String> {
+    let version = version.trim();
+    let valid = !version.is_empty()
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | '_'))
+        && !version.starts_with('.');
+    if !valid {
+        return Err(LauncherError::Config(format!(
+            "invalid launcher release version: {version}"
+        )));
+    }
+    Ok(version.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_only_chunks_that_verify() {
+        let dir = std::env::temp_dir().join(format!("otoshi-update-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("package.bin");
+        fs::write(&path, b"hello world!").unwrap();
+        let chunk = |offset: u64, data: &[u8]| PackageChunk {
+            url: String::new(),
+            offset,
+            size: data.len() as u64,
+            sha256: hex::encode(Sha256::digest(data)),
+        };
+
+        let mut file = File::open(&path).unwrap();
+        assert!(chunk_on_disk(&mut file, &chunk(0, b"hello")).unwrap());
+        assert!(chunk_on_disk(&mut file, &chunk(6, b"world!")).unwrap());
+        assert!(!chunk_on_disk(&mut file, &chunk(6, b"earth!")).unwrap());
+        assert!(!chunk_on_disk(&mut file, &chunk(10, b"past end")).unwrap());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn versions_must_be_plain_folder_names() {
+        assert_eq!(sanitize_version(" 1.4.0+120 ").unwrap(), "1.4.0+120");
+        assert!(sanitize_version("../1.4.0").is_err());
+        assert!(sanitize_version("1.4/0").is_err());
+        assert!(sanitize_version("").is_err());
+    }
+
+    fn stage_fixture(dir: &Path, version: &str, exe: &[u8]) -> PendingUpdate {
+        let version_dir = dir.join("updates").join(version);
+        fs::create_dir_all(&version_dir).unwrap();
+        let path = version_dir.join("launcher.exe");
+        fs::write(&path, exe).unwrap();
+        fs::write(
+            version_dir.join(runtime_integrity::CHECKSUM_FILE),
+            b"new sums",
+        )
+        .unwrap();
+        fs::write(
+            version_dir.join(runtime_integrity::SIGNATURE_FILE),
+            b"new sig",
+        )
+        .unwrap();
+        let pending = PendingUpdate {
+            version: version.to_string(),
+            path,
+            size: exe.len() as u64,
+            sha256: hex::encode(Sha256::digest(exe)),
+        };
+        fs::write(
+            dir.join("updates").join(PENDING_FILE),
+            serde_json::to_vec(&pending).unwrap(),
+        )
+        .unwrap();
+        pending
+    }
+
+    fn install_fixture(dir: &Path) -> PathBuf {
+        let install = dir.join("install");
+        fs::create_dir_all(&install).unwrap();
+        fs::write(install.join("launcher.exe"), b"old build").unwrap();
+        fs::write(install.join(runtime_integrity::CHECKSUM_FILE), b"old sums").unwrap();
+        fs::write(install.join(runtime_integrity::SIGNATURE_FILE), b"old sig").unwrap();
+        install.join("launcher.exe")
+    }
+
+    #[test]
+    fn applying_replaces_the_executable_and_its_manifest() {
+        let dir = std::env::temp_dir().join(format!("otoshi-update-{}", uuid::Uuid::new_v4()));
+        let exe = install_fixture(&dir);
+        stage_fixture(&dir, "2.0.0", b"new build");
+        let updates = dir.join("updates");
+
+        assert_eq!(
+            apply_pending(&updates, &exe).unwrap().as_deref(),
+            Some("2.0.0")
+        );
+        let install = exe.parent().unwrap();
+        assert_eq!(fs::read(&exe).unwrap(), b"new build");
+        assert_eq!(
+            fs::read(install.join(runtime_integrity::CHECKSUM_FILE)).unwrap(),
+            b"new sums"
+        );
+        assert_eq!(
+            fs::read(install.join(runtime_integrity::SIGNATURE_FILE)).unwrap(),
+            b"new sig"
+        );
+        assert_eq!(fs::read(previous_path(&exe)).unwrap(), b"old build");
+        assert!(load_pending(&updates).is_none());
+        assert!(apply_pending(&updates, &exe).unwrap().is_none());
+
+        clean_previous(&updates, &exe, "2.0.0");
+        assert!(!previous_path(&exe).exists());
+        assert!(!previous_path(&install.join(runtime_integrity::CHECKSUM_FILE)).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn staged_builds_without_a_manifest_are_discarded() {
+        let dir = std::env::temp_dir().join(format!("otoshi-update-{}", uuid::Uuid::new_v4()));
+        let exe = install_fixture(&dir);
+        let pending = stage_fixture(&dir, "2.0.0", b"new build");
+        fs::remove_file(
+            pending
+                .path
+                .parent()
+                .unwrap()
+                .join(runtime_integrity::SIGNATURE_FILE),
+        )
+        .unwrap();
+        let updates = dir.join("updates");
+
+        assert!(apply_pending(&updates, &exe).is_err());
+        assert_eq!(fs::read(&exe).unwrap(), b"old build");
+        assert!(load_pending(&updates).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_failed_replacement_restores_every_file() {
+        let dir = std::env::temp_dir().join(format!("otoshi-update-{}", uuid::Uuid::new_v4()));
+        let exe = install_fixture(&dir);
+        let install = exe.parent().unwrap().to_path_buf();
+        let staged = dir.join("staged");
+        fs::create_dir_all(&staged).unwrap();
+        fs::write(staged.join("launcher.exe"), b"new build").unwrap();
+        let pairs = vec![
+            (staged.join("launcher.exe"), exe.clone()),
+            (
+                staged.join("missing.sha256"),
+                install.join(runtime_integrity::CHECKSUM_FILE),
+            ),
+        ];
+
+        assert!(replace_files(&pairs).is_err());
+        assert_eq!(fs::read(&exe).unwrap(), b"old build");
+        assert_eq!(
+            fs::read(install.join(runtime_integrity::CHECKSUM_FILE)).unwrap(),
+            b"old sums"
+        );
+        assert!(!previous_path(&exe).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn startup_drops_staged_builds_that_are_not_newer() {
+        let dir = std::env::temp_dir().join(format!("otoshi-update-{}", uuid::Uuid::new_v4()));
+        let exe = install_fixture(&dir);
+        let updates = dir.join("updates");
+        fs::write(previous_path(&exe), b"older build").unwrap();
+
+        let pending = stage_fixture(&dir, "2.0.0", b"new build");
+        clean_previous(&updates, &exe, "1.0.0");
+        assert!(load_pending(&updates).is_some());
+        assert!(!previous_path(&exe).exists());
+
+        clean_previous(&updates, &exe, "2.0.0");
+        assert!(load_pending(&updates).is_none());
+        assert!(!pending.path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use crate::db::queries::SettingsQueries;
+use crate::db::Database;
+use crate::errors::Result;
+use crate::services::crack_manager::BACKUP_DIR_NAME;
+
+/// Per-game overrides, stored as `{game_id: [pattern, ...]}`.
+const VOLATILE_PATHS_SETTING: &str = "volatile_paths";
+
+/// Install-relative glob patterns for files a game is expected to change
+/// after install: saves, config, modded binaries. Integrity scans report a
+/// present volatile file without hashing it and never queue it for repair.
+///
+/// `*` and `?` stay within one path segment, `**` spans any number of them
+/// and a trailing `/` covers everything below a folder. Matching ignores
+/// case. The crack manager's backup folder is always included.
+#[derive(Clone, Debug, Default)]
+pub struct VolatilePaths {
+    patterns: Vec<String>,
+}
+
+impl VolatilePaths {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        let patterns = std::iter::once(format!("{BACKUP_DIR_NAME}/"))
+            .chain(patterns.iter().map(|pattern| pattern.as_ref().to_string()))
+            .filter_map(|pattern| normalize_pattern(&pattern))
+            .collect();
+        Self { patterns }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let path = normalize_path(path);
+        self.patterns
+            .iter()
+            .any(|pattern| glob_matches(pattern.as_bytes(), path.as_bytes()))
+    }
+}
+
+/// Patterns for `game_id`: the user's override when one is set, otherwise
+/// what its manifest declares.
+pub fn resolve(db: &Database, game_id: &str, manifest_patterns: &[String]) -> VolatilePaths {
+    match load_overrides(db).remove(game_id) {
+        Some(patterns) => VolatilePaths::new(&patterns),
+        None => VolatilePaths::new(manifest_patterns),
+    }
+}
+
+pub fn get_override(db: &Database, game_id: &str) -> Option<Vec<String>> {
+    load_overrides(db).remove(game_id)
+}
+
+/// Replaces the manifest's list for `game_id`; `None` goes back to it.
+pub fn set_override(db: &Database, game_id: &str, patterns: Option<Vec<String>>) -> Result<()> {
+    let mut overrides = load_overrides(db);
+    match patterns {
+        Some(patterns) => {
+            let patterns = patterns
+                .into_iter()
+                .map(|pattern| pattern.trim().to_string())
+                .filter(|pattern| !pattern.is_empty())
+                .collect();
+            overrides.insert(game_id.to_string(), patterns);
+        }
+        None => {
+            overrides.remove(game_id);
+        }
+    }
+    db.set_setting(VOLATILE_PATHS_SETTING, &serde_json::to_string(&overrides)?)
+}
+
+fn load_overrides(db: &Database) -> HashMap<String, Vec<String>> {
+    let Ok(Some(raw)) = db.get_setting(VOLATILE_PATHS_SETTING) else {
+        return HashMap::new();
+    };
+    serde_json::from_str(&raw).unwrap_or_else(|err| {
+        tracing::warn!("ignoring invalid {VOLATILE_PATHS_SETTING}: {err}");
+        HashMap::new()
+    })
+}
+
+fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/")
+        .trim_start_matches("./")
+        .trim_start_matches('/')
+        .to_ascii_lowercase()
+}
+
+fn normalize_pattern(pattern: &str) -> Option<String> {
+    let pattern = normalize_path(pattern.trim());
+    if pattern.is_empty() {
+        return None;
+    }
+    Some(match pattern.strip_suffix('/') {
+        Some(dir) => format!("{dir}/**"),
+        None => pattern,
+    })
+}
+
+/// Fills a table of which pattern suffix matches which path suffix, from
+/// the ends of both, so the work stays at pattern length times path length
+/// however many `**` a pattern has, with no backtracking or recursion.
+fn glob_matches(pattern: &[u8], path: &[u8]) -> bool {
+    let width = path.len() + 1;
+    // `table[i * width + j]`: does `pattern[i..]` match `path[j..]`?
+    let mut table = vec![false; (pattern.len() + 1) * width];
+    table[pattern.len() * width + path.len()] = true;
+    for i in (0..pattern.len()).rev() {
+        let double_star = pattern[i] == b'*' && pattern.get(i + 1) == Some(&b'*');
+        // For `**`: whether what follows it matches here or further on.
+        let mut rest_matches_later = false;
+        for j in (0..=path.len()).rev() {
+            let next = path.get(j).copied();
+            let in_segment = next.is_some_and(|c| c != b'/');
+            table[i * width + j] = if double_star {
+                rest_matches_later |= table[(i + 2) * width + j];
+                // `**/` may also stand for no folder at all.
+                rest_matches_later
+                    || (pattern.get(i + 2) == Some(&b'/') && table[(i + 3) * width + j])
+            } else {
+                match pattern[i] {
+                    b'*' => table[(i + 1) * width + j] || (in_segment && table[i * width + j + 1]),
+                    b'?' => in_segment && table[(i + 1) * width + j + 1],
+                    c => next == Some(c) && table[(i + 1) * width + j + 1],
+                }
+            };
+        }
+    }
+    table[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_match_install_relative_paths() {
+        let volatile = VolatilePaths::new(&["saves/", "*.ini", "bin/**/steam_api?4.dll"]);
+        assert!(volatile.matches("Saves/slot1.sav"));
+        assert!(volatile.matches("saves\\profiles\\a.json"));
+        assert!(volatile.matches("settings.INI"));
+        assert!(!volatile.matches("config/settings.ini"));
+        assert!(volatile.matches("bin/steam_api64.dll"));
+        assert!(volatile.matches("bin/x64/release/steam_api64.dll"));
+        assert!(!volatile.matches("bin/steam_api.dll"));
+        assert!(volatile.matches(".otoshi-backup/steam_api64.dll"));
+        assert!(!volatile.matches("game.exe"));
+    }
+
+    #[test]
+    fn many_wildcards_match_long_paths_quickly() {
+        let pattern = "**/a**/a**/a**/a**/a**/a**/a**/b";
+        let path = format!("{}/c", "a/".repeat(2000));
+        assert!(!glob_matches(pattern.as_bytes(), path.as_bytes()));
+        assert!(glob_matches(b"**/*.sav", b"saves/slot1.sav"));
+        assert!(glob_matches(b"**/*.sav", b"slot1.sav"));
+        assert!(!glob_matches(b"*.sav", b"saves/slot1.sav"));
+        assert!(glob_matches(b"", b""));
+        assert!(!glob_matches(b"", b"a"));
+    }
+}
@@ -1,16 +1,165 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::{Component, Path, PathBuf};
+
 use serde::{Deserialize, Serialize};
 
-use crate::errors::Result;
-use crate::services::ApiClient;
+use crate::db::queries::{SettingsQueries, WorkshopFileQueries};
+use crate::db::Database;
+use crate::errors::{LauncherError, Result};
+use crate::models::WorkshopFileRecord;
+use crate::services::{patch_engine, ApiClient};
+use crate::utils::file::relative_files;
+
+const DELTA_STAGING_DIR: &str = ".otoshi-workshop-delta";
+const STORAGE_DIR_SETTING: &str = "workshop_storage_dir";
+const PLACEMENTS_FILE: &str = "placements.json";
 
 #[derive(Clone)]
 pub struct WorkshopService {
     api: ApiClient,
+    db: Database,
 }
 
 impl WorkshopService {
-    pub fn new(api: ApiClient) -> Self {
-        Self { api }
+    pub fn new(api: ApiClient, db: Database) -> Self {
+        Self { api, db }
+    }
+
+    /// Managed folder that holds synced workshop content, if one is configured.
+    pub fn storage_dir(&self) -> Option<PathBuf> {
+        self.db
+            .get_setting(STORAGE_DIR_SETTING)
+            .ok()
+            .flatten()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    }
+
+    /// Sets the managed storage folder, or clears it with `None` so items are
+    /// copied straight into the game directory again. Items placed under the
+    /// previous folder move to the new one. A folder inside the current one,
+    /// or one that contains it, is rejected.
+    pub fn set_storage_dir(&self, dir: Option<PathBuf>) -> Result<()> {
+        let Some(dir) = dir else {
+            return self.db.delete_setting(STORAGE_DIR_SETTING);
+        };
+        if !dir.is_absolute() {
+            return Err(LauncherError::Config(format!(
+                "workshop storage dir must be absolute: {}",
+                dir.display()
+            )));
+        }
+        if let Some(current) = self.storage_dir() {
+            if resolve_path(&current) != resolve_path(&dir) {
+                if paths_overlap(&current, &dir) {
+                    return Err(LauncherError::Config(format!(
+                        "workshop storage dir {} overlaps the current one at {}",
+                        dir.display(),
+                        current.display()
+                    )));
+                }
+                move_placements(&current, &dir)?;
+            }
+        }
+        std::fs::create_dir_all(&dir)?;
+        self.db
+            .set_setting(STORAGE_DIR_SETTING, &dir.to_string_lossy())
+    }
+
+    /// Records `files` as the expected contents of `item_id`.
+    pub fn record_manifest(&self, item_id: &str, files: &[WorkshopFileRecord]) -> Result<usize> {
+        self.db.replace_workshop_files(item_id, files)?;
+        Ok(files.len())
+    }
+
+    /// Places `src` like [`place_item`], hashing it first and checking the
+    /// placed copy against those hashes before recording them, so an
+    /// incomplete copy is reported instead of becoming the expected state.
+    pub fn sync_item(
+        &self,
+        storage_root: Option<&Path>,
+        app_id: &str,
+        item_id: &str,
+        src: &Path,
+        game_path: &Path,
+    ) -> Result<WorkshopPlacement> {
+        let files = build_manifest(src)?;
+        let placement = place_item(storage_root, app_id, item_id, src, game_path)?;
+        let report = verify_files(item_id, game_path, &files);
+        if !report.missing.is_empty() || !report.corrupt.is_empty() {
+            return Err(LauncherError::Integrity(format!(
+                "workshop item {item_id} was placed with {} missing and {} damaged file(s)",
+                report.missing.len(),
+                report.corrupt.len()
+            )));
+        }
+        self.record_manifest(item_id, &files)?;
+        Ok(placement)
+    }
+
+    /// Checks the files of an installed item against its recorded manifest.
+    pub fn verify_item(&self, item_id: &str, item_dir: &Path) -> Result<WorkshopVerifyReport> {
+        let expected = self.db.list_workshop_files(item_id)?;
+        if expected.is_empty() {
+            return Err(LauncherError::NotFound(format!(
+                "no file manifest recorded for workshop item {item_id}"
+            )));
+        }
+        Ok(verify_files(item_id, item_dir, &expected))
+    }
+
+    /// Finds items whose file sets overlap once synced into `mod_dir`, where
+    /// each item gets its own folder. Folders already in `mod_dir` that aren't
+    /// in `items` count as placed first, then `items` in the order given; each
+    /// conflict names the pair and the shared paths inside their folders, and
+    /// `second_item` is the one that wins. Items that can't be read are logged
+    /// and left out.
+    pub fn detect_conflicts(items: &[(String, PathBuf)], mod_dir: &Path) -> Vec<WorkshopConflict> {
+        let mut sources = placed_mod_folders(mod_dir, items);
+        sources.extend(items.iter().cloned());
+
+        let mut owners: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (index, (item_id, dir)) in sources.iter().enumerate() {
+            let files = match relative_files(dir) {
+                Ok(files) => files,
+                Err(err) => {
+                    tracing::warn!(
+                        "skipping workshop item {} in the conflict check: {}",
+                        item_id,
+                        err
+                    );
+                    continue;
+                }
+            };
+            for path in files.into_iter().map(|path| path.to_lowercase()) {
+                let entry = owners.entry(path).or_default();
+                if !entry.contains(&index) {
+                    entry.push(index);
+                }
+            }
+        }
+
+        let mut pairs: BTreeMap<(usize, usize), Vec<String>> = BTreeMap::new();
+        for (path, indexes) in owners {
+            for (position, first) in indexes.iter().enumerate() {
+                for second in &indexes[position + 1..] {
+                    pairs
+                        .entry((*first, *second))
+                        .or_default()
+                        .push(path.clone());
+                }
+            }
+        }
+        pairs
+            .into_iter()
+            .map(|((first, second), paths)| WorkshopConflict {
+                first_item: sources[first].0.clone(),
+                second_item: sources[second].0.clone(),
+                paths,
+            })
+            .collect()
     }
 
     pub async fn list_items(
@@ -52,6 +201,719 @@ impl WorkshopService {
         let _: serde_json::Value = self.api.delete(&path, true).await?;
         Ok(())
     }
+
+    /// Patch set that turns `from_version` into `target`, if the backend has one.
+    pub async fn get_delta(
+        &self,
+        item_id: &str,
+        from_version: &str,
+        target: &WorkshopVersion,
+    ) -> Result<Option<WorkshopDelta>> {
+        let path = format!(
+            "/workshop/items/{}/versions/{}/delta?from={}",
+            item_id,
+            target.id,
+            urlencoding::encode(from_version)
+        );
+        match self.api.get::<Option<WorkshopDelta>>(&path, true).await {
+            Ok(delta) => {
+                Ok(delta.filter(|delta| !delta.files.is_empty() || !delta.removed.is_empty()))
+            }
+            Err(LauncherError::Http(message)) if message.starts_with("HTTP 404") => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Updates an installed item in `install_dir` to `target`. Patches the
+    /// installed files when a delta from `installed_version` is available and
+    /// falls back to downloading the full version otherwise.
+    pub async fn update_item(
+        &self,
+        item_id: &str,
+        installed_version: Option<&str>,
+        target: &WorkshopVersion,
+        install_dir: &Path,
+    ) -> Result<WorkshopUpdateResult> {
+        let mut fallback_reason = None;
+        match installed_version.filter(|value| !value.trim().is_empty()) {
+            Some(from) if install_dir.is_dir() => {
+                match self
+                    .apply_delta_update(item_id, from, target, install_dir)
+                    .await
+                {
+                    Ok(Some(patched_files)) => {
+                        return Ok(WorkshopUpdateResult {
+                            item_id: item_id.to_string(),
+                            version: target.version.clone(),
+                            mode: "delta".to_string(),
+                            patched_files,
+                            fallback_reason: None,
+                        });
+                    }
+                    Ok(None) => fallback_reason = Some("delta_unavailable".to_string()),
+                    Err(err) => {
+                        tracing::warn!(
+                            "workshop delta update failed for {} ({} -> {}): {}",
+                            item_id,
+                            from,
+                            target.version,
+                            err
+                        );
+                        fallback_reason = Some(format!("delta_failed: {}", err));
+                    }
+                }
+            }
+            _ => fallback_reason = Some("not_installed".to_string()),
+        }
+
+        let files = self.full_sync(target, install_dir).await?;
+        if let Err(err) = self.record_manifest(item_id, &files) {
+            tracing::warn!(
+                "failed to record workshop manifest for {}: {}",
+                item_id,
+                err
+            );
+        }
+        Ok(WorkshopUpdateResult {
+            item_id: item_id.to_string(),
+            version: target.version.clone(),
+            mode: "full".to_string(),
+            patched_files: 0,
+            fallback_reason,
+        })
+    }
+
+    /// Updates the recorded manifest after a delta: patched files take the
+    /// hashes of the verified outputs and removed files are dropped. Without
+    /// an earlier manifest the installed files are hashed instead.
+    fn record_delta_manifest(
+        &self,
+        item_id: &str,
+        install_dir: &Path,
+        patched: &[WorkshopFileRecord],
+        removed: &[String],
+    ) -> Result<usize> {
+        let mut files = self.db.list_workshop_files(item_id)?;
+        if files.is_empty() {
+            files = build_manifest(install_dir)?;
+        } else {
+            let removed = removed
+                .iter()
+                .map(|path| path.replace('\\', "/"))
+                .collect::<Vec<_>>();
+            files.retain(|file| {
+                !removed.contains(&file.path) && !patched.iter().any(|p| p.path == file.path)
+            });
+            files.extend(patched.iter().cloned());
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        self.record_manifest(item_id, &files)
+    }
+
+    async fn apply_delta_update(
+        &self,
+        item_id: &str,
+        from_version: &str,
+        target: &WorkshopVersion,
+        install_dir: &Path,
+    ) -> Result<Option<usize>> {
+        let Some(delta) = self.get_delta(item_id, from_version, target).await? else {
+            return Ok(None);
+        };
+
+        let staging = install_dir.join(DELTA_STAGING_DIR);
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)?;
+        }
+        std::fs::create_dir_all(&staging)?;
+
+        let mut staged = Vec::with_capacity(delta.files.len());
+        for (index, file) in delta.files.iter().enumerate() {
+            let bytes = self.download_bytes(&file.patch_url).await?;
+            let patch_path = staging.join(format!("{index}.patch"));
+            std::fs::write(&patch_path, &bytes)?;
+            staged.push((file.clone(), patch_path));
+        }
+
+        let root = install_dir.to_path_buf();
+        let removed = delta.removed.clone();
+        let result =
+            tokio::task::spawn_blocking(move || apply_delta_files(&root, &staged, &removed))
+                .await
+                .map_err(|err| {
+                    LauncherError::Config(format!("workshop delta join error: {err}"))
+                })?;
+        let _ = std::fs::remove_dir_all(&staging);
+        let patched = result?;
+        if let Err(err) = self.record_delta_manifest(item_id, install_dir, &patched, &delta.removed)
+        {
+            tracing::warn!(
+                "failed to record workshop manifest for {}: {}",
+                item_id,
+                err
+            );
+        }
+        Ok(Some(patched.len()))
+    }
+
+    /// Replaces `install_dir` with the contents of the target's archive and
+    /// returns their manifest, hashed before they are moved into place.
+    async fn full_sync(
+        &self,
+        target: &WorkshopVersion,
+        install_dir: &Path,
+    ) -> Result<Vec<WorkshopFileRecord>> {
+        let url = target
+            .download_url
+            .as_deref()
+            .filter(|value| !value.trim().is_empty())
+            .ok_or_else(|| {
+                LauncherError::NotFound(format!("workshop version {} has no download", target.id))
+            })?;
+        let bytes = self.download_bytes(url).await?;
+        let root = install_dir.to_path_buf();
+        tokio::task::spawn_blocking(move || replace_with_archive(&root, &bytes))
+            .await
+            .map_err(|err| LauncherError::Config(format!("workshop extract join error: {err}")))?
+    }
+
+    async fn download_bytes(&self, url: &str) -> Result<Vec<u8>> {
+        let url = if url.starts_with("http://") || url.starts_with("https://") {
+            url.to_string()
+        } else {
+            format!(
+                "{}/{}",
+                self.api.base_url().trim_end_matches('/'),
+                url.trim_start_matches('/')
+            )
+        };
+        let response = self.api.client().get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(LauncherError::Http(format!(
+                "workshop download failed ({}) {}",
+                response.status(),
+                url
+            )));
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// Applies staged patches against the files in `install_dir`. Every output is
+/// built and verified next to the staged patch before any installed file is
+/// touched, so a failed patch leaves the item as it was. Returns the records
+/// of the patched files.
+fn apply_delta_files(
+    install_dir: &Path,
+    staged: &[(WorkshopPatchFile, PathBuf)],
+    removed: &[String],
+) -> Result<Vec<WorkshopFileRecord>> {
+    let mut outputs = Vec::with_capacity(staged.len());
+    let mut records = Vec::with_capacity(staged.len());
+    for (file, patch_path) in staged {
+        let target = item_path(install_dir, &file.path)?;
+        if !target.is_file() {
+            return Err(LauncherError::NotFound(format!(
+                "workshop patch source missing: {}",
+                file.path
+            )));
+        }
+        let output = patch_path.with_extension("out");
+        patch_engine::apply_patch(&target, patch_path, &output)?;
+        patch_engine::verify_output(&output, file.expected_size, file.expected_sha256.as_deref())?;
+        records.push(WorkshopFileRecord {
+            path: file.path.replace('\\', "/"),
+            size: std::fs::metadata(&output)?.len(),
+            sha256: patch_engine::sha256_file(&output)?,
+        });
+        outputs.push((output, target));
+    }
+
+    for (output, target) in &outputs {
+        std::fs::remove_file(target)?;
+        std::fs::rename(output, target)?;
+    }
+    for relative in removed {
+        let path = item_path(install_dir, relative)?;
+        if path.is_file() {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(records)
+}
+
+/// Extracts `bytes` next to `install_dir`, hashes the extracted files and
+/// only then swaps them in. Returns their manifest.
+fn replace_with_archive(install_dir: &Path, bytes: &[u8]) -> Result<Vec<WorkshopFileRecord>> {
+    let staging = install_dir.with_extension("incoming");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|err| LauncherError::Config(format!("invalid workshop archive: {err}")))?;
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|err| LauncherError::Config(format!("workshop archive entry error: {err}")))?;
+        let Ok(out_path) = item_path(&staging, &entry.name().replace('\\', "/")) else {
+            continue;
+        };
+        if entry.is_dir() {
+            std::fs::create_dir_all(&out_path)?;
+            continue;
+        }
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut out = std::fs::File::create(&out_path)?;
+        std::io::copy(&mut entry, &mut out)?;
+    }
+
+    let files = build_manifest(&staging)?;
+    if install_dir.exists() {
+        std::fs::remove_dir_all(install_dir)?;
+    }
+    std::fs::rename(&staging, install_dir)?;
+    Ok(files)
+}
+
+fn item_path(root: &Path, relative: &str) -> Result<PathBuf> {
+    let mut path = root.to_path_buf();
+    for component in Path::new(relative).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(LauncherError::Config(format!(
+                    "unsafe workshop path: {relative}"
+                )))
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// How a synced item shows up inside the game directory.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlacementMode {
+    /// Symlink (junction on Windows) to the copy in the storage root.
+    Link,
+    /// Plain copy; used when no storage root is set or linking failed.
+    Copy,
+}
+
+/// A workshop item placed into a game directory.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkshopPlacement {
+    pub app_id: String,
+    pub item_id: String,
+    pub stored_path: Option<PathBuf>,
+    pub game_path: PathBuf,
+    pub mode: PlacementMode,
+}
+
+/// Places `src` at `game_path`. With a storage root the content is copied
+/// under `<root>/<app_id>/<item_id>` and linked into the game, falling back to
+/// a copy when the link can't be created. Placements under a storage root are
+/// recorded so they can be removed cleanly later.
+pub fn place_item(
+    storage_root: Option<&Path>,
+    app_id: &str,
+    item_id: &str,
+    src: &Path,
+    game_path: &Path,
+) -> Result<WorkshopPlacement> {
+    let Some(root) = storage_root else {
+        copy_dir(src, game_path)?;
+        return Ok(WorkshopPlacement {
+            app_id: app_id.to_string(),
+            item_id: item_id.to_string(),
+            stored_path: None,
+            game_path: game_path.to_path_buf(),
+            mode: PlacementMode::Copy,
+        });
+    };
+
+    let stored = item_path(root, &format!("{app_id}/{item_id}"))?;
+    copy_dir(src, &stored)?;
+    clear_path(game_path)?;
+    if let Some(parent) = game_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mode = match link_dir(&stored, game_path) {
+        Ok(()) => PlacementMode::Link,
+        Err(err) => {
+            tracing::warn!(
+                "workshop link {} -> {} failed, copying instead: {}",
+                game_path.display(),
+                stored.display(),
+                err
+            );
+            copy_dir(&stored, game_path)?;
+            PlacementMode::Copy
+        }
+    };
+
+    let placement = WorkshopPlacement {
+        app_id: app_id.to_string(),
+        item_id: item_id.to_string(),
+        stored_path: Some(stored),
+        game_path: game_path.to_path_buf(),
+        mode,
+    };
+    let mut placements = load_placements(root);
+    placements.retain(|entry| !(entry.app_id == app_id && entry.item_id == item_id));
+    placements.push(placement.clone());
+    save_placements(root, &placements)?;
+    Ok(placement)
+}
+
+/// Removes a tracked item from its game directory. The stored copy is kept
+/// unless `delete_content` is set, so a disabled item can be re-linked.
+pub fn remove_placement(
+    storage_root: &Path,
+    app_id: &str,
+    item_id: &str,
+    delete_content: bool,
+) -> Result<bool> {
+    let mut placements = load_placements(storage_root);
+    let Some(index) = placements
+        .iter()
+        .position(|entry| entry.app_id == app_id && entry.item_id == item_id)
+    else {
+        return Ok(false);
+    };
+    let placement = placements.remove(index);
+    clear_path(&placement.game_path)?;
+    if delete_content {
+        if let Some(stored) = placement.stored_path.as_deref() {
+            clear_path(stored)?;
+        }
+    }
+    save_placements(storage_root, &placements)?;
+    Ok(true)
+}
+
+/// Moves the stored copies of the items placed under `old_root` to `new_root`,
+/// re-points their game links and records them there. The old copies are
+/// only deleted once the new placements are saved. Returns how many moved.
+fn move_placements(old_root: &Path, new_root: &Path) -> Result<usize> {
+    let mut moved = load_placements(old_root);
+    if moved.is_empty() {
+        return Ok(0);
+    }
+    let mut old_copies = Vec::new();
+    for placement in &mut moved {
+        let Some(stored) = placement.stored_path.clone() else {
+            continue;
+        };
+        let relative = stored.strip_prefix(old_root).map_err(|_| {
+            LauncherError::Config(format!(
+                "workshop item {} is stored outside {}",
+                placement.item_id,
+                old_root.display()
+            ))
+        })?;
+        let target = new_root.join(relative);
+        copy_dir(&stored, &target)?;
+        placement.stored_path = Some(target);
+        old_copies.push(stored);
+    }
+
+    for placement in &mut moved {
+        let (Some(stored), PlacementMode::Link) = (&placement.stored_path, placement.mode) else {
+            continue;
+        };
+        clear_path(&placement.game_path)?;
+        if let Err(err) = link_dir(stored, &placement.game_path) {
+            tracing::warn!(
+                "workshop link {} -> {} failed, copying instead: {}",
+                placement.game_path.display(),
+                stored.display(),
+                err
+            );
+            copy_dir(stored, &placement.game_path)?;
+            placement.mode = PlacementMode::Copy;
+        }
+    }
+
+    let mut placements = load_placements(new_root);
+    placements.retain(|entry| {
+        !moved
+            .iter()
+            .any(|item| item.app_id == entry.app_id && item.item_id == entry.item_id)
+    });
+    let count = moved.len();
+    placements.extend(moved);
+    save_placements(new_root, &placements)?;
+
+    for stored in old_copies {
+        if let Err(err) = clear_path(&stored) {
+            tracing::warn!(
+                "failed to remove old workshop copy {}: {}",
+                stored.display(),
+                err
+            );
+        }
+    }
+    std::fs::remove_file(old_root.join(PLACEMENTS_FILE))?;
+    Ok(count)
+}
+
+/// Folders already in the game's mod dir that aren't about to be synced, by
+/// name.
+fn placed_mod_folders(mod_dir: &Path, items: &[(String, PathBuf)]) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(mod_dir) else {
+        return Vec::new();
+    };
+    let mut folders = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().to_string(),
+                entry.path(),
+            )
+        })
+        .filter(|(name, _)| !items.iter().any(|(item_id, _)| item_id == name))
+        .collect::<Vec<_>>();
+    folders.sort();
+    folders
+}
+
+/// Size and SHA-256 of every file under `item_dir`, sorted by path.
+pub fn build_manifest(item_dir: &Path) -> Result<Vec<WorkshopFileRecord>> {
+    relative_files(item_dir)?
+        .into_iter()
+        .filter(|relative| !relative.split('/').any(|part| part == DELTA_STAGING_DIR))
+        .map(|relative| {
+            let path = item_dir.join(&relative);
+            Ok(WorkshopFileRecord {
+                size: std::fs::metadata(&path)?.len(),
+                sha256: patch_engine::sha256_file(&path)?,
+                path: relative,
+            })
+        })
+        .collect()
+}
+
+/// Size check first, hash only when the size matches, like the self-heal scan.
+fn verify_files(
+    item_id: &str,
+    item_dir: &Path,
+    expected: &[WorkshopFileRecord],
+) -> WorkshopVerifyReport {
+    let mut report = WorkshopVerifyReport {
+        item_id: item_id.to_string(),
+        total_files: expected.len(),
+        verified_files: 0,
+        missing: Vec::new(),
+        corrupt: Vec::new(),
+    };
+    for file in expected {
+        let Ok(path) = item_path(item_dir, &file.path) else {
+            report
+                .corrupt
+                .push(WorkshopFileIssue::new(file, "unsafe_path"));
+            continue;
+        };
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            report
+                .missing
+                .push(WorkshopFileIssue::new(file, "missing_file"));
+            continue;
+        };
+        if !metadata.is_file() {
+            report
+                .missing
+                .push(WorkshopFileIssue::new(file, "missing_file"));
+        } else if metadata.len() != file.size {
+            report
+                .corrupt
+                .push(WorkshopFileIssue::new(file, "size_mismatch"));
+        } else {
+            match patch_engine::sha256_file(&path) {
+                Ok(hash) if hash.eq_ignore_ascii_case(&file.sha256) => report.verified_files += 1,
+                Ok(_) => report
+                    .corrupt
+                    .push(WorkshopFileIssue::new(file, "hash_mismatch")),
+                Err(_) => report
+                    .corrupt
+                    .push(WorkshopFileIssue::new(file, "read_failed")),
+            }
+        }
+    }
+    report
+}
+
+fn load_placements(root: &Path) -> Vec<WorkshopPlacement> {
+    std::fs::read(root.join(PLACEMENTS_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn save_placements(root: &Path, placements: &[WorkshopPlacement]) -> Result<()> {
+    std::fs::create_dir_all(root)?;
+    std::fs::write(
+        root.join(PLACEMENTS_FILE),
+        serde_json::to_vec_pretty(placements)?,
+    )?;
+    Ok(())
+}
+
+/// Removes a file, a directory tree, or a directory link without following it.
+fn clear_path(path: &Path) -> std::io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => {
+            std::fs::remove_file(path).or_else(|_| std::fs::remove_dir(path))
+        }
+        Ok(meta) if meta.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(unix)]
+fn link_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Junctions don't need the symlink privilege that `symlink_dir` does.
+#[cfg(windows)]
+fn link_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let status = std::process::Command::new("cmd")
+        .creation_flags(CREATE_NO_WINDOW)
+        .args([
+            "/c",
+            "mklink",
+            "/J",
+            link.to_string_lossy().as_ref(),
+            target.to_string_lossy().as_ref(),
+        ])
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("mklink /J exited with {status}"),
+        ))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn link_dir(_target: &Path, _link: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "directory links are not supported on this platform",
+    ))
+}
+
+/// Replaces `dest` with a copy of `src`. Refuses when one lies inside the
+/// other, since clearing `dest` first would delete the source.
+fn copy_dir(src: &Path, dest: &Path) -> std::io::Result<()> {
+    if paths_overlap(src, dest) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "can't copy {} onto {}: one is inside the other",
+                src.display(),
+                dest.display()
+            ),
+        ));
+    }
+    clear_path(dest)?;
+    copy_tree(src, dest)
+}
+
+fn copy_tree(src: &Path, dest: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dest)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        let target = dest.join(entry.file_name());
+        if path.is_dir() {
+            copy_tree(&path, &target)?;
+        } else {
+            std::fs::copy(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `a` and `b` are the same directory or one lies inside the other.
+/// A link at `b` itself isn't followed, since clearing it leaves its target
+/// alone.
+fn paths_overlap(a: &Path, b: &Path) -> bool {
+    let a = resolve_path(a);
+    let b = match (b.parent(), b.file_name()) {
+        (Some(parent), Some(name)) => resolve_path(parent).join(name),
+        _ => resolve_path(b),
+    };
+    a.starts_with(&b) || b.starts_with(&a)
+}
+
+/// Canonicalizes the deepest existing ancestor of `path` and re-appends the
+/// rest, so paths that don't exist yet can still be compared.
+fn resolve_path(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return rest
+                .iter()
+                .rev()
+                .fold(resolved, |resolved, part| resolved.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// Two items that both ship `paths`; `second_item` wins if both are synced
+/// into the same directory in the given order.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkshopConflict {
+    pub first_item: String,
+    pub second_item: String,
+    pub paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkshopFileIssue {
+    pub path: String,
+    pub reason: String,
+}
+
+impl WorkshopFileIssue {
+    fn new(file: &WorkshopFileRecord, reason: &str) -> Self {
+        Self {
+            path: file.path.clone(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkshopVerifyReport {
+    pub item_id: String,
+    pub total_files: usize,
+    pub verified_files: usize,
+    pub missing: Vec<WorkshopFileIssue>,
+    pub corrupt: Vec<WorkshopFileIssue>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -93,3 +955,320 @@ pub struct WorkshopSubscription {
     pub auto_update: bool,
     pub item: Option<WorkshopItem>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkshopDelta {
+    pub from_version: String,
+    pub to_version: String,
+    #[serde(default)]
+    pub files: Vec<WorkshopPatchFile>,
+    #[serde(default)]
+    pub removed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkshopPatchFile {
+    pub path: String,
+    pub patch_url: String,
+    #[serde(default)]
+    pub expected_sha256: Option<String>,
+    #[serde(default)]
+    pub expected_size: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkshopUpdateResult {
+    pub item_id: String,
+    pub version: String,
+    /// `delta` when installed files were patched, `full` after a re-download,
+    /// `current` when nothing needed updating.
+    pub mode: String,
+    pub patched_files: usize,
+    pub fallback_reason: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_synthetic_zstd_patch_to_installed_item() {
+        let root = std::env::temp_dir().join(format!("otoshi-workshop-{}", uuid::Uuid::new_v4()));
+        let staging = root.join(DELTA_STAGING_DIR);
+        std::fs::create_dir_all(root.join("textures")).unwrap();
+        std::fs::create_dir_all(&staging).unwrap();
+
+        let old = b"workshop item v1 - shared content shared content shared content".to_vec();
+        let new = b"workshop item v2 - shared content shared content shared content!".to_vec();
+        std::fs::write(root.join("textures/pack.bin"), &old).unwrap();
+        std::fs::write(root.join("obsolete.cfg"), b"old").unwrap();
+
+        let patch = zstd::bulk::Compressor::with_dictionary(3, &old)
+            .unwrap()
+            .compress(&new)
+            .unwrap();
+        let patch_path = staging.join("0.patch");
+        std::fs::write(&patch_path, patch).unwrap();
+
+        let file = WorkshopPatchFile {
+            path: "textures/pack.bin".to_string(),
+            patch_url: String::new(),
+            expected_sha256: Some(hex::encode(<sha2::Sha256 as sha2::Digest>::digest(&new))),
+            expected_size: Some(new.len() as u64),
+        };
+        let patched = apply_delta_files(
+            &root,
+            &[(file.clone(), patch_path.clone())],
+            &["obsolete.cfg".to_string()],
+        )
+        .unwrap();
+
+        assert_eq!(patched.len(), 1);
+        assert_eq!(patched[0].size, new.len() as u64);
+        assert_eq!(
+            patched[0].sha256,
+            hex::encode(<sha2::Sha256 as sha2::Digest>::digest(&new))
+        );
+        assert_eq!(std::fs::read(root.join("textures/pack.bin")).unwrap(), new);
+        assert!(!root.join("obsolete.cfg").exists());
+
+        // A patch that no longer matches the installed file is rejected and
+        // leaves the file untouched.
+        let bad = WorkshopPatchFile {
+            expected_sha256: Some("00".repeat(32)),
+            ..file
+        };
+        std::fs::write(root.join("textures/pack.bin"), &old).unwrap();
+        assert!(apply_delta_files(&root, &[(bad, patch_path)], &[]).is_err());
+        assert_eq!(std::fs::read(root.join("textures/pack.bin")).unwrap(), old);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn synced_items_land_in_storage_root() {
+        let base = std::env::temp_dir().join(format!("otoshi-workshop-{}", uuid::Uuid::new_v4()));
+        let src = base.join("steam/content/480/1001");
+        let storage = base.join("storage");
+        let game_path = base.join("game/Mods/1001");
+        std::fs::create_dir_all(src.join("data")).unwrap();
+        std::fs::write(src.join("data/mod.pak"), b"mod content").unwrap();
+
+        let placement = place_item(Some(&storage), "480", "1001", &src, &game_path).unwrap();
+
+        let stored = storage.join("480").join("1001");
+        assert_eq!(placement.stored_path.as_deref(), Some(stored.as_path()));
+        assert_eq!(
+            std::fs::read(stored.join("data/mod.pak")).unwrap(),
+            b"mod content"
+        );
+        assert_eq!(
+            std::fs::read(game_path.join("data/mod.pak")).unwrap(),
+            b"mod content"
+        );
+        assert_eq!(load_placements(&storage).len(), 1);
+
+        assert!(remove_placement(&storage, "480", "1001", false).unwrap());
+        assert!(std::fs::symlink_metadata(&game_path).is_err());
+        assert!(stored.join("data/mod.pak").is_file());
+        assert!(load_placements(&storage).is_empty());
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+    #[test]
+    fn conflicts_include_folders_already_in_the_mod_dir() {
+        let base = std::env::temp_dir().join(format!("otoshi-workshop-{}", uuid::Uuid::new_v4()));
+        let mod_dir = base.join("game/Mods");
+        let content = base.join("steam/content/480");
+        for (dir, file) in [
+            (mod_dir.join("ManualMod"), "Textures/Rock.dds"),
+            (content.join("1001"), "textures/rock.dds"),
+            (content.join("1001"), "readme.txt"),
+            (content.join("1002"), "readme.txt"),
+            (content.join("1003"), "plugins/other.dll"),
+            (mod_dir.join("1002"), "stale.txt"),
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"x").unwrap();
+        }
+        let items = ["1001", "1002", "1003", "1004"]
+            .map(|id| (id.to_string(), content.join(id)))
+            .to_vec();
+
+        // 1004 has no content on disk; it is skipped instead of failing.
+        let conflicts = WorkshopService::detect_conflicts(&items, &mod_dir);
+        let pairs = conflicts
+            .iter()
+            .map(|conflict| {
+                (
+                    conflict.first_item.as_str(),
+                    conflict.second_item.as_str(),
+                    conflict.paths.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            [
+                ("ManualMod", "1001", vec!["textures/rock.dds".to_string()]),
+                ("1001", "1002", vec!["readme.txt".to_string()]),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn synced_items_record_the_hashes_of_their_source() {
+        let base = std::env::temp_dir().join(format!("otoshi-workshop-{}", uuid::Uuid::new_v4()));
+        let src = base.join("steam/content/480/1001");
+        let game_path = base.join("game/Mods/1001");
+        std::fs::create_dir_all(src.join("data")).unwrap();
+        std::fs::write(src.join("data/mod.pak"), b"mod content").unwrap();
+        std::fs::write(src.join("about.xml"), b"<about/>").unwrap();
+
+        let service = offline_service();
+        service
+            .sync_item(None, "480", "1001", &src, &game_path)
+            .unwrap();
+        let recorded = service.db.list_workshop_files("1001").unwrap();
+        assert_eq!(recorded, build_manifest(&src).unwrap());
+        assert_eq!(
+            recorded
+                .iter()
+                .map(|file| file.path.as_str())
+                .collect::<Vec<_>>(),
+            ["about.xml", "data/mod.pak"]
+        );
+        assert_eq!(
+            service
+                .verify_item("1001", &game_path)
+                .unwrap()
+                .verified_files,
+            2
+        );
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_sources_are_neither_placed_nor_recorded() {
+        let base = std::env::temp_dir().join(format!("otoshi-workshop-{}", uuid::Uuid::new_v4()));
+        let src = base.join("steam/content/480/1001");
+        let game_path = base.join("game/Mods/1001");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("mod.pak"), b"mod content").unwrap();
+        std::os::unix::fs::symlink(base.join("missing"), src.join("broken.pak")).unwrap();
+
+        let service = offline_service();
+        assert!(service
+            .sync_item(None, "480", "1001", &src, &game_path)
+            .is_err());
+        assert!(!game_path.exists());
+        assert!(service.db.list_workshop_files("1001").unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn full_syncs_hash_the_extracted_files_before_swapping_them_in() {
+        let base = std::env::temp_dir().join(format!("otoshi-workshop-{}", uuid::Uuid::new_v4()));
+        let install_dir = base.join("1001");
+        std::fs::create_dir_all(&install_dir).unwrap();
+        std::fs::write(install_dir.join("old.pak"), b"old").unwrap();
+        let archive = crate::utils::archive::build_test_zip(&[
+            ("data/mod.pak", "mod content v2", None),
+            ("about.xml", "<about/>", None),
+        ]);
+
+        let files = replace_with_archive(&install_dir, &archive).unwrap();
+        assert_eq!(files, build_manifest(&install_dir).unwrap());
+        assert_eq!(files.len(), 2);
+        assert!(!install_dir.join("old.pak").exists());
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    fn offline_service() -> WorkshopService {
+        let db = crate::db::open_temp();
+        let auth = crate::services::AuthService::new(
+            "http://127.0.0.1:9".to_string(),
+            db.clone(),
+            vec![7; 32],
+        );
+        let config = crate::services::api_client::ApiClientConfig {
+            max_retries: 0,
+            ..Default::default()
+        };
+        let api = ApiClient::new("http://127.0.0.1:9".to_string(), auth, config);
+        WorkshopService::new(api, db)
+    }
+
+    #[test]
+    fn copying_a_dir_onto_itself_or_inside_itself_keeps_the_source() {
+        let base = std::env::temp_dir().join(format!("otoshi-workshop-{}", uuid::Uuid::new_v4()));
+        let src = base.join("item");
+        std::fs::create_dir_all(src.join("data")).unwrap();
+        std::fs::write(src.join("data/mod.pak"), b"mod content").unwrap();
+
+        assert!(copy_dir(&src, &src).is_err());
+        assert!(copy_dir(&src, &src.join("data/copy")).is_err());
+        assert!(copy_dir(&src.join("data"), &src).is_err());
+        assert!(copy_dir(&src, &base.join("item/../item")).is_err());
+        assert_eq!(
+            std::fs::read(src.join("data/mod.pak")).unwrap(),
+            b"mod content"
+        );
+
+        copy_dir(&src, &base.join("copy")).unwrap();
+        assert!(base.join("copy/data/mod.pak").is_file());
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn moving_the_storage_dir_carries_placements_along() {
+        let base = std::env::temp_dir().join(format!("otoshi-workshop-{}", uuid::Uuid::new_v4()));
+        let src = base.join("steam/content/480/1001");
+        let old_root = base.join("old");
+        let new_root = base.join("new");
+        let game_path = base.join("game/Mods/1001");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("mod.pak"), b"mod content").unwrap();
+
+        let service = offline_service();
+        service.set_storage_dir(Some(old_root.clone())).unwrap();
+        let placed = place_item(Some(&old_root), "480", "1001", &src, &game_path).unwrap();
+
+        assert!(service
+            .set_storage_dir(Some(old_root.join("nested")))
+            .is_err());
+        assert!(service.set_storage_dir(Some(base.clone())).is_err());
+        service.set_storage_dir(Some(old_root.clone())).unwrap();
+        assert!(old_root.join("480/1001/mod.pak").is_file());
+
+        service.set_storage_dir(Some(new_root.clone())).unwrap();
+        assert_eq!(service.storage_dir(), Some(new_root.clone()));
+        let stored = new_root.join("480").join("1001");
+        let placements = load_placements(&new_root);
+        assert_eq!(placements.len(), 1);
+        assert_eq!(placements[0].stored_path.as_deref(), Some(stored.as_path()));
+        assert_eq!(placements[0].mode, placed.mode);
+        assert!(load_placements(&old_root).is_empty());
+        assert!(!old_root.join("480/1001").exists());
+        assert_eq!(
+            std::fs::read(game_path.join("mod.pak")).unwrap(),
+            b"mod content"
+        );
+        if placed.mode == PlacementMode::Link {
+            assert_eq!(std::fs::read_link(&game_path).unwrap(), stored);
+        }
+
+        assert!(remove_placement(&new_root, "480", "1001", true).unwrap());
+        assert!(!stored.exists());
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+}
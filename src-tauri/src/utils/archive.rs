@@ -0,0 +1,80 @@
+use std::path::{Component, Path};
+
+use zip::read::ZipFile;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Whether a zip entry was stored as a symlink. Extracting one would write
+/// its target path as file content at best, or point outside the install.
+pub fn is_symlink(entry: &ZipFile) -> bool {
+    entry
+        .unix_mode()
+        .is_some_and(|mode| mode & S_IFMT == S_IFLNK)
+}
+
+/// `C:` style names are plain relative paths on Unix but absolute on Windows,
+/// so archive names carrying one are refused on every platform.
+pub fn has_drive_prefix(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// An archive entry name that stays inside whatever directory it is joined
+/// onto: no root, no drive and no `..`.
+pub fn is_contained_name(name: &str) -> bool {
+    let name = name.replace('\\', "/");
+    !has_drive_prefix(&name)
+        && Path::new(&name)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Builds an in-memory zip for extraction tests. Entries with a `Some` link
+/// target are stored as symlinks.
+#[cfg(test)]
+pub fn build_test_zip(entries: &[(&str, &str, Option<&str>)]) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (name, data, link) in entries {
+        match link {
+            Some(target) => writer.add_symlink(*name, *target, options).unwrap(),
+            None => {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(data.as_bytes()).unwrap();
+            }
+        }
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contained_names_reject_traversal_roots_and_drives() {
+        assert!(is_contained_name("bin/game.exe"));
+        assert!(is_contained_name("./data/pak0.pak"));
+        assert!(!is_contained_name("../escape.dll"));
+        assert!(!is_contained_name("data/../../escape.dll"));
+        assert!(!is_contained_name("/etc/passwd"));
+        assert!(!is_contained_name("C:/Windows/System32/evil.dll"));
+        assert!(!is_contained_name("c:evil.dll"));
+        assert!(!is_contained_name("..\\escape.dll"));
+    }
+
+    #[test]
+    fn detects_symlink_entries() {
+        let bytes = build_test_zip(&[
+            ("plain.txt", "data", None),
+            ("link", "", Some("/etc/passwd")),
+        ]);
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        assert!(!is_symlink(&archive.by_index(0).unwrap()));
+        assert!(is_symlink(&archive.by_index(1).unwrap()));
+    }
+}
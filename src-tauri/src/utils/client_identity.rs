@@ -0,0 +1,131 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+
+const INSTALL_ID_FILE: &str = "install_id";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+struct InstallId {
+    path: PathBuf,
+    id: String,
+}
+
+static INSTALL_ID: RwLock<Option<InstallId>> = RwLock::new(None);
+
+/// Loads the anonymous per-install id from `data_dir`, creating it on first run.
+pub fn init(data_dir: &Path) {
+    let path = data_dir.join(INSTALL_ID_FILE);
+    let id = std::fs::read_to_string(&path)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| uuid::Uuid::parse_str(value).is_ok())
+        .unwrap_or_else(|| write_new_id(&path));
+    if let Ok(mut slot) = INSTALL_ID.write() {
+        *slot = Some(InstallId { path, id });
+    }
+}
+
+/// Replaces the install id so earlier requests can no longer be linked to
+/// later ones. Returns the new id.
+pub fn reset_install_id() -> Option<String> {
+    let mut slot = INSTALL_ID.write().ok()?;
+    let install = slot.as_mut()?;
+    install.id = write_new_id(&install.path);
+    Some(install.id.clone())
+}
+
+fn write_new_id(path: &Path) -> String {
+    let id = uuid::Uuid::new_v4().to_string();
+    if let Err(err) = std::fs::write(path, format!("{id}\n")) {
+        tracing::warn!("failed to persist install id: {}", err);
+    }
+    id
+}
+
+pub fn install_id() -> Option<String> {
+    INSTALL_ID
+        .read()
+        .ok()
+        .and_then(|slot| slot.as_ref().map(|install| install.id.clone()))
+}
+
+/// Generic user-agent for third-party hosts (CDNs, mirrors). Carries no
+/// install id.
+pub fn user_agent() -> String {
+    format!(
+        "OtoshiLauncher/{} ({}; {})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
+
+/// User-agent for the launcher backend, tagged with the install id.
+pub fn backend_user_agent() -> String {
+    match install_id() {
+        Some(id) => format!(
+            "OtoshiLauncher/{} ({}; {}; install {})",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            id
+        ),
+        None => user_agent(),
+    }
+}
+
+/// Headers for one backend call: the tagged user-agent and a fresh request id
+/// that support can match against server logs.
+pub fn request_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&backend_user_agent()) {
+        headers.insert(USER_AGENT, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&uuid::Uuid::new_v4().to_string()) {
+        headers.insert(REQUEST_ID_HEADER, value);
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outgoing_requests_carry_user_agent_and_request_id() {
+        let dir = std::env::temp_dir().join(format!("otoshi-identity-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        init(&dir);
+        let first_id = install_id().unwrap();
+
+        let client = reqwest::Client::new();
+        let first = client
+            .get("http://127.0.0.1:8000/games")
+            .headers(request_headers())
+            .build()
+            .unwrap();
+        let second = client
+            .get("http://127.0.0.1:8000/games")
+            .headers(request_headers())
+            .build()
+            .unwrap();
+
+        let agent = first.headers()[USER_AGENT].to_str().unwrap();
+        assert!(agent.starts_with("OtoshiLauncher/"));
+        assert!(agent.contains(&first_id));
+        let request_id = first.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(request_id).is_ok());
+        assert_ne!(request_id, second.headers()[REQUEST_ID_HEADER]);
+
+        // The id survives a restart and changes on reset.
+        init(&dir);
+        assert_eq!(install_id().unwrap(), first_id);
+        let reset = reset_install_id().unwrap();
+        assert_ne!(reset, first_id);
+        assert!(backend_user_agent().contains(&reset));
+        assert!(!user_agent().contains(&reset));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,21 +1,28 @@
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 use memmap2::Mmap;
 use memmap2::MmapOptions;
+use sysinfo::{Disk, DiskKind, Disks};
 
 #[derive(Clone)]
 pub struct FileManager {
     app_data_dir: PathBuf,
-    install_dir: PathBuf,
+    install_dir: Arc<RwLock<PathBuf>>,
+    game_roots: Arc<RwLock<HashMap<String, PathBuf>>>,
+    library_roots: Arc<RwLock<Vec<PathBuf>>>,
 }
 
 impl FileManager {
     pub fn new(app_data_dir: PathBuf, install_dir: PathBuf) -> Self {
         Self {
             app_data_dir,
-            install_dir,
+            install_dir: Arc::new(RwLock::new(install_dir)),
+            game_roots: Arc::new(RwLock::new(HashMap::new())),
+            library_roots: Arc::new(RwLock::new(Vec::new())),
         }
     }
 
@@ -23,8 +30,134 @@ impl FileManager {
         &self.app_data_dir
     }
 
-    pub fn install_dir(&self) -> &Path {
-        &self.install_dir
+    pub fn install_dir(&self) -> PathBuf {
+        self.install_dir
+            .read()
+            .map(|guard| guard.clone())
+            .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+    }
+
+    pub fn set_install_dir(&self, path: PathBuf) {
+        if let Ok(mut guard) = self.install_dir.write() {
+            *guard = path;
+        }
+    }
+
+    /// Root used for a single game: its own root if one was set or claimed,
+    /// else the library folder it is already installed in, else the default
+    /// install root.
+    pub fn game_install_root(&self, game_slug: &str) -> PathBuf {
+        self.settled_install_root(game_slug)
+            .unwrap_or_else(|| self.install_dir())
+    }
+
+    /// Pins the root a new install of `game_slug` goes to, picking the
+    /// library folder with the most free space when the game has none yet.
+    /// Returns the root and whether it was just picked, in which case the
+    /// caller should persist [`Self::game_install_roots`].
+    pub fn claim_install_root(&self, game_slug: &str) -> (PathBuf, bool) {
+        if let Some(root) = self.settled_install_root(game_slug) {
+            return (root, false);
+        }
+        let candidates = self.library_candidates();
+        if candidates.len() == 1 {
+            return (candidates[0].clone(), false);
+        }
+        let root = candidates
+            .iter()
+            .filter(|root| *root == &candidates[0] || root.is_dir())
+            .max_by_key(|root| available_disk_space(root).unwrap_or(0))
+            .cloned()
+            .unwrap_or_else(|| self.install_dir());
+        self.set_game_install_root(game_slug, Some(root.clone()));
+        (root, true)
+    }
+
+    fn settled_install_root(&self, game_slug: &str) -> Option<PathBuf> {
+        if let Some(root) = self
+            .game_roots
+            .read()
+            .ok()
+            .and_then(|roots| roots.get(game_slug).cloned())
+        {
+            return Some(root);
+        }
+        let candidates = self.library_candidates();
+        if candidates.len() == 1 {
+            return None;
+        }
+        candidates
+            .into_iter()
+            .find(|root| root.join(game_slug).is_dir())
+    }
+
+    /// Extra library folders, without the default install root.
+    pub fn library_roots(&self) -> Vec<PathBuf> {
+        self.library_roots
+            .read()
+            .map(|roots| roots.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_library_roots(&self, roots: Vec<PathBuf>) {
+        if let Ok(mut guard) = self.library_roots.write() {
+            *guard = roots;
+        }
+    }
+
+    /// The default install root followed by every library folder.
+    fn library_candidates(&self) -> Vec<PathBuf> {
+        let mut candidates = vec![self.install_dir()];
+        for root in self.library_roots() {
+            if !candidates.contains(&root) {
+                candidates.push(root);
+            }
+        }
+        candidates
+    }
+
+    pub fn set_game_install_root(&self, game_slug: &str, root: Option<PathBuf>) {
+        if let Ok(mut roots) = self.game_roots.write() {
+            match root {
+                Some(path) => {
+                    roots.insert(game_slug.to_string(), path);
+                }
+                None => {
+                    roots.remove(game_slug);
+                }
+            }
+        }
+    }
+
+    /// Pins each `(slug, install dir)` without a root of its own to the folder
+    /// it is installed in, so changing the default install root leaves it
+    /// there. Returns whether any was pinned, in which case the caller should
+    /// persist [`Self::game_install_roots`].
+    pub fn pin_install_roots(&self, installs: impl IntoIterator<Item = (String, PathBuf)>) -> bool {
+        let Ok(mut roots) = self.game_roots.write() else {
+            return false;
+        };
+        let mut pinned = false;
+        for (slug, install_dir) in installs {
+            let Some(root) = install_dir
+                .parent()
+                .filter(|root| !root.as_os_str().is_empty())
+            else {
+                continue;
+            };
+            if !roots.contains_key(&slug) {
+                roots.insert(slug, root.to_path_buf());
+                pinned = true;
+            }
+        }
+        pinned
+    }
+
+    pub fn game_install_roots(&self) -> HashMap<String, PathBuf> {
+        self.game_roots
+            .read()
+            .map(|roots| roots.clone())
+            .unwrap_or_default()
     }
 
     pub fn write_atomic(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
@@ -47,7 +180,7 @@ impl FileManager {
     }
 
     pub fn get_game_dir(&self, game_slug: &str) -> PathBuf {
-        self.install_dir.join(game_slug)
+        self.game_install_root(game_slug).join(game_slug)
     }
 
     pub fn dir_size(&self, path: &Path) -> io::Result<u64> {
@@ -64,3 +197,164 @@ impl FileManager {
         Ok(total)
     }
 }
+
+fn nearest_existing_path(path: &Path) -> PathBuf {
+    let mut candidate = path.to_path_buf();
+    while !candidate.exists() {
+        if !candidate.pop() {
+            return PathBuf::from(".");
+        }
+    }
+    candidate
+}
+
+/// The disk with the longest mount point containing `path`.
+fn disk_for_path<'a>(disks: &'a Disks, path: &Path) -> Option<&'a Disk> {
+    let target = nearest_existing_path(path);
+    let target = std::fs::canonicalize(&target).unwrap_or(target);
+
+    let mut best: Option<(usize, &Disk)> = None;
+    for disk in disks.list() {
+        let mount = disk.mount_point();
+        if target.starts_with(mount) {
+            let score = mount.as_os_str().to_string_lossy().len();
+            match best {
+                Some((best_score, _)) if best_score >= score => {}
+                _ => best = Some((score, disk)),
+            }
+        }
+    }
+    best.map(|(_, disk)| disk)
+}
+
+pub fn available_disk_space(path: &Path) -> Option<u64> {
+    let disks = Disks::new_with_refreshed_list();
+    disk_for_path(&disks, path)
+        .or_else(|| disks.list().first())
+        .map(|disk| disk.available_space())
+}
+
+pub fn disk_kind_for_path(path: &Path) -> Option<DiskKind> {
+    let disks = Disks::new_with_refreshed_list();
+    disk_for_path(&disks, path).map(Disk::kind)
+}
+
+/// Every file under `root`, relative to it with `/` separators, sorted.
+pub fn relative_files(root: &Path) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("otoshi-{name}-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn a_claimed_root_sticks_for_the_rest_of_the_install() {
+        let default_root = temp_root("default-root");
+        let library = temp_root("library");
+        let files = FileManager::new(default_root.clone(), default_root.clone());
+        files.set_library_roots(vec![library.clone()]);
+
+        assert_eq!(files.game_install_root("hollow"), default_root);
+        let (root, claimed) = files.claim_install_root("hollow");
+        assert!(claimed);
+        assert!(root == default_root || root == library);
+        assert_eq!(files.claim_install_root("hollow"), (root.clone(), false));
+        assert_eq!(files.get_game_dir("hollow"), root.join("hollow"));
+        assert_eq!(files.game_install_roots().get("hollow"), Some(&root));
+
+        let _ = fs::remove_dir_all(&default_root);
+        let _ = fs::remove_dir_all(&library);
+    }
+
+    #[test]
+    fn installed_and_explicit_roots_win_over_a_new_pick() {
+        let default_root = temp_root("default-root");
+        let library = temp_root("library");
+        fs::create_dir_all(library.join("celeste")).unwrap();
+        let files = FileManager::new(default_root.clone(), default_root.clone());
+        files.set_library_roots(vec![library.clone()]);
+
+        assert_eq!(files.game_install_root("celeste"), library);
+        assert_eq!(
+            files.claim_install_root("celeste"),
+            (library.clone(), false)
+        );
+
+        files.set_game_install_root("hades", Some(default_root.join("custom")));
+        assert_eq!(
+            files.claim_install_root("hades"),
+            (default_root.join("custom"), false)
+        );
+
+        let _ = fs::remove_dir_all(&default_root);
+        let _ = fs::remove_dir_all(&library);
+    }
+
+    #[test]
+    fn installs_stay_put_when_the_default_root_moves() {
+        let old_root = temp_root("old-root");
+        let new_root = temp_root("new-root");
+        let files = FileManager::new(old_root.clone(), old_root.clone());
+        files.set_game_install_root("hades", Some(new_root.join("custom")));
+
+        assert!(files.pin_install_roots([
+            ("hollow".to_string(), old_root.join("hollow")),
+            ("hades".to_string(), old_root.join("hades")),
+        ]));
+        assert!(!files.pin_install_roots([("hollow".to_string(), old_root.join("hollow"))]));
+        files.set_install_dir(new_root.clone());
+
+        assert_eq!(files.get_game_dir("hollow"), old_root.join("hollow"));
+        assert_eq!(
+            files.get_game_dir("hades"),
+            new_root.join("custom").join("hades")
+        );
+        assert_eq!(files.get_game_dir("celeste"), new_root.join("celeste"));
+
+        let _ = fs::remove_dir_all(&old_root);
+        let _ = fs::remove_dir_all(&new_root);
+    }
+
+    #[test]
+    fn a_single_root_is_used_without_pinning_it() {
+        let default_root = temp_root("default-root");
+        let files = FileManager::new(default_root.clone(), default_root.clone());
+
+        assert_eq!(
+            files.claim_install_root("hollow"),
+            (default_root.clone(), false)
+        );
+        assert!(files.game_install_roots().is_empty());
+
+        let _ = fs::remove_dir_all(&default_root);
+    }
+
+    #[test]
+    fn disk_lookups_start_from_the_nearest_existing_folder() {
+        let missing = std::env::temp_dir().join(format!("otoshi-missing-{}", uuid::Uuid::new_v4()));
+        assert_eq!(
+            nearest_existing_path(&missing.join("a/b")),
+            std::env::temp_dir()
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::vdf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameStore {
+    Steam,
+    Epic,
+    Gog,
+}
+
+impl GameStore {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GameStore::Steam => "steam",
+            GameStore::Epic => "epic",
+            GameStore::Gog => "gog",
+        }
+    }
+}
+
+/// A game another store's client has installed.
+#[derive(Clone, Debug, Serialize)]
+pub struct StoreGame {
+    pub store: GameStore,
+    pub id: String,
+    pub name: String,
+    pub install_path: PathBuf,
+}
+
+/// The fields of an Epic launcher `.item` manifest we match on.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct EpicItem {
+    #[serde(default)]
+    app_name: String,
+    #[serde(default)]
+    catalog_item_id: String,
+    #[serde(default)]
+    catalog_namespace: String,
+    #[serde(default)]
+    display_name: String,
+    #[serde(default)]
+    install_location: String,
+    #[serde(default, rename = "bIsIncompleteInstall")]
+    is_incomplete_install: bool,
+}
+
+/// Steam install folders, most authoritative first: the registry entries
+/// the Steam client writes on Windows, then the usual default locations.
+pub fn steam_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    #[cfg(target_os = "windows")]
+    {
+        use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+
+        roots.extend(
+            [
+                (HKEY_CURRENT_USER, "Software\\Valve\\Steam", "SteamPath"),
+                (
+                    HKEY_LOCAL_MACHINE,
+                    "SOFTWARE\\WOW6432Node\\Valve\\Steam",
+                    "InstallPath",
+                ),
+                (HKEY_LOCAL_MACHINE, "SOFTWARE\\Valve\\Steam", "InstallPath"),
+            ]
+            .into_iter()
+            .filter_map(|(hive, key, name)| registry_string(hive, key, name))
+            .map(PathBuf::from),
+        );
+        roots.push(PathBuf::from("C:\\Program Files (x86)\\Steam"));
+        roots.push(PathBuf::from("C:\\Program Files\\Steam"));
+        for var in ["ProgramFiles(x86)", "ProgramFiles"] {
+            if let Ok(dir) = std::env::var(var) {
+                roots.push(PathBuf::from(dir).join("Steam"));
+            }
+        }
+    }
+    #[cfg(target_os = "macos")]
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        roots.push(
+            home.join("Library")
+                .join("Application Support")
+                .join("Steam"),
+        );
+    }
+    #[cfg(target_os = "linux")]
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        roots.push(home.join(".steam").join("steam"));
+        roots.push(home.join(".local").join("share").join("Steam"));
+    }
+    dedup_paths(roots)
+}
+
+/// Every Steam library: each root plus the folders its
+/// `libraryfolders.vdf` lists.
+pub fn steam_libraries() -> Vec<PathBuf> {
+    let mut libraries = Vec::new();
+    for root in steam_roots() {
+        if !root.join("steamapps").is_dir() {
+            continue;
+        }
+        let library_file = root.join("steamapps").join("libraryfolders.vdf");
+        libraries.push(root);
+        let Ok(content) = fs::read_to_string(&library_file) else {
+            continue;
+        };
+        match vdf::library_folders(&content) {
+            Ok(folders) => libraries.extend(folders),
+            Err(err) => tracing::warn!("failed to parse {}: {}", library_file.display(), err),
+        }
+    }
+    dedup_paths(libraries)
+}
+
+/// Where `app_id` is installed according to its `appmanifest_*.acf` in any
+/// Steam library.
+pub fn find_steam_game(app_id: &str) -> Option<PathBuf> {
+    if app_id.is_empty() || !app_id.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    steam_libraries().into_iter().find_map(|library| {
+        let steamapps = library.join("steamapps");
+        let manifest =
+            fs::read_to_string(steamapps.join(format!("appmanifest_{app_id}.acf"))).ok()?;
+        let game_path = steamapps
+            .join("common")
+            .join(vdf::app_install_dir(&manifest)?);
+        game_path.is_dir().then_some(game_path)
+    })
+}
+
+/// Every game with an `appmanifest_*.acf` in any Steam library.
+pub fn steam_games() -> Vec<StoreGame> {
+    let mut games = Vec::new();
+    for library in steam_libraries() {
+        let steamapps = library.join("steamapps");
+        let Ok(entries) = fs::read_dir(&steamapps) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if !file_name.starts_with("appmanifest_") || !file_name.ends_with(".acf") {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            if let Some(game) = parse_steam_manifest(&steamapps, &content) {
+                games.push(game);
+            }
+        }
+    }
+    games.retain(|game| game.install_path.is_dir());
+    games
+}
+
+/// Looks `app_id` up in each store in turn: Steam by app id, then Epic and
+/// GOG by their own ids or, failing that, by `title`.
+pub fn find_installed(app_id: &str, title: Option<&str>) -> Option<StoreGame> {
+    let wanted = title.map(normalize_title).filter(|title| !title.is_empty());
+    let matches = |game: &StoreGame| {
+        game.id.eq_ignore_ascii_case(app_id)
+            || wanted
+                .as_deref()
+                .is_some_and(|wanted| normalize_title(&game.name) == wanted)
+    };
+    find_steam_game(app_id)
+        .map(|install_path| StoreGame {
+            store: GameStore::Steam,
+            id: app_id.to_string(),
+            name: title.unwrap_or_default().to_string(),
+            install_path,
+        })
+        .or_else(|| epic_games().into_iter().find(|game| matches(game)))
+        .or_else(|| gog_games().into_iter().find(|game| matches(game)))
+}
+
+/// Games installed through the Epic Games Launcher, read from the `.item`
+/// manifests it keeps under ProgramData.
+pub fn epic_games() -> Vec<StoreGame> {
+    let Some(dir) = epic_manifest_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("item"))
+        })
+        .filter_map(|path| fs::read_to_string(&path).ok())
+        .filter_map(|content| parse_epic_item(&content))
+        .filter(|game| game.install_path.is_dir())
+        .collect()
+}
+
+/// Games installed through GOG Galaxy or GOG's offline installers, which
+/// register each game under `Software\GOG.com\Games`.
+pub fn gog_games() -> Vec<StoreGame> {
+    #[cfg(target_os = "windows")]
+    {
+        use winreg::enums::HKEY_LOCAL_MACHINE;
+
+        let hklm = winreg::RegKey::predef(HKEY_LOCAL_MACHINE);
+        let mut games = Vec::new();
+        for root in [
+            "SOFTWARE\\WOW6432Node\\GOG.com\\Games",
+            "SOFTWARE\\GOG.com\\Games",
+        ] {
+            let Ok(key) = hklm.open_subkey(root) else {
+                continue;
+            };
+            for id in key.enum_keys().flatten() {
+                let Ok(game) = key.open_subkey(&id) else {
+                    continue;
+                };
+                let value = |name: &str| game.get_value::<String, _>(name).ok();
+                let Some(path) = value("path").filter(|path| !path.trim().is_empty()) else {
+                    continue;
+                };
+                games.push(StoreGame {
+                    store: GameStore::Gog,
+                    id: value("gameID").unwrap_or(id),
+                    name: value("gameName").unwrap_or_default(),
+                    install_path: PathBuf::from(path),
+                });
+            }
+        }
+        games.retain(|game| game.install_path.is_dir());
+        games
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        Vec::new()
+    }
+}
+
+fn epic_manifest_dir() -> Option<PathBuf> {
+    if !cfg!(target_os = "windows") {
+        return None;
+    }
+    let program_data = std::env::var_os("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("C:\\ProgramData"));
+    Some(
+        program_data
+            .join("Epic")
+            .join("EpicGamesLauncher")
+            .join("Data")
+            .join("Manifests"),
+    )
+}
+
+fn parse_steam_manifest(steamapps: &Path, content: &str) -> Option<StoreGame> {
+    let root = vdf::parse(content).ok()?;
+    let app = root.get("AppState")?;
+    let field = |key: &str| {
+        app.get(key)
+            .and_then(vdf::VdfValue::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    Some(StoreGame {
+        store: GameStore::Steam,
+        id: field("appid")?.to_string(),
+        name: field("name").unwrap_or_default().to_string(),
+        install_path: steamapps.join("common").join(field("installdir")?),
+    })
+}
+
+fn parse_epic_item(content: &str) -> Option<StoreGame> {
+    let item: EpicItem = serde_json::from_str(content).ok()?;
+    if item.is_incomplete_install || item.install_location.trim().is_empty() {
+        return None;
+    }
+    let id = [
+        &item.app_name,
+        &item.catalog_item_id,
+        &item.catalog_namespace,
+    ]
+    .into_iter()
+    .find(|value| !value.trim().is_empty())?
+    .clone();
+    Some(StoreGame {
+        store: GameStore::Epic,
+        id,
+        name: item.display_name,
+        install_path: PathBuf::from(item.install_location),
+    })
+}
+
+/// Lowercase letters and digits only, so "DOOM Eternal™" matches "Doom
+/// Eternal".
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn registry_string(hive: winreg::HKEY, key: &str, name: &str) -> Option<String> {
+    winreg::RegKey::predef(hive)
+        .open_subkey(key)
+        .ok()?
+        .get_value::<String, _>(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Drops repeats, comparing case-insensitively with either separator.
+fn dedup_paths(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    paths
+        .into_iter()
+        .filter(|path| seen.insert(path_key(path)))
+        .collect()
+}
+
+/// A path normalised for comparison: lowercase, forward slashes, no
+/// trailing slash.
+pub fn path_key(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('\\', "/")
+        .trim_end_matches('/')
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_epic_item_manifests() {
+        let content = r#"{
+            "FormatVersion": 0,
+            "bIsIncompleteInstall": false,
+            "DisplayName": "Hades",
+            "InstallLocation": "D:\\Epic Games\\Hades",
+            "CatalogNamespace": "min",
+            "CatalogItemId": "0c1a8d5e0a6f4d6b9b8a1f0e2c3d4e5f",
+            "AppName": "Min"
+        }"#;
+        let game = parse_epic_item(content).unwrap();
+        assert_eq!(game.store, GameStore::Epic);
+        assert_eq!(game.id, "Min");
+        assert_eq!(game.name, "Hades");
+        assert_eq!(game.install_path, PathBuf::from("D:\\Epic Games\\Hades"));
+        assert!(parse_epic_item(r#"{"AppName": "Min", "InstallLocation": ""}"#).is_none());
+        assert_eq!(
+            normalize_title("DOOM Eternal™"),
+            normalize_title("Doom Eternal")
+        );
+    }
+}
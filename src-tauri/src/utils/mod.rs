@@ -1,3 +1,7 @@
+pub mod archive;
+pub mod client_identity;
 pub mod crypto;
 pub mod file;
+pub mod game_stores;
 pub mod paths;
+pub mod vdf;
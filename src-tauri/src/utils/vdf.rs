@@ -0,0 +1,299 @@
+//! Reader for Valve's KeyValues text format (`libraryfolders.vdf`,
+//! `appmanifest_*.acf`).
+
+use std::iter::Peekable;
+use std::path::PathBuf;
+use std::str::Chars;
+
+use crate::errors::{LauncherError, Result};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VdfValue {
+    String(String),
+    Object(Vec<(String, VdfValue)>),
+}
+
+impl VdfValue {
+    /// The first child named `key`; keys compare case-insensitively, as in
+    /// Steam.
+    pub fn get(&self, key: &str) -> Option<&VdfValue> {
+        match self {
+            VdfValue::Object(entries) => entries
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value),
+            VdfValue::String(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VdfValue::String(value) => Some(value),
+            VdfValue::Object(_) => None,
+        }
+    }
+
+    pub fn entries(&self) -> &[(String, VdfValue)] {
+        match self {
+            VdfValue::Object(entries) => entries,
+            VdfValue::String(_) => &[],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Text(String),
+    Open,
+    Close,
+}
+
+/// Parses a whole document into an object holding its top-level keys.
+pub fn parse(content: &str) -> Result<VdfValue> {
+    let mut tokens = Tokenizer {
+        chars: content.trim_start_matches('\u{feff}').chars().peekable(),
+    };
+    let entries = parse_entries(&mut tokens, false)?;
+    Ok(VdfValue::Object(entries))
+}
+
+/// Library roots listed in `libraryfolders.vdf`, in file order. Handles both
+/// the current `"0" { "path" "..." }` layout and the older one where each
+/// numbered key maps straight to a path.
+pub fn library_folders(content: &str) -> Result<Vec<PathBuf>> {
+    let root = parse(content)?;
+    let folders = root.get("libraryfolders").ok_or_else(|| {
+        LauncherError::Config("libraryfolders.vdf has no folder list".to_string())
+    })?;
+    Ok(folders
+        .entries()
+        .iter()
+        .filter(|(key, _)| key.chars().all(|c| c.is_ascii_digit()))
+        .filter_map(|(_, value)| match value {
+            VdfValue::String(path) => Some(path.as_str()),
+            VdfValue::Object(_) => value.get("path").and_then(VdfValue::as_str),
+        })
+        .filter(|path| !path.trim().is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// `AppState.installdir` from an `appmanifest_*.acf`.
+pub fn app_install_dir(content: &str) -> Option<String> {
+    parse(content)
+        .ok()?
+        .get("AppState")?
+        .get("installdir")?
+        .as_str()
+        .map(str::to_string)
+        .filter(|dir| !dir.trim().is_empty())
+}
+
+fn parse_entries(tokens: &mut Tokenizer, nested: bool) -> Result<Vec<(String, VdfValue)>> {
+    let mut entries = Vec::new();
+    loop {
+        let key = match tokens.next_token()? {
+            Some(Token::Text(key)) => key,
+            Some(Token::Close) if nested => return Ok(entries),
+            None if !nested => return Ok(entries),
+            Some(Token::Close) => {
+                return Err(LauncherError::Config("unexpected '}' in VDF".to_string()))
+            }
+            Some(Token::Open) => {
+                return Err(LauncherError::Config("unexpected '{' in VDF".to_string()))
+            }
+            None => return Err(LauncherError::Config("unterminated VDF object".to_string())),
+        };
+        let value = match tokens.next_token()? {
+            Some(Token::Text(value)) => VdfValue::String(value),
+            Some(Token::Open) => VdfValue::Object(parse_entries(tokens, true)?),
+            _ => {
+                return Err(LauncherError::Config(format!(
+                    "VDF key \"{key}\" has no value"
+                )))
+            }
+        };
+        entries.push((key, value));
+    }
+}
+
+struct Tokenizer<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Tokenizer<'_> {
+    fn next_token(&mut self) -> Result<Option<Token>> {
+        loop {
+            let Some(&c) = self.chars.peek() else {
+                return Ok(None);
+            };
+            match c {
+                c if c.is_whitespace() => {
+                    self.chars.next();
+                }
+                '/' => {
+                    self.chars.next();
+                    if self.chars.peek() == Some(&'/') {
+                        self.skip_line();
+                    } else {
+                        return Ok(Some(Token::Text(self.read_bare(Some('/')))));
+                    }
+                }
+                // Platform conditionals such as `[$WIN32]` don't change
+                // which keys exist; they are skipped.
+                '[' => {
+                    for c in self.chars.by_ref() {
+                        if c == ']' {
+                            break;
+                        }
+                    }
+                }
+                '{' => {
+                    self.chars.next();
+                    return Ok(Some(Token::Open));
+                }
+                '}' => {
+                    self.chars.next();
+                    return Ok(Some(Token::Close));
+                }
+                '"' => {
+                    self.chars.next();
+                    return self.read_quoted().map(|text| Some(Token::Text(text)));
+                }
+                _ => return Ok(Some(Token::Text(self.read_bare(None)))),
+            }
+        }
+    }
+
+    /// A quoted string. `\\`, `\"`, `\n` and `\t` are escapes; any other
+    /// backslash is kept, so unescaped Windows paths survive.
+    fn read_quoted(&mut self) -> Result<String> {
+        let mut text = String::new();
+        while let Some(c) = self.chars.next() {
+            match c {
+                '"' => return Ok(text),
+                '\\' => match self.chars.peek() {
+                    Some('\\') | Some('"') => text.extend(self.chars.next()),
+                    Some('n') => {
+                        self.chars.next();
+                        text.push('\n');
+                    }
+                    Some('t') => {
+                        self.chars.next();
+                        text.push('\t');
+                    }
+                    _ => text.push('\\'),
+                },
+                _ => text.push(c),
+            }
+        }
+        Err(LauncherError::Config("unterminated VDF string".to_string()))
+    }
+
+    fn read_bare(&mut self, first: Option<char>) -> String {
+        let mut text: String = first.into_iter().collect();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || matches!(c, '"' | '{' | '}') {
+                break;
+            }
+            text.push(c);
+            self.chars.next();
+        }
+        text
+    }
+
+    fn skip_line(&mut self) {
+        for c in self.chars.by_ref() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIBRARY_FOLDERS: &str = r#""libraryfolders"
+{
+	"0"
+	{
+		"path"		"C:\\Program Files (x86)\\Steam"
+		"label"		""
+		"contentid"		"6573045326341148393"
+		"totalsize"		"0"
+		"apps"
+		{
+			"228980"		"311245548"
+			"1091500"		"71382147237"
+		}
+	}
+	"1"
+	{
+		"path"		"D:\\Games\\Steam \"Library\" (2)"
+		"label"		"Fast SSD"
+		"apps"
+		{
+			"570"		"32435463545"
+		}
+	}
+	"2"
+	{
+		"path"		"E:\\ゲーム\\SteamLibrary"
+	}
+}
+"#;
+
+    #[test]
+    fn reads_every_library_with_escaped_paths() {
+        let folders = library_folders(LIBRARY_FOLDERS).unwrap();
+        assert_eq!(
+            folders,
+            vec![
+                PathBuf::from("C:\\Program Files (x86)\\Steam"),
+                PathBuf::from("D:\\Games\\Steam \"Library\" (2)"),
+                PathBuf::from("E:\\ゲーム\\SteamLibrary"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_the_legacy_library_layout() {
+        let content = "\"LibraryFolders\"\n{\n\t\"TimeNextStatsReport\"\t\t\"1700000000\"\n\t\"ContentStatsID\"\t\t\"-123\"\n\t\"1\"\t\t\"D:\\\\SteamLibrary\"\n}\n";
+        assert_eq!(
+            library_folders(content).unwrap(),
+            vec![PathBuf::from("D:\\SteamLibrary")]
+        );
+    }
+
+    #[test]
+    fn reads_the_install_dir_from_an_app_manifest() {
+        let content = "\u{feff}\"AppState\"\n{\n\t\"appid\"\t\t\"1091500\"\n\t\"name\"\t\t\"Cyberpunk 2077\"\n\t// comment\n\t\"installdir\"\t\t\"Cyberpunk 2077 — Édition Ultime\"\n\t\"UserConfig\"\n\t{\n\t\t\"language\"\t\t\"english\"\n\t}\n}\n";
+        assert_eq!(
+            app_install_dir(content).as_deref(),
+            Some("Cyberpunk 2077 — Édition Ultime")
+        );
+        assert_eq!(
+            app_install_dir("\"AppState\"\n{\n\t\"appid\"\t\"1\"\n}\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        assert!(parse("\"a\" { \"b\" \"c\"").is_err());
+        assert!(parse("\"a\" \"unterminated").is_err());
+        assert!(parse("\"a\" }").is_err());
+        assert_eq!(
+            parse("key [$WIN32] { sub value }").unwrap(),
+            VdfValue::Object(vec![(
+                "key".to_string(),
+                VdfValue::Object(vec![(
+                    "sub".to_string(),
+                    VdfValue::String("value".to_string())
+                )])
+            )])
+        );
+    }
+}
@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, UNIX_EPOCH};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{Emitter, Manager};
+
+use crate::errors::{LauncherError, Result};
+use crate::utils::archive;
+
+const WEB_PACK_STAMP_FILE: &str = ".web-pack.stamp";
+/// `sha256sum`-style listing of every file in the pack, stored at its root.
+const WEB_PACK_MANIFEST_FILE: &str = "web-pack.sha256";
+const PROGRESS_STEP_PERCENT: u8 = 10;
+
+/// Serializes restores so a runtime restore can't race the startup one.
+static RESTORE_LOCK: Mutex<()> = Mutex::new(());
+
+/// A `web.pack` extraction that still has to run.
+pub struct PendingRestore {
+    pack_path: PathBuf,
+    web_dir: PathBuf,
+    pack_stamp: String,
+    content_changed: bool,
+}
+
+impl PendingRestore {
+    pub fn stamp(&self) -> &str {
+        &self.pack_stamp
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct WebAssetsProgressPayload {
+    percent: u8,
+    extracted_entries: usize,
+    total_entries: usize,
+    extracted_bytes: u64,
+    total_bytes: u64,
+}
+
+/// Byte-weighted progress for a pack extraction. `advance` reports each time
+/// another `PROGRESS_STEP_PERCENT` step is crossed so callers can emit
+/// milestones without flooding the event bus.
+#[derive(Debug, Default)]
+pub struct ExtractProgress {
+    total_entries: usize,
+    total_bytes: u64,
+    extracted_entries: usize,
+    extracted_bytes: u64,
+    last_milestone: u8,
+}
+
+impl ExtractProgress {
+    pub fn new(total_entries: usize, total_bytes: u64) -> Self {
+        Self {
+            total_entries,
+            total_bytes,
+            ..Self::default()
+        }
+    }
+
+    pub fn percent(&self) -> u8 {
+        if self.total_bytes == 0 {
+            if self.total_entries == 0 {
+                return 100;
+            }
+            return ((self.extracted_entries * 100) / self.total_entries).min(100) as u8;
+        }
+        ((self.extracted_bytes.saturating_mul(100)) / self.total_bytes).min(100) as u8
+    }
+
+    pub fn advance(&mut self, entry_bytes: u64) -> Option<u8> {
+        self.extracted_entries = (self.extracted_entries + 1).min(self.total_entries);
+        self.extracted_bytes = self
+            .extracted_bytes
+            .saturating_add(entry_bytes)
+            .min(self.total_bytes);
+        let percent = self.percent();
+        let milestone = percent - percent % PROGRESS_STEP_PERCENT;
+        if milestone > self.last_milestone {
+            self.last_milestone = milestone;
+            return Some(milestone);
+        }
+        None
+    }
+
+    fn payload(&self) -> WebAssetsProgressPayload {
+        WebAssetsProgressPayload {
+            percent: self.percent(),
+            extracted_entries: self.extracted_entries,
+            total_entries: self.total_entries,
+            extracted_bytes: self.extracted_bytes,
+            total_bytes: self.total_bytes,
+        }
+    }
+}
+
+/// Checks whether the extracted web assets match `web.pack`. The stamp
+/// comparison keeps the common case free of any extraction work; only
+/// `OTOSHI_FORCE_WEB_RESTORE` re-extracts an unchanged pack, since that swaps
+/// out the directory the window is loading from.
+pub fn check(app: &tauri::AppHandle) -> Result<Option<PendingRestore>> {
+    let force_restore = std::env::var("OTOSHI_FORCE_WEB_RESTORE")
+        .map(|value| {
+            let normalized = value.trim().to_ascii_lowercase();
+            normalized == "1" || normalized == "true" || normalized == "yes"
+        })
+        .unwrap_or(false);
+    check_with(app, force_restore)
+}
+
+/// Like `check`, but with an explicit force flag instead of the env default.
+pub fn check_with(app: &tauri::AppHandle, force_restore: bool) -> Result<Option<PendingRestore>> {
+    let resource_dir = app
+        .path()
+        .resource_dir()
+        .map_err(|_| LauncherError::Config("resource dir unavailable".to_string()))?;
+    let web_dir = resource_dir.join("web");
+    let index_path = web_dir.join("index.html");
+
+    let pack_candidates = [
+        resource_dir.join("web.pack"),
+        resource_dir.join("resources").join("web.pack"),
+    ];
+    let pack_path = pack_candidates
+        .iter()
+        .find(|candidate| candidate.exists())
+        .cloned();
+    let Some(pack_path) = pack_path else {
+        if !index_path.exists() {
+            tracing::warn!(
+                "web assets missing and web.pack not found; checked {:?}",
+                pack_candidates
+                    .iter()
+                    .map(|value| value.display().to_string())
+                    .collect::<Vec<_>>()
+            );
+        }
+        return Ok(None);
+    };
+
+    let pack_stamp = pack_signature(&pack_path)?;
+    let current_stamp = read_web_stamp(&web_dir);
+    let content_changed = stamp_changed(index_path.exists(), current_stamp.as_deref(), &pack_stamp);
+    if !should_restore(force_restore, content_changed) {
+        return Ok(None);
+    }
+
+    tracing::info!(
+        "Restoring web assets from {:?} (stamp={} previous={:?} force_restore={})",
+        pack_path,
+        pack_stamp,
+        current_stamp,
+        force_restore
+    );
+    Ok(Some(PendingRestore {
+        pack_path,
+        web_dir,
+        pack_stamp,
+        content_changed,
+    }))
+}
+
+fn stamp_changed(index_exists: bool, current_stamp: Option<&str>, pack_stamp: &str) -> bool {
+    !index_exists || current_stamp != Some(pack_stamp)
+}
+
+fn should_restore(force_restore: bool, content_changed: bool) -> bool {
+    force_restore || content_changed
+}
+
+/// Runs a pending restore on a background thread so setup can finish and the
+/// window can paint. Assets are extracted next to the live directory and
+/// swapped in at the end; the main window reloads only if the content changed.
+pub fn spawn_restore(app: &tauri::AppHandle, pending: PendingRestore) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let started = Instant::now();
+        match restore(&app, &pending) {
+            Ok(()) => {
+                tracing::info!("web assets restored in {}ms", started.elapsed().as_millis());
+                let _ = app.emit("web-assets-ready", &pending.pack_stamp);
+                if pending.content_changed {
+                    if let Some(main_window) = app.get_webview_window("main") {
+                        let _ = main_window.eval("window.location.reload()");
+                    }
+                }
+            }
+            Err(err) => {
+                tracing::error!("web asset restore failed: {}", err);
+                let _ = app.emit("web-assets-error", err.to_string());
+            }
+        }
+    });
+}
+
+/// Extracts `web.pack` into the web directory on the calling thread. The
+/// live directory is only replaced once the staged copy is complete, verified
+/// and stamped, and is put back if the swap fails.
+pub fn restore(app: &tauri::AppHandle, pending: &PendingRestore) -> Result<()> {
+    let _guard = RESTORE_LOCK
+        .lock()
+        .map_err(|_| LauncherError::Config("web asset restore lock poisoned".to_string()))?;
+    let staging_dir = pending.web_dir.with_extension("staging");
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir)?;
+    }
+    let staged = extract_pack(&pending.pack_path, &staging_dir, |progress, milestone| {
+        tracing::info!(
+            "web assets extraction {}% ({} of {} entries)",
+            milestone,
+            progress.extracted_entries,
+            progress.total_entries
+        );
+        let _ = app.emit("web-assets-progress", progress.payload());
+    })
+    .and_then(|()| write_web_stamp(&staging_dir, &pending.pack_stamp));
+    if let Err(err) = staged {
+        let _ = fs::remove_dir_all(&staging_dir);
+        return Err(err);
+    }
+
+    swap_web_dir(&staging_dir, &pending.web_dir)?;
+
+    #[cfg(target_os = "windows")]
+    {
+        let _ = std::process::Command::new("cmd")
+            .args([
+                "/c",
+                "attrib",
+                "+h",
+                "+s",
+                pending.web_dir.to_string_lossy().as_ref(),
+            ])
+            .status();
+        let _ = std::process::Command::new("cmd")
+            .args([
+                "/c",
+                "attrib",
+                "+h",
+                "+s",
+                pending.pack_path.to_string_lossy().as_ref(),
+            ])
+            .status();
+    }
+
+    Ok(())
+}
+
+/// Moves `staging_dir` into place at `web_dir`, keeping the previous copy
+/// aside until the new one is there.
+fn swap_web_dir(staging_dir: &Path, web_dir: &Path) -> Result<()> {
+    let previous_dir = web_dir.with_extension("previous");
+    if previous_dir.exists() {
+        fs::remove_dir_all(&previous_dir)?;
+    }
+    let had_live = web_dir.exists();
+    if had_live {
+        fs::rename(web_dir, &previous_dir)?;
+    }
+    if let Err(err) = fs::rename(staging_dir, web_dir) {
+        if had_live {
+            let _ = fs::rename(&previous_dir, web_dir);
+        }
+        return Err(err.into());
+    }
+    if had_live {
+        if let Err(err) = fs::remove_dir_all(&previous_dir) {
+            tracing::warn!(
+                "failed to clear previous web assets at {}: {}",
+                previous_dir.display(),
+                err
+            );
+        }
+    }
+    Ok(())
+}
+
+fn pack_signature(pack_path: &Path) -> Result<String> {
+    let metadata = fs::metadata(pack_path)?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|value| value.duration_since(UNIX_EPOCH).ok())
+        .map(|value| value.as_secs())
+        .unwrap_or(0);
+    Ok(format!("{}:{}", metadata.len(), modified))
+}
+
+fn read_web_stamp(web_dir: &Path) -> Option<String> {
+    let stamp_path = web_dir.join(WEB_PACK_STAMP_FILE);
+    fs::read_to_string(stamp_path)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn write_web_stamp(web_dir: &Path, stamp: &str) -> Result<()> {
+    fs::create_dir_all(web_dir)?;
+    fs::write(web_dir.join(WEB_PACK_STAMP_FILE), format!("{stamp}\n"))?;
+    Ok(())
+}
+
+fn extract_pack<F>(pack_path: &Path, dest: &Path, mut on_milestone: F) -> Result<()>
+where
+    F: FnMut(&ExtractProgress, u8),
+{
+    let file = fs::File::open(pack_path)?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| LauncherError::Config(format!("invalid web.pack: {e}")))?;
+
+    let mut total_bytes = 0u64;
+    for i in 0..archive.len() {
+        if let Ok(entry) = archive.by_index_raw(i) {
+            total_bytes = total_bytes.saturating_add(entry.size());
+        }
+    }
+    let mut progress = ExtractProgress::new(archive.len(), total_bytes);
+    let mut manifest = None;
+    let mut hashes = HashMap::new();
+
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| LauncherError::Config(format!("web.pack entry error: {e}")))?;
+        let name = entry.name().replace('\\', "/");
+        let entry_size = entry.size();
+        if archive::is_symlink(&entry) {
+            return Err(LauncherError::Integrity(format!(
+                "web.pack contains a symlink entry: {name}"
+            )));
+        }
+        if name == WEB_PACK_MANIFEST_FILE {
+            let mut text = String::new();
+            entry.read_to_string(&mut text)?;
+            manifest = Some(text);
+        } else if let Some(out_path) = safe_pack_path(dest, &name) {
+            if entry.is_dir() {
+                fs::create_dir_all(&out_path)?;
+            } else {
+                if let Some(parent) = out_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut out_file = fs::File::create(&out_path)?;
+                let mut buffer = Vec::new();
+                entry.read_to_end(&mut buffer)?;
+                std::io::Write::write_all(&mut out_file, &buffer)?;
+                hashes.insert(name, hex::encode(Sha256::digest(&buffer)));
+            }
+        }
+        if let Some(milestone) = progress.advance(entry_size) {
+            on_milestone(&progress, milestone);
+        }
+    }
+
+    match manifest {
+        Some(manifest) => verify_pack_manifest(&manifest, &hashes),
+        None => {
+            tracing::warn!(
+                "web.pack at {} has no {}; skipping per-file verification",
+                pack_path.display(),
+                WEB_PACK_MANIFEST_FILE
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Checks every file listed in the pack manifest against the hashes of what
+/// was extracted.
+fn verify_pack_manifest(manifest: &str, extracted: &HashMap<String, String>) -> Result<()> {
+    for (index, line) in manifest.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let Some((expected, name)) = trimmed.split_once(char::is_whitespace) else {
+            return Err(LauncherError::Integrity(format!(
+                "invalid web.pack manifest line {}",
+                index + 1
+            )));
+        };
+        let name = name.trim().trim_start_matches('*').replace('\\', "/");
+        match extracted.get(&name) {
+            None => {
+                return Err(LauncherError::Integrity(format!(
+                    "web.pack is missing {name}"
+                )))
+            }
+            Some(actual) if !actual.eq_ignore_ascii_case(expected) => {
+                return Err(LauncherError::Integrity(format!(
+                    "web.pack hash mismatch: {name}"
+                )))
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+fn safe_pack_path(base: &Path, name: &str) -> Option<PathBuf> {
+    if archive::has_drive_prefix(name) {
+        return None;
+    }
+    let path = Path::new(name);
+    let mut out = PathBuf::from(base);
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_reports_each_step_once_and_ends_at_100() {
+        let mut progress = ExtractProgress::new(4, 1_000);
+        let milestones = [100, 250, 400, 250]
+            .into_iter()
+            .filter_map(|bytes| progress.advance(bytes))
+            .collect::<Vec<_>>();
+
+        assert_eq!(milestones, vec![10, 30, 70, 100]);
+        assert_eq!(progress.percent(), 100);
+        assert_eq!(progress.advance(0), None);
+    }
+
+    #[test]
+    fn force_restores_even_when_stamp_matches() {
+        let unchanged = stamp_changed(true, Some("10:20"), "10:20");
+        assert!(!unchanged);
+        assert!(!should_restore(false, unchanged));
+        assert!(should_restore(true, unchanged));
+
+        assert!(stamp_changed(true, Some("10:20"), "11:20"));
+        assert!(stamp_changed(true, None, "10:20"));
+        assert!(stamp_changed(false, Some("10:20"), "10:20"));
+        assert!(should_restore(
+            false,
+            stamp_changed(false, Some("10:20"), "10:20")
+        ));
+    }
+
+    #[test]
+    fn pack_manifest_rejects_missing_and_corrupt_files() {
+        let extracted = HashMap::from([
+            ("index.html".to_string(), "aa11".to_string()),
+            ("assets/app.js".to_string(), "bb22".to_string()),
+        ]);
+
+        assert!(
+            verify_pack_manifest("AA11  index.html\nbb22 *assets/app.js\n", &extracted).is_ok()
+        );
+        assert!(verify_pack_manifest("cc33  index.html\n", &extracted).is_err());
+        assert!(verify_pack_manifest("aa11  missing.css\n", &extracted).is_err());
+    }
+
+    #[test]
+    fn pack_paths_stay_inside_the_web_dir() {
+        let base = Path::new("/srv/web");
+        assert_eq!(
+            safe_pack_path(base, "assets/app.js"),
+            Some(base.join("assets").join("app.js"))
+        );
+        assert_eq!(safe_pack_path(base, "../escape.js"), None);
+        assert_eq!(safe_pack_path(base, "assets/../../escape.js"), None);
+        assert_eq!(safe_pack_path(base, "/etc/passwd"), None);
+        assert_eq!(safe_pack_path(base, "C:/Windows/evil.js"), None);
+    }
+
+    #[test]
+    fn extract_pack_rejects_symlink_entries() {
+        let dir = std::env::temp_dir().join(format!("otoshi-web-pack-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let pack_path = dir.join("web.pack");
+        fs::write(
+            &pack_path,
+            archive::build_test_zip(&[
+                ("index.html", "<html></html>", None),
+                ("assets", "", Some("/etc")),
+            ]),
+        )
+        .unwrap();
+
+        let result = extract_pack(&pack_path, &dir.join("out"), |_, _| {});
+        let _ = fs::remove_dir_all(&dir);
+        assert!(matches!(result, Err(LauncherError::Integrity(_))));
+    }
+
+    #[test]
+    fn progress_falls_back_to_entry_count_for_empty_entries() {
+        let mut progress = ExtractProgress::new(2, 0);
+        assert_eq!(progress.advance(0), Some(50));
+        assert_eq!(progress.advance(0), Some(100));
+        assert_eq!(ExtractProgress::new(0, 0).percent(), 100);
+    }
+}
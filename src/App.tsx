@@ -222,6 +222,8 @@ function AppContent() {
           game_id?: string;
           slug?: string;
           message?: string;
+          code?: string;
+          retryable?: boolean;
         }>("download-runtime-error", (event) => {
           const detail = event.payload || {};
           const message =
@@ -234,6 +236,8 @@ function AppContent() {
                 downloadId: detail.download_id,
                 gameId: detail.game_id,
                 slug: detail.slug,
+                code: detail.code,
+                retryable: detail.retryable,
               },
             })
           );
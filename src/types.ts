@@ -866,6 +866,13 @@ export type WorkshopSyncResult = {
   itemsTotal: number;
   itemsSynced: number;
   errors: string[];
+  conflicts: WorkshopConflict[];
+};
+
+export type WorkshopConflict = {
+  first_item: string;
+  second_item: string;
+  paths: string[];
 };
 
 export type WishlistEntry = {